@@ -1,4 +1,11 @@
 // We'll generate the schema at runtime since we need access to the complete application context
 fn main() {
     println!("cargo:rerun-if-changed=src/");
+    println!("cargo:rerun-if-changed=proto/goose_plugin.proto");
+    let protoc =
+        protoc_bin_vendored::protoc_bin_path().expect("no bundled protoc for this platform");
+    prost_build::Config::new()
+        .protoc_executable(protoc)
+        .compile_protos(&["proto/goose_plugin.proto"], &["proto"])
+        .expect("failed to compile proto/goose_plugin.proto");
 }
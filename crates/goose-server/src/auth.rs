@@ -1,3 +1,6 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
 use axum::{
     extract::{Request, State},
     http::StatusCode,
@@ -5,8 +8,37 @@ use axum::{
     response::Response,
 };
 
+/// Path prefixes scoped tokens may reach. Routes under them check each
+/// token's grants themselves.
+const SCOPED_PATH_PREFIXES: &[&str] = &["/plugins", "/tasks"];
+
+#[derive(Clone)]
+pub struct AuthState {
+    secret_key: String,
+    /// Tokens limited to the plugin and task routes.
+    scoped_tokens: Arc<HashSet<String>>,
+}
+
+impl AuthState {
+    pub fn new(secret_key: String, scoped_tokens: HashSet<String>) -> Self {
+        Self {
+            secret_key,
+            scoped_tokens: Arc::new(scoped_tokens),
+        }
+    }
+
+    fn accepts(&self, key: &str, path: &str) -> bool {
+        key == self.secret_key
+            || (self.scoped_tokens.contains(key)
+                && SCOPED_PATH_PREFIXES.iter().any(|prefix| {
+                    path.strip_prefix(prefix)
+                        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+                }))
+    }
+}
+
 pub async fn check_token(
-    State(state): State<String>,
+    State(state): State<AuthState>,
     request: Request,
     next: Next,
 ) -> Result<Response, StatusCode> {
@@ -19,7 +51,7 @@ pub async fn check_token(
         .and_then(|value| value.to_str().ok());
 
     match secret_key {
-        Some(key) if key == state => Ok(next.run(request).await),
+        Some(key) if state.accepts(key, request.uri().path()) => Ok(next.run(request).await),
         _ => Err(StatusCode::UNAUTHORIZED),
     }
 }
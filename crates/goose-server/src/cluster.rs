@@ -0,0 +1,375 @@
+//! Federation of several goosed instances. Members report their hardware and
+//! load to a coordinator with periodic heartbeats; the coordinator places
+//! downloads and services on the least loaded member.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tokio::sync::RwLock;
+use utoipa::ToSchema;
+
+/// How often a member reports to its coordinator.
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Missed heartbeats after which a member is considered offline.
+const MISSED_HEARTBEATS: u32 = 3;
+
+pub const API_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema, PartialEq)]
+pub struct NodeResources {
+    pub cpus: usize,
+    #[serde(default)]
+    pub memory_bytes: Option<u64>,
+    /// One-minute load average, where the platform reports one.
+    #[serde(default)]
+    pub load_average: Option<f64>,
+}
+
+impl NodeResources {
+    pub fn collect() -> Self {
+        Self {
+            cpus: std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1),
+            memory_bytes: total_memory(),
+            load_average: load_average(),
+        }
+    }
+
+    /// Load per CPU; lower is better when placing work.
+    fn load_score(&self) -> f64 {
+        self.load_average.unwrap_or(0.0) / self.cpus.max(1) as f64
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn total_memory() -> Option<u64> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    let line = meminfo.lines().find(|line| line.starts_with("MemTotal:"))?;
+    let kib: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kib * 1024)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn total_memory() -> Option<u64> {
+    None
+}
+
+#[cfg(target_os = "linux")]
+fn load_average() -> Option<f64> {
+    std::fs::read_to_string("/proc/loadavg")
+        .ok()?
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn load_average() -> Option<f64> {
+    None
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct NodeHeartbeat {
+    pub node_id: String,
+    /// Base URL at which the coordinator can reach the member's API.
+    pub url: String,
+    pub version: String,
+    pub resources: NodeResources,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ClusterNodeStatus {
+    pub node_id: String,
+    pub url: String,
+    pub version: String,
+    pub resources: NodeResources,
+    pub registered_at: DateTime<Utc>,
+    pub last_heartbeat: DateTime<Utc>,
+    pub online: bool,
+}
+
+struct ClusterMember {
+    heartbeat: NodeHeartbeat,
+    registered_at: DateTime<Utc>,
+    last_heartbeat: DateTime<Utc>,
+}
+
+impl ClusterMember {
+    fn status(&self, now: DateTime<Utc>) -> ClusterNodeStatus {
+        let timeout = HEARTBEAT_INTERVAL * MISSED_HEARTBEATS;
+        let online = (now - self.last_heartbeat)
+            .to_std()
+            .map_or(true, |age| age <= timeout);
+        ClusterNodeStatus {
+            node_id: self.heartbeat.node_id.clone(),
+            url: self.heartbeat.url.clone(),
+            version: self.heartbeat.version.clone(),
+            resources: self.heartbeat.resources.clone(),
+            registered_at: self.registered_at,
+            last_heartbeat: self.last_heartbeat,
+            online,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ClusterError {
+    #[error("unknown cluster node {0}")]
+    UnknownNode(String),
+    #[error("cluster node {0} is offline")]
+    NodeOffline(String),
+    #[error("no online cluster nodes")]
+    NoNodesAvailable,
+    #[error("cluster node {0} is unreachable: {1}")]
+    Unreachable(String, String),
+    /// The member answered with an error; its status and message are passed on.
+    #[error("{message}")]
+    Member { status: u16, message: String },
+}
+
+/// Members known to this node when it acts as coordinator.
+pub struct ClusterRegistry {
+    members: RwLock<HashMap<String, ClusterMember>>,
+    client: reqwest::Client,
+    secret: String,
+}
+
+impl ClusterRegistry {
+    /// `secret` authenticates requests forwarded to members.
+    pub fn new(secret: String) -> Self {
+        Self {
+            members: RwLock::new(HashMap::new()),
+            client: reqwest::Client::new(),
+            secret,
+        }
+    }
+
+    /// Sends `body` to `path` on a member's API and decodes the reply.
+    pub async fn forward<B: Serialize, R: DeserializeOwned>(
+        &self,
+        node: &ClusterNodeStatus,
+        path: &str,
+        body: &B,
+    ) -> Result<R, ClusterError> {
+        let unreachable =
+            |err: reqwest::Error| ClusterError::Unreachable(node.node_id.clone(), err.to_string());
+        let response = self
+            .client
+            .post(format!("{}{}", node.url.trim_end_matches('/'), path))
+            .header("X-Secret-Key", &self.secret)
+            .json(body)
+            .send()
+            .await
+            .map_err(unreachable)?;
+
+        let status = response.status();
+        if !status.is_success() {
+            #[derive(Deserialize)]
+            struct Message {
+                message: String,
+            }
+            let message = match response.json::<Message>().await {
+                Ok(body) => body.message,
+                Err(_) => status.to_string(),
+            };
+            return Err(ClusterError::Member {
+                status: status.as_u16(),
+                message: format!("node {}: {}", node.node_id, message),
+            });
+        }
+        response.json().await.map_err(unreachable)
+    }
+
+    /// Registers a member or refreshes an existing registration.
+    pub async fn heartbeat(&self, heartbeat: NodeHeartbeat) -> ClusterNodeStatus {
+        let now = Utc::now();
+        let mut members = self.members.write().await;
+        let member = members.entry(heartbeat.node_id.clone()).or_insert_with(|| {
+            tracing::info!(
+                "cluster node {} registered at {}",
+                heartbeat.node_id,
+                heartbeat.url
+            );
+            ClusterMember {
+                heartbeat: heartbeat.clone(),
+                registered_at: now,
+                last_heartbeat: now,
+            }
+        });
+        member.heartbeat = heartbeat;
+        member.last_heartbeat = now;
+        member.status(now)
+    }
+
+    pub async fn list(&self) -> Vec<ClusterNodeStatus> {
+        let now = Utc::now();
+        let mut nodes: Vec<_> = self
+            .members
+            .read()
+            .await
+            .values()
+            .map(|member| member.status(now))
+            .collect();
+        nodes.sort_by(|a, b| a.node_id.cmp(&b.node_id));
+        nodes
+    }
+
+    pub async fn remove(&self, node_id: &str) -> bool {
+        self.members.write().await.remove(node_id).is_some()
+    }
+
+    /// Picks the member to run work on: the requested one if given, else the
+    /// online member with the lowest load per CPU.
+    pub async fn select(&self, node_id: Option<&str>) -> Result<ClusterNodeStatus, ClusterError> {
+        let nodes = self.list().await;
+        match node_id {
+            Some(node_id) => {
+                let node = nodes
+                    .into_iter()
+                    .find(|node| node.node_id == node_id)
+                    .ok_or_else(|| ClusterError::UnknownNode(node_id.to_string()))?;
+                if !node.online {
+                    return Err(ClusterError::NodeOffline(node.node_id));
+                }
+                Ok(node)
+            }
+            None => nodes
+                .into_iter()
+                .filter(|node| node.online)
+                .min_by(|a, b| {
+                    a.resources
+                        .load_score()
+                        .total_cmp(&b.resources.load_score())
+                        .then_with(|| b.resources.memory_bytes.cmp(&a.resources.memory_bytes))
+                })
+                .ok_or(ClusterError::NoNodesAvailable),
+        }
+    }
+}
+
+/// Settings for joining a coordinator, from `GOOSE_CLUSTER_COORDINATOR`
+/// (coordinator base URL), `GOOSE_CLUSTER_ADVERTISE_URL` (how the
+/// coordinator reaches this node) and optionally `GOOSE_CLUSTER_NODE_ID`.
+#[derive(Debug, Clone)]
+pub struct MemberConfig {
+    pub coordinator: String,
+    pub node_id: String,
+    pub advertise_url: String,
+}
+
+impl MemberConfig {
+    pub fn from_env() -> Option<Self> {
+        let coordinator = std::env::var("GOOSE_CLUSTER_COORDINATOR").ok()?;
+        let Ok(advertise_url) = std::env::var("GOOSE_CLUSTER_ADVERTISE_URL") else {
+            tracing::warn!(
+                "GOOSE_CLUSTER_COORDINATOR is set but GOOSE_CLUSTER_ADVERTISE_URL is not; not joining the cluster"
+            );
+            return None;
+        };
+        let node_id = std::env::var("GOOSE_CLUSTER_NODE_ID")
+            .or_else(|_| std::env::var("HOSTNAME"))
+            .unwrap_or_else(|_| advertise_url.clone());
+        Some(Self {
+            coordinator: coordinator.trim_end_matches('/').to_string(),
+            node_id,
+            advertise_url: advertise_url.trim_end_matches('/').to_string(),
+        })
+    }
+}
+
+/// Secret sent to other cluster nodes: `GOOSE_CLUSTER_SECRET_KEY`, falling
+/// back to this server's own secret. Every node must accept it.
+pub fn cluster_secret() -> String {
+    std::env::var("GOOSE_CLUSTER_SECRET_KEY")
+        .or_else(|_| std::env::var("GOOSE_SERVER__SECRET_KEY"))
+        .unwrap_or_else(|_| "test".to_string())
+}
+
+/// Reports this node to its coordinator until the process exits.
+pub fn spawn_heartbeat(config: MemberConfig, secret: String) {
+    tokio::spawn(async move {
+        let client = reqwest::Client::new();
+        let url = format!("{}/cluster/heartbeat", config.coordinator);
+        let mut interval = tokio::time::interval(HEARTBEAT_INTERVAL);
+        let mut reachable = true;
+        loop {
+            interval.tick().await;
+            let heartbeat = NodeHeartbeat {
+                node_id: config.node_id.clone(),
+                url: config.advertise_url.clone(),
+                version: API_VERSION.to_string(),
+                resources: NodeResources::collect(),
+            };
+            let result = client
+                .post(&url)
+                .header("X-Secret-Key", &secret)
+                .timeout(HEARTBEAT_INTERVAL)
+                .json(&heartbeat)
+                .send()
+                .await
+                .and_then(|response| response.error_for_status());
+            match result {
+                Ok(_) if !reachable => {
+                    tracing::info!("cluster coordinator {} reachable again", config.coordinator);
+                    reachable = true;
+                }
+                Ok(_) => {}
+                // Only log the transition so an unreachable coordinator does
+                // not flood the log.
+                Err(err) if reachable => {
+                    tracing::warn!(
+                        "cluster heartbeat to {} failed: {}",
+                        config.coordinator,
+                        err
+                    );
+                    reachable = false;
+                }
+                Err(_) => {}
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn heartbeat(node_id: &str, cpus: usize, load: f64) -> NodeHeartbeat {
+        NodeHeartbeat {
+            node_id: node_id.to_string(),
+            url: format!("http://{}:3000", node_id),
+            version: API_VERSION.to_string(),
+            resources: NodeResources {
+                cpus,
+                memory_bytes: None,
+                load_average: Some(load),
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn selects_least_loaded_online_node() {
+        let registry = ClusterRegistry::new("secret".to_string());
+        assert!(matches!(
+            registry.select(None).await,
+            Err(ClusterError::NoNodesAvailable)
+        ));
+
+        registry.heartbeat(heartbeat("small", 4, 2.0)).await;
+        registry.heartbeat(heartbeat("big", 32, 4.0)).await;
+        assert_eq!(registry.select(None).await.unwrap().node_id, "big");
+        assert_eq!(
+            registry.select(Some("small")).await.unwrap().node_id,
+            "small"
+        );
+        assert!(matches!(
+            registry.select(Some("missing")).await,
+            Err(ClusterError::UnknownNode(_))
+        ));
+    }
+}
@@ -1,8 +1,13 @@
+use crate::cluster::{self, MemberConfig};
 use crate::configuration;
+use crate::discovery::{self, Advertisement};
+use crate::plugins::retention::{self, LogRetention};
 use crate::state;
 use anyhow::Result;
 use axum::middleware;
-use goose_server::auth::check_token;
+use goose_server::auth::{check_token, AuthState};
+use goose_server::timeout::{enforce_timeout, RouteTimeouts};
+use std::sync::Arc;
 use tower_http::cors::{Any, CorsLayer};
 use tracing::info;
 
@@ -23,10 +28,23 @@ pub async fn run() -> Result<()> {
         );
     }
 
+    spawn_server_log_pruner();
+
+    if let Some(member) = MemberConfig::from_env() {
+        info!(
+            "joining cluster coordinated by {} as {}",
+            member.coordinator, member.node_id
+        );
+        cluster::spawn_heartbeat(member, cluster::cluster_secret());
+    }
+
     let secret_key =
         std::env::var("GOOSE_SERVER__SECRET_KEY").unwrap_or_else(|_| "test".to_string());
 
     let app_state = state::AppState::new().await?;
+    let plugins = app_state.plugins.clone();
+    let plugin_state = app_state.plugin_state.clone();
+    let auth = AuthState::new(secret_key, app_state.plugin_credentials.tokens());
 
     let cors = CorsLayer::new()
         .allow_origin(Any)
@@ -35,13 +53,102 @@ pub async fn run() -> Result<()> {
 
     let app = crate::routes::configure(app_state)
         .layer(middleware::from_fn_with_state(
-            secret_key.clone(),
-            check_token,
+            Arc::new(RouteTimeouts::from_env()),
+            enforce_timeout,
         ))
+        .layer(middleware::from_fn_with_state(auth, check_token))
         .layer(cors);
 
     let listener = tokio::net::TcpListener::bind(settings.socket_addr()).await?;
     info!("listening on {}", listener.local_addr()?);
-    axum::serve(listener, app).await?;
+
+    // Held for the lifetime of the server; dropping it withdraws the record.
+    let _advertiser = if settings.mdns_enabled {
+        let advertisement = Advertisement {
+            name: settings
+                .mdns_name
+                .clone()
+                .unwrap_or_else(discovery::default_name),
+            addr: listener.local_addr()?,
+            tls_fingerprint: settings.tls_fingerprint.clone(),
+        };
+        match discovery::advertise(&advertisement) {
+            Ok(advertiser) => Some(advertiser),
+            Err(err) => {
+                tracing::warn!("failed to advertise via mDNS: {}", err);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    plugins.server_started().await;
+    axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown_signal())
+        .await?;
+    info!("shutting down plugins");
+    plugins.save_states(&plugin_state).await;
+    plugins.shutdown_all().await;
     Ok(())
 }
+
+/// Resolves on Ctrl+C or, on unix, SIGTERM.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(err) = tokio::signal::ctrl_c().await {
+            tracing::warn!("failed to listen for Ctrl+C: {}", err);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(err) => {
+                tracing::warn!("failed to listen for SIGTERM: {}", err);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
+
+/// Applies the global log retention to goosed's own log files at startup and
+/// then periodically.
+fn spawn_server_log_pruner() {
+    let policy = LogRetention::from_env();
+    if policy == LogRetention::default() {
+        return;
+    }
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(retention::SERVER_PRUNE_INTERVAL);
+        loop {
+            interval.tick().await;
+            let result = tokio::task::spawn_blocking(move || {
+                let dir = goose::logging::get_log_directory("server", false)?;
+                Ok::<_, anyhow::Error>(retention::prune_directory(&dir, &policy)?)
+            })
+            .await;
+            match result {
+                Ok(Ok(report)) if report.files_removed > 0 => info!(
+                    "pruned {} server log files ({} bytes)",
+                    report.files_removed, report.bytes_freed
+                ),
+                Ok(Ok(_)) => {}
+                Ok(Err(err)) => tracing::warn!("failed to prune server logs: {}", err),
+                Err(err) => tracing::warn!("server log pruning task failed: {}", err),
+            }
+        }
+    });
+}
@@ -9,6 +9,15 @@ pub struct Settings {
     pub host: String,
     #[serde(default = "default_port")]
     pub port: u16,
+    /// Advertise the API on the local network via mDNS (`GOOSE_MDNS_ENABLED`).
+    #[serde(default)]
+    pub mdns_enabled: bool,
+    /// Instance name shown to companion apps; defaults to the host name.
+    #[serde(default)]
+    pub mdns_name: Option<String>,
+    /// Fingerprint of the TLS certificate in front of goosed, if any.
+    #[serde(default)]
+    pub tls_fingerprint: Option<String>,
 }
 
 impl Settings {
@@ -83,6 +92,7 @@ mod tests {
         let server_settings = Settings {
             host: "127.0.0.1".to_string(),
             port: 3000,
+            ..Settings::default()
         };
         let addr = server_settings.socket_addr();
         assert_eq!(addr.to_string(), "127.0.0.1:3000");
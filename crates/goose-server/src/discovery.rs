@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+
+use anyhow::Result;
+use mdns_sd::{ServiceDaemon, ServiceInfo};
+
+/// DNS-SD service type companion apps browse for.
+pub const SERVICE_TYPE: &str = "_goosed._tcp.local.";
+
+/// Version of the HTTP API advertised to companion apps.
+pub const API_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// What goosed announces about itself on the local network.
+#[derive(Debug, Clone)]
+pub struct Advertisement {
+    pub name: String,
+    pub addr: SocketAddr,
+    /// SHA-256 fingerprint of the certificate served in front of goosed, so
+    /// a companion can pin it when pairing.
+    pub tls_fingerprint: Option<String>,
+}
+
+impl Advertisement {
+    fn properties(&self) -> HashMap<String, String> {
+        let tls = if self.tls_fingerprint.is_some() {
+            "1"
+        } else {
+            "0"
+        };
+        let mut properties = HashMap::from([
+            ("api_version".to_string(), API_VERSION.to_string()),
+            ("tls".to_string(), tls.to_string()),
+        ]);
+        if let Some(fingerprint) = &self.tls_fingerprint {
+            properties.insert("tls_fingerprint".to_string(), fingerprint.clone());
+        }
+        properties
+    }
+}
+
+/// Keeps the service registered until dropped.
+pub struct Advertiser {
+    daemon: ServiceDaemon,
+}
+
+impl Drop for Advertiser {
+    fn drop(&mut self) {
+        let _ = self.daemon.shutdown();
+    }
+}
+
+/// Default instance name: the machine's host name, or `goosed`.
+pub fn default_name() -> String {
+    std::env::var("HOSTNAME")
+        .or_else(|_| std::env::var("COMPUTERNAME"))
+        .ok()
+        .filter(|name| !name.trim().is_empty())
+        .unwrap_or_else(|| "goosed".to_string())
+}
+
+/// Turns an arbitrary name into a single DNS label for the `.local.` host.
+fn host_label(name: &str) -> String {
+    let label: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    let label = label.trim_matches('-');
+    if label.is_empty() {
+        "goosed".to_string()
+    } else {
+        label.to_ascii_lowercase()
+    }
+}
+
+pub fn advertise(advertisement: &Advertisement) -> Result<Advertiser> {
+    if advertisement.addr.ip().is_loopback() {
+        tracing::warn!(
+            "advertising goosed via mDNS while listening on {}; other devices will not be able to connect",
+            advertisement.addr
+        );
+    }
+
+    let daemon = ServiceDaemon::new()?;
+    let host_name = format!("{}.local.", host_label(&advertisement.name));
+    let service = ServiceInfo::new(
+        SERVICE_TYPE,
+        &advertisement.name,
+        &host_name,
+        (),
+        advertisement.addr.port(),
+        advertisement.properties(),
+    )?
+    .enable_addr_auto();
+    daemon.register(service)?;
+
+    tracing::info!(
+        "advertising {} as {} on port {}",
+        SERVICE_TYPE,
+        advertisement.name,
+        advertisement.addr.port()
+    );
+    Ok(Advertiser { daemon })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn host_label_is_a_single_dns_label() {
+        assert_eq!(host_label("Jo's MacBook.lan"), "jo-s-macbook-lan");
+        assert_eq!(host_label("..."), "goosed");
+    }
+}
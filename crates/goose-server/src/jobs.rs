@@ -0,0 +1,685 @@
+//! Long-running operations run in the background. Starting one returns a job
+//! at once; clients poll it through `/jobs` or cancel it instead of holding a
+//! request open until the operation finishes.
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::future::Future;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::{broadcast, watch, RwLock};
+use tokio_util::sync::CancellationToken;
+use utoipa::ToSchema;
+
+use crate::plugins::admission::{OperationPriority, QueueTicket};
+use crate::plugins::progress::{DownloadProgress, DownloadProgressUpdate};
+use crate::plugins::PartialDownload;
+
+/// Finished jobs kept for clients to read; older ones are forgotten first.
+const MAX_FINISHED_JOBS: usize = 100;
+
+const FINISHED_CHANNEL_CAPACITY: usize = 64;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    /// A download waiting for the time it was scheduled to start at.
+    Scheduled,
+    Running,
+    /// A download stopped by a pause, waiting to be resumed.
+    Paused,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+impl JobState {
+    pub fn is_finished(self) -> bool {
+        matches!(
+            self,
+            JobState::Succeeded | JobState::Failed | JobState::Cancelled
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct JobStatus {
+    pub id: String,
+    /// Operation the job runs, e.g. `model_download`.
+    pub kind: String,
+    #[serde(default)]
+    pub plugin_id: Option<String>,
+    pub state: JobState,
+    /// 1-based place in its operation queue while the job waits to start.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub queue_position: Option<usize>,
+    /// Time a scheduled job starts at.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub finished_at: Option<DateTime<Utc>>,
+    /// Response of the operation once it succeeded.
+    #[serde(default)]
+    #[schema(value_type = Option<Object>)]
+    pub result: Option<Value>,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+impl JobStatus {
+    fn new(kind: &str, plugin_id: Option<String>, start_at: Option<DateTime<Utc>>) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            kind: kind.to_string(),
+            plugin_id,
+            state: JobState::Running,
+            queue_position: None,
+            start_at,
+            created_at: Utc::now(),
+            finished_at: None,
+            result: None,
+            error: None,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum JobError {
+    #[error("unknown job {0}")]
+    UnknownJob(String),
+    #[error("job {0} already finished")]
+    Finished(String),
+    #[error("job {0} cannot be paused")]
+    NotPausable(String),
+    #[error("job {0} is not waiting in a queue")]
+    NotQueued(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PrioritizeJobRequest {
+    pub priority: OperationPriority,
+}
+
+/// What a download job can be followed and steered through besides its
+/// status.
+pub struct DownloadJob<'a> {
+    pub progress: &'a DownloadProgress,
+    pub partial: PartialDownload,
+    pub queue: JobQueue,
+    pub pause: DownloadPause,
+    /// Time the job's work starts at instead of right away.
+    pub start_at: Option<DateTime<Utc>>,
+}
+
+/// The place of a download job in its operation queue. A scheduled download
+/// only joins its queue once it starts.
+#[derive(Clone, Default)]
+pub struct JobQueue(Arc<std::sync::Mutex<Option<QueueTicket>>>);
+
+impl JobQueue {
+    pub fn joined(&self, ticket: QueueTicket) {
+        *self.0.lock().expect("queue ticket") = Some(ticket);
+    }
+
+    fn ticket(&self) -> Option<QueueTicket> {
+        self.0.lock().expect("queue ticket").clone()
+    }
+}
+
+impl From<QueueTicket> for JobQueue {
+    fn from(ticket: QueueTicket) -> Self {
+        let queue = Self::default();
+        queue.joined(ticket);
+        queue
+    }
+}
+
+/// Pauses and resumes a download job. Every transfer runs under a child of
+/// the job's cancellation token, which a pause cancels; the job then waits
+/// for [`resumed`](Self::resumed) and starts a new transfer, which picks up
+/// the partial file where the last one stopped.
+#[derive(Clone)]
+pub struct DownloadPause {
+    paused: Arc<watch::Sender<bool>>,
+    transfer: Arc<std::sync::Mutex<CancellationToken>>,
+}
+
+impl Default for DownloadPause {
+    fn default() -> Self {
+        Self {
+            paused: Arc::new(watch::channel(false).0),
+            transfer: Arc::default(),
+        }
+    }
+}
+
+impl DownloadPause {
+    /// Token for the next transfer of the job `cancel` belongs to. It is
+    /// cancelled with the job, or by a pause.
+    pub fn transfer(&self, cancel: &CancellationToken) -> CancellationToken {
+        let token = cancel.child_token();
+        *self.transfer.lock().expect("transfer token") = token.clone();
+        // A pause that came in before the transfer started stops it at once.
+        if self.is_paused() {
+            token.cancel();
+        }
+        token
+    }
+
+    pub fn is_paused(&self) -> bool {
+        *self.paused.borrow()
+    }
+
+    /// Waits until the download is not paused.
+    pub async fn resumed(&self) {
+        let mut paused = self.paused.subscribe();
+        let _ = paused.wait_for(|paused| !paused).await;
+    }
+
+    fn pause(&self) {
+        self.paused.send_replace(true);
+        self.transfer.lock().expect("transfer token").cancel();
+    }
+
+    fn resume(&self) {
+        self.paused.send_replace(false);
+    }
+}
+
+struct Job {
+    status: JobStatus,
+    cancel: CancellationToken,
+    progress: Option<watch::Receiver<DownloadProgressUpdate>>,
+    partial: Option<PartialDownload>,
+    queue: Option<JobQueue>,
+    pause: Option<DownloadPause>,
+}
+
+impl Job {
+    fn status(&self) -> JobStatus {
+        let mut status = self.status.clone();
+        if status.state == JobState::Running
+            && status
+                .start_at
+                .is_some_and(|start_at| start_at > Utc::now())
+        {
+            status.state = JobState::Scheduled;
+        }
+        if !status.state.is_finished() {
+            status.queue_position = self
+                .queue
+                .as_ref()
+                .and_then(JobQueue::ticket)
+                .and_then(|ticket| ticket.position());
+        }
+        status
+    }
+}
+
+/// Jobs started since the server came up.
+pub struct JobRegistry {
+    jobs: RwLock<HashMap<String, Job>>,
+    finished: broadcast::Sender<JobStatus>,
+}
+
+impl Default for JobRegistry {
+    fn default() -> Self {
+        Self {
+            jobs: RwLock::default(),
+            finished: broadcast::channel(FINISHED_CHANNEL_CAPACITY).0,
+        }
+    }
+}
+
+impl JobRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Statuses of jobs as they finish.
+    pub fn subscribe(&self) -> broadcast::Receiver<JobStatus> {
+        self.finished.subscribe()
+    }
+
+    /// Runs `work` on its own task and records its outcome. `cancel` is fired
+    /// by [`cancel`](Self::cancel); the work is expected to stop at its next
+    /// cancellation point, and fails as cancelled once it does. A `download`
+    /// can be followed through [`progress`](Self::progress), reports its
+    /// queue position, keeps its partial file when cancelled through
+    /// [`cancel_download`](Self::cancel_download), and can be paused. A
+    /// download with a start time is scheduled: its work is only started
+    /// at that time, unless it is cancelled before.
+    pub async fn spawn<T, E, F>(
+        self: &Arc<Self>,
+        kind: &str,
+        plugin_id: Option<String>,
+        cancel: CancellationToken,
+        download: Option<DownloadJob<'_>>,
+        work: F,
+    ) -> JobStatus
+    where
+        T: Serialize,
+        E: Display,
+        F: Future<Output = Result<T, E>> + Send + 'static,
+    {
+        let start_at = download.as_ref().and_then(|download| download.start_at);
+        let status = JobStatus::new(kind, plugin_id, start_at);
+        let (progress, partial, queue, pause) = match download {
+            Some(download) => (
+                Some(download.progress.subscribe()),
+                Some(download.partial),
+                Some(download.queue),
+                Some(download.pause),
+            ),
+            None => (None, None, None, None),
+        };
+        let job = Job {
+            status,
+            cancel: cancel.clone(),
+            progress,
+            partial,
+            queue,
+            pause,
+        };
+        self.run(job, cancel, work).await
+    }
+
+    /// Like [`spawn`](Self::spawn) for work that reports its progress but
+    /// is neither queued nor paused, such as a model conversion.
+    pub async fn spawn_with_progress<T, E, F>(
+        self: &Arc<Self>,
+        kind: &str,
+        plugin_id: Option<String>,
+        cancel: CancellationToken,
+        progress: &DownloadProgress,
+        work: F,
+    ) -> JobStatus
+    where
+        T: Serialize,
+        E: Display,
+        F: Future<Output = Result<T, E>> + Send + 'static,
+    {
+        let job = Job {
+            status: JobStatus::new(kind, plugin_id, None),
+            cancel: cancel.clone(),
+            progress: Some(progress.subscribe()),
+            partial: None,
+            queue: None,
+            pause: None,
+        };
+        self.run(job, cancel, work).await
+    }
+
+    /// Records `job` and runs its work, starting at its start time if it has
+    /// one.
+    async fn run<T, E, F>(
+        self: &Arc<Self>,
+        job: Job,
+        cancel: CancellationToken,
+        work: F,
+    ) -> JobStatus
+    where
+        T: Serialize,
+        E: Display,
+        F: Future<Output = Result<T, E>> + Send + 'static,
+    {
+        let status = job.status();
+        self.jobs.write().await.insert(status.id.clone(), job);
+
+        let registry = self.clone();
+        let job_id = status.id.clone();
+        let start_at = status.start_at;
+        tokio::spawn(async move {
+            if let Some(start_at) = start_at {
+                let wait = (start_at - Utc::now()).to_std().unwrap_or_default();
+                tokio::select! {
+                    _ = tokio::time::sleep(wait) => {}
+                    _ = cancel.cancelled() => {
+                        let outcome = Err("cancelled before it started".to_string());
+                        registry.finish(&job_id, outcome, true).await;
+                        return;
+                    }
+                }
+            }
+            let outcome = match work.await {
+                Ok(response) => serde_json::to_value(response).map_err(|err| err.to_string()),
+                Err(err) => Err(err.to_string()),
+            };
+            registry
+                .finish(&job_id, outcome, cancel.is_cancelled())
+                .await;
+        });
+        status
+    }
+
+    async fn finish(&self, job_id: &str, outcome: Result<Value, String>, cancelled: bool) {
+        let mut jobs = self.jobs.write().await;
+        if let Some(job) = jobs.get_mut(job_id) {
+            let status = &mut job.status;
+            status.finished_at = Some(Utc::now());
+            match outcome {
+                Ok(result) => {
+                    status.state = JobState::Succeeded;
+                    status.result = Some(result);
+                }
+                Err(error) => {
+                    status.state = if cancelled {
+                        JobState::Cancelled
+                    } else {
+                        JobState::Failed
+                    };
+                    status.error = Some(error);
+                }
+            }
+            // An error only means nobody is subscribed right now.
+            let _ = self.finished.send(job.status());
+        }
+
+        let mut finished: Vec<_> = jobs
+            .values()
+            .filter_map(|job| job.status.finished_at.map(|at| (at, job.status.id.clone())))
+            .collect();
+        if finished.len() > MAX_FINISHED_JOBS {
+            finished.sort();
+            for (_, id) in &finished[..finished.len() - MAX_FINISHED_JOBS] {
+                jobs.remove(id);
+            }
+        }
+    }
+
+    pub async fn get(&self, job_id: &str) -> Result<JobStatus, JobError> {
+        self.jobs
+            .read()
+            .await
+            .get(job_id)
+            .map(Job::status)
+            .ok_or_else(|| JobError::UnknownJob(job_id.to_string()))
+    }
+
+    /// Progress updates of a job started with a progress reporter, the
+    /// latest first. `None` for other jobs.
+    pub async fn progress(
+        &self,
+        job_id: &str,
+    ) -> Result<Option<watch::Receiver<DownloadProgressUpdate>>, JobError> {
+        self.jobs
+            .read()
+            .await
+            .get(job_id)
+            .map(|job| job.progress.clone())
+            .ok_or_else(|| JobError::UnknownJob(job_id.to_string()))
+    }
+
+    /// Jobs newest first.
+    pub async fn list(&self) -> Vec<JobStatus> {
+        let mut jobs: Vec<_> = self.jobs.read().await.values().map(Job::status).collect();
+        jobs.sort_by_key(|job| std::cmp::Reverse(job.created_at));
+        jobs
+    }
+
+    /// Asks a running job to stop. Returns its status, which stays running
+    /// until the work notices.
+    pub async fn cancel(&self, job_id: &str) -> Result<JobStatus, JobError> {
+        self.cancel_download(job_id, false).await
+    }
+
+    /// Like [`cancel`](Self::cancel), but with `keep_partial` a download
+    /// leaves what it wrote for a later download to resume.
+    pub async fn cancel_download(
+        &self,
+        job_id: &str,
+        keep_partial: bool,
+    ) -> Result<JobStatus, JobError> {
+        let jobs = self.jobs.read().await;
+        let job = jobs
+            .get(job_id)
+            .ok_or_else(|| JobError::UnknownJob(job_id.to_string()))?;
+        if job.status.state.is_finished() {
+            return Err(JobError::Finished(job_id.to_string()));
+        }
+        if keep_partial {
+            if let Some(partial) = &job.partial {
+                partial.keep();
+            }
+        }
+        job.cancel.cancel();
+        Ok(job.status())
+    }
+
+    /// Stops a running download's transfer, keeping what it wrote, until
+    /// [`resume_download`](Self::resume_download). Pausing a paused
+    /// download changes nothing. A paused download that is cancelled leaves
+    /// its partial file for a later download of the same file.
+    pub async fn pause_download(&self, job_id: &str) -> Result<JobStatus, JobError> {
+        let mut jobs = self.jobs.write().await;
+        let job = jobs
+            .get_mut(job_id)
+            .ok_or_else(|| JobError::UnknownJob(job_id.to_string()))?;
+        if job.status.state.is_finished() {
+            return Err(JobError::Finished(job_id.to_string()));
+        }
+        let (Some(pause), Some(partial)) = (&job.pause, &job.partial) else {
+            return Err(JobError::NotPausable(job_id.to_string()));
+        };
+        partial.keep();
+        pause.pause();
+        job.status.state = JobState::Paused;
+        Ok(job.status())
+    }
+
+    /// Lets a paused download continue where it stopped.
+    pub async fn resume_download(&self, job_id: &str) -> Result<JobStatus, JobError> {
+        let mut jobs = self.jobs.write().await;
+        let job = jobs
+            .get_mut(job_id)
+            .ok_or_else(|| JobError::UnknownJob(job_id.to_string()))?;
+        if job.status.state.is_finished() {
+            return Err(JobError::Finished(job_id.to_string()));
+        }
+        let (Some(pause), Some(partial)) = (&job.pause, &job.partial) else {
+            return Err(JobError::NotPausable(job_id.to_string()));
+        };
+        // A cancellation after the resume removes the partial file again.
+        partial.discard();
+        pause.resume();
+        job.status.state = JobState::Running;
+        Ok(job.status())
+    }
+
+    /// Changes the queue priority of a job still waiting for a slot, so a
+    /// small download can be let ahead of a large one queued before it.
+    pub async fn prioritize(
+        &self,
+        job_id: &str,
+        priority: OperationPriority,
+    ) -> Result<JobStatus, JobError> {
+        let jobs = self.jobs.read().await;
+        let job = jobs
+            .get(job_id)
+            .ok_or_else(|| JobError::UnknownJob(job_id.to_string()))?;
+        if job.status.state.is_finished() {
+            return Err(JobError::Finished(job_id.to_string()));
+        }
+        match job.queue.as_ref().and_then(JobQueue::ticket) {
+            Some(ticket) if ticket.prioritize(priority) => Ok(job.status()),
+            _ => Err(JobError::NotQueued(job_id.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugins::admission::{AdmissionControl, QueuedOperation};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    async fn wait_finished(registry: &JobRegistry, job_id: &str) -> JobStatus {
+        for _ in 0..100 {
+            let status = registry.get(job_id).await.unwrap();
+            if status.state.is_finished() {
+                return status;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("job {} did not finish", job_id);
+    }
+
+    #[tokio::test]
+    async fn records_outcomes_and_cancellation() {
+        let registry = Arc::new(JobRegistry::new());
+        let done = registry
+            .spawn("echo", None, CancellationToken::new(), None, async {
+                Ok::<_, String>(serde_json::json!({"bytes": 42}))
+            })
+            .await;
+        let done = wait_finished(&registry, &done.id).await;
+        assert_eq!(done.state, JobState::Succeeded);
+        assert_eq!(done.result.unwrap()["bytes"], 42);
+
+        let cancel = CancellationToken::new();
+        let token = cancel.clone();
+        let partial = PartialDownload::default();
+        let admission = AdmissionControl::default()
+            .join(QueuedOperation::Download, OperationPriority::Normal)
+            .unwrap();
+        let download = DownloadJob {
+            progress: &DownloadProgress::new(),
+            partial: partial.clone(),
+            queue: admission.ticket().into(),
+            pause: DownloadPause::default(),
+            start_at: None,
+        };
+        let slow = registry
+            .spawn(
+                "sleep",
+                Some("llmserver-rs".to_string()),
+                cancel,
+                Some(download),
+                async move {
+                    token.cancelled().await;
+                    Err::<(), _>("operation cancelled")
+                },
+            )
+            .await;
+        assert_eq!(registry.list().await[0].id, slow.id);
+        registry.cancel_download(&slow.id, true).await.unwrap();
+        let slow = wait_finished(&registry, &slow.id).await;
+        assert_eq!(slow.state, JobState::Cancelled);
+        assert!(partial.is_kept());
+        assert!(matches!(
+            registry.cancel(&slow.id).await,
+            Err(JobError::Finished(_))
+        ));
+        assert!(matches!(
+            registry.get("missing").await,
+            Err(JobError::UnknownJob(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn pauses_and_resumes_downloads() {
+        let registry = Arc::new(JobRegistry::new());
+        let cancel = CancellationToken::new();
+        let partial = PartialDownload::default();
+        let pause = DownloadPause::default();
+        let admission = AdmissionControl::default()
+            .join(QueuedOperation::Download, OperationPriority::Normal)
+            .unwrap();
+        let download = DownloadJob {
+            progress: &DownloadProgress::new(),
+            partial: partial.clone(),
+            queue: admission.ticket().into(),
+            pause: pause.clone(),
+            start_at: None,
+        };
+        let (job_cancel, transfers) = (cancel.clone(), Arc::new(AtomicUsize::new(0)));
+        let started = transfers.clone();
+        let job = registry
+            .spawn("download", None, cancel, Some(download), async move {
+                loop {
+                    pause.resumed().await;
+                    let transfer = pause.transfer(&job_cancel);
+                    let resumes = started.fetch_add(1, Ordering::SeqCst);
+                    if resumes > 0 {
+                        return Ok::<_, String>(resumes);
+                    }
+                    transfer.cancelled().await;
+                }
+            })
+            .await;
+
+        while transfers.load(Ordering::SeqCst) == 0 {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        let paused = registry.pause_download(&job.id).await.unwrap();
+        assert_eq!(paused.state, JobState::Paused);
+        assert!(partial.is_kept());
+
+        let resumed = registry.resume_download(&job.id).await.unwrap();
+        assert_eq!(resumed.state, JobState::Running);
+        assert!(!partial.is_kept());
+        let done = wait_finished(&registry, &job.id).await;
+        assert_eq!(done.result.unwrap(), 1);
+
+        let plain = registry
+            .spawn("echo", None, CancellationToken::new(), None, async {
+                tokio::time::sleep(Duration::from_secs(60)).await;
+                Ok::<_, String>(())
+            })
+            .await;
+        assert!(matches!(
+            registry.pause_download(&plain.id).await,
+            Err(JobError::NotPausable(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn starts_scheduled_jobs_at_their_time() {
+        let registry = Arc::new(JobRegistry::new());
+        let progress = DownloadProgress::new();
+        let scheduled = |start_at: DateTime<Utc>| DownloadJob {
+            progress: &progress,
+            partial: PartialDownload::default(),
+            queue: JobQueue::default(),
+            pause: DownloadPause::default(),
+            start_at: Some(start_at),
+        };
+
+        let soon = Utc::now() + chrono::Duration::milliseconds(50);
+        let job = registry
+            .spawn(
+                "download",
+                None,
+                CancellationToken::new(),
+                Some(scheduled(soon)),
+                async { Ok::<_, String>(Utc::now()) },
+            )
+            .await;
+        assert_eq!(job.state, JobState::Scheduled);
+        assert_eq!(job.start_at, Some(soon));
+        let done = wait_finished(&registry, &job.id).await;
+        let started: DateTime<Utc> = serde_json::from_value(done.result.unwrap()).unwrap();
+        assert!(started >= soon);
+
+        let later = Utc::now() + chrono::Duration::hours(8);
+        let job = registry
+            .spawn(
+                "download",
+                None,
+                CancellationToken::new(),
+                Some(scheduled(later)),
+                async { Ok::<_, String>(()) },
+            )
+            .await;
+        registry.cancel(&job.id).await.unwrap();
+        assert_eq!(
+            wait_finished(&registry, &job.id).await.state,
+            JobState::Cancelled
+        );
+    }
+}
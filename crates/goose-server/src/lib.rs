@@ -1,8 +1,12 @@
 pub mod auth;
+pub mod cluster;
+pub mod jobs;
 pub mod openapi;
 pub mod plugins;
 pub mod routes;
 pub mod state;
+pub mod timeout;
+pub mod webhooks;
 
 // Re-export commonly used items
 pub use openapi::*;
@@ -1,11 +1,15 @@
+mod cluster;
 mod commands;
 mod configuration;
+mod discovery;
 mod error;
+mod jobs;
 mod logging;
 mod openapi;
 mod plugins;
 mod routes;
 mod state;
+mod webhooks;
 
 use clap::{Parser, Subcommand};
 
@@ -359,9 +359,89 @@ derive_utoipa!(Icon as IconSchema);
         super::routes::session::export_session,
         super::routes::session::import_session,
         super::routes::plugins::list_plugins,
+        super::routes::plugins::list_queues,
+        super::routes::plugins::list_models,
+        super::routes::plugins::delete_model,
+        super::routes::plugins::import_model,
+        super::routes::plugins::convert_model,
+        super::routes::plugins::conversion_progress,
+        super::routes::plugins::quantize_model,
+        super::routes::plugins::quantization_progress,
+        super::routes::plugins::pin_model,
+        super::routes::plugins::unpin_model,
+        super::routes::plugins::list_adapters,
+        super::routes::plugins::share_model,
+        super::routes::plugins::model_usage,
         super::routes::plugins::download_model,
+        super::routes::plugins::restore_models,
+        super::routes::plugins::model_health,
+        super::routes::plugins::repair_models,
+        super::routes::plugins::list_downloads,
+        super::routes::plugins::get_download,
+        super::routes::plugins::cancel_download,
+        super::routes::plugins::pause_download,
+        super::routes::plugins::resume_download,
+        super::routes::plugins::download_progress,
+        super::routes::plugins::list_revisions,
+        super::routes::plugins::list_model_files,
+        super::routes::plugins::list_nodes,
+        super::routes::plugins::list_profiles,
+        super::routes::plugins::check_model_updates,
+        super::routes::plugins::register_plugin,
+        super::routes::plugins::list_catalog,
+        super::routes::plugins::install_catalog_plugin,
+        super::routes::plugins::unregister_plugin,
+        super::routes::plugins::reload_plugin,
+        super::routes::plugins::enable_plugin,
+        super::routes::plugins::disable_plugin,
+        super::routes::plugins::get_plugin_config,
+        super::routes::plugins::set_plugin_config,
+        super::routes::plugins::get_plugin_config_schema,
+        super::routes::plugins::get_plugin_compat,
+        super::routes::plugins::plugin_metrics,
+        super::routes::plugins::get_plugin_defaults,
+        super::routes::plugins::set_plugin_defaults,
+        super::routes::plugins::start_task,
+        super::routes::plugins::stop_task,
+        super::routes::plugins::task_status,
+        super::routes::plugins::search_models,
+        super::routes::plugins::model_catalog,
+        super::routes::plugins::invoke_plugin_operation,
+        super::routes::plugins::plugin_health,
+        super::routes::plugins::validate_token,
+        super::routes::plugins::collect_models,
         super::routes::plugins::start_service,
         super::routes::plugins::stop_service,
+        super::routes::plugins::upgrade_service,
+        super::routes::plugins::service_logs,
+        super::routes::plugins::follow_service_logs,
+        super::routes::plugins::list_log_files,
+        super::routes::plugins::download_log_file,
+        super::routes::plugins::list_services,
+        super::routes::plugins::service_status,
+        super::routes::plugins::stop_instance,
+        super::routes::plugins::instance_logs,
+        super::routes::plugins::follow_instance_logs,
+        super::routes::plugins::instance_status,
+        super::routes::plugins::signal_instance,
+        super::routes::plugins::attach_console,
+        super::routes::admin::prune_logs,
+        super::routes::admin::get_offline,
+        super::routes::admin::set_offline,
+        super::routes::cluster::heartbeat,
+        super::routes::cluster::list_nodes,
+        super::routes::cluster::remove_node,
+        super::routes::cluster::download_model,
+        super::routes::cluster::start_service,
+        super::routes::jobs::list_jobs,
+        super::routes::jobs::get_job,
+        super::routes::jobs::cancel_job,
+        super::routes::jobs::prioritize_job,
+        super::routes::webhooks::list_webhooks,
+        super::routes::webhooks::create_webhook,
+        super::routes::webhooks::delete_webhook,
+        super::routes::plugins::plugin_events,
+        super::routes::plugins::publish_plugin_event,
         super::routes::session::update_session_user_recipe_values,
         super::routes::schedule::create_schedule,
         super::routes::schedule::list_schedules,
@@ -492,13 +572,146 @@ derive_utoipa!(Icon as IconSchema);
         crate::plugins::PluginCapability,
         crate::plugins::PluginMetadata,
         crate::plugins::PluginTaskType,
+        crate::plugins::ServiceSelector,
         crate::plugins::DownloadModelRequest,
+        crate::plugins::DownloadMode,
+        crate::plugins::DownloadTransfer,
+        crate::plugins::splits::GgufSplits,
+        crate::plugins::sources::ModelSource,
+        crate::plugins::proxy::ProxyConfig,
+        crate::plugins::sources::ObjectSource,
+        crate::plugins::sources::ObjectCredentials,
+        crate::plugins::peers::PeerSource,
+        crate::plugins::provenance::ModelSignature,
         crate::plugins::DownloadModelResponse,
+        crate::plugins::progress::DownloadProgressUpdate,
         crate::plugins::StartServiceRequest,
         crate::plugins::StartServiceResponse,
         crate::plugins::StopServiceRequest,
         crate::plugins::StopServiceResponse,
+        crate::plugins::ServiceLogsRequest,
+        crate::plugins::ServiceLogsResponse,
+        crate::plugins::logs::LogStream,
+        crate::plugins::logs::LogLevel,
+        crate::plugins::logs::LogEvent,
+        crate::plugins::logs::LogEntry,
+        crate::plugins::diagnostics::CrashKind,
+        crate::plugins::diagnostics::CrashReport,
+        crate::plugins::ServiceStatusRequest,
+        crate::plugins::ServiceStatusResponse,
+        crate::plugins::ListServicesResponse,
+        crate::plugins::ListLogFilesResponse,
+        crate::plugins::logs::ServiceLogFile,
+        crate::plugins::health::ServiceHealth,
+        crate::plugins::health::RestartPolicy,
+        crate::plugins::health::HealthCheckConfig,
+        crate::plugins::readiness::ReadinessProbe,
+        crate::plugins::events::PluginEventKind,
+        crate::plugins::events::PluginEvent,
+        crate::plugins::UpgradeServiceRequest,
+        crate::plugins::UpgradeServiceResponse,
+        crate::plugins::upgrade::SmokeTestConfig,
+        crate::plugins::upgrade::SmokeTestResult,
+        crate::plugins::affinity::CpuAffinity,
+        crate::plugins::SignalServiceRequest,
+        crate::plugins::SignalServiceResponse,
+        crate::plugins::signals::ServiceSignal,
+        crate::plugins::stdio::StdioMode,
+        crate::plugins::stdio::StdioConfig,
+        crate::plugins::retention::LogRetention,
+        crate::plugins::retention::PruneReport,
+        crate::plugins::ServiceLogsPruned,
+        crate::plugins::RegisterPluginRequest,
+        crate::plugins::catalog::PluginCatalog,
+        crate::plugins::signing::PluginSignature,
+        crate::plugins::catalog::CatalogEntry,
+        crate::plugins::catalog::CatalogPluginKind,
+        crate::plugins::external::ExternalPluginConfig,
+        crate::plugins::http::HttpPluginConfig,
+        crate::plugins::UnregisterPluginResponse,
+        crate::plugins::ReloadPluginResponse,
+        crate::plugins::settings::PluginConfig,
+        crate::plugins::compat::PluginCompatibility,
+        crate::plugins::defaults::PluginDefaults,
+        crate::plugins::fallback::FallbackAttempt,
+        crate::plugins::metrics::PluginMetrics,
+        crate::plugins::metrics::PluginMetricsEntry,
+        crate::plugins::metrics::PluginMetricsSnapshot,
+        crate::plugins::health::PluginHealthResponse,
+        crate::plugins::health::TaskHealth,
+        crate::plugins::ValidateTokenRequest,
+        crate::plugins::ValidateTokenResponse,
+        crate::plugins::ListModelsResponse,
+        crate::plugins::DeleteModelResponse,
+        crate::plugins::ImportModelRequest,
+        crate::plugins::ImportModelResponse,
+        crate::plugins::RestoreModelsRequest,
+        crate::plugins::RestoreModelsResponse,
+        crate::plugins::RestoreFailure,
+        crate::plugins::integrity::ModelHealthResponse,
+        crate::plugins::integrity::IntegrityIssue,
+        crate::plugins::integrity::IntegrityProblem,
+        crate::plugins::integrity::ModelOrigin,
+        crate::plugins::integrity::RepairModelsRequest,
+        crate::plugins::integrity::RepairModelsResponse,
+        crate::plugins::integrity::RepairFailure,
+        crate::plugins::import::ImportMode,
+        crate::plugins::usage::ModelUsageResponse,
+        crate::plugins::usage::ModelDirUsage,
+        crate::plugins::ModelUpdatesResponse,
+        crate::plugins::manifest::ModelRecord,
+        crate::plugins::manifest::AvailableUpdate,
+        crate::plugins::provenance::ModelProvenance,
+        crate::plugins::revisions::ModelUpdate,
+        crate::plugins::gc::ModelGcRequest,
+        crate::plugins::gc::ModelGcResponse,
+        crate::plugins::gc::ModelGcCandidate,
+        crate::plugins::ModelRevisionsResponse,
+        crate::plugins::revisions::GitRef,
+        crate::plugins::revisions::ModelCommit,
+        crate::plugins::ModelFilesResponse,
+        crate::plugins::revisions::RepoFile,
+        crate::plugins::ModelSearchResponse,
+        crate::plugins::search::HubModel,
+        crate::plugins::recommended::ModelCatalogResponse,
+        crate::plugins::recommended::ModelCatalogSource,
+        crate::plugins::recommended::CatalogModel,
+        crate::plugins::ListNodesResponse,
+        crate::plugins::remote::RemoteNode,
+        crate::plugins::ListProfilesResponse,
+        crate::plugins::profiles::HardwareProfile,
+        crate::plugins::admission::QueuedOperation,
+        crate::plugins::admission::OperationPriority,
+        crate::plugins::admission::QueueStatus,
+        super::routes::admin::PruneLogsRequest,
+        super::routes::admin::PruneLogsResponse,
+        crate::plugins::offline::OfflineStatus,
+        crate::plugins::offload::GpuOffload,
+        crate::plugins::adapters::LoraAdapter,
+        crate::plugins::adapters::ListAdaptersResponse,
+        crate::plugins::adapters::AdapterInfo,
+        crate::plugins::convert::ConvertModelRequest,
+        crate::plugins::convert::ConvertModelResponse,
+        crate::plugins::quantize::QuantizeModelRequest,
+        crate::plugins::quantize::QuantizeModelResponse,
+        super::routes::cluster::ClusterDownloadRequest,
+        super::routes::cluster::ClusterDownloadResponse,
+        super::routes::cluster::ClusterStartRequest,
+        super::routes::cluster::ClusterStartResponse,
+        crate::cluster::NodeHeartbeat,
+        crate::cluster::NodeResources,
+        crate::cluster::ClusterNodeStatus,
+        crate::jobs::JobStatus,
+        crate::jobs::JobState,
+        crate::jobs::PrioritizeJobRequest,
+        crate::webhooks::Webhook,
+        crate::webhooks::WebhookEvent,
+        crate::webhooks::CreateWebhookRequest,
+        crate::webhooks::CreateWebhookResponse,
+        crate::webhooks::WebhookPayload,
+        super::routes::plugins::SignalInstanceBody,
         super::routes::plugins::PluginErrorResponse,
+        crate::plugins::codes::PluginErrorCode,
     ))
 )]
 pub struct ApiDoc;
@@ -0,0 +1,241 @@
+//! LoRA adapters for downloaded models. Adapters of a model are kept in
+//! `adapters/<model file stem>/` next to it and attached when a service is
+//! started, after checking their GGUF header names the model's architecture.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::offload::{self, GgufIdentity};
+use super::{PluginError, PluginTaskType};
+
+/// Directory next to a model file that holds the adapters of each model.
+pub const ADAPTERS_DIR: &str = "adapters";
+
+/// An adapter attached to a service.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq)]
+pub struct LoraAdapter {
+    /// Adapter file, or its path relative to the model's adapter directory.
+    pub path: String,
+    /// Strength the adapter is applied with; 1.0 when unset.
+    #[serde(default)]
+    pub scale: Option<f32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ListAdaptersRequest {
+    pub task_type: PluginTaskType,
+    /// Model file relative to the task type's model directory.
+    pub filename: String,
+    #[serde(default)]
+    pub destination_dir: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq)]
+pub struct AdapterInfo {
+    /// Path relative to the model's adapter directory, usable as a
+    /// [`LoraAdapter`] path.
+    pub filename: String,
+    pub saved_path: String,
+    pub bytes: u64,
+    /// Architecture from the adapter's GGUF header; unset when the file is
+    /// not a GGUF adapter.
+    pub architecture: Option<String>,
+    /// Whether the adapter can be attached to the model.
+    pub compatible: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ListAdaptersResponse {
+    pub model_path: String,
+    pub adapter_dir: String,
+    /// Architecture from the model's GGUF header.
+    pub architecture: Option<String>,
+    pub adapters: Vec<AdapterInfo>,
+}
+
+/// Directory the adapters of the model file `model_path` are kept in.
+pub fn adapter_dir(model_path: &Path) -> PathBuf {
+    let stem = model_path.file_stem().unwrap_or_default();
+    model_path
+        .parent()
+        .unwrap_or(Path::new(""))
+        .join(ADAPTERS_DIR)
+        .join(stem)
+}
+
+/// Checks that `adapter` is a GGUF adapter for the architecture of the model
+/// `model`.
+pub fn check_compatible(model: &Path, adapter: &Path) -> Result<(), PluginError> {
+    let model_identity = offload::read_identity(model).ok_or_else(|| {
+        PluginError::InvalidRequest(format!(
+            "adapters need a GGUF model, {} is not one",
+            model.display()
+        ))
+    })?;
+    compatible_with(&model_identity, adapter)
+}
+
+fn compatible_with(model: &GgufIdentity, adapter: &Path) -> Result<(), PluginError> {
+    let identity = offload::read_identity(adapter)
+        .filter(GgufIdentity::is_adapter)
+        .ok_or_else(|| {
+            PluginError::InvalidRequest(format!("{} is not a GGUF adapter", adapter.display()))
+        })?;
+    if identity.architecture != model.architecture {
+        return Err(PluginError::InvalidRequest(format!(
+            "{} is an adapter for {}, the model is {}",
+            adapter.display(),
+            identity.architecture,
+            model.architecture
+        )));
+    }
+    Ok(())
+}
+
+/// Lists the files in `dir` with whether they fit the model `model`.
+pub fn list(dir: &Path, model: &Path) -> Result<Vec<AdapterInfo>, PluginError> {
+    let model_identity = offload::read_identity(model);
+    let mut adapters = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        let entries = match std::fs::read_dir(&current) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(err.into()),
+        };
+        for entry in entries {
+            let entry = entry?;
+            let path = entry.path();
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                pending.push(path);
+                continue;
+            }
+            let identity = offload::read_identity(&path).filter(GgufIdentity::is_adapter);
+            adapters.push(AdapterInfo {
+                filename: path
+                    .strip_prefix(dir)
+                    .unwrap_or(&path)
+                    .to_string_lossy()
+                    .to_string(),
+                saved_path: path.to_string_lossy().to_string(),
+                bytes: entry.metadata()?.len(),
+                compatible: model_identity
+                    .as_ref()
+                    .is_some_and(|model| compatible_with(model, &path).is_ok()),
+                architecture: identity.map(|identity| identity.architecture),
+            });
+        }
+    }
+    adapters.sort_by(|a, b| a.filename.cmp(&b.filename));
+    Ok(adapters)
+}
+
+/// llama.cpp-style arguments attaching `adapters`, already resolved to
+/// their paths.
+pub fn args(adapters: &[(PathBuf, Option<f32>)]) -> Vec<String> {
+    let mut args = Vec::new();
+    for (path, scale) in adapters {
+        let path = path.to_string_lossy().to_string();
+        match scale {
+            Some(scale) => args.extend(["--lora-scaled".to_string(), path, scale.to_string()]),
+            None => args.extend(["--lora".to_string(), path]),
+        }
+    }
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gguf(path: &Path, strings: &[(&str, &str)]) {
+        fn string(out: &mut Vec<u8>, value: &str) {
+            out.extend((value.len() as u64).to_le_bytes());
+            out.extend(value.as_bytes());
+        }
+        let mut header = b"GGUF".to_vec();
+        header.extend(3u32.to_le_bytes());
+        header.extend(0u64.to_le_bytes());
+        header.extend((strings.len() as u64).to_le_bytes());
+        for (key, value) in strings {
+            string(&mut header, key);
+            header.extend(8u32.to_le_bytes());
+            string(&mut header, value);
+        }
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, header).unwrap();
+    }
+
+    #[test]
+    fn matches_adapters_to_the_model_architecture() {
+        let dir = tempfile::tempdir().unwrap();
+        let model = dir.path().join("llama-3-8b.Q4_K_M.gguf");
+        gguf(
+            &model,
+            &[("general.architecture", "llama"), ("general.type", "model")],
+        );
+        let adapters = adapter_dir(&model);
+        assert_eq!(
+            adapters,
+            dir.path().join("adapters").join("llama-3-8b.Q4_K_M")
+        );
+        let lora = adapters.join("chat.gguf");
+        gguf(
+            &lora,
+            &[
+                ("general.architecture", "llama"),
+                ("general.type", "adapter"),
+            ],
+        );
+        let other = adapters.join("nested/qwen.gguf");
+        gguf(
+            &other,
+            &[
+                ("general.architecture", "qwen2"),
+                ("general.type", "adapter"),
+            ],
+        );
+
+        assert!(check_compatible(&model, &lora).is_ok());
+        assert!(matches!(
+            check_compatible(&model, &other),
+            Err(PluginError::InvalidRequest(_))
+        ));
+        assert!(matches!(
+            check_compatible(&model, &model),
+            Err(PluginError::InvalidRequest(_))
+        ));
+
+        let listed = list(&adapters, &model).unwrap();
+        let summary: Vec<_> = listed
+            .iter()
+            .map(|adapter| {
+                (
+                    adapter.filename.as_str(),
+                    adapter.architecture.as_deref(),
+                    adapter.compatible,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("chat.gguf", Some("llama"), true),
+                ("nested/qwen.gguf", Some("qwen2"), false)
+            ]
+        );
+        assert_eq!(
+            args(&[(lora.clone(), None), (other.clone(), Some(0.5))]),
+            [
+                "--lora".to_string(),
+                lora.to_string_lossy().to_string(),
+                "--lora-scaled".to_string(),
+                other.to_string_lossy().to_string(),
+                "0.5".to_string()
+            ]
+        );
+    }
+}
@@ -0,0 +1,476 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::oneshot;
+use tokio_util::sync::CancellationToken;
+use utoipa::ToSchema;
+
+/// Suggested wait before retrying an operation that was turned away.
+pub const RETRY_AFTER_SECS: u64 = 5;
+
+/// Expensive plugin operations that are admitted through a bounded queue.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum QueuedOperation {
+    Download,
+    Upgrade,
+}
+
+impl QueuedOperation {
+    fn env_prefix(self) -> &'static str {
+        match self {
+            QueuedOperation::Download => "GOOSE_PLUGIN_QUEUE_DOWNLOAD",
+            QueuedOperation::Upgrade => "GOOSE_PLUGIN_QUEUE_UPGRADE",
+        }
+    }
+
+    fn defaults(self) -> (usize, usize) {
+        match self {
+            QueuedOperation::Download => (2, 8),
+            QueuedOperation::Upgrade => (1, 4),
+        }
+    }
+}
+
+/// Waiting operations with a higher priority are admitted first; equal
+/// priorities are admitted in arrival order.
+#[derive(
+    Debug, Clone, Copy, Default, Serialize, Deserialize, ToSchema, PartialEq, Eq, PartialOrd, Ord,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum OperationPriority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct QueueStatus {
+    pub operation: QueuedOperation,
+    /// Operations allowed to run at the same time.
+    pub concurrency: usize,
+    /// Waiting operations beyond which new requests are rejected.
+    pub max_queued: usize,
+    pub active: usize,
+    pub queued: usize,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("{operation:?} queue is full ({queued} waiting)")]
+pub struct QueueFull {
+    pub operation: QueuedOperation,
+    pub queued: usize,
+}
+
+struct Waiter {
+    priority: OperationPriority,
+    sequence: u64,
+    admit: oneshot::Sender<()>,
+}
+
+impl PartialEq for Waiter {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Waiter {}
+
+impl PartialOrd for Waiter {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Waiter {
+    // Max-heap: higher priority first, then the lower (earlier) sequence.
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.sequence.cmp(&self.sequence))
+    }
+}
+
+struct QueueState {
+    active: usize,
+    next_sequence: u64,
+    waiting: BinaryHeap<Waiter>,
+}
+
+struct Queue {
+    operation: QueuedOperation,
+    concurrency: usize,
+    max_queued: usize,
+    state: Mutex<QueueState>,
+}
+
+impl Queue {
+    fn from_env(operation: QueuedOperation) -> Self {
+        fn var(name: String) -> Option<usize> {
+            std::env::var(name).ok()?.trim().parse().ok()
+        }
+        let (concurrency, max_queued) = operation.defaults();
+        let prefix = operation.env_prefix();
+        Self {
+            operation,
+            concurrency: var(format!("{}_CONCURRENCY", prefix))
+                .unwrap_or(concurrency)
+                .max(1),
+            max_queued: var(format!("{}_MAX_QUEUED", prefix)).unwrap_or(max_queued),
+            state: Mutex::new(QueueState {
+                active: 0,
+                next_sequence: 0,
+                waiting: BinaryHeap::new(),
+            }),
+        }
+    }
+
+    fn status(&self) -> QueueStatus {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        QueueStatus {
+            operation: self.operation,
+            concurrency: self.concurrency,
+            max_queued: self.max_queued,
+            active: state.active,
+            queued: state.waiting.len(),
+        }
+    }
+
+    /// Hands the slot of a finished operation to the next live waiter.
+    fn release(&self) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        while let Some(waiter) = state.waiting.pop() {
+            record_depth(self.operation, -1);
+            // Fails when the waiting request was dropped, e.g. because its
+            // client disconnected.
+            if waiter.admit.send(()).is_ok() {
+                return;
+            }
+        }
+        state.active -= 1;
+    }
+}
+
+fn record_depth(operation: QueuedOperation, delta: i64) {
+    tracing::debug!(
+        target: "goose::metrics",
+        operation = ?operation,
+        counter.plugin_queue_depth = delta,
+        "plugin queue depth changed"
+    );
+}
+
+/// Holds a slot in an operation queue; the next waiter is admitted on drop.
+pub struct AdmissionPermit {
+    queue: Arc<Queue>,
+}
+
+impl Drop for AdmissionPermit {
+    fn drop(&mut self) {
+        self.queue.release();
+    }
+}
+
+/// A request waiting in a queue. If it is dropped after its slot was handed
+/// over but before it noticed, the slot is passed on instead of leaking.
+struct PendingAdmission {
+    queue: Arc<Queue>,
+    admitted: Option<oneshot::Receiver<()>>,
+}
+
+impl Drop for PendingAdmission {
+    fn drop(&mut self) {
+        if let Some(mut admitted) = self.admitted.take() {
+            admitted.close();
+            if admitted.try_recv().is_ok() {
+                self.queue.release();
+            }
+        }
+    }
+}
+
+/// An operation that joined a queue, holding a slot or waiting for one.
+pub struct QueuedAdmission {
+    ticket: QueueTicket,
+    slot: Slot,
+}
+
+enum Slot {
+    Held(AdmissionPermit),
+    Waiting(PendingAdmission),
+}
+
+impl QueuedAdmission {
+    /// Reports the operation's place in the queue while it waits.
+    pub fn ticket(&self) -> QueueTicket {
+        self.ticket.clone()
+    }
+
+    /// Waits until the operation holds a slot.
+    pub async fn admitted(self) -> AdmissionPermit {
+        match self.slot {
+            Slot::Held(permit) => permit,
+            Slot::Waiting(mut pending) => {
+                if let Some(admitted) = pending.admitted.as_mut() {
+                    // The sender is only dropped unsent when the queue is gone.
+                    let _ = admitted.await;
+                }
+                pending.admitted = None;
+                AdmissionPermit {
+                    queue: pending.queue.clone(),
+                }
+            }
+        }
+    }
+
+    /// Like [`admitted`](Self::admitted), but gives up the place in the
+    /// queue and returns `None` once `cancel` fires.
+    pub async fn admitted_unless_cancelled(
+        self,
+        cancel: &CancellationToken,
+    ) -> Option<AdmissionPermit> {
+        tokio::select! {
+            permit = self.admitted() => Some(permit),
+            _ = cancel.cancelled() => None,
+        }
+    }
+}
+
+/// Where a queued operation stands, readable while it waits.
+#[derive(Clone)]
+pub struct QueueTicket {
+    queue: Arc<Queue>,
+    /// Sequence of the operation's waiter, unless it was admitted without
+    /// waiting.
+    waiting: Option<u64>,
+}
+
+impl QueueTicket {
+    /// 1-based place among the waiting operations, `None` once the
+    /// operation was admitted.
+    pub fn position(&self) -> Option<usize> {
+        let sequence = self.waiting?;
+        let state = self.queue.state.lock().unwrap_or_else(|e| e.into_inner());
+        let priority = state
+            .waiting
+            .iter()
+            .find(|waiter| waiter.sequence == sequence)?
+            .priority;
+        let ahead = state
+            .waiting
+            .iter()
+            .filter(|waiter| {
+                !waiter.admit.is_closed()
+                    && (waiter.priority, Reverse(waiter.sequence)) > (priority, Reverse(sequence))
+            })
+            .count();
+        Some(ahead + 1)
+    }
+
+    /// Moves the waiting operation to `priority`, behind operations that
+    /// joined earlier with the same priority. Returns `false` once the
+    /// operation was admitted.
+    pub fn prioritize(&self, priority: OperationPriority) -> bool {
+        let Some(sequence) = self.waiting else {
+            return false;
+        };
+        let mut state = self.queue.state.lock().unwrap_or_else(|e| e.into_inner());
+        // The heap has no way to reorder one entry, so it is rebuilt.
+        let mut waiting = std::mem::take(&mut state.waiting).into_vec();
+        let waiter = waiting
+            .iter_mut()
+            .find(|waiter| waiter.sequence == sequence && !waiter.admit.is_closed());
+        let queued = waiter.is_some();
+        if let Some(waiter) = waiter {
+            waiter.priority = priority;
+        }
+        state.waiting = waiting.into();
+        queued
+    }
+}
+
+/// Bounded, prioritised admission for expensive plugin operations, shared by
+/// all plugins. Limits come from `GOOSE_PLUGIN_QUEUE_<OP>_CONCURRENCY` and
+/// `GOOSE_PLUGIN_QUEUE_<OP>_MAX_QUEUED`.
+#[derive(Clone)]
+pub struct AdmissionControl {
+    queues: Arc<[Arc<Queue>]>,
+}
+
+impl Default for AdmissionControl {
+    fn default() -> Self {
+        Self {
+            queues: [QueuedOperation::Download, QueuedOperation::Upgrade]
+                .into_iter()
+                .map(|operation| Arc::new(Queue::from_env(operation)))
+                .collect(),
+        }
+    }
+}
+
+impl AdmissionControl {
+    fn queue(&self, operation: QueuedOperation) -> &Arc<Queue> {
+        self.queues
+            .iter()
+            .find(|queue| queue.operation == operation)
+            .expect("every operation has a queue")
+    }
+
+    /// Waits for a free slot, or fails right away when too many operations
+    /// are already waiting.
+    pub async fn admit(
+        &self,
+        operation: QueuedOperation,
+        priority: OperationPriority,
+    ) -> Result<AdmissionPermit, QueueFull> {
+        Ok(self.join(operation, priority)?.admitted().await)
+    }
+
+    /// Takes a free slot or a place in the queue right away, failing when
+    /// too many operations are already waiting. The slot is held once
+    /// [`QueuedAdmission::admitted`] returns.
+    pub fn join(
+        &self,
+        operation: QueuedOperation,
+        priority: OperationPriority,
+    ) -> Result<QueuedAdmission, QueueFull> {
+        let queue = self.queue(operation).clone();
+        let mut state = queue.state.lock().unwrap_or_else(|e| e.into_inner());
+        if state.active < queue.concurrency && state.waiting.is_empty() {
+            state.active += 1;
+            drop(state);
+            return Ok(QueuedAdmission {
+                ticket: QueueTicket {
+                    queue: queue.clone(),
+                    waiting: None,
+                },
+                slot: Slot::Held(AdmissionPermit { queue }),
+            });
+        }
+        if state.waiting.len() >= queue.max_queued {
+            return Err(QueueFull {
+                operation,
+                queued: state.waiting.len(),
+            });
+        }
+
+        let (admit, admitted) = oneshot::channel();
+        let sequence = state.next_sequence;
+        state.next_sequence += 1;
+        state.waiting.push(Waiter {
+            priority,
+            sequence,
+            admit,
+        });
+        record_depth(operation, 1);
+        drop(state);
+        Ok(QueuedAdmission {
+            ticket: QueueTicket {
+                queue: queue.clone(),
+                waiting: Some(sequence),
+            },
+            slot: Slot::Waiting(PendingAdmission {
+                queue,
+                admitted: Some(admitted),
+            }),
+        })
+    }
+
+    pub fn status(&self) -> Vec<QueueStatus> {
+        self.queues.iter().map(|queue| queue.status()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn admits_by_priority_and_rejects_when_full() {
+        std::env::set_var("GOOSE_PLUGIN_QUEUE_UPGRADE_CONCURRENCY", "1");
+        std::env::set_var("GOOSE_PLUGIN_QUEUE_UPGRADE_MAX_QUEUED", "2");
+        let control = AdmissionControl::default();
+        let op = QueuedOperation::Upgrade;
+
+        let running = control.admit(op, OperationPriority::Normal).await.unwrap();
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        for (name, priority) in [
+            ("low", OperationPriority::Low),
+            ("high", OperationPriority::High),
+        ] {
+            let control = control.clone();
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                let _permit = control.admit(op, priority).await.unwrap();
+                tx.send(name).unwrap();
+            });
+        }
+        while control.status()[1].queued < 2 {
+            tokio::task::yield_now().await;
+        }
+        assert!(control.admit(op, OperationPriority::High).await.is_err());
+
+        drop(running);
+        assert_eq!(rx.recv().await, Some("high"));
+        assert_eq!(rx.recv().await, Some("low"));
+    }
+
+    #[tokio::test]
+    async fn tickets_report_queue_position() {
+        let control = AdmissionControl::default();
+        let op = QueuedOperation::Download;
+        let mut running = Vec::new();
+        for _ in 0..control.status()[0].concurrency {
+            let admission = control.join(op, OperationPriority::Normal).unwrap();
+            assert_eq!(admission.ticket().position(), None);
+            running.push(admission.admitted().await);
+        }
+
+        let low = control.join(op, OperationPriority::Low).unwrap();
+        let normal = control.join(op, OperationPriority::Normal).unwrap();
+        let high = control.join(op, OperationPriority::High).unwrap();
+        assert_eq!(high.ticket().position(), Some(1));
+        assert_eq!(normal.ticket().position(), Some(2));
+        assert_eq!(low.ticket().position(), Some(3));
+
+        running.pop();
+        let _high = high.admitted().await;
+        assert_eq!(normal.ticket().position(), Some(1));
+        assert_eq!(low.ticket().position(), Some(2));
+
+        assert!(low.ticket().prioritize(OperationPriority::High));
+        assert_eq!(low.ticket().position(), Some(1));
+        assert_eq!(normal.ticket().position(), Some(2));
+        let ticket = low.ticket();
+        running.pop();
+        let _low = low.admitted().await;
+        assert!(!ticket.prioritize(OperationPriority::Low));
+    }
+
+    #[tokio::test]
+    async fn cancelled_operations_leave_the_queue() {
+        let control = AdmissionControl::default();
+        let op = QueuedOperation::Upgrade;
+        let mut running = Vec::new();
+        for _ in 0..control.status()[1].concurrency {
+            running.push(control.admit(op, OperationPriority::Normal).await.unwrap());
+        }
+
+        let cancelled = control.join(op, OperationPriority::High).unwrap();
+        let next = control.join(op, OperationPriority::Normal).unwrap();
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        assert!(cancelled.admitted_unless_cancelled(&cancel).await.is_none());
+        assert_eq!(next.ticket().position(), Some(1));
+
+        running.pop();
+        let _next = next
+            .admitted_unless_cancelled(&CancellationToken::new())
+            .await
+            .unwrap();
+        assert_eq!(control.status()[1].queued, 0);
+    }
+}
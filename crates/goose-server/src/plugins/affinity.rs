@@ -0,0 +1,180 @@
+use serde::{Deserialize, Serialize};
+use tokio::process::{Child, Command};
+use utoipa::ToSchema;
+
+use super::PluginError;
+
+/// Restricts a managed process to a subset of logical CPUs.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct CpuAffinity {
+    /// Logical CPU indices, e.g. `[0, 1, 2, 3]`.
+    #[serde(default)]
+    pub cores: Option<Vec<usize>>,
+    /// NUMA node whose CPUs the process may run on (Linux only). When combined
+    /// with `cores`, only cores that belong to the node are used.
+    #[serde(default)]
+    pub numa_node: Option<u32>,
+}
+
+impl CpuAffinity {
+    /// Resolves the requested cores and NUMA node into a sorted list of CPU indices.
+    pub fn resolve(&self) -> Result<Vec<usize>, PluginError> {
+        let mut cores = match (&self.cores, self.numa_node) {
+            (None, None) => {
+                return Err(PluginError::InvalidRequest(
+                    "cpu_affinity requires cores or numa_node".to_string(),
+                ))
+            }
+            (Some(cores), None) => cores.clone(),
+            (cores, Some(node)) => {
+                let node_cores = numa_node_cpus(node)?;
+                match cores {
+                    Some(cores) => cores
+                        .iter()
+                        .copied()
+                        .filter(|core| node_cores.contains(core))
+                        .collect(),
+                    None => node_cores,
+                }
+            }
+        };
+        cores.sort_unstable();
+        cores.dedup();
+
+        if cores.is_empty() {
+            return Err(PluginError::InvalidRequest(
+                "cpu_affinity does not select any CPU".to_string(),
+            ));
+        }
+        if let Some(max) = cores.last().filter(|max| **max >= MAX_CPUS) {
+            return Err(PluginError::InvalidRequest(format!(
+                "cpu index {} exceeds the supported maximum of {}",
+                max,
+                MAX_CPUS - 1
+            )));
+        }
+        Ok(cores)
+    }
+}
+
+#[cfg(target_os = "linux")]
+const MAX_CPUS: usize = libc::CPU_SETSIZE as usize;
+
+#[cfg(not(target_os = "linux"))]
+const MAX_CPUS: usize = usize::BITS as usize;
+
+/// Parses the kernel's CPU list format, e.g. `0-3,8,10-11`.
+pub fn parse_cpu_list(list: &str) -> Option<Vec<usize>> {
+    let mut cpus = Vec::new();
+    for part in list.trim().split(',').filter(|part| !part.is_empty()) {
+        match part.split_once('-') {
+            Some((start, end)) => {
+                let start: usize = start.trim().parse().ok()?;
+                let end: usize = end.trim().parse().ok()?;
+                cpus.extend(start..=end);
+            }
+            None => cpus.push(part.trim().parse().ok()?),
+        }
+    }
+    Some(cpus)
+}
+
+#[cfg(target_os = "linux")]
+fn numa_node_cpus(node: u32) -> Result<Vec<usize>, PluginError> {
+    let path = format!("/sys/devices/system/node/node{}/cpulist", node);
+    let list = std::fs::read_to_string(&path)
+        .map_err(|_| PluginError::InvalidRequest(format!("NUMA node {} not found", node)))?;
+    parse_cpu_list(&list)
+        .ok_or_else(|| PluginError::Internal(format!("unexpected contents in {}", path)))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn numa_node_cpus(_node: u32) -> Result<Vec<usize>, PluginError> {
+    Err(PluginError::InvalidRequest(
+        "NUMA pinning is only supported on Linux".to_string(),
+    ))
+}
+
+/// Arranges for the child to be pinned before it executes (Linux).
+#[cfg(target_os = "linux")]
+pub fn configure_command(command: &mut Command, cores: &[usize]) -> Result<(), PluginError> {
+    // SAFETY: cpu_set_t is plain data; CPU_ZERO/CPU_SET only write into it.
+    let set = unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        libc::CPU_ZERO(&mut set);
+        for core in cores {
+            libc::CPU_SET(*core, &mut set);
+        }
+        set
+    };
+
+    // SAFETY: the closure only performs the async-signal-safe sched_setaffinity
+    // syscall on a set that was fully built before forking.
+    unsafe {
+        command.pre_exec(move || {
+            if libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+pub fn configure_command(_command: &mut Command, _cores: &[usize]) -> Result<(), PluginError> {
+    // Windows applies the mask to the spawned process, see `apply_to_child`.
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+pub fn configure_command(_command: &mut Command, _cores: &[usize]) -> Result<(), PluginError> {
+    Err(PluginError::InvalidRequest(
+        "CPU affinity is not supported on this platform".to_string(),
+    ))
+}
+
+#[cfg(windows)]
+pub fn apply_to_child(child: &Child, cores: &[usize]) -> Result<(), PluginError> {
+    use winapi::um::winbase::SetProcessAffinityMask;
+
+    let handle = child
+        .raw_handle()
+        .ok_or_else(|| PluginError::ProcessStart("process already exited".to_string()))?;
+    let mask = cores.iter().fold(0usize, |mask, core| mask | (1 << core));
+    // SAFETY: the handle belongs to a live child process owned by `child`.
+    if unsafe { SetProcessAffinityMask(handle as _, mask) } == 0 {
+        return Err(PluginError::Io(std::io::Error::last_os_error()));
+    }
+    Ok(())
+}
+
+#[cfg(not(windows))]
+pub fn apply_to_child(_child: &Child, _cores: &[usize]) -> Result<(), PluginError> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_kernel_cpu_lists() {
+        assert_eq!(
+            parse_cpu_list("0-3,8,10-11\n"),
+            Some(vec![0, 1, 2, 3, 8, 10, 11])
+        );
+        assert_eq!(parse_cpu_list(""), Some(vec![]));
+        assert_eq!(parse_cpu_list("a-b"), None);
+    }
+
+    #[test]
+    fn resolves_explicit_cores() {
+        let affinity = CpuAffinity {
+            cores: Some(vec![3, 1, 1]),
+            numa_node: None,
+        };
+        assert_eq!(affinity.resolve().unwrap(), vec![1, 3]);
+        assert!(CpuAffinity::default().resolve().is_err());
+    }
+}
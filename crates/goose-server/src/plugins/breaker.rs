@@ -0,0 +1,225 @@
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use super::PluginError;
+
+/// Recent outcomes considered when deciding whether a host is failing.
+const WINDOW: usize = 20;
+
+#[derive(Debug, Clone, Copy)]
+struct BreakerConfig {
+    /// Outcomes needed in the window before the circuit can open.
+    min_requests: usize,
+    /// Share of failed outcomes that opens the circuit.
+    failure_ratio: f64,
+    /// How long an open circuit fails fast before a trial request.
+    cooldown: Duration,
+}
+
+impl BreakerConfig {
+    fn from_env() -> Self {
+        fn var<T: std::str::FromStr>(name: &str) -> Option<T> {
+            std::env::var(name).ok()?.trim().parse().ok()
+        }
+        Self {
+            min_requests: var("GOOSE_PLUGIN_CIRCUIT_MIN_REQUESTS")
+                .unwrap_or(5usize)
+                .clamp(1, WINDOW),
+            failure_ratio: var("GOOSE_PLUGIN_CIRCUIT_FAILURE_RATIO")
+                .unwrap_or(0.5f64)
+                .clamp(0.0, 1.0),
+            cooldown: Duration::from_secs(var("GOOSE_PLUGIN_CIRCUIT_COOLDOWN_SECS").unwrap_or(60)),
+        }
+    }
+}
+
+#[derive(Default)]
+struct HostCircuit {
+    /// `true` for failures, oldest first.
+    outcomes: VecDeque<bool>,
+    open_until: Option<Instant>,
+    /// A trial request is in flight after the cool-down.
+    probing: bool,
+}
+
+/// Whether an upstream result says anything about the host's health: only
+/// connection problems, timeouts, 429s and 5xx count against it.
+fn host_failed<T>(result: &Result<T, PluginError>) -> Option<bool> {
+    match result {
+        Ok(_) => Some(false),
+        Err(PluginError::Network(err)) => Some(match err.status() {
+            Some(status) => status.is_server_error() || status.as_u16() == 429,
+            None => true,
+        }),
+        Err(_) => None,
+    }
+}
+
+/// Per-host circuit breakers for upstream model hosts. When most recent
+/// requests to a host fail, further requests fail fast with
+/// [`PluginError::CircuitOpen`] until a cool-down has passed; then a single
+/// trial request decides whether the circuit closes again.
+///
+/// Tuned with `GOOSE_PLUGIN_CIRCUIT_MIN_REQUESTS`,
+/// `GOOSE_PLUGIN_CIRCUIT_FAILURE_RATIO` and `GOOSE_PLUGIN_CIRCUIT_COOLDOWN_SECS`.
+#[derive(Clone)]
+pub struct CircuitBreakers {
+    config: BreakerConfig,
+    hosts: Arc<Mutex<HashMap<String, HostCircuit>>>,
+}
+
+impl Default for CircuitBreakers {
+    fn default() -> Self {
+        Self::new(BreakerConfig::from_env())
+    }
+}
+
+/// Releases a trial slot that never reported an outcome, e.g. because the
+/// request was cancelled.
+struct Attempt<'a> {
+    breakers: &'a CircuitBreakers,
+    host: &'a str,
+    probe: bool,
+}
+
+impl Drop for Attempt<'_> {
+    fn drop(&mut self) {
+        if self.probe {
+            let mut hosts = self.breakers.lock();
+            if let Some(circuit) = hosts.get_mut(self.host) {
+                circuit.probing = false;
+            }
+        }
+    }
+}
+
+impl CircuitBreakers {
+    fn new(config: BreakerConfig) -> Self {
+        Self {
+            config,
+            hosts: Arc::default(),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, HostCircuit>> {
+        self.hosts.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Runs `work` against `host` unless its circuit is open, and records
+    /// the outcome.
+    pub async fn run<T, F>(&self, host: &str, work: F) -> Result<T, PluginError>
+    where
+        F: Future<Output = Result<T, PluginError>>,
+    {
+        let mut attempt = self.admit(host)?;
+        let result = work.await;
+        if let Some(failed) = host_failed(&result) {
+            attempt.probe = false;
+            self.record(host, failed);
+        }
+        result
+    }
+
+    fn admit<'a>(&'a self, host: &'a str) -> Result<Attempt<'a>, PluginError> {
+        let mut hosts = self.lock();
+        let circuit = hosts.entry(host.to_string()).or_default();
+        let Some(open_until) = circuit.open_until else {
+            return Ok(Attempt {
+                breakers: self,
+                host,
+                probe: false,
+            });
+        };
+        let now = Instant::now();
+        if now < open_until || circuit.probing {
+            let remaining = open_until.saturating_duration_since(now);
+            return Err(PluginError::CircuitOpen {
+                host: host.to_string(),
+                retry_after_secs: remaining.as_secs().max(1),
+            });
+        }
+        circuit.probing = true;
+        Ok(Attempt {
+            breakers: self,
+            host,
+            probe: true,
+        })
+    }
+
+    fn record(&self, host: &str, failed: bool) {
+        let mut hosts = self.lock();
+        let circuit = hosts.entry(host.to_string()).or_default();
+        if circuit.probing {
+            circuit.probing = false;
+            if failed {
+                circuit.open_until = Some(Instant::now() + self.config.cooldown);
+                tracing::warn!("upstream {} still failing; circuit stays open", host);
+            } else {
+                circuit.open_until = None;
+                circuit.outcomes.clear();
+                tracing::info!("upstream {} recovered; circuit closed", host);
+            }
+            return;
+        }
+        if circuit.open_until.is_some() {
+            // Finished after the circuit opened; it was already counted.
+            return;
+        }
+
+        circuit.outcomes.push_back(failed);
+        if circuit.outcomes.len() > WINDOW {
+            circuit.outcomes.pop_front();
+        }
+        let failures = circuit.outcomes.iter().filter(|failed| **failed).count();
+        let total = circuit.outcomes.len();
+        if total >= self.config.min_requests
+            && failures as f64 >= self.config.failure_ratio * total as f64
+            && failures > 0
+        {
+            tracing::warn!(
+                "upstream {} failed {} of the last {} requests; opening circuit for {:?}",
+                host,
+                failures,
+                total,
+                self.config.cooldown
+            );
+            circuit.open_until = Some(Instant::now() + self.config.cooldown);
+            circuit.outcomes.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opens_after_failures_and_closes_after_a_good_trial() {
+        let breakers = CircuitBreakers::new(BreakerConfig {
+            min_requests: 3,
+            failure_ratio: 0.5,
+            cooldown: Duration::from_secs(60),
+        });
+        let host = "huggingface.co";
+        breakers.record(host, false);
+        breakers.record(host, true);
+        assert!(breakers.admit(host).is_ok());
+        breakers.record(host, true);
+        assert!(matches!(
+            breakers.admit(host),
+            Err(PluginError::CircuitOpen { .. })
+        ));
+        assert!(breakers.admit("mirror.example").is_ok());
+
+        // Cool-down over: one trial at a time.
+        breakers.lock().get_mut(host).unwrap().open_until = Some(Instant::now());
+        let mut trial = breakers.admit(host).unwrap();
+        assert!(breakers.admit(host).is_err());
+        trial.probe = false;
+        drop(trial);
+        breakers.record(host, false);
+        assert!(breakers.admit(host).is_ok());
+    }
+}
@@ -0,0 +1,328 @@
+//! Installable plugins listed by a remote index, a JSON document such as:
+//!
+//! ```json
+//! {"plugins": [{
+//!     "id": "piper",
+//!     "name": "Piper",
+//!     "description": "Local text to speech",
+//!     "version": "1.2.0",
+//!     "type": "executable",
+//!     "url": "https://plugins.example.com/piper/1.2.0/piper-linux-x86_64",
+//!     "sha256": "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08",
+//!     "signature_url": "https://plugins.example.com/piper/1.2.0/piper-linux-x86_64.minisig",
+//!     "args": ["--stdio"],
+//!     "capabilities": ["service_start", "service_stop"]
+//! }]}
+//! ```
+//!
+//! Installing a plugin downloads it next to the manifests in
+//! [`super::discovery::manifest_dir`], checks its checksum and writes a
+//! manifest for it, so it is registered again on the next start. The
+//! signature, when listed, is stored next to the download for
+//! [`super::signing`] to check.
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::fs;
+use utoipa::ToSchema;
+
+use super::discovery::{self, PluginManifest};
+use super::offline::OfflineMode;
+use super::{PluginCapability, PluginError};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CatalogPluginKind {
+    /// Started as in [`super::external`].
+    Executable,
+    /// Shared library loaded as in [`super::dynamic`].
+    Library,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CatalogEntry {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub version: String,
+    #[serde(rename = "type")]
+    pub kind: CatalogPluginKind,
+    /// Where the executable or library is downloaded from.
+    pub url: String,
+    /// Hex SHA-256 of the download.
+    pub sha256: String,
+    /// Where the download's minisign signature is fetched from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature_url: Option<String>,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Replace what the plugin reports, as in a manifest.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub capabilities: Option<Vec<PluginCapability>>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct PluginCatalog {
+    pub plugins: Vec<CatalogEntry>,
+}
+
+impl PluginCatalog {
+    pub fn get(&self, plugin_id: &str) -> Option<&CatalogEntry> {
+        self.plugins.iter().find(|entry| entry.id == plugin_id)
+    }
+}
+
+/// What an installed plugin's manifest holds.
+#[derive(Serialize)]
+struct InstalledManifest<'a> {
+    id: &'a str,
+    #[serde(rename = "type")]
+    kind: CatalogPluginKind,
+    binary: String,
+    args: &'a [String],
+    name: &'a str,
+    description: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    capabilities: Option<&'a [PluginCapability]>,
+}
+
+pub struct CatalogClient {
+    index_url: Option<String>,
+    manifest_dir: PathBuf,
+    client: reqwest::Client,
+}
+
+impl CatalogClient {
+    pub fn new(index_url: Option<String>, manifest_dir: PathBuf) -> Self {
+        Self {
+            index_url,
+            manifest_dir,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// The index at `GOOSE_PLUGIN_INDEX_URL`, installing into the manifest
+    /// directory. Without the variable there is no catalog.
+    pub fn from_env() -> Self {
+        let index_url = std::env::var("GOOSE_PLUGIN_INDEX_URL")
+            .ok()
+            .filter(|url| !url.is_empty());
+        Self::new(index_url, discovery::manifest_dir())
+    }
+
+    pub async fn fetch(&self, offline: &OfflineMode) -> Result<PluginCatalog, PluginError> {
+        let index_url = self.index_url.as_deref().ok_or_else(|| {
+            PluginError::NotFound(
+                "plugin index; set GOOSE_PLUGIN_INDEX_URL to browse installable plugins"
+                    .to_string(),
+            )
+        })?;
+        offline.ensure_online("fetching the plugin index")?;
+        let catalog = self
+            .client
+            .get(index_url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(catalog)
+    }
+
+    /// Downloads the plugin, checks it against the index's checksum and
+    /// writes its manifest. The manifest is returned for registration.
+    pub async fn install(
+        &self,
+        entry: &CatalogEntry,
+        offline: &OfflineMode,
+    ) -> Result<PluginManifest, PluginError> {
+        if !is_safe_name(&entry.id) {
+            return Err(PluginError::InvalidRequest(format!(
+                "plugin id {:?} cannot name a file",
+                entry.id
+            )));
+        }
+        offline.ensure_online("installing a plugin")?;
+        let manifest_path = self.manifest_dir.join(format!("{}.toml", entry.id));
+        if fs::try_exists(&manifest_path).await? {
+            return Err(PluginError::AlreadyRegistered(entry.id.clone()));
+        }
+
+        let bytes = self
+            .client
+            .get(&entry.url)
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+        let sha256 = format!("{:x}", Sha256::digest(&bytes));
+        if !sha256.eq_ignore_ascii_case(entry.sha256.trim()) {
+            return Err(PluginError::VerificationFailed(format!(
+                "{} has checksum {}, the index lists {}",
+                entry.url, sha256, entry.sha256
+            )));
+        }
+        let signature = match &entry.signature_url {
+            Some(url) => Some(
+                self.client
+                    .get(url)
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .bytes()
+                    .await?,
+            ),
+            None => None,
+        };
+
+        let file_name = entry
+            .url
+            .split(['?', '#'])
+            .next()
+            .and_then(|url| url.rsplit('/').next())
+            .filter(|name| is_safe_name(name))
+            .unwrap_or(&entry.id);
+        let binary = format!("{}/{}", entry.id, file_name);
+        let binary_path = self.manifest_dir.join(&entry.id).join(file_name);
+        fs::create_dir_all(self.manifest_dir.join(&entry.id)).await?;
+        let tmp = binary_path.with_extension("download");
+        fs::write(&tmp, &bytes).await?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&tmp, std::fs::Permissions::from_mode(0o755)).await?;
+        }
+        fs::rename(&tmp, &binary_path).await?;
+        if let Some(signature) = signature {
+            let signature_path = self
+                .manifest_dir
+                .join(&entry.id)
+                .join(format!("{}.minisig", file_name));
+            fs::write(signature_path, signature).await?;
+        }
+
+        let text = toml::to_string(&InstalledManifest {
+            id: &entry.id,
+            kind: entry.kind,
+            binary,
+            args: &entry.args,
+            name: &entry.name,
+            description: &entry.description,
+            capabilities: entry.capabilities.as_deref(),
+        })
+        .map_err(|err| PluginError::Internal(err.to_string()))?;
+        let mut manifest = PluginManifest::parse(&text, &self.manifest_dir)
+            .map_err(|err| PluginError::Internal(err.to_string()))?;
+        fs::write(&manifest_path, text).await?;
+        manifest.path = Some(manifest_path);
+        tracing::info!("installed plugin {} {}", entry.id, entry.version);
+        Ok(manifest)
+    }
+
+    /// Removes what [`install`](Self::install) wrote for a plugin.
+    pub async fn uninstall(&self, plugin_id: &str) -> Result<(), PluginError> {
+        if !is_safe_name(plugin_id) {
+            return Ok(());
+        }
+        let manifest_path = self.manifest_dir.join(format!("{}.toml", plugin_id));
+        match fs::remove_file(&manifest_path).await {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => return Err(err.into()),
+            _ => {}
+        }
+        match fs::remove_dir_all(self.manifest_dir.join(plugin_id)).await {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn is_safe_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::get;
+    use axum::{Json, Router};
+    use serde_json::json;
+
+    const ARTIFACT: &[u8] = b"#!/bin/sh\nexec cat\n";
+
+    async fn serve_index(sha256: String) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let url = format!("{}/files/echo-plugin", base);
+        let app = Router::new()
+            .route(
+                "/index.json",
+                get(move || async move {
+                    Json(json!({"plugins": [{
+                        "id": "echo",
+                        "name": "Echo",
+                        "version": "0.1.0",
+                        "type": "executable",
+                        "url": url,
+                        "sha256": sha256,
+                        "capabilities": ["custom_operations"]
+                    }]}))
+                }),
+            )
+            .route("/files/echo-plugin", get(|| async { ARTIFACT }));
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        format!("{}/index.json", base)
+    }
+
+    #[tokio::test]
+    async fn installs_verified_plugins() {
+        let dir = tempfile::tempdir().unwrap();
+        let offline = OfflineMode::default();
+        let sha256 = format!("{:x}", Sha256::digest(ARTIFACT));
+        let client = CatalogClient::new(Some(serve_index(sha256).await), dir.path().to_path_buf());
+
+        let catalog = client.fetch(&offline).await.unwrap();
+        let entry = catalog.get("echo").unwrap();
+        let manifest = client.install(entry, &offline).await.unwrap();
+        assert_eq!(manifest.id, "echo");
+        assert_eq!(manifest.binary.as_deref(), Some("echo/echo-plugin"));
+        assert_eq!(
+            manifest.capabilities,
+            Some(vec![PluginCapability::CustomOperations])
+        );
+        assert_eq!(
+            std::fs::read(dir.path().join("echo").join("echo-plugin")).unwrap(),
+            ARTIFACT
+        );
+        let discovered = discovery::load(dir.path()).unwrap();
+        assert!(discovered.iter().any(|manifest| manifest.id == "echo"));
+        assert!(matches!(
+            client.install(entry, &offline).await,
+            Err(PluginError::AlreadyRegistered(_))
+        ));
+
+        client.uninstall("echo").await.unwrap();
+        assert!(!dir.path().join("echo.toml").exists());
+        assert!(!dir.path().join("echo").exists());
+
+        let tampered = CatalogClient::new(
+            Some(serve_index("00".repeat(32)).await),
+            dir.path().to_path_buf(),
+        );
+        let catalog = tampered.fetch(&offline).await.unwrap();
+        assert!(matches!(
+            tampered
+                .install(catalog.get("echo").unwrap(), &offline)
+                .await,
+            Err(PluginError::VerificationFailed(_))
+        ));
+        assert!(!dir.path().join("echo.toml").exists());
+    }
+}
@@ -0,0 +1,260 @@
+//! Stable codes for plugin errors, so clients can branch on what went wrong
+//! without matching messages.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use utoipa::ToSchema;
+
+use super::diagnostics::CrashKind;
+use super::quota::QuotaLimit;
+use super::PluginError;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PluginErrorCode {
+    UnsupportedOperation,
+    InvalidRequest,
+    NotReady,
+    NotFound,
+    PluginNotFound,
+    /// The model does not exist upstream.
+    ModelNotFound,
+    /// The model's license has to be accepted on its hub page before it
+    /// can be downloaded.
+    GatedModel,
+    PathNotAllowed,
+    ServiceAlreadyRunning,
+    ServiceNotRunning,
+    InstanceNotFound,
+    /// A running service uses the model the request would remove.
+    ModelInUse,
+    /// No binary was given or configured, or it does not exist.
+    BinaryMissing,
+    ProcessStartFailed,
+    /// The service exited because its port is taken.
+    PortInUse,
+    MissingSharedLibrary,
+    OutOfMemory,
+    CorruptModel,
+    /// The service exited for a reason not recognized from its output.
+    ProcessCrashed,
+    /// The service started but was not ready in time, and was stopped.
+    ServiceNotReady,
+    ResourceLimit,
+    SmokeTestFailed,
+    VerificationFailed,
+    /// The downloaded file does not have the expected SHA-256.
+    ChecksumMismatch,
+    DiskFull,
+    /// The destination lacks the room a download needs, checked before it
+    /// started.
+    InsufficientDiskSpace,
+    IoError,
+    NetworkError,
+    RemoteError,
+    CircuitOpen,
+    Offline,
+    Cancelled,
+    QueueFull,
+    ConsoleDisabled,
+    /// The credential was not granted the capability the route needs.
+    PermissionDenied,
+    AlreadyRegistered,
+    PluginDisabled,
+    DependencyError,
+    IncompatibleApiVersion,
+    QuotaExceeded,
+    /// The plugin binary is not signed by a trusted key.
+    UntrustedPlugin,
+    Internal,
+}
+
+impl PluginErrorCode {
+    /// Whether repeating the same request may succeed without any change.
+    pub fn retryable(self) -> bool {
+        matches!(
+            self,
+            PluginErrorCode::NotReady
+                | PluginErrorCode::NetworkError
+                | PluginErrorCode::RemoteError
+                | PluginErrorCode::CircuitOpen
+                | PluginErrorCode::Cancelled
+                | PluginErrorCode::QueueFull
+        )
+    }
+}
+
+impl PluginError {
+    pub fn code(&self) -> PluginErrorCode {
+        match self {
+            PluginError::UnsupportedOperation => PluginErrorCode::UnsupportedOperation,
+            PluginError::InvalidRequest(_) => PluginErrorCode::InvalidRequest,
+            PluginError::NotReady(_) => PluginErrorCode::NotReady,
+            PluginError::NotFound(_) => PluginErrorCode::NotFound,
+            PluginError::PathNotAllowed(_) => PluginErrorCode::PathNotAllowed,
+            PluginError::ProcessAlreadyRunning(_) => PluginErrorCode::ServiceAlreadyRunning,
+            PluginError::ProcessNotRunning(_) => PluginErrorCode::ServiceNotRunning,
+            PluginError::InstanceNotFound(_) => PluginErrorCode::InstanceNotFound,
+            PluginError::ModelInUse(_) => PluginErrorCode::ModelInUse,
+            PluginError::Io(err)
+                if matches!(
+                    err.kind(),
+                    std::io::ErrorKind::StorageFull | std::io::ErrorKind::QuotaExceeded
+                ) =>
+            {
+                PluginErrorCode::DiskFull
+            }
+            PluginError::Io(_) => PluginErrorCode::IoError,
+            PluginError::Network(err) if err.status() == Some(reqwest::StatusCode::NOT_FOUND) => {
+                PluginErrorCode::ModelNotFound
+            }
+            PluginError::Network(_) => PluginErrorCode::NetworkError,
+            PluginError::BinaryMissing(_) => PluginErrorCode::BinaryMissing,
+            PluginError::ProcessStart(_) => PluginErrorCode::ProcessStartFailed,
+            PluginError::ProcessCrashed(report) => match report.kind {
+                CrashKind::PortInUse => PluginErrorCode::PortInUse,
+                CrashKind::MissingSharedLibrary => PluginErrorCode::MissingSharedLibrary,
+                CrashKind::OutOfMemory => PluginErrorCode::OutOfMemory,
+                CrashKind::CorruptModel => PluginErrorCode::CorruptModel,
+                CrashKind::Unknown => PluginErrorCode::ProcessCrashed,
+            },
+            PluginError::ServiceNotReady { .. } => PluginErrorCode::ServiceNotReady,
+            PluginError::ResourceLimit(_) => PluginErrorCode::ResourceLimit,
+            PluginError::SmokeTestFailed(_) => PluginErrorCode::SmokeTestFailed,
+            PluginError::VerificationFailed(_) => PluginErrorCode::VerificationFailed,
+            PluginError::ChecksumMismatch { .. } => PluginErrorCode::ChecksumMismatch,
+            PluginError::InsufficientDiskSpace { .. } => PluginErrorCode::InsufficientDiskSpace,
+            PluginError::GatedModel { .. } => PluginErrorCode::GatedModel,
+            PluginError::Remote(_) => PluginErrorCode::RemoteError,
+            PluginError::Cancelled => PluginErrorCode::Cancelled,
+            PluginError::Offline(_) => PluginErrorCode::Offline,
+            PluginError::CircuitOpen { .. } => PluginErrorCode::CircuitOpen,
+            PluginError::AlreadyRegistered(_) => PluginErrorCode::AlreadyRegistered,
+            PluginError::Disabled(_) => PluginErrorCode::PluginDisabled,
+            PluginError::Dependency(_) => PluginErrorCode::DependencyError,
+            PluginError::Incompatible(_) => PluginErrorCode::IncompatibleApiVersion,
+            PluginError::QuotaExceeded { .. } => PluginErrorCode::QuotaExceeded,
+            PluginError::Untrusted(_) => PluginErrorCode::UntrustedPlugin,
+            PluginError::Internal(_) => PluginErrorCode::Internal,
+        }
+    }
+
+    /// Like [`PluginErrorCode::retryable`], but also retryable once other
+    /// calls finish when a plugin is at its limit of concurrent operations.
+    pub fn retryable(&self) -> bool {
+        match self {
+            PluginError::QuotaExceeded { limit, .. } => *limit == QuotaLimit::ConcurrentOperations,
+            _ => self.code().retryable(),
+        }
+    }
+
+    /// Seconds to wait before retrying, when the error says.
+    pub fn retry_after_secs(&self) -> Option<u64> {
+        match self {
+            PluginError::CircuitOpen {
+                retry_after_secs, ..
+            } => Some(*retry_after_secs),
+            _ => None,
+        }
+    }
+
+    /// Context beyond the message, shaped per code. Crash reports and
+    /// compatibility reports have fields of their own in error responses.
+    pub fn details(&self) -> Option<Value> {
+        match self {
+            PluginError::ProcessAlreadyRunning(task_type)
+            | PluginError::ProcessNotRunning(task_type) => Some(json!({ "task_type": task_type })),
+            PluginError::InstanceNotFound(instance_id) => {
+                Some(json!({ "instance_id": instance_id }))
+            }
+            PluginError::ModelInUse(saved_path) => Some(json!({ "saved_path": saved_path })),
+            PluginError::Network(err) => err
+                .status()
+                .map(|status| json!({ "status": status.as_u16() })),
+            PluginError::CircuitOpen { host, .. } => Some(json!({ "host": host })),
+            PluginError::ServiceNotReady {
+                waited_secs,
+                stderr_tail,
+                ..
+            } => Some(json!({ "waited_secs": waited_secs, "stderr_tail": stderr_tail })),
+            PluginError::ChecksumMismatch {
+                filename,
+                expected,
+                actual,
+            } => Some(json!({ "filename": filename, "expected": expected, "actual": actual })),
+            PluginError::InsufficientDiskSpace {
+                path,
+                required,
+                available,
+            } => Some(json!({ "path": path, "required": required, "available": available })),
+            PluginError::GatedModel { model_id, repo_url } => Some(json!({
+                "model_id": model_id,
+                "repo_url": repo_url,
+                "requires_license_acceptance": true,
+            })),
+            PluginError::AlreadyRegistered(plugin_id) | PluginError::Disabled(plugin_id) => {
+                Some(json!({ "plugin_id": plugin_id }))
+            }
+            PluginError::QuotaExceeded {
+                plugin_id, limit, ..
+            } => Some(json!({ "plugin_id": plugin_id, "limit": limit })),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugins::diagnostics::CrashReport;
+
+    #[test]
+    fn codes_follow_error_causes() {
+        let report = CrashReport {
+            command: "llmserver".to_string(),
+            exit_code: Some(1),
+            signal: None,
+            kind: CrashKind::PortInUse,
+            stderr_tail: Vec::new(),
+        };
+        assert_eq!(
+            PluginError::ProcessCrashed(Box::new(report)).code(),
+            PluginErrorCode::PortInUse
+        );
+        let full = std::io::Error::from(std::io::ErrorKind::StorageFull);
+        assert_eq!(PluginError::Io(full).code(), PluginErrorCode::DiskFull);
+
+        let open = PluginError::CircuitOpen {
+            host: "huggingface.co".to_string(),
+            retry_after_secs: 30,
+        };
+        assert!(open.code().retryable());
+        assert_eq!(open.retry_after_secs(), Some(30));
+        assert_eq!(open.details().unwrap()["host"], "huggingface.co");
+
+        let mismatch = PluginError::ChecksumMismatch {
+            filename: "model.gguf".to_string(),
+            expected: "ab".repeat(32),
+            actual: "cd".repeat(32),
+        };
+        assert_eq!(mismatch.code(), PluginErrorCode::ChecksumMismatch);
+        assert!(!mismatch.retryable());
+        assert_eq!(mismatch.details().unwrap()["expected"], "ab".repeat(32));
+
+        let gated = PluginError::GatedModel {
+            model_id: "meta-llama/Llama-3.1-8B".to_string(),
+            repo_url: "https://huggingface.co/meta-llama/Llama-3.1-8B".to_string(),
+        };
+        assert_eq!(gated.code(), PluginErrorCode::GatedModel);
+        assert!(!gated.retryable());
+        assert_eq!(
+            gated.details().unwrap()["requires_license_acceptance"],
+            true
+        );
+        assert_eq!(
+            serde_json::to_value(PluginErrorCode::BinaryMissing).unwrap(),
+            "binary_missing"
+        );
+        assert!(!PluginErrorCode::BinaryMissing.retryable());
+    }
+}
@@ -0,0 +1,103 @@
+//! Versions of the [`ServerPlugin`](super::ServerPlugin) API. Plugins report
+//! the version they were built against in [`PluginMetadata::api_version`];
+//! the server only registers plugins whose version it still supports.
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::{PluginError, PluginMetadata};
+
+/// Version of the plugin API this server implements.
+pub const PLUGIN_API_VERSION: u32 = 1;
+
+/// Oldest plugin API version this server still accepts.
+pub const MIN_PLUGIN_API_VERSION: u32 = 1;
+
+/// Version assumed for plugins that do not report one, which predate
+/// versioning.
+pub fn default_api_version() -> u32 {
+    1
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+pub struct PluginCompatibility {
+    pub plugin_id: String,
+    /// Plugin API version the plugin was built against.
+    pub api_version: u32,
+    pub min_supported_version: u32,
+    pub max_supported_version: u32,
+    pub compatible: bool,
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+impl PluginCompatibility {
+    pub fn check(metadata: &PluginMetadata) -> Self {
+        let version = metadata.api_version;
+        let reason = if version < MIN_PLUGIN_API_VERSION {
+            Some(format!(
+                "plugin {} targets plugin API version {}, older than the oldest supported version {}",
+                metadata.id, version, MIN_PLUGIN_API_VERSION
+            ))
+        } else if version > PLUGIN_API_VERSION {
+            Some(format!(
+                "plugin {} targets plugin API version {}, newer than this server's version {}",
+                metadata.id, version, PLUGIN_API_VERSION
+            ))
+        } else {
+            None
+        };
+        Self {
+            plugin_id: metadata.id.clone(),
+            api_version: version,
+            min_supported_version: MIN_PLUGIN_API_VERSION,
+            max_supported_version: PLUGIN_API_VERSION,
+            compatible: reason.is_none(),
+            reason,
+        }
+    }
+
+    /// Fails with [`PluginError::Incompatible`] for incompatible plugins.
+    pub fn into_result(self) -> Result<Self, PluginError> {
+        if self.compatible {
+            Ok(self)
+        } else {
+            Err(PluginError::Incompatible(Box::new(self)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_unsupported_versions() {
+        let mut metadata = PluginMetadata {
+            id: "tts".to_string(),
+            name: "tts".to_string(),
+            description: String::new(),
+            capabilities: Vec::new(),
+            enabled: true,
+            dependencies: Vec::new(),
+            api_version: PLUGIN_API_VERSION,
+            subscriptions: Vec::new(),
+            signature: None,
+        };
+        assert!(PluginCompatibility::check(&metadata).into_result().is_ok());
+
+        metadata.api_version = PLUGIN_API_VERSION + 1;
+        let Err(PluginError::Incompatible(report)) =
+            PluginCompatibility::check(&metadata).into_result()
+        else {
+            panic!("newer plugin API version accepted");
+        };
+        assert!(!report.compatible);
+        assert!(report.reason.unwrap().contains("newer"));
+
+        let unversioned: PluginMetadata =
+            serde_json::from_str(r#"{"id":"old","name":"old","description":"","capabilities":[]}"#)
+                .unwrap();
+        assert_eq!(unversioned.api_version, default_api_version());
+    }
+}
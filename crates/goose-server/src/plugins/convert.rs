@@ -0,0 +1,249 @@
+//! Conversion of downloaded models to GGUF, for repositories that publish
+//! only safetensors weights. The conversion itself is left to a configured
+//! converter such as llama.cpp's `convert_hf_to_gguf.py`, which is called as
+//! `<converter> <model dir> --outfile <file> [--outtype <type>]`.
+
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::Command;
+use tokio_util::sync::CancellationToken;
+use utoipa::ToSchema;
+
+use super::progress::DownloadProgress;
+use super::{PluginError, PluginTaskType};
+
+/// Lines of tool output kept to explain a failed run.
+const OUTPUT_TAIL_LINES: usize = 20;
+
+/// How often the size of the file being written is reported.
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ConvertModelRequest {
+    pub task_type: PluginTaskType,
+    /// Directory of the model relative to the task type's model directory,
+    /// such as the `org/model` a snapshot download saved.
+    pub source_dir: String,
+    /// GGUF file written, relative to the model directory. Defaults to
+    /// `<source_dir>.gguf`, or `<source_dir>-<outtype>.gguf` with an
+    /// `outtype`.
+    #[serde(default)]
+    pub filename: Option<String>,
+    /// Weight type of the GGUF file, such as `f16`, `bf16` or `q8_0`; the
+    /// converter picks one when unset.
+    #[serde(default)]
+    pub outtype: Option<String>,
+    #[serde(default)]
+    pub destination_dir: Option<String>,
+    /// Stops the converter, e.g. when the conversion's job is cancelled.
+    #[serde(skip)]
+    pub cancel: CancellationToken,
+    /// Receives the bytes of the GGUF file written so far.
+    #[serde(skip)]
+    pub progress: DownloadProgress,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ConvertModelResponse {
+    pub saved_path: String,
+    pub bytes: u64,
+    pub sha256: String,
+}
+
+/// The file a conversion writes when the request names none.
+pub fn default_filename(source_dir: &str, outtype: Option<&str>) -> String {
+    let source_dir = source_dir.trim_end_matches('/');
+    match outtype {
+        Some(outtype) => format!("{}-{}.gguf", source_dir, outtype),
+        None => format!("{}.gguf", source_dir),
+    }
+}
+
+/// Command running `converter`; Python scripts are run with `python3`, so
+/// they need not be executable.
+fn converter_command(converter: &Path) -> Command {
+    if converter
+        .extension()
+        .is_some_and(|extension| extension == "py")
+    {
+        let mut command = Command::new("python3");
+        command.arg(converter);
+        command
+    } else {
+        Command::new(converter)
+    }
+}
+
+/// Runs `converter` on the model in `source`, writing `outfile`, and reports
+/// the size of `outfile` as it grows. The converter is killed when `cancel`
+/// fires.
+pub async fn run(
+    converter: &Path,
+    source: &Path,
+    outfile: &Path,
+    outtype: Option<&str>,
+    cancel: &CancellationToken,
+    progress: &DownloadProgress,
+) -> Result<(), PluginError> {
+    let mut command = converter_command(converter);
+    command.arg(source).arg("--outfile").arg(outfile);
+    if let Some(outtype) = outtype {
+        command.args(["--outtype", outtype]);
+    }
+    run_writing(command, "converter", outfile, cancel, progress).await
+}
+
+/// Runs `command`, a `tool` writing `outfile`, reporting the size of
+/// `outfile` as it grows and failing with the tail of its output when it
+/// exits unsuccessfully.
+pub(super) async fn run_writing(
+    mut command: Command,
+    tool: &str,
+    outfile: &Path,
+    cancel: &CancellationToken,
+    progress: &DownloadProgress,
+) -> Result<(), PluginError> {
+    let program = command.as_std().get_program().to_owned();
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|err| {
+            PluginError::ProcessStart(format!(
+                "cannot run {} {}: {}",
+                tool,
+                program.to_string_lossy(),
+                err
+            ))
+        })?;
+
+    let mut tail = VecDeque::with_capacity(OUTPUT_TAIL_LINES);
+    let mut stdout = child.stdout.take().map(|out| BufReader::new(out).lines());
+    let mut stderr = child.stderr.take().map(|err| BufReader::new(err).lines());
+    let mut ticks = tokio::time::interval(PROGRESS_INTERVAL);
+    progress.start(None);
+    let status = loop {
+        let line = tokio::select! {
+            status = child.wait(), if stdout.is_none() && stderr.is_none() => break status?,
+            line = next_line(&mut stdout), if stdout.is_some() => line,
+            line = next_line(&mut stderr), if stderr.is_some() => line,
+            _ = ticks.tick() => {
+                if let Ok(metadata) = tokio::fs::metadata(outfile).await {
+                    progress.advance(metadata.len());
+                }
+                continue;
+            }
+            _ = cancel.cancelled() => return Err(PluginError::Cancelled),
+        };
+        if let Some(line) = line {
+            if tail.len() == OUTPUT_TAIL_LINES {
+                tail.pop_front();
+            }
+            tail.push_back(line);
+        }
+    };
+    if !status.success() {
+        let output: Vec<_> = tail.into_iter().collect();
+        return Err(PluginError::Internal(format!(
+            "{} exited with {}: {}",
+            tool,
+            status,
+            output.join("\n")
+        )));
+    }
+    if let Ok(metadata) = tokio::fs::metadata(outfile).await {
+        progress.advance(metadata.len());
+    }
+    progress.finish();
+    Ok(())
+}
+
+/// The next line of a tool's output stream, closing it at its end.
+async fn next_line<R>(lines: &mut Option<tokio::io::Lines<BufReader<R>>>) -> Option<String>
+where
+    R: tokio::io::AsyncRead + Unpin,
+{
+    let stream = lines.as_mut()?;
+    match stream.next_line().await {
+        Ok(Some(line)) => Some(line),
+        _ => {
+            *lines = None;
+            None
+        }
+    }
+}
+
+/// The converter configured through `GOOSE_PLUGIN_LLM_CONVERTER`.
+pub fn converter_from_env() -> Option<PathBuf> {
+    std::env::var_os("GOOSE_PLUGIN_LLM_CONVERTER")
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_output_after_the_source() {
+        assert_eq!(default_filename("org/model/", None), "org/model.gguf");
+        assert_eq!(
+            default_filename("org/model", Some("q8_0")),
+            "org/model-q8_0.gguf"
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn runs_the_converter() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let converter = dir.path().join("convert");
+        std::fs::write(
+            &converter,
+            "#!/bin/sh\n\
+             [ \"$2\" = --outfile ] || exit 2\n\
+             [ \"$5\" = f16 ] || { echo \"bad outtype $5\" >&2; exit 3; }\n\
+             printf GGUF > \"$3\"\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(&converter, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let outfile = dir.path().join("model.gguf");
+        let cancel = CancellationToken::new();
+        let progress = DownloadProgress::new();
+
+        run(
+            &converter,
+            dir.path(),
+            &outfile,
+            Some("f16"),
+            &cancel,
+            &progress,
+        )
+        .await
+        .unwrap();
+        assert_eq!(std::fs::read(&outfile).unwrap(), b"GGUF");
+        assert!(progress.subscribe().borrow().done);
+
+        let failed = run(
+            &converter,
+            dir.path(),
+            &outfile,
+            Some("q8_0"),
+            &cancel,
+            &progress,
+        )
+        .await;
+        assert!(
+            matches!(failed, Err(PluginError::Internal(message)) if message.contains("bad outtype q8_0"))
+        );
+    }
+}
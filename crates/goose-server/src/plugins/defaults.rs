@@ -0,0 +1,203 @@
+//! The plugin task-level routes dispatch to for each task type, so clients
+//! can start a text or TTS service without naming a plugin, and the plugins
+//! to fall back on per capability. Set through the API and persisted as JSON
+//! like [`PluginConfigStore`].
+//!
+//! [`PluginConfigStore`]: super::settings::PluginConfigStore
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use goose::config::paths::Paths;
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+use utoipa::ToSchema;
+
+use super::{PluginCapability, PluginError, PluginMetadata, PluginTaskType};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema, PartialEq)]
+pub struct PluginDefaults {
+    /// Plugin id by task type.
+    #[serde(default)]
+    #[schema(value_type = Object)]
+    pub defaults: HashMap<PluginTaskType, String>,
+    /// Plugin ids by capability, tried in order when one fails the
+    /// operation.
+    #[serde(default)]
+    #[schema(value_type = Object)]
+    pub fallbacks: HashMap<PluginCapability, Vec<String>>,
+}
+
+impl PluginDefaults {
+    /// `plugin_id` followed by the plugins after it in the fallback chain for
+    /// `capability`. Plugins outside the chain have no fallbacks.
+    pub fn fallback_chain(&self, capability: &PluginCapability, plugin_id: &str) -> Vec<String> {
+        let fallbacks = self
+            .fallbacks
+            .get(capability)
+            .and_then(|chain| {
+                let position = chain.iter().position(|id| id == plugin_id)?;
+                Some(&chain[position + 1..])
+            })
+            .unwrap_or_default();
+        std::iter::once(plugin_id.to_string())
+            .chain(fallbacks.iter().filter(|id| *id != plugin_id).cloned())
+            .collect()
+    }
+
+    /// Every plugin id the defaults and fallback chains name.
+    pub fn plugin_ids(&self) -> impl Iterator<Item = &String> {
+        self.defaults
+            .values()
+            .chain(self.fallbacks.values().flatten())
+    }
+}
+
+/// Default plugins by task type. The default store keeps them in memory only.
+#[derive(Debug, Default)]
+pub struct PluginDefaultsStore {
+    path: Option<PathBuf>,
+    defaults: PluginDefaults,
+}
+
+impl PluginDefaultsStore {
+    /// `GOOSE_PLUGIN_DEFAULTS_PATH`, or `plugin_defaults.json` in goose's
+    /// config directory.
+    pub fn default_path() -> PathBuf {
+        std::env::var_os("GOOSE_PLUGIN_DEFAULTS_PATH")
+            .filter(|path| !path.is_empty())
+            .map(PathBuf::from)
+            .unwrap_or_else(|| Paths::config_dir().join("plugin_defaults.json"))
+    }
+
+    /// Reads the store at `path`, which need not exist yet.
+    pub async fn load(path: &Path) -> Result<Self, PluginError> {
+        let defaults = match fs::read(path).await {
+            Ok(bytes) => serde_json::from_slice(&bytes).map_err(|err| {
+                PluginError::Internal(format!("invalid {}: {}", path.display(), err))
+            })?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => PluginDefaults::default(),
+            Err(err) => return Err(err.into()),
+        };
+        Ok(Self {
+            path: Some(path.to_path_buf()),
+            defaults,
+        })
+    }
+
+    pub fn get(&self) -> &PluginDefaults {
+        &self.defaults
+    }
+
+    /// Replaces the defaults and writes the store atomically.
+    pub async fn set(&mut self, defaults: PluginDefaults) -> Result<(), PluginError> {
+        self.defaults = defaults;
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let json = serde_json::to_vec_pretty(&self.defaults)
+            .map_err(|err| PluginError::Internal(err.to_string()))?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, json).await?;
+        fs::rename(&tmp, path).await?;
+        Ok(())
+    }
+
+    /// The plugin for `task_type`: the one set as its default, otherwise the
+    /// first enabled plugin of `plugins` that can start services.
+    pub fn resolve(
+        &self,
+        task_type: &PluginTaskType,
+        plugins: &[PluginMetadata],
+    ) -> Option<String> {
+        if let Some(plugin_id) = self.defaults.defaults.get(task_type) {
+            return Some(plugin_id.clone());
+        }
+        plugins
+            .iter()
+            .find(|plugin| {
+                plugin.enabled
+                    && plugin
+                        .capabilities
+                        .contains(&PluginCapability::ServiceStart)
+            })
+            .map(|plugin| plugin.id.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plugin(id: &str, capabilities: Vec<PluginCapability>) -> PluginMetadata {
+        PluginMetadata {
+            id: id.to_string(),
+            name: id.to_string(),
+            description: String::new(),
+            capabilities,
+            enabled: true,
+            dependencies: Vec::new(),
+            api_version: crate::plugins::compat::PLUGIN_API_VERSION,
+            subscriptions: Vec::new(),
+            signature: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn persists_and_resolves_defaults() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("plugin_defaults.json");
+        let plugins = [
+            plugin("whisper", vec![PluginCapability::ModelDownload]),
+            plugin("llmserver-rs", vec![PluginCapability::ServiceStart]),
+            plugin("piper", vec![PluginCapability::ServiceStart]),
+        ];
+
+        let mut store = PluginDefaultsStore::load(&path).await.unwrap();
+        assert_eq!(
+            store.resolve(&PluginTaskType::TTS, &plugins).as_deref(),
+            Some("llmserver-rs")
+        );
+
+        let defaults = PluginDefaults {
+            defaults: HashMap::from([(PluginTaskType::TTS, "piper".to_string())]),
+            fallbacks: HashMap::from([(
+                PluginCapability::ModelDownload,
+                vec![
+                    "llmserver-rs".to_string(),
+                    "whisper".to_string(),
+                    "piper".to_string(),
+                ],
+            )]),
+        };
+        store.set(defaults.clone()).await.unwrap();
+
+        let reloaded = PluginDefaultsStore::load(&path).await.unwrap();
+        assert_eq!(reloaded.get(), &defaults);
+        assert_eq!(
+            reloaded.resolve(&PluginTaskType::TTS, &plugins).as_deref(),
+            Some("piper")
+        );
+        assert_eq!(
+            reloaded.resolve(&PluginTaskType::TEXT, &plugins).as_deref(),
+            Some("llmserver-rs")
+        );
+
+        let defaults = reloaded.get();
+        assert_eq!(
+            defaults.fallback_chain(&PluginCapability::ModelDownload, "whisper"),
+            ["whisper", "piper"]
+        );
+        assert_eq!(
+            defaults.fallback_chain(&PluginCapability::ModelDownload, "ollama"),
+            ["ollama"]
+        );
+        assert_eq!(
+            defaults.fallback_chain(&PluginCapability::ServiceStart, "llmserver-rs"),
+            ["llmserver-rs"]
+        );
+    }
+}
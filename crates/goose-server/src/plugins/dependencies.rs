@@ -0,0 +1,95 @@
+//! Order in which plugins are registered and started, given the plugins each
+//! declares in [`PluginMetadata::dependencies`].
+
+use std::collections::HashSet;
+
+use super::{PluginError, PluginMetadata};
+
+/// Indices of `plugins` ordered so every plugin comes after the plugins it
+/// depends on, keeping the given order otherwise. Each dependency must be one
+/// of `plugins` or in `available`, the ids of plugins already registered.
+pub fn start_order(
+    plugins: &[PluginMetadata],
+    available: &HashSet<&str>,
+) -> Result<Vec<usize>, PluginError> {
+    let ids: HashSet<&str> = plugins.iter().map(|plugin| plugin.id.as_str()).collect();
+    for plugin in plugins {
+        if let Some(missing) = plugin
+            .dependencies
+            .iter()
+            .find(|dep| !ids.contains(dep.as_str()) && !available.contains(dep.as_str()))
+        {
+            return Err(PluginError::Dependency(format!(
+                "plugin {} depends on {}, which is not registered",
+                plugin.id, missing
+            )));
+        }
+    }
+
+    let mut order = Vec::with_capacity(plugins.len());
+    let mut placed = vec![false; plugins.len()];
+    let mut started: HashSet<&str> = HashSet::new();
+    while order.len() < plugins.len() {
+        let ready = (0..plugins.len()).find(|&index| {
+            !placed[index]
+                && plugins[index]
+                    .dependencies
+                    .iter()
+                    .all(|dep| started.contains(dep.as_str()) || available.contains(dep.as_str()))
+        });
+        let Some(index) = ready else {
+            let cycle: Vec<&str> = (0..plugins.len())
+                .filter(|&index| !placed[index])
+                .map(|index| plugins[index].id.as_str())
+                .collect();
+            return Err(PluginError::Dependency(format!(
+                "dependency cycle among {}",
+                cycle.join(", ")
+            )));
+        };
+        placed[index] = true;
+        started.insert(&plugins[index].id);
+        order.push(index);
+    }
+    Ok(order)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plugin(id: &str, dependencies: &[&str]) -> PluginMetadata {
+        PluginMetadata {
+            id: id.to_string(),
+            name: id.to_string(),
+            description: String::new(),
+            capabilities: Vec::new(),
+            enabled: true,
+            dependencies: dependencies.iter().map(|dep| dep.to_string()).collect(),
+            api_version: crate::plugins::compat::PLUGIN_API_VERSION,
+            subscriptions: Vec::new(),
+            signature: None,
+        }
+    }
+
+    #[test]
+    fn orders_dependencies_first() {
+        let plugins = [
+            plugin("tts", &["text"]),
+            plugin("whisper", &[]),
+            plugin("text", &["llmserver-rs"]),
+        ];
+        let available = HashSet::from(["llmserver-rs"]);
+        assert_eq!(start_order(&plugins, &available).unwrap(), vec![1, 2, 0]);
+
+        assert!(matches!(
+            start_order(&plugins, &HashSet::new()),
+            Err(PluginError::Dependency(message)) if message.contains("llmserver-rs")
+        ));
+        let cycle = [plugin("a", &["b"]), plugin("b", &["a"]), plugin("c", &[])];
+        assert!(matches!(
+            start_order(&cycle, &HashSet::new()),
+            Err(PluginError::Dependency(message)) if message == "dependency cycle among a, b"
+        ));
+    }
+}
@@ -0,0 +1,168 @@
+use std::process::ExitStatus;
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Number of trailing stderr lines attached to a crash report.
+pub const CRASH_TAIL_LINES: usize = 50;
+
+/// Heuristic classification of why a managed process exited.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CrashKind {
+    MissingSharedLibrary,
+    OutOfMemory,
+    PortInUse,
+    CorruptModel,
+    Unknown,
+}
+
+impl std::fmt::Display for CrashKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            CrashKind::MissingSharedLibrary => "missing shared library",
+            CrashKind::OutOfMemory => "out of memory",
+            CrashKind::PortInUse => "port already in use",
+            CrashKind::CorruptModel => "corrupt or unsupported model file",
+            CrashKind::Unknown => "unknown cause",
+        };
+        f.write_str(text)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CrashReport {
+    pub command: String,
+    pub exit_code: Option<i32>,
+    pub signal: Option<i32>,
+    pub kind: CrashKind,
+    pub stderr_tail: Vec<String>,
+}
+
+impl CrashReport {
+    pub fn new(command: String, status: ExitStatus, stderr_tail: Vec<String>) -> Self {
+        let signal = exit_signal(&status);
+        let kind = classify(&stderr_tail, signal);
+        Self {
+            command,
+            exit_code: status.code(),
+            signal,
+            kind,
+            stderr_tail,
+        }
+    }
+}
+
+impl std::fmt::Display for CrashReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.exit_code, self.signal) {
+            (Some(code), _) => write!(f, "process exited with code {} ({})", code, self.kind),
+            (None, Some(signal)) => {
+                write!(f, "process killed by signal {} ({})", signal, self.kind)
+            }
+            (None, None) => write!(f, "process exited ({})", self.kind),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn exit_signal(status: &ExitStatus) -> Option<i32> {
+    use std::os::unix::process::ExitStatusExt;
+    status.signal()
+}
+
+#[cfg(not(unix))]
+fn exit_signal(_status: &ExitStatus) -> Option<i32> {
+    None
+}
+
+const MISSING_LIBRARY_PATTERNS: &[&str] = &[
+    "error while loading shared libraries",
+    "cannot open shared object file",
+    "library not loaded",
+    "dll was not found",
+    "dll not found",
+];
+
+const OUT_OF_MEMORY_PATTERNS: &[&str] = &[
+    "out of memory",
+    "cannot allocate memory",
+    "failed to allocate",
+    "std::bad_alloc",
+    "memory allocation of",
+];
+
+const PORT_IN_USE_PATTERNS: &[&str] = &[
+    "address already in use",
+    "eaddrinuse",
+    "os error 98",
+    "only one usage of each socket address",
+];
+
+const CORRUPT_MODEL_PATTERNS: &[&str] = &[
+    "invalid magic",
+    "failed to load model",
+    "error loading model",
+    "unexpected end of file",
+    "invalid gguf",
+    "not within the file bounds",
+    "corrupt",
+];
+
+/// Classifies a crash from the captured stderr tail, falling back to the exit
+/// signal (SIGKILL usually means the kernel OOM killer stepped in).
+pub fn classify(stderr: &[String], signal: Option<i32>) -> CrashKind {
+    let text = stderr.join("\n").to_ascii_lowercase();
+    let matches = |patterns: &[&str]| patterns.iter().any(|pattern| text.contains(pattern));
+
+    if matches(MISSING_LIBRARY_PATTERNS) {
+        CrashKind::MissingSharedLibrary
+    } else if matches(PORT_IN_USE_PATTERNS) {
+        CrashKind::PortInUse
+    } else if matches(OUT_OF_MEMORY_PATTERNS) {
+        CrashKind::OutOfMemory
+    } else if matches(CORRUPT_MODEL_PATTERNS) {
+        CrashKind::CorruptModel
+    } else if signal == Some(9) {
+        CrashKind::OutOfMemory
+    } else {
+        CrashKind::Unknown
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn classifies_common_failures() {
+        assert_eq!(
+            classify(
+                &lines("llmserver: error while loading shared libraries: libcudart.so.12"),
+                None
+            ),
+            CrashKind::MissingSharedLibrary
+        );
+        assert_eq!(
+            classify(
+                &lines("bind failed: Address already in use (os error 98)"),
+                None
+            ),
+            CrashKind::PortInUse
+        );
+        assert_eq!(
+            classify(&lines("ggml: failed to allocate 4096 MiB buffer"), None),
+            CrashKind::OutOfMemory
+        );
+        assert_eq!(
+            classify(&lines("gguf_init: invalid magic characters 'lmth'"), None),
+            CrashKind::CorruptModel
+        );
+        assert_eq!(classify(&[], Some(9)), CrashKind::OutOfMemory);
+        assert_eq!(classify(&lines("bye"), None), CrashKind::Unknown);
+    }
+}
@@ -0,0 +1,345 @@
+//! Plugins described by TOML manifests in a `plugins.d` directory, read at
+//! startup. Each `*.toml` file describes one plugin:
+//!
+//! ```toml
+//! id = "whisper"
+//! type = "executable"
+//! binary = "bin/whisper"
+//! args = ["--stdio"]
+//! capabilities = ["service_start", "service_stop"]
+//! ```
+//!
+//! - `builtin`: `binary` names a plugin compiled into the server, defaulting
+//!   to the id.
+//! - `library`: `binary` is a shared library as in [`super::dynamic`].
+//! - `executable`: `binary` is started as in [`super::external`], with `args`
+//!   and `environment`.
+//! - `remote`: `url` and `auth_token` as in [`super::http`].
+//! - `grpc`: `url` and `auth_token` as in [`super::grpc`].
+//!
+//! Relative binary paths are resolved against the manifest's directory. The
+//! manifest's `id` and, when given, `name`, `description`, `capabilities`,
+//! `enabled`, `dependencies` and `subscriptions` replace what the plugin
+//! reports, and a `[quota]` table limits it as in [`super::quota`]. Plugins
+//! are registered after the plugins they depend on. Libraries and
+//! executables must be signed when the server checks signatures, as in
+//! [`super::signing`].
+//! Built-ins without a manifest are registered with their defaults. Reloading
+//! a plugin reads its manifest again.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use goose::config::paths::Paths;
+use serde::Deserialize;
+
+use super::events::EventBus;
+use super::external::ExternalPluginConfig;
+use super::grpc::GrpcPluginConfig;
+use super::http::HttpPluginConfig;
+use super::llmserver::LlmServerPlugin;
+use super::offline::OfflineMode;
+use super::quota::PluginQuota;
+use super::signing::TrustRoot;
+use super::{dynamic, external, grpc, http, PluginCapability, PluginMetadata, ServerPlugin};
+
+/// Plugins compiled into the server, by name.
+pub const BUILTINS: &[&str] = &[LLMSERVER];
+
+const LLMSERVER: &str = "llmserver-rs";
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PluginKind {
+    Builtin,
+    Library,
+    Executable,
+    Remote,
+    Grpc,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct PluginManifest {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub kind: PluginKind,
+    #[serde(default)]
+    pub binary: Option<String>,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub environment: HashMap<String, String>,
+    #[serde(default)]
+    pub url: Option<String>,
+    #[serde(default)]
+    pub auth_token: Option<String>,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub capabilities: Option<Vec<PluginCapability>>,
+    #[serde(default)]
+    pub enabled: Option<bool>,
+    #[serde(default)]
+    pub dependencies: Option<Vec<String>>,
+    #[serde(default)]
+    pub subscriptions: Option<Vec<String>>,
+    #[serde(default)]
+    pub quota: Option<PluginQuota>,
+    /// Directory relative binary paths are resolved against.
+    #[serde(skip)]
+    pub dir: PathBuf,
+    /// File the manifest was read from.
+    #[serde(skip)]
+    pub path: Option<PathBuf>,
+}
+
+impl PluginManifest {
+    pub fn parse(text: &str, dir: &Path) -> anyhow::Result<Self> {
+        let mut manifest: PluginManifest = toml::from_str(text)?;
+        manifest.dir = dir.to_path_buf();
+        Ok(manifest)
+    }
+
+    /// Manifest of a plugin registered without one, such as a library from
+    /// `GOOSE_PLUGIN_DIR`.
+    pub fn new(id: &str, kind: PluginKind) -> Self {
+        Self {
+            id: id.to_string(),
+            kind,
+            binary: None,
+            args: Vec::new(),
+            environment: HashMap::new(),
+            url: None,
+            auth_token: None,
+            name: None,
+            description: None,
+            capabilities: None,
+            enabled: None,
+            dependencies: None,
+            subscriptions: None,
+            quota: None,
+            dir: PathBuf::new(),
+            path: None,
+        }
+    }
+
+    fn read(path: &Path) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let mut manifest = Self::parse(&text, dir)?;
+        manifest.path = Some(path.to_path_buf());
+        Ok(manifest)
+    }
+
+    /// The manifest as currently on disk, or itself if it has no file.
+    pub fn reread(&self) -> anyhow::Result<Self> {
+        match &self.path {
+            Some(path) => Self::read(path),
+            None => Ok(self.clone()),
+        }
+    }
+
+    /// The built-in this manifest configures, if any.
+    pub fn builtin_name(&self) -> Option<&str> {
+        (self.kind == PluginKind::Builtin).then(|| self.binary.as_deref().unwrap_or(&self.id))
+    }
+
+    fn binary(&self) -> anyhow::Result<String> {
+        let binary = self
+            .binary
+            .as_deref()
+            .ok_or_else(|| anyhow::anyhow!("plugin {} has no binary", self.id))?;
+        // Bare command names are looked up on PATH.
+        let path = Path::new(binary);
+        if path.is_relative() && path.components().count() > 1 {
+            return Ok(self.dir.join(path).to_string_lossy().into_owned());
+        }
+        Ok(binary.to_string())
+    }
+
+    /// Applies the manifest's overrides to the metadata a plugin reported.
+    pub fn metadata(&self, reported: PluginMetadata) -> PluginMetadata {
+        PluginMetadata {
+            id: self.id.clone(),
+            name: self.name.clone().unwrap_or(reported.name),
+            description: self.description.clone().unwrap_or(reported.description),
+            capabilities: self.capabilities.clone().unwrap_or(reported.capabilities),
+            enabled: self.enabled.unwrap_or(reported.enabled),
+            dependencies: self.dependencies.clone().unwrap_or(reported.dependencies),
+            api_version: reported.api_version,
+            subscriptions: self.subscriptions.clone().unwrap_or(reported.subscriptions),
+            signature: reported.signature,
+        }
+    }
+
+    /// Starts, loads or connects to the plugin.
+    pub async fn instantiate(
+        &self,
+        events: &EventBus,
+        offline: &OfflineMode,
+        trust: &TrustRoot,
+    ) -> anyhow::Result<Arc<dyn ServerPlugin>> {
+        Ok(match self.kind {
+            PluginKind::Builtin => match self.builtin_name() {
+                Some(LLMSERVER) => Arc::new(
+                    LlmServerPlugin::bootstrap(
+                        self.metadata(LlmServerPlugin::default_metadata()),
+                        events.clone(),
+                        offline.clone(),
+                    )
+                    .await?,
+                ),
+                name => anyhow::bail!("unknown built-in plugin {}", name.unwrap_or_default()),
+            },
+            PluginKind::Library => {
+                let binary = self.binary()?;
+                let signature = trust.verify(Path::new(&binary)).await?;
+                let plugin = dynamic::load(Path::new(&binary))?.with_signature(signature);
+                let metadata = self.metadata(plugin.metadata());
+                Arc::new(plugin.with_metadata(metadata))
+            }
+            PluginKind::Executable => {
+                let binary = self.binary()?;
+                let signature = trust.verify_command(&binary).await?;
+                let plugin = external::spawn(&ExternalPluginConfig {
+                    command: binary,
+                    args: self.args.clone(),
+                    environment: self.environment.clone(),
+                })
+                .await?
+                .with_signature(signature);
+                let metadata = self.metadata(plugin.metadata());
+                Arc::new(plugin.with_metadata(metadata))
+            }
+            PluginKind::Remote => {
+                let config = HttpPluginConfig {
+                    url: self
+                        .url
+                        .clone()
+                        .ok_or_else(|| anyhow::anyhow!("plugin {} has no url", self.id))?,
+                    auth_token: self.auth_token.clone(),
+                };
+                let plugin = http::connect(&config, None).await?;
+                let metadata = self.metadata(plugin.metadata());
+                Arc::new(plugin.with_metadata(metadata))
+            }
+            PluginKind::Grpc => {
+                let config = GrpcPluginConfig {
+                    url: self
+                        .url
+                        .clone()
+                        .ok_or_else(|| anyhow::anyhow!("plugin {} has no url", self.id))?,
+                    auth_token: self.auth_token.clone(),
+                };
+                let plugin = grpc::connect(&config, None).await?;
+                let metadata = self.metadata(plugin.metadata());
+                Arc::new(plugin.with_metadata(metadata))
+            }
+        })
+    }
+}
+
+/// `GOOSE_PLUGINS_D`, or `plugins.d` in goose's config directory.
+pub fn manifest_dir() -> PathBuf {
+    std::env::var_os("GOOSE_PLUGINS_D")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| Paths::config_dir().join("plugins.d"))
+}
+
+/// Manifests in `dir` sorted by file name, followed by the defaults of
+/// built-ins none of them configure. A missing directory holds no manifests;
+/// manifests that cannot be parsed are skipped with a warning.
+pub fn load(dir: &Path) -> std::io::Result<Vec<PluginManifest>> {
+    let mut paths = match std::fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+            .collect(),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+        Err(err) => return Err(err),
+    };
+    paths.sort();
+
+    let mut manifests = Vec::new();
+    for path in paths {
+        match PluginManifest::read(&path) {
+            Ok(manifest) => manifests.push(manifest),
+            Err(err) => tracing::warn!("skipping plugin manifest {}: {}", path.display(), err),
+        }
+    }
+    for name in BUILTINS {
+        if !manifests
+            .iter()
+            .any(|manifest| manifest.builtin_name() == Some(name))
+        {
+            manifests.push(PluginManifest::new(name, PluginKind::Builtin));
+        }
+    }
+    Ok(manifests)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_manifests_and_adds_missing_builtins() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("whisper.toml"),
+            r#"
+                id = "whisper"
+                type = "executable"
+                binary = "bin/whisper"
+                capabilities = ["service_start"]
+
+                [quota]
+                max_processes = 2
+            "#,
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("broken.toml"), "id = ").unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "").unwrap();
+
+        let manifests = load(dir.path()).unwrap();
+        assert_eq!(manifests.len(), 2);
+        let whisper = &manifests[0];
+        assert_eq!(whisper.kind, PluginKind::Executable);
+        assert_eq!(whisper.quota.and_then(|quota| quota.max_processes), Some(2));
+        assert_eq!(
+            whisper.binary().unwrap(),
+            dir.path().join("bin/whisper").to_string_lossy()
+        );
+        let metadata = whisper.metadata(PluginMetadata {
+            id: "reported".to_string(),
+            name: "Whisper".to_string(),
+            description: String::new(),
+            capabilities: Vec::new(),
+            enabled: true,
+            dependencies: Vec::new(),
+            api_version: crate::plugins::compat::PLUGIN_API_VERSION,
+            subscriptions: Vec::new(),
+            signature: None,
+        });
+        assert_eq!(metadata.id, "whisper");
+        assert_eq!(metadata.name, "Whisper");
+        assert_eq!(metadata.capabilities, vec![PluginCapability::ServiceStart]);
+        assert_eq!(manifests[1].builtin_name(), Some(LLMSERVER));
+
+        std::fs::write(
+            dir.path().join("whisper.toml"),
+            "id = \"whisper\"\ntype = \"remote\"\nurl = \"http://localhost:9000\"",
+        )
+        .unwrap();
+        let reread = whisper.reread().unwrap();
+        assert_eq!(reread.kind, PluginKind::Remote);
+        assert_eq!(reread.path, whisper.path);
+
+        assert_eq!(load(&dir.path().join("missing")).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,182 @@
+//! Plugins compiled as shared libraries and loaded at startup.
+//!
+//! A plugin library exports [`ENTRY_SYMBOL`], a function returning a pointer
+//! to a static [`PluginVTable`]. Everything crossing the boundary is a
+//! NUL-terminated UTF-8 JSON string:
+//!
+//! - `metadata()` returns the plugin's [`PluginMetadata`].
+//! - `call(method, params)` runs one [`ServerPlugin`] method, named and encoded
+//!   as in [`super::forward`], and returns `{"ok": <response>}` or
+//!   `{"error": {"kind": ..., "message": ...}}`.
+//! - Strings returned by the plugin are handed back to its `free_string`.
+//!
+//! `call` may block and is always invoked off the async runtime. Libraries
+//! stay loaded for the lifetime of the process.
+//!
+//! [`ServerPlugin`]: super::ServerPlugin
+
+use std::ffi::{c_char, CStr, CString};
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+use serde_json::Value;
+
+use super::forward::{read_config_schema, CallResult, ForwardingPlugin, PluginTransport};
+use super::{PluginError, PluginMetadata};
+
+/// Version of [`PluginVTable`] this server understands.
+pub const ABI_VERSION: u32 = 1;
+
+/// Symbol every plugin library exports, with the signature
+/// `extern "C" fn() -> *const PluginVTable`.
+pub const ENTRY_SYMBOL: &str = "goose_plugin_entry";
+
+#[repr(C)]
+pub struct PluginVTable {
+    pub abi_version: u32,
+    pub metadata: extern "C" fn() -> *mut c_char,
+    pub call: extern "C" fn(method: *const c_char, params: *const c_char) -> *mut c_char,
+    pub free_string: extern "C" fn(value: *mut c_char),
+}
+
+/// Calls into a loaded library.
+#[derive(Clone, Copy)]
+pub struct LibraryTransport {
+    vtable: &'static PluginVTable,
+}
+
+impl LibraryTransport {
+    /// Takes ownership of a string returned by the plugin.
+    fn take_string(&self, value: *mut c_char) -> Result<String, PluginError> {
+        if value.is_null() {
+            return Err(PluginError::Internal(
+                "plugin returned a null string".to_string(),
+            ));
+        }
+        // SAFETY: the ABI requires returned strings to be NUL-terminated and
+        // valid until passed to free_string, which happens right after the copy.
+        let copied = unsafe { CStr::from_ptr(value) }
+            .to_str()
+            .map(str::to_string)
+            .map_err(|err| PluginError::Internal(err.to_string()));
+        (self.vtable.free_string)(value);
+        copied
+    }
+
+    fn metadata(&self) -> Result<PluginMetadata, PluginError> {
+        let json = self.take_string((self.vtable.metadata)())?;
+        serde_json::from_str(&json)
+            .map_err(|err| PluginError::Internal(format!("invalid plugin metadata: {}", err)))
+    }
+
+    fn call_blocking(&self, method: &str, params: &Value) -> Result<Value, PluginError> {
+        let internal = |err: std::ffi::NulError| PluginError::Internal(err.to_string());
+        let method = CString::new(method).map_err(internal)?;
+        let params = CString::new(params.to_string()).map_err(internal)?;
+        let result = self.take_string((self.vtable.call)(method.as_ptr(), params.as_ptr()))?;
+        serde_json::from_str::<CallResult>(&result)
+            .map_err(|err| PluginError::Internal(format!("invalid plugin response: {}", err)))?
+            .into_result()
+    }
+}
+
+#[async_trait]
+impl PluginTransport for LibraryTransport {
+    async fn call(&self, method: &str, params: Value) -> Result<Value, PluginError> {
+        let transport = *self;
+        let method = method.to_string();
+        tokio::task::spawn_blocking(move || transport.call_blocking(&method, &params))
+            .await
+            .map_err(|err| PluginError::Internal(err.to_string()))?
+    }
+}
+
+pub type DynamicPlugin = ForwardingPlugin<LibraryTransport>;
+
+/// Loads one plugin library.
+pub fn load(path: &Path) -> anyhow::Result<DynamicPlugin> {
+    let vtable = open(path)?;
+    if vtable.abi_version != ABI_VERSION {
+        anyhow::bail!(
+            "{} implements plugin ABI {}, expected {}",
+            path.display(),
+            vtable.abi_version,
+            ABI_VERSION
+        );
+    }
+    let transport = LibraryTransport { vtable };
+    let metadata = transport.metadata()?;
+    let config_schema = read_config_schema(transport.call_blocking("config_schema", &Value::Null));
+    Ok(ForwardingPlugin::new(metadata, transport).with_config_schema(config_schema))
+}
+
+/// Shared libraries in `dir` with this platform's extension, sorted by name.
+pub fn libraries(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let extension = std::env::consts::DLL_EXTENSION;
+    let mut paths: Vec<_> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == extension))
+        .collect();
+    paths.sort();
+    Ok(paths)
+}
+
+/// Directory from `GOOSE_PLUGIN_DIR`, if set.
+pub fn plugin_dir() -> Option<PathBuf> {
+    std::env::var_os("GOOSE_PLUGIN_DIR")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+}
+
+#[cfg(unix)]
+fn open(path: &Path) -> anyhow::Result<&'static PluginVTable> {
+    use std::os::unix::ffi::OsStrExt;
+
+    fn last_error() -> String {
+        // SAFETY: dlerror returns null or a NUL-terminated message that stays
+        // valid until the next dl* call on this thread.
+        unsafe {
+            let message = libc::dlerror();
+            if message.is_null() {
+                "unknown error".to_string()
+            } else {
+                CStr::from_ptr(message).to_string_lossy().into_owned()
+            }
+        }
+    }
+
+    let filename = CString::new(path.as_os_str().as_bytes())?;
+    // SAFETY: loading a library runs its initializers; plugin directories
+    // are trusted like the server binary itself. The handle is never
+    // closed, so code and statics stay valid for the process lifetime.
+    let handle = unsafe { libc::dlopen(filename.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL) };
+    if handle.is_null() {
+        anyhow::bail!("cannot load {}: {}", path.display(), last_error());
+    }
+    let symbol = CString::new(ENTRY_SYMBOL)?;
+    // SAFETY: handle came from a successful dlopen.
+    let entry = unsafe { libc::dlsym(handle, symbol.as_ptr()) };
+    if entry.is_null() {
+        anyhow::bail!(
+            "{} does not export {}: {}",
+            path.display(),
+            ENTRY_SYMBOL,
+            last_error()
+        );
+    }
+    // SAFETY: the ABI defines the entry point's signature, and the vtable it
+    // returns is static data of a library that is never unloaded.
+    let vtable = unsafe {
+        let entry: extern "C" fn() -> *const PluginVTable = std::mem::transmute(entry);
+        entry().as_ref()
+    };
+    vtable.ok_or_else(|| anyhow::anyhow!("{} returned no plugin vtable", path.display()))
+}
+
+#[cfg(not(unix))]
+fn open(path: &Path) -> anyhow::Result<&'static PluginVTable> {
+    anyhow::bail!(
+        "cannot load {}: plugin libraries are only supported on unix",
+        path.display()
+    )
+}
@@ -0,0 +1,340 @@
+//! Opt-in encryption of downloaded model files at rest.
+//!
+//! Encrypted files start with a header holding a random salt, from which a
+//! per-file AES-256-GCM key is derived off the master key. The plaintext
+//! follows in sealed chunks whose nonce carries the chunk index and a flag on
+//! the last chunk, so reordered or truncated files fail to decrypt. Services
+//! are launched from a decrypted copy in a private runtime directory that is
+//! removed once no process needs it.
+
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use goose::config::Config;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::hkdf::{Salt, HKDF_SHA256};
+use ring::rand::{SecureRandom, SystemRandom};
+
+use super::PluginError;
+
+const MAGIC: &[u8; 8] = b"GOOSEENC";
+const VERSION: u8 = 1;
+const SALT_LEN: usize = 32;
+const HEADER_LEN: usize = MAGIC.len() + 1 + SALT_LEN;
+const CHUNK_LEN: usize = 1024 * 1024;
+const TAG_LEN: usize = 16;
+
+/// Secret holding the hex-encoded 32-byte master key.
+pub const KEY_SECRET: &str = "GOOSE_PLUGIN_LLM_MODEL_KEY";
+
+/// Prefix of decrypted copies, so stale ones can be told apart from anything
+/// else in the runtime directory.
+const DECRYPTED_PREFIX: &str = ".goose-decrypted-";
+
+/// Encrypts downloads and decrypts models for launch when enabled.
+#[derive(Clone)]
+pub struct ModelEncryption {
+    master_key: Option<Arc<[u8; 32]>>,
+    runtime_dir: PathBuf,
+}
+
+impl ModelEncryption {
+    /// Enabled by `GOOSE_PLUGIN_LLM_ENCRYPT_MODELS`, with the key read from
+    /// the [`KEY_SECRET`] secret. Decrypted copies go to
+    /// `GOOSE_PLUGIN_LLM_RUNTIME_DIR`, by default `<base_dir>/runtime`;
+    /// pointing it at a tmpfs keeps plaintext weights off the disk entirely.
+    pub fn from_env(base_dir: &Path) -> anyhow::Result<Self> {
+        let runtime_dir = std::env::var_os("GOOSE_PLUGIN_LLM_RUNTIME_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| base_dir.join("runtime"));
+        let enabled = std::env::var("GOOSE_PLUGIN_LLM_ENCRYPT_MODELS")
+            .map(|value| matches!(value.trim(), "1" | "true" | "yes"))
+            .unwrap_or(false);
+        if !enabled {
+            return Ok(Self {
+                master_key: None,
+                runtime_dir,
+            });
+        }
+        let hex: String = Config::global().get_secret(KEY_SECRET).map_err(|err| {
+            anyhow::anyhow!("model encryption needs the {} secret: {}", KEY_SECRET, err)
+        })?;
+        let master_key = parse_key(&hex)
+            .ok_or_else(|| anyhow::anyhow!("{} must be 64 hex characters", KEY_SECRET))?;
+        prepare_runtime_dir(&runtime_dir)?;
+        Ok(Self {
+            master_key: Some(Arc::new(master_key)),
+            runtime_dir,
+        })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.master_key.is_some()
+    }
+
+    /// Directory decrypted copies are written to.
+    pub fn runtime_dir(&self) -> &Path {
+        &self.runtime_dir
+    }
+
+    /// Starts encrypting a new file, or `None` when encryption is off.
+    pub fn encryptor(&self) -> Result<Option<Encryptor>, PluginError> {
+        self.master_key.as_deref().map(Encryptor::new).transpose()
+    }
+
+    /// Decrypts `model_path` into the runtime directory if it is encrypted.
+    /// The copy lives as long as the returned handle.
+    pub async fn open(&self, model_path: &str) -> Result<Option<Arc<DecryptedModel>>, PluginError> {
+        let source = PathBuf::from(model_path);
+        let master_key = self.master_key.clone();
+        let runtime_dir = self.runtime_dir.clone();
+        tokio::task::spawn_blocking(move || {
+            if !is_encrypted(&source)? {
+                return Ok(None);
+            }
+            let master_key = master_key.ok_or_else(|| {
+                PluginError::InvalidRequest(format!(
+                    "{} is encrypted but GOOSE_PLUGIN_LLM_ENCRYPT_MODELS is off",
+                    source.display()
+                ))
+            })?;
+            let name = source
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let target = runtime_dir.join(format!(
+                "{}{}-{}",
+                DECRYPTED_PREFIX,
+                uuid::Uuid::new_v4(),
+                name
+            ));
+            let decrypted = DecryptedModel {
+                path: target.clone(),
+            };
+            decrypt_file(&master_key, &source, &target)?;
+            Ok(Some(Arc::new(decrypted)))
+        })
+        .await
+        .map_err(|err| PluginError::Internal(err.to_string()))?
+    }
+}
+
+/// Plaintext copy of an encrypted model, deleted on drop.
+#[derive(Debug)]
+pub struct DecryptedModel {
+    path: PathBuf,
+}
+
+impl DecryptedModel {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for DecryptedModel {
+    fn drop(&mut self) {
+        if let Err(err) = std::fs::remove_file(&self.path) {
+            if err.kind() != std::io::ErrorKind::NotFound {
+                tracing::warn!("failed to remove {}: {}", self.path.display(), err);
+            }
+        }
+    }
+}
+
+/// Turns a plaintext stream into the encrypted file format.
+pub struct Encryptor {
+    key: LessSafeKey,
+    header: Option<Vec<u8>>,
+    buffer: Vec<u8>,
+    index: u64,
+}
+
+impl Encryptor {
+    fn new(master_key: &[u8; 32]) -> Result<Self, PluginError> {
+        let mut salt = [0u8; SALT_LEN];
+        SystemRandom::new()
+            .fill(&mut salt)
+            .map_err(|_| PluginError::Internal("no randomness for encryption".to_string()))?;
+        let mut header = Vec::with_capacity(HEADER_LEN);
+        header.extend_from_slice(MAGIC);
+        header.push(VERSION);
+        header.extend_from_slice(&salt);
+        Ok(Self {
+            key: file_key(master_key, &salt)?,
+            header: Some(header),
+            buffer: Vec::new(),
+            index: 0,
+        })
+    }
+
+    /// Bytes to write for `plaintext`; sealing lags by one chunk so the last
+    /// one can be marked as such.
+    pub fn update(&mut self, plaintext: &[u8]) -> Result<Vec<u8>, PluginError> {
+        let mut out = self.header.take().unwrap_or_default();
+        self.buffer.extend_from_slice(plaintext);
+        while self.buffer.len() > CHUNK_LEN {
+            let rest = self.buffer.split_off(CHUNK_LEN);
+            let chunk = std::mem::replace(&mut self.buffer, rest);
+            out.extend(self.seal(chunk, false)?);
+        }
+        Ok(out)
+    }
+
+    /// Seals whatever is left as the final chunk.
+    pub fn finish(mut self) -> Result<Vec<u8>, PluginError> {
+        let mut out = self.header.take().unwrap_or_default();
+        let chunk = std::mem::take(&mut self.buffer);
+        out.extend(self.seal(chunk, true)?);
+        Ok(out)
+    }
+
+    fn seal(&mut self, mut chunk: Vec<u8>, last: bool) -> Result<Vec<u8>, PluginError> {
+        self.key
+            .seal_in_place_append_tag(nonce(self.index, last), Aad::empty(), &mut chunk)
+            .map_err(|_| PluginError::Internal("failed to encrypt model chunk".to_string()))?;
+        self.index += 1;
+        Ok(chunk)
+    }
+}
+
+fn parse_key(hex: &str) -> Option<[u8; 32]> {
+    let hex = hex.trim();
+    if hex.len() != 64 || !hex.is_ascii() {
+        return None;
+    }
+    let mut key = [0u8; 32];
+    for (byte, pair) in key.iter_mut().zip(hex.as_bytes().chunks(2)) {
+        *byte = u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok()?;
+    }
+    Some(key)
+}
+
+fn file_key(master_key: &[u8; 32], salt: &[u8]) -> Result<LessSafeKey, PluginError> {
+    let failed = |_| PluginError::Internal("failed to derive model key".to_string());
+    let prk = Salt::new(HKDF_SHA256, salt).extract(master_key);
+    let okm = prk
+        .expand(&[b"goose model file"], &AES_256_GCM)
+        .map_err(failed)?;
+    Ok(LessSafeKey::new(UnboundKey::from(okm)))
+}
+
+fn nonce(index: u64, last: bool) -> Nonce {
+    let mut bytes = [0u8; NONCE_LEN];
+    bytes[3] = last as u8;
+    bytes[4..].copy_from_slice(&index.to_be_bytes());
+    Nonce::assume_unique_for_key(bytes)
+}
+
+fn is_encrypted(path: &Path) -> Result<bool, PluginError> {
+    let mut magic = [0u8; MAGIC.len()];
+    // Model paths need not name a local file, e.g. for binaries that fetch
+    // models themselves.
+    let mut file = match std::fs::File::open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(err) => return Err(err.into()),
+    };
+    match file.read_exact(&mut magic) {
+        Ok(()) => Ok(&magic == MAGIC),
+        Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(err) => Err(err.into()),
+    }
+}
+
+fn prepare_runtime_dir(dir: &Path) -> std::io::Result<()> {
+    let mut builder = std::fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    builder.create(dir)?;
+    // Copies left behind by a crash.
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry
+            .file_name()
+            .to_string_lossy()
+            .starts_with(DECRYPTED_PREFIX)
+        {
+            let _ = std::fs::remove_file(entry.path());
+        }
+    }
+    Ok(())
+}
+
+fn decrypt_file(master_key: &[u8; 32], source: &Path, target: &Path) -> Result<(), PluginError> {
+    let mut reader = std::io::BufReader::new(std::fs::File::open(source)?);
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut writer = std::io::BufWriter::new(options.open(target)?);
+    decrypt(master_key, &mut reader, &mut writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+fn decrypt(
+    master_key: &[u8; 32],
+    reader: &mut impl Read,
+    writer: &mut impl Write,
+) -> Result<(), PluginError> {
+    let corrupt =
+        || PluginError::Internal("encrypted model is corrupt or the key is wrong".to_string());
+    let mut header = [0u8; HEADER_LEN];
+    reader.read_exact(&mut header)?;
+    if &header[..MAGIC.len()] != MAGIC || header[MAGIC.len()] != VERSION {
+        return Err(corrupt());
+    }
+    let key = file_key(master_key, &header[MAGIC.len() + 1..])?;
+
+    let mut current = read_record(reader)?;
+    let mut index = 0u64;
+    loop {
+        let next = read_record(reader)?;
+        let last = next.is_empty();
+        let plaintext = key
+            .open_in_place(nonce(index, last), Aad::empty(), &mut current)
+            .map_err(|_| corrupt())?;
+        writer.write_all(plaintext)?;
+        if last {
+            return Ok(());
+        }
+        current = next;
+        index += 1;
+    }
+}
+
+/// Reads one sealed chunk; empty at the end of the file.
+fn read_record(reader: &mut impl Read) -> std::io::Result<Vec<u8>> {
+    let mut record = Vec::with_capacity(CHUNK_LEN + TAG_LEN);
+    reader
+        .take((CHUNK_LEN + TAG_LEN) as u64)
+        .read_to_end(&mut record)?;
+    Ok(record)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_and_detects_truncation() {
+        let master_key = [7u8; 32];
+        let plaintext: Vec<u8> = (0..CHUNK_LEN * 2 + 100).map(|i| i as u8).collect();
+        let mut encryptor = Encryptor::new(&master_key).unwrap();
+        let mut encrypted = Vec::new();
+        for piece in plaintext.chunks(300_000) {
+            encrypted.extend(encryptor.update(piece).unwrap());
+        }
+        encrypted.extend(encryptor.finish().unwrap());
+
+        let mut decrypted = Vec::new();
+        decrypt(&master_key, &mut encrypted.as_slice(), &mut decrypted).unwrap();
+        assert_eq!(decrypted, plaintext);
+
+        let truncated = &encrypted[..HEADER_LEN + CHUNK_LEN + TAG_LEN];
+        assert!(decrypt(&master_key, &mut &truncated[..], &mut Vec::new()).is_err());
+        assert!(decrypt(&[8u8; 32], &mut encrypted.as_slice(), &mut Vec::new()).is_err());
+    }
+}
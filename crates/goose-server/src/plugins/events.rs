@@ -0,0 +1,167 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::broadcast;
+use utoipa::ToSchema;
+
+use super::diagnostics::CrashReport;
+use super::logs::LogStream;
+use super::revisions::ModelUpdate;
+use super::PluginTaskType;
+
+const EVENT_CHANNEL_CAPACITY: usize = 256;
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+#[allow(clippy::enum_variant_names)]
+pub enum PluginEventKind {
+    ServiceUnhealthy {
+        instance_id: String,
+        task_type: PluginTaskType,
+        consecutive_failures: u32,
+        reason: String,
+    },
+    ServiceRecovered {
+        instance_id: String,
+        task_type: PluginTaskType,
+    },
+    ServiceRestarted {
+        instance_id: String,
+        task_type: PluginTaskType,
+        pid: u32,
+        restarts: u32,
+    },
+    ServiceOutput {
+        instance_id: String,
+        task_type: PluginTaskType,
+        stream: LogStream,
+        line: String,
+    },
+    ServiceRestartFailed {
+        instance_id: String,
+        task_type: PluginTaskType,
+        reason: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        crash: Option<CrashReport>,
+    },
+    ModelUpdateAvailable {
+        update: ModelUpdate,
+    },
+    ModelDownloaded {
+        model_id: String,
+        filename: String,
+        saved_path: String,
+        bytes: u64,
+        /// Cluster node the model was stored on, when not this one.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        node: Option<String>,
+    },
+    /// A model file copied into a model directory by hand was indexed.
+    ModelDiscovered {
+        task_type: PluginTaskType,
+        filename: String,
+        saved_path: String,
+        bytes: u64,
+    },
+    /// An indexed model file was deleted outside of goosed.
+    ModelRemoved {
+        model_id: String,
+        task_type: PluginTaskType,
+        filename: String,
+        saved_path: String,
+    },
+    /// A write failed because the disk or the user's quota is full.
+    DiskFull {
+        path: String,
+        message: String,
+    },
+    /// Defined by a plugin for other plugins; `name` is what they subscribe
+    /// to.
+    Custom {
+        name: String,
+        #[serde(default)]
+        #[schema(value_type = Object)]
+        payload: Value,
+    },
+}
+
+impl PluginEventKind {
+    /// The event's `type`, e.g. `model_downloaded`.
+    pub fn event_type(&self) -> String {
+        serde_json::to_value(self)
+            .ok()
+            .and_then(|value| value["type"].as_str().map(str::to_string))
+            .unwrap_or_default()
+    }
+
+    /// Whether a plugin subscribed to `subscription`, an event type or the
+    /// name of a custom event, receives this event.
+    pub fn matches(&self, subscription: &str) -> bool {
+        match self {
+            PluginEventKind::Custom { name, .. } if name == subscription => true,
+            // Output lines are most of the traffic; skip serializing them.
+            PluginEventKind::ServiceOutput { .. } => subscription == "service_output",
+            _ => self.event_type() == subscription,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PluginEvent {
+    pub plugin_id: String,
+    pub timestamp: DateTime<Utc>,
+    pub event: PluginEventKind,
+}
+
+/// Fan-out channel for plugin events. Publishing never blocks; slow subscribers
+/// miss events rather than holding up plugins.
+#[derive(Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<PluginEvent>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+        Self { sender }
+    }
+}
+
+impl EventBus {
+    pub fn publish(&self, plugin_id: &str, event: PluginEventKind) {
+        let event = PluginEvent {
+            plugin_id: plugin_id.to_string(),
+            timestamp: Utc::now(),
+            event,
+        };
+        // An error only means nobody is subscribed right now.
+        let _ = self.sender.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<PluginEvent> {
+        self.sender.subscribe()
+    }
+}
+
+/// Publishes captured output lines of one service instance as events.
+#[derive(Clone)]
+pub struct OutputForwarder {
+    pub events: EventBus,
+    pub plugin_id: String,
+    pub instance_id: String,
+    pub task_type: PluginTaskType,
+}
+
+impl OutputForwarder {
+    pub fn forward(&self, stream: LogStream, line: &str) {
+        self.events.publish(
+            &self.plugin_id,
+            PluginEventKind::ServiceOutput {
+                instance_id: self.instance_id.clone(),
+                task_type: self.task_type.clone(),
+                stream,
+                line: line.to_string(),
+            },
+        );
+    }
+}
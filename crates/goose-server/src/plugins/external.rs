@@ -0,0 +1,283 @@
+//! Plugins running as separate executables, spoken to with JSON-RPC 2.0 over
+//! the child's stdin and stdout, one message per line.
+//!
+//! Right after spawning, the server calls `metadata` (no params), which must
+//! return the plugin's [`PluginMetadata`], and then `config_schema`. All other
+//! methods are the
+//! [`ServerPlugin`] methods as described in [`super::forward`]. Errors should
+//! carry `{"kind": ...}` from [`RemoteError`] in their `data`; "method not
+//! found" errors are read as unsupported operations. Anything the
+//! plugin writes to stderr ends up in the server log.
+//!
+//! [`ServerPlugin`]: super::ServerPlugin
+
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, Command};
+use tokio::sync::{oneshot, Mutex};
+use utoipa::ToSchema;
+
+use super::forward::{read_config_schema, ForwardingPlugin, PluginTransport, RemoteError};
+use super::{PluginError, PluginMetadata};
+
+/// How long a freshly spawned plugin has to answer `metadata` and
+/// `config_schema`.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// JSON-RPC error code for unknown methods.
+const METHOD_NOT_FOUND: i64 = -32601;
+
+/// How to launch an external plugin.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+pub struct ExternalPluginConfig {
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub environment: HashMap<String, String>,
+}
+
+impl ExternalPluginConfig {
+    /// Plugins from `GOOSE_EXTERNAL_PLUGINS`, a JSON array of configs.
+    pub fn from_env() -> anyhow::Result<Vec<ExternalPluginConfig>> {
+        let Ok(raw) = std::env::var("GOOSE_EXTERNAL_PLUGINS") else {
+            return Ok(Vec::new());
+        };
+        serde_json::from_str(&raw)
+            .map_err(|err| anyhow::anyhow!("invalid GOOSE_EXTERNAL_PLUGINS: {}", err))
+    }
+}
+
+#[derive(Deserialize)]
+struct RpcResponse {
+    #[serde(default)]
+    id: Option<u64>,
+    #[serde(default)]
+    result: Option<Value>,
+    #[serde(default)]
+    error: Option<RpcError>,
+}
+
+#[derive(Deserialize)]
+struct RpcError {
+    #[serde(default)]
+    code: i64,
+    #[serde(default)]
+    message: String,
+    #[serde(default)]
+    data: Option<Value>,
+}
+
+impl From<RpcError> for PluginError {
+    fn from(error: RpcError) -> Self {
+        let kind = error
+            .data
+            .as_ref()
+            .and_then(|data| data["kind"].as_str())
+            .unwrap_or(if error.code == METHOD_NOT_FOUND {
+                "unsupported_operation"
+            } else {
+                "internal"
+            })
+            .to_string();
+        RemoteError {
+            kind,
+            message: error.message,
+        }
+        .into()
+    }
+}
+
+type Pending = Arc<std::sync::Mutex<HashMap<u64, oneshot::Sender<Result<Value, PluginError>>>>>;
+
+/// JSON-RPC connection to a plugin process. The process is killed when the
+/// transport is dropped.
+pub struct StdioTransport {
+    stdin: Mutex<ChildStdin>,
+    pending: Pending,
+    next_id: AtomicU64,
+    closed: Arc<AtomicBool>,
+    _child: Child,
+}
+
+impl StdioTransport {
+    fn spawn(config: &ExternalPluginConfig) -> Result<Self, PluginError> {
+        let mut child = Command::new(&config.command)
+            .args(&config.args)
+            .envs(&config.environment)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|err| PluginError::ProcessStart(format!("{}: {}", config.command, err)))?;
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let stderr = child.stderr.take().expect("stderr is piped");
+
+        let pending = Pending::default();
+        let closed = Arc::new(AtomicBool::new(false));
+        let command = config.command.clone();
+        tokio::spawn({
+            let pending = pending.clone();
+            let closed = closed.clone();
+            let command = command.clone();
+            async move {
+                let mut lines = BufReader::new(stdout).lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    let response: RpcResponse = match serde_json::from_str(&line) {
+                        Ok(response) => response,
+                        Err(err) => {
+                            tracing::warn!("plugin {} sent invalid JSON-RPC: {}", command, err);
+                            continue;
+                        }
+                    };
+                    // Messages without an id are notifications, which no
+                    // method uses yet.
+                    let Some(id) = response.id else {
+                        continue;
+                    };
+                    let Some(reply) = pending.lock().expect("pending calls").remove(&id) else {
+                        continue;
+                    };
+                    let result = match (response.error, response.result) {
+                        (Some(error), _) => Err(error.into()),
+                        (None, result) => Ok(result.unwrap_or(Value::Null)),
+                    };
+                    let _ = reply.send(result);
+                }
+                closed.store(true, Ordering::SeqCst);
+                // Dropping the senders fails every call still waiting.
+                pending.lock().expect("pending calls").clear();
+                tracing::warn!("plugin process {} exited", command);
+            }
+        });
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(stderr).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                tracing::info!(target: "goose_server::plugins::external", "{}: {}", command, line);
+            }
+        });
+
+        Ok(Self {
+            stdin: Mutex::new(stdin),
+            pending,
+            next_id: AtomicU64::new(1),
+            closed,
+            _child: child,
+        })
+    }
+}
+
+#[async_trait]
+impl PluginTransport for StdioTransport {
+    async fn call(&self, method: &str, params: Value) -> Result<Value, PluginError> {
+        let exited = || PluginError::NotReady("plugin process exited".to_string());
+        if self.closed.load(Ordering::SeqCst) {
+            return Err(exited());
+        }
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let (reply, response) = oneshot::channel();
+        self.pending
+            .lock()
+            .expect("pending calls")
+            .insert(id, reply);
+
+        let mut line =
+            json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params}).to_string();
+        line.push('\n');
+        let written = {
+            let mut stdin = self.stdin.lock().await;
+            match stdin.write_all(line.as_bytes()).await {
+                Ok(()) => stdin.flush().await,
+                Err(err) => Err(err),
+            }
+        };
+        if written.is_err() {
+            self.pending.lock().expect("pending calls").remove(&id);
+            return Err(exited());
+        }
+        response.await.map_err(|_| exited())?
+    }
+}
+
+pub type ExternalPlugin = ForwardingPlugin<StdioTransport>;
+
+/// Starts the plugin process and asks it for its metadata.
+pub async fn spawn(config: &ExternalPluginConfig) -> Result<ExternalPlugin, PluginError> {
+    let transport = StdioTransport::spawn(config)?;
+    let metadata = tokio::time::timeout(HANDSHAKE_TIMEOUT, transport.call("metadata", Value::Null))
+        .await
+        .map_err(|_| {
+            PluginError::NotReady(format!(
+                "{} did not answer metadata in time",
+                config.command
+            ))
+        })??;
+    let metadata: PluginMetadata = serde_json::from_value(metadata).map_err(|err| {
+        PluginError::Internal(format!("{} sent invalid metadata: {}", config.command, err))
+    })?;
+    let config_schema = tokio::time::timeout(
+        HANDSHAKE_TIMEOUT,
+        transport.call("config_schema", Value::Null),
+    )
+    .await
+    .unwrap_or_else(|_| Err(PluginError::NotReady("no answer".to_string())));
+    Ok(ForwardingPlugin::new(metadata, transport)
+        .with_config_schema(read_config_schema(config_schema)))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::plugins::{ServerPlugin, ServiceSelector, StopServiceRequest};
+
+    #[tokio::test]
+    async fn speaks_json_rpc_over_stdio() {
+        // Answers metadata, has no config_schema, answers one stop_service
+        // call, then an error.
+        let script = r#"
+            read -r _
+            echo '{"jsonrpc":"2.0","id":1,"result":{"id":"sh","name":"sh","description":"","capabilities":[]}}'
+            read -r _
+            echo '{"jsonrpc":"2.0","id":2,"error":{"code":-32601,"message":"no such method"}}'
+            read -r _
+            echo '{"jsonrpc":"2.0","id":3,"result":{"instance_id":"a","task_type":"tts","terminated":true}}'
+            read -r _
+            echo '{"jsonrpc":"2.0","id":4,"error":{"code":-32000,"message":"no models","data":{"kind":"not_found"}}}'
+        "#;
+        let plugin = spawn(&ExternalPluginConfig {
+            command: "/bin/sh".to_string(),
+            args: vec!["-c".to_string(), script.to_string()],
+            environment: HashMap::new(),
+        })
+        .await
+        .unwrap();
+        assert_eq!(plugin.metadata().id, "sh");
+        assert!(plugin.config_schema().is_null());
+
+        let stopped = plugin
+            .stop_service(StopServiceRequest {
+                service: ServiceSelector::instance("a"),
+            })
+            .await
+            .unwrap();
+        assert!(stopped.terminated);
+        assert!(matches!(
+            plugin.list_models().await,
+            Err(PluginError::NotFound(message)) if message == "no models"
+        ));
+        assert!(matches!(
+            plugin.list_nodes().await,
+            Err(PluginError::NotReady(_))
+        ));
+    }
+}
@@ -0,0 +1,204 @@
+//! Plugins tried in turn when one fails an operation, e.g. `llmserver-rs`
+//! then `ollama` for model downloads. Chains are configured per capability
+//! with the default plugins, in [`PluginDefaults::fallbacks`].
+//!
+//! [`PluginDefaults::fallbacks`]: super::defaults::PluginDefaults::fallbacks
+
+use std::future::Future;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::codes::PluginErrorCode;
+use super::{PluginError, ServerPlugin, SharedPluginManager};
+
+/// One plugin's turn in a fallback chain.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq)]
+pub struct FallbackAttempt {
+    pub plugin_id: String,
+    /// Why the plugin failed; absent for the plugin that answered.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<PluginErrorCode>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// The error of the last plugin tried, with every attempt.
+#[derive(Debug)]
+pub struct FallbackError {
+    pub error: PluginError,
+    pub attempts: Vec<FallbackAttempt>,
+}
+
+impl std::fmt::Display for FallbackError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.attempts.len() {
+            0 | 1 => write!(f, "{}", self.error),
+            tried => write!(f, "{} (after trying {} plugins)", self.error, tried),
+        }
+    }
+}
+
+impl From<PluginError> for FallbackError {
+    fn from(error: PluginError) -> Self {
+        Self {
+            error,
+            attempts: Vec::new(),
+        }
+    }
+}
+
+/// Runs `operation` on the plugins of `chain` in order until one succeeds.
+/// Plugins that are not registered or are disabled count as failed attempts,
+/// and a cancelled operation ends the chain. Attempts are only reported when
+/// the chain has fallbacks.
+pub async fn run_chain<T, F, Fut>(
+    plugins: &SharedPluginManager,
+    chain: &[String],
+    mut operation: F,
+) -> Result<(T, Vec<FallbackAttempt>), FallbackError>
+where
+    F: FnMut(Arc<dyn ServerPlugin>) -> Fut,
+    Fut: Future<Output = Result<T, PluginError>>,
+{
+    let mut attempts = Vec::new();
+    let mut last_error = None;
+    for plugin_id in chain {
+        let result = match plugins.active(plugin_id).await {
+            Some(Ok(plugin)) => operation(plugin).await,
+            Some(Err(err)) => Err(err),
+            None => Err(PluginError::NotFound(format!("plugin {}", plugin_id))),
+        };
+        match result {
+            Ok(response) => {
+                attempts.push(FallbackAttempt {
+                    plugin_id: plugin_id.clone(),
+                    code: None,
+                    error: None,
+                });
+                if chain.len() == 1 {
+                    attempts.clear();
+                }
+                return Ok((response, attempts));
+            }
+            Err(err) => {
+                if chain.len() > 1 {
+                    tracing::warn!("plugin {} failed in a fallback chain: {}", plugin_id, err);
+                }
+                attempts.push(FallbackAttempt {
+                    plugin_id: plugin_id.clone(),
+                    code: Some(err.code()),
+                    error: Some(err.to_string()),
+                });
+                let cancelled = matches!(err, PluginError::Cancelled);
+                last_error = Some(err);
+                if cancelled {
+                    break;
+                }
+            }
+        }
+    }
+    if chain.len() == 1 {
+        attempts.clear();
+    }
+    Err(FallbackError {
+        error: last_error
+            .unwrap_or_else(|| PluginError::Internal("fallback chain is empty".to_string())),
+        attempts,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugins::operations::InvokeContext;
+    use crate::plugins::{PluginManager, PluginMetadata};
+    use async_trait::async_trait;
+    use serde_json::Value;
+
+    struct Answer {
+        id: &'static str,
+        result: Result<Value, &'static str>,
+    }
+
+    #[async_trait]
+    impl ServerPlugin for Answer {
+        fn metadata(&self) -> PluginMetadata {
+            PluginMetadata {
+                id: self.id.to_string(),
+                name: self.id.to_string(),
+                description: String::new(),
+                capabilities: Vec::new(),
+                enabled: true,
+                dependencies: Vec::new(),
+                api_version: crate::plugins::compat::PLUGIN_API_VERSION,
+                subscriptions: Vec::new(),
+                signature: None,
+            }
+        }
+
+        async fn invoke(
+            &self,
+            _operation: &str,
+            _payload: Value,
+            _context: InvokeContext,
+        ) -> Result<Value, PluginError> {
+            self.result
+                .clone()
+                .map_err(|message| PluginError::NotReady(message.to_string()))
+        }
+    }
+
+    fn chain(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn falls_back_until_a_plugin_answers() {
+        let mut manager = PluginManager::new();
+        manager.register(Arc::new(Answer {
+            id: "llmserver-rs",
+            result: Err("model host unreachable"),
+        }));
+        manager.register(Arc::new(Answer {
+            id: "ollama",
+            result: Ok(Value::from("ollama")),
+        }));
+        let plugins = SharedPluginManager::new(manager);
+        let invoke = |plugin: Arc<dyn ServerPlugin>| async move {
+            plugin
+                .invoke("pull", Value::Null, InvokeContext::default())
+                .await
+        };
+
+        let (answer, attempts) = run_chain(
+            &plugins,
+            &chain(&["llmserver-rs", "missing", "ollama"]),
+            invoke,
+        )
+        .await
+        .unwrap();
+        assert_eq!(answer, "ollama");
+        let tried: Vec<_> = attempts.iter().map(|a| a.code).collect();
+        assert_eq!(
+            tried,
+            [
+                Some(PluginErrorCode::NotReady),
+                Some(PluginErrorCode::NotFound),
+                None
+            ]
+        );
+
+        let (_, attempts) = run_chain(&plugins, &chain(&["ollama"]), invoke)
+            .await
+            .unwrap();
+        assert!(attempts.is_empty());
+
+        let err = run_chain(&plugins, &chain(&["llmserver-rs", "missing"]), invoke)
+            .await
+            .unwrap_err();
+        assert!(matches!(err.error, PluginError::NotFound(_)));
+        assert_eq!(err.attempts.len(), 2);
+    }
+}
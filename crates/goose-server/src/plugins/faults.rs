@@ -0,0 +1,137 @@
+//! Deterministic failures for exercising retry, restart and recovery paths in
+//! integration tests. Never configured from the API or the environment; tests
+//! opt in with [`super::llmserver::LlmServerPlugin::with_faults`].
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::PluginError;
+
+#[derive(Debug, Clone, Default)]
+pub struct FaultPlan {
+    /// Fail model downloads with a connection reset once this many bytes
+    /// have been received.
+    pub download_error_at_byte: Option<u64>,
+    /// Number of upcoming process spawns, including restarts, that fail.
+    pub spawn_failures: u32,
+    /// Extra latency added before every watchdog health probe.
+    pub health_check_delay: Option<Duration>,
+    /// Kill every spawned process this long after it started.
+    pub crash_after: Option<Duration>,
+}
+
+#[derive(Debug)]
+struct FaultState {
+    plan: FaultPlan,
+    spawn_failures_left: AtomicU32,
+}
+
+/// Injects the failures of a [`FaultPlan`]; does nothing when disabled.
+#[derive(Debug, Clone, Default)]
+pub struct FaultInjector {
+    state: Option<Arc<FaultState>>,
+}
+
+impl FaultInjector {
+    pub fn new(plan: FaultPlan) -> Self {
+        Self {
+            state: Some(Arc::new(FaultState {
+                spawn_failures_left: AtomicU32::new(plan.spawn_failures),
+                plan,
+            })),
+        }
+    }
+
+    fn plan(&self) -> Option<&FaultPlan> {
+        self.state.as_ref().map(|state| &state.plan)
+    }
+
+    /// Called before a received chunk is stored.
+    pub fn check_download(&self, received: u64) -> Result<(), PluginError> {
+        match self.plan().and_then(|plan| plan.download_error_at_byte) {
+            Some(at) if received >= at => Err(PluginError::Io(std::io::Error::new(
+                std::io::ErrorKind::ConnectionReset,
+                format!("injected fault: connection reset at byte {}", at),
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Called before a process is spawned.
+    pub fn check_spawn(&self) -> Result<(), PluginError> {
+        let Some(state) = &self.state else {
+            return Ok(());
+        };
+        let failed = state
+            .spawn_failures_left
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |left| {
+                left.checked_sub(1)
+            })
+            .is_ok();
+        if failed {
+            return Err(PluginError::ProcessStart(
+                "injected fault: spawn failure".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    pub async fn delay_health_check(&self) {
+        if let Some(delay) = self.plan().and_then(|plan| plan.health_check_delay) {
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Schedules the configured crash of a freshly spawned process.
+    pub fn arm_crash(&self, pid: Option<u32>) {
+        let (Some(after), Some(pid)) = (self.plan().and_then(|plan| plan.crash_after), pid) else {
+            return;
+        };
+        tokio::spawn(async move {
+            tokio::time::sleep(after).await;
+            tracing::warn!("injected fault: killing process {}", pid);
+            kill(pid);
+        });
+    }
+}
+
+#[cfg(unix)]
+fn kill(pid: u32) {
+    // SAFETY: kill takes plain integers. The pid may already have been
+    // reaped, which only matters for the tests that configure this.
+    unsafe {
+        libc::kill(pid as libc::pid_t, libc::SIGKILL);
+    }
+}
+
+#[cfg(not(unix))]
+fn kill(pid: u32) {
+    tracing::warn!(
+        "injected crashes are not supported here; process {} keeps running",
+        pid
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn injects_only_the_planned_failures() {
+        let disabled = FaultInjector::default();
+        assert!(disabled.check_spawn().is_ok());
+        assert!(disabled.check_download(u64::MAX).is_ok());
+
+        let faults = FaultInjector::new(FaultPlan {
+            download_error_at_byte: Some(1024),
+            spawn_failures: 2,
+            ..FaultPlan::default()
+        });
+        assert!(faults.check_download(1023).is_ok());
+        assert!(faults.check_download(1024).is_err());
+        assert!(faults.check_spawn().is_err());
+        assert!(faults.clone().check_spawn().is_err());
+        assert!(faults.check_spawn().is_ok());
+    }
+}
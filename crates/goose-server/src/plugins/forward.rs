@@ -0,0 +1,387 @@
+//! Adapts plugins that live outside the server process. Each [`ServerPlugin`]
+//! call is sent as a method name plus JSON request through a
+//! [`PluginTransport`], which answers with the JSON response or a
+//! [`RemoteError`].
+
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::events::PluginEvent;
+use super::gc::{ModelGcRequest, ModelGcResponse};
+use super::health::PluginHealthResponse;
+use super::metrics::PluginMetrics;
+use super::operations::InvokeContext;
+use super::settings::PluginConfig;
+use super::signing::PluginSignature;
+use super::{
+    DownloadModelRequest, DownloadModelResponse, ListLogFilesResponse, ListModelsResponse,
+    ListNodesResponse, ListProfilesResponse, ListServicesResponse, ModelRevisionsRequest,
+    ModelRevisionsResponse, ModelUpdatesResponse, PluginError, PluginMetadata, ServerPlugin,
+    ServiceLogsPruned, ServiceLogsRequest, ServiceLogsResponse, ServiceStatusRequest,
+    ServiceStatusResponse, SignalServiceRequest, SignalServiceResponse, StartServiceRequest,
+    StartServiceResponse, StopServiceRequest, StopServiceResponse, UpgradeServiceRequest,
+    UpgradeServiceResponse,
+};
+
+/// Error reported by an out-of-process plugin. `kind` names the
+/// [`PluginError`] variant in snake case; unknown kinds become internal
+/// errors.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RemoteError {
+    pub kind: String,
+    #[serde(default)]
+    pub message: String,
+}
+
+impl From<RemoteError> for PluginError {
+    fn from(error: RemoteError) -> Self {
+        let RemoteError { kind, message } = error;
+        match kind.as_str() {
+            "unsupported_operation" => PluginError::UnsupportedOperation,
+            "invalid_request" => PluginError::InvalidRequest(message),
+            "not_ready" => PluginError::NotReady(message),
+            "not_found" => PluginError::NotFound(message),
+            "path_not_allowed" => PluginError::PathNotAllowed(message),
+            "instance_not_found" => PluginError::InstanceNotFound(message),
+            "model_in_use" => PluginError::ModelInUse(message),
+            "binary_missing" => PluginError::BinaryMissing(message),
+            "process_start" => PluginError::ProcessStart(message),
+            "resource_limit" => PluginError::ResourceLimit(message),
+            "verification_failed" | "checksum_mismatch" => PluginError::VerificationFailed(message),
+            "cancelled" => PluginError::Cancelled,
+            "offline" => PluginError::Offline(message),
+            _ => PluginError::Internal(format!("{}: {}", kind, message)),
+        }
+    }
+}
+
+/// Outcome envelope used by transports without their own error framing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CallResult {
+    Ok(Value),
+    Error(RemoteError),
+}
+
+impl CallResult {
+    pub fn into_result(self) -> Result<Value, PluginError> {
+        match self {
+            CallResult::Ok(value) => Ok(value),
+            CallResult::Error(error) => Err(error.into()),
+        }
+    }
+}
+
+#[async_trait]
+pub trait PluginTransport: Send + Sync {
+    /// Invokes `method`, named after the [`ServerPlugin`] method, with its
+    /// request serialized as `params` (`null` for methods without one).
+    async fn call(&self, method: &str, params: Value) -> Result<Value, PluginError>;
+}
+
+/// Reads the answer to `config_schema`, which plugins are asked once when
+/// they are loaded. Plugins that cannot answer have no settings.
+pub fn read_config_schema(answer: Result<Value, PluginError>) -> Value {
+    match answer {
+        Ok(schema) => schema,
+        Err(PluginError::UnsupportedOperation) => Value::Null,
+        Err(err) => {
+            tracing::debug!("plugin did not describe its config: {}", err);
+            Value::Null
+        }
+    }
+}
+
+/// A [`ServerPlugin`] whose operations are all handled over a transport.
+pub struct ForwardingPlugin<T> {
+    metadata: PluginMetadata,
+    config_schema: Value,
+    transport: T,
+}
+
+impl<T: PluginTransport> ForwardingPlugin<T> {
+    pub fn new(metadata: PluginMetadata, transport: T) -> Self {
+        Self {
+            metadata,
+            config_schema: Value::Null,
+            transport,
+        }
+    }
+
+    pub fn with_config_schema(mut self, config_schema: Value) -> Self {
+        self.config_schema = config_schema;
+        self
+    }
+
+    /// Replaces the metadata the plugin reported about itself.
+    pub fn with_metadata(mut self, metadata: PluginMetadata) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    /// Records how the plugin's binary was verified; see [`signing`].
+    ///
+    /// [`signing`]: super::signing
+    pub fn with_signature(mut self, signature: Option<PluginSignature>) -> Self {
+        self.metadata.signature = signature;
+        self
+    }
+
+    async fn forward<Req: Serialize, Resp: DeserializeOwned>(
+        &self,
+        method: &str,
+        request: &Req,
+    ) -> Result<Resp, PluginError> {
+        let params =
+            serde_json::to_value(request).map_err(|err| PluginError::Internal(err.to_string()))?;
+        let response = self.transport.call(method, params).await?;
+        serde_json::from_value(response).map_err(|err| {
+            PluginError::Internal(format!(
+                "plugin {} sent an invalid {} response: {}",
+                self.metadata.id, method, err
+            ))
+        })
+    }
+}
+
+#[async_trait]
+impl<T: PluginTransport> ServerPlugin for ForwardingPlugin<T> {
+    fn metadata(&self) -> PluginMetadata {
+        self.metadata.clone()
+    }
+
+    fn config_schema(&self) -> Value {
+        self.config_schema.clone()
+    }
+
+    async fn download_model(
+        &self,
+        request: DownloadModelRequest,
+    ) -> Result<DownloadModelResponse, PluginError> {
+        self.forward("download_model", &request).await
+    }
+
+    async fn start_service(
+        &self,
+        request: StartServiceRequest,
+    ) -> Result<StartServiceResponse, PluginError> {
+        self.forward("start_service", &request).await
+    }
+
+    async fn stop_service(
+        &self,
+        request: StopServiceRequest,
+    ) -> Result<StopServiceResponse, PluginError> {
+        self.forward("stop_service", &request).await
+    }
+
+    async fn service_logs(
+        &self,
+        request: ServiceLogsRequest,
+    ) -> Result<ServiceLogsResponse, PluginError> {
+        self.forward("service_logs", &request).await
+    }
+
+    async fn service_status(
+        &self,
+        request: ServiceStatusRequest,
+    ) -> Result<ServiceStatusResponse, PluginError> {
+        self.forward("service_status", &request).await
+    }
+
+    async fn list_services(&self) -> Result<ListServicesResponse, PluginError> {
+        self.forward("list_services", &()).await
+    }
+
+    async fn list_log_files(&self) -> Result<ListLogFilesResponse, PluginError> {
+        self.forward("list_log_files", &()).await
+    }
+
+    async fn upgrade_service(
+        &self,
+        request: UpgradeServiceRequest,
+    ) -> Result<UpgradeServiceResponse, PluginError> {
+        self.forward("upgrade_service", &request).await
+    }
+
+    async fn signal_service(
+        &self,
+        request: SignalServiceRequest,
+    ) -> Result<SignalServiceResponse, PluginError> {
+        self.forward("signal_service", &request).await
+    }
+
+    async fn list_models(&self) -> Result<ListModelsResponse, PluginError> {
+        self.forward("list_models", &()).await
+    }
+
+    async fn list_nodes(&self) -> Result<ListNodesResponse, PluginError> {
+        self.forward("list_nodes", &()).await
+    }
+
+    async fn list_profiles(&self) -> Result<ListProfilesResponse, PluginError> {
+        self.forward("list_profiles", &()).await
+    }
+
+    async fn list_revisions(
+        &self,
+        request: ModelRevisionsRequest,
+    ) -> Result<ModelRevisionsResponse, PluginError> {
+        self.forward("list_revisions", &request).await
+    }
+
+    async fn check_model_updates(&self) -> Result<ModelUpdatesResponse, PluginError> {
+        self.forward("check_model_updates", &()).await
+    }
+
+    async fn collect_models(
+        &self,
+        request: ModelGcRequest,
+    ) -> Result<ModelGcResponse, PluginError> {
+        self.forward("collect_models", &request).await
+    }
+
+    async fn prune_logs(&self) -> Result<ServiceLogsPruned, PluginError> {
+        self.forward("prune_logs", &()).await
+    }
+
+    async fn health(&self) -> Result<PluginHealthResponse, PluginError> {
+        self.forward("health", &()).await
+    }
+
+    async fn metrics(&self) -> Result<PluginMetrics, PluginError> {
+        self.forward("metrics", &()).await
+    }
+
+    /// The context stays behind: remote plugins cancel and report progress
+    /// of their own operations.
+    async fn invoke(
+        &self,
+        operation: &str,
+        payload: Value,
+        _context: InvokeContext,
+    ) -> Result<Value, PluginError> {
+        self.forward(
+            "invoke",
+            &json!({ "operation": operation, "payload": payload }),
+        )
+        .await
+    }
+
+    async fn configure(&self, config: &PluginConfig) -> Result<(), PluginError> {
+        self.forward("configure", config).await
+    }
+
+    async fn shutdown(&self) -> Result<Vec<String>, PluginError> {
+        match self.forward("shutdown", &()).await {
+            Err(PluginError::UnsupportedOperation) => Ok(Vec::new()),
+            result => result,
+        }
+    }
+
+    async fn on_register(&self) -> Result<(), PluginError> {
+        match self.forward("on_register", &()).await {
+            Err(PluginError::UnsupportedOperation) => Ok(()),
+            result => result,
+        }
+    }
+
+    async fn on_server_start(&self) -> Result<(), PluginError> {
+        match self.forward("on_server_start", &()).await {
+            Err(PluginError::UnsupportedOperation) => Ok(()),
+            result => result,
+        }
+    }
+
+    async fn on_event(&self, event: &PluginEvent) -> Result<(), PluginError> {
+        match self.forward("on_event", event).await {
+            Err(PluginError::UnsupportedOperation) => Ok(()),
+            result => result,
+        }
+    }
+
+    async fn save_state(&self) -> Result<Option<Value>, PluginError> {
+        match self.forward("save_state", &()).await {
+            Err(PluginError::UnsupportedOperation) => Ok(None),
+            result => result,
+        }
+    }
+
+    async fn restore_state(&self, state: Value) -> Result<(), PluginError> {
+        match self.forward("restore_state", &state).await {
+            Err(PluginError::UnsupportedOperation) => Ok(()),
+            result => result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugins::PluginTaskType;
+
+    struct Echo;
+
+    #[async_trait]
+    impl PluginTransport for Echo {
+        async fn call(&self, method: &str, params: Value) -> Result<Value, PluginError> {
+            match method {
+                "stop_service" => Ok(json!({
+                    "instance_id": params["instance_id"],
+                    "task_type": "text",
+                    "terminated": true
+                })),
+                "invoke" => Ok(params),
+                _ => Err(RemoteError {
+                    kind: "unsupported_operation".to_string(),
+                    message: String::new(),
+                }
+                .into()),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn forwards_requests_and_errors() {
+        let plugin = ForwardingPlugin::new(
+            PluginMetadata {
+                id: "echo".to_string(),
+                name: "echo".to_string(),
+                description: String::new(),
+                capabilities: Vec::new(),
+                enabled: true,
+                dependencies: Vec::new(),
+                api_version: crate::plugins::compat::PLUGIN_API_VERSION,
+                subscriptions: Vec::new(),
+                signature: None,
+            },
+            Echo,
+        );
+        let stopped = plugin
+            .stop_service(StopServiceRequest {
+                service: crate::plugins::ServiceSelector::instance("abc"),
+            })
+            .await
+            .unwrap();
+        assert_eq!(stopped.instance_id, "abc");
+        assert_eq!(stopped.task_type, PluginTaskType::TEXT);
+        assert!(matches!(
+            plugin.list_models().await,
+            Err(PluginError::UnsupportedOperation)
+        ));
+        let invoked = plugin
+            .invoke(
+                "list-voices",
+                json!({"lang": "en"}),
+                InvokeContext::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(invoked["operation"], "list-voices");
+        assert_eq!(invoked["payload"]["lang"], "en");
+        // Hooks are optional.
+        plugin.on_register().await.unwrap();
+    }
+}
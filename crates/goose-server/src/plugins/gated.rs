@@ -0,0 +1,76 @@
+//! Gated repositories, whose files the hub only serves to accounts that
+//! accepted the model's license on its page.
+
+use reqwest::{Response, StatusCode, Url};
+
+use super::revisions::repo_url;
+use super::PluginError;
+
+/// Header the hub names the reason for a refusal in.
+const ERROR_CODE_HEADER: &str = "x-error-code";
+
+/// Whether the text of a refusal is the hub's gating notice, "Access to
+/// model ... is restricted", rather than a missing token or repository.
+fn is_gating_notice(text: &str) -> bool {
+    let text = text.to_ascii_lowercase();
+    text.contains("gated") || (text.contains("access to model") && text.contains("restricted"))
+}
+
+/// Passes on a successful `response`. A refusal to serve the gated
+/// repository `model_id` becomes [`PluginError::GatedModel`] pointing at the
+/// page its license is accepted on; other error statuses stay network errors.
+pub async fn check(response: Response, hub: &Url, model_id: &str) -> Result<Response, PluginError> {
+    let err = match response.error_for_status_ref() {
+        Ok(_) => return Ok(response),
+        Err(err) => err,
+    };
+    if !matches!(
+        response.status(),
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN
+    ) {
+        return Err(err.into());
+    }
+    let flagged = response
+        .headers()
+        .get(ERROR_CODE_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|code| code == "GatedRepo");
+    if flagged || is_gating_notice(&response.text().await.unwrap_or_default()) {
+        return Err(PluginError::GatedModel {
+            model_id: model_id.to_string(),
+            repo_url: repo_url(hub, &[], model_id, &[])?.to_string(),
+        });
+    }
+    Err(err.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(status: u16, body: &str) -> Response {
+        http::Response::builder()
+            .status(status)
+            .body(body.to_string())
+            .unwrap()
+            .into()
+    }
+
+    #[tokio::test]
+    async fn recognizes_refusals_of_gated_repos() {
+        let hub = Url::parse("https://huggingface.co/").unwrap();
+        let notice = "Access to model meta-llama/Llama-3.1-8B is restricted and you are not \
+                      in the authorized list. Visit https://huggingface.co/meta-llama/Llama-3.1-8B \
+                      to ask for access.";
+        match check(response(403, notice), &hub, "meta-llama/Llama-3.1-8B").await {
+            Err(PluginError::GatedModel { repo_url, .. }) => {
+                assert_eq!(repo_url, "https://huggingface.co/meta-llama/Llama-3.1-8B")
+            }
+            other => panic!("expected a gated model error, got {:?}", other.map(|_| ())),
+        }
+
+        let missing = check(response(401, "Repository not found"), &hub, "org/model").await;
+        assert!(matches!(missing, Err(PluginError::Network(_))));
+        assert!(check(response(200, ""), &hub, "org/model").await.is_ok());
+    }
+}
@@ -0,0 +1,230 @@
+//! Finds and removes downloaded models nobody has used in a while.
+
+use std::collections::HashSet;
+use std::path::Path;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+use utoipa::ToSchema;
+
+use super::manifest::{ModelManifest, ModelRecord};
+use super::PluginError;
+
+/// Models idle for less than this are never collected unless a request asks
+/// for a shorter threshold.
+const DEFAULT_MIN_IDLE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct ModelGcRequest {
+    /// Delete the candidates. Without it the response only lists them.
+    #[serde(default)]
+    pub confirm: bool,
+    /// Overrides `GOOSE_PLUGIN_MODEL_GC_MIN_IDLE_SECS` for this run.
+    #[serde(default)]
+    pub min_idle_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+pub struct ModelGcCandidate {
+    pub model_id: String,
+    pub filename: String,
+    pub saved_path: String,
+    pub bytes: u64,
+    /// Last launch of a service from this file; never launched when unset.
+    pub last_used: Option<DateTime<Utc>>,
+    pub idle_secs: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ModelGcResponse {
+    pub candidates: Vec<ModelGcCandidate>,
+    /// Combined size of the candidates.
+    pub bytes: u64,
+    /// Whether the candidates were deleted.
+    pub deleted: bool,
+}
+
+/// When models count as forgotten, and whether goosed removes them itself.
+#[derive(Debug, Clone, Copy)]
+pub struct GcPolicy {
+    pub min_idle: Duration,
+    /// Time between automatic collections; unset leaves collection to the API.
+    pub interval: Option<Duration>,
+}
+
+impl Default for GcPolicy {
+    /// Reads `GOOSE_PLUGIN_MODEL_GC_MIN_IDLE_SECS` and
+    /// `GOOSE_PLUGIN_MODEL_GC_INTERVAL_SECS`.
+    fn default() -> Self {
+        fn secs(name: &str) -> Option<Duration> {
+            let secs: u64 = std::env::var(name).ok()?.trim().parse().ok()?;
+            (secs > 0).then(|| Duration::from_secs(secs))
+        }
+        Self {
+            min_idle: secs("GOOSE_PLUGIN_MODEL_GC_MIN_IDLE_SECS").unwrap_or(DEFAULT_MIN_IDLE),
+            interval: secs("GOOSE_PLUGIN_MODEL_GC_INTERVAL_SECS"),
+        }
+    }
+}
+
+/// Unpinned records not backing a running service and idle for at least
+/// `min_idle`, longest idle first. A model that was never launched is idle
+/// since its download.
+pub fn candidates(
+    records: &[ModelRecord],
+    in_use: &HashSet<String>,
+    min_idle: Duration,
+    now: DateTime<Utc>,
+) -> Vec<ModelGcCandidate> {
+    let mut candidates: Vec<_> = records
+        .iter()
+        .filter(|record| !record.pinned && !in_use.contains(&record.saved_path))
+        .filter_map(|record| {
+            let since = record.last_used.unwrap_or(record.downloaded_at);
+            let idle = (now - since).to_std().ok()?;
+            (idle >= min_idle).then(|| ModelGcCandidate {
+                model_id: record.model_id.clone(),
+                filename: record.filename.clone(),
+                saved_path: record.saved_path.clone(),
+                bytes: record.bytes,
+                last_used: record.last_used,
+                idle_secs: idle.as_secs(),
+            })
+        })
+        .collect();
+    candidates.sort_by_key(|candidate| std::cmp::Reverse(candidate.idle_secs));
+    candidates
+}
+
+/// Unpinned records under `dir` not backing a running service, least
+/// recently used first: what quota eviction removes to make room.
+pub fn eviction_order<'a>(
+    records: &'a [ModelRecord],
+    in_use: &HashSet<String>,
+    dir: &Path,
+) -> Vec<&'a ModelRecord> {
+    let mut evictable: Vec<_> = records
+        .iter()
+        .filter(|record| !record.pinned && !in_use.contains(&record.saved_path))
+        .filter(|record| Path::new(&record.saved_path).starts_with(dir))
+        .collect();
+    evictable.sort_by_key(|record| record.last_used.unwrap_or(record.downloaded_at));
+    evictable
+}
+
+/// Lists the current candidates and, with `delete`, removes their files and
+/// manifest records.
+pub async fn collect(
+    manifest: &mut ModelManifest,
+    in_use: &HashSet<String>,
+    min_idle: Duration,
+    delete: bool,
+) -> Result<ModelGcResponse, PluginError> {
+    let candidates = candidates(manifest.records(), in_use, min_idle, Utc::now());
+    let bytes = candidates.iter().map(|candidate| candidate.bytes).sum();
+    if delete && !candidates.is_empty() {
+        for candidate in &candidates {
+            match fs::remove_file(&candidate.saved_path).await {
+                Ok(()) => {}
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+                Err(err) => return Err(err.into()),
+            }
+            manifest.remove(&candidate.saved_path);
+            tracing::info!(
+                "garbage collected model {} ({} bytes)",
+                candidate.saved_path,
+                candidate.bytes
+            );
+        }
+        manifest.save().await?;
+    }
+    Ok(ModelGcResponse {
+        candidates,
+        bytes,
+        deleted: delete,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugins::PluginTaskType;
+
+    fn record(
+        saved_path: &str,
+        downloaded_days_ago: i64,
+        used_days_ago: Option<i64>,
+    ) -> ModelRecord {
+        let now = Utc::now();
+        ModelRecord {
+            model_id: "org/model".to_string(),
+            filename: saved_path.to_string(),
+            revision: "main".to_string(),
+            commit: None,
+            task_type: PluginTaskType::TEXT,
+            saved_path: saved_path.to_string(),
+            bytes: 10,
+            sha256: None,
+            downloaded_at: now - chrono::Duration::days(downloaded_days_ago),
+            last_checked: None,
+            update: None,
+            last_used: used_days_ago.map(|days| now - chrono::Duration::days(days)),
+            encrypted: false,
+            pinned: false,
+            source_url: None,
+            provenance: None,
+        }
+    }
+
+    #[test]
+    fn skips_running_and_recently_used_models() {
+        let records = [
+            record("fresh.gguf", 1, None),
+            record("forgotten.gguf", 90, None),
+            record("used.gguf", 90, Some(2)),
+            record("stale.gguf", 90, Some(40)),
+            record("running.gguf", 90, None),
+            ModelRecord {
+                pinned: true,
+                ..record("pinned.gguf", 90, None)
+            },
+        ];
+        let in_use = HashSet::from(["running.gguf".to_string()]);
+        let found = candidates(
+            &records,
+            &in_use,
+            Duration::from_secs(30 * 24 * 60 * 60),
+            Utc::now(),
+        );
+        let paths: Vec<_> = found.iter().map(|c| c.saved_path.as_str()).collect();
+        assert_eq!(paths, ["forgotten.gguf", "stale.gguf"]);
+    }
+
+    #[test]
+    fn evicts_least_recently_used_first() {
+        let records = [
+            record("/models/text/used.gguf", 90, Some(2)),
+            record("/models/text/fresh.gguf", 1, None),
+            record("/models/text/stale.gguf", 90, Some(40)),
+            record("/models/text/running.gguf", 90, None),
+            record("/models/tts/voice.gguf", 90, None),
+            ModelRecord {
+                pinned: true,
+                ..record("/models/text/pinned.gguf", 90, None)
+            },
+        ];
+        let in_use = HashSet::from(["/models/text/running.gguf".to_string()]);
+        let order = eviction_order(&records, &in_use, Path::new("/models/text"));
+        let paths: Vec<_> = order.iter().map(|r| r.saved_path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "/models/text/stale.gguf",
+                "/models/text/used.gguf",
+                "/models/text/fresh.gguf"
+            ]
+        );
+    }
+}
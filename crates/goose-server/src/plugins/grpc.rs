@@ -0,0 +1,345 @@
+//! Plugins served over gRPC by another machine, speaking the
+//! `goose.plugin.v1.PluginService` of `proto/goose_plugin.proto`.
+//!
+//! `Metadata` answers with the plugin's [`PluginMetadata`] and `Call` runs
+//! one method of [`super::forward`], with requests and responses encoded as
+//! JSON.
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tonic::codec::ProstCodec;
+use tonic::codegen::http::uri::PathAndQuery;
+use tonic::metadata::MetadataValue;
+use tonic::transport::{Channel, Endpoint};
+use tonic::{Code, Request, Status};
+
+use super::forward::{read_config_schema, ForwardingPlugin, PluginTransport, RemoteError};
+use super::{PluginError, PluginMetadata};
+
+/// Messages of `proto/goose_plugin.proto`, generated by `build.rs`.
+pub mod proto {
+    include!(concat!(env!("OUT_DIR"), "/goose.plugin.v1.rs"));
+}
+
+/// Where a gRPC plugin is served.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GrpcPluginConfig {
+    /// Endpoint of the plugin service, e.g. `http://gpu-box:50051`.
+    pub url: String,
+    /// Sent as a bearer token. Accepts `secret://NAME`.
+    #[serde(default, skip_serializing)]
+    pub auth_token: Option<String>,
+}
+
+impl GrpcPluginConfig {
+    /// Plugins from `GOOSE_GRPC_PLUGINS`, a JSON array of configs.
+    pub fn from_env() -> anyhow::Result<Vec<GrpcPluginConfig>> {
+        let Ok(raw) = std::env::var("GOOSE_GRPC_PLUGINS") else {
+            return Ok(Vec::new());
+        };
+        serde_json::from_str(&raw)
+            .map_err(|err| anyhow::anyhow!("invalid GOOSE_GRPC_PLUGINS: {}", err))
+    }
+}
+
+pub struct GrpcTransport {
+    channel: Channel,
+    authorization: Option<MetadataValue<tonic::metadata::Ascii>>,
+}
+
+impl GrpcTransport {
+    /// Connects lazily, so a plugin host that is down fails its calls
+    /// rather than the registration.
+    fn new(config: &GrpcPluginConfig) -> Result<Self, PluginError> {
+        let channel = Endpoint::from_shared(config.url.clone())
+            .map_err(|err| PluginError::InvalidRequest(format!("{}: {}", config.url, err)))?
+            .connect_lazy();
+        let authorization = config
+            .auth_token
+            .as_deref()
+            .map(super::redact::resolve_secret_ref)
+            .transpose()?
+            .map(|token| {
+                format!("Bearer {}", token)
+                    .parse()
+                    .map_err(|_| PluginError::InvalidRequest("invalid auth_token".to_string()))
+            })
+            .transpose()?;
+        Ok(Self {
+            channel,
+            authorization,
+        })
+    }
+
+    async fn unary<Req, Resp>(&self, method: &'static str, message: Req) -> Result<Resp, Status>
+    where
+        Req: prost::Message + Send + Sync + 'static,
+        Resp: prost::Message + Default + Send + Sync + 'static,
+    {
+        let mut grpc = tonic::client::Grpc::new(self.channel.clone());
+        grpc.ready()
+            .await
+            .map_err(|err| Status::unavailable(err.to_string()))?;
+        let mut request = Request::new(message);
+        if let Some(authorization) = &self.authorization {
+            request
+                .metadata_mut()
+                .insert("authorization", authorization.clone());
+        }
+        let path = PathAndQuery::from_static(method);
+        let response = grpc
+            .unary(request, path, ProstCodec::<Req, Resp>::default())
+            .await?;
+        Ok(response.into_inner())
+    }
+
+    async fn metadata(&self) -> Result<PluginMetadata, PluginError> {
+        let response: proto::MetadataResponse = self
+            .unary(
+                "/goose.plugin.v1.PluginService/Metadata",
+                proto::MetadataRequest {},
+            )
+            .await
+            .map_err(status_error)?;
+        serde_json::from_slice(&response.metadata_json)
+            .map_err(|err| PluginError::Internal(format!("invalid plugin metadata: {}", err)))
+    }
+}
+
+/// Transport failures; errors of the plugin itself come back as
+/// [`proto::PluginError`].
+fn status_error(status: Status) -> PluginError {
+    match status.code() {
+        Code::Unavailable | Code::DeadlineExceeded => {
+            PluginError::NotReady(status.message().to_string())
+        }
+        _ => PluginError::Internal(format!("gRPC call failed: {}", status)),
+    }
+}
+
+#[async_trait]
+impl PluginTransport for GrpcTransport {
+    async fn call(&self, method: &str, params: Value) -> Result<Value, PluginError> {
+        let request = proto::CallRequest {
+            method: method.to_string(),
+            params_json: serde_json::to_vec(&params)
+                .map_err(|err| PluginError::Internal(err.to_string()))?,
+        };
+        let response: proto::CallResponse = self
+            .unary("/goose.plugin.v1.PluginService/Call", request)
+            .await
+            .map_err(status_error)?;
+        match response.outcome {
+            Some(proto::call_response::Outcome::ResultJson(result)) => {
+                serde_json::from_slice(&result).map_err(|err| {
+                    PluginError::Internal(format!("invalid {} response: {}", method, err))
+                })
+            }
+            Some(proto::call_response::Outcome::Error(error)) => Err(RemoteError {
+                kind: error.kind,
+                message: error.message,
+            }
+            .into()),
+            None => Err(PluginError::Internal(format!("empty {} response", method))),
+        }
+    }
+}
+
+pub type GrpcPlugin = ForwardingPlugin<GrpcTransport>;
+
+/// Connects to the endpoint, asking it for its metadata unless given.
+pub async fn connect(
+    config: &GrpcPluginConfig,
+    metadata: Option<PluginMetadata>,
+) -> Result<GrpcPlugin, PluginError> {
+    let transport = GrpcTransport::new(config)?;
+    let metadata = match metadata {
+        Some(metadata) => metadata,
+        None => transport.metadata().await?,
+    };
+    let config_schema = read_config_schema(transport.call("config_schema", Value::Null).await);
+    Ok(ForwardingPlugin::new(metadata, transport).with_config_schema(config_schema))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+    use std::task::{Context, Poll};
+
+    use serde_json::json;
+    use tokio_stream::wrappers::TcpListenerStream;
+    use tonic::body::BoxBody;
+    use tonic::codegen::{http, BoxFuture, Service};
+    use tonic::server::{Grpc, NamedService, UnaryService};
+    use tonic::Response;
+
+    use super::*;
+    use crate::plugins::operations::InvokeContext;
+    use crate::plugins::{PluginTaskType, ServerPlugin, ServiceSelector, StopServiceRequest};
+
+    fn metadata() -> PluginMetadata {
+        PluginMetadata {
+            id: "echo".to_string(),
+            name: "echo".to_string(),
+            description: String::new(),
+            capabilities: Vec::new(),
+            enabled: true,
+            dependencies: Vec::new(),
+            api_version: crate::plugins::compat::PLUGIN_API_VERSION,
+            subscriptions: Vec::new(),
+            signature: None,
+        }
+    }
+
+    #[allow(clippy::result_large_err)] // Status is what tonic services return
+    fn authorize<T>(request: &Request<T>) -> Result<(), Status> {
+        match request.metadata().get("authorization") {
+            Some(value) if value == "Bearer sesame" => Ok(()),
+            _ => Err(Status::unauthenticated("missing token")),
+        }
+    }
+
+    struct Metadata;
+
+    impl UnaryService<proto::MetadataRequest> for Metadata {
+        type Response = proto::MetadataResponse;
+        type Future = BoxFuture<Response<Self::Response>, Status>;
+
+        fn call(&mut self, request: Request<proto::MetadataRequest>) -> Self::Future {
+            let response = authorize(&request).map(|()| {
+                Response::new(proto::MetadataResponse {
+                    metadata_json: serde_json::to_vec(&metadata()).unwrap(),
+                })
+            });
+            Box::pin(async move { response })
+        }
+    }
+
+    /// Answers `stop_service` and `invoke` and rejects every other method.
+    struct Call;
+
+    impl UnaryService<proto::CallRequest> for Call {
+        type Response = proto::CallResponse;
+        type Future = BoxFuture<Response<Self::Response>, Status>;
+
+        fn call(&mut self, request: Request<proto::CallRequest>) -> Self::Future {
+            let response = authorize(&request).map(|()| {
+                let request = request.into_inner();
+                let params: Value = serde_json::from_slice(&request.params_json).unwrap();
+                let outcome = match request.method.as_str() {
+                    "stop_service" => proto::call_response::Outcome::ResultJson(
+                        serde_json::to_vec(&json!({
+                            "instance_id": params["instance_id"],
+                            "task_type": "text",
+                            "terminated": true
+                        }))
+                        .unwrap(),
+                    ),
+                    "invoke" => proto::call_response::Outcome::ResultJson(request.params_json),
+                    _ => proto::call_response::Outcome::Error(proto::PluginError {
+                        kind: "unsupported_operation".to_string(),
+                        message: String::new(),
+                    }),
+                };
+                Response::new(proto::CallResponse {
+                    outcome: Some(outcome),
+                })
+            });
+            Box::pin(async move { response })
+        }
+    }
+
+    #[derive(Clone)]
+    struct EchoServer;
+
+    impl NamedService for EchoServer {
+        const NAME: &'static str = "goose.plugin.v1.PluginService";
+    }
+
+    impl Service<http::Request<BoxBody>> for EchoServer {
+        type Response = http::Response<BoxBody>;
+        type Error = Infallible;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: http::Request<BoxBody>) -> Self::Future {
+            Box::pin(async move {
+                Ok(match request.uri().path() {
+                    "/goose.plugin.v1.PluginService/Metadata" => {
+                        Grpc::new(ProstCodec::default())
+                            .unary(Metadata, request)
+                            .await
+                    }
+                    "/goose.plugin.v1.PluginService/Call" => {
+                        Grpc::new(ProstCodec::default()).unary(Call, request).await
+                    }
+                    _ => Status::unimplemented("unknown method").into_http(),
+                })
+            })
+        }
+    }
+
+    async fn serve() -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(EchoServer)
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+        url
+    }
+
+    #[tokio::test]
+    async fn forwards_calls_over_grpc() {
+        let url = serve().await;
+        let plugin = connect(
+            &GrpcPluginConfig {
+                url: url.clone(),
+                auth_token: Some("sesame".to_string()),
+            },
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(plugin.metadata().id, "echo");
+        assert_eq!(plugin.config_schema(), Value::Null);
+
+        let stopped = plugin
+            .stop_service(StopServiceRequest {
+                service: ServiceSelector::instance("abc"),
+            })
+            .await
+            .unwrap();
+        assert_eq!(stopped.instance_id, "abc");
+        assert_eq!(stopped.task_type, PluginTaskType::TEXT);
+        let invoked = plugin
+            .invoke(
+                "list-voices",
+                json!({"lang": "en"}),
+                InvokeContext::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(invoked["operation"], "list-voices");
+        assert_eq!(invoked["payload"]["lang"], "en");
+        assert!(matches!(
+            plugin.list_models().await,
+            Err(PluginError::UnsupportedOperation)
+        ));
+
+        let unauthenticated = connect(
+            &GrpcPluginConfig {
+                url,
+                auth_token: None,
+            },
+            None,
+        )
+        .await;
+        assert!(matches!(unauthenticated, Err(PluginError::Internal(_))));
+    }
+}
@@ -0,0 +1,129 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::PluginTaskType;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ServiceHealth {
+    /// No successful health check yet, or no health check configured.
+    Starting,
+    Healthy,
+    Unhealthy,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RestartPolicy {
+    #[default]
+    Never,
+    OnUnhealthy,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct HealthCheckConfig {
+    /// HTTP endpoint that answers with a 2xx status while the service is healthy.
+    pub url: String,
+    #[serde(default = "default_interval_secs")]
+    pub interval_secs: u64,
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+    /// Consecutive failed checks before the service is marked unhealthy.
+    #[serde(default = "default_failure_threshold")]
+    pub failure_threshold: u32,
+    #[serde(default)]
+    pub restart_policy: RestartPolicy,
+    #[serde(default = "default_max_restarts")]
+    pub max_restarts: u32,
+}
+
+fn default_interval_secs() -> u64 {
+    10
+}
+
+fn default_timeout_secs() -> u64 {
+    5
+}
+
+fn default_failure_threshold() -> u32 {
+    3
+}
+
+fn default_max_restarts() -> u32 {
+    3
+}
+
+impl HealthCheckConfig {
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval_secs.max(1))
+    }
+
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs.max(1))
+    }
+}
+
+/// Health of one service, checked when it was requested.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TaskHealth {
+    pub instance_id: String,
+    pub task_type: PluginTaskType,
+    /// Whether the process is still running.
+    pub alive: bool,
+    /// Whether the health endpoint answered just now; unset for services
+    /// without a health check.
+    pub responsive: Option<bool>,
+    /// Why the service is not alive or not responsive.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Status tracked by the periodic health checks.
+    pub health: ServiceHealth,
+    pub consecutive_failures: u32,
+    pub restarts: u32,
+    pub last_health_check: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub node: Option<String>,
+}
+
+impl TaskHealth {
+    pub fn is_healthy(&self) -> bool {
+        self.alive && self.responsive != Some(false)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PluginHealthResponse {
+    pub plugin_id: String,
+    /// True when every service is alive and responsive.
+    pub healthy: bool,
+    pub tasks: Vec<TaskHealth>,
+}
+
+impl PluginHealthResponse {
+    pub fn new(plugin_id: String, tasks: Vec<TaskHealth>) -> Self {
+        Self {
+            plugin_id,
+            healthy: tasks.iter().all(TaskHealth::is_healthy),
+            tasks,
+        }
+    }
+}
+
+/// Performs a single health probe, returning a human readable reason on failure.
+pub async fn probe(client: &reqwest::Client, config: &HealthCheckConfig) -> Result<(), String> {
+    let response = client
+        .get(&config.url)
+        .timeout(config.timeout())
+        .send()
+        .await
+        .map_err(|err| err.to_string())?;
+
+    if response.status().is_success() {
+        Ok(())
+    } else {
+        Err(format!("health endpoint returned {}", response.status()))
+    }
+}
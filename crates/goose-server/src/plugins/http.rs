@@ -0,0 +1,102 @@
+//! Plugins served over HTTP by another machine.
+//!
+//! The endpoint answers `GET <url>/metadata` with its [`PluginMetadata`] and
+//! `POST <url>/call/<method>` with `{"ok": <response>}` or
+//! `{"error": {"kind": ..., "message": ...}}`, where methods and payloads are
+//! those of [`super::forward`].
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::ToSchema;
+
+use super::forward::{read_config_schema, CallResult, ForwardingPlugin, PluginTransport};
+use super::{PluginError, PluginMetadata};
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+pub struct HttpPluginConfig {
+    /// Base URL of the plugin endpoint.
+    pub url: String,
+    /// Sent as a bearer token. Accepts `secret://NAME`.
+    #[serde(default, skip_serializing)]
+    pub auth_token: Option<String>,
+}
+
+pub struct HttpTransport {
+    client: reqwest::Client,
+    base: reqwest::Url,
+    auth_token: Option<String>,
+}
+
+impl HttpTransport {
+    fn new(config: &HttpPluginConfig) -> Result<Self, PluginError> {
+        let mut base = reqwest::Url::parse(&config.url)
+            .map_err(|err| PluginError::InvalidRequest(format!("{}: {}", config.url, err)))?;
+        if !base.path().ends_with('/') {
+            base.set_path(&format!("{}/", base.path()));
+        }
+        let auth_token = config
+            .auth_token
+            .as_deref()
+            .map(super::redact::resolve_secret_ref)
+            .transpose()?;
+        Ok(Self {
+            client: reqwest::Client::new(),
+            base,
+            auth_token,
+        })
+    }
+
+    fn url(&self, path: &str) -> Result<reqwest::Url, PluginError> {
+        self.base
+            .join(path)
+            .map_err(|err| PluginError::InvalidRequest(err.to_string()))
+    }
+
+    fn authorize(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match &self.auth_token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    async fn metadata(&self) -> Result<PluginMetadata, PluginError> {
+        let request = self.client.get(self.url("metadata")?);
+        Ok(self
+            .authorize(request)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?)
+    }
+}
+
+#[async_trait]
+impl PluginTransport for HttpTransport {
+    async fn call(&self, method: &str, params: Value) -> Result<Value, PluginError> {
+        let request = self
+            .client
+            .post(self.url(&format!("call/{}", method))?)
+            .json(&params);
+        // Error bodies carry the plugin's error, so the status is not checked.
+        let result: CallResult = self.authorize(request).send().await?.json().await?;
+        result.into_result()
+    }
+}
+
+pub type HttpPlugin = ForwardingPlugin<HttpTransport>;
+
+/// Connects to the endpoint, asking it for its metadata unless given.
+pub async fn connect(
+    config: &HttpPluginConfig,
+    metadata: Option<PluginMetadata>,
+) -> Result<HttpPlugin, PluginError> {
+    let transport = HttpTransport::new(config)?;
+    let metadata = match metadata {
+        Some(metadata) => metadata,
+        None => transport.metadata().await?,
+    };
+    let config_schema = read_config_schema(transport.call("config_schema", Value::Null).await);
+    Ok(ForwardingPlugin::new(metadata, transport).with_config_schema(config_schema))
+}
@@ -0,0 +1,63 @@
+//! Models the user already has on disk, such as files in the Hugging Face
+//! cache, taken under management without downloading them again.
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// How an imported file gets into the model directory.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportMode {
+    /// An independent copy, encrypted when model encryption is on.
+    #[default]
+    Copy,
+    /// A second name for the source file, which has to be on the same
+    /// filesystem as the model directory.
+    Hardlink,
+    /// A link to the source, which has to stay where it is.
+    Symlink,
+}
+
+/// Model id and commit of a file in the Hugging Face cache, which keeps
+/// files as `models--<org>--<name>/snapshots/<commit>/<file>`.
+pub fn hf_cache_origin(path: &Path) -> Option<(String, String)> {
+    let components: Vec<_> = path
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect();
+    let snapshots = components.iter().rposition(|part| part == "snapshots")?;
+    // The commit directory has to hold the file, not be it.
+    if components.len() < snapshots + 3 {
+        return None;
+    }
+    let repo = components.get(snapshots.checked_sub(1)?)?;
+    let model_id = repo.strip_prefix("models--")?.replace("--", "/");
+    Some((model_id, components[snapshots + 1].to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_origin_from_hugging_face_cache_paths() {
+        let path = Path::new(
+            "/home/me/.cache/huggingface/hub/models--TheBloke--Llama-2-7B-GGUF/snapshots/\
+             191239b3e26b2882fb562ffccdd1cf0f65402adb/llama-2-7b.Q4_K_M.gguf",
+        );
+        assert_eq!(
+            hf_cache_origin(path),
+            Some((
+                "TheBloke/Llama-2-7B-GGUF".to_string(),
+                "191239b3e26b2882fb562ffccdd1cf0f65402adb".to_string()
+            ))
+        );
+        assert_eq!(hf_cache_origin(Path::new("/models/llama.gguf")), None);
+        assert_eq!(
+            hf_cache_origin(Path::new("/hub/models--org--m/snapshots/abc")),
+            None
+        );
+    }
+}
@@ -0,0 +1,384 @@
+//! Integrity of the model directories. goosed scans them when it starts for
+//! `.part` files that interrupted downloads left behind, empty files and
+//! locked files whose hash no longer matches the lock file, and repairs the
+//! files it knows the origin of by resuming or repeating their download.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use utoipa::ToSchema;
+
+use super::lockfile::LockedModel;
+use super::manifest::ModelRecord;
+use super::sandbox::PathSandbox;
+use super::watcher::hash_file;
+use super::{DownloadModelResponse, PluginError, PluginTaskType};
+
+/// Extension of the files downloads are written to before they complete.
+const PARTIAL_EXTENSION: &str = "part";
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum IntegrityProblem {
+    /// A `.part` file an interrupted download left behind.
+    Partial,
+    /// A file without any content.
+    Empty,
+    /// A locked file whose SHA-256 differs from the locked one.
+    ChecksumMismatch,
+}
+
+/// The download that recreates a file.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+pub struct ModelOrigin {
+    pub model_id: String,
+    pub filename: String,
+    /// Commit the file was downloaded at, or the revision it was requested
+    /// for when the commit is unknown.
+    pub revision: String,
+    pub task_type: PluginTaskType,
+    #[serde(default)]
+    pub destination_dir: Option<String>,
+    /// Hex SHA-256 the file must have; set for locked files.
+    #[serde(default)]
+    pub sha256: Option<String>,
+}
+
+impl From<LockedModel> for ModelOrigin {
+    fn from(locked: LockedModel) -> Self {
+        Self {
+            model_id: locked.model_id,
+            filename: locked.filename,
+            revision: locked.revision,
+            task_type: locked.task_type,
+            destination_dir: locked.destination_dir,
+            sha256: Some(locked.sha256),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+pub struct IntegrityIssue {
+    pub problem: IntegrityProblem,
+    pub path: String,
+    pub bytes: u64,
+    /// Hex SHA-256 of the file on disk, for checksum mismatches.
+    #[serde(default)]
+    pub sha256: Option<String>,
+    /// How a repair downloads the file again. Unset for files goosed does
+    /// not know the origin of, which repairs leave alone.
+    #[serde(default)]
+    pub origin: Option<ModelOrigin>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct ModelHealthResponse {
+    /// When the last scan finished; unset while the startup scan runs.
+    pub scanned_at: Option<DateTime<Utc>>,
+    pub issues: Vec<IntegrityIssue>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct RepairModelsRequest {
+    /// Token for gated or private repositories among the damaged models.
+    #[serde(default)]
+    pub auth_token: Option<String>,
+    /// Hub base URLs tried in order instead of the plugin's.
+    #[serde(default)]
+    pub hub_endpoints: Vec<String>,
+    /// Stops the repair between and during downloads.
+    #[serde(skip)]
+    pub cancel: CancellationToken,
+}
+
+/// A damaged file that could not be repaired.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RepairFailure {
+    pub path: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct RepairModelsResponse {
+    pub repaired: Vec<DownloadModelResponse>,
+    pub failed: Vec<RepairFailure>,
+    /// The scan made once the repair finished.
+    pub health: ModelHealthResponse,
+}
+
+/// Whether scans hash locked files; `GOOSE_PLUGIN_LLM_VERIFY_MODELS=0`
+/// limits them to leftovers and empty files.
+pub fn verify_from_env() -> bool {
+    std::env::var("GOOSE_PLUGIN_LLM_VERIFY_MODELS")
+        .map(|value| !matches!(value.trim(), "0" | "false" | "no"))
+        .unwrap_or(true)
+}
+
+/// What a scan checks the model directories against.
+#[derive(Debug, Clone)]
+pub struct IntegrityScan {
+    /// Base directory whose task type directories are searched.
+    base_dir: PathBuf,
+    /// Directories never searched, such as the runtime directory.
+    excluded: Vec<PathBuf>,
+    /// Downloads that recreate the files goosed knows, by saved path.
+    origins: HashMap<PathBuf, ModelOrigin>,
+    verify_checksums: bool,
+}
+
+impl IntegrityScan {
+    /// A scan of `base_dir` knowing the origins of the files in the lock
+    /// file and of the hub downloads in the manifest.
+    pub fn new(
+        base_dir: &Path,
+        excluded: &[PathBuf],
+        sandbox: &PathSandbox,
+        locked: Vec<LockedModel>,
+        records: &[ModelRecord],
+        verify_checksums: bool,
+    ) -> Result<Self, PluginError> {
+        let base_dir = sandbox.resolve_dir(base_dir)?;
+        let default_dir =
+            |task_type: &PluginTaskType| sandbox.resolve_dir(&base_dir.join(task_type.as_str()));
+        let mut origins = HashMap::new();
+        let mut encrypted = HashSet::new();
+        for record in records.iter().filter(|record| record.source_url.is_none()) {
+            let saved_path = PathBuf::from(&record.saved_path);
+            if record.encrypted {
+                encrypted.insert(saved_path.clone());
+            }
+            let Some(dir) = record.saved_path.strip_suffix(&record.filename) else {
+                continue;
+            };
+            let dir = dir.trim_end_matches(std::path::MAIN_SEPARATOR);
+            let destination_dir = match default_dir(&record.task_type) {
+                Ok(default) if default == Path::new(dir) => None,
+                _ => Some(dir.to_string()),
+            };
+            origins.insert(
+                saved_path,
+                ModelOrigin {
+                    model_id: record.model_id.clone(),
+                    filename: record.filename.clone(),
+                    revision: record
+                        .commit
+                        .clone()
+                        .unwrap_or_else(|| record.revision.clone()),
+                    task_type: record.task_type.clone(),
+                    destination_dir,
+                    sha256: None,
+                },
+            );
+        }
+        for model in locked {
+            let dir = match &model.destination_dir {
+                Some(dir) => sandbox.resolve_dir(Path::new(dir)),
+                None => default_dir(&model.task_type),
+            };
+            let Ok(saved_path) = dir.and_then(|dir| sandbox.join_file(&dir, &model.filename))
+            else {
+                continue;
+            };
+            let mut origin = ModelOrigin::from(model);
+            // Encrypted files are stored with other bytes than were locked.
+            if encrypted.contains(&saved_path) {
+                origin.sha256 = None;
+            }
+            origins.insert(saved_path, origin);
+        }
+        Ok(Self {
+            base_dir,
+            excluded: excluded
+                .iter()
+                .map(|dir| dir.canonicalize().unwrap_or_else(|_| dir.clone()))
+                .collect(),
+            origins,
+            verify_checksums,
+        })
+    }
+
+    /// Searches the task type directories and hashes the locked files.
+    /// Blocks for as long as hashing takes.
+    pub fn run(&self) -> std::io::Result<ModelHealthResponse> {
+        let mut issues = Vec::new();
+        let mut pending = Vec::new();
+        for entry in std::fs::read_dir(&self.base_dir)? {
+            let entry = entry?;
+            let name = entry.file_name();
+            if entry.file_type()?.is_dir()
+                && name
+                    .to_str()
+                    .is_some_and(|name| PluginTaskType::new(name).is_ok())
+            {
+                pending.push(entry.path());
+            }
+        }
+        while let Some(dir) = pending.pop() {
+            if self
+                .excluded
+                .iter()
+                .any(|excluded| dir.starts_with(excluded))
+            {
+                continue;
+            }
+            for entry in std::fs::read_dir(&dir)? {
+                let entry = entry?;
+                let path = entry.path();
+                if entry.file_name().to_string_lossy().starts_with('.') {
+                    continue;
+                }
+                if entry.file_type()?.is_dir() {
+                    pending.push(path);
+                    continue;
+                }
+                let bytes = entry.metadata()?.len();
+                if path
+                    .extension()
+                    .is_some_and(|extension| extension == PARTIAL_EXTENSION)
+                {
+                    let target = path.with_extension("");
+                    issues.push(self.issue(IntegrityProblem::Partial, &path, &target, bytes));
+                } else if bytes == 0 {
+                    issues.push(self.issue(IntegrityProblem::Empty, &path, &path, bytes));
+                }
+            }
+        }
+
+        let reported: HashSet<String> = issues.iter().map(|issue| issue.path.clone()).collect();
+        for (path, origin) in &self.origins {
+            let Some(expected) = origin.sha256.as_deref().filter(|_| self.verify_checksums) else {
+                continue;
+            };
+            if reported.contains(&*path.to_string_lossy()) {
+                continue;
+            }
+            // Missing files are for a restore to download again.
+            let (bytes, sha256) = match hash_file(path) {
+                Ok(hashed) => hashed,
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(err),
+            };
+            if bytes == 0 {
+                issues.push(self.issue(IntegrityProblem::Empty, path, path, bytes));
+            } else if !sha256.eq_ignore_ascii_case(expected) {
+                let mut issue = self.issue(IntegrityProblem::ChecksumMismatch, path, path, bytes);
+                issue.sha256 = Some(sha256);
+                issues.push(issue);
+            }
+        }
+        issues.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(ModelHealthResponse {
+            scanned_at: Some(Utc::now()),
+            issues,
+        })
+    }
+
+    /// An issue with the file at `path`, which the download of `target`
+    /// repairs.
+    fn issue(
+        &self,
+        problem: IntegrityProblem,
+        path: &Path,
+        target: &Path,
+        bytes: u64,
+    ) -> IntegrityIssue {
+        IntegrityIssue {
+            problem,
+            path: path.to_string_lossy().to_string(),
+            bytes,
+            sha256: None,
+            origin: self.origins.get(target).cloned(),
+        }
+    }
+}
+
+/// Runs `scan` in the background, storing its result in `health`.
+pub fn spawn_scan(scan: IntegrityScan, health: Arc<Mutex<ModelHealthResponse>>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        match tokio::task::spawn_blocking(move || scan.run()).await {
+            Ok(Ok(report)) => {
+                if !report.issues.is_empty() {
+                    tracing::warn!(
+                        "found {} damaged files in the model directories",
+                        report.issues.len()
+                    );
+                }
+                *health.lock().await = report;
+            }
+            Ok(Err(err)) => tracing::warn!("scanning the model directories failed: {}", err),
+            Err(err) => tracing::warn!("scanning the model directories failed: {}", err),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_leftovers_empty_files_and_changed_locked_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let base_dir = dir.path().canonicalize().unwrap();
+        let text = base_dir.join("text");
+        std::fs::create_dir_all(text.join("org")).unwrap();
+        std::fs::create_dir_all(base_dir.join("runtime")).unwrap();
+        std::fs::write(text.join("org/model.gguf.part"), b"GG").unwrap();
+        std::fs::write(text.join("empty.gguf"), b"").unwrap();
+        std::fs::write(text.join("changed.gguf"), b"GGUF").unwrap();
+        std::fs::write(text.join(".hidden.part"), b"").unwrap();
+        std::fs::write(base_dir.join("runtime/copy.part"), b"").unwrap();
+        let sandbox = PathSandbox::new([base_dir.clone()]).unwrap();
+        let locked = |filename: &str| LockedModel {
+            model_id: "org/model".to_string(),
+            revision: "1".repeat(40),
+            filename: filename.to_string(),
+            sha256: "ab".repeat(32),
+            task_type: PluginTaskType::TEXT,
+            destination_dir: None,
+        };
+
+        let scan = IntegrityScan::new(
+            &base_dir,
+            &[base_dir.join("runtime")],
+            &sandbox,
+            vec![locked("org/model.gguf"), locked("changed.gguf")],
+            &[],
+            true,
+        )
+        .unwrap();
+        let issues = scan.run().unwrap().issues;
+        let summary: Vec<_> = issues
+            .iter()
+            .map(|issue| {
+                (
+                    issue.problem,
+                    issue.path.strip_prefix(text.to_str().unwrap()).unwrap(),
+                    issue.origin.as_ref().map(|origin| origin.filename.as_str()),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (
+                    IntegrityProblem::ChecksumMismatch,
+                    "/changed.gguf",
+                    Some("changed.gguf")
+                ),
+                (IntegrityProblem::Empty, "/empty.gguf", None),
+                (
+                    IntegrityProblem::Partial,
+                    "/org/model.gguf.part",
+                    Some("org/model.gguf")
+                ),
+            ]
+        );
+        assert_eq!(issues[0].sha256.as_deref().map(str::len), Some(64));
+    }
+}
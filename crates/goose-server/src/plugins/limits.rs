@@ -0,0 +1,282 @@
+use std::path::Path;
+
+use tokio::process::Command;
+
+use super::PluginError;
+
+/// Context sizes at or above this are considered large enough to warrant a
+/// memory commit check before launching.
+const LARGE_CONTEXT_TOKENS: u64 = 32_768;
+
+/// Memory related demands of a launch, derived from its arguments.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LaunchRequirements {
+    pub mlock: bool,
+    pub context_size: Option<u64>,
+    pub model_bytes: Option<u64>,
+}
+
+impl LaunchRequirements {
+    pub fn from_args(args: &[String], model_path: &str) -> Self {
+        let mut requirements = Self {
+            model_bytes: std::fs::metadata(Path::new(model_path))
+                .ok()
+                .map(|meta| meta.len()),
+            ..Self::default()
+        };
+
+        let mut iter = args.iter().peekable();
+        while let Some(arg) = iter.next() {
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) => (flag, Some(value.to_string())),
+                None => (arg.as_str(), None),
+            };
+            match flag {
+                "--mlock" => requirements.mlock = true,
+                "-c" | "--ctx-size" | "--ctx_size" | "--context-size" | "--n-ctx" | "--n_ctx" => {
+                    let value = inline_value.or_else(|| iter.peek().map(|next| next.to_string()));
+                    requirements.context_size = value.and_then(|value| value.parse().ok());
+                }
+                _ => {}
+            }
+        }
+        requirements
+    }
+
+    fn large_context(&self) -> bool {
+        self.context_size
+            .is_some_and(|size| size >= LARGE_CONTEXT_TOKENS)
+    }
+}
+
+/// Limit adjustments to apply in the child before it executes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LimitAdjustments {
+    pub raise_memlock: bool,
+    pub raise_address_space: bool,
+}
+
+impl LimitAdjustments {
+    pub fn is_empty(&self) -> bool {
+        !self.raise_memlock && !self.raise_address_space
+    }
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const MIB: u64 = 1024 * 1024;
+    if bytes >= 1024 * MIB {
+        format!("{:.1} GiB", bytes as f64 / (1024 * MIB) as f64)
+    } else if bytes >= MIB {
+        format!("{} MiB", bytes / MIB)
+    } else {
+        format!("{} KiB", bytes / 1024)
+    }
+}
+
+/// Checks host limits against the launch requirements. Returns the soft limit
+/// raises needed in the child, or an actionable error when the hard limits are
+/// too low to satisfy the request.
+#[cfg(unix)]
+pub fn preflight(requirements: &LaunchRequirements) -> Result<LimitAdjustments, PluginError> {
+    let mut adjustments = LimitAdjustments::default();
+    let model_bytes = requirements.model_bytes.unwrap_or(0);
+
+    if let Some((soft, hard)) = rlimit(libc::RLIMIT_AS as _) {
+        if soft < model_bytes {
+            if hard < model_bytes {
+                return Err(PluginError::ResourceLimit(format!(
+                    "address space limit (RLIMIT_AS) is {} but the model is {}; \
+                     raise it with `ulimit -v unlimited` before starting goosed",
+                    format_bytes(hard),
+                    format_bytes(model_bytes)
+                )));
+            }
+            adjustments.raise_address_space = true;
+        }
+    }
+
+    if requirements.mlock {
+        if let Some((soft, hard)) = rlimit(libc::RLIMIT_MEMLOCK as _) {
+            if soft < model_bytes {
+                if hard < model_bytes {
+                    return Err(PluginError::ResourceLimit(format!(
+                        "--mlock needs {} of lockable memory but RLIMIT_MEMLOCK is {}; \
+                         raise it with `ulimit -l unlimited` or a memlock entry in \
+                         /etc/security/limits.conf, or drop --mlock",
+                        format_bytes(model_bytes),
+                        format_bytes(hard)
+                    )));
+                }
+                adjustments.raise_memlock = true;
+            }
+        }
+    }
+
+    if requirements.mlock || requirements.large_context() {
+        check_overcommit(model_bytes)?;
+    }
+
+    Ok(adjustments)
+}
+
+#[cfg(not(unix))]
+pub fn preflight(_requirements: &LaunchRequirements) -> Result<LimitAdjustments, PluginError> {
+    Ok(LimitAdjustments::default())
+}
+
+/// Returns `(soft, hard)` for a resource, or `None` when it is unlimited.
+#[cfg(unix)]
+fn rlimit(resource: libc::c_int) -> Option<(u64, u64)> {
+    let mut limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    // SAFETY: getrlimit only writes into the provided struct.
+    if unsafe { libc::getrlimit(resource as _, &mut limit) } != 0 {
+        return None;
+    }
+    if limit.rlim_cur == libc::RLIM_INFINITY && limit.rlim_max == libc::RLIM_INFINITY {
+        return None;
+    }
+    // rlim_t is not u64 on every unix target.
+    #[allow(clippy::unnecessary_cast)]
+    let clamp = |value: libc::rlim_t| {
+        if value == libc::RLIM_INFINITY {
+            u64::MAX
+        } else {
+            value as u64
+        }
+    };
+    Some((clamp(limit.rlim_cur), clamp(limit.rlim_max)))
+}
+
+/// With strict overcommit (`vm.overcommit_memory = 2`) large allocations fail
+/// outright once the commit limit is reached, so make sure the model fits.
+#[cfg(target_os = "linux")]
+fn check_overcommit(model_bytes: u64) -> Result<(), PluginError> {
+    let mode = std::fs::read_to_string("/proc/sys/vm/overcommit_memory").unwrap_or_default();
+    if mode.trim() != "2" {
+        return Ok(());
+    }
+
+    let meminfo = std::fs::read_to_string("/proc/meminfo").unwrap_or_default();
+    let field = |name: &str| {
+        meminfo
+            .lines()
+            .find_map(|line| line.strip_prefix(name))
+            .and_then(|rest| rest.split_whitespace().next())
+            .and_then(|kb| kb.parse::<u64>().ok())
+            .map(|kb| kb * 1024)
+    };
+    let (Some(limit), Some(committed)) = (field("CommitLimit:"), field("Committed_AS:")) else {
+        return Ok(());
+    };
+
+    let available = limit.saturating_sub(committed);
+    if available < model_bytes {
+        return Err(PluginError::ResourceLimit(format!(
+            "strict overcommit (vm.overcommit_memory=2) leaves {} committable but the model \
+             needs {}; add swap, raise vm.overcommit_ratio, or reduce the context size",
+            format_bytes(available),
+            format_bytes(model_bytes)
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn check_overcommit(_model_bytes: u64) -> Result<(), PluginError> {
+    Ok(())
+}
+
+/// Raises the requested soft limits to their hard limits in the child.
+#[cfg(unix)]
+pub fn configure_command(command: &mut Command, adjustments: LimitAdjustments) {
+    if adjustments.is_empty() {
+        return;
+    }
+
+    // SAFETY: the closure only calls the async-signal-safe getrlimit/setrlimit.
+    unsafe {
+        command.pre_exec(move || {
+            for (enabled, resource) in [
+                (adjustments.raise_memlock, libc::RLIMIT_MEMLOCK),
+                (adjustments.raise_address_space, libc::RLIMIT_AS),
+            ] {
+                if !enabled {
+                    continue;
+                }
+                let mut limit = libc::rlimit {
+                    rlim_cur: 0,
+                    rlim_max: 0,
+                };
+                if libc::getrlimit(resource, &mut limit) != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                limit.rlim_cur = limit.rlim_max;
+                if libc::setrlimit(resource, &limit) != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+            }
+            Ok(())
+        });
+    }
+}
+
+#[cfg(not(unix))]
+pub fn configure_command(_command: &mut Command, _adjustments: LimitAdjustments) {}
+
+/// Bytes an unprivileged process can still write to the filesystem holding
+/// `path`, or `None` where this cannot be told.
+#[cfg(unix)]
+pub fn available_space(path: &Path) -> std::io::Result<Option<u64>> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = std::ffi::CString::new(path.as_os_str().as_bytes())
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidInput, err))?;
+    // SAFETY: statvfs only writes into the provided struct.
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    // The field types differ between unix targets.
+    #[allow(clippy::unnecessary_cast)]
+    let available = stat.f_bavail as u64 * stat.f_frsize as u64;
+    Ok(Some(available))
+}
+
+#[cfg(not(unix))]
+pub fn available_space(_path: &Path) -> std::io::Result<Option<u64>> {
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn detects_mlock_and_context_size() {
+        let requirements =
+            LaunchRequirements::from_args(&args(&["serve", "--mlock", "-c", "65536"]), "");
+        assert!(requirements.mlock);
+        assert_eq!(requirements.context_size, Some(65536));
+        assert!(requirements.large_context());
+
+        let requirements = LaunchRequirements::from_args(&args(&["--ctx-size=4096"]), "");
+        assert!(!requirements.mlock);
+        assert_eq!(requirements.context_size, Some(4096));
+        assert!(!requirements.large_context());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn reports_available_space() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(available_space(dir.path()).unwrap().is_some());
+        assert!(available_space(&dir.path().join("missing")).is_err());
+    }
+}
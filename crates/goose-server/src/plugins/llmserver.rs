@@ -1,288 +1,5236 @@
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::path::{Path, PathBuf};
-use std::process::Stdio;
+use std::process::{ExitStatus, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 
+use chrono::{DateTime, Utc};
+use futures::{StreamExt, TryStreamExt};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use tokio::fs;
-use tokio::io::AsyncWriteExt;
-use tokio::process::{Child, Command};
-use tokio::sync::Mutex;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tokio::process::{Child, ChildStdin, Command};
+use tokio::sync::{mpsc, Mutex};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
 
+use super::adapters::{self, ListAdaptersRequest, ListAdaptersResponse};
+use super::affinity::{self, CpuAffinity};
+use super::breaker::CircuitBreakers;
+use super::compat::PLUGIN_API_VERSION;
+use super::convert::{self, ConvertModelRequest, ConvertModelResponse};
+use super::diagnostics::{CrashReport, CRASH_TAIL_LINES};
+use super::encryption::{DecryptedModel, ModelEncryption};
+use super::events::{EventBus, OutputForwarder, PluginEventKind};
+use super::faults::{FaultInjector, FaultPlan};
+use super::gated;
+use super::gc::{self, GcPolicy, ModelGcRequest, ModelGcResponse};
+use super::health::{
+    self, HealthCheckConfig, PluginHealthResponse, RestartPolicy, ServiceHealth, TaskHealth,
+};
+use super::import::{self, ImportMode};
+use super::integrity::{
+    self, IntegrityScan, ModelHealthResponse, ModelOrigin, RepairFailure, RepairModelsRequest,
+    RepairModelsResponse,
+};
+use super::limits::{self, LaunchRequirements, LimitAdjustments};
+use super::lockfile::{LockedModel, ModelLock};
+use super::logs::{self, FollowedLog, LogFiles, LogSink, LogStream};
+use super::manifest::{ModelManifest, ModelRecord};
+use super::metrics::{self, DownloadCounters, PluginMetrics};
+use super::mirrors::{self, HubEndpoints};
+use super::offline::OfflineMode;
+use super::offload::{self, GpuOffload};
+use super::operations::{self, InvokeContext};
+use super::peers::{self, PeerSource};
+use super::profiles::HardwareProfile;
+use super::progress::DownloadProgress;
+use super::provenance::{self, ModelProvenance, ModelSignature};
+use super::proxy::ProxyConfig;
+use super::quantize::{self, QuantizeModelRequest, QuantizeModelResponse};
+use super::quota::QuotaLimit;
+use super::readiness::{self, ReadinessCheck};
+use super::redact::Redactor;
+use super::remote::{RemoteInstance, RemoteNode};
+use super::retention::LogRetention;
+use super::revisions::{self, REPO_COMMIT_HEADER};
+use super::sandbox::PathSandbox;
+use super::search;
+use super::settings::PluginConfig;
+use super::sidecars;
+use super::signals;
+use super::signing::TrustRoot;
+use super::snapshot::SnapshotFilter;
+use super::sources::{ModelSource, ObjectLocation};
+use super::splits::{GgufSplits, SplitName};
+use super::stdio::{StdioConfig, StdioMode};
+use super::throttle::{DownloadThrottle, Throttle};
+use super::tokens;
+use super::upgrade::{SmokeTestConfig, SmokeTestResult, SMOKE_RETRY_DELAY};
+use super::usage::{self, ModelDirUsage, ModelUsageResponse};
+use super::watcher;
 use super::{
-    DownloadModelRequest, DownloadModelResponse, PluginCapability, PluginError, PluginMetadata,
-    PluginTaskType, ServerPlugin, StartServiceRequest, StartServiceResponse, StopServiceRequest,
-    StopServiceResponse,
+    AttachConsoleRequest, ConsoleSession, DeleteModelRequest, DeleteModelResponse, DownloadMode,
+    DownloadModelRequest, DownloadModelResponse, DownloadTransfer, FollowLogsRequest,
+    FollowLogsSession, Handover, ImportModelRequest, ImportModelResponse, ListLogFilesResponse,
+    ListModelsResponse, ListNodesResponse, ListProfilesResponse, ListServicesResponse,
+    ModelFilesRequest, ModelFilesResponse, ModelRevisionsRequest, ModelRevisionsResponse,
+    ModelSearchRequest, ModelSearchResponse, ModelUpdatesResponse, PartialDownload,
+    PinModelRequest, PluginCapability, PluginError, PluginMetadata, PluginTaskType, RestoreFailure,
+    RestoreModelsRequest, RestoreModelsResponse, ServerPlugin, ServiceLogsPruned,
+    ServiceLogsRequest, ServiceLogsResponse, ServiceSelector, ServiceStatusRequest,
+    ServiceStatusResponse, SignalServiceRequest, SignalServiceResponse, StartServiceRequest,
+    StartServiceResponse, StopServiceRequest, StopServiceResponse, UpgradeServiceRequest,
+    UpgradeServiceResponse, ValidateTokenRequest, ValidateTokenResponse,
 };
 
-struct ManagedProcess {
-    child: Child,
-    command: String,
-    args: Vec<String>,
+/// How long a freshly spawned process is watched for an immediate exit.
+const STARTUP_GRACE_PERIOD: Duration = Duration::from_millis(500);
+
+/// Upper bound for draining output pipes after a process has exited.
+const CAPTURE_DRAIN_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Connections a download is split across unless configured otherwise.
+const DEFAULT_DOWNLOAD_CONNECTIONS: usize = 4;
+
+/// Files smaller than this are fetched over a single connection.
+const CHUNKED_DOWNLOAD_MIN_BYTES: u64 = 256 * 1024 * 1024;
+
+/// Connections of an accelerated download unless configured otherwise.
+const DEFAULT_ACCELERATED_CONNECTIONS: usize = 16;
+
+/// Size of the ranges an accelerated download is split into.
+const ACCELERATED_CHUNK_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Times a range of an accelerated download is retried after a network
+/// error, continuing where it stopped.
+const ACCELERATED_RETRIES: u32 = 5;
+
+/// How a download is split into byte ranges fetched in parallel.
+struct ChunkPlan {
+    chunk_len: u64,
+    connections: usize,
+    retries: u32,
 }
 
-impl ManagedProcess {
-    fn new(child: Child, command: String, args: Vec<String>) -> Self {
-        Self { child, command, args }
+impl ChunkPlan {
+    /// The ranges a download of `total` bytes is fetched in, in order.
+    fn ranges(&self, total: u64) -> impl Iterator<Item = std::ops::Range<u64>> {
+        let chunk_len = self.chunk_len;
+        (0..total)
+            .step_by(chunk_len as usize)
+            .map(move |start| start..(start + chunk_len).min(total))
     }
 }
 
-#[derive(Clone)]
-pub struct LlmServerPlugin {
-    metadata: PluginMetadata,
-    base_dir: PathBuf,
-    default_binary: Option<PathBuf>,
-    client: reqwest::Client,
-    processes: Arc<Mutex<HashMap<PluginTaskType, ManagedProcess>>>,
+const USER_AGENT: &str = "goose-llmserver-plugin/1.0";
+
+/// A client for requests to the hub and object storage, sent through
+/// `proxy` when there is one.
+fn download_client(proxy: Option<&ProxyConfig>) -> Result<reqwest::Client, PluginError> {
+    let mut builder = reqwest::Client::builder().user_agent(USER_AGENT);
+    if let Some(proxy) = proxy {
+        builder = proxy.apply(builder)?;
+    }
+    Ok(builder.build()?)
 }
 
-impl LlmServerPlugin {
-    pub async fn bootstrap() -> anyhow::Result<Self> {
-        let base_dir = match std::env::var("GOOSE_PLUGIN_LLM_BASE_DIR") {
-            Ok(value) => PathBuf::from(value),
-            Err(_) => std::env::current_dir()?.join("plugins").join("llmserver"),
-        };
+type ProcessTable = Arc<Mutex<ServiceRegistry>>;
 
-        fs::create_dir_all(&base_dir).await?;
-        fs::create_dir_all(base_dir.join("text")).await?;
-        fs::create_dir_all(base_dir.join("tts")).await?;
+/// Running services keyed by instance id, with task type as a lookup convenience.
+#[derive(Default)]
+struct ServiceRegistry {
+    instances: HashMap<String, ManagedProcess>,
+    /// Task types a start is launching a process for.
+    starting: HashSet<PluginTaskType>,
+}
 
-        let default_binary = std::env::var("GOOSE_PLUGIN_LLM_BINARY")
-            .ok()
-            .map(PathBuf::from);
+impl ServiceRegistry {
+    /// Resolves a selector to the id of exactly one running instance.
+    fn resolve(&self, selector: &ServiceSelector) -> Result<String, PluginError> {
+        match (&selector.instance_id, &selector.task_type) {
+            (Some(instance_id), task_type) => {
+                let managed = self
+                    .instances
+                    .get(instance_id)
+                    .ok_or_else(|| PluginError::InstanceNotFound(instance_id.clone()))?;
+                if let Some(task_type) = task_type.as_ref().filter(|t| **t != managed.task_type) {
+                    return Err(PluginError::InvalidRequest(format!(
+                        "instance {} does not serve {:?}",
+                        instance_id, task_type
+                    )));
+                }
+                Ok(instance_id.clone())
+            }
+            (None, Some(task_type)) => {
+                let mut matching = self
+                    .instances
+                    .iter()
+                    .filter(|(_, managed)| managed.task_type == *task_type)
+                    .map(|(instance_id, _)| instance_id);
+                match (matching.next(), matching.next()) {
+                    (Some(instance_id), None) => Ok(instance_id.clone()),
+                    (None, _) => Err(PluginError::ProcessNotRunning(task_type.clone())),
+                    (Some(_), Some(_)) => Err(PluginError::InvalidRequest(format!(
+                        "several {:?} instances are running; specify instance_id",
+                        task_type
+                    ))),
+                }
+            }
+            (None, None) => Err(PluginError::InvalidRequest(
+                "instance_id or task_type is required".to_string(),
+            )),
+        }
+    }
 
-        let metadata = PluginMetadata {
-            id: "llmserver-rs".to_string(),
-            name: "llmserver-rs".to_string(),
-            description: "Manage llmserver-rs instances and download models".to_string(),
-            capabilities: vec![
-                PluginCapability::ModelDownload,
-                PluginCapability::ServiceStart,
-                PluginCapability::ServiceStop,
-            ],
-        };
+    fn get(&self, selector: &ServiceSelector) -> Result<&ManagedProcess, PluginError> {
+        let instance_id = self.resolve(selector)?;
+        Ok(&self.instances[&instance_id])
+    }
+
+    fn get_mut(&mut self, selector: &ServiceSelector) -> Result<&mut ManagedProcess, PluginError> {
+        let instance_id = self.resolve(selector)?;
+        Ok(self
+            .instances
+            .get_mut(&instance_id)
+            .expect("resolved instance is registered"))
+    }
+
+    fn remove(&mut self, selector: &ServiceSelector) -> Result<ManagedProcess, PluginError> {
+        let instance_id = self.resolve(selector)?;
+        Ok(self
+            .instances
+            .remove(&instance_id)
+            .expect("resolved instance is registered"))
+    }
+
+    fn serves(&self, task_type: &PluginTaskType) -> bool {
+        self.instances
+            .values()
+            .any(|managed| managed.task_type == *task_type)
+    }
+}
 
-        let client = reqwest::Client::builder()
-            .user_agent("goose-llmserver-plugin/1.0")
-            .build()?;
+/// A task type reserved for one start, so that concurrent starts do not both
+/// launch a process for it. Released once the start registers its instance,
+/// fails or is dropped.
+struct StartReservation {
+    processes: ProcessTable,
+    task_type: Option<PluginTaskType>,
+}
 
+impl StartReservation {
+    async fn reserve(
+        processes: &ProcessTable,
+        task_type: &PluginTaskType,
+    ) -> Result<Self, PluginError> {
+        let mut registry = processes.lock().await;
+        if registry.serves(task_type) || !registry.starting.insert(task_type.clone()) {
+            return Err(PluginError::ProcessAlreadyRunning(task_type.clone()));
+        }
         Ok(Self {
-            metadata,
-            base_dir,
-            default_binary,
-            client,
-            processes: Arc::new(Mutex::new(HashMap::new())),
+            processes: processes.clone(),
+            task_type: Some(task_type.clone()),
         })
     }
 
-    fn resolve_destination_dir(&self, request: &DownloadModelRequest) -> PathBuf {
-        if let Some(dir) = &request.destination_dir {
-            PathBuf::from(dir)
-        } else {
-            self.base_dir.join(request.task_type.as_directory_suffix())
+    /// Registers the started instance in place of the reservation.
+    fn register(
+        mut self,
+        registry: &mut ServiceRegistry,
+        instance_id: String,
+        managed: ManagedProcess,
+    ) {
+        if let Some(task_type) = self.task_type.take() {
+            registry.starting.remove(&task_type);
         }
+        registry.instances.insert(instance_id, managed);
     }
+}
 
-    fn resolve_binary_path(&self, request: &StartServiceRequest) -> Result<PathBuf, PluginError> {
-        if let Some(explicit) = &request.binary_path {
-            return Ok(PathBuf::from(explicit));
+impl Drop for StartReservation {
+    fn drop(&mut self) {
+        let Some(task_type) = self.task_type.take() else {
+            return;
+        };
+        match self.processes.try_lock() {
+            Ok(mut registry) => {
+                registry.starting.remove(&task_type);
+            }
+            Err(_) => {
+                let processes = self.processes.clone();
+                tokio::spawn(async move {
+                    processes.lock().await.starting.remove(&task_type);
+                });
+            }
         }
+    }
+}
+
+/// Everything needed to (re)spawn a managed process.
+#[derive(Debug, Clone)]
+struct LaunchSpec {
+    model_path: String,
+    command: String,
+    args: Vec<String>,
+    /// Environment as requested, with `secret://` references left unresolved.
+    environment: HashMap<String, String>,
+    resolved_environment: HashMap<String, String>,
+    redactor: Arc<Redactor>,
+    health_check: Option<HealthCheckConfig>,
+    cpu_affinity: Option<Vec<usize>>,
+    limit_adjustments: LimitAdjustments,
+    interactive: bool,
+    stdio: StdioConfig,
+    log_retention: LogRetention,
+    /// Directory the process's output is written to.
+    log_dir: PathBuf,
+    faults: FaultInjector,
+    /// Offload settings appended to `args`, if any were chosen automatically.
+    gpu_offload: Option<GpuOffload>,
+    /// Plaintext copy launched in place of an encrypted `model_path`.
+    decrypted: Option<Arc<DecryptedModel>>,
+}
+
+impl LaunchSpec {
+    /// Resolves secret references, validates host memory limits for this launch
+    /// and records the soft limit raises the child needs.
+    fn preflight(&mut self) -> Result<(), PluginError> {
+        let (resolved, redactor) = Redactor::resolve_environment(&self.environment)?;
+        self.resolved_environment = resolved;
+        self.redactor = Arc::new(redactor);
+
+        let requirements = LaunchRequirements::from_args(&self.args, &self.model_path);
+        self.limit_adjustments = limits::preflight(&requirements)?;
+        Ok(())
+    }
 
-        if let Some(default) = &self.default_binary {
-            return Ok(default.clone());
+    fn spawn(&self) -> Result<Child, PluginError> {
+        self.faults.check_spawn()?;
+        let mut command = Command::new(&self.command);
+        match &self.decrypted {
+            Some(decrypted) => {
+                let path = decrypted.path().to_string_lossy();
+                command.args(
+                    self.args
+                        .iter()
+                        .map(|arg| arg.replace(&self.model_path, &path)),
+                );
+            }
+            None => {
+                command.args(&self.args);
+            }
+        }
+        command.envs(&self.resolved_environment);
+        command.stdin(if self.interactive {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        });
+        command.stdout(self.stdio.stdout.to_stdio());
+        command.stderr(self.stdio.stderr.to_stdio());
+        // A start dropped before it registers the process takes it along.
+        command.kill_on_drop(true);
+        if let Some(cores) = &self.cpu_affinity {
+            affinity::configure_command(&mut command, cores)?;
         }
+        limits::configure_command(&mut command, self.limit_adjustments);
 
-        Err(PluginError::InvalidRequest(
-            "binary_path not provided and GOOSE_PLUGIN_LLM_BINARY unset".to_string(),
-        ))
+        let mut child = command.spawn().map_err(|err| {
+            if err.kind() == std::io::ErrorKind::NotFound {
+                PluginError::BinaryMissing(format!("{}: {}", self.command, err))
+            } else {
+                PluginError::ProcessStart(err.to_string())
+            }
+        })?;
+        if let Some(cores) = &self.cpu_affinity {
+            if let Err(err) = affinity::apply_to_child(&child, cores) {
+                let _ = child.start_kill();
+                return Err(err);
+            }
+        }
+        self.faults.arm_crash(child.id());
+        Ok(child)
     }
 
-    fn default_args(task: &PluginTaskType, model_path: &str) -> Vec<String> {
-        vec![
-            "serve".to_string(),
-            "--model".to_string(),
-            model_path.to_string(),
-            "--task".to_string(),
-            task.as_directory_suffix().to_string(),
-        ]
+    /// Arguments safe to return to clients and write to logs.
+    fn display_args(&self) -> Vec<String> {
+        self.redactor.redact_args(&self.args)
     }
 
-    async fn store_model(
-        &self,
-        path: &Path,
-        mut response: reqwest::Response,
-    ) -> Result<u64, PluginError> {
-        if let Some(parent) = path.parent() {
-            fs::create_dir_all(parent).await?;
+    fn command_line(&self) -> String {
+        std::iter::once(self.command.clone())
+            .chain(self.display_args())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+struct ManagedProcess {
+    instance_id: String,
+    task_type: PluginTaskType,
+    spec: LaunchSpec,
+    child: Child,
+    logs: LogSink,
+    output_events: OutputForwarder,
+    capture: Vec<JoinHandle<()>>,
+    /// Forwards console input to stdin of interactive processes.
+    console: Option<mpsc::Sender<Vec<u8>>>,
+    health: ServiceHealth,
+    consecutive_failures: u32,
+    restarts: u32,
+    last_health_check: Option<DateTime<Utc>>,
+    /// When the current child was spawned.
+    started_at: DateTime<Utc>,
+    watchdog: Option<JoinHandle<()>>,
+}
+
+impl ManagedProcess {
+    /// A start request that launches this process again as it runs now.
+    fn relaunch_request(&self) -> StartServiceRequest {
+        let spec = &self.spec;
+        StartServiceRequest {
+            task_type: self.task_type.clone(),
+            model_path: spec.model_path.clone(),
+            binary_path: Some(spec.command.clone()),
+            args: Some(spec.args.clone()),
+            environment: Some(spec.environment.clone()),
+            health_check: spec.health_check.clone(),
+            cpu_affinity: spec.cpu_affinity.clone().map(|cores| CpuAffinity {
+                cores: Some(cores),
+                numa_node: None,
+            }),
+            interactive: spec.interactive,
+            stdio: spec.stdio,
+            log_retention: Some(spec.log_retention),
+            node: None,
+            // `args` already carry the offload flags and adapters chosen at
+            // launch.
+            auto_gpu_layers: Some(false),
+            profile: None,
+            adapters: Vec::new(),
+            wait_for_ready: false,
+            readiness: None,
         }
+    }
 
-        let mut file = fs::File::create(path).await?;
-        let mut bytes_written: u64 = 0;
-        while let Some(chunk) = response.chunk().await? {
-            bytes_written += chunk.len() as u64;
-            file.write_all(&chunk).await?;
+    fn new(output_events: OutputForwarder, spec: LaunchSpec, child: Child) -> Self {
+        let mut logs = LogSink::new(logs::DEFAULT_LOG_CAPACITY, spec.log_retention);
+        match LogFiles::open(spec.log_dir.clone(), spec.log_retention) {
+            Ok(files) => logs = logs.with_files(files),
+            Err(err) => tracing::warn!(
+                "output of {} is not written to {}: {}",
+                output_events.instance_id,
+                spec.log_dir.display(),
+                err
+            ),
         }
+        let mut managed = Self {
+            instance_id: output_events.instance_id.clone(),
+            task_type: output_events.task_type.clone(),
+            spec,
+            child,
+            logs,
+            output_events,
+            capture: Vec::new(),
+            console: None,
+            health: ServiceHealth::Starting,
+            consecutive_failures: 0,
+            restarts: 0,
+            last_health_check: None,
+            started_at: Utc::now(),
+            watchdog: None,
+        };
+        managed.attach_output();
+        managed
+    }
 
-        Ok(bytes_written)
+    fn attach_output(&mut self) {
+        self.console = self.child.stdin.take().map(spawn_console_writer);
+        if let Some(stdout) = self.child.stdout.take() {
+            self.capture.push(logs::spawn_capture(
+                stdout,
+                LogStream::Stdout,
+                self.logs.clone(),
+                self.spec.redactor.clone(),
+                self.forwarder(self.spec.stdio.stdout),
+            ));
+        }
+        if let Some(stderr) = self.child.stderr.take() {
+            self.capture.push(logs::spawn_capture(
+                stderr,
+                LogStream::Stderr,
+                self.logs.clone(),
+                self.spec.redactor.clone(),
+                self.forwarder(self.spec.stdio.stderr),
+            ));
+        }
     }
 
-    fn build_download_url(
-        &self,
-        request: &DownloadModelRequest,
-    ) -> Result<reqwest::Url, PluginError> {
-        let mut url = reqwest::Url::parse("https://huggingface.co/")
-            .map_err(|err| PluginError::InvalidRequest(err.to_string()))?;
+    fn forwarder(&self, mode: StdioMode) -> Option<OutputForwarder> {
+        (mode == StdioMode::Events).then(|| self.output_events.clone())
+    }
 
-        {
-            let mut segments = url.path_segments_mut().map_err(|_| {
-                PluginError::InvalidRequest("cannot modify URL segments".to_string())
-            })?;
-            for segment in request.model_id.split('/') {
-                if segment.is_empty() {
-                    continue;
-                }
-                segments.push(segment);
+    /// Watches the process for an immediate exit after it has been spawned.
+    async fn wait_for_startup(&mut self) -> Result<(), PluginError> {
+        match tokio::time::timeout(STARTUP_GRACE_PERIOD, self.child.wait()).await {
+            Ok(Ok(status)) => {
+                let report = self.crash_report(status).await;
+                tracing::warn!("{} exited during startup: {}", report.command, report);
+                Err(PluginError::ProcessCrashed(Box::new(report)))
             }
-            segments.push("resolve");
-            segments.push(request.revision.as_str());
-            segments.push(request.filename.as_str());
+            Ok(Err(err)) => Err(PluginError::ProcessStart(err.to_string())),
+            Err(_) => Ok(()),
         }
-
-        url.set_query(Some("download=1"));
-        Ok(url)
     }
-}
 
-#[async_trait::async_trait]
-impl ServerPlugin for LlmServerPlugin {
-    fn metadata(&self) -> PluginMetadata {
-        self.metadata.clone()
+    fn describe(&self) -> StartServiceResponse {
+        StartServiceResponse {
+            instance_id: self.instance_id.clone(),
+            task_type: self.task_type.clone(),
+            pid: self.child.id().unwrap_or_default(),
+            command: self.spec.command.clone(),
+            args: self.spec.display_args(),
+            node: None,
+            gpu_offload: self.spec.gpu_offload.clone(),
+            fallback: Vec::new(),
+        }
     }
 
-    async fn download_model(
-        &self,
-        request: DownloadModelRequest,
-    ) -> Result<DownloadModelResponse, PluginError> {
-        if request.model_id.trim().is_empty() {
-            return Err(PluginError::InvalidRequest(
-                "model_id is required".to_string(),
-            ));
+    fn status(&self) -> ServiceStatusResponse {
+        ServiceStatusResponse {
+            instance_id: self.instance_id.clone(),
+            task_type: self.task_type.clone(),
+            pid: self.child.id(),
+            command: self.spec.command.clone(),
+            args: self.spec.display_args(),
+            environment: self
+                .spec
+                .redactor
+                .redact_environment(&self.spec.environment),
+            health: self.health,
+            consecutive_failures: self.consecutive_failures,
+            restarts: self.restarts,
+            last_health_check: self.last_health_check,
+            cpu_affinity: self.spec.cpu_affinity.clone(),
+            started_at: Some(self.started_at),
+            uptime_secs: Some((Utc::now() - self.started_at).num_seconds().max(0) as u64),
+            log_file: self
+                .logs
+                .current_file()
+                .map(|path| path.to_string_lossy().to_string()),
+            node: None,
         }
+    }
 
-        if request.filename.trim().is_empty() {
-            return Err(PluginError::InvalidRequest(
-                "filename is required".to_string(),
-            ));
+    /// Stops the watchdog and kills the child if it is still running.
+    async fn terminate(&mut self) -> Result<(), PluginError> {
+        if let Some(watchdog) = self.watchdog.take() {
+            watchdog.abort();
         }
+        if self.child.try_wait()?.is_none() {
+            self.child
+                .kill()
+                .await
+                .map_err(|err| PluginError::ProcessStart(err.to_string()))?;
+            self.child
+                .wait()
+                .await
+                .map_err(|err| PluginError::ProcessStart(err.to_string()))?;
+        }
+        Ok(())
+    }
 
-        let url = self.build_download_url(&request)?;
-        let mut builder = self.client.get(url);
-
-        if let Some(token) = &request.auth_token {
-            builder = builder.bearer_auth(token);
+    /// Kills the current child and spawns a fresh one from the same launch spec.
+    /// Captured logs are kept so the output leading up to the restart stays visible.
+    async fn restart(&mut self) -> Result<u32, PluginError> {
+        if self.child.try_wait()?.is_none() {
+            self.child.kill().await?;
+        }
+        for handle in self.capture.drain(..) {
+            handle.abort();
         }
 
-        let response = builder.send().await?.error_for_status()?;
-        let destination_dir = self.resolve_destination_dir(&request);
-        let target_path = destination_dir.join(&request.filename);
-        let bytes_written = self.store_model(&target_path, response).await?;
+        self.child = self.spec.spawn()?;
+        self.started_at = Utc::now();
+        self.attach_output();
+        self.restarts += 1;
+        self.health = ServiceHealth::Starting;
+        self.consecutive_failures = 0;
+        self.wait_for_startup().await?;
 
-        Ok(DownloadModelResponse {
-            saved_path: target_path.to_string_lossy().to_string(),
-            bytes_written,
+        self.child.id().ok_or_else(|| {
+            PluginError::ProcessStart("failed to obtain process identifier".to_string())
         })
     }
 
-    async fn start_service(
-        &self,
-        request: StartServiceRequest,
-    ) -> Result<StartServiceResponse, PluginError> {
-        if request.model_path.trim().is_empty() {
-            return Err(PluginError::InvalidRequest(
-                "model_path is required".to_string(),
-            ));
-        }
+    /// Waits until `check` passes. A process that exits meanwhile is reported
+    /// as crashed; one that is still not ready at the deadline is killed.
+    async fn wait_until_ready(
+        &mut self,
+        client: &reqwest::Client,
+        check: &ReadinessCheck,
+    ) -> Result<(), PluginError> {
+        let started = std::time::Instant::now();
+        let mut output = check
+            .watches_output()
+            .then(|| self.logs.follow(None, usize::MAX, None));
+        let mut reason = format!("waited for {}", check.describe());
+        loop {
+            if let Some(status) = self.child.try_wait()? {
+                let report = self.crash_report(status).await;
+                tracing::warn!("{} exited before it was ready: {}", report.command, report);
+                return Err(PluginError::ProcessCrashed(Box::new(report)));
+            }
+            if let Some(url) = &check.url {
+                match readiness::probe_url(client, url).await {
+                    Ok(()) => return Ok(()),
+                    Err(err) => reason = err,
+                }
+            }
 
-        let binary_path = self.resolve_binary_path(&request)?;
-        let args = request
-            .args
-            .clone()
-            .unwrap_or_else(|| Self::default_args(&request.task_type, &request.model_path));
-        let mut command = Command::new(&binary_path);
-        command.args(&args);
-        command.stdin(Stdio::null());
-        command.stdout(Stdio::inherit());
-        command.stderr(Stdio::inherit());
+            let elapsed = started.elapsed();
+            if elapsed >= check.timeout {
+                tracing::warn!("{} was not ready in time: {}", self.spec.command, reason);
+                self.terminate().await?;
+                return Err(PluginError::ServiceNotReady {
+                    reason,
+                    waited_secs: elapsed.as_secs(),
+                    stderr_tail: self.stderr_tail().await,
+                });
+            }
 
-        if let Some(env) = &request.environment {
-            for (key, value) in env {
-                command.env(key, value);
+            let pause = tokio::time::sleep(readiness::POLL_INTERVAL.min(check.timeout - elapsed));
+            tokio::pin!(pause);
+            loop {
+                let Some(follower) = output.as_mut() else {
+                    (&mut pause).await;
+                    break;
+                };
+                tokio::select! {
+                    _ = &mut pause => break,
+                    next = follower.next() => match next {
+                        Some(FollowedLog::Entry(entry)) if check.matches(&entry) => return Ok(()),
+                        Some(_) => {}
+                        None => output = None,
+                    },
+                }
             }
         }
+    }
 
-        {
-            let processes = self.processes.lock().await;
-            if processes.contains_key(&request.task_type) {
-                return Err(PluginError::ProcessAlreadyRunning(request.task_type));
+    /// Builds a crash report for an exited process once its output has been drained.
+    async fn crash_report(&mut self, status: ExitStatus) -> CrashReport {
+        let stderr_tail = self.stderr_tail().await;
+        CrashReport::new(self.spec.command_line(), status, stderr_tail)
+    }
+
+    /// The last lines on stderr of an exited process, once its output has
+    /// been drained.
+    async fn stderr_tail(&mut self) -> Vec<String> {
+        for handle in self.capture.drain(..) {
+            let _ = tokio::time::timeout(CAPTURE_DRAIN_TIMEOUT, handle).await;
+        }
+        self.logs
+            .buffer
+            .lock()
+            .map(|buffer| buffer.tail(LogStream::Stderr, CRASH_TAIL_LINES))
+            .unwrap_or_default()
+    }
+}
+
+/// Writes console input to the child's stdin until every sender is dropped or
+/// the pipe closes.
+fn spawn_console_writer(mut stdin: ChildStdin) -> mpsc::Sender<Vec<u8>> {
+    let (sender, mut receiver) = mpsc::channel::<Vec<u8>>(64);
+    tokio::spawn(async move {
+        while let Some(input) = receiver.recv().await {
+            if stdin.write_all(&input).await.is_err() || stdin.flush().await.is_err() {
+                break;
             }
         }
+    });
+    sender
+}
+
+/// Periodically probes a service's health endpoint, marking it unhealthy after
+/// repeated failures and restarting it when the policy allows.
+async fn run_watchdog(
+    plugin_id: String,
+    instance_id: String,
+    config: HealthCheckConfig,
+    processes: ProcessTable,
+    client: reqwest::Client,
+    events: EventBus,
+    faults: FaultInjector,
+) {
+    loop {
+        tokio::time::sleep(config.interval()).await;
+        faults.delay_health_check().await;
+        let outcome = health::probe(&client, &config).await;
 
-        let child = command
-            .spawn()
-            .map_err(|err| PluginError::ProcessStart(err.to_string()))?;
+        let mut guard = processes.lock().await;
+        let Some(managed) = guard.instances.get_mut(&instance_id) else {
+            return;
+        };
+        let task_type = managed.task_type.clone();
+        managed.last_health_check = Some(Utc::now());
 
-        let pid = child.id().ok_or_else(|| {
-            PluginError::ProcessStart("failed to obtain process identifier".to_string())
-        })?;
+        let outcome = match managed.child.try_wait() {
+            Ok(Some(status)) => Err(format!("process exited with {}", status)),
+            _ => outcome,
+        };
 
-        let mut processes = self.processes.lock().await;
-        processes.insert(
-            request.task_type.clone(),
-            ManagedProcess::new(
-                child,
-                binary_path.to_string_lossy().to_string(),
-                args.clone(),
-            ),
-        );
+        let reason = match outcome {
+            Ok(()) => {
+                if managed.health == ServiceHealth::Unhealthy {
+                    events.publish(
+                        &plugin_id,
+                        PluginEventKind::ServiceRecovered {
+                            instance_id: instance_id.clone(),
+                            task_type: task_type.clone(),
+                        },
+                    );
+                }
+                managed.health = ServiceHealth::Healthy;
+                managed.consecutive_failures = 0;
+                continue;
+            }
+            Err(reason) => reason,
+        };
 
-        Ok(StartServiceResponse {
-            pid,
-            command: binary_path.to_string_lossy().to_string(),
-            args,
-        })
-    }
+        managed.consecutive_failures += 1;
+        if managed.consecutive_failures < config.failure_threshold {
+            continue;
+        }
 
-    async fn stop_service(
-        &self,
-        request: StopServiceRequest,
-    ) -> Result<StopServiceResponse, PluginError> {
-        let mut processes = self.processes.lock().await;
-        let managed = processes
-            .remove(&request.task_type)
-            .ok_or_else(|| PluginError::ProcessNotRunning(request.task_type.clone()))?;
-        let mut child = managed.child;
+        if managed.health != ServiceHealth::Unhealthy {
+            tracing::warn!(
+                "{:?} service marked unhealthy after {} failed checks: {}",
+                task_type,
+                managed.consecutive_failures,
+                reason
+            );
+            managed.health = ServiceHealth::Unhealthy;
+            events.publish(
+                &plugin_id,
+                PluginEventKind::ServiceUnhealthy {
+                    instance_id: instance_id.clone(),
+                    task_type: task_type.clone(),
+                    consecutive_failures: managed.consecutive_failures,
+                    reason: reason.clone(),
+                },
+            );
+        }
 
-        if child.id().is_some() {
-            child
-                .kill()
-                .await
-                .map_err(|err| PluginError::ProcessStart(err.to_string()))?;
-            child
-                .wait()
-                .await
-                .map_err(|err| PluginError::ProcessStart(err.to_string()))?;
+        if config.restart_policy != RestartPolicy::OnUnhealthy
+            || managed.restarts >= config.max_restarts
+        {
+            continue;
         }
 
-        Ok(StopServiceResponse {
-            task_type: request.task_type,
-            terminated: true,
-        })
+        match managed.restart().await {
+            Ok(pid) => events.publish(
+                &plugin_id,
+                PluginEventKind::ServiceRestarted {
+                    instance_id: instance_id.clone(),
+                    task_type: task_type.clone(),
+                    pid,
+                    restarts: managed.restarts,
+                },
+            ),
+            Err(err) => {
+                tracing::error!("failed to restart {:?} service: {}", task_type, err);
+                managed.health = ServiceHealth::Unhealthy;
+                let crash = match err {
+                    PluginError::ProcessCrashed(report) => Some(*report),
+                    _ => None,
+                };
+                events.publish(
+                    &plugin_id,
+                    PluginEventKind::ServiceRestartFailed {
+                        instance_id: instance_id.clone(),
+                        task_type: task_type.clone(),
+                        reason,
+                        crash,
+                    },
+                );
+            }
+        }
     }
 }
 
+/// Settings that can be changed through the plugin config store. Unset keys
+/// fall back to the environment read at bootstrap.
+#[derive(Debug, Clone, Default, Deserialize, schemars::JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct LlmServerConfig {
+    /// Binary started when a request names none; overrides
+    /// `GOOSE_PLUGIN_LLM_BINARY`.
+    #[serde(default)]
+    binary: Option<PathBuf>,
+    /// Retention for services started without one; overrides the
+    /// `GOOSE_LOG_RETENTION_*` variables.
+    #[serde(default)]
+    log_retention: Option<LogRetention>,
+    /// Connections large downloads are split across; overrides
+    /// `GOOSE_PLUGIN_LLM_DOWNLOAD_CONNECTIONS`. `1` disables chunking.
+    #[serde(default)]
+    download_connections: Option<usize>,
+    /// Connections of accelerated downloads; overrides
+    /// `GOOSE_PLUGIN_LLM_ACCELERATED_CONNECTIONS`.
+    #[serde(default)]
+    accelerated_connections: Option<usize>,
+    /// Bandwidth shared by all downloads; overrides
+    /// `GOOSE_PLUGIN_LLM_DOWNLOAD_BYTES_PER_SEC`. `0` means unlimited.
+    #[serde(default)]
+    max_download_bytes_per_sec: Option<u64>,
+    /// Maximum bytes per task type model directory; overrides
+    /// `GOOSE_PLUGIN_LLM_MODEL_DIR_QUOTAS`.
+    #[serde(default)]
+    model_dir_quotas: Option<HashMap<String, u64>>,
+    /// Hub endpoints tried in order; overrides
+    /// `GOOSE_PLUGIN_LLM_HUB_ENDPOINTS`.
+    #[serde(default)]
+    hub_endpoints: Option<Vec<String>>,
+    /// Whether downloads over a model directory quota evict the least
+    /// recently used unpinned models; overrides `GOOSE_PLUGIN_LLM_EVICT_LRU`.
+    #[serde(default)]
+    evict_lru: Option<bool>,
+    /// Proxy downloads go through; overrides `GOOSE_PLUGIN_LLM_PROXY`.
+    #[serde(default)]
+    proxy: Option<ProxyConfig>,
+    /// Converter turning downloaded models into GGUF; overrides
+    /// `GOOSE_PLUGIN_LLM_CONVERTER`.
+    #[serde(default)]
+    converter: Option<PathBuf>,
+    /// Quantizer of downloaded models; overrides
+    /// `GOOSE_PLUGIN_LLM_QUANTIZER`.
+    #[serde(default)]
+    quantizer: Option<PathBuf>,
+    /// Peers hub downloads are first pulled from; overrides
+    /// `GOOSE_PLUGIN_LLM_PEERS`.
+    #[serde(default)]
+    peers: Option<Vec<PeerSource>>,
+}
+
+/// Services detached from an instance of the plugin that is being reloaded.
+struct LlmServerHandover {
+    instances: HashMap<String, ManagedProcess>,
+    remote: HashMap<String, RemoteInstance>,
+}
+
+/// What the plugin keeps across server restarts: the local services it was
+/// running, restarted when the state is restored. Services on remote nodes
+/// are not kept.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct LlmServerState {
+    #[serde(default)]
+    services: Vec<StartServiceRequest>,
+}
+
+#[derive(Clone)]
+pub struct LlmServerPlugin {
+    metadata: PluginMetadata,
+    base_dir: PathBuf,
+    sandbox: PathSandbox,
+    default_binary: Option<PathBuf>,
+    converter: Option<PathBuf>,
+    quantizer: Option<PathBuf>,
+    download_connections: usize,
+    accelerated_connections: usize,
+    download_bytes_per_sec: Option<u64>,
+    /// Paces all downloads to the global bandwidth limit.
+    download_throttle: Arc<Throttle>,
+    model_dir_quotas: HashMap<String, u64>,
+    evict_lru: bool,
+    hub_endpoints: HubEndpoints,
+    peers: Vec<PeerSource>,
+    /// For local services, such as health checks.
+    client: reqwest::Client,
+    proxy: Option<ProxyConfig>,
+    /// For the hub and object storage, through the configured proxy.
+    download_client: Arc<std::sync::RwLock<reqwest::Client>>,
+    breakers: CircuitBreakers,
+    offline: OfflineMode,
+    /// Last revision listings per model and revision, served while offline.
+    revision_cache: Arc<Mutex<HashMap<(String, String), ModelRevisionsResponse>>>,
+    processes: ProcessTable,
+    events: EventBus,
+    log_retention: LogRetention,
+    config: Arc<std::sync::RwLock<LlmServerConfig>>,
+    manifest: Arc<Mutex<ModelManifest>>,
+    lock: Arc<Mutex<ModelLock>>,
+    nodes: HashMap<String, RemoteNode>,
+    remote: Arc<Mutex<HashMap<String, RemoteInstance>>>,
+    profiles: Vec<HardwareProfile>,
+    gc_policy: GcPolicy,
+    encryption: ModelEncryption,
+    /// Keys downloaded models may be signed with.
+    model_keys: Arc<TrustRoot>,
+    /// Every local download is checked against its default signature.
+    require_signatures: bool,
+    /// What the last integrity scan of the model directories found.
+    integrity: Arc<Mutex<ModelHealthResponse>>,
+    /// Update checks, garbage collection and the model directory watcher,
+    /// stopped on shutdown.
+    background: Arc<Vec<JoinHandle<()>>>,
+    faults: FaultInjector,
+    downloads: Arc<DownloadCounters>,
+}
+
 impl LlmServerPlugin {
-    pub fn base_dir(&self) -> &Path {
-        &self.base_dir
+    pub fn default_metadata() -> PluginMetadata {
+        PluginMetadata {
+            id: "llmserver-rs".to_string(),
+            name: "llmserver-rs".to_string(),
+            description: "Manage llmserver-rs instances and download models".to_string(),
+            capabilities: vec![
+                PluginCapability::ModelDownload,
+                PluginCapability::ServiceStart,
+                PluginCapability::ServiceStop,
+                PluginCapability::ServiceLogs,
+                PluginCapability::ServiceStatus,
+                PluginCapability::ServiceUpgrade,
+                PluginCapability::ServiceSignal,
+                PluginCapability::ServiceConsole,
+                PluginCapability::ModelList,
+                PluginCapability::ModelUpdateCheck,
+                PluginCapability::ModelRevisions,
+                PluginCapability::ModelSearch,
+                PluginCapability::RemoteNodes,
+                PluginCapability::HardwareProfiles,
+                PluginCapability::ModelGc,
+                PluginCapability::ModelDelete,
+                PluginCapability::ModelImport,
+                PluginCapability::ModelPin,
+                PluginCapability::ModelAdapters,
+                PluginCapability::ModelConvert,
+                PluginCapability::ModelQuantize,
+                PluginCapability::ModelShare,
+                PluginCapability::ModelHealth,
+                PluginCapability::HealthCheck,
+                PluginCapability::TokenValidation,
+                PluginCapability::Metrics,
+            ],
+            enabled: true,
+            dependencies: Vec::new(),
+            api_version: PLUGIN_API_VERSION,
+            subscriptions: Vec::new(),
+            signature: None,
+        }
     }
-}
 
-pub type SharedLlmServerPlugin = Arc<LlmServerPlugin>;
+    pub async fn bootstrap(
+        metadata: PluginMetadata,
+        events: EventBus,
+        offline: OfflineMode,
+    ) -> anyhow::Result<Self> {
+        let base_dir = match std::env::var("GOOSE_PLUGIN_LLM_BASE_DIR") {
+            Ok(value) => PathBuf::from(value),
+            Err(_) => std::env::current_dir()?.join("plugins").join("llmserver"),
+        };
+        Self::bootstrap_in(metadata, base_dir, events, offline).await
+    }
+
+    /// Bootstraps the plugin with its models, logs and state kept under
+    /// `base_dir`.
+    async fn bootstrap_in(
+        metadata: PluginMetadata,
+        base_dir: PathBuf,
+        events: EventBus,
+        offline: OfflineMode,
+    ) -> anyhow::Result<Self> {
+        fs::create_dir_all(&base_dir).await?;
+        fs::create_dir_all(base_dir.join("text")).await?;
+        fs::create_dir_all(base_dir.join("tts")).await?;
+
+        let mut allowed_roots = vec![base_dir.clone()];
+        if let Some(extra) = std::env::var_os("GOOSE_PLUGIN_LLM_ALLOWED_DIRS") {
+            allowed_roots
+                .extend(std::env::split_paths(&extra).filter(|p| !p.as_os_str().is_empty()));
+        }
+        let sandbox = PathSandbox::new(allowed_roots)?;
+
+        let default_binary = std::env::var("GOOSE_PLUGIN_LLM_BINARY")
+            .ok()
+            .map(PathBuf::from);
+        let download_connections = std::env::var("GOOSE_PLUGIN_LLM_DOWNLOAD_CONNECTIONS")
+            .ok()
+            .and_then(|value| value.trim().parse::<usize>().ok())
+            .unwrap_or(DEFAULT_DOWNLOAD_CONNECTIONS);
+        let accelerated_connections = std::env::var("GOOSE_PLUGIN_LLM_ACCELERATED_CONNECTIONS")
+            .ok()
+            .and_then(|value| value.trim().parse::<usize>().ok())
+            .unwrap_or(DEFAULT_ACCELERATED_CONNECTIONS);
+        let download_bytes_per_sec = std::env::var("GOOSE_PLUGIN_LLM_DOWNLOAD_BYTES_PER_SEC")
+            .ok()
+            .and_then(|value| value.trim().parse::<u64>().ok());
+        let evict_lru = std::env::var("GOOSE_PLUGIN_LLM_EVICT_LRU")
+            .map(|value| matches!(value.trim(), "1" | "true" | "yes"))
+            .unwrap_or(false);
+
+        let client = reqwest::Client::builder().user_agent(USER_AGENT).build()?;
+        let proxy = ProxyConfig::from_env();
+        let hub_client = download_client(proxy.as_ref())?;
+
+        let hub_endpoints = HubEndpoints::from_env()?;
+        let breakers = CircuitBreakers::default();
+        let manifest = Arc::new(Mutex::new(ModelManifest::load(&base_dir).await?));
+        let lock = Arc::new(Mutex::new(ModelLock::load(&base_dir).await?));
+        let mut background = Vec::new();
+        if let Some(interval) = revisions::check_interval() {
+            background.push(revisions::spawn_update_checks(
+                metadata.id.clone(),
+                hub_client.clone(),
+                hub_endpoints.clone(),
+                breakers.clone(),
+                offline.clone(),
+                manifest.clone(),
+                events.clone(),
+                interval,
+            ));
+        }
+
+        let encryption = ModelEncryption::from_env(&base_dir)?;
+        let processes: ProcessTable = Arc::default();
+        let gc_policy = GcPolicy::default();
+        if let Some(interval) = gc_policy.interval {
+            background.push(spawn_model_gc(
+                manifest.clone(),
+                processes.clone(),
+                gc_policy.min_idle,
+                interval,
+            ));
+        }
+        let integrity = Arc::new(Mutex::new(ModelHealthResponse::default()));
+        let locked = lock.lock().await.models().to_vec();
+        let scan = IntegrityScan::new(
+            &base_dir,
+            &[encryption.runtime_dir().to_path_buf()],
+            &sandbox,
+            locked,
+            manifest.lock().await.records(),
+            integrity::verify_from_env(),
+        )?;
+        background.push(integrity::spawn_scan(scan, integrity.clone()));
+        if watcher::enabled_from_env() {
+            let index = watcher::ModelIndex {
+                plugin_id: metadata.id.clone(),
+                base_dir: base_dir.canonicalize()?,
+                excluded: vec![encryption
+                    .runtime_dir()
+                    .canonicalize()
+                    .unwrap_or_else(|_| encryption.runtime_dir().to_path_buf())],
+                manifest: manifest.clone(),
+                events: events.clone(),
+            };
+            match watcher::spawn(index) {
+                Ok(task) => background.push(task),
+                Err(err) => {
+                    tracing::warn!("cannot watch {} for models: {}", base_dir.display(), err)
+                }
+            }
+        }
+
+        Ok(Self {
+            metadata,
+            base_dir,
+            sandbox,
+            default_binary,
+            converter: convert::converter_from_env(),
+            quantizer: quantize::quantizer_from_env(),
+            download_connections,
+            accelerated_connections,
+            download_bytes_per_sec,
+            download_throttle: Arc::default(),
+            model_dir_quotas: usage::quotas_from_env()?,
+            evict_lru,
+            hub_endpoints,
+            peers: peers::peers_from_env(),
+            client,
+            proxy,
+            download_client: Arc::new(std::sync::RwLock::new(hub_client)),
+            breakers,
+            offline,
+            revision_cache: Arc::default(),
+            processes,
+            events,
+            log_retention: LogRetention::from_env(),
+            config: Arc::default(),
+            manifest,
+            lock,
+            nodes: RemoteNode::from_env()?,
+            remote: Arc::default(),
+            profiles: HardwareProfile::from_env()?,
+            encryption,
+            model_keys: Arc::new(TrustRoot::load(&provenance::trusted_keys_path()).await?),
+            require_signatures: provenance::required_from_env(),
+            integrity,
+            gc_policy,
+            background: Arc::new(background),
+            faults: FaultInjector::default(),
+            downloads: Arc::default(),
+        })
+    }
+
+    /// Injects the failures in `plan` into later operations. For tests only.
+    #[allow(dead_code)] // goosed itself never injects faults
+    pub fn with_faults(mut self, plan: FaultPlan) -> Self {
+        self.faults = FaultInjector::new(plan);
+        self
+    }
+
+    /// The directory models of `task_type` are kept in, unless the request
+    /// names another.
+    fn resolve_destination_dir(
+        &self,
+        task_type: &PluginTaskType,
+        destination_dir: Option<&str>,
+    ) -> Result<PathBuf, PluginError> {
+        let dir = match destination_dir {
+            Some(dir) => PathBuf::from(dir),
+            None => self.base_dir.join(task_type.as_str()),
+        };
+        self.sandbox.resolve_dir(&dir)
+    }
+
+    /// Scans the model directories again, keeping the result for
+    /// [`ServerPlugin::model_health`].
+    async fn scan_integrity(&self) -> Result<ModelHealthResponse, PluginError> {
+        let locked = self.lock.lock().await.models().to_vec();
+        let scan = IntegrityScan::new(
+            &self.base_dir,
+            &[self.encryption.runtime_dir().to_path_buf()],
+            &self.sandbox,
+            locked,
+            self.manifest.lock().await.records(),
+            integrity::verify_from_env(),
+        )?;
+        let health = tokio::task::spawn_blocking(move || scan.run())
+            .await
+            .map_err(|err| PluginError::Internal(err.to_string()))??;
+        *self.integrity.lock().await = health.clone();
+        Ok(health)
+    }
+
+    fn config(&self) -> LlmServerConfig {
+        self.config.read().expect("plugin config").clone()
+    }
+
+    fn download_connections(&self) -> usize {
+        self.config()
+            .download_connections
+            .unwrap_or(self.download_connections)
+    }
+
+    fn accelerated_connections(&self) -> usize {
+        self.config()
+            .accelerated_connections
+            .unwrap_or(self.accelerated_connections)
+            .max(1)
+    }
+
+    fn converter(&self) -> Option<PathBuf> {
+        self.config().converter.or_else(|| self.converter.clone())
+    }
+
+    fn peers(&self) -> Vec<PeerSource> {
+        self.config().peers.unwrap_or_else(|| self.peers.clone())
+    }
+
+    fn quantizer(&self) -> Option<PathBuf> {
+        self.config().quantizer.or_else(|| self.quantizer.clone())
+    }
+
+    fn model_dir_quotas(&self) -> HashMap<String, u64> {
+        self.config()
+            .model_dir_quotas
+            .unwrap_or_else(|| self.model_dir_quotas.clone())
+    }
+
+    fn evict_lru(&self) -> bool {
+        self.config().evict_lru.unwrap_or(self.evict_lru)
+    }
+
+    /// A client through the proxy a request names, else the configured one.
+    fn hub_client(&self, requested: Option<&ProxyConfig>) -> Result<reqwest::Client, PluginError> {
+        match requested {
+            Some(proxy) => download_client(Some(proxy)),
+            None => Ok(self
+                .download_client
+                .read()
+                .expect("download client")
+                .clone()),
+        }
+    }
+
+    /// The endpoints a request names, else the configured ones.
+    fn hub_endpoints(&self, requested: &[String]) -> Result<HubEndpoints, PluginError> {
+        if !requested.is_empty() {
+            return HubEndpoints::parse(requested);
+        }
+        match self.config().hub_endpoints {
+            Some(endpoints) => HubEndpoints::parse(&endpoints),
+            None => Ok(self.hub_endpoints.clone()),
+        }
+    }
+
+    fn resolve_binary_path(&self, request: &StartServiceRequest) -> Result<PathBuf, PluginError> {
+        if let Some(explicit) = &request.binary_path {
+            return Ok(PathBuf::from(explicit));
+        }
+
+        if let Some(default) = self.config().binary.or_else(|| self.default_binary.clone()) {
+            return Ok(default);
+        }
+
+        Err(PluginError::BinaryMissing(
+            "binary_path not provided and no default binary configured".to_string(),
+        ))
+    }
+
+    /// The request's arguments, or the defaults, completed by its profile.
+    fn launch_args(&self, request: &StartServiceRequest) -> Result<Vec<String>, PluginError> {
+        let mut args = request
+            .args
+            .clone()
+            .unwrap_or_else(|| Self::default_args(&request.task_type, &request.model_path));
+        if let Some(id) = &request.profile {
+            let profile = self
+                .profiles
+                .iter()
+                .find(|profile| profile.id == *id)
+                .ok_or_else(|| {
+                    PluginError::InvalidRequest(format!("unknown hardware profile {}", id))
+                })?;
+            profile.apply(&mut args);
+        }
+        Ok(args)
+    }
+
+    fn default_args(task: &PluginTaskType, model_path: &str) -> Vec<String> {
+        vec![
+            "serve".to_string(),
+            "--model".to_string(),
+            model_path.to_string(),
+            "--task".to_string(),
+            task.to_string(),
+        ]
+    }
+
+    fn node(&self, node_id: &str) -> Result<&RemoteNode, PluginError> {
+        self.nodes
+            .get(node_id)
+            .ok_or_else(|| PluginError::NotFound(format!("remote node {}", node_id)))
+    }
+
+    /// The remote instance a selector names, if it names one by id.
+    async fn remote_instance(&self, selector: &ServiceSelector) -> Option<RemoteInstance> {
+        let instance_id = selector.instance_id.as_ref()?;
+        self.remote.lock().await.get(instance_id).cloned()
+    }
+
+    async fn download_remote(
+        &self,
+        node_id: &str,
+        request: &DownloadModelRequest,
+    ) -> Result<DownloadModelResponse, PluginError> {
+        let node = self.node(node_id)?;
+        if request.filename.contains('/') || request.filename == ".." {
+            return Err(PluginError::InvalidRequest(format!(
+                "invalid filename {}",
+                request.filename
+            )));
+        }
+        let dir = request
+            .destination_dir
+            .clone()
+            .unwrap_or_else(|| node.model_dir(&request.task_type));
+        let path = format!("{}/{}", dir.trim_end_matches('/'), request.filename);
+
+        // The node fetches the file itself, from the first endpoint only.
+        let hubs = self.hub_endpoints(&request.hub_endpoints)?;
+        let url = self.build_download_url(request, hubs.primary())?;
+        // Dropping the transfer kills the ssh session and with it the
+        // remote curl.
+        let bytes_written = tokio::select! {
+            bytes = node.download(url.as_str(), request.auth_token.as_deref(), &path) => bytes?,
+            _ = request.cancel.cancelled() => return Err(PluginError::Cancelled),
+        };
+        Ok(DownloadModelResponse {
+            saved_path: path,
+            bytes_written,
+            fallback: Vec::new(),
+            sidecars: Vec::new(),
+        })
+    }
+
+    /// Downloads an object from its store or a file from the peer named,
+    /// and a hub file from the first configured peer that has it, else from
+    /// each hub endpoint in turn until one serves it.
+    async fn download_local(
+        &self,
+        request: DownloadModelRequest,
+    ) -> Result<DownloadModelResponse, PluginError> {
+        match &request.source {
+            ModelSource::Hub => {}
+            ModelSource::Object(source) => {
+                let location = source.resolve()?;
+                return self.download_from(request, &location, None).await;
+            }
+            ModelSource::Peer(peer) => {
+                let location = self.peer_location(peer, &request)?;
+                return self.download_from(request, &location, None).await;
+            }
+        }
+        // A peer serves no signatures, so a file checked against its
+        // default one is fetched from the hub.
+        let peers = match &request.signature {
+            Some(ModelSignature { url: None }) => Vec::new(),
+            _ => self.peers(),
+        };
+        for peer in peers {
+            let location = match self.peer_location(&peer, &request) {
+                Ok(location) => location,
+                Err(err) => {
+                    tracing::warn!("skipping peer {}: {}", peer.url, err);
+                    continue;
+                }
+            };
+            match self.download_from(request.clone(), &location, None).await {
+                Err(PluginError::Cancelled) => return Err(PluginError::Cancelled),
+                Err(err) => tracing::debug!(
+                    "{} not pulled from peer {}: {}",
+                    request.filename,
+                    peer.url,
+                    err
+                ),
+                result => return result,
+            }
+        }
+        let hubs = self.hub_endpoints(&request.hub_endpoints)?;
+        let mut hubs = hubs.iter().peekable();
+        loop {
+            let hub = hubs.next().expect("hub endpoints are never empty");
+            let url = self.build_download_url(&request, hub)?;
+            let location = ObjectLocation::hub(url, request.auth_token.clone());
+            match self
+                .download_from(request.clone(), &location, Some(hub))
+                .await
+            {
+                Err(err) if mirrors::fails_over(&err) && hubs.peek().is_some() => {
+                    tracing::warn!("downloading from {} failed, trying the next: {}", hub, err);
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Rejects signed downloads whose files cannot be checked.
+    fn check_signature_request(
+        &self,
+        request: &DownloadModelRequest,
+        signature: &ModelSignature,
+    ) -> Result<(), PluginError> {
+        if request.node.is_some() {
+            return Err(PluginError::InvalidRequest(
+                "signatures are only checked for local downloads".to_string(),
+            ));
+        }
+        if self.encryption.is_enabled() {
+            return Err(PluginError::InvalidRequest(
+                "signatures cannot be checked on models encrypted at rest".to_string(),
+            ));
+        }
+        match (&signature.url, &request.source) {
+            (Some(url), source) => {
+                parse_signature_url(url)?;
+                let split = matches!(source, ModelSource::Hub)
+                    && SplitName::parse(&request.filename).is_some();
+                if request.mode != DownloadMode::File || request.sidecars || split {
+                    return Err(PluginError::InvalidRequest(
+                        "a signature URL only covers a single file; leave it out to check \
+                         every file against its own signature"
+                            .to_string(),
+                    ));
+                }
+            }
+            (None, ModelSource::Peer(_)) => {
+                return Err(PluginError::InvalidRequest(
+                    "peers serve no signatures, so files pulled from one need a signature URL"
+                        .to_string(),
+                ));
+            }
+            (None, _) => {}
+        }
+        if self.model_keys.is_empty() {
+            return Err(PluginError::VerificationFailed(format!(
+                "no trusted model keys in {}",
+                provenance::trusted_keys_path().display()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Where `peer` shares the requested file.
+    fn peer_location(
+        &self,
+        peer: &PeerSource,
+        request: &DownloadModelRequest,
+    ) -> Result<ObjectLocation, PluginError> {
+        peer.locate(
+            &self.metadata.id,
+            &request.model_id,
+            &request.revision,
+            &request.filename,
+        )
+    }
+
+    /// Downloads `location` as the requested file. Files of a `hub` are
+    /// checked against the SHA-256 it lists for them, and files of a peer
+    /// against the one it sends.
+    async fn download_from(
+        &self,
+        request: DownloadModelRequest,
+        location: &ObjectLocation,
+        hub: Option<&reqwest::Url>,
+    ) -> Result<DownloadModelResponse, PluginError> {
+        let destination_dir =
+            self.resolve_destination_dir(&request.task_type, request.destination_dir.as_deref())?;
+        let target_path = self
+            .sandbox
+            .join_file(&destination_dir, &request.filename)?;
+
+        let host = mirrors::host(&location.url).to_string();
+        // Download next to the target so a failed verification never
+        // replaces a good copy.
+        let mut partial_name = target_path.file_name().unwrap_or_default().to_os_string();
+        partial_name.push(".part");
+        let partial_path = target_path.with_file_name(partial_name);
+        let validator_path = validator_path(&partial_path);
+        let expected_sha256 = match (&request.expected_sha256, hub) {
+            (Some(expected), _) => Some(expected.to_ascii_lowercase()),
+            (None, Some(hub)) => self.listed_sha256(&request, hub).await,
+            (None, None) if location.is_peer() => Some(self.peer_sha256(location, &request).await?),
+            (None, None) => None,
+        };
+        let size = self.download_size(location, &request).await?;
+        self.check_model_dir_quota(&request.task_type, &target_path, &partial_path, size)
+            .await?;
+        if let Some(size) = size {
+            let required = size.saturating_sub(self.resumable_length(&partial_path).await);
+            check_disk_space(&partial_path, required)?;
+        }
+        let throttle = DownloadThrottle::new(
+            &self.download_throttle,
+            self.config()
+                .max_download_bytes_per_sec
+                .or(self.download_bytes_per_sec),
+            request.max_bytes_per_sec,
+        );
+        let client = self.hub_client(request.proxy.as_ref())?;
+        let transfer = async {
+            // What a dropped connection left behind is kept and continued
+            // when the host still serves the same file in byte ranges.
+            let mut offset = self.resumable_length(&partial_path).await;
+            let validator = match offset {
+                0 => None,
+                _ => fs::read_to_string(&validator_path).await.ok(),
+            };
+            let (cancel, model_id) = (&request.cancel, &request.model_id);
+            let send = |range: Option<String>| {
+                let mut builder = location.request(&client, reqwest::Method::GET);
+                let ranged = range.is_some();
+                if let Some(range) = range {
+                    builder = builder.header(reqwest::header::RANGE, range);
+                    if let Some(validator) = &validator {
+                        builder = builder.header(reqwest::header::IF_RANGE, validator.as_str());
+                    }
+                }
+                async move {
+                    let response = tokio::select! {
+                        response = builder.send() => response?,
+                        _ = cancel.cancelled() => return Err(PluginError::Cancelled),
+                    };
+                    // Answers a resume of a file that is already complete.
+                    if ranged && response.status() == reqwest::StatusCode::RANGE_NOT_SATISFIABLE {
+                        return Ok(response);
+                    }
+                    match hub {
+                        Some(hub) => gated::check(response, hub, model_id).await,
+                        None => Ok(response.error_for_status()?),
+                    }
+                }
+            };
+
+            let mut response = send((offset > 0).then(|| format!("bytes={}-", offset))).await?;
+            let served_commit = response
+                .headers()
+                .get(REPO_COMMIT_HEADER)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string);
+            if offset > 0 && complete_length(&response) == Some(offset) {
+                let mut hasher = Sha256::new();
+                hash_prefix(&partial_path, offset, &mut hasher).await?;
+                request.progress.start(Some(offset));
+                request.progress.advance(offset);
+                request.progress.finish();
+                return Ok((served_commit, offset, format!("{:x}", hasher.finalize())));
+            }
+            if offset > 0 && !resumes_at(&response, offset) {
+                tracing::info!(
+                    "{} does not serve {} in byte ranges, downloading it again",
+                    host,
+                    request.filename
+                );
+                offset = 0;
+                if response.status() != reqwest::StatusCode::OK {
+                    response = send(None).await?;
+                }
+            }
+            if offset == 0 {
+                let _ = fs::remove_file(&validator_path).await;
+                if let Some(etag) = strong_etag(response.headers()) {
+                    self.prepare_parent(&validator_path).await?;
+                    fs::write(&validator_path, etag).await?;
+                }
+            }
+            let (bytes_written, sha256) = match self.chunk_plan(&response, offset, &request) {
+                Some((total, plan)) => {
+                    drop(response);
+                    self.store_model_chunked(&partial_path, total, plan, &send, &request, &throttle)
+                        .await?
+                }
+                None => {
+                    self.store_model(&partial_path, offset, response, &request, &throttle)
+                        .await?
+                }
+            };
+            Ok((served_commit, bytes_written, sha256))
+        };
+        let (served_commit, bytes_written, sha256) = self.breakers.run(&host, transfer).await?;
+        if let Some(expected) = expected_sha256.filter(|expected| *expected != sha256) {
+            let _ = fs::remove_file(&partial_path).await;
+            return Err(PluginError::ChecksumMismatch {
+                filename: request.filename,
+                expected,
+                actual: sha256,
+            });
+        }
+        let saved_path = target_path.to_string_lossy().to_string();
+        if let Err(err) = self
+            .verify_pinned_download(&request, &saved_path, served_commit.as_deref(), &sha256)
+            .await
+        {
+            let _ = fs::remove_file(&partial_path).await;
+            return Err(err);
+        }
+        let provenance = match &request.signature {
+            Some(signature) => {
+                match self
+                    .verify_signature(signature, location, &partial_path, &client)
+                    .await
+                {
+                    Ok(provenance) => Some(provenance),
+                    Err(err) => {
+                        let _ = fs::remove_file(&partial_path).await;
+                        return Err(err);
+                    }
+                }
+            }
+            None => None,
+        };
+        fs::rename(&partial_path, &target_path).await?;
+        let _ = fs::remove_file(&validator_path).await;
+
+        // A pinned download is recorded against its pin even when the host
+        // did not report the commit.
+        let commit = served_commit.or_else(|| {
+            revisions::is_commit_sha(&request.revision).then(|| request.revision.clone())
+        });
+        let mut manifest = self.manifest.lock().await;
+        let previous = manifest.find(&saved_path);
+        let last_used = previous.and_then(|record| record.last_used);
+        let pinned = previous.is_some_and(|record| record.pinned);
+        // Files pulled from a peer are tracked as the hub files they are.
+        let source_url = match &request.source {
+            ModelSource::Hub | ModelSource::Peer(_) => None,
+            ModelSource::Object(source) => Some(source.url.clone()),
+        };
+        let locked = match (&source_url, &commit) {
+            (None, Some(commit)) => Some(LockedModel {
+                model_id: request.model_id.clone(),
+                revision: commit.clone(),
+                filename: request.filename.clone(),
+                sha256: sha256.clone(),
+                task_type: request.task_type.clone(),
+                destination_dir: request.destination_dir.clone(),
+            }),
+            _ => None,
+        };
+        manifest.upsert(ModelRecord {
+            model_id: request.model_id,
+            filename: request.filename,
+            revision: request.revision,
+            commit,
+            task_type: request.task_type,
+            saved_path: saved_path.clone(),
+            bytes: bytes_written,
+            sha256: Some(sha256),
+            downloaded_at: Utc::now(),
+            last_checked: None,
+            update: None,
+            last_used,
+            encrypted: self.encryption.is_enabled(),
+            pinned,
+            source_url,
+            provenance,
+        });
+        manifest.save().await?;
+        drop(manifest);
+        match locked {
+            Some(locked) => {
+                let mut lock = self.lock.lock().await;
+                lock.record(locked);
+                lock.save().await?;
+            }
+            None if hub.is_some() => {
+                tracing::warn!("{} not locked: the host reported no commit", saved_path)
+            }
+            None => {}
+        }
+
+        Ok(DownloadModelResponse {
+            saved_path,
+            bytes_written,
+            fallback: Vec::new(),
+            sidecars: Vec::new(),
+        })
+    }
+
+    /// Checks the file downloaded from `location` to `path` against the
+    /// detached signature `signature` names, by default the one next to it.
+    async fn verify_signature(
+        &self,
+        signature: &ModelSignature,
+        location: &ObjectLocation,
+        path: &Path,
+        client: &reqwest::Client,
+    ) -> Result<ModelProvenance, PluginError> {
+        let signature_location = match &signature.url {
+            Some(url) => ObjectLocation::hub(parse_signature_url(url)?, None),
+            None => location.signature(),
+        };
+        // A peer's query names the file and carries no credentials.
+        let source_url = if location.is_peer() {
+            location.url.to_string()
+        } else {
+            provenance::public_url(&location.url)
+        };
+        let signature_url = provenance::public_url(&signature_location.url);
+        let response = signature_location
+            .request(client, reqwest::Method::GET)
+            .send()
+            .await?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Err(PluginError::VerificationFailed(format!(
+                "{} is not signed; {} does not exist",
+                source_url, signature_url
+            )));
+        }
+        let text = response.error_for_status()?.text().await?;
+        let keys = self.model_keys.clone();
+        let file = path.to_path_buf();
+        let signer = tokio::task::spawn_blocking(move || keys.verify_file(&file, &text))
+            .await
+            .map_err(|err| PluginError::Internal(err.to_string()))?
+            .map_err(|err| PluginError::VerificationFailed(format!("{}: {}", source_url, err)))?;
+        Ok(ModelProvenance {
+            source_url,
+            signature_url,
+            key_id: signer.key_id,
+            trusted_comment: signer.trusted_comment,
+            verified_at: Utc::now(),
+        })
+    }
+
+    /// Downloads the files of a repository the request selects one after
+    /// another, each as a download of its own into the repository's
+    /// directory. Progress covers all of them.
+    async fn download_snapshot(
+        &self,
+        request: DownloadModelRequest,
+    ) -> Result<DownloadModelResponse, PluginError> {
+        let filter = SnapshotFilter::new(&request.include, &request.exclude)?;
+        let destination_dir =
+            self.resolve_destination_dir(&request.task_type, request.destination_dir.as_deref())?;
+        let repo_dir = self
+            .sandbox
+            .join_file(&destination_dir, &request.model_id)?;
+        let hubs = self.hub_endpoints(&request.hub_endpoints)?;
+        let client = &self.hub_client(request.proxy.as_ref())?;
+        let (model_id, revision) = (&request.model_id, &request.revision);
+        let files: Vec<_> = mirrors::failover(&hubs, &self.breakers, |hub| async move {
+            revisions::list_files(client, &hub, model_id, revision).await
+        })
+        .await?
+        .into_iter()
+        .filter(|file| filter.matches(&file.path))
+        .collect();
+        if files.is_empty() {
+            return Err(PluginError::NotFound(format!(
+                "no files of {} at {} match the patterns",
+                request.model_id, request.revision
+            )));
+        }
+
+        let total = files.iter().map(|file| file.size).sum();
+        let requests = files
+            .into_iter()
+            .map(|file| DownloadModelRequest {
+                filename: file.path,
+                destination_dir: Some(repo_dir.to_string_lossy().to_string()),
+                expected_sha256: file.sha256,
+                mode: DownloadMode::File,
+                include: Vec::new(),
+                exclude: Vec::new(),
+                ..request.clone()
+            })
+            .collect();
+        let downloaded = self.download_each(&request, requests, Some(total)).await?;
+
+        Ok(DownloadModelResponse {
+            saved_path: repo_dir.to_string_lossy().to_string(),
+            bytes_written: downloaded.iter().map(|file| file.bytes_written).sum(),
+            fallback: Vec::new(),
+            sidecars: Vec::new(),
+        })
+    }
+
+    /// Downloads every part of a split model, then concatenates them if
+    /// the request asks to. Kept parts are reported by the first one, which
+    /// services are started from.
+    async fn download_split(
+        &self,
+        request: DownloadModelRequest,
+        split: SplitName,
+    ) -> Result<DownloadModelResponse, PluginError> {
+        if request.expected_sha256.is_some() {
+            return Err(PluginError::InvalidRequest(
+                "expected_sha256 cannot be checked against the parts of a split model".to_string(),
+            ));
+        }
+        let concatenate = request.gguf_splits == GgufSplits::Concatenate;
+        if concatenate && self.encryption.is_enabled() {
+            return Err(PluginError::InvalidRequest(
+                "encrypted split models cannot be concatenated".to_string(),
+            ));
+        }
+
+        let requests = split
+            .parts()
+            .into_iter()
+            .map(|filename| DownloadModelRequest {
+                filename,
+                ..request.clone()
+            })
+            .collect();
+        let downloaded = self.download_each(&request, requests, None).await?;
+        if concatenate {
+            let merged = DownloadModelRequest {
+                filename: split.merged(),
+                ..request
+            };
+            return self.concatenate_parts(&merged, &downloaded).await;
+        }
+        Ok(DownloadModelResponse {
+            saved_path: downloaded[0].saved_path.clone(),
+            bytes_written: downloaded.iter().map(|part| part.bytes_written).sum(),
+            fallback: Vec::new(),
+            sidecars: Vec::new(),
+        })
+    }
+
+    /// Downloads the companion files the repository has next to the file
+    /// `request` downloaded into `response`, into the same directory. They
+    /// are not reported to the request's progress, which ended with the
+    /// model.
+    async fn download_sidecars(
+        &self,
+        request: &DownloadModelRequest,
+        mut response: DownloadModelResponse,
+    ) -> Result<DownloadModelResponse, PluginError> {
+        let hubs = self.hub_endpoints(&request.hub_endpoints)?;
+        let client = &self.hub_client(request.proxy.as_ref())?;
+        let (model_id, revision) = (&request.model_id, &request.revision);
+        let requests: Vec<_> = mirrors::failover(&hubs, &self.breakers, |hub| async move {
+            revisions::list_files(client, &hub, model_id, revision).await
+        })
+        .await?
+        .into_iter()
+        .filter(|file| sidecars::is_sidecar(&file.path, &request.filename))
+        .map(|file| DownloadModelRequest {
+            filename: file.path,
+            expected_sha256: file.sha256,
+            gguf_splits: GgufSplits::Keep,
+            sidecars: false,
+            ..request.clone()
+        })
+        .collect();
+        if requests.is_empty() {
+            tracing::debug!("{} has no companion files", request.model_id);
+            return Ok(response);
+        }
+
+        let sidecar_request = DownloadModelRequest {
+            progress: DownloadProgress::new(),
+            ..request.clone()
+        };
+        for file in self.download_each(&sidecar_request, requests, None).await? {
+            response.bytes_written += file.bytes_written;
+            response.sidecars.push(file.saved_path);
+        }
+        Ok(response)
+    }
+
+    /// Points a download of a LoRA adapter for the downloaded `model` at the
+    /// model's adapter directory. Returns the model's path and what deletes
+    /// the adapter again should it not fit the model.
+    async fn adapter_download(
+        &self,
+        request: &mut DownloadModelRequest,
+        model: &str,
+    ) -> Result<(PathBuf, DeleteModelRequest), PluginError> {
+        if request.mode != DownloadMode::File || request.node.is_some() || request.sidecars {
+            return Err(PluginError::InvalidRequest(
+                "adapters are downloaded locally as a single file".to_string(),
+            ));
+        }
+        if self.encryption.is_enabled() {
+            return Err(PluginError::InvalidRequest(
+                "adapters cannot be used with encrypted models".to_string(),
+            ));
+        }
+        let dir =
+            self.resolve_destination_dir(&request.task_type, request.destination_dir.as_deref())?;
+        let model_path = self.sandbox.join_file(&dir, model)?;
+        if !fs::try_exists(&model_path).await? {
+            return Err(PluginError::NotFound(format!(
+                "model {}",
+                model_path.display()
+            )));
+        }
+        let adapter_dir = adapters::adapter_dir(&model_path)
+            .to_string_lossy()
+            .to_string();
+        request.destination_dir = Some(adapter_dir);
+        let download = DeleteModelRequest {
+            task_type: request.task_type.clone(),
+            filename: request.filename.clone(),
+            destination_dir: request.destination_dir.clone(),
+        };
+        Ok((model_path, download))
+    }
+
+    /// Keeps a downloaded adapter only if it fits the model at
+    /// `model_path`.
+    async fn check_downloaded_adapter(
+        &self,
+        model_path: PathBuf,
+        download: DeleteModelRequest,
+        response: DownloadModelResponse,
+    ) -> Result<DownloadModelResponse, PluginError> {
+        let adapter = PathBuf::from(&response.saved_path);
+        let checked =
+            tokio::task::spawn_blocking(move || adapters::check_compatible(&model_path, &adapter))
+                .await
+                .map_err(|err| PluginError::Internal(err.to_string()))?;
+        if let Err(err) = checked {
+            if let Err(remove) = self.delete_model(download).await {
+                tracing::warn!(
+                    "failed to remove adapter {}: {}",
+                    response.saved_path,
+                    remove
+                );
+            }
+            return Err(err);
+        }
+        Ok(response)
+    }
+
+    /// Arguments attaching the adapters `request` asks for to the model at
+    /// `model_file`, each checked to fit it. Relative adapter paths are
+    /// looked up in the model's adapter directory.
+    async fn adapter_args(
+        &self,
+        request: &StartServiceRequest,
+        model_file: &str,
+    ) -> Result<Vec<String>, PluginError> {
+        if self.encryption.is_enabled() {
+            return Err(PluginError::InvalidRequest(
+                "adapters cannot be used with encrypted models".to_string(),
+            ));
+        }
+        let dir = adapters::adapter_dir(Path::new(&request.model_path));
+        let mut resolved = Vec::with_capacity(request.adapters.len());
+        for adapter in &request.adapters {
+            let path = if Path::new(&adapter.path).is_absolute() {
+                PathBuf::from(&adapter.path)
+            } else {
+                self.sandbox.join_file(&dir, &adapter.path)?
+            };
+            if !fs::try_exists(&path).await? {
+                return Err(PluginError::NotFound(format!("adapter {}", path.display())));
+            }
+            self.sandbox.verify_existing(&path)?;
+            resolved.push((path, adapter.scale));
+        }
+        let model_file = PathBuf::from(model_file);
+        let checked = resolved.clone();
+        tokio::task::spawn_blocking(move || {
+            checked
+                .iter()
+                .try_for_each(|(path, _)| adapters::check_compatible(&model_file, path))
+        })
+        .await
+        .map_err(|err| PluginError::Internal(err.to_string()))??;
+        Ok(adapters::args(&resolved))
+    }
+
+    /// Downloads `requests` one after another, reporting them to the
+    /// progress of `request` as one transfer of `total` bytes.
+    async fn download_each(
+        &self,
+        request: &DownloadModelRequest,
+        requests: Vec<DownloadModelRequest>,
+        total: Option<u64>,
+    ) -> Result<Vec<DownloadModelResponse>, PluginError> {
+        let progress = &request.progress;
+        progress.start(total);
+        let mut downloaded = Vec::with_capacity(requests.len());
+        let mut bytes_written = 0;
+        for file_request in requests {
+            if request.cancel.is_cancelled() {
+                return Err(PluginError::Cancelled);
+            }
+            let file_progress = DownloadProgress::new();
+            let mut updates = file_progress.subscribe();
+            let file_request = DownloadModelRequest {
+                progress: file_progress,
+                ..file_request
+            };
+            // Ends once the download drops its request.
+            let forward = async {
+                while updates.changed().await.is_ok() {
+                    let written = updates.borrow().bytes_written;
+                    progress.advance(bytes_written + written);
+                }
+            };
+            let (file, ()) = tokio::join!(self.download_local(file_request), forward);
+            let file = file?;
+            bytes_written += file.bytes_written;
+            progress.advance(bytes_written);
+            downloaded.push(file);
+        }
+        progress.finish();
+        Ok(downloaded)
+    }
+
+    /// Appends the downloaded `parts` in order into the file `request`
+    /// names, then removes them. The manifest tracks the result in their
+    /// place.
+    async fn concatenate_parts(
+        &self,
+        request: &DownloadModelRequest,
+        parts: &[DownloadModelResponse],
+    ) -> Result<DownloadModelResponse, PluginError> {
+        let destination_dir =
+            self.resolve_destination_dir(&request.task_type, request.destination_dir.as_deref())?;
+        let target_path = self
+            .sandbox
+            .join_file(&destination_dir, &request.filename)?;
+        let mut partial_name = target_path.file_name().unwrap_or_default().to_os_string();
+        partial_name.push(".part");
+        let partial_path = target_path.with_file_name(partial_name);
+
+        let mut hasher = Sha256::new();
+        let concatenated = async {
+            let mut output = fs::File::create(&partial_path).await?;
+            let mut buffer = vec![0; 1 << 20];
+            let mut bytes_written = 0;
+            for part in parts {
+                let mut input = fs::File::open(&part.saved_path).await?;
+                loop {
+                    let read = input.read(&mut buffer).await?;
+                    if read == 0 {
+                        break;
+                    }
+                    hasher.update(&buffer[..read]);
+                    output.write_all(&buffer[..read]).await?;
+                    bytes_written += read as u64;
+                }
+            }
+            output.flush().await?;
+            Ok::<_, std::io::Error>(bytes_written)
+        }
+        .await;
+        let bytes_written = match concatenated {
+            Ok(bytes_written) => bytes_written,
+            Err(err) => {
+                let _ = fs::remove_file(&partial_path).await;
+                return Err(err.into());
+            }
+        };
+        fs::rename(&partial_path, &target_path).await?;
+
+        let saved_path = target_path.to_string_lossy().to_string();
+        let mut manifest = self.manifest.lock().await;
+        let commit = manifest
+            .find(&parts[0].saved_path)
+            .and_then(|record| record.commit.clone());
+        for part in parts {
+            fs::remove_file(&part.saved_path).await?;
+            manifest.remove(&part.saved_path);
+        }
+        manifest.upsert(ModelRecord {
+            model_id: request.model_id.clone(),
+            filename: request.filename.clone(),
+            revision: request.revision.clone(),
+            commit,
+            task_type: request.task_type.clone(),
+            saved_path: saved_path.clone(),
+            bytes: bytes_written,
+            sha256: Some(format!("{:x}", hasher.finalize())),
+            downloaded_at: Utc::now(),
+            last_checked: None,
+            update: None,
+            last_used: None,
+            encrypted: false,
+            pinned: false,
+            source_url: None,
+            provenance: None,
+        });
+        manifest.save().await?;
+
+        Ok(DownloadModelResponse {
+            saved_path,
+            bytes_written,
+            fallback: Vec::new(),
+            sidecars: Vec::new(),
+        })
+    }
+
+    async fn start_remote(
+        &self,
+        node_id: &str,
+        request: StartServiceRequest,
+    ) -> Result<StartServiceResponse, PluginError> {
+        let node = self.node(node_id)?;
+        if request.interactive
+            || request.cpu_affinity.is_some()
+            || request.health_check.is_some()
+            || request.stdio != StdioConfig::default()
+            || request.wait_for_ready
+        {
+            return Err(PluginError::InvalidRequest(
+                "interactive, cpu_affinity, health_check, stdio and wait_for_ready are not supported on remote nodes"
+                    .to_string(),
+            ));
+        }
+
+        let command = request
+            .binary_path
+            .clone()
+            .or_else(|| node.binary.clone())
+            .ok_or_else(|| {
+                PluginError::BinaryMissing(format!(
+                    "binary_path not provided and node {} has no binary configured",
+                    node.id
+                ))
+            })?;
+        let args = self.launch_args(&request)?;
+        let environment = request.environment.unwrap_or_default();
+        let (resolved, redactor) = Redactor::resolve_environment(&environment)?;
+
+        let instance_id = uuid::Uuid::new_v4().to_string();
+        let log_path = node.log_path(&instance_id);
+        let pid = node.spawn(&command, &args, &resolved, &log_path).await?;
+        tracing::info!(
+            "started {:?} instance {} on node {} (pid {})",
+            request.task_type,
+            instance_id,
+            node.id,
+            pid
+        );
+
+        let instance = RemoteInstance {
+            instance_id: instance_id.clone(),
+            task_type: request.task_type,
+            node: node.clone(),
+            pid,
+            command,
+            args: redactor.redact_args(&args),
+            environment: redactor.redact_environment(&environment),
+            redactor: Arc::new(redactor),
+            log_path,
+        };
+        let response = instance.describe();
+        self.remote.lock().await.insert(instance_id, instance);
+        Ok(response)
+    }
+
+    fn output_forwarder(&self, instance_id: &str, task_type: &PluginTaskType) -> OutputForwarder {
+        OutputForwarder {
+            events: self.events.clone(),
+            plugin_id: self.metadata.id.clone(),
+            instance_id: instance_id.to_string(),
+            task_type: task_type.clone(),
+        }
+    }
+
+    fn spawn_watchdog(&self, managed: &ManagedProcess) -> Option<JoinHandle<()>> {
+        let config = managed.spec.health_check.clone()?;
+        Some(tokio::spawn(run_watchdog(
+            self.metadata.id.clone(),
+            managed.instance_id.clone(),
+            config,
+            self.processes.clone(),
+            self.client.clone(),
+            self.events.clone(),
+            managed.spec.faults.clone(),
+        )))
+    }
+
+    /// Derives the launch spec of the replacement instance from the running one.
+    fn upgrade_spec(
+        previous: &LaunchSpec,
+        request: &UpgradeServiceRequest,
+        model_path: String,
+    ) -> LaunchSpec {
+        let args = request.args.clone().unwrap_or_else(|| {
+            previous
+                .args
+                .iter()
+                .map(|arg| arg.replace(&previous.model_path, &model_path))
+                .collect()
+        });
+        let mut environment = previous.environment.clone();
+        if let Some(overrides) = &request.environment {
+            environment.extend(overrides.clone());
+        }
+
+        LaunchSpec {
+            command: request
+                .binary_path
+                .clone()
+                .unwrap_or_else(|| previous.command.clone()),
+            args,
+            environment,
+            resolved_environment: HashMap::new(),
+            redactor: Arc::default(),
+            health_check: request
+                .health_check
+                .clone()
+                .or_else(|| previous.health_check.clone()),
+            cpu_affinity: previous.cpu_affinity.clone(),
+            limit_adjustments: LimitAdjustments::default(),
+            interactive: previous.interactive,
+            stdio: previous.stdio,
+            log_retention: previous.log_retention,
+            log_dir: previous.log_dir.clone(),
+            faults: previous.faults.clone(),
+            gpu_offload: request
+                .args
+                .is_none()
+                .then(|| previous.gpu_offload.clone())
+                .flatten(),
+            decrypted: None,
+            model_path,
+        }
+    }
+
+    /// Retries the smoke test until it passes, the new instance dies, or the
+    /// readiness deadline passes.
+    async fn smoke_test(
+        &self,
+        candidate: &mut ManagedProcess,
+        config: &SmokeTestConfig,
+    ) -> Result<SmokeTestResult, PluginError> {
+        let deadline = config.ready_deadline();
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            let failure = match config.attempt(&self.client, attempts).await {
+                Ok(result) => return Ok(result),
+                Err(reason) => reason,
+            };
+
+            if let Some(status) = candidate.child.try_wait()? {
+                let report = candidate.crash_report(status).await;
+                return Err(PluginError::ProcessCrashed(Box::new(report)));
+            }
+            if std::time::Instant::now() >= deadline {
+                return Err(PluginError::SmokeTestFailed(format!(
+                    "{} after {} attempts",
+                    failure, attempts
+                )));
+            }
+            tokio::time::sleep(SMOKE_RETRY_DELAY).await;
+        }
+    }
+
+    /// Size of the file a download fetches, as the host announces it.
+    /// Hosts that do not announce one leave the download unchecked.
+    async fn download_size(
+        &self,
+        location: &ObjectLocation,
+        request: &DownloadModelRequest,
+    ) -> Result<Option<u64>, PluginError> {
+        let client = self.hub_client(request.proxy.as_ref())?;
+        let builder = location.request(&client, reqwest::Method::HEAD);
+        let response = tokio::select! {
+            response = builder.send() => response.and_then(|response| response.error_for_status()),
+            _ = request.cancel.cancelled() => return Err(PluginError::Cancelled),
+        };
+        // The body of a HEAD response is empty, so the header is read as is.
+        match response {
+            Ok(response) => Ok(response
+                .headers()
+                .get(reqwest::header::CONTENT_LENGTH)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse::<u64>().ok())),
+            Err(err) => {
+                tracing::debug!("not checking the size of {}: {}", request.filename, err);
+                Ok(None)
+            }
+        }
+    }
+
+    /// The SHA-256 a peer recorded for the file it shares at `location`.
+    /// Peers only share files with a recorded checksum, so a missing one
+    /// fails the download.
+    async fn peer_sha256(
+        &self,
+        location: &ObjectLocation,
+        request: &DownloadModelRequest,
+    ) -> Result<String, PluginError> {
+        let client = self.hub_client(request.proxy.as_ref())?;
+        let builder = location.request(&client, reqwest::Method::HEAD);
+        let response = tokio::select! {
+            response = builder.send() => response?.error_for_status()?,
+            _ = request.cancel.cancelled() => return Err(PluginError::Cancelled),
+        };
+        response
+            .headers()
+            .get(peers::SHA256_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .filter(|sha256| sha256.len() == 64 && sha256.bytes().all(|b| b.is_ascii_hexdigit()))
+            .map(str::to_ascii_lowercase)
+            .ok_or_else(|| {
+                PluginError::VerificationFailed(format!(
+                    "peer {} sent no checksum for {}",
+                    mirrors::host(&location.url),
+                    request.filename
+                ))
+            })
+    }
+
+    /// Refuses a download that would take the model directory of its task
+    /// type past its quota, unless evicting models makes room for it. The
+    /// file it replaces, and a partial download of it, are counted as freed.
+    /// Without a known `size` only a directory already at its quota is
+    /// refused. Downloads to other directories are not checked.
+    async fn check_model_dir_quota(
+        &self,
+        task_type: &PluginTaskType,
+        target_path: &Path,
+        partial_path: &Path,
+        size: Option<u64>,
+    ) -> Result<(), PluginError> {
+        let Some(max) = self.model_dir_quotas().get(task_type.as_str()).copied() else {
+            return Ok(());
+        };
+        let dir = self.resolve_destination_dir(task_type, None)?;
+        if !target_path.starts_with(&dir) {
+            return Ok(());
+        }
+        let (measured, measured_dir) = (task_type.clone(), dir.clone());
+        let usage = tokio::task::spawn_blocking(move || {
+            ModelDirUsage::measure(measured, &measured_dir, Some(max))
+        })
+        .await
+        .map_err(|err| PluginError::Internal(err.to_string()))??;
+        let mut freed = 0;
+        for path in [target_path, partial_path] {
+            if let Ok(metadata) = fs::metadata(path).await {
+                freed += metadata.len();
+            }
+        }
+        let used = usage.used_bytes.saturating_sub(freed);
+        let needed = used + size.unwrap_or_default();
+        if needed > max || (size.is_none() && used >= max) {
+            let excess = (needed + u64::from(size.is_none())).saturating_sub(max);
+            if self.evict_lru() && self.evict_models(&dir, target_path, excess).await? {
+                return Ok(());
+            }
+            return Err(PluginError::QuotaExceeded {
+                plugin_id: self.metadata.id.clone(),
+                limit: QuotaLimit::ModelDirBytes,
+                detail: format!("{} models would use {} of {} bytes", task_type, needed, max),
+            });
+        }
+        Ok(())
+    }
+
+    /// Deletes the least recently used unpinned models in `dir` until at
+    /// least `excess` bytes are freed. Models backing a running service and
+    /// the file at `keep` stay; when removing every other model would not
+    /// free enough, nothing is removed and false returned.
+    async fn evict_models(
+        &self,
+        dir: &Path,
+        keep: &Path,
+        excess: u64,
+    ) -> Result<bool, PluginError> {
+        let in_use = models_in_use(&self.processes).await;
+        let mut manifest = self.manifest.lock().await;
+        let mut evictable: Vec<_> = gc::eviction_order(manifest.records(), &in_use, dir)
+            .into_iter()
+            .filter(|record| Path::new(&record.saved_path) != keep)
+            .map(|record| (record.saved_path.clone(), record.bytes))
+            .collect();
+        let mut total = 0;
+        let Some(count) = evictable.iter().position(|(_, bytes)| {
+            total += bytes;
+            total >= excess
+        }) else {
+            return Ok(false);
+        };
+        evictable.truncate(count + 1);
+        for (saved_path, bytes) in evictable {
+            match fs::remove_file(&saved_path).await {
+                Ok(()) => {}
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+                Err(err) => return Err(err.into()),
+            }
+            manifest.remove(&saved_path);
+            tracing::info!(
+                "evicted model {} ({} bytes) to stay within quota",
+                saved_path,
+                bytes
+            );
+        }
+        manifest.save().await?;
+        Ok(true)
+    }
+
+    /// Size of a partial download at `path` that a new transfer can
+    /// continue, 0 when there is none. Encrypted downloads always start over
+    /// since the cipher state is not kept.
+    async fn resumable_length(&self, path: &Path) -> u64 {
+        if self.encryption.is_enabled() {
+            return 0;
+        }
+        fs::metadata(path)
+            .await
+            .map(|metadata| metadata.len())
+            .unwrap_or_default()
+    }
+
+    /// Size of the file `response` starts serving, and how to split it,
+    /// when it is worth fetching over several connections: a fresh download
+    /// from a host serving byte ranges of a large file, or of any file
+    /// larger than one range when the request asks for an accelerated
+    /// transfer. Encrypted downloads are written in order and never chunked.
+    fn chunk_plan(
+        &self,
+        response: &reqwest::Response,
+        offset: u64,
+        request: &DownloadModelRequest,
+    ) -> Option<(u64, ChunkPlan)> {
+        if offset > 0
+            || self.encryption.is_enabled()
+            || response.status() != reqwest::StatusCode::OK
+            || !accepts_byte_ranges(response.headers())
+        {
+            return None;
+        }
+        let total = response.content_length()?;
+        let plan = match request.transfer {
+            DownloadTransfer::Accelerated if total > ACCELERATED_CHUNK_BYTES => ChunkPlan {
+                chunk_len: ACCELERATED_CHUNK_BYTES,
+                connections: self.accelerated_connections(),
+                retries: ACCELERATED_RETRIES,
+            },
+            DownloadTransfer::Accelerated => return None,
+            DownloadTransfer::Standard => {
+                let connections = self.download_connections();
+                if connections < 2 || total < CHUNKED_DOWNLOAD_MIN_BYTES {
+                    return None;
+                }
+                ChunkPlan {
+                    chunk_len: total.div_ceil(connections as u64),
+                    connections,
+                    retries: 0,
+                }
+            }
+        };
+        Some((total, plan))
+    }
+
+    /// Fetches the `total` bytes of a download as the ranges of `plan`
+    /// over parallel connections opened by `send`, each writing its part of
+    /// `path` in place. Returns the same as `store_model`. A failed transfer,
+    /// or a cancelled one the request keeps, leaves the ranges finished from
+    /// the start of the file for a later download to resume and cuts off
+    /// the rest, which has holes.
+    async fn store_model_chunked<F, Fut>(
+        &self,
+        path: &Path,
+        total: u64,
+        plan: ChunkPlan,
+        send: &F,
+        request: &DownloadModelRequest,
+        throttle: &DownloadThrottle,
+    ) -> Result<(u64, String), PluginError>
+    where
+        F: Fn(Option<String>) -> Fut,
+        Fut: std::future::Future<Output = Result<reqwest::Response, PluginError>>,
+    {
+        self.prepare_parent(path).await?;
+        let file = fs::File::create(path).await?;
+        if let Err(err) = file.set_len(total).await {
+            return Err(self.write_failed(path, err));
+        }
+        drop(file);
+
+        let progress = &request.progress;
+        progress.start(Some(total));
+        let received = AtomicU64::new(0);
+        let finished = std::sync::Mutex::new(HashSet::new());
+        let fetches = futures::stream::iter(plan.ranges(total).map(|range| {
+            let (finished, received) = (&finished, &received);
+            let (start, retries) = (range.start, plan.retries);
+            async move {
+                self.fetch_range_retrying(path, range, retries, send, received, progress, throttle)
+                    .await?;
+                finished.lock().unwrap().insert(start);
+                Ok::<_, PluginError>(())
+            }
+        }))
+        .buffer_unordered(plan.connections)
+        .try_collect::<Vec<()>>();
+        let fetched = tokio::select! {
+            fetched = fetches => fetched,
+            _ = request.cancel.cancelled() => Err(PluginError::Cancelled),
+        };
+        if let Err(err) = fetched {
+            let finished = finished.into_inner().unwrap();
+            let resumable = plan
+                .ranges(total)
+                .take_while(|range| finished.contains(&range.start))
+                .last()
+                .map_or(0, |range| range.end);
+            let discarded = matches!(err, PluginError::Cancelled) && !request.partial.is_kept();
+            if resumable == 0 || discarded {
+                let _ = fs::remove_file(path).await;
+            } else if let Ok(file) = fs::OpenOptions::new().write(true).open(path).await {
+                let _ = file.set_len(resumable).await;
+            }
+            return Err(err);
+        }
+        progress.finish();
+
+        let mut hasher = Sha256::new();
+        hash_prefix(path, total, &mut hasher).await?;
+        Ok((total, format!("{:x}", hasher.finalize())))
+    }
+
+    /// Like [`fetch_range`](Self::fetch_range), retrying up to `retries`
+    /// times after network errors or connections that ended early, each
+    /// time from where the last attempt stopped.
+    #[allow(clippy::too_many_arguments)]
+    async fn fetch_range_retrying<F, Fut>(
+        &self,
+        path: &Path,
+        range: std::ops::Range<u64>,
+        retries: u32,
+        send: &F,
+        received: &AtomicU64,
+        progress: &DownloadProgress,
+        throttle: &DownloadThrottle,
+    ) -> Result<(), PluginError>
+    where
+        F: Fn(Option<String>) -> Fut,
+        Fut: std::future::Future<Output = Result<reqwest::Response, PluginError>>,
+    {
+        let mut position = range.start;
+        let mut attempt = 0;
+        loop {
+            let err = match self
+                .fetch_range(
+                    path,
+                    &mut position,
+                    range.end,
+                    send,
+                    received,
+                    progress,
+                    throttle,
+                )
+                .await
+            {
+                Ok(()) => return Ok(()),
+                Err(err) => err,
+            };
+            let transient = match &err {
+                PluginError::Network(_) => true,
+                PluginError::Io(io) => io.kind() == std::io::ErrorKind::UnexpectedEof,
+                _ => false,
+            };
+            if !transient || attempt >= retries {
+                return Err(err);
+            }
+            attempt += 1;
+            tracing::debug!(
+                "retrying bytes {}-{} (attempt {}): {}",
+                position,
+                range.end - 1,
+                attempt,
+                err
+            );
+            tokio::time::sleep(Duration::from_millis(200 << attempt)).await;
+        }
+    }
+
+    /// Writes bytes `*position..end` of a download into `path`, advancing
+    /// `position` past what was written and counting it in `received`.
+    #[allow(clippy::too_many_arguments)]
+    async fn fetch_range<F, Fut>(
+        &self,
+        path: &Path,
+        position: &mut u64,
+        end: u64,
+        send: &F,
+        received: &AtomicU64,
+        progress: &DownloadProgress,
+        throttle: &DownloadThrottle,
+    ) -> Result<(), PluginError>
+    where
+        F: Fn(Option<String>) -> Fut,
+        Fut: std::future::Future<Output = Result<reqwest::Response, PluginError>>,
+    {
+        let start = *position;
+        let mut response = send(Some(format!("bytes={}-{}", start, end - 1))).await?;
+        if !resumes_at(&response, start) {
+            return Err(PluginError::Io(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("host did not serve bytes {}-{}", start, end - 1),
+            )));
+        }
+        let mut file = fs::OpenOptions::new().write(true).open(path).await?;
+        file.seek(std::io::SeekFrom::Start(start)).await?;
+        while let Some(chunk) = response.chunk().await? {
+            let chunk = &chunk[..chunk.len().min((end - *position) as usize)];
+            let total =
+                received.fetch_add(chunk.len() as u64, Ordering::Relaxed) + chunk.len() as u64;
+            self.faults.check_download(total)?;
+            if let Err(err) = file.write_all(chunk).await {
+                return Err(self.write_failed(path, err));
+            }
+            *position += chunk.len() as u64;
+            progress.advance(total);
+            if *position == end {
+                break;
+            }
+            throttle.wait(chunk.len() as u64).await;
+        }
+        if *position < end {
+            return Err(PluginError::Io(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                format!("bytes {}-{} ended at {}", start, end - 1, position),
+            )));
+        }
+        if let Err(err) = file.flush().await {
+            return Err(self.write_failed(path, err));
+        }
+        Ok(())
+    }
+
+    /// Creates the directory `path` is written to, inside the sandbox.
+    async fn prepare_parent(&self, path: &Path) -> Result<(), PluginError> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await?;
+            self.sandbox.verify_existing(parent)?;
+        }
+        Ok(())
+    }
+
+    /// Streams the response to `path`, returning the file's size and hex
+    /// SHA-256. With an `offset`, the response continues the first `offset`
+    /// bytes already at `path`. Reading is paced by `throttle`. A cancelled
+    /// transfer removes what was written so far unless the request keeps it
+    /// for a later resume.
+    async fn store_model(
+        &self,
+        path: &Path,
+        offset: u64,
+        mut response: reqwest::Response,
+        request: &DownloadModelRequest,
+        throttle: &DownloadThrottle,
+    ) -> Result<(u64, String), PluginError> {
+        let progress = &request.progress;
+        self.prepare_parent(path).await?;
+
+        let mut hasher = Sha256::new();
+        let mut file = if offset > 0 {
+            hash_prefix(path, offset, &mut hasher).await?;
+            let mut file = fs::OpenOptions::new().write(true).open(path).await?;
+            file.set_len(offset).await?;
+            file.seek(std::io::SeekFrom::End(0)).await?;
+            file
+        } else {
+            fs::File::create(path).await?
+        };
+        let mut encryptor = self.encryption.encryptor()?;
+        let mut bytes_written = offset;
+        progress.start(response.content_length().map(|length| length + offset));
+        progress.advance(bytes_written);
+        let mut pause = None;
+        loop {
+            let next = async {
+                if let Some(until) = pause.take() {
+                    tokio::time::sleep_until(until).await;
+                }
+                response.chunk().await
+            };
+            let chunk = tokio::select! {
+                chunk = next => chunk?,
+                _ = request.cancel.cancelled() => {
+                    if request.partial.is_kept() && encryptor.is_none() {
+                        let _ = file.flush().await;
+                    } else {
+                        drop(file);
+                        let _ = fs::remove_file(path).await;
+                    }
+                    return Err(PluginError::Cancelled);
+                }
+            };
+            let Some(chunk) = chunk else {
+                break;
+            };
+            self.faults
+                .check_download(bytes_written + chunk.len() as u64)?;
+            bytes_written += chunk.len() as u64;
+            hasher.update(&chunk);
+            let written = match &mut encryptor {
+                Some(encryptor) => file.write_all(&encryptor.update(&chunk)?).await,
+                None => file.write_all(&chunk).await,
+            };
+            if let Err(err) = written {
+                return Err(self.write_failed(path, err));
+            }
+            progress.advance(bytes_written);
+            pause = throttle.pace(chunk.len() as u64);
+        }
+        if let Some(encryptor) = encryptor {
+            if let Err(err) = file.write_all(&encryptor.finish()?).await {
+                return Err(self.write_failed(path, err));
+            }
+        }
+        if let Err(err) = file.flush().await {
+            return Err(self.write_failed(path, err));
+        }
+        progress.finish();
+
+        Ok((bytes_written, format!("{:x}", hasher.finalize())))
+    }
+
+    /// Copies `source` to `path`, encrypting it when encryption is on, and
+    /// returns the hex SHA-256 of the source.
+    async fn copy_model(&self, source: &Path, path: &Path) -> Result<String, PluginError> {
+        let mut reader = fs::File::open(source).await?;
+        let mut file = fs::File::create(path).await?;
+        let mut encryptor = self.encryption.encryptor()?;
+        let mut hasher = Sha256::new();
+        let mut buffer = vec![0; 1 << 20];
+        loop {
+            let read = reader.read(&mut buffer).await?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+            let written = match &mut encryptor {
+                Some(encryptor) => file.write_all(&encryptor.update(&buffer[..read])?).await,
+                None => file.write_all(&buffer[..read]).await,
+            };
+            if let Err(err) = written {
+                return Err(self.write_failed(path, err));
+            }
+        }
+        if let Some(encryptor) = encryptor {
+            if let Err(err) = file.write_all(&encryptor.finish()?).await {
+                return Err(self.write_failed(path, err));
+            }
+        }
+        if let Err(err) = file.flush().await {
+            return Err(self.write_failed(path, err));
+        }
+        Ok(format!("{:x}", hasher.finalize()))
+    }
+
+    /// Stamps a launched model in the manifest so garbage collection keeps it.
+    async fn record_usage(&self, model_path: &str) {
+        let mut manifest = self.manifest.lock().await;
+        if manifest.mark_used(model_path) {
+            if let Err(err) = manifest.save().await {
+                tracing::warn!("failed to record usage of {}: {}", model_path, err);
+            }
+        }
+    }
+
+    /// Reports a failed model write as a `disk_full` event when the disk or
+    /// quota ran out.
+    fn write_failed(&self, path: &Path, err: std::io::Error) -> PluginError {
+        if matches!(
+            err.kind(),
+            std::io::ErrorKind::StorageFull | std::io::ErrorKind::QuotaExceeded
+        ) {
+            self.events.publish(
+                &self.metadata.id,
+                PluginEventKind::DiskFull {
+                    path: path.to_string_lossy().to_string(),
+                    message: err.to_string(),
+                },
+            );
+        }
+        PluginError::Io(err)
+    }
+
+    /// Checks a download of a commit-pinned revision against the pin and, on
+    /// re-download, against the checksum recorded the first time.
+    async fn verify_pinned_download(
+        &self,
+        request: &DownloadModelRequest,
+        saved_path: &str,
+        served_commit: Option<&str>,
+        sha256: &str,
+    ) -> Result<(), PluginError> {
+        if !revisions::is_commit_sha(&request.revision) {
+            return Ok(());
+        }
+        if let Some(commit) = served_commit {
+            if !commit.eq_ignore_ascii_case(&request.revision) {
+                return Err(PluginError::VerificationFailed(format!(
+                    "requested commit {} but the host served {}",
+                    request.revision, commit
+                )));
+            }
+        }
+
+        let manifest = self.manifest.lock().await;
+        let previous = manifest.find(saved_path).filter(|record| {
+            record
+                .commit
+                .as_deref()
+                .is_some_and(|commit| commit.eq_ignore_ascii_case(&request.revision))
+        });
+        match previous.and_then(|record| record.sha256.as_deref()) {
+            Some(expected) if expected != sha256 => Err(PluginError::VerificationFailed(format!(
+                "{} at commit {} has sha256 {} but {} was recorded previously",
+                request.filename, request.revision, sha256, expected
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// The SHA-256 `hub` lists for the requested file, if it lists one.
+    /// Failing to look it up leaves the download unchecked.
+    async fn listed_sha256(
+        &self,
+        request: &DownloadModelRequest,
+        hub: &reqwest::Url,
+    ) -> Option<String> {
+        let client = self.hub_client(request.proxy.as_ref()).ok()?;
+        let lookup = revisions::file_sha256(
+            &client,
+            hub,
+            &request.model_id,
+            &request.revision,
+            &request.filename,
+            request.auth_token.as_deref(),
+        );
+        match self.breakers.run(mirrors::host(hub), lookup).await {
+            Ok(sha256) => sha256.map(|sha256| sha256.to_ascii_lowercase()),
+            Err(err) => {
+                tracing::debug!(
+                    "no listed sha256 for {}/{}: {}",
+                    request.model_id,
+                    request.filename,
+                    err
+                );
+                None
+            }
+        }
+    }
+
+    fn build_download_url(
+        &self,
+        request: &DownloadModelRequest,
+        hub: &reqwest::Url,
+    ) -> Result<reqwest::Url, PluginError> {
+        let mut url = revisions::repo_url(
+            hub,
+            &[],
+            &request.model_id,
+            &["resolve", &request.revision, &request.filename],
+        )?;
+        url.set_query(Some("download=1"));
+        Ok(url)
+    }
+
+    async fn model_usage(&self) -> Result<ModelUsageResponse, PluginError> {
+        let quotas = self.model_dir_quotas();
+        let mut task_types: BTreeSet<String> = [PluginTaskType::TEXT, PluginTaskType::TTS]
+            .iter()
+            .map(PluginTaskType::to_string)
+            .collect();
+        task_types.extend(quotas.keys().cloned());
+        task_types.extend(
+            self.manifest
+                .lock()
+                .await
+                .records()
+                .iter()
+                .map(|record| record.task_type.to_string()),
+        );
+        let mut dirs = Vec::new();
+        for name in task_types {
+            let task_type = PluginTaskType::new(name)?;
+            let dir = self.resolve_destination_dir(&task_type, None)?;
+            let max = quotas.get(task_type.as_str()).copied();
+            dirs.push((task_type, dir, max));
+        }
+        let directories = tokio::task::spawn_blocking(move || {
+            dirs.into_iter()
+                .map(|(task_type, dir, max)| ModelDirUsage::measure(task_type, &dir, max))
+                .collect::<std::io::Result<Vec<_>>>()
+        })
+        .await
+        .map_err(|err| PluginError::Internal(err.to_string()))??;
+        Ok(ModelUsageResponse { directories })
+    }
+
+    async fn search_models(
+        &self,
+        request: ModelSearchRequest,
+    ) -> Result<ModelSearchResponse, PluginError> {
+        self.offline.ensure_online("searching models")?;
+        let hubs = self.hub_endpoints(&[])?;
+        let client = &self.hub_client(None)?;
+        let request = &request;
+        let models = mirrors::failover(&hubs, &self.breakers, |hub| async move {
+            search::search(client, &hub, request).await
+        })
+        .await?;
+        Ok(ModelSearchResponse { models })
+    }
+
+    async fn list_model_files(
+        &self,
+        request: ModelFilesRequest,
+    ) -> Result<ModelFilesResponse, PluginError> {
+        if request.model_id.trim().is_empty() {
+            return Err(PluginError::InvalidRequest(
+                "model_id is required".to_string(),
+            ));
+        }
+        self.offline.ensure_online("listing repository files")?;
+        let hubs = self.hub_endpoints(&[])?;
+        let client = &self.hub_client(None)?;
+        let (model_id, revision) = (&request.model_id, &request.revision);
+        let files = mirrors::failover(&hubs, &self.breakers, |hub| async move {
+            revisions::list_files(client, &hub, model_id, revision).await
+        })
+        .await?;
+        Ok(ModelFilesResponse {
+            model_id: request.model_id,
+            revision: request.revision,
+            files,
+        })
+    }
+
+    async fn validate_token(
+        &self,
+        request: ValidateTokenRequest,
+    ) -> Result<ValidateTokenResponse, PluginError> {
+        let token = request.token.trim();
+        if token.is_empty() {
+            return Err(PluginError::InvalidRequest("token is required".to_string()));
+        }
+        self.offline.ensure_online("validating tokens")?;
+        let hubs = self.hub_endpoints(&[])?;
+        let client = &self.hub_client(None)?;
+        mirrors::failover(&hubs, &self.breakers, |hub| async move {
+            tokens::validate(client, &hub, token).await
+        })
+        .await
+    }
+
+    async fn delete_model(
+        &self,
+        request: DeleteModelRequest,
+    ) -> Result<DeleteModelResponse, PluginError> {
+        let dir =
+            self.resolve_destination_dir(&request.task_type, request.destination_dir.as_deref())?;
+        let path = self.sandbox.join_file(&dir, &request.filename)?;
+        let metadata = match fs::metadata(&path).await {
+            Ok(metadata) => metadata,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                return Err(PluginError::NotFound(format!("model {}", path.display())));
+            }
+            Err(err) => return Err(err.into()),
+        };
+        if !metadata.is_file() {
+            return Err(PluginError::InvalidRequest(format!(
+                "{} is not a model file",
+                path.display()
+            )));
+        }
+        // Symlinks must not delete anything outside of the sandbox, except
+        // for imported links, of which only the link itself is removed.
+        let linked = fs::symlink_metadata(&path).await?.file_type().is_symlink();
+        if !linked {
+            self.sandbox.verify_existing(&path)?;
+        }
+
+        let saved_path = path.to_string_lossy().to_string();
+        let canonical = path.canonicalize()?;
+        let in_use = models_in_use(&self.processes).await;
+        if in_use.iter().any(|used| {
+            *used == saved_path || Path::new(used).canonicalize().ok().as_ref() == Some(&canonical)
+        }) {
+            return Err(PluginError::ModelInUse(saved_path));
+        }
+
+        // Held across the removal so a download cannot record the file in
+        // between.
+        let mut manifest = self.manifest.lock().await;
+        fs::remove_file(&path).await?;
+        manifest.remove(&saved_path);
+        manifest.save().await?;
+        let mut lock = self.lock.lock().await;
+        lock.remove(
+            &request.task_type,
+            request.destination_dir.as_deref(),
+            &request.filename,
+        );
+        lock.save().await?;
+        let bytes_reclaimed = if linked { 0 } else { metadata.len() };
+        tracing::info!("deleted model {} ({} bytes)", saved_path, bytes_reclaimed);
+        Ok(DeleteModelResponse {
+            saved_path,
+            bytes_reclaimed,
+        })
+    }
+
+    async fn pin_model(&self, request: PinModelRequest) -> Result<ModelRecord, PluginError> {
+        let dir =
+            self.resolve_destination_dir(&request.task_type, request.destination_dir.as_deref())?;
+        let path = self.sandbox.join_file(&dir, &request.filename)?;
+        let saved_path = path.to_string_lossy().to_string();
+        let mut manifest = self.manifest.lock().await;
+        let record = manifest
+            .set_pinned(&saved_path, request.pinned)
+            .cloned()
+            .ok_or_else(|| PluginError::NotFound(format!("model {}", saved_path)))?;
+        manifest.save().await?;
+        Ok(record)
+    }
+
+    async fn list_adapters(
+        &self,
+        request: ListAdaptersRequest,
+    ) -> Result<ListAdaptersResponse, PluginError> {
+        let dir =
+            self.resolve_destination_dir(&request.task_type, request.destination_dir.as_deref())?;
+        let model_path = self.sandbox.join_file(&dir, &request.filename)?;
+        if !fs::try_exists(&model_path).await? {
+            return Err(PluginError::NotFound(format!(
+                "model {}",
+                model_path.display()
+            )));
+        }
+        let adapter_dir = adapters::adapter_dir(&model_path);
+        let (model, listed) = (model_path.clone(), adapter_dir.clone());
+        let (architecture, adapters) = tokio::task::spawn_blocking(move || {
+            (
+                offload::read_identity(&model).map(|identity| identity.architecture),
+                adapters::list(&listed, &model),
+            )
+        })
+        .await
+        .map_err(|err| PluginError::Internal(err.to_string()))?;
+        Ok(ListAdaptersResponse {
+            model_path: model_path.to_string_lossy().to_string(),
+            adapter_dir: adapter_dir.to_string_lossy().to_string(),
+            architecture,
+            adapters: adapters?,
+        })
+    }
+
+    async fn convert_model(
+        &self,
+        request: ConvertModelRequest,
+    ) -> Result<ConvertModelResponse, PluginError> {
+        let converter = self.converter().ok_or_else(|| {
+            PluginError::BinaryMissing("no model converter configured".to_string())
+        })?;
+        if self.encryption.is_enabled() {
+            return Err(PluginError::InvalidRequest(
+                "models are encrypted at rest, so they cannot be converted".to_string(),
+            ));
+        }
+        let outtype = request.outtype.as_deref().map(str::trim);
+        if let Some(outtype) = outtype {
+            if outtype.is_empty()
+                || !outtype
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_')
+            {
+                return Err(PluginError::InvalidRequest(format!(
+                    "invalid outtype {}",
+                    outtype
+                )));
+            }
+        }
+
+        let dir =
+            self.resolve_destination_dir(&request.task_type, request.destination_dir.as_deref())?;
+        let source = self.sandbox.join_file(&dir, &request.source_dir)?;
+        if !fs::metadata(&source)
+            .await
+            .is_ok_and(|metadata| metadata.is_dir())
+        {
+            return Err(PluginError::NotFound(format!(
+                "model directory {}",
+                source.display()
+            )));
+        }
+        let filename = match &request.filename {
+            Some(filename) => filename.clone(),
+            None => convert::default_filename(&request.source_dir, outtype),
+        };
+        let target_path = self.sandbox.join_file(&dir, &filename)?;
+        let mut partial_name = target_path.file_name().unwrap_or_default().to_os_string();
+        partial_name.push(".part");
+        let partial_path = target_path.with_file_name(partial_name);
+        self.check_model_dir_quota(&request.task_type, &target_path, &partial_path, None)
+            .await?;
+
+        self.prepare_parent(&partial_path).await?;
+        let _ = fs::remove_file(&partial_path).await;
+        if let Err(err) = convert::run(
+            &converter,
+            &source,
+            &partial_path,
+            outtype,
+            &request.cancel,
+            &request.progress,
+        )
+        .await
+        {
+            let _ = fs::remove_file(&partial_path).await;
+            return Err(err);
+        }
+        let bytes = fs::metadata(&partial_path).await?.len();
+        let mut hasher = Sha256::new();
+        hash_prefix(&partial_path, bytes, &mut hasher).await?;
+        let sha256 = format!("{:x}", hasher.finalize());
+        fs::rename(&partial_path, &target_path).await?;
+
+        // The converted file keeps the repository and revision its weights
+        // were downloaded from.
+        let saved_path = target_path.to_string_lossy().to_string();
+        let mut manifest = self.manifest.lock().await;
+        let origin = manifest
+            .records()
+            .iter()
+            .find(|record| Path::new(&record.saved_path).starts_with(&source))
+            .map(|record| {
+                (
+                    record.model_id.clone(),
+                    record.revision.clone(),
+                    record.commit.clone(),
+                )
+            });
+        let (model_id, revision, commit) =
+            origin.unwrap_or_else(|| (request.source_dir.clone(), "main".to_string(), None));
+        manifest.upsert(ModelRecord {
+            model_id,
+            filename,
+            revision,
+            commit,
+            task_type: request.task_type,
+            saved_path: saved_path.clone(),
+            bytes,
+            sha256: Some(sha256.clone()),
+            downloaded_at: Utc::now(),
+            last_checked: None,
+            update: None,
+            last_used: None,
+            encrypted: false,
+            pinned: false,
+            source_url: None,
+            provenance: None,
+        });
+        manifest.save().await?;
+        tracing::info!("converted {} to {}", source.display(), saved_path);
+        Ok(ConvertModelResponse {
+            saved_path,
+            bytes,
+            sha256,
+        })
+    }
+
+    async fn quantize_model(
+        &self,
+        request: QuantizeModelRequest,
+    ) -> Result<QuantizeModelResponse, PluginError> {
+        let quantizer = self.quantizer().ok_or_else(|| {
+            PluginError::BinaryMissing("no model quantizer configured".to_string())
+        })?;
+        if self.encryption.is_enabled() {
+            return Err(PluginError::InvalidRequest(
+                "models are encrypted at rest, so they cannot be quantized".to_string(),
+            ));
+        }
+        let (quant_type, target_bits) = quantize::quant_type(&request.quant_type)?;
+
+        let dir =
+            self.resolve_destination_dir(&request.task_type, request.destination_dir.as_deref())?;
+        let source = self.sandbox.join_file(&dir, &request.filename)?;
+        let source_bytes = match fs::metadata(&source).await {
+            Ok(metadata) if metadata.is_file() => metadata.len(),
+            _ => return Err(PluginError::NotFound(format!("model {}", source.display()))),
+        };
+        let header = source.clone();
+        let file_type = tokio::task::spawn_blocking(move || offload::read_file_type(&header))
+            .await
+            .map_err(|err| PluginError::Internal(err.to_string()))?;
+        let source_bits = file_type.and_then(quantize::full_precision_bits);
+        if file_type.is_some() && source_bits.is_none() && !request.allow_requantize {
+            return Err(PluginError::InvalidRequest(format!(
+                "{} is already quantized; allow requantizing to quantize it again",
+                request.filename
+            )));
+        }
+
+        let filename = match &request.output_filename {
+            Some(filename) => filename.clone(),
+            None => quantize::default_filename(&request.filename, quant_type),
+        };
+        let target_path = self.sandbox.join_file(&dir, &filename)?;
+        if target_path == source {
+            return Err(PluginError::InvalidRequest(
+                "the quantized model cannot replace its source".to_string(),
+            ));
+        }
+        let mut partial_name = target_path.file_name().unwrap_or_default().to_os_string();
+        partial_name.push(".part");
+        let partial_path = target_path.with_file_name(partial_name);
+        let estimate = quantize::estimated_bytes(source_bytes, source_bits, target_bits);
+        self.check_model_dir_quota(
+            &request.task_type,
+            &target_path,
+            &partial_path,
+            Some(estimate),
+        )
+        .await?;
+        check_disk_space(&partial_path, estimate)?;
+
+        self.prepare_parent(&partial_path).await?;
+        let _ = fs::remove_file(&partial_path).await;
+        if let Err(err) = quantize::run(
+            &quantizer,
+            &source,
+            &partial_path,
+            quant_type,
+            request.allow_requantize,
+            &request.cancel,
+            &request.progress,
+        )
+        .await
+        {
+            let _ = fs::remove_file(&partial_path).await;
+            return Err(err);
+        }
+        let bytes = fs::metadata(&partial_path).await?.len();
+        let mut hasher = Sha256::new();
+        hash_prefix(&partial_path, bytes, &mut hasher).await?;
+        let sha256 = format!("{:x}", hasher.finalize());
+        fs::rename(&partial_path, &target_path).await?;
+
+        let saved_path = target_path.to_string_lossy().to_string();
+        let mut manifest = self.manifest.lock().await;
+        let origin = manifest.find(&source.to_string_lossy()).map(|record| {
+            (
+                record.model_id.clone(),
+                record.revision.clone(),
+                record.commit.clone(),
+            )
+        });
+        let (model_id, revision, commit) =
+            origin.unwrap_or_else(|| (request.filename.clone(), "main".to_string(), None));
+        manifest.upsert(ModelRecord {
+            model_id,
+            filename,
+            revision,
+            commit,
+            task_type: request.task_type,
+            saved_path: saved_path.clone(),
+            bytes,
+            sha256: Some(sha256.clone()),
+            downloaded_at: Utc::now(),
+            last_checked: None,
+            update: None,
+            last_used: None,
+            encrypted: false,
+            pinned: false,
+            source_url: None,
+            provenance: None,
+        });
+        manifest.save().await?;
+        tracing::info!(
+            "quantized {} to {} as {}",
+            source.display(),
+            quant_type,
+            saved_path
+        );
+        Ok(QuantizeModelResponse {
+            saved_path,
+            bytes,
+            sha256,
+            quant_type: quant_type.to_string(),
+        })
+    }
+
+    async fn import_model(
+        &self,
+        request: ImportModelRequest,
+    ) -> Result<ImportModelResponse, PluginError> {
+        // Links point at the file itself, not at a link to it such as the
+        // entries of the Hugging Face cache.
+        let source = match fs::canonicalize(&request.source_path).await {
+            Ok(source) => source,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                return Err(PluginError::NotFound(format!(
+                    "model {}",
+                    request.source_path
+                )));
+            }
+            Err(err) => return Err(err.into()),
+        };
+        let metadata = fs::metadata(&source).await?;
+        if !metadata.is_file() {
+            return Err(PluginError::InvalidRequest(format!(
+                "{} is not a model file",
+                request.source_path
+            )));
+        }
+        if request.mode != ImportMode::Copy && self.encryption.is_enabled() {
+            return Err(PluginError::InvalidRequest(
+                "models are encrypted at rest, so they can only be imported as copies".to_string(),
+            ));
+        }
+
+        let filename = match &request.filename {
+            Some(filename) => filename.clone(),
+            None => source
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default(),
+        };
+        let dir =
+            self.resolve_destination_dir(&request.task_type, request.destination_dir.as_deref())?;
+        let target_path = self.sandbox.join_file(&dir, &filename)?;
+        if target_path.canonicalize().ok().as_ref() == Some(&source) {
+            return Err(PluginError::InvalidRequest(format!(
+                "{} is already in the model directory",
+                request.source_path
+            )));
+        }
+        let mut partial_name = target_path.file_name().unwrap_or_default().to_os_string();
+        partial_name.push(".part");
+        let partial_path = target_path.with_file_name(partial_name);
+        if request.mode != ImportMode::Symlink {
+            self.check_model_dir_quota(
+                &request.task_type,
+                &target_path,
+                &partial_path,
+                Some(metadata.len()),
+            )
+            .await?;
+        }
+        if request.mode == ImportMode::Copy {
+            check_disk_space(&partial_path, metadata.len())?;
+        }
+
+        self.prepare_parent(&partial_path).await?;
+        let _ = fs::remove_file(&partial_path).await;
+        let imported = match request.mode {
+            ImportMode::Copy => self.copy_model(&source, &partial_path).await,
+            ImportMode::Hardlink | ImportMode::Symlink => {
+                let linked = match request.mode {
+                    ImportMode::Hardlink => fs::hard_link(&source, &partial_path).await,
+                    _ => symlink_file(&source, &partial_path).await,
+                };
+                match linked {
+                    Ok(()) => {
+                        let mut hasher = Sha256::new();
+                        match hash_prefix(&source, metadata.len(), &mut hasher).await {
+                            Ok(()) => Ok(format!("{:x}", hasher.finalize())),
+                            Err(err) => Err(err.into()),
+                        }
+                    }
+                    Err(err) => Err(PluginError::InvalidRequest(format!(
+                        "cannot link {} into {}: {}",
+                        source.display(),
+                        dir.display(),
+                        err
+                    ))),
+                }
+            }
+        };
+        let sha256 = match imported {
+            Ok(sha256) => sha256,
+            Err(err) => {
+                let _ = fs::remove_file(&partial_path).await;
+                return Err(err);
+            }
+        };
+        fs::rename(&partial_path, &target_path).await?;
+
+        // The cache path, not the blob it resolves to, names the repository.
+        let origin = import::hf_cache_origin(Path::new(&request.source_path));
+        let (model_id, commit) = match origin {
+            Some((model_id, commit)) => (request.model_id.unwrap_or(model_id), Some(commit)),
+            None => (request.model_id.unwrap_or_else(|| filename.clone()), None),
+        };
+        let saved_path = target_path.to_string_lossy().to_string();
+        let mut manifest = self.manifest.lock().await;
+        manifest.upsert(ModelRecord {
+            model_id,
+            filename,
+            revision: commit.clone().unwrap_or_else(|| "main".to_string()),
+            commit,
+            task_type: request.task_type,
+            saved_path: saved_path.clone(),
+            bytes: metadata.len(),
+            sha256: Some(sha256.clone()),
+            downloaded_at: Utc::now(),
+            last_checked: None,
+            update: None,
+            last_used: None,
+            encrypted: request.mode == ImportMode::Copy && self.encryption.is_enabled(),
+            pinned: false,
+            source_url: reqwest::Url::from_file_path(&source).ok().map(String::from),
+            provenance: None,
+        });
+        manifest.save().await?;
+        tracing::info!(
+            "imported {} as {} ({:?})",
+            source.display(),
+            saved_path,
+            request.mode
+        );
+        Ok(ImportModelResponse {
+            saved_path,
+            bytes: metadata.len(),
+            sha256,
+            mode: request.mode,
+        })
+    }
+
+    async fn restore_models(
+        &self,
+        request: RestoreModelsRequest,
+    ) -> Result<RestoreModelsResponse, PluginError> {
+        let locked = self.lock.lock().await.models().to_vec();
+        let mut response = RestoreModelsResponse::default();
+        for model in locked {
+            if request.cancel.is_cancelled() {
+                return Err(PluginError::Cancelled);
+            }
+            let dir =
+                self.resolve_destination_dir(&model.task_type, model.destination_dir.as_deref())?;
+            let saved_path = self
+                .sandbox
+                .join_file(&dir, &model.filename)?
+                .to_string_lossy()
+                .to_string();
+            // Files the manifest already has at the locked hash are kept.
+            let present = self
+                .manifest
+                .lock()
+                .await
+                .find(&saved_path)
+                .is_some_and(|record| record.sha256.as_deref() == Some(model.sha256.as_str()));
+            if present && fs::try_exists(&saved_path).await.unwrap_or(false) {
+                response.unchanged.push(saved_path);
+                continue;
+            }
+            let download = redownload(
+                ModelOrigin::from(model.clone()),
+                request.auth_token.clone(),
+                request.hub_endpoints.clone(),
+                request.cancel.clone(),
+            );
+            match self.download_model(download).await {
+                Ok(downloaded) => response.restored.push(downloaded),
+                Err(PluginError::Cancelled) => return Err(PluginError::Cancelled),
+                Err(err) => {
+                    tracing::warn!(
+                        "could not restore {}/{}: {}",
+                        model.model_id,
+                        model.filename,
+                        err
+                    );
+                    response.failed.push(RestoreFailure {
+                        model_id: model.model_id,
+                        filename: model.filename,
+                        error: err.to_string(),
+                    });
+                }
+            }
+        }
+        Ok(response)
+    }
+
+    async fn model_health(&self) -> Result<ModelHealthResponse, PluginError> {
+        Ok(self.integrity.lock().await.clone())
+    }
+
+    async fn repair_models(
+        &self,
+        request: RepairModelsRequest,
+    ) -> Result<RepairModelsResponse, PluginError> {
+        let issues = self.integrity.lock().await.issues.clone();
+        let mut response = RepairModelsResponse::default();
+        let mut downloaded = HashSet::new();
+        for issue in issues {
+            if request.cancel.is_cancelled() {
+                return Err(PluginError::Cancelled);
+            }
+            let Some(origin) = issue.origin else {
+                response.failed.push(RepairFailure {
+                    path: issue.path,
+                    error: "unknown origin; delete the file or download its model again"
+                        .to_string(),
+                });
+                continue;
+            };
+            // A leftover and the file it was downloading are repaired by
+            // the same download.
+            if !downloaded.insert((
+                origin.task_type.clone(),
+                origin.destination_dir.clone(),
+                origin.filename.clone(),
+            )) {
+                continue;
+            }
+            let download = redownload(
+                origin,
+                request.auth_token.clone(),
+                request.hub_endpoints.clone(),
+                request.cancel.clone(),
+            );
+            match self.download_model(download).await {
+                Ok(repaired) => response.repaired.push(repaired),
+                Err(PluginError::Cancelled) => return Err(PluginError::Cancelled),
+                Err(err) => {
+                    tracing::warn!("could not repair {}: {}", issue.path, err);
+                    response.failed.push(RepairFailure {
+                        path: issue.path,
+                        error: err.to_string(),
+                    });
+                }
+            }
+        }
+        response.health = self.scan_integrity().await?;
+        Ok(response)
+    }
+}
+
+#[async_trait::async_trait]
+impl ServerPlugin for LlmServerPlugin {
+    fn metadata(&self) -> PluginMetadata {
+        self.metadata.clone()
+    }
+
+    fn storage_dir(&self) -> Option<PathBuf> {
+        Some(self.base_dir.clone())
+    }
+
+    async fn download_model(
+        &self,
+        mut request: DownloadModelRequest,
+    ) -> Result<DownloadModelResponse, PluginError> {
+        if request.model_id.trim().is_empty() {
+            return Err(PluginError::InvalidRequest(
+                "model_id is required".to_string(),
+            ));
+        }
+
+        match request.mode {
+            DownloadMode::File if request.filename.trim().is_empty() => {
+                return Err(PluginError::InvalidRequest(
+                    "filename is required".to_string(),
+                ));
+            }
+            DownloadMode::Snapshot if request.node.is_some() => {
+                return Err(PluginError::InvalidRequest(
+                    "snapshots are only downloaded locally".to_string(),
+                ));
+            }
+            _ => {}
+        }
+        if let ModelSource::Object(_) | ModelSource::Peer(_) = &request.source {
+            if request.node.is_some() || request.mode == DownloadMode::Snapshot || request.sidecars
+            {
+                return Err(PluginError::InvalidRequest(
+                    "objects and peer files are downloaded locally as a single file".to_string(),
+                ));
+            }
+        }
+        if request.sidecars && request.node.is_some() {
+            return Err(PluginError::InvalidRequest(
+                "companion files are only downloaded locally".to_string(),
+            ));
+        }
+
+        if let Some(expected) = &request.expected_sha256 {
+            if expected.len() != 64 || !expected.bytes().all(|b| b.is_ascii_hexdigit()) {
+                return Err(PluginError::InvalidRequest(format!(
+                    "expected_sha256 {} is not a hex SHA-256",
+                    expected
+                )));
+            }
+        }
+
+        if self.require_signatures && request.signature.is_none() {
+            request.signature = Some(ModelSignature::default());
+        }
+        if let Some(signature) = &request.signature {
+            self.check_signature_request(&request, signature)?;
+        }
+
+        let adapter = match request.adapter_for.clone() {
+            Some(model) => Some(self.adapter_download(&mut request, &model).await?),
+            None => None,
+        };
+
+        // Peers are on the local network, so they are reachable offline.
+        if !matches!(request.source, ModelSource::Peer(_)) {
+            self.offline.ensure_online("model downloads")?;
+        }
+        let model_id = request.model_id.clone();
+        let filename = request.filename.clone();
+        let node = request.node.clone();
+        let sidecars =
+            (request.sidecars && request.mode == DownloadMode::File).then(|| request.clone());
+        let outcome = match &node {
+            Some(node) => self.download_remote(node, &request).await,
+            None if request.mode == DownloadMode::Snapshot => self.download_snapshot(request).await,
+            None => match (&request.source, SplitName::parse(&request.filename)) {
+                (ModelSource::Hub, Some(split)) => self.download_split(request, split).await,
+                _ => self.download_local(request).await,
+            },
+        };
+        let outcome = match (outcome, sidecars) {
+            (Ok(response), Some(request)) => self.download_sidecars(&request, response).await,
+            (outcome, _) => outcome,
+        };
+        let outcome = match (outcome, adapter) {
+            (Ok(response), Some((model_path, download))) => {
+                self.check_downloaded_adapter(model_path, download, response)
+                    .await
+            }
+            (outcome, _) => outcome,
+        };
+        let response = match outcome {
+            Ok(response) => response,
+            Err(err) => {
+                self.downloads.record_failure();
+                return Err(err);
+            }
+        };
+        self.downloads.record_download(response.bytes_written);
+        self.events.publish(
+            &self.metadata.id,
+            PluginEventKind::ModelDownloaded {
+                model_id,
+                filename,
+                saved_path: response.saved_path.clone(),
+                bytes: response.bytes_written,
+                node,
+            },
+        );
+        Ok(response)
+    }
+
+    async fn list_models(&self) -> Result<ListModelsResponse, PluginError> {
+        Ok(ListModelsResponse {
+            models: self.manifest.lock().await.records().to_vec(),
+        })
+    }
+
+    async fn list_nodes(&self) -> Result<ListNodesResponse, PluginError> {
+        let mut nodes: Vec<_> = self.nodes.values().cloned().collect();
+        nodes.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(ListNodesResponse { nodes })
+    }
+
+    async fn list_profiles(&self) -> Result<ListProfilesResponse, PluginError> {
+        Ok(ListProfilesResponse {
+            profiles: self.profiles.clone(),
+        })
+    }
+
+    async fn list_revisions(
+        &self,
+        request: ModelRevisionsRequest,
+    ) -> Result<ModelRevisionsResponse, PluginError> {
+        if request.model_id.trim().is_empty() {
+            return Err(PluginError::InvalidRequest(
+                "model_id is required".to_string(),
+            ));
+        }
+
+        let key = (request.model_id.clone(), request.revision.clone());
+        if self.offline.is_enabled() {
+            if let Some(cached) = self.revision_cache.lock().await.get(&key) {
+                return Ok(ModelRevisionsResponse {
+                    cached: true,
+                    ..cached.clone()
+                });
+            }
+            let manifest = self.manifest.lock().await;
+            return Ok(ModelRevisionsResponse {
+                branches: revisions::known_refs(&manifest, &request.model_id),
+                model_id: request.model_id,
+                tags: Vec::new(),
+                commits: Vec::new(),
+                cached: true,
+            });
+        }
+
+        let hubs = self.hub_endpoints(&[])?;
+        let client = &self.hub_client(None)?;
+        let model_id = &request.model_id;
+        let (branches, tags) = mirrors::failover(&hubs, &self.breakers, |hub| async move {
+            revisions::list_refs(client, &hub, model_id).await
+        })
+        .await?;
+        let (revision, limit) = (&request.revision, request.limit);
+        let commits = mirrors::failover(&hubs, &self.breakers, |hub| async move {
+            revisions::list_commits(client, &hub, model_id, revision, limit).await
+        })
+        .await?;
+        let response = ModelRevisionsResponse {
+            model_id: request.model_id,
+            branches,
+            tags,
+            commits,
+            cached: false,
+        };
+        self.revision_cache
+            .lock()
+            .await
+            .insert(key, response.clone());
+        Ok(response)
+    }
+
+    async fn check_model_updates(&self) -> Result<ModelUpdatesResponse, PluginError> {
+        if self.offline.is_enabled() {
+            let manifest = self.manifest.lock().await;
+            return Ok(ModelUpdatesResponse {
+                updates: revisions::known_updates(&manifest),
+            });
+        }
+        let updates = revisions::check_updates(
+            &self.metadata.id,
+            &self.hub_client(None)?,
+            &self.hub_endpoints(&[])?,
+            &self.breakers,
+            &self.manifest,
+            &self.events,
+        )
+        .await?;
+        Ok(ModelUpdatesResponse { updates })
+    }
+
+    async fn start_service(
+        &self,
+        request: StartServiceRequest,
+    ) -> Result<StartServiceResponse, PluginError> {
+        if request.model_path.trim().is_empty() {
+            return Err(PluginError::InvalidRequest(
+                "model_path is required".to_string(),
+            ));
+        }
+
+        if let Some(node) = request.node.clone() {
+            if !request.adapters.is_empty() {
+                return Err(PluginError::InvalidRequest(
+                    "adapters are only attached to local services".to_string(),
+                ));
+            }
+            return self.start_remote(&node, request).await;
+        }
+
+        let readiness = request
+            .wait_for_ready
+            .then(|| {
+                ReadinessCheck::resolve(request.readiness.as_ref(), request.health_check.as_ref())
+            })
+            .transpose()?;
+        let binary_path = self.resolve_binary_path(&request)?;
+        self.offline.ensure_local(&request.model_path)?;
+        self.offline.ensure_local(&binary_path.to_string_lossy())?;
+        let command = binary_path.to_string_lossy().to_string();
+        let mut args = self.launch_args(&request)?;
+        let decrypted = self.encryption.open(&request.model_path).await?;
+        let model_file = decrypted
+            .as_ref()
+            .map(|decrypted| decrypted.path().to_string_lossy().to_string())
+            .unwrap_or_else(|| request.model_path.clone());
+        if !request.adapters.is_empty() {
+            args.extend(self.adapter_args(&request, &model_file).await?);
+        }
+        let mut gpu_offload = None;
+        if request.auto_gpu_layers.unwrap_or(true)
+            && offload::applies(&command, &request.model_path, &args)
+        {
+            gpu_offload = offload::tune(&model_file, &args).await;
+            if let Some(chosen) = &gpu_offload {
+                tracing::info!(
+                    "offloading {} of {} layers to the GPU (kv cache on GPU: {})",
+                    chosen.n_gpu_layers,
+                    chosen.total_layers,
+                    chosen.kv_offload
+                );
+                args.extend(chosen.args());
+            }
+        }
+        let mut spec = LaunchSpec {
+            model_path: request.model_path.clone(),
+            command,
+            args,
+            environment: request.environment.clone().unwrap_or_default(),
+            resolved_environment: HashMap::new(),
+            redactor: Arc::default(),
+            health_check: request.health_check.clone(),
+            cpu_affinity: request
+                .cpu_affinity
+                .as_ref()
+                .map(|affinity| affinity.resolve())
+                .transpose()?,
+            limit_adjustments: LimitAdjustments::default(),
+            interactive: request.interactive,
+            stdio: request.stdio,
+            log_retention: request
+                .log_retention
+                .unwrap_or_default()
+                .or(self.config().log_retention.unwrap_or(self.log_retention)),
+            log_dir: self.log_dir().join(request.task_type.as_str()),
+            faults: self.faults.clone(),
+            gpu_offload,
+            decrypted,
+        };
+        spec.preflight()?;
+
+        let reservation = StartReservation::reserve(&self.processes, &request.task_type).await?;
+        let child = spec.spawn()?;
+        let pid = child.id().ok_or_else(|| {
+            PluginError::ProcessStart("failed to obtain process identifier".to_string())
+        })?;
+
+        let instance_id = uuid::Uuid::new_v4().to_string();
+        let mut managed = ManagedProcess::new(
+            self.output_forwarder(&instance_id, &request.task_type),
+            spec,
+            child,
+        );
+        managed.wait_for_startup().await?;
+        if let Some(readiness) = &readiness {
+            managed.wait_until_ready(&self.client, readiness).await?;
+        }
+
+        managed.watchdog = self.spawn_watchdog(&managed);
+
+        let response = StartServiceResponse {
+            pid,
+            ..managed.describe()
+        };
+
+        let mut processes = self.processes.lock().await;
+        reservation.register(&mut processes, instance_id, managed);
+        drop(processes);
+        self.record_usage(&request.model_path).await;
+
+        Ok(response)
+    }
+
+    async fn stop_service(
+        &self,
+        request: StopServiceRequest,
+    ) -> Result<StopServiceResponse, PluginError> {
+        if let Some(remote) = self.remote_instance(&request.service).await {
+            let response = remote.stop().await?;
+            self.remote.lock().await.remove(&remote.instance_id);
+            return Ok(response);
+        }
+
+        let mut processes = self.processes.lock().await;
+        let mut managed = processes.remove(&request.service)?;
+        if let Some(watchdog) = managed.watchdog.take() {
+            watchdog.abort();
+        }
+
+        if let Some(status) = managed.child.try_wait()? {
+            let report = managed.crash_report(status).await;
+            tracing::warn!("{} was found dead on stop: {}", report.command, report);
+            return Ok(StopServiceResponse {
+                instance_id: managed.instance_id,
+                task_type: managed.task_type,
+                terminated: false,
+                crash: Some(report),
+            });
+        }
+
+        managed.terminate().await?;
+
+        Ok(StopServiceResponse {
+            instance_id: managed.instance_id,
+            task_type: managed.task_type,
+            terminated: true,
+            crash: None,
+        })
+    }
+
+    async fn service_logs(
+        &self,
+        request: ServiceLogsRequest,
+    ) -> Result<ServiceLogsResponse, PluginError> {
+        if let Some(remote) = self.remote_instance(&request.service).await {
+            return remote.logs(request.min_level, request.limit).await;
+        }
+
+        let processes = self.processes.lock().await;
+        let managed = processes.get(&request.service)?;
+        let entries = managed
+            .logs
+            .buffer
+            .lock()
+            .map_err(|_| PluginError::Internal("log buffer poisoned".to_string()))?
+            .query(request.min_level, request.limit);
+
+        Ok(ServiceLogsResponse {
+            instance_id: managed.instance_id.clone(),
+            task_type: managed.task_type.clone(),
+            entries,
+        })
+    }
+
+    async fn upgrade_service(
+        &self,
+        request: UpgradeServiceRequest,
+    ) -> Result<UpgradeServiceResponse, PluginError> {
+        if self.remote_instance(&request.service).await.is_some() {
+            return Err(PluginError::InvalidRequest(
+                "upgrades are not supported for services on remote nodes".to_string(),
+            ));
+        }
+
+        let (instance_id, task_type, previous_spec) = {
+            let processes = self.processes.lock().await;
+            let managed = processes.get(&request.service)?;
+            (
+                managed.instance_id.clone(),
+                managed.task_type.clone(),
+                managed.spec.clone(),
+            )
+        };
+
+        let download = match &request.download {
+            Some(download) => Some(self.download_model(download.clone()).await?),
+            None => None,
+        };
+        let model_path = match (&download, &request.model_path) {
+            (Some(download), _) => download.saved_path.clone(),
+            (None, Some(path)) if !path.trim().is_empty() => path.clone(),
+            _ => {
+                return Err(PluginError::InvalidRequest(
+                    "either download or model_path is required".to_string(),
+                ))
+            }
+        };
+
+        let mut spec = Self::upgrade_spec(&previous_spec, &request, model_path);
+        self.offline.ensure_local(&spec.model_path)?;
+        self.offline.ensure_local(&spec.command)?;
+        spec.decrypted = self.encryption.open(&spec.model_path).await?;
+        spec.preflight()?;
+        let child = spec.spawn()?;
+        let mut green =
+            ManagedProcess::new(self.output_forwarder(&instance_id, &task_type), spec, child);
+        green.wait_for_startup().await?;
+
+        let smoke_test = match self.smoke_test(&mut green, &request.smoke_test).await {
+            Ok(result) => result,
+            Err(err) => {
+                tracing::warn!(
+                    "rolling back upgrade of {:?} instance {}, keeping previous instance: {}",
+                    task_type,
+                    instance_id,
+                    err
+                );
+                green.terminate().await?;
+                return Err(err);
+            }
+        };
+
+        green.watchdog = self.spawn_watchdog(&green);
+        let current = green.describe();
+        let model_path = green.spec.model_path.clone();
+        let blue = {
+            let mut processes = self.processes.lock().await;
+            processes.instances.insert(instance_id.clone(), green)
+        };
+
+        let previous = match blue {
+            Some(mut blue) => {
+                let previous = blue.describe();
+                if let Err(err) = blue.terminate().await {
+                    tracing::warn!("failed to retire previous instance: {}", err);
+                }
+                previous
+            }
+            None => StartServiceResponse {
+                instance_id: instance_id.clone(),
+                task_type: task_type.clone(),
+                pid: 0,
+                args: previous_spec.display_args(),
+                command: previous_spec.command,
+                node: None,
+                gpu_offload: previous_spec.gpu_offload,
+                fallback: Vec::new(),
+            },
+        };
+
+        self.record_usage(&model_path).await;
+
+        Ok(UpgradeServiceResponse {
+            instance_id,
+            task_type,
+            previous,
+            current,
+            download,
+            smoke_test,
+        })
+    }
+
+    async fn follow_logs(
+        &self,
+        request: FollowLogsRequest,
+    ) -> Result<FollowLogsSession, PluginError> {
+        if self.remote_instance(&request.service).await.is_some() {
+            return Err(PluginError::InvalidRequest(
+                "following logs is not supported for services on remote nodes".to_string(),
+            ));
+        }
+
+        let processes = self.processes.lock().await;
+        let managed = processes.get(&request.service)?;
+        Ok(FollowLogsSession {
+            instance_id: managed.instance_id.clone(),
+            task_type: managed.task_type.clone(),
+            follower: managed
+                .logs
+                .follow(request.after, request.tail, request.min_level),
+        })
+    }
+
+    async fn service_status(
+        &self,
+        request: ServiceStatusRequest,
+    ) -> Result<ServiceStatusResponse, PluginError> {
+        if let Some(remote) = self.remote_instance(&request.service).await {
+            return remote.status().await;
+        }
+
+        let processes = self.processes.lock().await;
+        Ok(processes.get(&request.service)?.status())
+    }
+
+    async fn list_services(&self) -> Result<ListServicesResponse, PluginError> {
+        let processes = self.processes.lock().await;
+        let mut services: Vec<_> = processes
+            .instances
+            .values()
+            .map(ManagedProcess::status)
+            .collect();
+        services.sort_by(|a, b| a.instance_id.cmp(&b.instance_id));
+        Ok(ListServicesResponse { services })
+    }
+
+    async fn list_log_files(&self) -> Result<ListLogFilesResponse, PluginError> {
+        Ok(ListLogFilesResponse {
+            files: logs::list_files(&self.log_dir()).await?,
+        })
+    }
+
+    async fn health(&self) -> Result<PluginHealthResponse, PluginError> {
+        let mut checks = Vec::new();
+        {
+            let mut processes = self.processes.lock().await;
+            for managed in processes.instances.values_mut() {
+                let exited = match managed.child.try_wait() {
+                    Ok(Some(status)) => Some(format!("process exited with {}", status)),
+                    Ok(None) => None,
+                    Err(err) => Some(err.to_string()),
+                };
+                let task = TaskHealth {
+                    instance_id: managed.instance_id.clone(),
+                    task_type: managed.task_type.clone(),
+                    alive: exited.is_none(),
+                    responsive: None,
+                    reason: exited,
+                    health: managed.health,
+                    consecutive_failures: managed.consecutive_failures,
+                    restarts: managed.restarts,
+                    last_health_check: managed.last_health_check,
+                    node: None,
+                };
+                checks.push((task, managed.spec.health_check.clone()));
+            }
+        }
+
+        // Probe without holding the process table so slow endpoints do not
+        // block other operations.
+        let mut tasks = futures::future::join_all(checks.into_iter().map(|(mut task, config)| {
+            let client = self.client.clone();
+            async move {
+                if let (true, Some(config)) = (task.alive, config) {
+                    let outcome = health::probe(&client, &config).await;
+                    task.responsive = Some(outcome.is_ok());
+                    task.reason = outcome.err();
+                }
+                task
+            }
+        }))
+        .await;
+
+        let remote: Vec<_> = self.remote.lock().await.values().cloned().collect();
+        for instance in remote {
+            let task = match instance.status().await {
+                Ok(status) => TaskHealth {
+                    instance_id: status.instance_id,
+                    task_type: status.task_type,
+                    alive: status.pid.is_some(),
+                    responsive: None,
+                    reason: None,
+                    health: status.health,
+                    consecutive_failures: status.consecutive_failures,
+                    restarts: status.restarts,
+                    last_health_check: status.last_health_check,
+                    node: status.node,
+                },
+                Err(err) => TaskHealth {
+                    instance_id: instance.instance_id.clone(),
+                    task_type: instance.task_type.clone(),
+                    alive: false,
+                    responsive: None,
+                    reason: Some(err.to_string()),
+                    health: ServiceHealth::Unhealthy,
+                    consecutive_failures: 0,
+                    restarts: 0,
+                    last_health_check: None,
+                    node: Some(instance.node.id.clone()),
+                },
+            };
+            tasks.push(task);
+        }
+        tasks.sort_by(|a, b| a.instance_id.cmp(&b.instance_id));
+        Ok(PluginHealthResponse::new(self.metadata.id.clone(), tasks))
+    }
+
+    async fn metrics(&self) -> Result<PluginMetrics, PluginError> {
+        let local = self.processes.lock().await.instances.len();
+        let remote = self.remote.lock().await.len();
+        let models = self.manifest.lock().await.records().len();
+        Ok(self
+            .downloads
+            .metrics()
+            .gauge(metrics::RUNNING_PROCESSES, (local + remote) as f64)
+            .gauge(metrics::STORED_MODELS, models as f64))
+    }
+
+    async fn signal_service(
+        &self,
+        request: SignalServiceRequest,
+    ) -> Result<SignalServiceResponse, PluginError> {
+        if let Some(remote) = self.remote_instance(&request.service).await {
+            return remote.signal(request.signal).await;
+        }
+
+        let mut processes = self.processes.lock().await;
+        let managed = processes.get_mut(&request.service)?;
+        if managed.child.try_wait()?.is_some() {
+            return Err(PluginError::ProcessNotRunning(managed.task_type.clone()));
+        }
+
+        let pid = managed.child.id().unwrap_or_default();
+        signals::send(&mut managed.child, request.signal)?;
+        tracing::info!(
+            "sent {} to {:?} instance {} (pid {})",
+            request.signal,
+            managed.task_type,
+            managed.instance_id,
+            pid
+        );
+
+        Ok(SignalServiceResponse {
+            instance_id: managed.instance_id.clone(),
+            task_type: managed.task_type.clone(),
+            pid,
+            signal: request.signal,
+        })
+    }
+
+    async fn attach_console(
+        &self,
+        request: AttachConsoleRequest,
+    ) -> Result<ConsoleSession, PluginError> {
+        if self.remote_instance(&request.service).await.is_some() {
+            return Err(PluginError::InvalidRequest(
+                "consoles are not supported for services on remote nodes".to_string(),
+            ));
+        }
+
+        let processes = self.processes.lock().await;
+        let managed = processes.get(&request.service)?;
+        let input = managed.console.clone().ok_or_else(|| {
+            PluginError::InvalidRequest(format!(
+                "instance {} was not started with interactive stdin",
+                managed.instance_id
+            ))
+        })?;
+
+        Ok(ConsoleSession {
+            instance_id: managed.instance_id.clone(),
+            task_type: managed.task_type.clone(),
+            input,
+            output: managed.logs.subscribe(),
+        })
+    }
+
+    async fn collect_models(
+        &self,
+        request: ModelGcRequest,
+    ) -> Result<ModelGcResponse, PluginError> {
+        let min_idle = request
+            .min_idle_secs
+            .map(Duration::from_secs)
+            .unwrap_or(self.gc_policy.min_idle);
+        let in_use = models_in_use(&self.processes).await;
+        let mut manifest = self.manifest.lock().await;
+        gc::collect(&mut manifest, &in_use, min_idle, request.confirm).await
+    }
+
+    async fn invoke(
+        &self,
+        operation: &str,
+        payload: serde_json::Value,
+        context: InvokeContext,
+    ) -> Result<serde_json::Value, PluginError> {
+        match operation {
+            operations::MODEL_USAGE => operations::respond(self.model_usage().await),
+            operations::SEARCH_MODELS => operations::respond(
+                self.search_models(operations::request(operation, payload)?)
+                    .await,
+            ),
+            operations::LIST_MODEL_FILES => operations::respond(
+                self.list_model_files(operations::request(operation, payload)?)
+                    .await,
+            ),
+            operations::VALIDATE_TOKEN => operations::respond(
+                self.validate_token(operations::request(operation, payload)?)
+                    .await,
+            ),
+            operations::DELETE_MODEL => operations::respond(
+                self.delete_model(operations::request(operation, payload)?)
+                    .await,
+            ),
+            operations::PIN_MODEL => operations::respond(
+                self.pin_model(operations::request(operation, payload)?)
+                    .await,
+            ),
+            operations::LIST_ADAPTERS => operations::respond(
+                self.list_adapters(operations::request(operation, payload)?)
+                    .await,
+            ),
+            operations::CONVERT_MODEL => {
+                let request = ConvertModelRequest {
+                    cancel: context.cancel,
+                    progress: context.progress,
+                    ..operations::request(operation, payload)?
+                };
+                operations::respond(self.convert_model(request).await)
+            }
+            operations::QUANTIZE_MODEL => {
+                let request = QuantizeModelRequest {
+                    cancel: context.cancel,
+                    progress: context.progress,
+                    ..operations::request(operation, payload)?
+                };
+                operations::respond(self.quantize_model(request).await)
+            }
+            operations::IMPORT_MODEL => operations::respond(
+                self.import_model(operations::request(operation, payload)?)
+                    .await,
+            ),
+            operations::RESTORE_MODELS => {
+                let request = RestoreModelsRequest {
+                    cancel: context.cancel,
+                    ..operations::request(operation, payload)?
+                };
+                operations::respond(self.restore_models(request).await)
+            }
+            operations::MODEL_HEALTH => operations::respond(self.model_health().await),
+            operations::REPAIR_MODELS => {
+                let request = RepairModelsRequest {
+                    cancel: context.cancel,
+                    ..operations::request(operation, payload)?
+                };
+                operations::respond(self.repair_models(request).await)
+            }
+            _ => Err(PluginError::UnsupportedOperation),
+        }
+    }
+
+    async fn shutdown(&self) -> Result<Vec<String>, PluginError> {
+        for task in self.background.iter() {
+            task.abort();
+        }
+        let mut stopped = Vec::new();
+        let remote: Vec<_> = self.remote.lock().await.drain().map(|(_, r)| r).collect();
+        for instance in remote {
+            match instance.stop().await {
+                Ok(_) => stopped.push(instance.instance_id),
+                Err(err) => tracing::warn!(
+                    "failed to stop remote instance {}: {}",
+                    instance.instance_id,
+                    err
+                ),
+            }
+        }
+        let local: Vec<_> = self.processes.lock().await.instances.drain().collect();
+        for (instance_id, mut managed) in local {
+            match managed.terminate().await {
+                Ok(()) => stopped.push(instance_id),
+                Err(err) => tracing::warn!("failed to stop instance {}: {}", instance_id, err),
+            }
+        }
+        Ok(stopped)
+    }
+
+    fn config_schema(&self) -> serde_json::Value {
+        serde_json::to_value(schemars::schema_for!(LlmServerConfig)).unwrap_or_default()
+    }
+
+    async fn configure(&self, config: &PluginConfig) -> Result<(), PluginError> {
+        let parsed: LlmServerConfig = serde_json::from_value(config.values.clone().into())
+            .map_err(|err| PluginError::InvalidRequest(format!("invalid config: {}", err)))?;
+        if let Some(endpoints) = &parsed.hub_endpoints {
+            HubEndpoints::parse(endpoints)?;
+        }
+        let hub_client = download_client(parsed.proxy.as_ref().or(self.proxy.as_ref()))?;
+        *self.download_client.write().expect("download client") = hub_client;
+        *self.config.write().expect("plugin config") = parsed;
+        Ok(())
+    }
+
+    async fn hand_over(&self) -> Result<Handover, PluginError> {
+        let mut instances: HashMap<_, _> = self.processes.lock().await.instances.drain().collect();
+        for managed in instances.values_mut() {
+            if let Some(watchdog) = managed.watchdog.take() {
+                watchdog.abort();
+            }
+        }
+        let remote = self.remote.lock().await.drain().collect();
+        Ok(Box::new(LlmServerHandover { instances, remote }))
+    }
+
+    async fn take_over(&self, handover: Handover) -> Result<Vec<String>, PluginError> {
+        let handover = handover
+            .downcast::<LlmServerHandover>()
+            .map_err(|_| PluginError::InvalidRequest("services of another plugin".to_string()))?;
+        let mut adopted: Vec<String> = handover.remote.keys().cloned().collect();
+        self.remote.lock().await.extend(handover.remote);
+        let mut processes = self.processes.lock().await;
+        for (instance_id, mut managed) in handover.instances {
+            managed.watchdog = self.spawn_watchdog(&managed);
+            processes.instances.insert(instance_id.clone(), managed);
+            adopted.push(instance_id);
+        }
+        Ok(adopted)
+    }
+
+    async fn save_state(&self) -> Result<Option<serde_json::Value>, PluginError> {
+        let services: Vec<_> = self
+            .processes
+            .lock()
+            .await
+            .instances
+            .values()
+            .map(ManagedProcess::relaunch_request)
+            .collect();
+        if services.is_empty() {
+            return Ok(None);
+        }
+        serde_json::to_value(LlmServerState { services })
+            .map(Some)
+            .map_err(|err| PluginError::Internal(err.to_string()))
+    }
+
+    async fn restore_state(&self, state: serde_json::Value) -> Result<(), PluginError> {
+        let state: LlmServerState = serde_json::from_value(state)
+            .map_err(|err| PluginError::InvalidRequest(format!("invalid saved state: {}", err)))?;
+        for request in state.services {
+            let task_type = request.task_type.clone();
+            match self.start_service(request).await {
+                Ok(started) => tracing::info!(
+                    "restarted {:?} service as {}",
+                    task_type,
+                    started.instance_id
+                ),
+                Err(err) => tracing::warn!("failed to restart {:?} service: {}", task_type, err),
+            }
+        }
+        Ok(())
+    }
+
+    async fn prune_logs(&self) -> Result<ServiceLogsPruned, PluginError> {
+        let mut entries_removed = 0;
+        {
+            let processes = self.processes.lock().await;
+            for managed in processes.instances.values() {
+                entries_removed += managed
+                    .logs
+                    .buffer
+                    .lock()
+                    .map_err(|_| PluginError::Internal("log buffer poisoned".to_string()))?
+                    .prune();
+            }
+        }
+        let log_dir = self.log_dir();
+        let retention = self.config().log_retention.unwrap_or(self.log_retention);
+        let files = tokio::task::spawn_blocking(move || logs::prune_files(&log_dir, retention))
+            .await
+            .map_err(|err| PluginError::Internal(err.to_string()))??;
+
+        Ok(ServiceLogsPruned {
+            plugin_id: self.metadata.id.clone(),
+            entries_removed,
+            files,
+        })
+    }
+}
+
+/// Model files backing running local services.
+async fn models_in_use(processes: &ProcessTable) -> HashSet<String> {
+    processes
+        .lock()
+        .await
+        .instances
+        .values()
+        .map(|managed| managed.spec.model_path.clone())
+        .collect()
+}
+
+/// Refuses a download the destination has no room for, `required`
+/// being what is left to fetch.
+fn check_disk_space(partial_path: &Path, required: u64) -> Result<(), PluginError> {
+    // The destination directory is only created once the transfer starts.
+    let Some(dir) = partial_path.ancestors().skip(1).find(|dir| dir.exists()) else {
+        return Ok(());
+    };
+    let available = match limits::available_space(dir) {
+        Ok(Some(available)) => available,
+        Ok(None) => return Ok(()),
+        Err(err) => {
+            tracing::debug!("not checking disk space of {}: {}", dir.display(), err);
+            return Ok(());
+        }
+    };
+    if required > available {
+        return Err(PluginError::InsufficientDiskSpace {
+            path: dir.to_string_lossy().to_string(),
+            required,
+            available,
+        });
+    }
+    Ok(())
+}
+
+/// Whether `response` continues a download at `offset`: a partial response
+/// starting where the file ends. Hosts serve ranges without announcing them
+/// in `Accept-Ranges`, so the `Content-Range` is what counts.
+fn resumes_at(response: &reqwest::Response, offset: u64) -> bool {
+    let start = content_range(response)
+        .and_then(|range| range.split_once('-'))
+        .and_then(|(start, _)| start.trim().parse::<u64>().ok());
+    response.status() == reqwest::StatusCode::PARTIAL_CONTENT && start == Some(offset)
+}
+
+/// Length of the file a resume asked for bytes past the end of, as the
+/// `Content-Range: bytes */LENGTH` of its 416 response reports it.
+fn complete_length(response: &reqwest::Response) -> Option<u64> {
+    if response.status() != reqwest::StatusCode::RANGE_NOT_SATISFIABLE {
+        return None;
+    }
+    content_range(response)?
+        .strip_prefix("*/")?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// The `Content-Range` of `response` without its `bytes ` unit.
+fn content_range(response: &reqwest::Response) -> Option<&str> {
+    response
+        .headers()
+        .get(reqwest::header::CONTENT_RANGE)?
+        .to_str()
+        .ok()?
+        .strip_prefix("bytes ")
+}
+
+/// The ETag of a response when it is strong enough for `If-Range`.
+fn strong_etag(headers: &reqwest::header::HeaderMap) -> Option<&str> {
+    headers
+        .get(reqwest::header::ETAG)
+        .and_then(|value| value.to_str().ok())
+        .filter(|etag| !etag.starts_with("W/"))
+}
+
+/// Where the ETag of the file a `.part` file holds the start of is kept,
+/// so that resuming it never appends the rest of a changed file. Hidden,
+/// so model scans pass it over.
+fn validator_path(partial: &Path) -> PathBuf {
+    let mut name = std::ffi::OsString::from(".");
+    name.push(partial.file_name().unwrap_or_default());
+    name.push(".etag");
+    partial.with_file_name(name)
+}
+
+/// Whether a host announces that it serves byte ranges.
+fn accepts_byte_ranges(headers: &reqwest::header::HeaderMap) -> bool {
+    headers
+        .get(reqwest::header::ACCEPT_RANGES)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.trim().eq_ignore_ascii_case("bytes"))
+}
+
+/// Feeds the first `length` bytes of `path` to `hasher`.
+async fn hash_prefix(path: &Path, length: u64, hasher: &mut Sha256) -> std::io::Result<()> {
+    let mut reader = fs::File::open(path).await?.take(length);
+    let mut buffer = vec![0; 1 << 20];
+    loop {
+        let read = reader.read(&mut buffer).await?;
+        if read == 0 {
+            return Ok(());
+        }
+        hasher.update(&buffer[..read]);
+    }
+}
+
+/// Creates a symbolic link at `link` pointing to the file `target`.
+#[cfg(unix)]
+async fn symlink_file(target: &Path, link: &Path) -> std::io::Result<()> {
+    fs::symlink(target, link).await
+}
+
+#[cfg(windows)]
+async fn symlink_file(target: &Path, link: &Path) -> std::io::Result<()> {
+    fs::symlink_file(target, link).await
+}
+
+/// The URL of a detached signature a request names.
+fn parse_signature_url(url: &str) -> Result<reqwest::Url, PluginError> {
+    let parsed = reqwest::Url::parse(url).map_err(|err| {
+        PluginError::InvalidRequest(format!("invalid signature URL {}: {}", url, err))
+    })?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(PluginError::InvalidRequest(format!(
+            "invalid signature URL {}: an http(s) URL is required",
+            url
+        )));
+    }
+    Ok(parsed)
+}
+
+/// A download of the file `origin` describes, from the hub.
+fn redownload(
+    origin: ModelOrigin,
+    auth_token: Option<String>,
+    hub_endpoints: Vec<String>,
+    cancel: CancellationToken,
+) -> DownloadModelRequest {
+    DownloadModelRequest {
+        model_id: origin.model_id,
+        filename: origin.filename,
+        revision: origin.revision,
+        destination_dir: origin.destination_dir,
+        auth_token,
+        task_type: origin.task_type,
+        node: None,
+        expected_sha256: origin.sha256,
+        max_bytes_per_sec: None,
+        mode: DownloadMode::File,
+        transfer: DownloadTransfer::Standard,
+        include: Vec::new(),
+        exclude: Vec::new(),
+        gguf_splits: GgufSplits::Keep,
+        sidecars: false,
+        adapter_for: None,
+        hub_endpoints,
+        source: ModelSource::Hub,
+        proxy: None,
+        signature: None,
+        start_after: None,
+        schedule: None,
+        cancel,
+        progress: DownloadProgress::new(),
+        partial: PartialDownload::default(),
+    }
+}
+
+/// Deletes idle models every `interval` under the configured policy.
+fn spawn_model_gc(
+    manifest: Arc<Mutex<ModelManifest>>,
+    processes: ProcessTable,
+    min_idle: Duration,
+    interval: Duration,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(interval).await;
+            let in_use = models_in_use(&processes).await;
+            let mut manifest = manifest.lock().await;
+            match gc::collect(&mut manifest, &in_use, min_idle, true).await {
+                Ok(report) if report.deleted && !report.candidates.is_empty() => {
+                    tracing::info!("model garbage collection freed {} bytes", report.bytes)
+                }
+                Ok(_) => {}
+                Err(err) => tracing::warn!("model garbage collection failed: {}", err),
+            }
+        }
+    })
+}
+
+impl LlmServerPlugin {
+    #[allow(dead_code)] // Used by tests of downstream crates
+    pub fn base_dir(&self) -> &Path {
+        &self.base_dir
+    }
+
+    /// Holds the log files of services, one directory per task type.
+    fn log_dir(&self) -> PathBuf {
+        self.base_dir.join("logs")
+    }
+}
+
+#[allow(dead_code)] // Used by tests of downstream crates
+pub type SharedLlmServerPlugin = Arc<LlmServerPlugin>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::extract::State;
+    use axum::http::{header, HeaderMap, StatusCode};
+    use axum::response::{IntoResponse, Response};
+    use axum::routing::{get, post};
+    use axum::Router;
+    use serde_json::json;
+
+    async fn plugin(base_dir: &Path) -> LlmServerPlugin {
+        LlmServerPlugin::bootstrap_in(
+            LlmServerPlugin::default_metadata(),
+            base_dir.to_path_buf(),
+            EventBus::default(),
+            OfflineMode::default(),
+        )
+        .await
+        .unwrap()
+    }
+
+    fn model_path(base_dir: &Path) -> String {
+        base_dir
+            .join("text")
+            .join("model.gguf")
+            .display()
+            .to_string()
+    }
+
+    /// Starts `sleep` as a text service serving an empty model file.
+    async fn sleeper(base_dir: &Path, extra: serde_json::Value) -> StartServiceRequest {
+        let model_path = model_path(base_dir);
+        fs::write(&model_path, b"").await.unwrap();
+        let mut request = json!({
+            "task_type": "text",
+            "model_path": model_path,
+            "binary_path": "sleep",
+            "args": ["30"],
+        });
+        request
+            .as_object_mut()
+            .unwrap()
+            .extend(extra.as_object().cloned().unwrap_or_default());
+        serde_json::from_value(request).unwrap()
+    }
+
+    /// Serves a completion endpoint answering every prompt with `hello`.
+    async fn serve_completions() -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/v1/completions", listener.local_addr().unwrap());
+        let app = Router::new().route("/v1/completions", post(|| async { "hello" }));
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        url
+    }
+
+    /// The `Range` and `If-Range` of a request.
+    type RangeHeaders = (Option<String>, Option<String>);
+
+    /// A host serving `content` as `/model.gguf` in byte ranges, tagged
+    /// `etag`. Records the `Range` and `If-Range` of each GET.
+    #[derive(Clone)]
+    struct ObjectHost {
+        content: Arc<Vec<u8>>,
+        etag: &'static str,
+        /// Whether responses carry `Accept-Ranges: bytes`.
+        announces_ranges: bool,
+        /// Start of a range answered with the whole file instead.
+        ignored_range: Option<u64>,
+        /// Start of a range never answered.
+        stalled_range: Option<u64>,
+        requests: Arc<std::sync::Mutex<Vec<RangeHeaders>>>,
+    }
+
+    impl ObjectHost {
+        fn new(content: Vec<u8>, etag: &'static str) -> Self {
+            Self {
+                content: Arc::new(content),
+                etag,
+                announces_ranges: false,
+                ignored_range: None,
+                stalled_range: None,
+                requests: Arc::default(),
+            }
+        }
+
+        async fn object(
+            State(host): State<ObjectHost>,
+            method: axum::http::Method,
+            headers: HeaderMap,
+        ) -> Response {
+            let header = |name| {
+                headers
+                    .get(name)
+                    .and_then(|value| value.to_str().ok())
+                    .map(str::to_string)
+            };
+            let (range, if_range) = (header(header::RANGE), header(header::IF_RANGE));
+            if method == axum::http::Method::GET {
+                host.requests
+                    .lock()
+                    .unwrap()
+                    .push((range.clone(), if_range.clone()));
+            }
+            let len = host.content.len() as u64;
+            let range = range
+                .filter(|_| if_range.as_deref().is_none_or(|tag| tag == host.etag))
+                .and_then(|range| {
+                    let (start, end) = range.strip_prefix("bytes=")?.split_once('-')?;
+                    let end = end.parse::<u64>().map_or(len, |end| (end + 1).min(len));
+                    Some((start.parse::<u64>().ok()?, end))
+                })
+                .filter(|(start, _)| host.ignored_range != Some(*start));
+            if range.is_some_and(|(start, _)| host.stalled_range == Some(start)) {
+                std::future::pending::<()>().await;
+            }
+            let response = match range {
+                None => (StatusCode::OK, host.content.to_vec()).into_response(),
+                Some((start, _)) if start >= len => (
+                    StatusCode::RANGE_NOT_SATISFIABLE,
+                    [(header::CONTENT_RANGE, format!("bytes */{}", len))],
+                )
+                    .into_response(),
+                Some((start, end)) => (
+                    StatusCode::PARTIAL_CONTENT,
+                    [(
+                        header::CONTENT_RANGE,
+                        format!("bytes {}-{}/{}", start, end - 1, len),
+                    )],
+                    host.content[start as usize..end as usize].to_vec(),
+                )
+                    .into_response(),
+            };
+            let accept_ranges = if host.announces_ranges {
+                "bytes"
+            } else {
+                "none"
+            };
+            (
+                [
+                    (header::ETAG, host.etag),
+                    (header::ACCEPT_RANGES, accept_ranges),
+                ],
+                response,
+            )
+                .into_response()
+        }
+
+        /// Serves the object, returning a request downloading it as the
+        /// text model `model.gguf`.
+        async fn serve(&self) -> DownloadModelRequest {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let url = format!("http://{}/model.gguf", listener.local_addr().unwrap());
+            let app = Router::new()
+                .route("/model.gguf", get(Self::object))
+                .with_state(self.clone());
+            tokio::spawn(async move { axum::serve(listener, app).await });
+            serde_json::from_value(json!({
+                "model_id": "org/model",
+                "filename": "model.gguf",
+                "task_type": "text",
+                "source": {"kind": "object", "url": url},
+            }))
+            .unwrap()
+        }
+
+        fn requests(&self) -> Vec<RangeHeaders> {
+            self.requests.lock().unwrap().clone()
+        }
+    }
+
+    /// An address nothing listens on.
+    fn unreachable_url() -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        format!("http://{}/health", listener.local_addr().unwrap())
+    }
+
+    #[tokio::test]
+    async fn watchdog_restarts_unresponsive_services() {
+        let dir = tempfile::tempdir().unwrap();
+        let plugin = plugin(dir.path()).await;
+        let mut events = plugin.events.subscribe();
+        let request = sleeper(
+            dir.path(),
+            json!({
+                "health_check": {
+                    "url": unreachable_url(),
+                    "interval_secs": 1,
+                    "timeout_secs": 1,
+                    "failure_threshold": 1,
+                    "restart_policy": "on_unhealthy",
+                    "max_restarts": 1,
+                }
+            }),
+        )
+        .await;
+        let started = plugin.start_service(request).await.unwrap();
+
+        let mut unhealthy = false;
+        let (instance_id, pid, restarts) = tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                match events.recv().await.unwrap().event {
+                    PluginEventKind::ServiceUnhealthy { .. } => unhealthy = true,
+                    PluginEventKind::ServiceRestarted {
+                        instance_id,
+                        pid,
+                        restarts,
+                        ..
+                    } => break (instance_id, pid, restarts),
+                    _ => {}
+                }
+            }
+        })
+        .await
+        .unwrap();
+        assert!(unhealthy);
+        assert_eq!(instance_id, started.instance_id);
+        assert_ne!(pid, started.pid);
+        assert_eq!(restarts, 1);
+
+        let selector = ServiceSelector::instance(started.instance_id.clone());
+        let status = plugin
+            .service_status(ServiceStatusRequest {
+                service: selector.clone(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(status.pid, Some(pid));
+        assert_eq!(status.restarts, 1);
+
+        plugin
+            .stop_service(StopServiceRequest { service: selector })
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn upgrades_switch_to_the_green_instance_once_it_passes() {
+        let dir = tempfile::tempdir().unwrap();
+        let plugin = plugin(dir.path()).await;
+        let blue = plugin
+            .start_service(sleeper(dir.path(), json!({})).await)
+            .await
+            .unwrap();
+        let selector = ServiceSelector::instance(blue.instance_id.clone());
+
+        let upgraded = plugin
+            .upgrade_service(UpgradeServiceRequest {
+                service: selector.clone(),
+                download: None,
+                model_path: Some(model_path(dir.path())),
+                binary_path: None,
+                args: None,
+                environment: None,
+                health_check: None,
+                smoke_test: serde_json::from_value(json!({
+                    "url": serve_completions().await,
+                    "expect_contains": "hello",
+                }))
+                .unwrap(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(upgraded.instance_id, blue.instance_id);
+        assert_eq!(upgraded.previous.pid, blue.pid);
+        assert_ne!(upgraded.current.pid, blue.pid);
+        assert_eq!(upgraded.smoke_test.output_excerpt, "hello");
+
+        let status = plugin
+            .service_status(ServiceStatusRequest {
+                service: selector.clone(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(status.pid, Some(upgraded.current.pid));
+        assert_eq!(plugin.list_services().await.unwrap().services.len(), 1);
+
+        plugin
+            .stop_service(StopServiceRequest { service: selector })
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn failed_smoke_tests_keep_the_blue_instance() {
+        let dir = tempfile::tempdir().unwrap();
+        let plugin = plugin(dir.path()).await;
+        let blue = plugin
+            .start_service(sleeper(dir.path(), json!({})).await)
+            .await
+            .unwrap();
+        let selector = ServiceSelector::instance(blue.instance_id.clone());
+
+        let err = plugin
+            .upgrade_service(UpgradeServiceRequest {
+                service: selector.clone(),
+                download: None,
+                model_path: Some(model_path(dir.path())),
+                binary_path: None,
+                args: None,
+                environment: None,
+                health_check: None,
+                smoke_test: serde_json::from_value(json!({
+                    "url": unreachable_url(),
+                    "timeout_secs": 1,
+                    "ready_timeout_secs": 1,
+                }))
+                .unwrap(),
+            })
+            .await
+            .unwrap_err();
+        assert!(matches!(err, PluginError::SmokeTestFailed(_)), "{}", err);
+
+        let status = plugin
+            .service_status(ServiceStatusRequest {
+                service: selector.clone(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(status.pid, Some(blue.pid));
+
+        plugin
+            .stop_service(StopServiceRequest { service: selector })
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn services_are_addressed_by_instance_or_task_type() {
+        let dir = tempfile::tempdir().unwrap();
+        let plugin = plugin(dir.path()).await;
+        let text = plugin
+            .start_service(sleeper(dir.path(), json!({})).await)
+            .await
+            .unwrap();
+        let tts = plugin
+            .start_service(sleeper(dir.path(), json!({"task_type": "tts"})).await)
+            .await
+            .unwrap();
+        assert_ne!(text.instance_id, tts.instance_id);
+        assert!(matches!(
+            plugin
+                .start_service(sleeper(dir.path(), json!({})).await)
+                .await,
+            Err(PluginError::ProcessAlreadyRunning(_))
+        ));
+
+        let by_task_type = plugin
+            .service_status(ServiceStatusRequest {
+                service: ServiceSelector::task_type(PluginTaskType::TTS),
+            })
+            .await
+            .unwrap();
+        assert_eq!(by_task_type.instance_id, tts.instance_id);
+        assert!(matches!(
+            plugin
+                .service_status(ServiceStatusRequest {
+                    service: ServiceSelector {
+                        instance_id: Some(text.instance_id.clone()),
+                        task_type: Some(PluginTaskType::TTS),
+                    },
+                })
+                .await,
+            Err(PluginError::InvalidRequest(_))
+        ));
+        assert!(matches!(
+            plugin
+                .service_status(ServiceStatusRequest {
+                    service: ServiceSelector::instance("unknown"),
+                })
+                .await,
+            Err(PluginError::InstanceNotFound(_))
+        ));
+
+        let stopped = plugin
+            .stop_service(StopServiceRequest {
+                service: ServiceSelector::instance(tts.instance_id.clone()),
+            })
+            .await
+            .unwrap();
+        assert_eq!(stopped.instance_id, tts.instance_id);
+        assert!(matches!(
+            plugin
+                .service_status(ServiceStatusRequest {
+                    service: ServiceSelector::task_type(PluginTaskType::TTS),
+                })
+                .await,
+            Err(PluginError::ProcessNotRunning(_))
+        ));
+        let services = plugin.list_services().await.unwrap().services;
+        assert_eq!(services.len(), 1);
+        assert_eq!(services[0].instance_id, text.instance_id);
+
+        plugin
+            .stop_service(StopServiceRequest {
+                service: ServiceSelector::task_type(PluginTaskType::TEXT),
+            })
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn model_operations_run_through_invoke() {
+        let dir = tempfile::tempdir().unwrap();
+        let plugin = plugin(dir.path()).await;
+        let usage: ModelUsageResponse = operations::invoke(
+            &plugin,
+            operations::MODEL_USAGE,
+            &(),
+            InvokeContext::default(),
+        )
+        .await
+        .unwrap();
+        assert!(usage.directories.iter().all(|dir| dir.used_bytes == 0));
+
+        let invalid = plugin
+            .invoke(
+                operations::DELETE_MODEL,
+                json!({"filename": 3}),
+                InvokeContext::default(),
+            )
+            .await;
+        assert!(matches!(invalid, Err(PluginError::InvalidRequest(_))));
+        let unknown = plugin
+            .invoke("list-voices", json!({}), InvokeContext::default())
+            .await;
+        assert!(matches!(unknown, Err(PluginError::UnsupportedOperation)));
+    }
+
+    #[tokio::test]
+    async fn partial_downloads_resume_while_the_file_is_unchanged() {
+        let dir = tempfile::tempdir().unwrap();
+        let plugin = plugin(dir.path()).await;
+        let content: Vec<u8> = (0..=255).cycle().take(10_000).collect();
+        let partial = dir.path().join("text/model.gguf.part");
+        let validator = dir.path().join("text/.model.gguf.part.etag");
+
+        // A resume without Accept-Ranges continues after the 206.
+        let host = ObjectHost::new(content.clone(), "\"v1\"");
+        fs::write(&partial, &content[..4_000]).await.unwrap();
+        fs::write(&validator, "\"v1\"").await.unwrap();
+        let downloaded = plugin.download_model(host.serve().await).await.unwrap();
+        assert_eq!(downloaded.bytes_written, 10_000);
+        assert_eq!(fs::read(&downloaded.saved_path).await.unwrap(), content);
+        assert_eq!(
+            host.requests(),
+            [(Some("bytes=4000-".to_string()), Some("\"v1\"".to_string()))]
+        );
+        assert!(!validator.exists());
+
+        // A part file holding the whole file is taken as it is.
+        let host = ObjectHost::new(content.clone(), "\"v1\"");
+        fs::write(&partial, &content).await.unwrap();
+        fs::write(&validator, "\"v1\"").await.unwrap();
+        let downloaded = plugin.download_model(host.serve().await).await.unwrap();
+        assert_eq!(downloaded.bytes_written, 10_000);
+        assert_eq!(fs::read(&downloaded.saved_path).await.unwrap(), content);
+        assert_eq!(host.requests().len(), 1);
+
+        // The start of a file that changed since is downloaded again.
+        let changed: Vec<u8> = content.iter().rev().copied().collect();
+        let host = ObjectHost::new(changed.clone(), "\"v2\"");
+        fs::write(&partial, &content[..4_000]).await.unwrap();
+        fs::write(&validator, "\"v1\"").await.unwrap();
+        let downloaded = plugin.download_model(host.serve().await).await.unwrap();
+        assert_eq!(fs::read(&downloaded.saved_path).await.unwrap(), changed);
+        assert_eq!(host.requests().len(), 1);
+    }
+
+    #[test]
+    fn chunk_plans_cover_the_file_in_order() {
+        let plan = ChunkPlan {
+            chunk_len: 4,
+            connections: 2,
+            retries: 0,
+        };
+        assert_eq!(plan.ranges(10).collect::<Vec<_>>(), [0..4, 4..8, 8..10]);
+        assert_eq!(plan.ranges(8).collect::<Vec<_>>(), [0..4, 4..8]);
+        assert!(plan.ranges(3).eq(std::iter::once(0..3)));
+        assert_eq!(plan.ranges(0).count(), 0);
+    }
+
+    #[tokio::test]
+    async fn accelerated_downloads_reassemble_their_ranges() {
+        let dir = tempfile::tempdir().unwrap();
+        let plugin = plugin(dir.path()).await;
+        let len = 2 * ACCELERATED_CHUNK_BYTES as usize + 12_345;
+        let content: Vec<u8> = (0..len).map(|i| (i % 251) as u8).collect();
+        let mut host = ObjectHost::new(content.clone(), "\"v1\"");
+        host.announces_ranges = true;
+        let mut request = host.serve().await;
+        request.transfer = DownloadTransfer::Accelerated;
+
+        let downloaded = plugin.download_model(request).await.unwrap();
+        assert_eq!(downloaded.bytes_written, len as u64);
+        assert_eq!(fs::read(&downloaded.saved_path).await.unwrap(), content);
+        let mut ranges: Vec<_> = host
+            .requests()
+            .into_iter()
+            .map(|(range, _)| range)
+            .collect();
+        ranges.sort();
+        assert_eq!(
+            ranges,
+            [
+                None,
+                Some(format!("bytes=0-{}", ACCELERATED_CHUNK_BYTES - 1)),
+                Some(format!(
+                    "bytes={}-{}",
+                    ACCELERATED_CHUNK_BYTES,
+                    2 * ACCELERATED_CHUNK_BYTES - 1
+                )),
+                Some(format!("bytes={}-{}", 2 * ACCELERATED_CHUNK_BYTES, len - 1)),
+            ]
+        );
+
+        // A range the host does not serve fails the download.
+        fs::remove_file(&downloaded.saved_path).await.unwrap();
+        host.ignored_range = Some(0);
+        let mut request = host.serve().await;
+        request.transfer = DownloadTransfer::Accelerated;
+        let failed = plugin.download_model(request).await;
+        assert!(matches!(failed, Err(PluginError::Io(_))));
+        assert!(!Path::new(&downloaded.saved_path).exists());
+        assert!(!dir.path().join("text/model.gguf.part").exists());
+    }
+
+    #[tokio::test]
+    async fn downloads_with_another_checksum_are_discarded() {
+        let dir = tempfile::tempdir().unwrap();
+        let plugin = plugin(dir.path()).await;
+        let content = b"not the model you are looking for".to_vec();
+        let actual = format!("{:x}", Sha256::digest(&content));
+        let host = ObjectHost::new(content, "\"v1\"");
+        let mut request = host.serve().await;
+        request.expected_sha256 = Some("AB".repeat(32));
+
+        match plugin.download_model(request).await {
+            Err(PluginError::ChecksumMismatch {
+                filename,
+                expected,
+                actual: got,
+            }) => {
+                assert_eq!(filename, "model.gguf");
+                assert_eq!(expected, "ab".repeat(32));
+                assert_eq!(got, actual);
+            }
+            other => panic!("expected a checksum mismatch, got {:?}", other.map(|_| ())),
+        }
+        assert!(!dir.path().join("text/model.gguf").exists());
+        assert!(!dir.path().join("text/model.gguf.part").exists());
+    }
+
+    #[tokio::test]
+    async fn cancelled_chunked_downloads_keep_their_finished_ranges() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut plugin = plugin(dir.path()).await;
+        plugin.accelerated_connections = 1;
+        let chunk = ACCELERATED_CHUNK_BYTES as usize;
+        let content: Vec<u8> = (0..2 * chunk + 100).map(|i| (i % 251) as u8).collect();
+        let mut host = ObjectHost::new(content.clone(), "\"v1\"");
+        host.announces_ranges = true;
+        host.stalled_range = Some(chunk as u64);
+        let mut request = host.serve().await;
+        request.transfer = DownloadTransfer::Accelerated;
+
+        let (cancel, partial) = (request.cancel.clone(), request.partial.clone());
+        let stalled = format!("bytes={}-", chunk);
+        let pause = async {
+            while !host.requests().iter().any(|(range, _)| {
+                range
+                    .as_deref()
+                    .is_some_and(|range| range.starts_with(&stalled))
+            }) {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+            partial.keep();
+            cancel.cancel();
+        };
+        let (cancelled, ()) = tokio::join!(plugin.download_model(request), pause);
+        assert!(matches!(cancelled, Err(PluginError::Cancelled)));
+        let part = dir.path().join("text/model.gguf.part");
+        assert_eq!(fs::read(&part).await.unwrap(), content[..chunk]);
+
+        host.stalled_range = None;
+        host.requests = Arc::default();
+        let downloaded = plugin.download_model(host.serve().await).await.unwrap();
+        assert_eq!(fs::read(&downloaded.saved_path).await.unwrap(), content);
+        assert_eq!(
+            host.requests(),
+            [(Some(stalled), Some("\"v1\"".to_string()))]
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn deleting_never_reaches_outside_the_model_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        let plugin = plugin(dir.path()).await;
+        let secret = outside.path().join("secret.gguf");
+        fs::write(&secret, b"GGUF").await.unwrap();
+        std::os::unix::fs::symlink(outside.path(), dir.path().join("text/escape")).unwrap();
+        std::os::unix::fs::symlink(&secret, dir.path().join("text/linked.gguf")).unwrap();
+
+        let delete = |filename: &str, destination_dir: Option<&Path>| {
+            let request = json!({
+                "task_type": "text",
+                "filename": filename,
+                "destination_dir": destination_dir,
+            });
+            let plugin = &plugin;
+            async move {
+                operations::invoke::<DeleteModelResponse>(
+                    plugin,
+                    operations::DELETE_MODEL,
+                    &request,
+                    InvokeContext::default(),
+                )
+                .await
+            }
+        };
+        let traversal = format!("../{}/secret.gguf", outside.path().display());
+        for denied in [
+            delete(&traversal, None).await,
+            delete(&secret.display().to_string(), None).await,
+            delete("secret.gguf", Some(outside.path())).await,
+            delete("escape/secret.gguf", None).await,
+        ] {
+            assert!(
+                matches!(denied, Err(PluginError::PathNotAllowed(_))),
+                "{:?}",
+                denied.map(|deleted| deleted.saved_path)
+            );
+        }
+        assert!(secret.exists());
+
+        // A link to a file elsewhere is removed without its target.
+        let unlinked = delete("linked.gguf", None).await.unwrap();
+        assert_eq!(unlinked.bytes_reclaimed, 0);
+        assert!(!dir.path().join("text/linked.gguf").exists());
+        assert!(secret.exists());
+    }
+
+    #[tokio::test]
+    async fn concurrent_starts_launch_one_process() {
+        let dir = tempfile::tempdir().unwrap();
+        let plugin = plugin(dir.path()).await;
+        // A start that fails releases the task type.
+        let crashing = sleeper(dir.path(), json!({"binary_path": "false"})).await;
+        assert!(matches!(
+            plugin.start_service(crashing).await,
+            Err(PluginError::ProcessCrashed(_))
+        ));
+
+        let (first, second) = (
+            sleeper(dir.path(), json!({})).await,
+            sleeper(dir.path(), json!({})).await,
+        );
+        let (first, second) =
+            tokio::join!(plugin.start_service(first), plugin.start_service(second));
+        let (started, refused) = match (first, second) {
+            (Ok(started), refused) | (refused, Ok(started)) => (started, refused),
+            (Err(first), Err(second)) => panic!("both starts failed: {} / {}", first, second),
+        };
+        assert!(matches!(
+            refused,
+            Err(PluginError::ProcessAlreadyRunning(_))
+        ));
+        plugin
+            .stop_service(StopServiceRequest {
+                service: ServiceSelector::instance(started.instance_id),
+            })
+            .await
+            .unwrap();
+    }
+}
@@ -0,0 +1,162 @@
+//! `models.lock`, the hub downloads of a machine pinned to the commit and
+//! hash they were made at, so another machine can download the very same
+//! files again.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+use utoipa::ToSchema;
+
+use super::{PluginError, PluginTaskType};
+
+/// File in the plugin base directory that locks downloaded models.
+pub const LOCK_FILE: &str = "models.lock";
+
+/// Version of the lock file format written.
+const LOCK_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+pub struct LockedModel {
+    pub model_id: String,
+    /// Commit SHA the file was downloaded at.
+    pub revision: String,
+    pub filename: String,
+    /// Hex SHA-256 of the file as downloaded.
+    pub sha256: String,
+    pub task_type: PluginTaskType,
+    /// Directory the model was downloaded to, when not the default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub destination_dir: Option<String>,
+}
+
+impl LockedModel {
+    fn same_file(&self, other: &LockedModel) -> bool {
+        self.task_type == other.task_type
+            && self.destination_dir == other.destination_dir
+            && self.filename == other.filename
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct LockContents {
+    version: u32,
+    #[serde(default)]
+    models: Vec<LockedModel>,
+}
+
+/// Persistent list of locked models, one per downloaded file.
+#[derive(Debug)]
+pub struct ModelLock {
+    path: PathBuf,
+    models: Vec<LockedModel>,
+}
+
+impl ModelLock {
+    pub async fn load(base_dir: &Path) -> Result<Self, PluginError> {
+        let path = base_dir.join(LOCK_FILE);
+        let models = match fs::read(&path).await {
+            Ok(bytes) => {
+                let contents: LockContents = serde_json::from_slice(&bytes).map_err(|err| {
+                    PluginError::Internal(format!("corrupt {}: {}", path.display(), err))
+                })?;
+                if contents.version > LOCK_VERSION {
+                    return Err(PluginError::Internal(format!(
+                        "{} has version {}, newer than {}",
+                        path.display(),
+                        contents.version,
+                        LOCK_VERSION
+                    )));
+                }
+                contents.models
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(err.into()),
+        };
+        Ok(Self { path, models })
+    }
+
+    pub fn models(&self) -> &[LockedModel] {
+        &self.models
+    }
+
+    /// Locks a downloaded file, replacing what was locked for it before.
+    /// Entries stay sorted so the file diffs cleanly between machines.
+    pub fn record(&mut self, model: LockedModel) {
+        self.models.retain(|locked| !locked.same_file(&model));
+        self.models.push(model);
+        self.models.sort_by(|a, b| {
+            (a.task_type.as_str(), &a.model_id, &a.filename).cmp(&(
+                b.task_type.as_str(),
+                &b.model_id,
+                &b.filename,
+            ))
+        });
+    }
+
+    /// Unlocks a deleted file.
+    pub fn remove(
+        &mut self,
+        task_type: &PluginTaskType,
+        destination_dir: Option<&str>,
+        filename: &str,
+    ) {
+        self.models.retain(|locked| {
+            !(locked.task_type == *task_type
+                && locked.destination_dir.as_deref() == destination_dir
+                && locked.filename == filename)
+        });
+    }
+
+    /// Writes the lock file atomically so a crash never leaves it half
+    /// written.
+    pub async fn save(&self) -> Result<(), PluginError> {
+        let contents = LockContents {
+            version: LOCK_VERSION,
+            models: self.models.clone(),
+        };
+        let json = serde_json::to_vec_pretty(&contents)
+            .map_err(|err| PluginError::Internal(err.to_string()))?;
+        let tmp = self.path.with_extension("lock.tmp");
+        fs::write(&tmp, json).await?;
+        fs::rename(&tmp, &self.path).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn locked(model_id: &str, filename: &str, revision: &str) -> LockedModel {
+        LockedModel {
+            model_id: model_id.to_string(),
+            revision: revision.to_string(),
+            filename: filename.to_string(),
+            sha256: "ab".repeat(32),
+            task_type: PluginTaskType::TEXT,
+            destination_dir: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn keeps_one_sorted_entry_per_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut lock = ModelLock::load(dir.path()).await.unwrap();
+        lock.record(locked("org/zeta", "zeta.gguf", &"1".repeat(40)));
+        lock.record(locked("org/alpha", "alpha.gguf", &"2".repeat(40)));
+        lock.record(locked("org/zeta", "zeta.gguf", &"3".repeat(40)));
+        lock.save().await.unwrap();
+
+        let lock = ModelLock::load(dir.path()).await.unwrap();
+        let models: Vec<_> = lock
+            .models()
+            .iter()
+            .map(|model| (model.model_id.as_str(), model.revision.clone()))
+            .collect();
+        assert_eq!(
+            models,
+            [("org/alpha", "2".repeat(40)), ("org/zeta", "3".repeat(40))]
+        );
+    }
+}
@@ -0,0 +1,749 @@
+use std::collections::VecDeque;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use utoipa::ToSchema;
+
+use super::events::OutputForwarder;
+use super::redact::Redactor;
+use super::retention::{self, LogRetention, PruneReport};
+use super::PluginTaskType;
+
+/// Number of log entries kept in memory per managed process.
+pub const DEFAULT_LOG_CAPACITY: usize = 2000;
+
+/// Entries buffered for live subscribers before slow ones start missing lines.
+const LIVE_CHANNEL_CAPACITY: usize = 256;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum LogStream {
+    Stdout,
+    Stderr,
+}
+
+impl LogStream {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LogStream::Stdout => "stdout",
+            LogStream::Stderr => "stderr",
+        }
+    }
+}
+
+#[derive(
+    Debug, Clone, Copy, Serialize, Deserialize, ToSchema, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+/// Well-known runtime messages recognised in model server output.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LogEvent {
+    ModelLoaded,
+    Listening { port: Option<u16> },
+    CudaError,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct LogEntry {
+    /// Position of the entry in the service's output, counting from 1 and
+    /// kept across restarts of the service.
+    #[serde(default)]
+    pub seq: u64,
+    pub timestamp: DateTime<Utc>,
+    pub stream: LogStream,
+    pub level: LogLevel,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event: Option<LogEvent>,
+}
+
+impl LogEntry {
+    pub fn parse(stream: LogStream, line: &str) -> Self {
+        let message = line.trim_end().to_string();
+        let event = detect_event(&message);
+        let level = match event {
+            Some(LogEvent::CudaError) => LogLevel::Error,
+            _ => detect_level(&message).unwrap_or(LogLevel::Info),
+        };
+
+        Self {
+            seq: 0,
+            timestamp: Utc::now(),
+            stream,
+            level,
+            message,
+            event,
+        }
+    }
+}
+
+fn detect_level(message: &str) -> Option<LogLevel> {
+    // Levels are normally printed near the start of a line, either bare or inside
+    // brackets, e.g. `[2024-01-01T00:00:00Z WARN server]` or `error: bad model`.
+    let head: String = message.chars().take(64).collect();
+    for token in head.split(|c: char| !c.is_ascii_alphabetic()) {
+        let level = match token {
+            "TRACE" | "trace" => LogLevel::Trace,
+            "DEBUG" | "debug" | "DBG" => LogLevel::Debug,
+            "INFO" | "info" | "INF" => LogLevel::Info,
+            "WARN" | "WARNING" | "warn" | "warning" | "WRN" => LogLevel::Warn,
+            "ERROR" | "error" | "ERR" | "FATAL" | "fatal" | "PANIC" | "panicked" => LogLevel::Error,
+            _ => continue,
+        };
+        return Some(level);
+    }
+    None
+}
+
+fn detect_event(message: &str) -> Option<LogEvent> {
+    let lower = message.to_ascii_lowercase();
+    if lower.contains("cuda error") || lower.contains("cuda_error") {
+        return Some(LogEvent::CudaError);
+    }
+    if lower.contains("model loaded") || lower.contains("loaded model") {
+        return Some(LogEvent::ModelLoaded);
+    }
+    if let Some(index) = lower.find("listening on") {
+        return Some(LogEvent::Listening {
+            port: extract_port(&lower[index..]),
+        });
+    }
+    None
+}
+
+fn extract_port(text: &str) -> Option<u16> {
+    if let Some(index) = text.find("port") {
+        let digits: String = text[index + 4..]
+            .trim_start_matches(|c: char| !c.is_ascii_digit())
+            .chars()
+            .take_while(|c| c.is_ascii_digit())
+            .collect();
+        if let Ok(port) = digits.parse() {
+            return Some(port);
+        }
+    }
+
+    let (_, tail) = text.rsplit_once(':')?;
+    let digits: String = tail.chars().take_while(|c| c.is_ascii_digit()).collect();
+    digits.parse().ok()
+}
+
+/// Bounded in-memory buffer of parsed log entries.
+#[derive(Debug)]
+pub struct LogBuffer {
+    entries: VecDeque<LogEntry>,
+    capacity: usize,
+    retention: LogRetention,
+    bytes: u64,
+    last_seq: u64,
+}
+
+impl LogBuffer {
+    pub fn new(capacity: usize, retention: LogRetention) -> Self {
+        Self {
+            entries: VecDeque::with_capacity(capacity.min(256)),
+            capacity,
+            retention,
+            bytes: 0,
+            last_seq: 0,
+        }
+    }
+
+    /// Stores `entry` as the next in sequence, returning it numbered.
+    pub fn push(&mut self, mut entry: LogEntry) -> LogEntry {
+        self.last_seq += 1;
+        entry.seq = self.last_seq;
+        let pushed = entry.clone();
+        if self.entries.len() == self.capacity {
+            self.pop_oldest();
+        }
+        self.bytes += entry.message.len() as u64;
+        self.entries.push_back(entry);
+        while self
+            .retention
+            .max_bytes
+            .is_some_and(|max| self.bytes > max && self.entries.len() > 1)
+        {
+            self.pop_oldest();
+        }
+        self.prune();
+        pushed
+    }
+
+    /// Drops entries older than the retention age, returning how many were removed.
+    pub fn prune(&mut self) -> usize {
+        let mut removed = 0;
+        while self
+            .entries
+            .front()
+            .is_some_and(|entry| self.is_expired(entry))
+        {
+            self.pop_oldest();
+            removed += 1;
+        }
+        removed
+    }
+
+    fn pop_oldest(&mut self) {
+        if let Some(entry) = self.entries.pop_front() {
+            self.bytes -= entry.message.len() as u64;
+        }
+    }
+
+    fn is_expired(&self, entry: &LogEntry) -> bool {
+        self.retention.max_age().is_some_and(|max_age| {
+            (Utc::now() - entry.timestamp)
+                .to_std()
+                .is_ok_and(|age| age > max_age)
+        })
+    }
+
+    /// Returns the most recent entries at or above `min_level`, oldest first.
+    pub fn query(&self, min_level: Option<LogLevel>, limit: Option<usize>) -> Vec<LogEntry> {
+        let mut matching: Vec<LogEntry> = self
+            .entries
+            .iter()
+            .rev()
+            .filter(|entry| !self.is_expired(entry))
+            .filter(|entry| min_level.is_none_or(|level| entry.level >= level))
+            .take(limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect();
+        matching.reverse();
+        matching
+    }
+
+    /// Returns the entries after `seq`, oldest first, and how many of them
+    /// are no longer kept.
+    pub fn since(&self, seq: u64) -> (Vec<LogEntry>, u64) {
+        let entries: Vec<LogEntry> = self
+            .entries
+            .iter()
+            .filter(|entry| entry.seq > seq && !self.is_expired(entry))
+            .cloned()
+            .collect();
+        let first = entries.first().map_or(self.last_seq + 1, |entry| entry.seq);
+        (entries, first.saturating_sub(seq + 1))
+    }
+
+    /// Returns the last `count` raw lines captured from `stream`, oldest first.
+    pub fn tail(&self, stream: LogStream, count: usize) -> Vec<String> {
+        let mut lines: Vec<String> = self
+            .entries
+            .iter()
+            .rev()
+            .filter(|entry| entry.stream == stream && !self.is_expired(entry))
+            .take(count)
+            .map(|entry| entry.message.clone())
+            .collect();
+        lines.reverse();
+        lines
+    }
+}
+
+pub type SharedLogBuffer = Arc<Mutex<LogBuffer>>;
+
+/// Size at which a service's log file is rotated when its retention does
+/// not set `max_file_bytes`.
+pub const DEFAULT_LOG_FILE_MAX_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Log files kept per task type when the retention does not set
+/// `max_files`.
+pub const DEFAULT_LOG_FILES_KEPT: usize = 20;
+
+/// Names log files for when they were opened, sorting as they were written.
+const LOG_FILE_TIMESTAMP: &str = "%Y%m%dT%H%M%S%.3fZ";
+
+/// The files a service's output is written to, `<dir>/<timestamp>.log`.
+/// Once a file reaches the rotation size the next one is opened and the
+/// files in `dir` are pruned by the retention. They outlive the service, so
+/// the output of one that crashed can still be read.
+#[derive(Debug)]
+pub struct LogFiles {
+    dir: PathBuf,
+    retention: LogRetention,
+    path: PathBuf,
+    file: std::fs::File,
+    written: u64,
+}
+
+pub type SharedLogFiles = Arc<Mutex<LogFiles>>;
+
+impl LogFiles {
+    pub fn open(dir: PathBuf, retention: LogRetention) -> std::io::Result<Self> {
+        std::fs::create_dir_all(&dir)?;
+        let (path, file) = create_log_file(&dir)?;
+        let files = Self {
+            dir,
+            retention: file_retention(retention),
+            path,
+            file,
+            written: 0,
+        };
+        files.prune();
+        Ok(files)
+    }
+
+    /// The file being written to.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Writes `record` to the end of the current file, rotating first when
+    /// it would grow past the rotation size.
+    fn append(&mut self, record: &str) -> std::io::Result<()> {
+        let len = record.len() as u64;
+        let full = self
+            .retention
+            .max_file_bytes
+            .is_some_and(|max| self.written > 0 && self.written + len > max);
+        if full {
+            (self.path, self.file) = create_log_file(&self.dir)?;
+            self.written = 0;
+            self.prune();
+        }
+        self.file.write_all(record.as_bytes())?;
+        self.written += len;
+        Ok(())
+    }
+
+    fn prune(&self) {
+        if let Err(err) = retention::prune_directory(&self.dir, &self.retention) {
+            tracing::warn!(
+                "pruning the log files in {} failed: {}",
+                self.dir.display(),
+                err
+            );
+        }
+    }
+}
+
+/// `retention` with the defaults of log files filled in.
+fn file_retention(retention: LogRetention) -> LogRetention {
+    retention.or(LogRetention {
+        max_files: Some(DEFAULT_LOG_FILES_KEPT),
+        max_file_bytes: Some(DEFAULT_LOG_FILE_MAX_BYTES),
+        ..LogRetention::default()
+    })
+}
+
+fn create_log_file(dir: &Path) -> std::io::Result<(PathBuf, std::fs::File)> {
+    let path = dir.join(format!("{}.log", Utc::now().format(LOG_FILE_TIMESTAMP)));
+    let file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)?;
+    Ok((path, file))
+}
+
+/// A file a service's output was written to.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq)]
+pub struct ServiceLogFile {
+    pub task_type: PluginTaskType,
+    /// Name of the file, such as `20261017T093000.000Z.log` for one opened
+    /// at that time.
+    pub name: String,
+    pub path: String,
+    pub size_bytes: u64,
+    pub modified_at: DateTime<Utc>,
+}
+
+/// Applies `retention` to the log files in `dir`, which holds one directory
+/// of them per task type. Blocks while the files are removed.
+pub fn prune_files(dir: &Path, retention: LogRetention) -> std::io::Result<PruneReport> {
+    let retention = file_retention(retention);
+    let mut report = PruneReport::default();
+    let task_dirs = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(report),
+        Err(err) => return Err(err),
+    };
+    for task_dir in task_dirs {
+        let task_dir = task_dir?;
+        if task_dir.file_type()?.is_dir() {
+            let pruned = retention::prune_directory(&task_dir.path(), &retention)?;
+            report.files_removed += pruned.files_removed;
+            report.bytes_freed += pruned.bytes_freed;
+        }
+    }
+    Ok(report)
+}
+
+/// The log files in `dir`, which holds one directory of them per task type,
+/// newest first.
+pub async fn list_files(dir: &Path) -> std::io::Result<Vec<ServiceLogFile>> {
+    let mut files = Vec::new();
+    let mut task_dirs = match tokio::fs::read_dir(dir).await {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(files),
+        Err(err) => return Err(err),
+    };
+    while let Some(task_dir) = task_dirs.next_entry().await? {
+        let Ok(task_type) = PluginTaskType::new(task_dir.file_name().to_string_lossy()) else {
+            continue;
+        };
+        if !task_dir.file_type().await?.is_dir() {
+            continue;
+        }
+        let mut entries = tokio::fs::read_dir(task_dir.path()).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            let metadata = entry.metadata().await?;
+            if !metadata.is_file() || path.extension().is_none_or(|ext| ext != "log") {
+                continue;
+            }
+            files.push(ServiceLogFile {
+                task_type: task_type.clone(),
+                name: entry.file_name().to_string_lossy().to_string(),
+                path: path.to_string_lossy().to_string(),
+                size_bytes: metadata.len(),
+                modified_at: metadata.modified()?.into(),
+            });
+        }
+    }
+    files.sort_by(|a, b| b.modified_at.cmp(&a.modified_at));
+    Ok(files)
+}
+
+/// Where captured output goes: the bounded history, any live subscribers
+/// and the service's log files, if it has them.
+#[derive(Clone)]
+pub struct LogSink {
+    pub buffer: SharedLogBuffer,
+    pub live: broadcast::Sender<LogEntry>,
+    pub files: Option<SharedLogFiles>,
+}
+
+impl LogSink {
+    pub fn new(capacity: usize, retention: LogRetention) -> Self {
+        let (live, _) = broadcast::channel(LIVE_CHANNEL_CAPACITY);
+        Self {
+            buffer: Arc::new(Mutex::new(LogBuffer::new(capacity, retention))),
+            live,
+            files: None,
+        }
+    }
+
+    /// Also appends captured lines to `files`.
+    pub fn with_files(mut self, files: LogFiles) -> Self {
+        self.files = Some(Arc::new(Mutex::new(files)));
+        self
+    }
+
+    /// The log file being written to, if there is one.
+    pub fn current_file(&self) -> Option<PathBuf> {
+        let files = self.files.as_ref()?.lock().ok()?;
+        Some(files.path().to_path_buf())
+    }
+
+    /// Numbers `entry`, stores it and sends it to live subscribers.
+    pub fn record(&self, entry: LogEntry) -> LogEntry {
+        let mut buffer = self.buffer.lock().unwrap_or_else(|err| err.into_inner());
+        let entry = buffer.push(entry);
+        // Sent under the lock to keep the order followers see. An error only
+        // means nobody is attached right now.
+        let _ = self.live.send(entry.clone());
+        entry
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<LogEntry> {
+        self.live.subscribe()
+    }
+
+    /// Follows the output from after the entry numbered `after`, or from
+    /// the last `tail` entries when it is not given.
+    pub fn follow(
+        &self,
+        after: Option<u64>,
+        tail: usize,
+        min_level: Option<LogLevel>,
+    ) -> LogFollower {
+        // Held while subscribing, so no entry is both replayed and received.
+        let buffer = self.buffer.lock().unwrap_or_else(|err| err.into_inner());
+        let receiver = self.live.subscribe();
+        let (last_seq, replay, missed) = match after {
+            Some(after) => {
+                let (entries, missed) = buffer.since(after.min(buffer.last_seq));
+                (after.min(buffer.last_seq), entries, missed)
+            }
+            None => {
+                let start = buffer.last_seq.saturating_sub(tail as u64);
+                (start, buffer.since(start).0, 0)
+            }
+        };
+        LogFollower {
+            buffer: self.buffer.clone(),
+            receiver,
+            min_level,
+            last_seq,
+            replay: replay.into(),
+            missed,
+        }
+    }
+}
+
+/// What a [`LogFollower`] yields.
+#[derive(Debug, Clone)]
+pub enum FollowedLog {
+    Entry(LogEntry),
+    /// Entries that were dropped from the buffer before they could be sent.
+    Missed(u64),
+}
+
+/// Live output of a service. A follower that falls behind the live channel
+/// catches up from the buffer, so lines are only lost once they are evicted
+/// from it.
+#[derive(Debug)]
+pub struct LogFollower {
+    buffer: SharedLogBuffer,
+    receiver: broadcast::Receiver<LogEntry>,
+    min_level: Option<LogLevel>,
+    last_seq: u64,
+    replay: VecDeque<LogEntry>,
+    missed: u64,
+}
+
+impl LogFollower {
+    /// The next entry at or above the minimum level, or `None` once the
+    /// service and its output are gone.
+    pub async fn next(&mut self) -> Option<FollowedLog> {
+        loop {
+            if self.missed > 0 {
+                return Some(FollowedLog::Missed(std::mem::take(&mut self.missed)));
+            }
+            let entry = match self.replay.pop_front() {
+                Some(entry) => entry,
+                None => match self.receiver.recv().await {
+                    Ok(entry) => entry,
+                    Err(broadcast::error::RecvError::Lagged(_)) => {
+                        let buffer = self.buffer.lock().unwrap_or_else(|err| err.into_inner());
+                        let (entries, missed) = buffer.since(self.last_seq);
+                        self.replay = entries.into();
+                        self.missed = missed;
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                },
+            };
+            if entry.seq <= self.last_seq {
+                continue;
+            }
+            self.last_seq = entry.seq;
+            if self.min_level.is_none_or(|level| entry.level >= level) {
+                return Some(FollowedLog::Entry(entry));
+            }
+        }
+    }
+}
+
+/// Reads `reader` line by line until EOF, recording parsed entries in `sink`.
+/// Secrets known to `redactor` are masked before a line is logged, stored or
+/// forwarded as an event.
+pub fn spawn_capture<R>(
+    reader: R,
+    stream: LogStream,
+    sink: LogSink,
+    redactor: Arc<Redactor>,
+    forwarder: Option<OutputForwarder>,
+) -> JoinHandle<()>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+{
+    tokio::spawn(async move {
+        let mut lines = BufReader::new(reader).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            let line = redactor.redact_text(&line);
+            tracing::debug!(target: "goose_server::plugins", ?stream, "{}", line);
+            if let Some(forwarder) = &forwarder {
+                forwarder.forward(stream, &line);
+            }
+            let entry = sink.record(LogEntry::parse(stream, &line));
+            if let Some(files) = &sink.files {
+                let record = format!(
+                    "{} {} {}\n",
+                    entry.timestamp.to_rfc3339_opts(SecondsFormat::Millis, true),
+                    stream.as_str(),
+                    line
+                );
+                let mut files = files.lock().unwrap_or_else(|err| err.into_inner());
+                if let Err(err) = files.append(&record) {
+                    tracing::debug!("writing a service log line failed: {}", err);
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_levels_and_events() {
+        let entry = LogEntry::parse(LogStream::Stderr, "[2024-05-01 WARN llm] slow tokenizer");
+        assert_eq!(entry.level, LogLevel::Warn);
+        assert_eq!(entry.event, None);
+
+        let entry = LogEntry::parse(LogStream::Stdout, "INFO server listening on 127.0.0.1:8080");
+        assert_eq!(entry.level, LogLevel::Info);
+        assert_eq!(entry.event, Some(LogEvent::Listening { port: Some(8080) }));
+
+        let entry = LogEntry::parse(LogStream::Stderr, "ggml_cuda: CUDA error 2: out of memory");
+        assert_eq!(entry.level, LogLevel::Error);
+        assert_eq!(entry.event, Some(LogEvent::CudaError));
+
+        let entry = LogEntry::parse(LogStream::Stdout, "model loaded in 2.3s");
+        assert_eq!(entry.event, Some(LogEvent::ModelLoaded));
+    }
+
+    #[test]
+    fn buffer_filters_and_evicts() {
+        let mut buffer = LogBuffer::new(3, LogRetention::default());
+        for line in ["INFO a", "ERROR b", "DEBUG c", "WARN d"] {
+            buffer.push(LogEntry::parse(LogStream::Stdout, line));
+        }
+
+        let all = buffer.query(None, None);
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[0].message, "ERROR b");
+
+        let warnings = buffer.query(Some(LogLevel::Warn), Some(1));
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].message, "WARN d");
+
+        let mut buffer = LogBuffer::new(
+            10,
+            LogRetention {
+                max_bytes: Some(12),
+                ..LogRetention::default()
+            },
+        );
+        for line in ["INFO a", "ERROR b", "DEBUG c"] {
+            buffer.push(LogEntry::parse(LogStream::Stdout, line));
+        }
+        let kept = buffer.query(None, None);
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].message, "DEBUG c");
+    }
+
+    #[tokio::test]
+    async fn writes_captured_lines_to_the_log_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = LogFiles::open(dir.path().to_path_buf(), LogRetention::default()).unwrap();
+        let sink = LogSink::new(10, LogRetention::default()).with_files(files);
+        let path = sink.current_file().unwrap();
+        let output: &[u8] = b"loading model\nerror: out of memory\n";
+        spawn_capture(
+            output,
+            LogStream::Stderr,
+            sink.clone(),
+            Arc::new(Redactor::default()),
+            None,
+        )
+        .await
+        .unwrap();
+
+        let written = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = written.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[1].ends_with(" stderr error: out of memory"));
+        assert_eq!(sink.buffer.lock().unwrap().query(None, None).len(), 2);
+    }
+
+    #[tokio::test]
+    async fn rotates_and_prunes_log_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let retention = LogRetention {
+            max_files: Some(2),
+            max_file_bytes: Some(20),
+            ..LogRetention::default()
+        };
+        let mut files = LogFiles::open(dir.path().join("text"), retention).unwrap();
+        let first = files.path().to_path_buf();
+        for record in ["0123456789\n", "abcdefghij\n", "ABCDEFGHIJ\n"] {
+            // Files are named and pruned by time.
+            std::thread::sleep(std::time::Duration::from_millis(5));
+            files.append(record).unwrap();
+        }
+        assert!(!first.exists());
+        assert_eq!(
+            std::fs::read_to_string(files.path()).unwrap(),
+            "ABCDEFGHIJ\n"
+        );
+
+        let listed = list_files(dir.path()).await.unwrap();
+        assert_eq!(listed.len(), 2);
+        assert_eq!(listed[0].task_type, PluginTaskType::TEXT);
+        assert_eq!(Path::new(&listed[0].path), files.path());
+        assert_eq!(listed[1].size_bytes, 11);
+    }
+
+    #[tokio::test]
+    async fn followers_resume_and_catch_up_from_the_buffer() {
+        let sink = LogSink::new(300, LogRetention::default());
+        for line in ["INFO a", "ERROR b", "INFO c"] {
+            sink.record(LogEntry::parse(LogStream::Stdout, line));
+        }
+
+        let mut tail = sink.follow(None, 2, None);
+        let mut errors = sink.follow(None, 0, Some(LogLevel::Error));
+        let mut resumed = sink.follow(Some(1), 0, None);
+        sink.record(LogEntry::parse(LogStream::Stderr, "ERROR d"));
+
+        let mut messages = Vec::new();
+        for _ in 0..3 {
+            match tail.next().await {
+                Some(FollowedLog::Entry(entry)) => messages.push((entry.seq, entry.message)),
+                other => panic!("unexpected {:?}", other),
+            }
+        }
+        assert_eq!(
+            messages,
+            vec![
+                (2u64, "ERROR b".to_string()),
+                (3, "INFO c".to_string()),
+                (4, "ERROR d".to_string())
+            ]
+        );
+        assert!(matches!(errors.next().await, Some(FollowedLog::Entry(entry)) if entry.seq == 4));
+        assert!(matches!(resumed.next().await, Some(FollowedLog::Entry(entry)) if entry.seq == 2));
+
+        // A follower that falls behind the live channel reads the buffer,
+        // and reports what was evicted before it got there.
+        let mut slow = sink.follow(None, 0, None);
+        for index in 0..LIVE_CHANNEL_CAPACITY + 100 {
+            sink.record(LogEntry::parse(
+                LogStream::Stdout,
+                &format!("INFO {}", index),
+            ));
+        }
+        let missed = LIVE_CHANNEL_CAPACITY as u64 + 100 - 300;
+        assert!(matches!(slow.next().await, Some(FollowedLog::Missed(count)) if count == missed));
+        match slow.next().await {
+            Some(FollowedLog::Entry(entry)) => assert_eq!(entry.seq, 4 + missed + 1),
+            other => panic!("unexpected {:?}", other),
+        }
+
+        drop(sink);
+        let mut drained = 0;
+        while slow.next().await.is_some() {
+            drained += 1;
+        }
+        assert_eq!(drained, 299);
+    }
+}
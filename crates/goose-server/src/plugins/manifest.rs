@@ -0,0 +1,147 @@
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+use utoipa::ToSchema;
+
+use super::provenance::ModelProvenance;
+use super::{PluginError, PluginTaskType};
+
+/// File in the plugin base directory that records downloaded models.
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// A newer upstream commit found for a tracked model.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+pub struct AvailableUpdate {
+    pub commit: String,
+    pub checked_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ModelRecord {
+    pub model_id: String,
+    pub filename: String,
+    /// Branch, tag or commit the download was requested for.
+    pub revision: String,
+    /// Commit the downloaded file was resolved from, when the host reported it.
+    #[serde(default)]
+    pub commit: Option<String>,
+    pub task_type: PluginTaskType,
+    pub saved_path: String,
+    pub bytes: u64,
+    /// Hex SHA-256 of the file as downloaded.
+    #[serde(default)]
+    pub sha256: Option<String>,
+    pub downloaded_at: DateTime<Utc>,
+    #[serde(default)]
+    pub last_checked: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub update: Option<AvailableUpdate>,
+    /// Last time a local service was launched from the file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_used: Option<DateTime<Utc>>,
+    /// Stored encrypted; services launch from a decrypted copy.
+    #[serde(default)]
+    pub encrypted: bool,
+    /// Kept by garbage collection and quota eviction.
+    #[serde(default)]
+    pub pinned: bool,
+    /// Where the file came from when not from the hub: an object storage
+    /// URL, or a `file://` URL for imports. Such files are not checked for
+    /// updates.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_url: Option<String>,
+    /// Signature the file was verified against when downloaded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<ModelProvenance>,
+}
+
+/// Persistent list of downloaded models, keyed by saved path.
+#[derive(Debug)]
+pub struct ModelManifest {
+    path: PathBuf,
+    records: Vec<ModelRecord>,
+}
+
+impl ModelManifest {
+    pub async fn load(base_dir: &Path) -> Result<Self, PluginError> {
+        let path = base_dir.join(MANIFEST_FILE);
+        let records = match fs::read(&path).await {
+            Ok(bytes) => serde_json::from_slice(&bytes).map_err(|err| {
+                PluginError::Internal(format!("corrupt {}: {}", path.display(), err))
+            })?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(err.into()),
+        };
+        Ok(Self { path, records })
+    }
+
+    pub fn records(&self) -> &[ModelRecord] {
+        &self.records
+    }
+
+    pub fn find(&self, saved_path: &str) -> Option<&ModelRecord> {
+        self.records
+            .iter()
+            .find(|record| record.saved_path == saved_path)
+    }
+
+    pub fn records_mut(&mut self) -> &mut [ModelRecord] {
+        &mut self.records
+    }
+
+    /// Adds a record, replacing any previous one for the same file.
+    pub fn upsert(&mut self, record: ModelRecord) {
+        match self
+            .records
+            .iter_mut()
+            .find(|existing| existing.saved_path == record.saved_path)
+        {
+            Some(existing) => *existing = record,
+            None => self.records.push(record),
+        }
+    }
+
+    pub fn remove(&mut self, saved_path: &str) {
+        self.records
+            .retain(|record| record.saved_path != saved_path);
+    }
+
+    /// Stamps the record for `saved_path` as used now. Returns false for
+    /// files the manifest does not track.
+    pub fn mark_used(&mut self, saved_path: &str) -> bool {
+        match self
+            .records
+            .iter_mut()
+            .find(|record| record.saved_path == saved_path)
+        {
+            Some(record) => {
+                record.last_used = Some(Utc::now());
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Pins or unpins the record for `saved_path`, returning it, or `None`
+    /// for files the manifest does not track.
+    pub fn set_pinned(&mut self, saved_path: &str, pinned: bool) -> Option<&ModelRecord> {
+        let record = self
+            .records
+            .iter_mut()
+            .find(|record| record.saved_path == saved_path)?;
+        record.pinned = pinned;
+        Some(record)
+    }
+
+    /// Writes the manifest atomically so a crash never leaves it half written.
+    pub async fn save(&self) -> Result<(), PluginError> {
+        let json = serde_json::to_vec_pretty(&self.records)
+            .map_err(|err| PluginError::Internal(err.to_string()))?;
+        let tmp = self.path.with_extension("json.tmp");
+        fs::write(&tmp, json).await?;
+        fs::rename(&tmp, &self.path).await?;
+        Ok(())
+    }
+}
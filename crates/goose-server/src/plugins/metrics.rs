@@ -0,0 +1,148 @@
+//! Counters and gauges plugins report through [`ServerPlugin::metrics`],
+//! gathered into one snapshot for the whole server.
+//!
+//! [`ServerPlugin::metrics`]: super::ServerPlugin::metrics
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Completed model downloads.
+pub const DOWNLOADS: &str = "downloads";
+/// Model downloads that failed.
+pub const DOWNLOAD_FAILURES: &str = "download_failures";
+/// Bytes written by completed model downloads.
+pub const BYTES_DOWNLOADED: &str = "bytes_downloaded";
+/// Service processes running now.
+pub const RUNNING_PROCESSES: &str = "running_processes";
+/// Models stored by the plugin.
+pub const STORED_MODELS: &str = "stored_models";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema, PartialEq)]
+pub struct PluginMetrics {
+    /// Totals that only grow while the plugin runs, by name.
+    #[serde(default)]
+    pub counters: BTreeMap<String, u64>,
+    /// Current values, by name.
+    #[serde(default)]
+    pub gauges: BTreeMap<String, f64>,
+}
+
+impl PluginMetrics {
+    pub fn counter(mut self, name: &str, value: u64) -> Self {
+        self.counters.insert(name.to_string(), value);
+        self
+    }
+
+    pub fn gauge(mut self, name: &str, value: f64) -> Self {
+        self.gauges.insert(name.to_string(), value);
+        self
+    }
+
+    /// Adds every counter and gauge to the one of the same name in `totals`.
+    fn add_to(&self, totals: &mut PluginMetrics) {
+        for (name, value) in &self.counters {
+            *totals.counters.entry(name.clone()).or_default() += value;
+        }
+        for (name, value) in &self.gauges {
+            *totals.gauges.entry(name.clone()).or_default() += value;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PluginMetricsEntry {
+    pub plugin_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metrics: Option<PluginMetrics>,
+    /// Why the plugin's metrics could not be collected.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PluginMetricsSnapshot {
+    pub collected_at: DateTime<Utc>,
+    /// Enabled plugins that report metrics, dependencies first.
+    pub plugins: Vec<PluginMetricsEntry>,
+    /// Each counter and gauge summed across plugins.
+    pub totals: PluginMetrics,
+}
+
+impl PluginMetricsSnapshot {
+    pub fn new(plugins: Vec<PluginMetricsEntry>) -> Self {
+        let mut totals = PluginMetrics::default();
+        for metrics in plugins.iter().filter_map(|entry| entry.metrics.as_ref()) {
+            metrics.add_to(&mut totals);
+        }
+        Self {
+            collected_at: Utc::now(),
+            plugins,
+            totals,
+        }
+    }
+}
+
+/// Download counters a plugin keeps for its metrics.
+#[derive(Debug, Default)]
+pub struct DownloadCounters {
+    downloads: AtomicU64,
+    failures: AtomicU64,
+    bytes: AtomicU64,
+}
+
+impl DownloadCounters {
+    pub fn record_download(&self, bytes: u64) {
+        self.downloads.fetch_add(1, Ordering::Relaxed);
+        self.bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub fn record_failure(&self) {
+        self.failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn metrics(&self) -> PluginMetrics {
+        PluginMetrics::default()
+            .counter(DOWNLOADS, self.downloads.load(Ordering::Relaxed))
+            .counter(DOWNLOAD_FAILURES, self.failures.load(Ordering::Relaxed))
+            .counter(BYTES_DOWNLOADED, self.bytes.load(Ordering::Relaxed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn totals_sum_plugins() {
+        let counters = DownloadCounters::default();
+        counters.record_download(40);
+        counters.record_download(2);
+        counters.record_failure();
+
+        let snapshot = PluginMetricsSnapshot::new(vec![
+            PluginMetricsEntry {
+                plugin_id: "llmserver-rs".to_string(),
+                metrics: Some(counters.metrics().gauge(RUNNING_PROCESSES, 2.0)),
+                error: None,
+            },
+            PluginMetricsEntry {
+                plugin_id: "piper".to_string(),
+                metrics: Some(PluginMetrics::default().gauge(RUNNING_PROCESSES, 1.0)),
+                error: None,
+            },
+            PluginMetricsEntry {
+                plugin_id: "remote".to_string(),
+                metrics: None,
+                error: Some("connection refused".to_string()),
+            },
+        ]);
+        assert_eq!(snapshot.totals.counters[DOWNLOADS], 2);
+        assert_eq!(snapshot.totals.counters[DOWNLOAD_FAILURES], 1);
+        assert_eq!(snapshot.totals.counters[BYTES_DOWNLOADED], 42);
+        assert_eq!(snapshot.totals.gauges[RUNNING_PROCESSES], 3.0);
+    }
+}
@@ -0,0 +1,129 @@
+//! Endpoints serving the Hugging Face hub API, tried in order. Mirrors such
+//! as hf-mirror.com or an internal Artifactory remote take over from an
+//! endpoint that refuses a request or does not answer in time.
+
+use std::future::Future;
+
+use reqwest::{StatusCode, Url};
+
+use super::breaker::CircuitBreakers;
+use super::revisions::HUGGING_FACE_URL;
+use super::PluginError;
+
+/// An ordered, non-empty list of hub endpoints.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HubEndpoints(Vec<Url>);
+
+impl Default for HubEndpoints {
+    fn default() -> Self {
+        Self(vec![
+            Url::parse(HUGGING_FACE_URL).expect("valid Hugging Face URL")
+        ])
+    }
+}
+
+impl HubEndpoints {
+    /// Parses base URLs, keeping their order. An empty list means the
+    /// default hub.
+    pub fn parse<S: AsRef<str>>(endpoints: &[S]) -> Result<Self, PluginError> {
+        let mut urls = Vec::with_capacity(endpoints.len());
+        for endpoint in endpoints {
+            let endpoint = endpoint.as_ref().trim();
+            // Joined paths go under the base, not next to its last segment.
+            let normalized = format!("{}/", endpoint.trim_end_matches('/'));
+            let url = Url::parse(&normalized)
+                .ok()
+                .filter(|url| matches!(url.scheme(), "http" | "https") && url.has_host())
+                .ok_or_else(|| {
+                    PluginError::InvalidRequest(format!("invalid hub endpoint {:?}", endpoint))
+                })?;
+            urls.push(url);
+        }
+        if urls.is_empty() {
+            return Ok(Self::default());
+        }
+        Ok(Self(urls))
+    }
+
+    /// Reads `GOOSE_PLUGIN_LLM_HUB_ENDPOINTS`, a comma separated list, or
+    /// else the `HF_ENDPOINT` the Hugging Face tools use.
+    pub fn from_env() -> anyhow::Result<Self> {
+        let raw = std::env::var("GOOSE_PLUGIN_LLM_HUB_ENDPOINTS")
+            .or_else(|_| std::env::var("HF_ENDPOINT"))
+            .unwrap_or_default();
+        let endpoints: Vec<_> = raw.split(',').filter(|s| !s.trim().is_empty()).collect();
+        Ok(Self::parse(&endpoints)?)
+    }
+
+    pub fn primary(&self) -> &Url {
+        &self.0[0]
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Url> {
+        self.0.iter()
+    }
+}
+
+/// Circuit breaker key of an endpoint.
+pub fn host(endpoint: &Url) -> &str {
+    endpoint.host_str().unwrap_or_default()
+}
+
+/// Whether a failed request is worth repeating against the next endpoint:
+/// the endpoint refused it, did not answer in time or is known to be down.
+pub fn fails_over(error: &PluginError) -> bool {
+    match error {
+        PluginError::Network(err) => {
+            err.is_timeout() || err.is_connect() || err.status() == Some(StatusCode::FORBIDDEN)
+        }
+        PluginError::CircuitOpen { .. } => true,
+        _ => false,
+    }
+}
+
+/// Runs `call` against each endpoint in turn, behind its circuit breaker,
+/// until one answers or fails in a way the next one would not fix.
+pub async fn failover<T, F, Fut>(
+    endpoints: &HubEndpoints,
+    breakers: &CircuitBreakers,
+    call: F,
+) -> Result<T, PluginError>
+where
+    F: Fn(Url) -> Fut,
+    Fut: Future<Output = Result<T, PluginError>>,
+{
+    let mut endpoints = endpoints.iter().peekable();
+    loop {
+        let endpoint = endpoints.next().expect("hub endpoints are never empty");
+        match breakers.run(host(endpoint), call(endpoint.clone())).await {
+            Err(err) if fails_over(&err) && endpoints.peek().is_some() => {
+                tracing::warn!("hub endpoint {} failed, trying the next: {}", endpoint, err);
+            }
+            result => return result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_order_and_normalizes_bases() {
+        let endpoints =
+            HubEndpoints::parse(&["https://hf-mirror.com", "https://repo.internal/hf/"]).unwrap();
+        let urls: Vec<_> = endpoints.iter().map(Url::as_str).collect();
+        assert_eq!(
+            urls,
+            ["https://hf-mirror.com/", "https://repo.internal/hf/"]
+        );
+        assert_eq!(host(endpoints.primary()), "hf-mirror.com");
+
+        assert_eq!(
+            HubEndpoints::parse::<&str>(&[]).unwrap(),
+            HubEndpoints::default()
+        );
+        assert!(HubEndpoints::parse(&["ftp://mirror"]).is_err());
+        assert!(HubEndpoints::parse(&["not a url"]).is_err());
+    }
+}
@@ -0,0 +1,178 @@
+//! A plugin with scripted answers, for integration tests of code that talks
+//! to the plugin API. [`MockPlugin`] forwards every call to a
+//! [`MockTransport`], which waits out the latency scripted for the method,
+//! records the call and answers with the scripted response. Methods without
+//! a script are unsupported, so optional hooks succeed.
+//!
+//! ```ignore
+//! let transport = MockTransport::new();
+//! transport.respond("list_models", json!({ "models": [] }));
+//! transport.delay("start_service", Duration::from_millis(200));
+//! let plugin = transport.plugin("mock", vec![PluginCapability::ModelList]);
+//! let state = AppState::with_plugins(vec![Arc::new(plugin)]).await?;
+//! ```
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde_json::Value;
+
+use super::forward::{ForwardingPlugin, PluginTransport, RemoteError};
+use super::{PluginCapability, PluginError, PluginMetadata};
+
+pub type MockPlugin = ForwardingPlugin<MockTransport>;
+
+/// A call the plugin received: the [`ServerPlugin`] method and its request.
+///
+/// [`ServerPlugin`]: super::ServerPlugin
+#[derive(Debug, Clone, PartialEq)]
+pub struct MockCall {
+    pub method: String,
+    pub params: Value,
+}
+
+#[derive(Debug, Default)]
+struct MockScript {
+    responses: HashMap<String, Result<Value, RemoteError>>,
+    latencies: HashMap<String, Duration>,
+    calls: Vec<MockCall>,
+}
+
+/// The script behind a [`MockPlugin`]. Clones share it, so tests keep one to
+/// change answers and inspect calls while the server holds the plugin.
+#[derive(Debug, Clone, Default)]
+pub struct MockTransport {
+    script: Arc<Mutex<MockScript>>,
+}
+
+impl MockTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A plugin answered by this transport.
+    pub fn plugin(&self, id: &str, capabilities: Vec<PluginCapability>) -> MockPlugin {
+        let metadata = PluginMetadata {
+            id: id.to_string(),
+            name: id.to_string(),
+            description: "Scripted plugin for tests".to_string(),
+            capabilities,
+            enabled: true,
+            dependencies: Vec::new(),
+            api_version: super::compat::PLUGIN_API_VERSION,
+            subscriptions: Vec::new(),
+            signature: None,
+        };
+        ForwardingPlugin::new(metadata, self.clone())
+    }
+
+    /// Answers every later `method` call with `response`.
+    pub fn respond(&self, method: &str, response: Value) {
+        self.lock()
+            .responses
+            .insert(method.to_string(), Ok(response));
+    }
+
+    /// Fails every later `method` call. `kind` names the [`PluginError`]
+    /// variant in snake case, as out-of-process plugins report it.
+    pub fn fail(&self, method: &str, kind: &str, message: &str) {
+        let error = RemoteError {
+            kind: kind.to_string(),
+            message: message.to_string(),
+        };
+        self.lock().responses.insert(method.to_string(), Err(error));
+    }
+
+    /// Waits `latency` before answering each later `method` call.
+    pub fn delay(&self, method: &str, latency: Duration) {
+        self.lock().latencies.insert(method.to_string(), latency);
+    }
+
+    /// Calls received so far, oldest first.
+    pub fn calls(&self) -> Vec<MockCall> {
+        self.lock().calls.clone()
+    }
+
+    /// Calls of `method` received so far, oldest first.
+    pub fn calls_to(&self, method: &str) -> Vec<Value> {
+        self.lock()
+            .calls
+            .iter()
+            .filter(|call| call.method == method)
+            .map(|call| call.params.clone())
+            .collect()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, MockScript> {
+        self.script.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+#[async_trait]
+impl PluginTransport for MockTransport {
+    async fn call(&self, method: &str, params: Value) -> Result<Value, PluginError> {
+        let latency = {
+            let mut script = self.lock();
+            script.calls.push(MockCall {
+                method: method.to_string(),
+                params,
+            });
+            script.latencies.get(method).copied()
+        };
+        if let Some(latency) = latency {
+            tokio::time::sleep(latency).await;
+        }
+        match self.lock().responses.get(method) {
+            Some(Ok(response)) => Ok(response.clone()),
+            Some(Err(error)) => Err(error.clone().into()),
+            None => Err(PluginError::UnsupportedOperation),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugins::{ServerPlugin, ServiceSelector, StopServiceRequest};
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn answers_from_script() {
+        let transport = MockTransport::new();
+        let plugin = transport.plugin("mock", vec![PluginCapability::ServiceStop]);
+        assert!(matches!(
+            plugin.list_models().await,
+            Err(PluginError::UnsupportedOperation)
+        ));
+        plugin.on_register().await.unwrap();
+
+        transport.respond(
+            "stop_service",
+            json!({"instance_id": "abc", "task_type": "text", "terminated": true}),
+        );
+        transport.delay("stop_service", Duration::from_millis(20));
+        let started = std::time::Instant::now();
+        let stopped = plugin
+            .stop_service(StopServiceRequest {
+                service: ServiceSelector::instance("abc"),
+            })
+            .await
+            .unwrap();
+        assert!(stopped.terminated);
+        assert!(started.elapsed() >= Duration::from_millis(20));
+
+        transport.fail("stop_service", "instance_not_found", "abc");
+        assert!(matches!(
+            plugin
+                .stop_service(StopServiceRequest {
+                    service: ServiceSelector::instance("abc"),
+                })
+                .await,
+            Err(PluginError::InstanceNotFound(_))
+        ));
+        assert_eq!(transport.calls_to("stop_service").len(), 2);
+        assert_eq!(transport.calls()[0].method, "list_models");
+    }
+}
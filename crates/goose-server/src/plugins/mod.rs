@@ -1,26 +1,215 @@
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 
+use arc_swap::ArcSwap;
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
-use tokio::sync::RwLock;
+use tokio::sync::{broadcast, mpsc, RwLock, RwLockWriteGuard};
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
 use utoipa::ToSchema;
 
+use adapters::LoraAdapter;
+use admission::AdmissionControl;
+use affinity::CpuAffinity;
+use chrono::{DateTime, Utc};
+use compat::PluginCompatibility;
+use diagnostics::CrashReport;
+use discovery::{PluginKind, PluginManifest};
+use events::{EventBus, PluginEvent};
+use external::ExternalPluginConfig;
+use fallback::FallbackAttempt;
+use gc::{ModelGcRequest, ModelGcResponse};
+use grpc::GrpcPluginConfig;
+use health::{HealthCheckConfig, PluginHealthResponse, ServiceHealth};
+use http::HttpPluginConfig;
+use import::ImportMode;
+use logs::{LogEntry, LogFollower, LogLevel, ServiceLogFile};
+use manifest::ModelRecord;
+use metrics::{PluginMetrics, PluginMetricsEntry, PluginMetricsSnapshot};
+use offline::OfflineMode;
+use offload::GpuOffload;
+use operations::InvokeContext;
+use persistence::PluginStateDir;
+use profiles::HardwareProfile;
+use progress::DownloadProgress;
+use provenance::ModelSignature;
+use proxy::ProxyConfig;
+use quota::{QuotaLimit, QuotaPlugin, QuotaTracker};
+use readiness::ReadinessProbe;
+use remote::RemoteNode;
+use retention::{LogRetention, PruneReport};
+use revisions::{GitRef, ModelCommit, ModelUpdate, RepoFile};
+use search::HubModel;
+use settings::{PluginConfig, PluginConfigStore};
+use signals::ServiceSignal;
+use signing::{PluginSignature, TrustRoot};
+use sources::ModelSource;
+use splits::GgufSplits;
+use stdio::StdioConfig;
+use upgrade::{SmokeTestConfig, SmokeTestResult};
+
+pub mod adapters;
+pub mod admission;
+pub mod affinity;
+pub mod breaker;
+pub mod catalog;
+pub mod codes;
+pub mod compat;
+pub mod convert;
+pub mod defaults;
+pub mod dependencies;
+pub mod diagnostics;
+pub mod discovery;
+pub mod dynamic;
+pub mod encryption;
+pub mod events;
+pub mod external;
+pub mod fallback;
+pub mod faults;
+pub mod forward;
+pub mod gated;
+pub mod gc;
+pub mod grpc;
+pub mod health;
+pub mod http;
+pub mod import;
+pub mod integrity;
+pub mod limits;
 pub mod llmserver;
+pub mod lockfile;
+pub mod logs;
+pub mod manifest;
+pub mod metrics;
+pub mod mirrors;
+#[cfg(any(test, feature = "test-util"))]
+#[allow(dead_code)] // Used by tests of downstream crates
+pub mod mock;
+pub mod notifications;
+pub mod offline;
+pub mod offload;
+pub mod operations;
+pub mod peers;
+pub mod permissions;
+pub mod persistence;
+pub mod profiles;
+pub mod progress;
+pub mod provenance;
+pub mod proxy;
+pub mod quantize;
+pub mod quota;
+pub mod readiness;
+pub mod recommended;
+pub mod redact;
+pub mod remote;
+pub mod retention;
+pub mod revisions;
+pub mod sandbox;
+pub mod schedule;
+pub mod search;
+pub mod settings;
+pub mod sidecars;
+pub mod signals;
+pub mod signing;
+pub mod snapshot;
+pub mod sources;
+pub mod splits;
+pub mod stdio;
+pub mod throttle;
+pub mod tokens;
+pub mod upgrade;
+pub mod usage;
+pub mod watcher;
 
-#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq, Eq, Hash)]
-#[serde(rename_all = "snake_case")]
-pub enum PluginTaskType {
-    Text,
-    Tts,
-}
+/// What a service is for, such as text generation or TTS. Plugins may define
+/// their own task types; a name is 1 to 64 lowercase ASCII letters, digits,
+/// `-` and `_`, starting with a letter, so it can also name a directory.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(try_from = "String", into = "String")]
+pub struct PluginTaskType(#[schema(value_type = String, example = "text")] Cow<'static, str>);
 
 impl PluginTaskType {
-    pub fn as_directory_suffix(&self) -> &'static str {
-        match self {
-            PluginTaskType::Text => "text",
-            PluginTaskType::Tts => "tts",
+    pub const TEXT: PluginTaskType = PluginTaskType(Cow::Borrowed("text"));
+    pub const TTS: PluginTaskType = PluginTaskType(Cow::Borrowed("tts"));
+
+    pub fn new(name: impl Into<String>) -> Result<Self, PluginError> {
+        let name = name.into();
+        let valid = (1..=64).contains(&name.len())
+            && name.starts_with(|c: char| c.is_ascii_lowercase())
+            && name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '-' | '_'));
+        if !valid {
+            return Err(PluginError::InvalidRequest(format!(
+                "invalid task type {:?}: use 1 to 64 lowercase letters, digits, '-' and '_', starting with a letter",
+                name
+            )));
+        }
+        Ok(Self(Cow::Owned(name)))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for PluginTaskType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::str::FromStr for PluginTaskType {
+    type Err = PluginError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Self::new(name)
+    }
+}
+
+impl TryFrom<String> for PluginTaskType {
+    type Error = PluginError;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        Self::new(name)
+    }
+}
+
+impl From<PluginTaskType> for String {
+    fn from(task_type: PluginTaskType) -> Self {
+        task_type.0.into_owned()
+    }
+}
+
+/// Identifies a managed service, either by the instance handle returned from
+/// `start_service` or, as a convenience, by task type when only one instance of
+/// that type is running.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+pub struct ServiceSelector {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task_type: Option<PluginTaskType>,
+}
+
+impl ServiceSelector {
+    pub fn instance(instance_id: impl Into<String>) -> Self {
+        Self {
+            instance_id: Some(instance_id.into()),
+            task_type: None,
+        }
+    }
+
+    pub fn task_type(task_type: PluginTaskType) -> Self {
+        Self {
+            instance_id: None,
+            task_type: Some(task_type),
         }
     }
 }
@@ -31,6 +220,37 @@ pub enum PluginCapability {
     ModelDownload,
     ServiceStart,
     ServiceStop,
+    ServiceLogs,
+    ServiceStatus,
+    ServiceUpgrade,
+    ServiceSignal,
+    ServiceConsole,
+    ModelList,
+    ModelUpdateCheck,
+    ModelRevisions,
+    ModelSearch,
+    RemoteNodes,
+    HardwareProfiles,
+    ModelGc,
+    ModelDelete,
+    ModelImport,
+    ModelPin,
+    /// LoRA adapters of downloaded models.
+    ModelAdapters,
+    /// Conversion of downloaded models to GGUF.
+    ModelConvert,
+    /// Quantization of downloaded full-precision models.
+    ModelQuantize,
+    /// Sharing downloaded models with peer goosed instances.
+    ModelShare,
+    /// Integrity scans of the model directories and their repair.
+    ModelHealth,
+    HealthCheck,
+    TokenValidation,
+    /// Counters and gauges reported through `metrics`.
+    Metrics,
+    /// Operations of the plugin's own, run through `invoke`.
+    CustomOperations,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
@@ -39,11 +259,36 @@ pub struct PluginMetadata {
     pub name: String,
     pub description: String,
     pub capabilities: Vec<PluginCapability>,
+    /// Disabled plugins stay registered but reject every operation.
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Ids of plugins that must be registered and started before this one.
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+    /// Version of the plugin API the plugin was built against; see
+    /// [`compat`].
+    #[serde(default = "compat::default_api_version")]
+    pub api_version: u32,
+    /// Event types, or names of custom events, delivered to the plugin's
+    /// [`ServerPlugin::on_event`].
+    #[serde(default)]
+    pub subscriptions: Vec<String>,
+    /// The trusted signature of the plugin's binary, when the server checks
+    /// signatures; see [`signing`]. Never taken from what a plugin reports.
+    #[serde(default, skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<PluginSignature>,
+}
+
+fn default_enabled() -> bool {
+    true
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct DownloadModelRequest {
     pub model_id: String,
+    /// File to download, or the name an object is saved as. Unused by
+    /// snapshots.
+    #[serde(default)]
     pub filename: String,
     #[serde(default = "default_revision")]
     pub revision: String,
@@ -52,6 +297,123 @@ pub struct DownloadModelRequest {
     #[serde(default)]
     pub auth_token: Option<String>,
     pub task_type: PluginTaskType,
+    /// Download onto this remote node instead of the local machine. Remote
+    /// files are not tracked in the model manifest.
+    #[serde(default)]
+    pub node: Option<String>,
+    /// Hex SHA-256 the downloaded file must have. Without it, the hash
+    /// Hugging Face lists for the file is used when it lists one. Only
+    /// checked for local downloads.
+    #[serde(default)]
+    pub expected_sha256: Option<String>,
+    /// Bandwidth this download may use, on top of the plugin's limit for
+    /// all downloads.
+    #[serde(default)]
+    pub max_bytes_per_sec: Option<u64>,
+    #[serde(default)]
+    pub mode: DownloadMode,
+    #[serde(default)]
+    pub transfer: DownloadTransfer,
+    /// Glob patterns of the files a snapshot downloads; every file when
+    /// empty.
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Glob patterns of files a snapshot skips.
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// How a `filename` naming one part of a split model, such as
+    /// `model-00001-of-00004.gguf`, is stored. Every part is downloaded.
+    #[serde(default)]
+    pub gguf_splits: GgufSplits,
+    /// Also download the config, tokenizer, vocabulary and multimodal
+    /// projector files the repository has next to `filename`. Unused by
+    /// snapshots.
+    #[serde(default)]
+    pub sidecars: bool,
+    /// Downloaded model, relative to the task type's model directory, the
+    /// file is a LoRA adapter of. It is saved in the model's adapter
+    /// directory and must be an adapter for the model's architecture.
+    #[serde(default)]
+    pub adapter_for: Option<String>,
+    /// Hub base URLs tried in order, such as `https://hf-mirror.com`,
+    /// instead of the plugin's.
+    #[serde(default)]
+    pub hub_endpoints: Vec<String>,
+    /// Object storage or a peer goosed to download from instead of the hub.
+    #[serde(default)]
+    pub source: ModelSource,
+    /// Proxy for this download instead of the plugin's.
+    #[serde(default)]
+    pub proxy: Option<ProxyConfig>,
+    /// Detached minisign signature the downloaded file must carry from a
+    /// trusted model key; the signer is recorded in the model's provenance.
+    /// Only checked for local downloads of a single file.
+    #[serde(default)]
+    pub signature: Option<ModelSignature>,
+    /// Time the download waits for before it joins the download queue.
+    #[serde(default)]
+    pub start_after: Option<DateTime<Utc>>,
+    /// Cron expression in UTC, such as `0 2 * * *`; the download starts the
+    /// next time it matches, after `start_after` if both are given.
+    #[serde(default)]
+    pub schedule: Option<String>,
+    /// Aborts the transfer, e.g. when the requesting client disconnects.
+    #[serde(skip)]
+    pub cancel: CancellationToken,
+    /// Receives the bytes written as the transfer runs.
+    #[serde(skip)]
+    pub progress: DownloadProgress,
+    /// Whether a cancelled transfer leaves what it wrote for a later
+    /// download of the same file to resume.
+    #[serde(skip)]
+    pub partial: PartialDownload,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DownloadMode {
+    /// The one file named by `filename`.
+    #[default]
+    File,
+    /// Every file of the repository, or those `include` and `exclude`
+    /// select, saved under a directory named after the repository.
+    Snapshot,
+}
+
+/// How the bytes of a file are fetched.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DownloadTransfer {
+    /// One stream, or a few large ranges in parallel for large files.
+    #[default]
+    Standard,
+    /// Many small ranges over many connections, retried one by one, as
+    /// `hf_transfer` fetches from the Hugging Face CDN. Falls back to one
+    /// stream for hosts that do not serve byte ranges.
+    Accelerated,
+}
+
+/// Decides, once a download is cancelled, whether its partial file is kept.
+/// Clones share the decision; it is removed unless [`keep`](Self::keep) was
+/// called before the cancellation.
+#[derive(Debug, Clone, Default)]
+pub struct PartialDownload {
+    keep: Arc<AtomicBool>,
+}
+
+impl PartialDownload {
+    pub fn keep(&self) {
+        self.keep.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_kept(&self) -> bool {
+        self.keep.load(Ordering::SeqCst)
+    }
+
+    /// Undoes [`keep`](Self::keep), e.g. when a paused download resumes.
+    pub fn discard(&self) {
+        self.keep.store(false, Ordering::SeqCst);
+    }
 }
 
 fn default_revision() -> String {
@@ -62,6 +424,14 @@ fn default_revision() -> String {
 pub struct DownloadModelResponse {
     pub saved_path: String,
     pub bytes_written: u64,
+    /// Plugins tried in turn when the plugin has fallbacks, ending with the
+    /// one that answered.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fallback: Vec<FallbackAttempt>,
+    /// Saved paths of the companion files downloaded along with the model.
+    /// `bytes_written` includes them.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sidecars: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
@@ -72,26 +442,461 @@ pub struct StartServiceRequest {
     pub binary_path: Option<String>,
     #[serde(default)]
     pub args: Option<Vec<String>>,
+    /// Extra environment variables. Values of the form `secret://NAME` are
+    /// resolved from goose's secret store.
     #[serde(default)]
     pub environment: Option<HashMap<String, String>>,
+    /// Periodic liveness probe; without it only the process itself is tracked.
+    #[serde(default)]
+    pub health_check: Option<HealthCheckConfig>,
+    #[serde(default)]
+    pub cpu_affinity: Option<CpuAffinity>,
+    /// Keep stdin open so a console can be attached to the process.
+    #[serde(default)]
+    pub interactive: bool,
+    /// Output handling per stream; both streams go to the service log by default.
+    #[serde(default)]
+    pub stdio: StdioConfig,
+    /// Overrides the global retention for this service's captured logs.
+    #[serde(default)]
+    pub log_retention: Option<LogRetention>,
+    /// Run the service on this remote node. `model_path` and `binary_path`
+    /// then refer to paths on the node.
+    #[serde(default)]
+    pub node: Option<String>,
+    /// Choose `--n-gpu-layers` from free VRAM when a llama.cpp-style binary
+    /// serves a GGUF model and the arguments set no layer count. Defaults
+    /// to true.
+    #[serde(default)]
+    pub auto_gpu_layers: Option<bool>,
+    /// Hardware profile whose thread, batch and offload defaults fill in
+    /// flags missing from `args`.
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// LoRA adapters applied to the model, checked to match its
+    /// architecture. Only for local GGUF models.
+    #[serde(default)]
+    pub adapters: Vec<LoraAdapter>,
+    /// Answer only once the service serves requests, as told by
+    /// `readiness`. A service that is not ready in time is stopped and the
+    /// start fails with its stderr.
+    #[serde(default)]
+    pub wait_for_ready: bool,
+    #[serde(default)]
+    pub readiness: Option<ReadinessProbe>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct StartServiceResponse {
+    /// Opaque handle identifying this service in later requests.
+    pub instance_id: String,
+    pub task_type: PluginTaskType,
     pub pid: u32,
     pub command: String,
     pub args: Vec<String>,
+    /// Remote node the service runs on; absent for local services.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub node: Option<String>,
+    /// GPU offload chosen automatically for this launch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gpu_offload: Option<GpuOffload>,
+    /// Plugins tried in turn when the plugin has fallbacks, ending with the
+    /// one that answered.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fallback: Vec<FallbackAttempt>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct StopServiceRequest {
-    pub task_type: PluginTaskType,
+    #[serde(flatten)]
+    pub service: ServiceSelector,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct StopServiceResponse {
+    pub instance_id: String,
     pub task_type: PluginTaskType,
     pub terminated: bool,
+    /// Present when the process had already exited before it was asked to stop.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub crash: Option<CrashReport>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ServiceLogsRequest {
+    #[serde(flatten)]
+    pub service: ServiceSelector,
+    #[serde(default)]
+    pub min_level: Option<LogLevel>,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ServiceLogsResponse {
+    pub instance_id: String,
+    pub task_type: PluginTaskType,
+    pub entries: Vec<LogEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ServiceStatusRequest {
+    #[serde(flatten)]
+    pub service: ServiceSelector,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ServiceStatusResponse {
+    pub instance_id: String,
+    pub task_type: PluginTaskType,
+    pub pid: Option<u32>,
+    pub command: String,
+    pub args: Vec<String>,
+    /// Environment passed to the process, with secret values masked.
+    pub environment: HashMap<String, String>,
+    pub health: ServiceHealth,
+    pub consecutive_failures: u32,
+    pub restarts: u32,
+    pub last_health_check: Option<DateTime<Utc>>,
+    /// Logical CPUs the process is pinned to, if any.
+    pub cpu_affinity: Option<Vec<usize>>,
+    /// When the running process was started; a restart resets it. Unknown
+    /// for services on remote nodes.
+    #[serde(default)]
+    pub started_at: Option<DateTime<Utc>>,
+    /// Seconds since `started_at`.
+    #[serde(default)]
+    pub uptime_secs: Option<u64>,
+    /// File the service's output is currently written to, on the machine it
+    /// runs on. Kept after the service stops.
+    #[serde(default)]
+    pub log_file: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub node: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ListServicesResponse {
+    /// Services running on this machine, by instance id.
+    pub services: Vec<ServiceStatusResponse>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ListLogFilesResponse {
+    /// Files services wrote their output to, newest first.
+    pub files: Vec<ServiceLogFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SignalServiceRequest {
+    #[serde(flatten)]
+    pub service: ServiceSelector,
+    pub signal: ServiceSignal,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SignalServiceResponse {
+    pub instance_id: String,
+    pub task_type: PluginTaskType,
+    pub pid: u32,
+    pub signal: ServiceSignal,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ListModelsResponse {
+    pub models: Vec<ModelRecord>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct DeleteModelRequest {
+    pub task_type: PluginTaskType,
+    /// File name relative to the task type's model directory.
+    pub filename: String,
+    /// Directory the model was downloaded to, when not the default.
+    #[serde(default)]
+    pub destination_dir: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct DeleteModelResponse {
+    pub saved_path: String,
+    pub bytes_reclaimed: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PinModelRequest {
+    pub task_type: PluginTaskType,
+    /// File name relative to the task type's model directory.
+    pub filename: String,
+    #[serde(default)]
+    pub destination_dir: Option<String>,
+    pub pinned: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ImportModelRequest {
+    /// Existing model file on the server's machine.
+    pub source_path: String,
+    pub task_type: PluginTaskType,
+    #[serde(default)]
+    pub mode: ImportMode,
+    /// File name relative to the task type's model directory; defaults to
+    /// the source's file name.
+    #[serde(default)]
+    pub filename: Option<String>,
+    #[serde(default)]
+    pub destination_dir: Option<String>,
+    /// Id the model is recorded under. Files in the Hugging Face cache
+    /// default to the repository they belong to, others to their name.
+    #[serde(default)]
+    pub model_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ImportModelResponse {
+    pub saved_path: String,
+    pub bytes: u64,
+    pub sha256: String,
+    pub mode: ImportMode,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct RestoreModelsRequest {
+    /// Token for gated or private repositories among the locked models.
+    #[serde(default)]
+    pub auth_token: Option<String>,
+    /// Hub base URLs tried in order instead of the plugin's.
+    #[serde(default)]
+    pub hub_endpoints: Vec<String>,
+    /// Stops the restore between and during downloads.
+    #[serde(skip)]
+    pub cancel: CancellationToken,
+}
+
+/// A locked model that could not be downloaded again.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RestoreFailure {
+    pub model_id: String,
+    pub filename: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct RestoreModelsResponse {
+    pub restored: Vec<DownloadModelResponse>,
+    /// Saved paths of locked models already on disk with the locked hash.
+    pub unchanged: Vec<String>,
+    pub failed: Vec<RestoreFailure>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ListNodesResponse {
+    pub nodes: Vec<RemoteNode>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ListProfilesResponse {
+    pub profiles: Vec<HardwareProfile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ModelRevisionsRequest {
+    pub model_id: String,
+    /// Branch, tag or commit whose history is listed.
+    #[serde(default = "default_revision")]
+    pub revision: String,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ModelRevisionsResponse {
+    pub model_id: String,
+    pub branches: Vec<GitRef>,
+    pub tags: Vec<GitRef>,
+    /// Recent commits on the requested revision, newest first. Any of these
+    /// ids can be passed as `revision` to pin a download.
+    pub commits: Vec<ModelCommit>,
+    /// Answered from metadata cached before going offline.
+    #[serde(default)]
+    pub cached: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ModelSearchRequest {
+    /// Words the repository id has to contain; every model when unset.
+    #[serde(default)]
+    pub query: Option<String>,
+    pub task_type: PluginTaskType,
+    /// Library or file format tag, such as `gguf`. Text models default to
+    /// `gguf`; an empty value searches every library.
+    #[serde(default)]
+    pub library: Option<String>,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ModelSearchResponse {
+    /// Most downloaded first.
+    pub models: Vec<HubModel>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ModelFilesRequest {
+    pub model_id: String,
+    /// Branch, tag or commit whose files are listed.
+    #[serde(default = "default_revision")]
+    pub revision: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ModelFilesResponse {
+    pub model_id: String,
+    pub revision: String,
+    pub files: Vec<RepoFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ValidateTokenRequest {
+    /// Hub access token, as it would be passed as a download's `auth_token`.
+    pub token: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ValidateTokenResponse {
+    /// Whether the hub accepts the token; nothing else is set when not.
+    pub valid: bool,
+    /// User or organization the token belongs to.
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Name the token was given when it was created.
+    #[serde(default)]
+    pub token_name: Option<String>,
+    /// `read`, `write` or `fineGrained`.
+    #[serde(default)]
+    pub role: Option<String>,
+    /// Permissions of the token. Those limited to one repository or
+    /// organization are prefixed with its name, as in
+    /// `org/model:repo.content.read`.
+    #[serde(default)]
+    pub scopes: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ModelUpdatesResponse {
+    pub updates: Vec<ModelUpdate>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ReloadPluginResponse {
+    pub metadata: PluginMetadata,
+    /// Instances the reloaded plugin took over from its previous instance.
+    pub preserved_instances: Vec<String>,
+    /// Instances that could not be handed over and have been stopped.
+    pub stopped_instances: Vec<String>,
+}
+
+/// A plugin to add while the server runs. Exactly one of `executable` and
+/// `remote` is required.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RegisterPluginRequest {
+    /// Executable speaking JSON-RPC over stdio.
+    #[serde(default)]
+    pub executable: Option<ExternalPluginConfig>,
+    /// HTTP endpoint serving the plugin.
+    #[serde(default)]
+    pub remote: Option<HttpPluginConfig>,
+    /// Used instead of the metadata the plugin reports.
+    #[serde(default)]
+    pub metadata: Option<PluginMetadata>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct UnregisterPluginResponse {
+    pub plugin_id: String,
+    /// Instances that were running under the plugin and have been stopped.
+    pub stopped_instances: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ServiceLogsPruned {
+    pub plugin_id: String,
+    pub entries_removed: usize,
+    /// Files the output of services was written to.
+    #[serde(default)]
+    pub files: PruneReport,
+}
+
+#[derive(Debug, Clone)]
+pub struct AttachConsoleRequest {
+    pub service: ServiceSelector,
+}
+
+/// Live connection to an interactive process.
+#[derive(Debug)]
+pub struct ConsoleSession {
+    pub instance_id: String,
+    pub task_type: PluginTaskType,
+    /// Bytes sent here are written to the process's stdin.
+    pub input: mpsc::Sender<Vec<u8>>,
+    /// Output lines as they are captured, already redacted.
+    pub output: broadcast::Receiver<LogEntry>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FollowLogsRequest {
+    pub service: ServiceSelector,
+    pub min_level: Option<LogLevel>,
+    /// Resumes after the entry with this `seq`, replaying the kept entries
+    /// the caller missed.
+    pub after: Option<u64>,
+    /// Entries already captured to send first when not resuming.
+    pub tail: usize,
+}
+
+/// Live output of a running service.
+#[derive(Debug)]
+pub struct FollowLogsSession {
+    pub instance_id: String,
+    pub task_type: PluginTaskType,
+    pub follower: LogFollower,
+}
+
+/// Replaces a running service with a new model or build without downtime.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct UpgradeServiceRequest {
+    /// Service to replace. The instance keeps its handle across the upgrade.
+    #[serde(flatten)]
+    pub service: ServiceSelector,
+    /// Model to download before starting the new instance; its saved path becomes `model_path`.
+    #[serde(default)]
+    pub download: Option<DownloadModelRequest>,
+    #[serde(default)]
+    pub model_path: Option<String>,
+    #[serde(default)]
+    pub binary_path: Option<String>,
+    /// Arguments for the new instance. Defaults to the running instance's arguments with
+    /// the old model path replaced; make sure the new instance listens on a free port.
+    #[serde(default)]
+    pub args: Option<Vec<String>>,
+    /// Merged over the running instance's environment.
+    #[serde(default)]
+    pub environment: Option<HashMap<String, String>>,
+    #[serde(default)]
+    pub health_check: Option<HealthCheckConfig>,
+    pub smoke_test: SmokeTestConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct UpgradeServiceResponse {
+    pub instance_id: String,
+    pub task_type: PluginTaskType,
+    pub previous: StartServiceResponse,
+    pub current: StartServiceResponse,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub download: Option<DownloadModelResponse>,
+    pub smoke_test: SmokeTestResult,
 }
 
 #[derive(Debug, Error)]
@@ -104,16 +909,76 @@ pub enum PluginError {
     NotReady(String),
     #[error("not found: {0}")]
     NotFound(String),
+    #[error("path not allowed: {0}")]
+    PathNotAllowed(String),
     #[error("process already running for {0:?}")]
     ProcessAlreadyRunning(PluginTaskType),
     #[error("process not running for {0:?}")]
     ProcessNotRunning(PluginTaskType),
+    #[error("no service instance with id {0}")]
+    InstanceNotFound(String),
+    #[error("model {0} is used by a running service")]
+    ModelInUse(String),
     #[error(transparent)]
     Io(#[from] std::io::Error),
     #[error(transparent)]
     Network(#[from] reqwest::Error),
+    #[error("service binary missing: {0}")]
+    BinaryMissing(String),
     #[error("failed to start process: {0}")]
     ProcessStart(String),
+    #[error("{0}")]
+    ProcessCrashed(Box<CrashReport>),
+    #[error("service not ready after {waited_secs}s: {reason}")]
+    ServiceNotReady {
+        reason: String,
+        waited_secs: u64,
+        stderr_tail: Vec<String>,
+    },
+    #[error("resource limits insufficient: {0}")]
+    ResourceLimit(String),
+    #[error("smoke test failed, kept previous instance: {0}")]
+    SmokeTestFailed(String),
+    #[error("download verification failed: {0}")]
+    VerificationFailed(String),
+    #[error("not enough disk space at {path}: {required} bytes needed, {available} free")]
+    InsufficientDiskSpace {
+        path: String,
+        required: u64,
+        available: u64,
+    },
+    #[error("{filename} has sha256 {actual} but {expected} was expected")]
+    ChecksumMismatch {
+        filename: String,
+        expected: String,
+        actual: String,
+    },
+    #[error("model {model_id} is gated; accept its license at {repo_url}")]
+    GatedModel { model_id: String, repo_url: String },
+    #[error("remote node error: {0}")]
+    Remote(String),
+    #[error("operation cancelled")]
+    Cancelled,
+    #[error("offline mode: {0}")]
+    Offline(String),
+    #[error("upstream {host} is failing; retry in {retry_after_secs}s")]
+    CircuitOpen { host: String, retry_after_secs: u64 },
+    #[error("plugin {0} is already registered")]
+    AlreadyRegistered(String),
+    #[error("plugin {0} is disabled")]
+    Disabled(String),
+    #[error("plugin dependencies: {0}")]
+    Dependency(String),
+    #[error("{}", .0.reason.as_deref().unwrap_or("incompatible plugin API version"))]
+    Incompatible(Box<PluginCompatibility>),
+    #[error("plugin {plugin_id} exceeded its {} quota: {detail}", .limit.as_str())]
+    QuotaExceeded {
+        plugin_id: String,
+        limit: QuotaLimit,
+        detail: String,
+    },
+    #[error("untrusted plugin: {0}")]
+    Untrusted(String),
     #[error("plugin internal error: {0}")]
     Internal(String),
 }
@@ -122,6 +987,12 @@ pub enum PluginError {
 pub trait ServerPlugin: Send + Sync {
     fn metadata(&self) -> PluginMetadata;
 
+    /// Directory the plugin stores models and data in, measured against
+    /// its disk quota.
+    fn storage_dir(&self) -> Option<PathBuf> {
+        None
+    }
+
     async fn download_model(
         &self,
         _request: DownloadModelRequest,
@@ -142,12 +1013,217 @@ pub trait ServerPlugin: Send + Sync {
     ) -> Result<StopServiceResponse, PluginError> {
         Err(PluginError::UnsupportedOperation)
     }
+
+    async fn service_logs(
+        &self,
+        _request: ServiceLogsRequest,
+    ) -> Result<ServiceLogsResponse, PluginError> {
+        Err(PluginError::UnsupportedOperation)
+    }
+
+    /// Output of a service as it is captured.
+    async fn follow_logs(
+        &self,
+        _request: FollowLogsRequest,
+    ) -> Result<FollowLogsSession, PluginError> {
+        Err(PluginError::UnsupportedOperation)
+    }
+
+    async fn service_status(
+        &self,
+        _request: ServiceStatusRequest,
+    ) -> Result<ServiceStatusResponse, PluginError> {
+        Err(PluginError::UnsupportedOperation)
+    }
+
+    /// Every service the plugin runs locally.
+    async fn list_services(&self) -> Result<ListServicesResponse, PluginError> {
+        Err(PluginError::UnsupportedOperation)
+    }
+
+    /// Files the plugin's services wrote their output to, including those
+    /// of services that have stopped.
+    async fn list_log_files(&self) -> Result<ListLogFilesResponse, PluginError> {
+        Err(PluginError::UnsupportedOperation)
+    }
+
+    async fn upgrade_service(
+        &self,
+        _request: UpgradeServiceRequest,
+    ) -> Result<UpgradeServiceResponse, PluginError> {
+        Err(PluginError::UnsupportedOperation)
+    }
+
+    async fn signal_service(
+        &self,
+        _request: SignalServiceRequest,
+    ) -> Result<SignalServiceResponse, PluginError> {
+        Err(PluginError::UnsupportedOperation)
+    }
+
+    async fn attach_console(
+        &self,
+        _request: AttachConsoleRequest,
+    ) -> Result<ConsoleSession, PluginError> {
+        Err(PluginError::UnsupportedOperation)
+    }
+
+    async fn list_models(&self) -> Result<ListModelsResponse, PluginError> {
+        Err(PluginError::UnsupportedOperation)
+    }
+
+    async fn list_nodes(&self) -> Result<ListNodesResponse, PluginError> {
+        Err(PluginError::UnsupportedOperation)
+    }
+
+    async fn list_profiles(&self) -> Result<ListProfilesResponse, PluginError> {
+        Err(PluginError::UnsupportedOperation)
+    }
+
+    async fn list_revisions(
+        &self,
+        _request: ModelRevisionsRequest,
+    ) -> Result<ModelRevisionsResponse, PluginError> {
+        Err(PluginError::UnsupportedOperation)
+    }
+
+    /// Checks downloaded models for newer upstream commits on their revision.
+    async fn check_model_updates(&self) -> Result<ModelUpdatesResponse, PluginError> {
+        Err(PluginError::UnsupportedOperation)
+    }
+
+    /// Lists models that are neither running nor recently used and, when
+    /// the request confirms, deletes them.
+    async fn collect_models(
+        &self,
+        _request: ModelGcRequest,
+    ) -> Result<ModelGcResponse, PluginError> {
+        Err(PluginError::UnsupportedOperation)
+    }
+
+    /// Applies log retention to captured service logs right away.
+    async fn prune_logs(&self) -> Result<ServiceLogsPruned, PluginError> {
+        Err(PluginError::UnsupportedOperation)
+    }
+
+    /// Checks every service the plugin runs, probing health endpoints now
+    /// rather than reporting the last periodic check.
+    async fn health(&self) -> Result<PluginHealthResponse, PluginError> {
+        Err(PluginError::UnsupportedOperation)
+    }
+
+    /// Counters and gauges for the server-wide metrics snapshot, such as
+    /// downloads and running processes.
+    async fn metrics(&self) -> Result<PluginMetrics, PluginError> {
+        Err(PluginError::UnsupportedOperation)
+    }
+
+    /// Stops every process the plugin owns and its background work, ahead of
+    /// the plugin being dropped. Returns the ids of the stopped instances.
+    async fn shutdown(&self) -> Result<Vec<String>, PluginError> {
+        Ok(Vec::new())
+    }
+
+    /// Called before the plugin is registered, at startup, at runtime or on
+    /// reload, and before it is configured. An error keeps it unregistered.
+    async fn on_register(&self) -> Result<(), PluginError> {
+        Ok(())
+    }
+
+    /// Called once the server accepts requests, or on registration for
+    /// plugins registered after that.
+    async fn on_server_start(&self) -> Result<(), PluginError> {
+        Ok(())
+    }
+
+    /// Called when the server stops. Stops the plugin's services by default.
+    async fn on_shutdown(&self) -> Result<(), PluginError> {
+        self.shutdown().await.map(|_| ())
+    }
+
+    /// Receives events other plugins publish that match
+    /// [`PluginMetadata::subscriptions`].
+    async fn on_event(&self, _event: &PluginEvent) -> Result<(), PluginError> {
+        Ok(())
+    }
+
+    /// State to keep across server restarts, such as the services running,
+    /// saved as the server stops and before [`on_shutdown`](Self::on_shutdown).
+    /// `None` keeps nothing.
+    async fn save_state(&self) -> Result<Option<serde_json::Value>, PluginError> {
+        Ok(None)
+    }
+
+    /// Receives the state the plugin saved when the server last stopped,
+    /// once all plugins are registered and configured at startup.
+    async fn restore_state(&self, _state: serde_json::Value) -> Result<(), PluginError> {
+        Ok(())
+    }
+
+    /// Runs an operation the plugin defines itself, such as `list-voices`,
+    /// with a payload whose shape is up to the plugin, or one of the model
+    /// [`operations`].
+    async fn invoke(
+        &self,
+        _operation: &str,
+        _payload: serde_json::Value,
+        _context: InvokeContext,
+    ) -> Result<serde_json::Value, PluginError> {
+        Err(PluginError::UnsupportedOperation)
+    }
+
+    /// JSON Schema of what [`configure`](Self::configure) accepts, used to
+    /// render settings forms. `null` for plugins without settings.
+    fn config_schema(&self) -> serde_json::Value {
+        serde_json::Value::Null
+    }
+
+    /// Applies configuration set through the API on top of what the plugin
+    /// reads from its environment. Replaces any configuration applied before.
+    async fn configure(&self, _config: &PluginConfig) -> Result<(), PluginError> {
+        Err(PluginError::UnsupportedOperation)
+    }
+
+    /// Detaches the running services so a reloaded instance of the same
+    /// plugin can [`take_over`](Self::take_over) instead of restarting them.
+    async fn hand_over(&self) -> Result<Handover, PluginError> {
+        Err(PluginError::UnsupportedOperation)
+    }
+
+    /// Adopts services detached from the instance this one replaces.
+    /// Returns the ids of the adopted instances.
+    async fn take_over(&self, _handover: Handover) -> Result<Vec<String>, PluginError> {
+        Err(PluginError::UnsupportedOperation)
+    }
 }
 
+/// Services passed between instances of a plugin on reload; only the plugin
+/// that detached them knows what they are.
+pub type Handover = Box<dyn std::any::Any + Send>;
+
+/// How long a reload waits for requests still using the previous instance.
+const RELOAD_DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
+
 #[derive(Default)]
 pub struct PluginManager {
     plugins: HashMap<String, Arc<dyn ServerPlugin>>, // keyed by plugin id
+    /// Plugin ids in registration order, which puts dependencies first.
+    order: Vec<String>,
     metadata_cache: HashMap<String, PluginMetadata>,
+    /// How each plugin was created, used to reload it.
+    sources: HashMap<String, PluginManifest>,
+    /// Why plugins were rejected for their API version, by plugin id.
+    rejected: HashMap<String, PluginCompatibility>,
+    configs: PluginConfigStore,
+    /// Quotas from plugin manifests, by plugin id.
+    quotas: HashMap<String, Arc<QuotaTracker>>,
+    /// Whether [`ServerPlugin::on_server_start`] has been called.
+    started: bool,
+    events: EventBus,
+    admission: AdmissionControl,
+    offline: OfflineMode,
+    /// Keys plugin binaries must be signed with.
+    trust: Arc<TrustRoot>,
 }
 
 impl PluginManager {
@@ -159,42 +1235,1178 @@ impl PluginManager {
         let metadata = plugin.metadata();
         self.metadata_cache
             .insert(metadata.id.clone(), metadata.clone());
-        self.plugins.insert(metadata.id.clone(), plugin);
+        if self.plugins.insert(metadata.id.clone(), plugin).is_none() {
+            self.order.push(metadata.id);
+        }
+    }
+
+    /// Registers the plugins `manifests` describe, each after the plugins it
+    /// depends on. A built-in that fails to start, a dependency no manifest
+    /// or registered plugin provides, and a dependency cycle are errors; other
+    /// plugins that fail to start, depend on one that did, or reuse the id of
+    /// a registered plugin are skipped with a warning.
+    pub async fn register_manifests(
+        &mut self,
+        manifests: &[PluginManifest],
+    ) -> anyhow::Result<Vec<PluginMetadata>> {
+        let mut instantiated = Vec::new();
+        let mut failed = Vec::new();
+        for manifest in manifests {
+            match manifest
+                .instantiate(&self.events, &self.offline, &self.trust)
+                .await
+            {
+                Ok(plugin) => instantiated.push((manifest, plugin)),
+                Err(err) if manifest.builtin_name().is_some() => return Err(err),
+                Err(err) => {
+                    tracing::warn!("skipping plugin {}: {}", manifest.id, err);
+                    failed.push(manifest.id.as_str());
+                }
+            }
+        }
+        let metadata: Vec<_> = instantiated
+            .iter()
+            .map(|(_, plugin)| plugin.metadata())
+            .collect();
+        // Dependents of plugins that failed to start are skipped below.
+        let available = self
+            .plugins
+            .keys()
+            .map(String::as_str)
+            .chain(failed)
+            .collect();
+        let order = dependencies::start_order(&metadata, &available)?;
+
+        let mut instantiated: Vec<_> = instantiated.into_iter().map(Some).collect();
+        let mut registered = Vec::new();
+        for index in order {
+            let Some((manifest, plugin)) = instantiated[index].take() else {
+                continue;
+            };
+            match self.try_register(plugin).await {
+                Ok(metadata) => {
+                    self.set_source(&metadata.id, manifest.clone());
+                    registered.push(metadata);
+                }
+                Err(err) => tracing::warn!("skipping plugin manifest: {}", err),
+            }
+        }
+        Ok(registered)
+    }
+
+    pub fn set_config_store(&mut self, configs: PluginConfigStore) {
+        self.configs = configs;
+    }
+
+    /// Requires executable and library plugins registered from now on to be
+    /// signed by one of `trust`'s keys, unless it has none.
+    pub fn set_trust_root(&mut self, trust: TrustRoot) {
+        self.trust = Arc::new(trust);
+    }
+
+    /// Hands every registered plugin its stored configuration. Plugins that
+    /// reject it keep running unconfigured, with a warning.
+    pub async fn configure_all(&self) {
+        for (plugin_id, plugin) in &self.plugins {
+            let Some(config) = self.configs.get(plugin_id) else {
+                continue;
+            };
+            if let Err(err) = plugin.configure(config).await {
+                tracing::warn!("cannot configure plugin {}: {}", plugin_id, err);
+            }
+        }
+    }
+
+    /// Registers every plugin library in `dir`. Libraries that fail to load or
+    /// reuse the id of a registered plugin are skipped with a warning.
+    pub async fn load_directory(&mut self, dir: &Path) -> std::io::Result<Vec<PluginMetadata>> {
+        let mut loaded = Vec::new();
+        for path in dynamic::libraries(dir)? {
+            let signature = match self.trust.verify(&path).await {
+                Ok(signature) => signature,
+                Err(err) => {
+                    tracing::warn!("skipping plugin library: {}", err);
+                    continue;
+                }
+            };
+            let plugin = match dynamic::load(&path) {
+                Ok(plugin) => plugin.with_signature(signature),
+                Err(err) => {
+                    tracing::warn!("skipping plugin library: {}", err);
+                    continue;
+                }
+            };
+            match self.try_register(Arc::new(plugin)).await {
+                Ok(metadata) => {
+                    tracing::info!("loaded plugin {} from {}", metadata.id, path.display());
+                    let mut source = PluginManifest::new(&metadata.id, PluginKind::Library);
+                    source.binary = Some(path.to_string_lossy().into_owned());
+                    self.set_source(&metadata.id, source);
+                    loaded.push(metadata);
+                }
+                Err(err) => tracing::warn!("skipping {}: {}", path.display(), err),
+            }
+        }
+        Ok(loaded)
+    }
+
+    /// Starts and registers external plugin processes. Plugins that fail to
+    /// start or reuse the id of a registered plugin are skipped with a warning.
+    pub async fn spawn_external(
+        &mut self,
+        configs: &[ExternalPluginConfig],
+    ) -> Vec<PluginMetadata> {
+        let mut started = Vec::new();
+        for config in configs {
+            let signature = match self.trust.verify_command(&config.command).await {
+                Ok(signature) => signature,
+                Err(err) => {
+                    tracing::warn!("skipping external plugin {}: {}", config.command, err);
+                    continue;
+                }
+            };
+            let plugin = match external::spawn(config).await {
+                Ok(plugin) => plugin.with_signature(signature),
+                Err(err) => {
+                    tracing::warn!("skipping external plugin {}: {}", config.command, err);
+                    continue;
+                }
+            };
+            match self.try_register(Arc::new(plugin)).await {
+                Ok(metadata) => {
+                    tracing::info!(
+                        "started external plugin {} ({})",
+                        metadata.id,
+                        config.command
+                    );
+                    self.sources
+                        .insert(metadata.id.clone(), executable_source(&metadata, config));
+                    started.push(metadata);
+                }
+                Err(err) => tracing::warn!("skipping {}: {}", config.command, err),
+            }
+        }
+        started
+    }
+
+    /// Connects to and registers gRPC plugins. Plugins that cannot be reached
+    /// or reuse the id of a registered plugin are skipped with a warning.
+    pub async fn connect_grpc(&mut self, configs: &[GrpcPluginConfig]) -> Vec<PluginMetadata> {
+        let mut connected = Vec::new();
+        for config in configs {
+            let plugin = match grpc::connect(config, None).await {
+                Ok(plugin) => plugin,
+                Err(err) => {
+                    tracing::warn!("skipping gRPC plugin {}: {}", config.url, err);
+                    continue;
+                }
+            };
+            match self.try_register(Arc::new(plugin)).await {
+                Ok(metadata) => {
+                    tracing::info!("connected gRPC plugin {} ({})", metadata.id, config.url);
+                    self.sources
+                        .insert(metadata.id.clone(), grpc_source(&metadata, config));
+                    connected.push(metadata);
+                }
+                Err(err) => tracing::warn!("skipping {}: {}", config.url, err),
+            }
+        }
+        connected
+    }
+
+    /// Registers a plugin unless one with the same id already is or one of
+    /// its dependencies is not, after running its registration hooks.
+    pub async fn try_register(
+        &mut self,
+        plugin: Arc<dyn ServerPlugin>,
+    ) -> Result<PluginMetadata, PluginError> {
+        self.check_registrable(&plugin.metadata())?;
+        run_register_hooks(plugin.as_ref(), self.started).await?;
+        self.try_insert(plugin)
+    }
+
+    fn try_insert(&mut self, plugin: Arc<dyn ServerPlugin>) -> Result<PluginMetadata, PluginError> {
+        let metadata = plugin.metadata();
+        self.check_registrable(&metadata)?;
+        self.register(plugin);
+        Ok(metadata)
+    }
+
+    fn check_registrable(&mut self, metadata: &PluginMetadata) -> Result<(), PluginError> {
+        if self.plugins.contains_key(&metadata.id) {
+            return Err(PluginError::AlreadyRegistered(metadata.id.clone()));
+        }
+        let report = PluginCompatibility::check(metadata);
+        if report.compatible {
+            self.rejected.remove(&metadata.id);
+        } else {
+            self.rejected.insert(metadata.id.clone(), report.clone());
+        }
+        report.into_result()?;
+        self.check_dependencies(metadata)
+    }
+
+    /// API compatibility of a registered plugin, or why it was rejected.
+    /// `None` when no plugin with this id was registered or rejected.
+    pub fn compatibility(
+        &self,
+        plugin_id: &str,
+    ) -> Option<Result<PluginCompatibility, PluginError>> {
+        match self.metadata_cache.get(plugin_id) {
+            Some(metadata) => Some(Ok(PluginCompatibility::check(metadata))),
+            None => self
+                .rejected
+                .get(plugin_id)
+                .map(|report| Err(PluginError::Incompatible(Box::new(report.clone())))),
+        }
+    }
+
+    fn check_dependencies(&self, metadata: &PluginMetadata) -> Result<(), PluginError> {
+        let registered = self.plugins.keys().map(String::as_str).collect();
+        dependencies::start_order(std::slice::from_ref(metadata), &registered).map(|_| ())
+    }
+
+    /// Ids of registered plugins that depend on `plugin_id`.
+    pub fn dependents(&self, plugin_id: &str) -> Vec<String> {
+        self.order
+            .iter()
+            .filter(|id| {
+                self.metadata_cache.get(*id).is_some_and(|metadata| {
+                    metadata.dependencies.iter().any(|dep| dep == plugin_id)
+                })
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Enabled plugins other than its publisher subscribed to `event`.
+    fn subscribers(&self, event: &PluginEvent) -> Vec<(String, Arc<dyn ServerPlugin>)> {
+        self.ordered_plugins()
+            .into_iter()
+            .filter(|(plugin_id, _)| {
+                *plugin_id != event.plugin_id
+                    && self.metadata_cache.get(plugin_id).is_some_and(|metadata| {
+                        metadata.enabled
+                            && metadata
+                                .subscriptions
+                                .iter()
+                                .any(|subscription| event.event.matches(subscription))
+                    })
+            })
+            .collect()
+    }
+
+    /// Registered plugins, dependencies first.
+    fn ordered_plugins(&self) -> Vec<(String, Arc<dyn ServerPlugin>)> {
+        self.order
+            .iter()
+            .filter_map(|id| Some((id.clone(), self.plugins.get(id)?.clone())))
+            .collect()
+    }
+
+    /// Removes a plugin without stopping it; see [`SharedPluginManager::unregister`].
+    pub fn unregister(&mut self, plugin_id: &str) -> Option<Arc<dyn ServerPlugin>> {
+        self.metadata_cache.remove(plugin_id);
+        self.sources.remove(plugin_id);
+        self.quotas.remove(plugin_id);
+        self.order.retain(|id| id != plugin_id);
+        self.plugins.remove(plugin_id)
     }
 
     pub fn plugin(&self, plugin_id: &str) -> Option<Arc<dyn ServerPlugin>> {
         self.plugins.get(plugin_id).cloned()
     }
 
+    /// Like [`plugin`](Self::plugin), but fails for disabled plugins and
+    /// enforces the plugin's quota on calls made through it.
+    pub fn active(&self, plugin_id: &str) -> Option<Result<Arc<dyn ServerPlugin>, PluginError>> {
+        let plugin = self.plugin(plugin_id)?;
+        if !self.is_enabled(plugin_id) {
+            return Some(Err(PluginError::Disabled(plugin_id.to_string())));
+        }
+        Some(Ok(match self.quotas.get(plugin_id) {
+            Some(tracker) => Arc::new(QuotaPlugin::new(plugin, tracker.clone())),
+            None => plugin,
+        }))
+    }
+
+    /// Records how a plugin was created, keeping its operations in flight
+    /// counted unless its quota changed.
+    fn set_source(&mut self, plugin_id: &str, source: PluginManifest) {
+        match source.quota {
+            Some(quota) => {
+                if self
+                    .quotas
+                    .get(plugin_id)
+                    .is_none_or(|tracker| tracker.quota() != quota)
+                {
+                    self.quotas
+                        .insert(plugin_id.to_string(), Arc::new(QuotaTracker::new(quota)));
+                }
+            }
+            None => {
+                self.quotas.remove(plugin_id);
+            }
+        }
+        self.sources.insert(plugin_id.to_string(), source);
+    }
+
+    pub fn is_enabled(&self, plugin_id: &str) -> bool {
+        self.metadata_cache
+            .get(plugin_id)
+            .is_some_and(|metadata| metadata.enabled)
+    }
+
+    /// Returns the updated metadata, or `None` when no such plugin is registered.
+    pub fn set_enabled(&mut self, plugin_id: &str, enabled: bool) -> Option<PluginMetadata> {
+        let metadata = self.metadata_cache.get_mut(plugin_id)?;
+        metadata.enabled = enabled;
+        Some(metadata.clone())
+    }
+
     pub fn all_metadata(&self) -> Vec<PluginMetadata> {
         self.metadata_cache.values().cloned().collect()
     }
+
+    fn snapshot(&self) -> PluginSnapshot {
+        PluginSnapshot {
+            active: self
+                .plugins
+                .keys()
+                .map(|plugin_id| {
+                    let plugin = self.active(plugin_id).and_then(Result::ok);
+                    (plugin_id.clone(), plugin)
+                })
+                .collect(),
+            metadata: self.all_metadata(),
+        }
+    }
+
+    pub fn events(&self) -> EventBus {
+        self.events.clone()
+    }
+
+    pub fn admission(&self) -> AdmissionControl {
+        self.admission.clone()
+    }
+
+    pub fn offline(&self) -> OfflineMode {
+        self.offline.clone()
+    }
+
+    pub fn trust_root(&self) -> Arc<TrustRoot> {
+        self.trust.clone()
+    }
+}
+
+/// Hooks a plugin runs before it is registered; `started` when the server
+/// already accepts requests.
+async fn run_register_hooks(plugin: &dyn ServerPlugin, started: bool) -> Result<(), PluginError> {
+    plugin.on_register().await?;
+    if started {
+        plugin.on_server_start().await?;
+    }
+    Ok(())
+}
+
+/// Source of an external plugin so it can be started again on reload.
+fn executable_source(metadata: &PluginMetadata, config: &ExternalPluginConfig) -> PluginManifest {
+    let mut source = PluginManifest::new(&metadata.id, PluginKind::Executable);
+    source.binary = Some(config.command.clone());
+    source.args = config.args.clone();
+    source.environment = config.environment.clone();
+    source
+}
+
+fn grpc_source(metadata: &PluginMetadata, config: &GrpcPluginConfig) -> PluginManifest {
+    let mut source = PluginManifest::new(&metadata.id, PluginKind::Grpc);
+    source.url = Some(config.url.clone());
+    source.auth_token = config.auth_token.clone();
+    source
+}
+
+/// What lookups on every request read, published anew after each change
+/// to the manager so they never wait for its lock.
+#[derive(Default)]
+struct PluginSnapshot {
+    /// As [`PluginManager::active`] returns them; `None` while disabled.
+    active: HashMap<String, Option<Arc<dyn ServerPlugin>>>,
+    metadata: Vec<PluginMetadata>,
+}
+
+/// Write access to the manager that publishes a new snapshot when released.
+struct ManagerWriteGuard<'a> {
+    guard: RwLockWriteGuard<'a, PluginManager>,
+    snapshot: &'a ArcSwap<PluginSnapshot>,
+}
+
+impl Deref for ManagerWriteGuard<'_> {
+    type Target = PluginManager;
+
+    fn deref(&self) -> &PluginManager {
+        &self.guard
+    }
+}
+
+impl DerefMut for ManagerWriteGuard<'_> {
+    fn deref_mut(&mut self) -> &mut PluginManager {
+        &mut self.guard
+    }
+}
+
+impl Drop for ManagerWriteGuard<'_> {
+    fn drop(&mut self) {
+        self.snapshot.store(Arc::new(self.guard.snapshot()));
+    }
 }
 
 #[derive(Clone)]
 pub struct SharedPluginManager {
     inner: Arc<RwLock<PluginManager>>,
+    snapshot: Arc<ArcSwap<PluginSnapshot>>,
 }
 
 impl SharedPluginManager {
     pub fn new(manager: PluginManager) -> Self {
+        let snapshot = ArcSwap::from_pointee(manager.snapshot());
         Self {
             inner: Arc::new(RwLock::new(manager)),
+            snapshot: Arc::new(snapshot),
+        }
+    }
+
+    async fn write(&self) -> ManagerWriteGuard<'_> {
+        ManagerWriteGuard {
+            guard: self.inner.write().await,
+            snapshot: &self.snapshot,
         }
     }
 
     pub async fn list_metadata(&self) -> Vec<PluginMetadata> {
+        self.snapshot.load().metadata.clone()
+    }
+
+    /// See [`PluginManager::active`]. Takes no lock.
+    pub async fn active(
+        &self,
+        plugin_id: &str,
+    ) -> Option<Result<Arc<dyn ServerPlugin>, PluginError>> {
+        match self.snapshot.load().active.get(plugin_id)? {
+            Some(plugin) => Some(Ok(plugin.clone())),
+            None => Some(Err(PluginError::Disabled(plugin_id.to_string()))),
+        }
+    }
+
+    /// Enables or disables a plugin. Services it is running are left alone.
+    pub async fn set_enabled(&self, plugin_id: &str, enabled: bool) -> Option<PluginMetadata> {
+        let mut guard = self.write().await;
+        let metadata = guard.set_enabled(plugin_id, enabled)?;
+        tracing::info!(
+            "plugin {} {}",
+            plugin_id,
+            if enabled { "enabled" } else { "disabled" }
+        );
+        Some(metadata)
+    }
+
+    /// Registers a plugin, replacing any with the same id, unless its
+    /// registration hooks fail.
+    #[allow(dead_code)] // Used by AppState::with_plugins
+    pub async fn register(&self, plugin: Arc<dyn ServerPlugin>) -> Result<(), PluginError> {
+        PluginCompatibility::check(&plugin.metadata()).into_result()?;
+        let started = self.inner.read().await.started;
+        run_register_hooks(plugin.as_ref(), started).await?;
+        let mut guard = self.write().await;
+        guard.register(plugin);
+        Ok(())
+    }
+
+    /// Delivers every published event to the enabled plugins subscribed to
+    /// it, other than the one that published it, until the bus closes.
+    pub async fn spawn_event_delivery(&self) {
+        let mut receiver = self.events().await.subscribe();
+        let manager = self.clone();
+        tokio::spawn(async move {
+            loop {
+                let event = match receiver.recv().await {
+                    Ok(event) => event,
+                    Err(broadcast::error::RecvError::Lagged(missed)) => {
+                        tracing::warn!("plugin event delivery skipped {} events", missed);
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return,
+                };
+                let subscribers = manager.inner.read().await.subscribers(&event);
+                let results = futures::future::join_all(
+                    subscribers
+                        .iter()
+                        .map(|(_, plugin)| plugin.on_event(&event)),
+                )
+                .await;
+                for ((plugin_id, _), result) in subscribers.iter().zip(results) {
+                    if let Err(err) = result {
+                        tracing::warn!(
+                            "plugin {} failed to handle {} event: {}",
+                            plugin_id,
+                            event.event.event_type(),
+                            err
+                        );
+                    }
+                }
+            }
+        });
+    }
+
+    /// Runs [`ServerPlugin::on_server_start`] on every registered plugin,
+    /// dependencies first. Plugins registered afterwards run it on
+    /// registration.
+    pub async fn server_started(&self) {
+        let plugins = {
+            let mut guard = self.write().await;
+            guard.started = true;
+            guard.ordered_plugins()
+        };
+        for (plugin_id, plugin) in plugins {
+            if let Err(err) = plugin.on_server_start().await {
+                tracing::warn!("plugin {} failed to start: {}", plugin_id, err);
+            }
+        }
+    }
+
+    /// Metrics of every enabled plugin that reports them, with totals.
+    pub async fn metrics(&self) -> PluginMetricsSnapshot {
+        let plugins: Vec<_> = {
+            let guard = self.inner.read().await;
+            guard
+                .ordered_plugins()
+                .into_iter()
+                .filter(|(plugin_id, _)| guard.is_enabled(plugin_id))
+                .collect()
+        };
+        let results =
+            futures::future::join_all(plugins.iter().map(|(_, plugin)| plugin.metrics())).await;
+        let entries = plugins
+            .into_iter()
+            .zip(results)
+            .filter_map(|((plugin_id, _), result)| match result {
+                Ok(metrics) => Some(PluginMetricsEntry {
+                    plugin_id,
+                    metrics: Some(metrics),
+                    error: None,
+                }),
+                Err(PluginError::UnsupportedOperation) => None,
+                Err(err) => Some(PluginMetricsEntry {
+                    plugin_id,
+                    metrics: None,
+                    error: Some(err.to_string()),
+                }),
+            })
+            .collect();
+        PluginMetricsSnapshot::new(entries)
+    }
+
+    /// Hands every registered plugin the state it saved when the server last
+    /// stopped, dependencies first.
+    pub async fn restore_states(&self, states: &PluginStateDir) {
+        let plugins = self.inner.read().await.ordered_plugins();
+        for (plugin_id, plugin) in plugins {
+            let result = match states.load(&plugin_id).await {
+                Ok(Some(state)) => plugin.restore_state(state).await,
+                Ok(None) => Ok(()),
+                Err(err) => Err(err),
+            };
+            if let Err(err) = result {
+                tracing::warn!("plugin {} failed to restore its state: {}", plugin_id, err);
+            }
+        }
+    }
+
+    /// Saves the state of every registered plugin, ahead of
+    /// [`shutdown_all`](Self::shutdown_all) stopping their services.
+    pub async fn save_states(&self, states: &PluginStateDir) {
+        let plugins = self.inner.read().await.ordered_plugins();
+        for (plugin_id, plugin) in plugins {
+            let result = match plugin.save_state().await {
+                Ok(state) => states.save(&plugin_id, state.as_ref()).await,
+                Err(err) => Err(err),
+            };
+            if let Err(err) = result {
+                tracing::warn!("plugin {} failed to save its state: {}", plugin_id, err);
+            }
+        }
+    }
+
+    /// Runs [`ServerPlugin::on_shutdown`] on every registered plugin as the
+    /// server stops, dependents before their dependencies. Plugins stay
+    /// registered.
+    pub async fn shutdown_all(&self) {
+        let plugins = self.inner.read().await.ordered_plugins();
+        for (plugin_id, plugin) in plugins.into_iter().rev() {
+            if let Err(err) = plugin.on_shutdown().await {
+                tracing::warn!("plugin {} failed to shut down: {}", plugin_id, err);
+            }
+        }
+    }
+
+    /// Starts or connects to the plugin `request` describes and registers it.
+    pub async fn register_runtime(
+        &self,
+        request: RegisterPluginRequest,
+    ) -> Result<PluginMetadata, PluginError> {
+        let (plugin, source): (Arc<dyn ServerPlugin>, _) =
+            match (&request.executable, &request.remote) {
+                (Some(config), None) => {
+                    let signature = self
+                        .trust_root()
+                        .await
+                        .verify_command(&config.command)
+                        .await?;
+                    let mut plugin = external::spawn(config).await?;
+                    if let Some(metadata) = request.metadata.clone() {
+                        plugin = plugin.with_metadata(metadata);
+                    }
+                    let plugin = plugin.with_signature(signature);
+                    let source = executable_source(&plugin.metadata(), config);
+                    (Arc::new(plugin), source)
+                }
+                (None, Some(config)) => {
+                    let plugin = http::connect(config, request.metadata.clone()).await?;
+                    let mut source = PluginManifest::new(&plugin.metadata().id, PluginKind::Remote);
+                    source.url = Some(config.url.clone());
+                    source.auth_token = config.auth_token.clone();
+                    (Arc::new(plugin), source)
+                }
+                _ => {
+                    return Err(PluginError::InvalidRequest(
+                        "exactly one of executable and remote is required".to_string(),
+                    ))
+                }
+            };
+        let source = match &request.metadata {
+            Some(metadata) => PluginManifest {
+                name: Some(metadata.name.clone()),
+                description: Some(metadata.description.clone()),
+                capabilities: Some(metadata.capabilities.clone()),
+                ..source
+            },
+            None => source,
+        };
+        self.register_instance(plugin, source).await
+    }
+
+    /// Registers the plugin a manifest describes, e.g. one installed from
+    /// the [`catalog`].
+    pub async fn register_manifest(
+        &self,
+        manifest: PluginManifest,
+    ) -> Result<PluginMetadata, PluginError> {
+        let (events, offline, trust) = {
+            let guard = self.inner.read().await;
+            (guard.events(), guard.offline(), guard.trust_root())
+        };
+        let plugin = manifest
+            .instantiate(&events, &offline, &trust)
+            .await
+            .map_err(|err| {
+                err.downcast::<PluginError>()
+                    .unwrap_or_else(|err| PluginError::Internal(err.to_string()))
+            })?;
+        self.register_instance(plugin, manifest).await
+    }
+
+    async fn register_instance(
+        &self,
+        plugin: Arc<dyn ServerPlugin>,
+        source: PluginManifest,
+    ) -> Result<PluginMetadata, PluginError> {
+        let metadata = plugin.metadata();
+        let started = {
+            let mut guard = self.write().await;
+            guard.check_registrable(&metadata)?;
+            guard.started
+        };
+        run_register_hooks(plugin.as_ref(), started).await?;
+        if let Err(err) = self
+            .apply_stored_config(plugin.as_ref(), &metadata.id)
+            .await
+        {
+            tracing::warn!("cannot configure plugin {}: {}", metadata.id, err);
+        }
+        let mut guard = self.write().await;
+        let metadata = guard.try_insert(plugin)?;
+        guard.set_source(&metadata.id, source);
+        Ok(metadata)
+    }
+
+    async fn apply_stored_config(
+        &self,
+        plugin: &dyn ServerPlugin,
+        plugin_id: &str,
+    ) -> Result<(), PluginError> {
+        let config = self.inner.read().await.configs.get(plugin_id).cloned();
+        match config {
+            Some(config) => plugin.configure(&config).await,
+            None => Ok(()),
+        }
+    }
+
+    /// The stored configuration of a plugin, empty if none was set. `None`
+    /// when no such plugin is registered.
+    pub async fn config(&self, plugin_id: &str) -> Option<PluginConfig> {
         let guard = self.inner.read().await;
-        guard.all_metadata()
+        guard.plugin(plugin_id)?;
+        Some(guard.configs.get(plugin_id).cloned().unwrap_or_default())
     }
 
-    pub async fn plugin(&self, plugin_id: &str) -> Option<Arc<dyn ServerPlugin>> {
+    /// See [`PluginManager::compatibility`].
+    pub async fn compatibility(
+        &self,
+        plugin_id: &str,
+    ) -> Option<Result<PluginCompatibility, PluginError>> {
         let guard = self.inner.read().await;
-        guard.plugin(plugin_id)
+        guard.compatibility(plugin_id)
     }
 
-    pub async fn register(&self, plugin: Arc<dyn ServerPlugin>) {
-        let mut guard = self.inner.write().await;
-        guard.register(plugin);
+    /// `None` when no such plugin is registered.
+    pub async fn config_schema(&self, plugin_id: &str) -> Option<serde_json::Value> {
+        let guard = self.inner.read().await;
+        guard.plugin(plugin_id).map(|plugin| plugin.config_schema())
+    }
+
+    /// Applies a configuration to a plugin and stores it once the plugin
+    /// accepts it. `None` when no such plugin is registered.
+    pub async fn set_config(
+        &self,
+        plugin_id: &str,
+        config: PluginConfig,
+    ) -> Option<Result<PluginConfig, PluginError>> {
+        let plugin = self.inner.read().await.plugin(plugin_id)?;
+        if let Err(err) = plugin.configure(&config).await {
+            return Some(Err(err));
+        }
+        let stored = self
+            .inner
+            .write()
+            .await
+            .configs
+            .set(plugin_id, config.clone())
+            .await;
+        Some(stored.map(|()| config))
+    }
+
+    /// Recreates a plugin from its manifest, read again if it came from a
+    /// file, and swaps it in. Requests still using the previous instance are
+    /// given [`RELOAD_DRAIN_TIMEOUT`] to finish; its running services are then
+    /// handed over if both instances support it and stopped otherwise. `None`
+    /// when no such plugin is registered.
+    pub async fn reload(
+        &self,
+        plugin_id: &str,
+    ) -> Option<Result<ReloadPluginResponse, PluginError>> {
+        let (source, events, offline, trust) = {
+            let guard = self.inner.read().await;
+            guard.plugins.get(plugin_id)?;
+            (
+                guard.sources.get(plugin_id).cloned(),
+                guard.events(),
+                guard.offline(),
+                guard.trust_root(),
+            )
+        };
+        let Some(source) = source else {
+            return Some(Err(PluginError::InvalidRequest(format!(
+                "plugin {} was not registered from a manifest and cannot be reloaded",
+                plugin_id
+            ))));
+        };
+        Some(
+            self.reload_from(plugin_id, source, events, offline, trust)
+                .await,
+        )
+    }
+
+    async fn reload_from(
+        &self,
+        plugin_id: &str,
+        source: PluginManifest,
+        events: EventBus,
+        offline: OfflineMode,
+        trust: Arc<TrustRoot>,
+    ) -> Result<ReloadPluginResponse, PluginError> {
+        let into_plugin_error = |err: anyhow::Error| {
+            err.downcast::<PluginError>()
+                .unwrap_or_else(|err| PluginError::Internal(err.to_string()))
+        };
+        let source = source.reread().map_err(|err| {
+            PluginError::InvalidRequest(format!("cannot read manifest of {}: {}", plugin_id, err))
+        })?;
+        let plugin = source
+            .instantiate(&events, &offline, &trust)
+            .await
+            .map_err(into_plugin_error)?;
+        let mut metadata = plugin.metadata();
+        if metadata.id != plugin_id {
+            return Err(PluginError::InvalidRequest(format!(
+                "reloaded plugin reports id {} instead of {}",
+                metadata.id, plugin_id
+            )));
+        }
+        PluginCompatibility::check(&metadata).into_result()?;
+        let started = {
+            let guard = self.inner.read().await;
+            guard.check_dependencies(&metadata)?;
+            guard.started
+        };
+        run_register_hooks(plugin.as_ref(), started).await?;
+        self.apply_stored_config(plugin.as_ref(), plugin_id).await?;
+
+        let previous = {
+            let mut guard = self.write().await;
+            let previous = guard.plugins.get(plugin_id).cloned();
+            // Reloading keeps a plugin disabled.
+            let enabled = previous.is_none() || guard.is_enabled(plugin_id);
+            guard.register(plugin.clone());
+            guard.set_source(&metadata.id, source);
+            if let Some(updated) = guard.set_enabled(plugin_id, enabled && metadata.enabled) {
+                metadata = updated;
+            }
+            previous
+        };
+        let mut response = ReloadPluginResponse {
+            metadata,
+            preserved_instances: Vec::new(),
+            stopped_instances: Vec::new(),
+        };
+        // Unregistered while the new instance was starting.
+        let Some(previous) = previous else {
+            return Ok(response);
+        };
+
+        let deadline = Instant::now() + RELOAD_DRAIN_TIMEOUT;
+        while Arc::strong_count(&previous) > 1 && Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        if Arc::strong_count(&previous) > 1 {
+            tracing::warn!("reloading {} while requests still use it", plugin_id);
+        }
+
+        match previous.hand_over().await {
+            Ok(handover) => response.preserved_instances = plugin.take_over(handover).await?,
+            Err(PluginError::UnsupportedOperation) => {}
+            Err(err) => tracing::warn!("{} cannot hand over its services: {}", plugin_id, err),
+        }
+        response.stopped_instances = previous.shutdown().await?;
+        Ok(response)
+    }
+
+    /// Removes a plugin and shuts it down once it no longer receives new
+    /// requests. Plugins other plugins depend on stay registered. `None` when
+    /// no such plugin is registered.
+    pub async fn unregister(
+        &self,
+        plugin_id: &str,
+    ) -> Option<Result<UnregisterPluginResponse, PluginError>> {
+        let plugin = {
+            let mut guard = self.write().await;
+            guard.plugins.get(plugin_id)?;
+            let dependents = guard.dependents(plugin_id);
+            if !dependents.is_empty() {
+                return Some(Err(PluginError::Dependency(format!(
+                    "plugin {} is required by {}",
+                    plugin_id,
+                    dependents.join(", ")
+                ))));
+            }
+            guard.unregister(plugin_id)?
+        };
+        Some(
+            plugin
+                .shutdown()
+                .await
+                .map(|stopped_instances| UnregisterPluginResponse {
+                    plugin_id: plugin_id.to_string(),
+                    stopped_instances,
+                }),
+        )
+    }
+
+    pub async fn events(&self) -> EventBus {
+        let guard = self.inner.read().await;
+        guard.events()
+    }
+
+    pub async fn admission(&self) -> AdmissionControl {
+        let guard = self.inner.read().await;
+        guard.admission()
+    }
+
+    pub async fn offline(&self) -> OfflineMode {
+        let guard = self.inner.read().await;
+        guard.offline()
+    }
+
+    pub async fn trust_root(&self) -> Arc<TrustRoot> {
+        let guard = self.inner.read().await;
+        guard.trust_root()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn task_types_are_validated_names() {
+        let embeddings: PluginTaskType = serde_json::from_str("\"embeddings\"").unwrap();
+        assert_eq!(embeddings.as_str(), "embeddings");
+        assert_eq!(
+            serde_json::from_str::<PluginTaskType>("\"tts\"").unwrap(),
+            PluginTaskType::TTS
+        );
+        assert_eq!(
+            serde_json::to_string(&PluginTaskType::TEXT).unwrap(),
+            "\"text\""
+        );
+        for invalid in ["", "Text", "1st", "../models", "speech to text"] {
+            assert!(PluginTaskType::new(invalid).is_err(), "{:?}", invalid);
+        }
+        let defaults: HashMap<PluginTaskType, String> =
+            serde_json::from_str(r#"{"speech-to-text": "whisper"}"#).unwrap();
+        assert_eq!(
+            defaults[&PluginTaskType::new("speech-to-text").unwrap()],
+            "whisper"
+        );
+    }
+
+    struct Idle(&'static str);
+
+    #[async_trait]
+    impl ServerPlugin for Idle {
+        fn metadata(&self) -> PluginMetadata {
+            PluginMetadata {
+                id: self.0.to_string(),
+                name: self.0.to_string(),
+                description: String::new(),
+                capabilities: Vec::new(),
+                enabled: true,
+                dependencies: Vec::new(),
+                api_version: compat::PLUGIN_API_VERSION,
+                subscriptions: Vec::new(),
+                signature: None,
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn lookups_do_not_wait_for_writers() {
+        let plugins = SharedPluginManager::new(PluginManager::new());
+        plugins.register(Arc::new(Idle("piper"))).await.unwrap();
+        plugins.set_enabled("piper", false).await.unwrap();
+        assert!(matches!(
+            plugins.active("piper").await,
+            Some(Err(PluginError::Disabled(_)))
+        ));
+        plugins.set_enabled("piper", true).await.unwrap();
+
+        let writer = plugins.inner.write().await;
+        assert!(matches!(plugins.active("piper").await, Some(Ok(_))));
+        assert!(plugins.active("whisper").await.is_none());
+        assert_eq!(plugins.list_metadata().await.len(), 1);
+        drop(writer);
+
+        plugins.unregister("piper").await.unwrap().unwrap();
+        assert!(plugins.active("piper").await.is_none());
+    }
+
+    /// Depends on `piper` and runs `instance` until shut down.
+    struct Narrator {
+        instance: &'static str,
+    }
+
+    #[async_trait]
+    impl ServerPlugin for Narrator {
+        fn metadata(&self) -> PluginMetadata {
+            PluginMetadata {
+                dependencies: vec!["piper".to_string()],
+                ..Idle("narrator").metadata()
+            }
+        }
+
+        async fn shutdown(&self) -> Result<Vec<String>, PluginError> {
+            Ok(vec![self.instance.to_string()])
+        }
+    }
+
+    #[tokio::test]
+    async fn unregistering_stops_the_plugin_unless_others_depend_on_it() {
+        let plugins = SharedPluginManager::new(PluginManager::new());
+        plugins.register(Arc::new(Idle("piper"))).await.unwrap();
+        plugins
+            .register(Arc::new(Narrator { instance: "abc" }))
+            .await
+            .unwrap();
+        assert!(plugins.unregister("whisper").await.is_none());
+
+        assert!(matches!(
+            plugins.unregister("piper").await,
+            Some(Err(PluginError::Dependency(_)))
+        ));
+        assert!(matches!(plugins.active("piper").await, Some(Ok(_))));
+
+        let response = plugins.unregister("narrator").await.unwrap().unwrap();
+        assert_eq!(response.plugin_id, "narrator");
+        assert_eq!(response.stopped_instances, vec!["abc".to_string()]);
+        assert!(plugins.active("narrator").await.is_none());
+        plugins.unregister("piper").await.unwrap().unwrap();
+        assert!(plugins.list_metadata().await.is_empty());
+    }
+
+    type Calls = Arc<std::sync::Mutex<Vec<String>>>;
+
+    /// Records in `calls` when it shuts down.
+    struct Closing {
+        id: &'static str,
+        calls: Calls,
+    }
+
+    #[async_trait]
+    impl ServerPlugin for Closing {
+        fn metadata(&self) -> PluginMetadata {
+            Idle(self.id).metadata()
+        }
+
+        async fn shutdown(&self) -> Result<Vec<String>, PluginError> {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(format!("{} shut down", self.id));
+            Ok(Vec::new())
+        }
+    }
+
+    /// A [`Closing`] that flushes in its shutdown hook first.
+    struct Flushing(Closing);
+
+    #[async_trait]
+    impl ServerPlugin for Flushing {
+        fn metadata(&self) -> PluginMetadata {
+            self.0.metadata()
+        }
+
+        async fn shutdown(&self) -> Result<Vec<String>, PluginError> {
+            self.0.shutdown().await
+        }
+
+        async fn on_shutdown(&self) -> Result<(), PluginError> {
+            self.0
+                .calls
+                .lock()
+                .unwrap()
+                .push(format!("{} flushed", self.0.id));
+            self.shutdown().await.map(|_| ())
+        }
+    }
+
+    #[tokio::test]
+    async fn shutting_down_runs_the_shutdown_hooks() {
+        let calls = Calls::default();
+        let plugins = SharedPluginManager::new(PluginManager::new());
+        plugins
+            .register(Arc::new(Closing {
+                id: "piper",
+                calls: calls.clone(),
+            }))
+            .await
+            .unwrap();
+        let flushing = Flushing(Closing {
+            id: "whisper",
+            calls: calls.clone(),
+        });
+        let tracker = Arc::new(QuotaTracker::new(quota::PluginQuota::default()));
+        plugins
+            .register(Arc::new(QuotaPlugin::new(Arc::new(flushing), tracker)))
+            .await
+            .unwrap();
+
+        plugins.shutdown_all().await;
+        assert_eq!(
+            *calls.lock().unwrap(),
+            ["whisper flushed", "whisper shut down", "piper shut down"]
+        );
+        assert_eq!(plugins.list_metadata().await.len(), 2);
+    }
+
+    /// Serves a remote plugin describing itself as `description` and
+    /// stopping `abc` when shut down.
+    async fn serve_remote(description: Arc<std::sync::Mutex<String>>) -> String {
+        use axum::extract::Path;
+        use axum::routing::{get, post};
+        use axum::{Json, Router};
+        use serde_json::json;
+
+        let app = Router::new()
+            .route(
+                "/metadata",
+                get(move || {
+                    let description = description.lock().unwrap().clone();
+                    async move {
+                        Json(PluginMetadata {
+                            description,
+                            ..Idle("piper").metadata()
+                        })
+                    }
+                }),
+            )
+            .route(
+                "/call/{method}",
+                post(|Path(method): Path<String>| async move {
+                    Json(match method.as_str() {
+                        "shutdown" => json!({ "ok": ["abc"] }),
+                        _ => json!({ "error": { "kind": "unsupported_operation" } }),
+                    })
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        url
+    }
+
+    #[tokio::test]
+    async fn reloading_reconnects_and_keeps_the_plugin_disabled() {
+        let description = Arc::new(std::sync::Mutex::new("v1".to_string()));
+        let url = serve_remote(description.clone()).await;
+        let plugins = SharedPluginManager::new(PluginManager::new());
+        let registered = plugins
+            .register_runtime(RegisterPluginRequest {
+                executable: None,
+                remote: Some(HttpPluginConfig {
+                    url,
+                    auth_token: None,
+                }),
+                metadata: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(registered.description, "v1");
+        plugins.set_enabled("piper", false).await.unwrap();
+
+        *description.lock().unwrap() = "v2".to_string();
+        let reloaded = plugins.reload("piper").await.unwrap().unwrap();
+        assert_eq!(reloaded.metadata.description, "v2");
+        assert!(!reloaded.metadata.enabled);
+        assert!(reloaded.preserved_instances.is_empty());
+        assert_eq!(reloaded.stopped_instances, vec!["abc".to_string()]);
+        assert!(matches!(
+            plugins.active("piper").await,
+            Some(Err(PluginError::Disabled(_)))
+        ));
+
+        assert!(plugins.reload("whisper").await.is_none());
+        plugins.register(Arc::new(Idle("whisper"))).await.unwrap();
+        assert!(matches!(
+            plugins.reload("whisper").await,
+            Some(Err(PluginError::InvalidRequest(_)))
+        ));
     }
 }
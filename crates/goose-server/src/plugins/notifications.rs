@@ -0,0 +1,254 @@
+//! Posts selected plugin events to Slack and Discord channels.
+
+use std::time::Duration;
+
+use serde::Deserialize;
+use serde_json::json;
+use tokio::sync::broadcast::error::RecvError;
+
+use super::events::{EventBus, PluginEvent, PluginEventKind};
+use super::redact::resolve_secret_ref;
+
+const SEND_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Event types posted when a sink does not list its own.
+const DEFAULT_EVENTS: &[&str] = &[
+    "model_downloaded",
+    "service_restarted",
+    "service_restart_failed",
+    "disk_full",
+];
+
+/// Where a sink posts. Either a webhook URL or a bot token plus channel is
+/// required; both accept `secret://NAME` references into goose's secret store.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SinkTarget {
+    Slack {
+        #[serde(default)]
+        webhook_url: Option<String>,
+        #[serde(default)]
+        bot_token: Option<String>,
+        #[serde(default)]
+        channel: Option<String>,
+    },
+    Discord {
+        #[serde(default)]
+        webhook_url: Option<String>,
+        #[serde(default)]
+        bot_token: Option<String>,
+        /// Channel id for bot posts.
+        #[serde(default)]
+        channel: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct NotificationSink {
+    #[serde(flatten)]
+    pub target: SinkTarget,
+    /// Event types to post, e.g. `service_restarted`; defaults to downloads,
+    /// restarts and full disks.
+    #[serde(default)]
+    pub events: Option<Vec<String>>,
+}
+
+impl NotificationSink {
+    /// Sinks from `GOOSE_NOTIFICATIONS`, a JSON array of sink objects.
+    pub fn from_env() -> anyhow::Result<Vec<NotificationSink>> {
+        let Ok(raw) = std::env::var("GOOSE_NOTIFICATIONS") else {
+            return Ok(Vec::new());
+        };
+        serde_json::from_str(&raw)
+            .map_err(|err| anyhow::anyhow!("invalid GOOSE_NOTIFICATIONS: {}", err))
+    }
+
+    fn wants(&self, event_type: &str) -> bool {
+        match &self.events {
+            Some(events) => events.iter().any(|wanted| wanted == event_type),
+            None => DEFAULT_EVENTS.contains(&event_type),
+        }
+    }
+
+    async fn post(&self, client: &reqwest::Client, text: &str) -> anyhow::Result<()> {
+        let request = match &self.target {
+            SinkTarget::Slack {
+                webhook_url: Some(url),
+                ..
+            } => client
+                .post(resolve_secret_ref(url)?)
+                .json(&json!({ "text": text })),
+            SinkTarget::Slack {
+                bot_token: Some(token),
+                channel: Some(channel),
+                ..
+            } => client
+                .post("https://slack.com/api/chat.postMessage")
+                .bearer_auth(resolve_secret_ref(token)?)
+                .json(&json!({ "channel": channel, "text": text })),
+            SinkTarget::Discord {
+                webhook_url: Some(url),
+                ..
+            } => client
+                .post(resolve_secret_ref(url)?)
+                .json(&json!({ "content": text })),
+            SinkTarget::Discord {
+                bot_token: Some(token),
+                channel: Some(channel),
+                ..
+            } => client
+                .post(format!(
+                    "https://discord.com/api/v10/channels/{}/messages",
+                    channel
+                ))
+                .header(
+                    "Authorization",
+                    format!("Bot {}", resolve_secret_ref(token)?),
+                )
+                .json(&json!({ "content": text })),
+            _ => anyhow::bail!("sink needs a webhook_url or a bot_token and channel"),
+        };
+        let response = request
+            .timeout(SEND_TIMEOUT)
+            .send()
+            .await?
+            .error_for_status()?;
+        // Slack's Web API reports failures in the body with a 200.
+        if matches!(
+            self.target,
+            SinkTarget::Slack {
+                webhook_url: None,
+                ..
+            }
+        ) {
+            let body: serde_json::Value = response.json().await?;
+            if body["ok"] != json!(true) {
+                anyhow::bail!("slack rejected the message: {}", body["error"]);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// One-line message for an event, or `None` for events never posted.
+fn format(event: &PluginEvent) -> Option<String> {
+    let text = match &event.event {
+        PluginEventKind::ServiceOutput { .. } | PluginEventKind::Custom { .. } => return None,
+        PluginEventKind::ServiceUnhealthy {
+            task_type,
+            consecutive_failures,
+            reason,
+            ..
+        } => format!(
+            "{:?} service is unhealthy after {} failed checks: {}",
+            task_type, consecutive_failures, reason
+        ),
+        PluginEventKind::ServiceRecovered { task_type, .. } => {
+            format!("{:?} service recovered", task_type)
+        }
+        PluginEventKind::ServiceRestarted {
+            task_type,
+            pid,
+            restarts,
+            ..
+        } => format!(
+            "{:?} service crashed and was restarted (pid {}, restart {})",
+            task_type, pid, restarts
+        ),
+        PluginEventKind::ServiceRestartFailed {
+            task_type, reason, ..
+        } => format!("{:?} service could not be restarted: {}", task_type, reason),
+        PluginEventKind::ModelUpdateAvailable { update } => format!(
+            "update available for {}/{} on {}: {}",
+            update.model_id, update.filename, update.revision, update.latest_commit
+        ),
+        PluginEventKind::ModelDownloaded {
+            model_id,
+            filename,
+            bytes,
+            node,
+            ..
+        } => match node {
+            Some(node) => format!(
+                "downloaded {}/{} ({} bytes) on {}",
+                model_id, filename, bytes, node
+            ),
+            None => format!("downloaded {}/{} ({} bytes)", model_id, filename, bytes),
+        },
+        PluginEventKind::ModelDiscovered {
+            saved_path, bytes, ..
+        } => format!("found model {} ({} bytes)", saved_path, bytes),
+        PluginEventKind::ModelRemoved { saved_path, .. } => {
+            format!("model {} was deleted", saved_path)
+        }
+        PluginEventKind::DiskFull { path, message } => {
+            format!("out of disk space writing {}: {}", path, message)
+        }
+    };
+    Some(format!("[goosed/{}] {}", event.plugin_id, text))
+}
+
+/// Posts events to the configured sinks until the bus closes. Failures are
+/// logged and never reach the plugins.
+pub fn spawn(events: &EventBus) -> anyhow::Result<()> {
+    let sinks = NotificationSink::from_env()?;
+    if sinks.is_empty() {
+        return Ok(());
+    }
+    let mut receiver = events.subscribe();
+    let client = reqwest::Client::new();
+    tokio::spawn(async move {
+        loop {
+            let event = match receiver.recv().await {
+                Ok(event) => event,
+                Err(RecvError::Lagged(missed)) => {
+                    tracing::warn!("notifications skipped {} events", missed);
+                    continue;
+                }
+                Err(RecvError::Closed) => return,
+            };
+            let event_type = event.event.event_type();
+            let Some(text) = format(&event) else {
+                continue;
+            };
+            for sink in sinks.iter().filter(|sink| sink.wants(&event_type)) {
+                if let Err(err) = sink.post(&client, &text).await {
+                    tracing::warn!("failed to post {} notification: {}", event_type, err);
+                }
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filters_and_formats_events() {
+        let sinks: Vec<NotificationSink> = serde_json::from_value(json!([
+            {"kind": "slack", "webhook_url": "https://hooks.slack.test/x"},
+            {"kind": "discord", "bot_token": "t", "channel": "1", "events": ["disk_full"]}
+        ]))
+        .unwrap();
+        let event = PluginEvent {
+            plugin_id: "llmserver-rs".to_string(),
+            timestamp: chrono::Utc::now(),
+            event: PluginEventKind::ModelDownloaded {
+                model_id: "org/model".to_string(),
+                filename: "m.gguf".to_string(),
+                saved_path: "/models/m.gguf".to_string(),
+                bytes: 42,
+                node: None,
+            },
+        };
+        let event_type = event.event.event_type();
+        assert!(sinks[0].wants(&event_type));
+        assert!(!sinks[1].wants(&event_type));
+        assert_eq!(
+            format(&event).unwrap(),
+            "[goosed/llmserver-rs] downloaded org/model/m.gguf (42 bytes)"
+        );
+    }
+}
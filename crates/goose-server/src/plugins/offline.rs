@@ -0,0 +1,104 @@
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::PluginError;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema)]
+pub struct OfflineStatus {
+    pub enabled: bool,
+}
+
+/// Server-wide switch for air-gapped use. While enabled, plugins refuse
+/// anything that reaches out to model hosts, answer listings from cached
+/// metadata and only launch models and binaries already on disk. Cluster
+/// members and SSH nodes are on the local network and stay reachable.
+///
+/// Starts from `GOOSE_OFFLINE_MODE` and can be toggled at runtime.
+#[derive(Clone)]
+pub struct OfflineMode {
+    enabled: Arc<AtomicBool>,
+}
+
+impl Default for OfflineMode {
+    fn default() -> Self {
+        let enabled = std::env::var("GOOSE_OFFLINE_MODE")
+            .map(|value| matches!(value.trim(), "1" | "true" | "yes" | "on"))
+            .unwrap_or(false);
+        Self {
+            enabled: Arc::new(AtomicBool::new(enabled)),
+        }
+    }
+}
+
+impl OfflineMode {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn set(&self, enabled: bool) {
+        let previous = self.enabled.swap(enabled, Ordering::Relaxed);
+        if previous != enabled {
+            tracing::info!(
+                "offline mode {}",
+                if enabled { "enabled" } else { "disabled" }
+            );
+        }
+    }
+
+    pub fn status(&self) -> OfflineStatus {
+        OfflineStatus {
+            enabled: self.is_enabled(),
+        }
+    }
+
+    /// Fails with [`PluginError::Offline`] if `operation` would need the network.
+    pub fn ensure_online(&self, operation: &str) -> Result<(), PluginError> {
+        if self.is_enabled() {
+            return Err(PluginError::Offline(format!(
+                "{} needs network access",
+                operation
+            )));
+        }
+        Ok(())
+    }
+
+    /// Fails if offline and `path` is not present locally. Bare command
+    /// names are left to the `PATH` lookup.
+    pub fn ensure_local(&self, path: &str) -> Result<(), PluginError> {
+        let local = Path::new(path);
+        if !self.is_enabled() || local.components().count() <= 1 || local.exists() {
+            return Ok(());
+        }
+        Err(PluginError::Offline(format!(
+            "{} is not available locally",
+            path
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refuses_network_and_missing_files_only_while_offline() {
+        let mode = OfflineMode {
+            enabled: Arc::default(),
+        };
+        assert!(mode.ensure_online("model downloads").is_ok());
+        assert!(mode.ensure_local("/missing/model.gguf").is_ok());
+
+        mode.set(true);
+        assert!(matches!(
+            mode.ensure_online("model downloads"),
+            Err(PluginError::Offline(_))
+        ));
+        assert!(mode.ensure_local("/missing/model.gguf").is_err());
+        assert!(mode.ensure_local("llmserver").is_ok());
+        assert!(mode.ensure_local(env!("CARGO_MANIFEST_DIR")).is_ok());
+    }
+}
@@ -0,0 +1,424 @@
+//! GPU layer offload tuning for llama.cpp-style runtimes. The number of layers
+//! to place on the GPU is estimated from free VRAM and the layer sizes in the
+//! model's GGUF header.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+use utoipa::ToSchema;
+
+use super::limits::LaunchRequirements;
+
+const GGUF_MAGIC: &[u8; 4] = b"GGUF";
+
+/// Context size assumed when neither the arguments nor the model limit it.
+const DEFAULT_CONTEXT: u64 = 4096;
+
+/// VRAM kept free for the runtime's scratch buffers and other processes.
+const MIN_VRAM_RESERVE: u64 = 512 * 1024 * 1024;
+
+const GPU_LAYER_FLAGS: &[&str] = &["-ngl", "--n-gpu-layers", "--gpu-layers", "--n_gpu_layers"];
+
+/// Offload settings chosen for a launch.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+pub struct GpuOffload {
+    /// Value passed as `--n-gpu-layers`.
+    pub n_gpu_layers: u64,
+    /// Repeating layers in the model, not counting the output layer.
+    pub total_layers: u64,
+    /// Whether the KV cache stays on the GPU; otherwise `--no-kv-offload`
+    /// is passed to make room for more weights.
+    pub kv_offload: bool,
+    pub vram_bytes: u64,
+}
+
+impl GpuOffload {
+    pub fn args(&self) -> Vec<String> {
+        let mut args = vec!["--n-gpu-layers".to_string(), self.n_gpu_layers.to_string()];
+        if !self.kv_offload {
+            args.push("--no-kv-offload".to_string());
+        }
+        args
+    }
+}
+
+/// Shape of a model as far as offloading is concerned.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ModelLayout {
+    pub block_count: u64,
+    pub embedding_length: u64,
+    pub head_count: u64,
+    pub head_count_kv: u64,
+    pub context_length: Option<u64>,
+}
+
+/// Whether offload tuning applies: a GGUF model, a llama.cpp-style binary and
+/// no layer count chosen by the user.
+pub fn applies(binary: &str, model_path: &str, args: &[String]) -> bool {
+    let binary = Path::new(binary)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    binary.contains("llama")
+        && model_path.to_ascii_lowercase().ends_with(".gguf")
+        && !args.iter().any(|arg| {
+            let flag = arg.split_once('=').map_or(arg.as_str(), |(flag, _)| flag);
+            GPU_LAYER_FLAGS.contains(&flag)
+        })
+}
+
+/// Free VRAM in bytes: `GOOSE_PLUGIN_LLM_VRAM_BYTES` if set, else the sum
+/// reported by `nvidia-smi`, else two thirds of unified memory on macOS.
+pub async fn detect_vram() -> Option<u64> {
+    if let Ok(value) = std::env::var("GOOSE_PLUGIN_LLM_VRAM_BYTES") {
+        return value.trim().parse().ok();
+    }
+    if let Ok(output) = Command::new("nvidia-smi")
+        .args(["--query-gpu=memory.free", "--format=csv,noheader,nounits"])
+        .output()
+        .await
+    {
+        if output.status.success() {
+            let mib: u64 = String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .filter_map(|line| line.trim().parse::<u64>().ok())
+                .sum();
+            if mib > 0 {
+                return Some(mib * 1024 * 1024);
+            }
+        }
+    }
+    if cfg!(target_os = "macos") {
+        let output = Command::new("sysctl")
+            .args(["-n", "hw.memsize"])
+            .output()
+            .await
+            .ok()?;
+        let total: u64 = String::from_utf8_lossy(&output.stdout)
+            .trim()
+            .parse()
+            .ok()?;
+        // Metal's default working set limit.
+        return Some(total / 3 * 2);
+    }
+    None
+}
+
+/// Picks the layer count for `layout`: everything including the KV cache
+/// when it fits, otherwise as many weight layers as fit with the KV cache
+/// kept in system memory.
+pub fn recommend(
+    layout: &ModelLayout,
+    model_bytes: u64,
+    context: Option<u64>,
+    vram_bytes: u64,
+) -> Option<GpuOffload> {
+    if layout.block_count == 0 || layout.head_count == 0 {
+        return None;
+    }
+    let layers = layout.block_count;
+    // Embeddings and the output head are roughly one more layer's worth.
+    let layer_bytes = model_bytes / (layers + 1);
+    let context = context
+        .or(layout
+            .context_length
+            .map(|limit| limit.min(DEFAULT_CONTEXT)))
+        .unwrap_or(DEFAULT_CONTEXT);
+    let kv_width = layout.embedding_length * layout.head_count_kv / layout.head_count;
+    // K and V, f16 each.
+    let kv_layer_bytes = 2 * context * kv_width * 2;
+
+    let reserve = MIN_VRAM_RESERVE.max(vram_bytes / 10);
+    let budget = vram_bytes.saturating_sub(reserve);
+    if (layers + 1) * layer_bytes + layers * kv_layer_bytes <= budget {
+        return Some(GpuOffload {
+            // One past the repeating layers also offloads the output layer.
+            n_gpu_layers: layers + 1,
+            total_layers: layers,
+            kv_offload: true,
+            vram_bytes,
+        });
+    }
+    Some(GpuOffload {
+        n_gpu_layers: (budget / layer_bytes.max(1)).min(layers),
+        total_layers: layers,
+        kv_offload: false,
+        vram_bytes,
+    })
+}
+
+/// Recommends offload settings for a launch, or `None` when the model or
+/// the GPU cannot be inspected.
+pub async fn tune(model_path: &str, args: &[String]) -> Option<GpuOffload> {
+    let requirements = LaunchRequirements::from_args(args, model_path);
+    let path = model_path.to_string();
+    let layout = tokio::task::spawn_blocking(move || read_layout(Path::new(&path)))
+        .await
+        .ok()??;
+    let vram = detect_vram().await?;
+    recommend(
+        &layout,
+        requirements.model_bytes?,
+        requirements.context_size,
+        vram,
+    )
+}
+
+/// What a GGUF file holds, as far as pairing adapters with models is
+/// concerned.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GgufIdentity {
+    /// `general.architecture`, such as `llama`.
+    pub architecture: String,
+    /// `general.type`: `model`, `adapter` or unset in older files.
+    pub kind: Option<String>,
+}
+
+impl GgufIdentity {
+    pub fn is_adapter(&self) -> bool {
+        self.kind.as_deref() == Some("adapter")
+    }
+}
+
+/// Reads the architecture and type from a GGUF file header.
+pub fn read_identity(path: &Path) -> Option<GgufIdentity> {
+    let header = read_header(path)?;
+    Some(GgufIdentity {
+        architecture: header.strings.get("general.architecture")?.clone(),
+        kind: header.strings.get("general.type").cloned(),
+    })
+}
+
+/// `general.file_type` of a GGUF file: how most of its weights are stored,
+/// as a llama.cpp `llama_ftype`.
+pub fn read_file_type(path: &Path) -> Option<u64> {
+    read_header(path)?
+        .numbers
+        .into_iter()
+        .find(|(key, _)| key == "general.file_type")
+        .map(|(_, value)| value)
+}
+
+/// Reads the offload-relevant metadata from a GGUF file header.
+pub fn read_layout(path: &Path) -> Option<ModelLayout> {
+    let GgufHeader { strings, numbers } = read_header(path)?;
+    let architecture = strings.get("general.architecture")?;
+    let field = |name: &str| {
+        let key = format!("{}.{}", architecture, name);
+        numbers
+            .iter()
+            .find(|(candidate, _)| *candidate == key)
+            .map(|(_, value)| *value)
+    };
+    let head_count = field("attention.head_count")?;
+    Some(ModelLayout {
+        block_count: field("block_count")?,
+        embedding_length: field("embedding_length")?,
+        head_count,
+        head_count_kv: field("attention.head_count_kv").unwrap_or(head_count),
+        context_length: field("context_length"),
+    })
+}
+
+/// The string and integer metadata of a GGUF file.
+struct GgufHeader {
+    strings: HashMap<String, String>,
+    numbers: Vec<(String, u64)>,
+}
+
+fn read_header(path: &Path) -> Option<GgufHeader> {
+    let mut reader = GgufReader(BufReader::new(File::open(path).ok()?));
+    let mut magic = [0u8; 4];
+    reader.0.read_exact(&mut magic).ok()?;
+    if &magic != GGUF_MAGIC || reader.u32()? < 2 {
+        return None;
+    }
+    let _tensor_count = reader.u64()?;
+    let kv_count = reader.u64()?;
+
+    let mut header = GgufHeader {
+        strings: HashMap::new(),
+        numbers: Vec::new(),
+    };
+    for _ in 0..kv_count {
+        let key = reader.string()?;
+        match reader.value()? {
+            GgufValue::String(value) => {
+                header.strings.insert(key, value);
+            }
+            GgufValue::Number(value) => header.numbers.push((key, value)),
+            GgufValue::Other => {}
+        }
+    }
+    Some(header)
+}
+
+enum GgufValue {
+    Number(u64),
+    String(String),
+    Other,
+}
+
+struct GgufReader<R>(R);
+
+impl<R: Read> GgufReader<R> {
+    fn bytes<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let mut buf = [0u8; N];
+        self.0.read_exact(&mut buf).ok()?;
+        Some(buf)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.bytes().map(u32::from_le_bytes)
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        self.bytes().map(u64::from_le_bytes)
+    }
+
+    fn string(&mut self) -> Option<String> {
+        let len = self.u64()?;
+        let mut buf = Vec::new();
+        (&mut self.0).take(len).read_to_end(&mut buf).ok()?;
+        (buf.len() as u64 == len).then(|| String::from_utf8_lossy(&buf).into_owned())
+    }
+
+    fn value(&mut self) -> Option<GgufValue> {
+        let kind = self.u32()?;
+        self.value_of(kind)
+    }
+
+    fn value_of(&mut self, kind: u32) -> Option<GgufValue> {
+        Some(match kind {
+            0 | 1 | 7 => {
+                self.bytes::<1>()?;
+                GgufValue::Other
+            }
+            2 | 3 => {
+                self.bytes::<2>()?;
+                GgufValue::Other
+            }
+            4 => GgufValue::Number(self.u32()?.into()),
+            5 => GgufValue::Number(u32::from_le_bytes(self.bytes()?) as i32 as u64),
+            6 => {
+                self.bytes::<4>()?;
+                GgufValue::Other
+            }
+            8 => GgufValue::String(self.string()?),
+            9 => {
+                let item = self.u32()?;
+                let len = self.u64()?;
+                for _ in 0..len {
+                    self.value_of(item)?;
+                }
+                GgufValue::Other
+            }
+            10 => GgufValue::Number(self.u64()?),
+            11 | 12 => {
+                self.bytes::<8>()?;
+                GgufValue::Other
+            }
+            _ => return None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GIB: u64 = 1024 * 1024 * 1024;
+
+    fn layout() -> ModelLayout {
+        ModelLayout {
+            block_count: 32,
+            embedding_length: 4096,
+            head_count: 32,
+            head_count_kv: 8,
+            context_length: Some(32768),
+        }
+    }
+
+    #[test]
+    fn offloads_everything_that_fits() {
+        let full = recommend(&layout(), 4 * GIB, None, 24 * GIB).unwrap();
+        assert_eq!(full.n_gpu_layers, 33);
+        assert!(full.kv_offload);
+
+        let partial = recommend(&layout(), 8 * GIB, Some(8192), 6 * GIB).unwrap();
+        assert!(partial.n_gpu_layers > 0 && partial.n_gpu_layers < 32);
+        assert!(!partial.kv_offload);
+        assert_eq!(
+            partial.args()[..2],
+            ["--n-gpu-layers", &partial.n_gpu_layers.to_string()]
+        );
+    }
+
+    #[test]
+    fn reads_layout_from_gguf_header() {
+        fn string(out: &mut Vec<u8>, value: &str) {
+            out.extend((value.len() as u64).to_le_bytes());
+            out.extend(value.as_bytes());
+        }
+        fn number(out: &mut Vec<u8>, key: &str, value: u32) {
+            string(out, key);
+            out.extend(4u32.to_le_bytes());
+            out.extend(value.to_le_bytes());
+        }
+        let mut header = b"GGUF".to_vec();
+        header.extend(3u32.to_le_bytes());
+        header.extend(0u64.to_le_bytes());
+        header.extend(5u64.to_le_bytes());
+        string(&mut header, "general.architecture");
+        header.extend(8u32.to_le_bytes());
+        string(&mut header, "llama");
+        string(&mut header, "tokenizer.ggml.tokens");
+        header.extend(9u32.to_le_bytes());
+        header.extend(8u32.to_le_bytes());
+        header.extend(2u64.to_le_bytes());
+        string(&mut header, "a");
+        string(&mut header, "b");
+        number(&mut header, "llama.block_count", 32);
+        number(&mut header, "llama.embedding_length", 4096);
+        number(&mut header, "llama.attention.head_count", 32);
+
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), header).unwrap();
+        assert_eq!(
+            read_layout(file.path()),
+            Some(ModelLayout {
+                block_count: 32,
+                embedding_length: 4096,
+                head_count: 32,
+                head_count_kv: 32,
+                context_length: None,
+            })
+        );
+    }
+
+    #[test]
+    fn applies_only_to_llama_cpp_gguf_launches_without_a_layer_count() {
+        let args = |list: &[&str]| list.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        assert!(applies(
+            "/opt/llama-server",
+            "m.gguf",
+            &args(&["-c", "4096"])
+        ));
+        assert!(!applies(
+            "/opt/llama-server",
+            "m.gguf",
+            &args(&["-ngl", "10"])
+        ));
+        assert!(!applies(
+            "/opt/llama-server",
+            "m.gguf",
+            &args(&["--n-gpu-layers=10"])
+        ));
+        assert!(!applies("/opt/llmserver", "m.gguf", &[]));
+        assert!(!applies("/opt/llama-server", "m.safetensors", &[]));
+    }
+}
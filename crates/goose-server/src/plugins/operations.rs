@@ -0,0 +1,77 @@
+//! Model-management operations plugins run through
+//! [`ServerPlugin::invoke`] rather than methods of their own. Payloads and
+//! results are the JSON forms of the request and response types of the
+//! matching routes; operations without a request take `null`.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use tokio_util::sync::CancellationToken;
+
+use super::progress::DownloadProgress;
+use super::{PluginError, ServerPlugin};
+
+/// Space used in the model directory of each task type, against its quota.
+pub const MODEL_USAGE: &str = "model-usage";
+/// Searches the model hub for models the plugin can serve.
+pub const SEARCH_MODELS: &str = "search-models";
+/// Lists the files of a model repository, to pick one to download.
+pub const LIST_MODEL_FILES: &str = "list-model-files";
+/// Checks a hub access token and reports whose it is and what it may do,
+/// without using it for anything.
+pub const VALIDATE_TOKEN: &str = "validate-token";
+/// Deletes one downloaded model file, unless a running service uses it.
+pub const DELETE_MODEL: &str = "delete-model";
+/// Pins a downloaded model so it is never removed automatically, or unpins
+/// it.
+pub const PIN_MODEL: &str = "pin-model";
+/// Lists the LoRA adapters downloaded for a model and whether they fit it.
+pub const LIST_ADAPTERS: &str = "list-adapters";
+/// Converts a downloaded model to GGUF.
+pub const CONVERT_MODEL: &str = "convert-model";
+/// Quantizes a downloaded model.
+pub const QUANTIZE_MODEL: &str = "quantize-model";
+/// Takes a model file that is already on disk under management.
+pub const IMPORT_MODEL: &str = "import-model";
+/// Downloads every model in the plugin's lock file again, at its locked
+/// commit.
+pub const RESTORE_MODELS: &str = "restore-models";
+/// Damaged files the last integrity scan of the model directories found.
+pub const MODEL_HEALTH: &str = "model-health";
+/// Resumes or repeats the downloads of the damaged files the last scan
+/// found, then scans again.
+pub const REPAIR_MODELS: &str = "repair-models";
+
+/// What an operation receives besides its payload when it runs in process.
+#[derive(Debug, Clone, Default)]
+pub struct InvokeContext {
+    /// Stops the operation, e.g. when its job is cancelled.
+    pub cancel: CancellationToken,
+    /// Receives the bytes the operation has written so far.
+    pub progress: DownloadProgress,
+}
+
+/// Runs `operation` with `request` as its payload and reads the result.
+pub async fn invoke<R: DeserializeOwned>(
+    plugin: &dyn ServerPlugin,
+    operation: &str,
+    request: &impl Serialize,
+    context: InvokeContext,
+) -> Result<R, PluginError> {
+    let payload =
+        serde_json::to_value(request).map_err(|err| PluginError::Internal(err.to_string()))?;
+    let result = plugin.invoke(operation, payload, context).await?;
+    serde_json::from_value(result)
+        .map_err(|err| PluginError::Internal(format!("invalid {} result: {}", operation, err)))
+}
+
+/// Reads the payload of `operation` as its request, for plugins running it.
+pub fn request<T: DeserializeOwned>(operation: &str, payload: Value) -> Result<T, PluginError> {
+    serde_json::from_value(payload)
+        .map_err(|err| PluginError::InvalidRequest(format!("{}: {}", operation, err)))
+}
+
+/// The result of an operation as [`ServerPlugin::invoke`] returns it.
+pub fn respond<T: Serialize>(result: Result<T, PluginError>) -> Result<Value, PluginError> {
+    serde_json::to_value(result?).map_err(|err| PluginError::Internal(err.to_string()))
+}
@@ -0,0 +1,266 @@
+//! Model sync between goosed instances. A goosed shares the models it has
+//! downloaded with its peers, which pull missing models from it over HTTP
+//! instead of from the hub, so a lab of machines downloads each model from
+//! the internet once. Shared files carry the SHA-256 they were recorded
+//! with, and pulled files are checked against it.
+
+use std::io::SeekFrom;
+use std::ops::Range;
+use std::path::PathBuf;
+
+use bytes::Bytes;
+use futures::Stream;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use utoipa::ToSchema;
+
+use super::manifest::ModelRecord;
+use super::redact::resolve_secret_ref;
+use super::sources::ObjectLocation;
+use super::PluginError;
+
+/// Header a peer sends the SHA-256 of a shared file in.
+pub const SHA256_HEADER: &str = "x-goose-sha256";
+
+/// Bytes read from a shared file at a time.
+const SHARE_CHUNK_BYTES: u64 = 256 * 1024;
+
+/// Another goosed models are pulled from.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, schemars::JsonSchema, PartialEq, Eq)]
+pub struct PeerSource {
+    /// Base URL of the peer's API, such as `http://gpu-01.lab:3000`.
+    pub url: String,
+    /// The peer's secret key, or a credential it granted `model_share`.
+    /// Accepts `secret://NAME` references into goose's secret store.
+    pub secret_key: String,
+    /// Plugin whose models are pulled; defaults to the plugin pulling them.
+    #[serde(default)]
+    pub plugin_id: Option<String>,
+}
+
+impl PeerSource {
+    /// Where the peer shares `filename` of `model_id` at `revision`.
+    pub fn locate(
+        &self,
+        plugin_id: &str,
+        model_id: &str,
+        revision: &str,
+        filename: &str,
+    ) -> Result<ObjectLocation, PluginError> {
+        let invalid = |reason: String| {
+            PluginError::InvalidRequest(format!("invalid peer URL {}: {}", self.url, reason))
+        };
+        let mut url = Url::parse(&self.url).map_err(|err| invalid(err.to_string()))?;
+        if !matches!(url.scheme(), "http" | "https") || !url.has_host() {
+            return Err(invalid("an http(s) URL is required".to_string()));
+        }
+        url.path_segments_mut()
+            .map_err(|_| invalid("cannot be a base".to_string()))?
+            .pop_if_empty()
+            .extend([
+                "plugins",
+                self.plugin_id.as_deref().unwrap_or(plugin_id),
+                "models",
+                "share",
+            ]);
+        url.query_pairs_mut()
+            .append_pair("model_id", model_id)
+            .append_pair("filename", filename)
+            .append_pair("revision", revision);
+        Ok(ObjectLocation::peer(
+            url,
+            resolve_secret_ref(&self.secret_key)?,
+        ))
+    }
+}
+
+/// `GOOSE_PLUGIN_LLM_PEERS`, a comma separated list of peer URLs, all
+/// reached with `GOOSE_PLUGIN_LLM_PEER_SECRET_KEY`.
+pub fn peers_from_env() -> Vec<PeerSource> {
+    let (Ok(urls), Ok(secret_key)) = (
+        std::env::var("GOOSE_PLUGIN_LLM_PEERS"),
+        std::env::var("GOOSE_PLUGIN_LLM_PEER_SECRET_KEY"),
+    ) else {
+        return Vec::new();
+    };
+    urls.split(',')
+        .map(str::trim)
+        .filter(|url| !url.is_empty())
+        .map(|url| PeerSource {
+            url: url.to_string(),
+            secret_key: secret_key.trim().to_string(),
+            plugin_id: None,
+        })
+        .collect()
+}
+
+/// The record of the file shared for a request: the latest download of
+/// `filename` of `model_id`, at `revision` when one is named. Encrypted
+/// files and files without a recorded checksum are not shared.
+pub fn find_shared<'a>(
+    records: &'a [ModelRecord],
+    model_id: &str,
+    filename: &str,
+    revision: Option<&str>,
+) -> Option<&'a ModelRecord> {
+    records
+        .iter()
+        .filter(|record| {
+            record.model_id == model_id
+                && record.filename == filename
+                && !record.encrypted
+                && record.sha256.is_some()
+                && revision.is_none_or(|revision| {
+                    record.revision == revision || record.commit.as_deref() == Some(revision)
+                })
+        })
+        .max_by_key(|record| record.downloaded_at)
+}
+
+/// A `Range` header asked for bytes past the end of the file.
+#[derive(Debug, PartialEq, Eq)]
+pub struct RangeNotSatisfiable;
+
+/// The bytes of a `len` byte file a `Range` header asks for. Headers that
+/// cannot be served as one range, such as several ranges, are ignored and
+/// the whole file is served.
+pub fn byte_range(
+    header: Option<&str>,
+    len: u64,
+) -> Result<Option<Range<u64>>, RangeNotSatisfiable> {
+    let Some(spec) = header.and_then(|header| header.trim().strip_prefix("bytes=")) else {
+        return Ok(None);
+    };
+    let Some((start, end)) = spec.split_once('-') else {
+        return Ok(None);
+    };
+    let (start, end) = (start.trim(), end.trim());
+    let range = match (start.parse::<u64>(), end.parse::<u64>()) {
+        (Ok(start), Ok(end)) if start <= end => start..(end + 1).min(len),
+        (Ok(start), Err(_)) if end.is_empty() => start..len,
+        (Err(_), Ok(suffix)) if start.is_empty() => len.saturating_sub(suffix)..len,
+        _ => return Ok(None),
+    };
+    if range.start >= len {
+        return Err(RangeNotSatisfiable);
+    }
+    Ok(Some(range))
+}
+
+/// The bytes `range` of the file at `path`, read as they are sent.
+pub fn read_range(path: PathBuf, range: Range<u64>) -> impl Stream<Item = std::io::Result<Bytes>> {
+    futures::stream::try_unfold(
+        (path, None::<fs::File>, range),
+        |(path, file, range)| async move {
+            if range.is_empty() {
+                return Ok(None);
+            }
+            let mut file = match file {
+                Some(file) => file,
+                None => {
+                    let mut file = fs::File::open(&path).await?;
+                    file.seek(SeekFrom::Start(range.start)).await?;
+                    file
+                }
+            };
+            let mut chunk = vec![0u8; (range.end - range.start).min(SHARE_CHUNK_BYTES) as usize];
+            let read = file.read(&mut chunk).await?;
+            if read == 0 {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    format!("{} shrank while being shared", path.display()),
+                ));
+            }
+            chunk.truncate(read);
+            let rest = range.start + read as u64..range.end;
+            Ok(Some((Bytes::from(chunk), (path, Some(file), rest))))
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use futures::TryStreamExt;
+
+    use super::*;
+    use crate::plugins::PluginTaskType;
+
+    fn record(revision: &str, commit: Option<&str>, day: u32) -> ModelRecord {
+        ModelRecord {
+            model_id: "org/model".to_string(),
+            filename: "model.gguf".to_string(),
+            revision: revision.to_string(),
+            commit: commit.map(str::to_string),
+            task_type: PluginTaskType::new("text").unwrap(),
+            saved_path: format!("/models/{}/model.gguf", day),
+            bytes: 4,
+            sha256: Some("ab".repeat(32)),
+            downloaded_at: Utc.with_ymd_and_hms(2026, 1, day, 0, 0, 0).unwrap(),
+            last_checked: None,
+            update: None,
+            last_used: None,
+            encrypted: false,
+            pinned: false,
+            source_url: None,
+            provenance: None,
+        }
+    }
+
+    #[test]
+    fn shares_the_latest_matching_download() {
+        let mut encrypted = record("main", Some("c3"), 3);
+        encrypted.encrypted = true;
+        let records = [
+            record("main", Some("c1"), 1),
+            record("v2", Some("c2"), 2),
+            encrypted,
+        ];
+        let shared = |revision| {
+            find_shared(&records, "org/model", "model.gguf", revision)
+                .map(|record| record.commit.as_deref().unwrap())
+        };
+        assert_eq!(shared(None), Some("c2"));
+        assert_eq!(shared(Some("main")), Some("c1"));
+        assert_eq!(shared(Some("c2")), Some("c2"));
+        assert_eq!(shared(Some("c3")), None);
+        assert!(find_shared(&records, "org/model", "other.gguf", None).is_none());
+    }
+
+    #[test]
+    fn parses_single_byte_ranges() {
+        assert_eq!(byte_range(None, 10), Ok(None));
+        assert_eq!(byte_range(Some("bytes=2-"), 10), Ok(Some(2..10)));
+        assert_eq!(byte_range(Some("bytes=2-4"), 10), Ok(Some(2..5)));
+        assert_eq!(byte_range(Some("bytes=2-40"), 10), Ok(Some(2..10)));
+        assert_eq!(byte_range(Some("bytes=-3"), 10), Ok(Some(7..10)));
+        assert_eq!(byte_range(Some("bytes=0-1,4-5"), 10), Ok(None));
+        assert_eq!(byte_range(Some("bytes=10-"), 10), Err(RangeNotSatisfiable));
+    }
+
+    #[tokio::test]
+    async fn reads_ranges_of_shared_files() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), b"0123456789").unwrap();
+        let chunks: Vec<Bytes> = read_range(file.path().to_path_buf(), 3..7)
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(chunks.concat(), b"3456");
+
+        let locate = PeerSource {
+            url: "http://gpu-01.lab:3000/".to_string(),
+            secret_key: "key".to_string(),
+            plugin_id: None,
+        }
+        .locate("llmserver-rs", "org/model", "main", "sub/model.gguf")
+        .unwrap();
+        assert_eq!(
+            locate.url.as_str(),
+            "http://gpu-01.lab:3000/plugins/llmserver-rs/models/share\
+             ?model_id=org%2Fmodel&filename=sub%2Fmodel.gguf&revision=main"
+        );
+    }
+}
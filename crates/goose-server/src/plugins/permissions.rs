@@ -0,0 +1,151 @@
+//! Credentials that may use only some capabilities of some plugins, for
+//! clients that should not hold the server's secret key. They are read at
+//! startup from a TOML file:
+//!
+//! ```toml
+//! [[credentials]]
+//! name = "dashboard"
+//! token = "d4c1..."
+//!
+//! [credentials.grants]
+//! "llmserver-rs" = ["service_start", "service_stop", "service_status"]
+//! "*" = ["health_check"]
+//! ```
+//!
+//! A grant for `*` applies to every plugin. Scoped credentials only reach
+//! the plugin and task routes; everything else needs the secret key.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use goose::config::paths::Paths;
+use serde::Deserialize;
+use tokio::fs;
+
+use super::{PluginCapability, PluginError};
+
+/// Grant key that applies to every plugin.
+pub const ANY_PLUGIN: &str = "*";
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PluginCredential {
+    pub name: String,
+    pub token: String,
+    /// Capabilities by plugin id, or by [`ANY_PLUGIN`].
+    #[serde(default)]
+    pub grants: HashMap<String, Vec<PluginCapability>>,
+}
+
+impl PluginCredential {
+    pub fn allows(&self, plugin_id: &str, capability: &PluginCapability) -> bool {
+        [plugin_id, ANY_PLUGIN].iter().any(|key| {
+            self.grants
+                .get(*key)
+                .is_some_and(|capabilities| capabilities.contains(capability))
+        })
+    }
+
+    /// Whether the capability is granted for every plugin, as server-wide
+    /// routes such as the metrics snapshot require.
+    pub fn allows_all(&self, capability: &PluginCapability) -> bool {
+        self.allows(ANY_PLUGIN, capability)
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PluginCredentials {
+    #[serde(default)]
+    credentials: Vec<PluginCredential>,
+}
+
+impl PluginCredentials {
+    /// `GOOSE_PLUGIN_CREDENTIALS_PATH`, or `plugin_credentials.toml` in
+    /// goose's config directory.
+    pub fn default_path() -> PathBuf {
+        std::env::var_os("GOOSE_PLUGIN_CREDENTIALS_PATH")
+            .filter(|path| !path.is_empty())
+            .map(PathBuf::from)
+            .unwrap_or_else(|| Paths::config_dir().join("plugin_credentials.toml"))
+    }
+
+    /// Reads the credentials at `path`; there are none when it does not exist.
+    pub async fn load(path: &Path) -> Result<Self, PluginError> {
+        let text = match fs::read_to_string(path).await {
+            Ok(text) => text,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => return Err(err.into()),
+        };
+        let credentials: Self = toml::from_str(&text)
+            .map_err(|err| PluginError::Internal(format!("invalid {}: {}", path.display(), err)))?;
+        let mut tokens = HashSet::new();
+        for credential in &credentials.credentials {
+            if credential.token.is_empty() || !tokens.insert(credential.token.as_str()) {
+                return Err(PluginError::Internal(format!(
+                    "invalid {}: credential {} needs a token of its own",
+                    path.display(),
+                    credential.name
+                )));
+            }
+        }
+        Ok(credentials)
+    }
+
+    pub fn find(&self, token: &str) -> Option<&PluginCredential> {
+        self.credentials
+            .iter()
+            .find(|credential| credential.token == token)
+    }
+
+    pub fn tokens(&self) -> HashSet<String> {
+        self.credentials
+            .iter()
+            .map(|credential| credential.token.clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn grants_capabilities_per_plugin() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("plugin_credentials.toml");
+        assert!(PluginCredentials::load(&path)
+            .await
+            .unwrap()
+            .tokens()
+            .is_empty());
+
+        std::fs::write(
+            &path,
+            r#"
+[[credentials]]
+name = "dashboard"
+token = "dashboard-token"
+
+[credentials.grants]
+"llmserver-rs" = ["service_start", "service_stop"]
+"*" = ["health_check"]
+"#,
+        )
+        .unwrap();
+        let credentials = PluginCredentials::load(&path).await.unwrap();
+        assert!(credentials.find("secret").is_none());
+        let dashboard = credentials.find("dashboard-token").unwrap();
+        assert!(dashboard.allows("llmserver-rs", &PluginCapability::ServiceStart));
+        assert!(!dashboard.allows("llmserver-rs", &PluginCapability::ModelDownload));
+        assert!(!dashboard.allows("piper", &PluginCapability::ServiceStart));
+        assert!(dashboard.allows("piper", &PluginCapability::HealthCheck));
+        assert!(dashboard.allows_all(&PluginCapability::HealthCheck));
+        assert!(!dashboard.allows_all(&PluginCapability::ServiceStop));
+
+        std::fs::write(
+            &path,
+            "[[credentials]]\nname = \"a\"\ntoken = \"t\"\n[[credentials]]\nname = \"b\"\ntoken = \"t\"\n",
+        )
+        .unwrap();
+        assert!(PluginCredentials::load(&path).await.is_err());
+    }
+}
@@ -0,0 +1,102 @@
+//! Plugin state kept across server restarts. Each plugin's state is a JSON
+//! file in a state directory, written from [`ServerPlugin::save_state`] as
+//! the server stops and handed to [`ServerPlugin::restore_state`] when it
+//! starts again.
+//!
+//! [`ServerPlugin::save_state`]: super::ServerPlugin::save_state
+//! [`ServerPlugin::restore_state`]: super::ServerPlugin::restore_state
+
+use std::path::PathBuf;
+
+use goose::config::paths::Paths;
+use serde_json::Value;
+use tokio::fs;
+
+use super::PluginError;
+
+#[derive(Debug, Clone)]
+pub struct PluginStateDir {
+    dir: PathBuf,
+}
+
+impl PluginStateDir {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// `GOOSE_PLUGIN_STATE_DIR`, or `plugins` in goose's state directory.
+    pub fn default_dir() -> PathBuf {
+        std::env::var_os("GOOSE_PLUGIN_STATE_DIR")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .unwrap_or_else(|| Paths::in_state_dir("plugins"))
+    }
+
+    /// Plugin ids may contain characters that are not safe in file names.
+    fn path(&self, plugin_id: &str) -> PathBuf {
+        let name: String = plugin_id
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        self.dir.join(format!("{}.json", name))
+    }
+
+    /// The state last saved for the plugin, if any.
+    pub async fn load(&self, plugin_id: &str) -> Result<Option<Value>, PluginError> {
+        let path = self.path(plugin_id);
+        match fs::read(&path).await {
+            Ok(bytes) => serde_json::from_slice(&bytes).map(Some).map_err(|err| {
+                PluginError::Internal(format!("invalid {}: {}", path.display(), err))
+            }),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Writes the plugin's state atomically, or removes it when there is none.
+    pub async fn save(&self, plugin_id: &str, state: Option<&Value>) -> Result<(), PluginError> {
+        let path = self.path(plugin_id);
+        let Some(state) = state else {
+            return match fs::remove_file(&path).await {
+                Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+                _ => Ok(()),
+            };
+        };
+        let json = serde_json::to_vec_pretty(state)
+            .map_err(|err| PluginError::Internal(err.to_string()))?;
+        fs::create_dir_all(&self.dir).await?;
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, json).await?;
+        fs::rename(&tmp, &path).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn saves_and_removes_state() {
+        let dir = tempfile::tempdir().unwrap();
+        let states = PluginStateDir::new(dir.path().join("plugins"));
+        assert_eq!(states.load("llmserver-rs").await.unwrap(), None);
+
+        let state = json!({"services": [{"task_type": "text"}]});
+        states.save("llmserver-rs", Some(&state)).await.unwrap();
+        states.save("acme/tts", Some(&json!(1))).await.unwrap();
+        assert_eq!(states.load("llmserver-rs").await.unwrap(), Some(state));
+        assert!(dir.path().join("plugins").join("acme_tts.json").exists());
+
+        states.save("llmserver-rs", None).await.unwrap();
+        assert_eq!(states.load("llmserver-rs").await.unwrap(), None);
+        states.save("llmserver-rs", None).await.unwrap();
+    }
+}
@@ -0,0 +1,147 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Performance defaults for a class of machine, expressed as llama.cpp-style
+/// launch flags. Flags already present in a start request's arguments win.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+pub struct HardwareProfile {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub threads: Option<u32>,
+    #[serde(default)]
+    pub batch_size: Option<u32>,
+    #[serde(default)]
+    pub ubatch_size: Option<u32>,
+    /// Layers to offload to the GPU; when unset the count is tuned from the
+    /// detected VRAM.
+    #[serde(default)]
+    pub n_gpu_layers: Option<u64>,
+    #[serde(default)]
+    pub flash_attention: bool,
+}
+
+impl HardwareProfile {
+    /// Profiles that ship with goosed.
+    pub fn builtin() -> Vec<HardwareProfile> {
+        vec![
+            HardwareProfile {
+                id: "apple-m2-max-32gb".to_string(),
+                name: "Apple M2 Max, 32 GB".to_string(),
+                threads: Some(8),
+                batch_size: Some(512),
+                ubatch_size: Some(512),
+                // Unified memory: the whole model lives on the GPU.
+                n_gpu_layers: Some(999),
+                flash_attention: true,
+            },
+            HardwareProfile {
+                id: "rtx-4090".to_string(),
+                name: "NVIDIA RTX 4090, 24 GB".to_string(),
+                threads: Some(8),
+                batch_size: Some(2048),
+                ubatch_size: Some(512),
+                n_gpu_layers: None,
+                flash_attention: true,
+            },
+            HardwareProfile {
+                id: "cpu-only-server".to_string(),
+                name: "CPU-only server".to_string(),
+                threads: std::thread::available_parallelism()
+                    .ok()
+                    .map(|n| n.get() as u32),
+                batch_size: Some(512),
+                ubatch_size: Some(128),
+                n_gpu_layers: Some(0),
+                flash_attention: false,
+            },
+        ]
+    }
+
+    /// Built-in profiles plus those in `GOOSE_PLUGIN_LLM_PROFILES`, a JSON
+    /// array of profile objects. Custom profiles replace built-ins with the
+    /// same id.
+    pub fn from_env() -> anyhow::Result<Vec<HardwareProfile>> {
+        let mut profiles = Self::builtin();
+        let Ok(raw) = std::env::var("GOOSE_PLUGIN_LLM_PROFILES") else {
+            return Ok(profiles);
+        };
+        let custom: Vec<HardwareProfile> = serde_json::from_str(&raw)
+            .map_err(|err| anyhow::anyhow!("invalid GOOSE_PLUGIN_LLM_PROFILES: {}", err))?;
+        for profile in custom {
+            profiles.retain(|existing| existing.id != profile.id);
+            profiles.push(profile);
+        }
+        Ok(profiles)
+    }
+
+    /// Appends the profile's flags to `args`, skipping any the caller set.
+    pub fn apply(&self, args: &mut Vec<String>) {
+        let present = |flags: &[&str], args: &[String]| {
+            args.iter().any(|arg| {
+                let flag = arg.split_once('=').map_or(arg.as_str(), |(flag, _)| flag);
+                flags.contains(&flag)
+            })
+        };
+        let settings: [(&[&str], Option<String>); 4] = [
+            (&["-t", "--threads"], self.threads.map(|v| v.to_string())),
+            (
+                &["-b", "--batch-size"],
+                self.batch_size.map(|v| v.to_string()),
+            ),
+            (
+                &["-ub", "--ubatch-size"],
+                self.ubatch_size.map(|v| v.to_string()),
+            ),
+            (
+                &["-ngl", "--n-gpu-layers", "--gpu-layers"],
+                self.n_gpu_layers.map(|v| v.to_string()),
+            ),
+        ];
+        for (flags, value) in settings {
+            if let Some(value) = value {
+                if !present(flags, args) {
+                    args.push(flags[1].to_string());
+                    args.push(value);
+                }
+            }
+        }
+        if self.flash_attention && !present(&["-fa", "--flash-attn"], args) {
+            args.push("--flash-attn".to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fills_in_flags_the_request_left_out() {
+        let profile = HardwareProfile::builtin()
+            .into_iter()
+            .find(|profile| profile.id == "rtx-4090")
+            .unwrap();
+        let mut args = vec![
+            "-m".to_string(),
+            "m.gguf".to_string(),
+            "-t".to_string(),
+            "4".to_string(),
+        ];
+        profile.apply(&mut args);
+        assert_eq!(
+            args,
+            [
+                "-m",
+                "m.gguf",
+                "-t",
+                "4",
+                "--batch-size",
+                "2048",
+                "--ubatch-size",
+                "512",
+                "--flash-attn"
+            ]
+        );
+    }
+}
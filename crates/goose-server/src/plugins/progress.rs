@@ -0,0 +1,126 @@
+//! Progress of model downloads, reported by plugins as they write and
+//! streamed to clients while the download runs as a job.
+
+use std::sync::Arc;
+use std::time::Instant;
+
+use serde::Serialize;
+use tokio::sync::watch;
+use utoipa::ToSchema;
+
+#[derive(Debug, Clone, Serialize, ToSchema, PartialEq)]
+pub struct DownloadProgressUpdate {
+    pub bytes_written: u64,
+    /// Size announced by the host, when it announced one.
+    pub total_bytes: Option<u64>,
+    /// Average since the transfer started.
+    pub bytes_per_second: f64,
+    /// Set once every byte is written.
+    pub done: bool,
+    #[serde(skip)]
+    started: Option<Instant>,
+}
+
+impl DownloadProgressUpdate {
+    fn new() -> Self {
+        Self {
+            bytes_written: 0,
+            total_bytes: None,
+            bytes_per_second: 0.0,
+            done: false,
+            started: None,
+        }
+    }
+}
+
+/// Where a download reports its progress. Clones report to the same
+/// subscribers; without subscribers, reports are dropped.
+#[derive(Clone)]
+pub struct DownloadProgress {
+    sender: Arc<watch::Sender<DownloadProgressUpdate>>,
+}
+
+impl Default for DownloadProgress {
+    fn default() -> Self {
+        Self {
+            sender: Arc::new(watch::Sender::new(DownloadProgressUpdate::new())),
+        }
+    }
+}
+
+impl std::fmt::Debug for DownloadProgress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("DownloadProgress")
+            .field(&*self.sender.borrow())
+            .finish()
+    }
+}
+
+impl DownloadProgress {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The latest update, then each later one. Ends once every handle
+    /// reporting to it is dropped.
+    pub fn subscribe(&self) -> watch::Receiver<DownloadProgressUpdate> {
+        self.sender.subscribe()
+    }
+
+    /// Starts counting a transfer of `total_bytes`, e.g. again after falling
+    /// back on another plugin.
+    pub fn start(&self, total_bytes: Option<u64>) {
+        self.sender.send_replace(DownloadProgressUpdate {
+            total_bytes,
+            started: Some(Instant::now()),
+            ..DownloadProgressUpdate::new()
+        });
+    }
+
+    pub fn advance(&self, bytes_written: u64) {
+        self.sender.send_modify(|update| {
+            update.bytes_written = bytes_written;
+            let elapsed = update
+                .started
+                .map(|started| started.elapsed().as_secs_f64())
+                .unwrap_or_default();
+            if elapsed > 0.0 {
+                update.bytes_per_second = bytes_written as f64 / elapsed;
+            }
+        });
+    }
+
+    pub fn finish(&self) {
+        self.sender.send_modify(|update| update.done = true);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn reports_to_subscribers_until_dropped() {
+        let progress = DownloadProgress::new();
+        progress.advance(10);
+
+        let mut updates = progress.subscribe();
+        let reporter = progress.clone();
+        drop(progress);
+        reporter.start(Some(100));
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        reporter.advance(40);
+        updates.changed().await.unwrap();
+        let update = updates.borrow_and_update().clone();
+        assert_eq!(update.bytes_written, 40);
+        assert_eq!(update.total_bytes, Some(100));
+        assert!(update.bytes_per_second > 0.0);
+        assert!(!update.done);
+
+        reporter.finish();
+        drop(reporter);
+        updates.changed().await.unwrap();
+        assert!(updates.borrow_and_update().done);
+        assert!(updates.changed().await.is_err());
+    }
+}
@@ -0,0 +1,115 @@
+//! Proxies downloads go through, for networks that only reach the hub or
+//! object storage through a corporate proxy. Without one, reqwest picks up
+//! the `HTTP_PROXY`, `HTTPS_PROXY` and `NO_PROXY` variables.
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::redact::resolve_secret_ref;
+use super::PluginError;
+
+#[derive(
+    Debug, Clone, Default, Serialize, Deserialize, ToSchema, schemars::JsonSchema, PartialEq, Eq,
+)]
+pub struct ProxyConfig {
+    /// Proxy for HTTP and HTTPS, such as `http://proxy.corp:3128`. Empty
+    /// connects directly, ignoring the proxy variables.
+    pub url: String,
+    /// User of an authenticated proxy. Credentials in `url` work as well.
+    #[serde(default)]
+    pub username: Option<String>,
+    /// Accepts `secret://NAME` references into goose's secret store.
+    #[serde(default)]
+    pub password: Option<String>,
+    /// Hosts reached directly, as in `NO_PROXY`: domains, which match their
+    /// subdomains, IP addresses and CIDR ranges.
+    #[serde(default)]
+    pub no_proxy: Vec<String>,
+}
+
+impl ProxyConfig {
+    /// `GOOSE_PLUGIN_LLM_PROXY`, with `GOOSE_PLUGIN_LLM_PROXY_USERNAME`,
+    /// `GOOSE_PLUGIN_LLM_PROXY_PASSWORD` and a comma separated
+    /// `GOOSE_PLUGIN_LLM_NO_PROXY`.
+    pub fn from_env() -> Option<Self> {
+        fn var(name: &str) -> Option<String> {
+            std::env::var(name)
+                .ok()
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        }
+        Some(Self {
+            url: std::env::var("GOOSE_PLUGIN_LLM_PROXY")
+                .ok()?
+                .trim()
+                .to_string(),
+            username: var("GOOSE_PLUGIN_LLM_PROXY_USERNAME"),
+            password: var("GOOSE_PLUGIN_LLM_PROXY_PASSWORD"),
+            no_proxy: var("GOOSE_PLUGIN_LLM_NO_PROXY")
+                .map(|hosts| {
+                    hosts
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|host| !host.is_empty())
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_default(),
+        })
+    }
+
+    /// Sends the requests of `builder` through the proxy.
+    pub fn apply(
+        &self,
+        builder: reqwest::ClientBuilder,
+    ) -> Result<reqwest::ClientBuilder, PluginError> {
+        let url = self.url.trim();
+        if url.is_empty() {
+            return Ok(builder.no_proxy());
+        }
+        // The URL may hold credentials, so it is left out of the error.
+        let mut proxy = reqwest::Proxy::all(url)
+            .map_err(|err| PluginError::InvalidRequest(format!("invalid proxy URL: {}", err)))?;
+        if let Some(username) = &self.username {
+            let password = match &self.password {
+                Some(password) => resolve_secret_ref(password)?,
+                None => String::new(),
+            };
+            proxy = proxy.basic_auth(username, &password);
+        }
+        if !self.no_proxy.is_empty() {
+            proxy = proxy.no_proxy(reqwest::NoProxy::from_string(&self.no_proxy.join(",")));
+        }
+        Ok(builder.proxy(proxy))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_clients_for_proxies() {
+        let proxy: ProxyConfig = serde_json::from_value(serde_json::json!({
+            "url": "http://proxy.corp:3128",
+            "username": "goose",
+            "password": "hunter2",
+            "no_proxy": ["localhost", "*.corp", "10.0.0.0/8"]
+        }))
+        .unwrap();
+        let builder = proxy.apply(reqwest::Client::builder()).unwrap();
+        assert!(builder.build().is_ok());
+
+        let direct = ProxyConfig::default();
+        assert!(direct.apply(reqwest::Client::builder()).is_ok());
+
+        let invalid = ProxyConfig {
+            url: "not a url".to_string(),
+            ..ProxyConfig::default()
+        };
+        assert!(matches!(
+            invalid.apply(reqwest::Client::builder()),
+            Err(PluginError::InvalidRequest(_))
+        ));
+    }
+}
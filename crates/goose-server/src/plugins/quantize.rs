@@ -0,0 +1,184 @@
+//! Quantization of downloaded full-precision GGUF models with a configured
+//! quantizer such as llama.cpp's `llama-quantize`, which is called as
+//! `<quantizer> [--allow-requantize] <model> <output> <type>`.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+use tokio_util::sync::CancellationToken;
+use utoipa::ToSchema;
+
+use super::convert;
+use super::progress::DownloadProgress;
+use super::{PluginError, PluginTaskType};
+
+/// Quantization types llama.cpp supports, with their approximate bits per
+/// weight.
+const QUANT_TYPES: &[(&str, f64)] = &[
+    ("F32", 32.0),
+    ("F16", 16.0),
+    ("BF16", 16.0),
+    ("Q8_0", 8.5),
+    ("Q6_K", 6.56),
+    ("Q5_1", 6.0),
+    ("Q5_K_M", 5.7),
+    ("Q5_K_S", 5.54),
+    ("Q5_0", 5.5),
+    ("Q4_1", 5.0),
+    ("Q4_K_M", 4.89),
+    ("Q4_K_S", 4.58),
+    ("Q4_0", 4.5),
+    ("IQ4_NL", 4.5),
+    ("IQ4_XS", 4.25),
+    ("Q3_K_L", 4.27),
+    ("Q3_K_M", 3.91),
+    ("IQ3_M", 3.66),
+    ("Q3_K_S", 3.5),
+    ("IQ3_S", 3.44),
+    ("IQ3_XS", 3.3),
+    ("IQ3_XXS", 3.06),
+    ("Q2_K", 3.35),
+    ("Q2_K_S", 2.97),
+    ("IQ2_M", 2.7),
+    ("IQ2_S", 2.5),
+    ("IQ2_XS", 2.31),
+    ("IQ2_XXS", 2.06),
+    ("TQ2_0", 2.06),
+    ("IQ1_M", 1.75),
+    ("TQ1_0", 1.69),
+    ("IQ1_S", 1.56),
+];
+
+/// Suffixes of full-precision file names, replaced by the quantization type
+/// in the default output name.
+const FULL_PRECISION_SUFFIXES: &[&str] = &["f32", "f16", "bf16", "fp16", "fp32"];
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct QuantizeModelRequest {
+    pub task_type: PluginTaskType,
+    /// Model file to quantize, relative to the task type's model directory.
+    pub filename: String,
+    /// Target type, such as `Q4_K_M` or `Q8_0`.
+    pub quant_type: String,
+    /// File written, relative to the model directory. Defaults to the
+    /// model's name with its precision replaced by the target type, such as
+    /// `model-Q4_K_M.gguf` for `model-f16.gguf`.
+    #[serde(default)]
+    pub output_filename: Option<String>,
+    #[serde(default)]
+    pub destination_dir: Option<String>,
+    /// Quantizes a model that already is, at a loss of quality.
+    #[serde(default)]
+    pub allow_requantize: bool,
+    /// Stops the quantizer, e.g. when the quantization's job is cancelled.
+    #[serde(skip)]
+    pub cancel: CancellationToken,
+    /// Receives the bytes of the quantized file written so far.
+    #[serde(skip)]
+    pub progress: DownloadProgress,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct QuantizeModelResponse {
+    pub saved_path: String,
+    pub bytes: u64,
+    pub sha256: String,
+    pub quant_type: String,
+}
+
+/// The canonical name of the quantization type `name` with its bits per
+/// weight.
+pub fn quant_type(name: &str) -> Result<(&'static str, f64), PluginError> {
+    QUANT_TYPES
+        .iter()
+        .find(|(known, _)| known.eq_ignore_ascii_case(name.trim()))
+        .copied()
+        .ok_or_else(|| PluginError::InvalidRequest(format!("unknown quantization type {}", name)))
+}
+
+/// Bits per weight of a GGUF `general.file_type`, for the full-precision
+/// types; `None` for files that are already quantized.
+pub fn full_precision_bits(file_type: u64) -> Option<f64> {
+    match file_type {
+        0 => Some(32.0),
+        1 | 32 => Some(16.0),
+        _ => None,
+    }
+}
+
+/// Size the quantized file is expected to take, from the size of its
+/// source. Sources of unknown precision are taken to be 16-bit.
+pub fn estimated_bytes(source_bytes: u64, source_bits: Option<f64>, target_bits: f64) -> u64 {
+    (source_bytes as f64 * target_bits / source_bits.unwrap_or(16.0)).ceil() as u64
+}
+
+/// The file a quantization writes when the request names none.
+pub fn default_filename(filename: &str, quant_type: &str) -> String {
+    let stem = filename.strip_suffix(".gguf").unwrap_or(filename);
+    let base = FULL_PRECISION_SUFFIXES
+        .iter()
+        .find_map(|suffix| {
+            let base = stem.get(..stem.len().checked_sub(suffix.len() + 1)?)?;
+            let tail = stem.get(base.len()..)?;
+            (matches!(tail.as_bytes()[0], b'-' | b'.' | b'_')
+                && tail[1..].eq_ignore_ascii_case(suffix))
+            .then_some(base)
+        })
+        .unwrap_or(stem);
+    format!("{}-{}.gguf", base, quant_type)
+}
+
+/// Runs `quantizer` on `source`, writing `outfile` and reporting its size as
+/// it grows. The quantizer is killed when `cancel` fires.
+pub async fn run(
+    quantizer: &Path,
+    source: &Path,
+    outfile: &Path,
+    quant_type: &str,
+    allow_requantize: bool,
+    cancel: &CancellationToken,
+    progress: &DownloadProgress,
+) -> Result<(), PluginError> {
+    let mut command = Command::new(quantizer);
+    if allow_requantize {
+        command.arg("--allow-requantize");
+    }
+    command.arg(source).arg(outfile).arg(quant_type);
+    convert::run_writing(command, "quantizer", outfile, cancel, progress).await
+}
+
+/// The quantizer configured through `GOOSE_PLUGIN_LLM_QUANTIZER`.
+pub fn quantizer_from_env() -> Option<PathBuf> {
+    std::env::var_os("GOOSE_PLUGIN_LLM_QUANTIZER")
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_and_sizes_quantized_files() {
+        assert_eq!(
+            default_filename("org/model-f16.gguf", "Q4_K_M"),
+            "org/model-Q4_K_M.gguf"
+        );
+        assert_eq!(
+            default_filename("model.BF16.gguf", "Q8_0"),
+            "model-Q8_0.gguf"
+        );
+        assert_eq!(default_filename("model.gguf", "Q8_0"), "model-Q8_0.gguf");
+        assert_eq!(default_filename("elf16.gguf", "Q8_0"), "elf16-Q8_0.gguf");
+
+        assert_eq!(quant_type("q4_k_m").unwrap(), ("Q4_K_M", 4.89));
+        assert!(matches!(
+            quant_type("Q9"),
+            Err(PluginError::InvalidRequest(_))
+        ));
+        assert_eq!(estimated_bytes(1600, Some(16.0), 8.5), 850);
+        assert_eq!(estimated_bytes(3200, Some(32.0), 8.5), 850);
+        assert_eq!(full_precision_bits(15), None);
+    }
+}
@@ -0,0 +1,497 @@
+//! Limits operators set per plugin in its manifest, so one team's plugin
+//! cannot starve the others on a shared server:
+//!
+//! ```toml
+//! [quota]
+//! max_concurrent_operations = 4
+//! max_disk_bytes = 50_000_000_000
+//! max_processes = 2
+//! ```
+//!
+//! Calls made through the API beyond a limit fail with
+//! [`PluginError::QuotaExceeded`] instead of waiting. Disk usage is measured
+//! in the plugin's [`storage_dir`](ServerPlugin::storage_dir) before each
+//! download, and processes are counted from the plugin's
+//! [`RUNNING_PROCESSES`] metric before each service start.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use super::events::PluginEvent;
+use super::gc::{ModelGcRequest, ModelGcResponse};
+use super::health::PluginHealthResponse;
+use super::metrics::{PluginMetrics, RUNNING_PROCESSES};
+use super::operations::InvokeContext;
+use super::settings::PluginConfig;
+use super::{
+    AttachConsoleRequest, ConsoleSession, DownloadModelRequest, DownloadModelResponse,
+    FollowLogsRequest, FollowLogsSession, Handover, ListLogFilesResponse, ListModelsResponse,
+    ListNodesResponse, ListProfilesResponse, ListServicesResponse, ModelRevisionsRequest,
+    ModelRevisionsResponse, ModelUpdatesResponse, PluginError, PluginMetadata, ServerPlugin,
+    ServiceLogsPruned, ServiceLogsRequest, ServiceLogsResponse, ServiceStatusRequest,
+    ServiceStatusResponse, SignalServiceRequest, SignalServiceResponse, StartServiceRequest,
+    StartServiceResponse, StopServiceRequest, StopServiceResponse, UpgradeServiceRequest,
+    UpgradeServiceResponse,
+};
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct PluginQuota {
+    /// API calls to the plugin that may run at once.
+    #[serde(default)]
+    pub max_concurrent_operations: Option<usize>,
+    /// Bytes the plugin may store before downloads are refused.
+    #[serde(default)]
+    pub max_disk_bytes: Option<u64>,
+    /// Service processes the plugin may run at once.
+    #[serde(default)]
+    pub max_processes: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum QuotaLimit {
+    ConcurrentOperations,
+    DiskBytes,
+    Processes,
+    /// The size limit of one task type's model directory.
+    ModelDirBytes,
+}
+
+impl QuotaLimit {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            QuotaLimit::ConcurrentOperations => "concurrent operations",
+            QuotaLimit::DiskBytes => "disk usage",
+            QuotaLimit::Processes => "process",
+            QuotaLimit::ModelDirBytes => "model directory",
+        }
+    }
+}
+
+/// A plugin's quota and the API calls it has in flight. Kept by the plugin
+/// manager for as long as the quota is unchanged, so reloads do not reset
+/// the count.
+#[derive(Debug)]
+pub struct QuotaTracker {
+    quota: PluginQuota,
+    operations: Option<Arc<Semaphore>>,
+}
+
+impl QuotaTracker {
+    pub fn new(quota: PluginQuota) -> Self {
+        Self {
+            quota,
+            operations: quota
+                .max_concurrent_operations
+                .map(|max| Arc::new(Semaphore::new(max))),
+        }
+    }
+
+    pub fn quota(&self) -> PluginQuota {
+        self.quota
+    }
+}
+
+/// Bytes stored under `dir`, not following symlinks.
+pub fn disk_usage(dir: &Path) -> std::io::Result<u64> {
+    let mut total = 0;
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(err),
+        };
+        for entry in entries {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            if metadata.is_dir() {
+                pending.push(entry.path());
+            } else {
+                total += metadata.len();
+            }
+        }
+    }
+    Ok(total)
+}
+
+/// Enforces a plugin's quota on the calls made through it and passes them on.
+pub struct QuotaPlugin {
+    inner: Arc<dyn ServerPlugin>,
+    tracker: Arc<QuotaTracker>,
+}
+
+impl QuotaPlugin {
+    pub fn new(inner: Arc<dyn ServerPlugin>, tracker: Arc<QuotaTracker>) -> Self {
+        Self { inner, tracker }
+    }
+
+    fn exceeded(&self, limit: QuotaLimit, detail: String) -> PluginError {
+        PluginError::QuotaExceeded {
+            plugin_id: self.inner.metadata().id,
+            limit,
+            detail,
+        }
+    }
+
+    /// Takes a slot for one call, held until the returned permit drops.
+    fn begin(&self) -> Result<Option<OwnedSemaphorePermit>, PluginError> {
+        let Some(operations) = &self.tracker.operations else {
+            return Ok(None);
+        };
+        operations
+            .clone()
+            .try_acquire_owned()
+            .map(Some)
+            .map_err(|_| {
+                self.exceeded(
+                    QuotaLimit::ConcurrentOperations,
+                    format!(
+                        "{} operations already running",
+                        self.tracker.quota.max_concurrent_operations.unwrap_or(0)
+                    ),
+                )
+            })
+    }
+
+    async fn check_disk(&self) -> Result<(), PluginError> {
+        let (Some(max), Some(dir)) = (self.tracker.quota.max_disk_bytes, self.storage_dir()) else {
+            return Ok(());
+        };
+        let used = tokio::task::spawn_blocking(move || disk_usage(&dir))
+            .await
+            .map_err(|err| PluginError::Internal(err.to_string()))??;
+        if used >= max {
+            return Err(self.exceeded(
+                QuotaLimit::DiskBytes,
+                format!("{} of {} bytes in use", used, max),
+            ));
+        }
+        Ok(())
+    }
+
+    async fn check_processes(&self) -> Result<(), PluginError> {
+        let Some(max) = self.tracker.quota.max_processes else {
+            return Ok(());
+        };
+        let running = match self.inner.metrics().await {
+            Ok(metrics) => metrics
+                .gauges
+                .get(RUNNING_PROCESSES)
+                .copied()
+                .unwrap_or(0.0),
+            Err(PluginError::UnsupportedOperation) => return Ok(()),
+            Err(err) => return Err(err),
+        };
+        if running >= max as f64 {
+            return Err(self.exceeded(
+                QuotaLimit::Processes,
+                format!("{} of {} processes running", running, max),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ServerPlugin for QuotaPlugin {
+    fn metadata(&self) -> PluginMetadata {
+        self.inner.metadata()
+    }
+
+    fn storage_dir(&self) -> Option<PathBuf> {
+        self.inner.storage_dir()
+    }
+
+    async fn download_model(
+        &self,
+        request: DownloadModelRequest,
+    ) -> Result<DownloadModelResponse, PluginError> {
+        let _permit = self.begin()?;
+        self.check_disk().await?;
+        self.inner.download_model(request).await
+    }
+
+    async fn start_service(
+        &self,
+        request: StartServiceRequest,
+    ) -> Result<StartServiceResponse, PluginError> {
+        let _permit = self.begin()?;
+        self.check_processes().await?;
+        self.inner.start_service(request).await
+    }
+
+    async fn stop_service(
+        &self,
+        request: StopServiceRequest,
+    ) -> Result<StopServiceResponse, PluginError> {
+        let _permit = self.begin()?;
+        self.inner.stop_service(request).await
+    }
+
+    async fn service_logs(
+        &self,
+        request: ServiceLogsRequest,
+    ) -> Result<ServiceLogsResponse, PluginError> {
+        let _permit = self.begin()?;
+        self.inner.service_logs(request).await
+    }
+
+    async fn follow_logs(
+        &self,
+        request: FollowLogsRequest,
+    ) -> Result<FollowLogsSession, PluginError> {
+        let _permit = self.begin()?;
+        self.inner.follow_logs(request).await
+    }
+
+    async fn service_status(
+        &self,
+        request: ServiceStatusRequest,
+    ) -> Result<ServiceStatusResponse, PluginError> {
+        let _permit = self.begin()?;
+        self.inner.service_status(request).await
+    }
+
+    async fn list_services(&self) -> Result<ListServicesResponse, PluginError> {
+        let _permit = self.begin()?;
+        self.inner.list_services().await
+    }
+
+    async fn list_log_files(&self) -> Result<ListLogFilesResponse, PluginError> {
+        let _permit = self.begin()?;
+        self.inner.list_log_files().await
+    }
+
+    async fn upgrade_service(
+        &self,
+        request: UpgradeServiceRequest,
+    ) -> Result<UpgradeServiceResponse, PluginError> {
+        let _permit = self.begin()?;
+        self.inner.upgrade_service(request).await
+    }
+
+    async fn signal_service(
+        &self,
+        request: SignalServiceRequest,
+    ) -> Result<SignalServiceResponse, PluginError> {
+        let _permit = self.begin()?;
+        self.inner.signal_service(request).await
+    }
+
+    async fn attach_console(
+        &self,
+        request: AttachConsoleRequest,
+    ) -> Result<ConsoleSession, PluginError> {
+        let _permit = self.begin()?;
+        self.inner.attach_console(request).await
+    }
+
+    async fn list_models(&self) -> Result<ListModelsResponse, PluginError> {
+        let _permit = self.begin()?;
+        self.inner.list_models().await
+    }
+
+    async fn list_nodes(&self) -> Result<ListNodesResponse, PluginError> {
+        let _permit = self.begin()?;
+        self.inner.list_nodes().await
+    }
+
+    async fn list_profiles(&self) -> Result<ListProfilesResponse, PluginError> {
+        let _permit = self.begin()?;
+        self.inner.list_profiles().await
+    }
+
+    async fn list_revisions(
+        &self,
+        request: ModelRevisionsRequest,
+    ) -> Result<ModelRevisionsResponse, PluginError> {
+        let _permit = self.begin()?;
+        self.inner.list_revisions(request).await
+    }
+
+    async fn check_model_updates(&self) -> Result<ModelUpdatesResponse, PluginError> {
+        let _permit = self.begin()?;
+        self.inner.check_model_updates().await
+    }
+
+    async fn collect_models(
+        &self,
+        request: ModelGcRequest,
+    ) -> Result<ModelGcResponse, PluginError> {
+        let _permit = self.begin()?;
+        self.inner.collect_models(request).await
+    }
+
+    async fn prune_logs(&self) -> Result<ServiceLogsPruned, PluginError> {
+        let _permit = self.begin()?;
+        self.inner.prune_logs().await
+    }
+
+    async fn health(&self) -> Result<PluginHealthResponse, PluginError> {
+        let _permit = self.begin()?;
+        self.inner.health().await
+    }
+
+    async fn metrics(&self) -> Result<PluginMetrics, PluginError> {
+        self.inner.metrics().await
+    }
+
+    async fn shutdown(&self) -> Result<Vec<String>, PluginError> {
+        self.inner.shutdown().await
+    }
+
+    async fn on_register(&self) -> Result<(), PluginError> {
+        self.inner.on_register().await
+    }
+
+    async fn on_server_start(&self) -> Result<(), PluginError> {
+        self.inner.on_server_start().await
+    }
+
+    async fn on_shutdown(&self) -> Result<(), PluginError> {
+        self.inner.on_shutdown().await
+    }
+
+    async fn on_event(&self, event: &PluginEvent) -> Result<(), PluginError> {
+        self.inner.on_event(event).await
+    }
+
+    async fn save_state(&self) -> Result<Option<serde_json::Value>, PluginError> {
+        self.inner.save_state().await
+    }
+
+    async fn restore_state(&self, state: serde_json::Value) -> Result<(), PluginError> {
+        self.inner.restore_state(state).await
+    }
+
+    async fn invoke(
+        &self,
+        operation: &str,
+        payload: serde_json::Value,
+        context: InvokeContext,
+    ) -> Result<serde_json::Value, PluginError> {
+        let _permit = self.begin()?;
+        self.inner.invoke(operation, payload, context).await
+    }
+
+    fn config_schema(&self) -> serde_json::Value {
+        self.inner.config_schema()
+    }
+
+    async fn configure(&self, config: &PluginConfig) -> Result<(), PluginError> {
+        self.inner.configure(config).await
+    }
+
+    async fn hand_over(&self) -> Result<Handover, PluginError> {
+        self.inner.hand_over().await
+    }
+
+    async fn take_over(&self, handover: Handover) -> Result<Vec<String>, PluginError> {
+        self.inner.take_over(handover).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugins::PluginCapability;
+
+    struct Busy {
+        dir: PathBuf,
+    }
+
+    #[async_trait]
+    impl ServerPlugin for Busy {
+        fn metadata(&self) -> PluginMetadata {
+            PluginMetadata {
+                id: "busy".to_string(),
+                name: "busy".to_string(),
+                description: String::new(),
+                capabilities: vec![PluginCapability::CustomOperations],
+                enabled: true,
+                dependencies: Vec::new(),
+                api_version: crate::plugins::compat::PLUGIN_API_VERSION,
+                subscriptions: Vec::new(),
+                signature: None,
+            }
+        }
+
+        fn storage_dir(&self) -> Option<PathBuf> {
+            Some(self.dir.clone())
+        }
+
+        async fn metrics(&self) -> Result<PluginMetrics, PluginError> {
+            Ok(PluginMetrics::default().gauge(RUNNING_PROCESSES, 1.0))
+        }
+
+        async fn invoke(
+            &self,
+            _operation: &str,
+            _payload: serde_json::Value,
+            _context: InvokeContext,
+        ) -> Result<serde_json::Value, PluginError> {
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            Ok(serde_json::Value::Null)
+        }
+    }
+
+    fn limit(err: PluginError) -> QuotaLimit {
+        match err {
+            PluginError::QuotaExceeded { limit, .. } => limit,
+            other => panic!("unexpected error: {}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_calls_beyond_quota() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("text")).unwrap();
+        std::fs::write(dir.path().join("text").join("model.gguf"), [0u8; 64]).unwrap();
+        let tracker = Arc::new(QuotaTracker::new(PluginQuota {
+            max_concurrent_operations: Some(1),
+            max_disk_bytes: Some(64),
+            max_processes: Some(1),
+        }));
+        let plugin = Arc::new(QuotaPlugin::new(
+            Arc::new(Busy {
+                dir: dir.path().to_path_buf(),
+            }),
+            tracker,
+        ));
+
+        let running = tokio::spawn({
+            let plugin = plugin.clone();
+            async move {
+                plugin
+                    .invoke("slow", serde_json::Value::Null, InvokeContext::default())
+                    .await
+            }
+        });
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        let err = plugin
+            .invoke("slow", serde_json::Value::Null, InvokeContext::default())
+            .await
+            .unwrap_err();
+        assert_eq!(limit(err), QuotaLimit::ConcurrentOperations);
+        running.await.unwrap().unwrap();
+
+        assert_eq!(
+            limit(plugin.check_disk().await.unwrap_err()),
+            QuotaLimit::DiskBytes
+        );
+        assert_eq!(
+            limit(plugin.check_processes().await.unwrap_err()),
+            QuotaLimit::Processes
+        );
+        plugin
+            .invoke("fast", serde_json::Value::Null, InvokeContext::default())
+            .await
+            .unwrap();
+    }
+}
@@ -0,0 +1,172 @@
+//! Readiness of a freshly started service. A start with `wait_for_ready`
+//! only answers once the service serves requests, rather than as soon as its
+//! process is running.
+
+use std::time::Duration;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::health::HealthCheckConfig;
+use super::logs::{LogEntry, LogEvent};
+use super::PluginError;
+
+/// How long a start waits for readiness unless the probe says otherwise.
+/// Loading a large model can take minutes.
+const DEFAULT_TIMEOUT_SECS: u64 = 300;
+
+/// How long one request to the readiness URL may take.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Pause between requests to the readiness URL.
+pub const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How a service shows that it serves requests. Without `url` and
+/// `log_pattern` the health check URL is polled, or, without a health check,
+/// the service is ready once its output reports that it is listening.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+pub struct ReadinessProbe {
+    /// Ready once this URL answers with a 2xx status.
+    #[serde(default)]
+    pub url: Option<String>,
+    /// Ready once a line of the service's output matches this regular
+    /// expression. With `url` as well, whichever passes first counts.
+    #[serde(default)]
+    pub log_pattern: Option<String>,
+    /// How long to wait before the start fails and the service is stopped.
+    /// Defaults to 300 seconds.
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+}
+
+/// A probe resolved against the rest of the start request.
+#[derive(Debug, Clone)]
+pub struct ReadinessCheck {
+    pub url: Option<String>,
+    pattern: Option<Regex>,
+    pub timeout: Duration,
+}
+
+impl ReadinessCheck {
+    pub fn resolve(
+        probe: Option<&ReadinessProbe>,
+        health_check: Option<&HealthCheckConfig>,
+    ) -> Result<Self, PluginError> {
+        let probe = probe.cloned().unwrap_or_default();
+        let pattern = probe
+            .log_pattern
+            .as_deref()
+            .map(Regex::new)
+            .transpose()
+            .map_err(|err| {
+                PluginError::InvalidRequest(format!("invalid readiness log_pattern: {}", err))
+            })?;
+        let url = match (probe.url, &pattern) {
+            (Some(url), _) => Some(url),
+            (None, Some(_)) => None,
+            (None, None) => health_check.map(|check| check.url.clone()),
+        };
+        let timeout_secs = probe.timeout_secs.unwrap_or(DEFAULT_TIMEOUT_SECS).max(1);
+        Ok(Self {
+            url,
+            pattern,
+            timeout: Duration::from_secs(timeout_secs),
+        })
+    }
+
+    /// Whether the service's output is watched for a readiness line.
+    pub fn watches_output(&self) -> bool {
+        self.pattern.is_some() || self.url.is_none()
+    }
+
+    /// Whether `entry` shows the service ready.
+    pub fn matches(&self, entry: &LogEntry) -> bool {
+        match &self.pattern {
+            Some(pattern) => pattern.is_match(&entry.message),
+            None => self.url.is_none() && matches!(entry.event, Some(LogEvent::Listening { .. })),
+        }
+    }
+
+    /// What the start is waiting for, for error messages.
+    pub fn describe(&self) -> String {
+        match (&self.url, &self.pattern) {
+            (Some(url), Some(pattern)) => {
+                format!("{} to answer or a line matching {}", url, pattern)
+            }
+            (Some(url), None) => format!("{} to answer", url),
+            (None, Some(pattern)) => format!("a line matching {}", pattern),
+            (None, None) => "the service to report that it is listening".to_string(),
+        }
+    }
+}
+
+/// Requests the readiness URL once, returning a human readable reason when
+/// it does not answer with a 2xx status.
+pub async fn probe_url(client: &reqwest::Client, url: &str) -> Result<(), String> {
+    let response = client
+        .get(url)
+        .timeout(PROBE_TIMEOUT)
+        .send()
+        .await
+        .map_err(|err| err.to_string())?;
+
+    if response.status().is_success() {
+        Ok(())
+    } else {
+        Err(format!("{} returned {}", url, response.status()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugins::logs::LogStream;
+
+    fn health_check(url: &str) -> HealthCheckConfig {
+        serde_json::from_value(serde_json::json!({ "url": url })).unwrap()
+    }
+
+    #[test]
+    fn resolves_probes_and_matches_output() {
+        let health = health_check("http://127.0.0.1:8080/health");
+        let listening = LogEntry::parse(
+            LogStream::Stderr,
+            "main: server is listening on http://127.0.0.1:8080",
+        );
+        let loaded = LogEntry::parse(LogStream::Stderr, "llama_model_load: model loaded");
+
+        let fallback = ReadinessCheck::resolve(None, Some(&health)).unwrap();
+        assert_eq!(
+            fallback.url.as_deref(),
+            Some("http://127.0.0.1:8080/health")
+        );
+        assert!(!fallback.watches_output());
+        assert_eq!(fallback.timeout, Duration::from_secs(DEFAULT_TIMEOUT_SECS));
+
+        let bare = ReadinessCheck::resolve(None, None).unwrap();
+        assert!(bare.watches_output());
+        assert!(bare.matches(&listening));
+        assert!(!bare.matches(&loaded));
+
+        let probe = ReadinessProbe {
+            log_pattern: Some("model loaded$".to_string()),
+            timeout_secs: Some(30),
+            ..Default::default()
+        };
+        let pattern = ReadinessCheck::resolve(Some(&probe), Some(&health)).unwrap();
+        assert_eq!(pattern.url, None);
+        assert!(pattern.matches(&loaded));
+        assert!(!pattern.matches(&listening));
+        assert_eq!(pattern.timeout, Duration::from_secs(30));
+
+        let invalid = ReadinessProbe {
+            log_pattern: Some("(".to_string()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            ReadinessCheck::resolve(Some(&invalid), None),
+            Err(PluginError::InvalidRequest(_))
+        ));
+    }
+}
@@ -0,0 +1,242 @@
+//! Recommended models, offered for one-click install. goosed ships a
+//! catalog of them in `recommended_models.json`; a newer one is fetched from
+//! `GOOSE_MODEL_CATALOG_URL` when it is set, a JSON document of the same
+//! form:
+//!
+//! ```json
+//! {"models": [{
+//!     "id": "llama-3.2-3b-instruct",
+//!     "name": "Llama 3.2 3B Instruct",
+//!     "task_type": "text",
+//!     "model_id": "bartowski/Llama-3.2-3B-Instruct-GGUF",
+//!     "filename": "Llama-3.2-3B-Instruct-Q4_K_M.gguf",
+//!     "size_bytes": 2020000000,
+//!     "ram_bytes": 4000000000,
+//!     "quality": "Good everyday assistant for laptops."
+//! }]}
+//! ```
+//!
+//! The fetched catalog is kept for an hour, and is served for as long as the
+//! URL cannot be reached again. The built-in one is served until a catalog
+//! is fetched.
+
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use utoipa::ToSchema;
+
+use super::offline::OfflineMode;
+use super::{PluginError, PluginTaskType};
+
+/// The catalog goosed ships with.
+const BUILT_IN: &str = include_str!("recommended_models.json");
+
+/// How long a fetched catalog is served before it is fetched again.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// How long fetching the catalog may take before the last one is served.
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A recommended model: a file of a hub repository, downloaded as
+/// `model_id`, `filename` and `revision` of a download request.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq)]
+pub struct CatalogModel {
+    /// Stable id of the entry.
+    pub id: String,
+    pub name: String,
+    pub task_type: PluginTaskType,
+    pub model_id: String,
+    pub filename: String,
+    #[serde(default = "super::default_revision")]
+    pub revision: String,
+    /// Approximate size of the download.
+    pub size_bytes: u64,
+    /// Approximate memory the model needs to serve a 4k token context.
+    pub ram_bytes: u64,
+    /// What the model is good and bad at.
+    #[serde(default)]
+    pub quality: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    /// Hex SHA-256 of the file, for `expected_sha256` of the download.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema, PartialEq)]
+pub struct ModelCatalog {
+    pub models: Vec<CatalogModel>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelCatalogSource {
+    /// The catalog goosed ships with.
+    BuiltIn,
+    /// The catalog at `GOOSE_MODEL_CATALOG_URL`.
+    Remote,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ModelCatalogResponse {
+    pub source: ModelCatalogSource,
+    /// When a remote catalog was fetched.
+    #[serde(default)]
+    pub fetched_at: Option<DateTime<Utc>>,
+    pub models: Vec<CatalogModel>,
+}
+
+/// The last catalog fetched from the remote URL.
+struct Fetched {
+    catalog: ModelCatalog,
+    at: Instant,
+    fetched_at: DateTime<Utc>,
+}
+
+pub struct ModelCatalogClient {
+    url: Option<String>,
+    client: reqwest::Client,
+    fetched: Mutex<Option<Fetched>>,
+}
+
+impl ModelCatalogClient {
+    pub fn new(url: Option<String>) -> Self {
+        Self {
+            url,
+            client: reqwest::Client::new(),
+            fetched: Mutex::new(None),
+        }
+    }
+
+    /// The catalog at `GOOSE_MODEL_CATALOG_URL`; without the variable only
+    /// the built-in one is served.
+    pub fn from_env() -> Self {
+        let url = std::env::var("GOOSE_MODEL_CATALOG_URL")
+            .ok()
+            .filter(|url| !url.is_empty());
+        Self::new(url)
+    }
+
+    /// The recommended models, of `task_type` when one is given.
+    pub async fn get(
+        &self,
+        task_type: Option<&PluginTaskType>,
+        offline: &OfflineMode,
+    ) -> ModelCatalogResponse {
+        let mut response = self.current(offline).await;
+        if let Some(task_type) = task_type {
+            response
+                .models
+                .retain(|model| model.task_type == *task_type);
+        }
+        response
+    }
+
+    async fn current(&self, offline: &OfflineMode) -> ModelCatalogResponse {
+        let mut fetched = self.fetched.lock().await;
+        if let Some(url) = &self.url {
+            let stale = fetched
+                .as_ref()
+                .is_none_or(|fetched| fetched.at.elapsed() >= REFRESH_INTERVAL);
+            if stale && !offline.is_enabled() {
+                match self.fetch(url).await {
+                    Ok(catalog) => {
+                        *fetched = Some(Fetched {
+                            catalog,
+                            at: Instant::now(),
+                            fetched_at: Utc::now(),
+                        })
+                    }
+                    Err(err) => tracing::warn!("fetching the model catalog failed: {}", err),
+                }
+            }
+        }
+        match &*fetched {
+            Some(fetched) => ModelCatalogResponse {
+                source: ModelCatalogSource::Remote,
+                fetched_at: Some(fetched.fetched_at),
+                models: fetched.catalog.models.clone(),
+            },
+            None => ModelCatalogResponse {
+                source: ModelCatalogSource::BuiltIn,
+                fetched_at: None,
+                models: built_in().models,
+            },
+        }
+    }
+
+    async fn fetch(&self, url: &str) -> Result<ModelCatalog, PluginError> {
+        Ok(self
+            .client
+            .get(url)
+            .timeout(FETCH_TIMEOUT)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?)
+    }
+}
+
+/// The catalog goosed ships with.
+pub fn built_in() -> ModelCatalog {
+    serde_json::from_str(BUILT_IN).expect("the built-in model catalog is valid")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::get;
+    use axum::{Json, Router};
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn serves_the_remote_catalog_over_the_built_in_one() {
+        let built_in = built_in();
+        assert!(!built_in.models.is_empty());
+        assert!(built_in
+            .models
+            .iter()
+            .all(|model| model.revision == "main" && model.ram_bytes >= model.size_bytes));
+
+        let offline = OfflineMode::default();
+        let unset = ModelCatalogClient::new(None);
+        let response = unset.get(Some(&PluginTaskType::TTS), &offline).await;
+        assert_eq!(response.source, ModelCatalogSource::BuiltIn);
+        assert!(response.models.is_empty());
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/models.json", listener.local_addr().unwrap());
+        let app = Router::new().route(
+            "/models.json",
+            get(|| async {
+                Json(json!({"models": [{
+                    "id": "voice",
+                    "name": "Voice",
+                    "task_type": "tts",
+                    "model_id": "org/voice",
+                    "filename": "voice.onnx",
+                    "size_bytes": 60000000,
+                    "ram_bytes": 200000000
+                }]}))
+            }),
+        );
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        let remote = ModelCatalogClient::new(Some(url));
+        let response = remote.get(Some(&PluginTaskType::TTS), &offline).await;
+        assert_eq!(response.source, ModelCatalogSource::Remote);
+        assert_eq!(response.models[0].id, "voice");
+        assert!(remote
+            .get(Some(&PluginTaskType::TEXT), &offline)
+            .await
+            .models
+            .is_empty());
+
+        let unreachable = ModelCatalogClient::new(Some("http://127.0.0.1:9/models.json".into()));
+        let response = unreachable.get(None, &offline).await;
+        assert_eq!(response.source, ModelCatalogSource::BuiltIn);
+        assert_eq!(response.models, built_in.models);
+    }
+}
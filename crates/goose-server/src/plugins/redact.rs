@@ -0,0 +1,202 @@
+use std::collections::HashMap;
+
+use goose::config::Config;
+
+use super::PluginError;
+
+/// Replacement shown instead of secret values.
+pub const MASK: &str = "********";
+
+/// Prefix for environment values that are looked up in goose's secret store.
+pub const SECRET_REF_PREFIX: &str = "secret://";
+
+/// Substrings that mark an environment variable or CLI flag as secret.
+const SECRET_KEY_PATTERNS: &[&str] = &[
+    "token",
+    "secret",
+    "password",
+    "passwd",
+    "api_key",
+    "api-key",
+    "apikey",
+    "access_key",
+    "access-key",
+    "private_key",
+    "private-key",
+    "credential",
+    "auth",
+];
+
+/// Secret values shorter than this are not scrubbed from free text, to avoid
+/// masking unrelated output.
+const MIN_SCRUB_LEN: usize = 4;
+
+/// Looks up `value` in goose's secret store if it is a `secret://NAME`
+/// reference, otherwise returns it unchanged.
+pub fn resolve_secret_ref(value: &str) -> Result<String, PluginError> {
+    match value.strip_prefix(SECRET_REF_PREFIX) {
+        Some(name) => Config::global().get_secret(name).map_err(|err| {
+            PluginError::InvalidRequest(format!(
+                "cannot resolve {}{}: {}",
+                SECRET_REF_PREFIX, name, err
+            ))
+        }),
+        None => Ok(value.to_string()),
+    }
+}
+
+pub fn is_secret_key(key: &str) -> bool {
+    let key = key.to_ascii_lowercase();
+    SECRET_KEY_PATTERNS
+        .iter()
+        .any(|pattern| key.contains(pattern))
+}
+
+/// Masks secrets in anything derived from a launch before it is serialized or logged.
+#[derive(Debug, Clone, Default)]
+pub struct Redactor {
+    secrets: Vec<String>,
+}
+
+impl Redactor {
+    /// Resolves `secret://NAME` references in `environment` and returns the
+    /// resolved environment together with a redactor that knows every secret
+    /// value: resolved references as well as values of secret-looking keys.
+    pub fn resolve_environment(
+        environment: &HashMap<String, String>,
+    ) -> Result<(HashMap<String, String>, Self), PluginError> {
+        let mut resolved = HashMap::with_capacity(environment.len());
+        let mut secrets = Vec::new();
+
+        for (key, value) in environment {
+            let value = match value.strip_prefix(SECRET_REF_PREFIX) {
+                Some(name) => {
+                    let secret: String = Config::global().get_secret(name).map_err(|err| {
+                        PluginError::InvalidRequest(format!(
+                            "cannot resolve {}{} for {}: {}",
+                            SECRET_REF_PREFIX, name, key, err
+                        ))
+                    })?;
+                    secrets.push(secret.clone());
+                    secret
+                }
+                None => {
+                    if is_secret_key(key) {
+                        secrets.push(value.clone());
+                    }
+                    value.clone()
+                }
+            };
+            resolved.insert(key.clone(), value);
+        }
+
+        let mut redactor = Self { secrets };
+        redactor.secrets.retain(|secret| !secret.is_empty());
+        redactor
+            .secrets
+            .sort_by_key(|secret| std::cmp::Reverse(secret.len()));
+        redactor.secrets.dedup();
+        Ok((resolved, redactor))
+    }
+
+    pub fn redact_text(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        for secret in self
+            .secrets
+            .iter()
+            .filter(|secret| secret.len() >= MIN_SCRUB_LEN)
+        {
+            if text.contains(secret.as_str()) {
+                text = text.replace(secret.as_str(), MASK);
+            }
+        }
+        text
+    }
+
+    pub fn redact_environment(
+        &self,
+        environment: &HashMap<String, String>,
+    ) -> HashMap<String, String> {
+        environment
+            .iter()
+            .map(|(key, value)| {
+                let value = if is_secret_key(key) || self.secrets.contains(value) {
+                    MASK.to_string()
+                } else {
+                    self.redact_text(value)
+                };
+                (key.clone(), value)
+            })
+            .collect()
+    }
+
+    /// Masks values of secret-looking flags (`--api-key X`, `--api-key=X`,
+    /// `TOKEN=X`) and any known secret value appearing in the arguments.
+    pub fn redact_args(&self, args: &[String]) -> Vec<String> {
+        let mut redacted = Vec::with_capacity(args.len());
+        let mut mask_next = false;
+        for arg in args {
+            if mask_next {
+                mask_next = false;
+                redacted.push(MASK.to_string());
+                continue;
+            }
+            if let Some((key, _)) = arg.split_once('=') {
+                if is_secret_key(key) {
+                    redacted.push(format!("{}={}", key, MASK));
+                    continue;
+                }
+            } else if arg.starts_with('-') && is_secret_key(arg) {
+                mask_next = true;
+            }
+            redacted.push(self.redact_text(arg));
+        }
+        redacted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn masks_secret_environment_and_args() {
+        let environment = HashMap::from([
+            ("HF_TOKEN".to_string(), "hf_abcdef".to_string()),
+            ("RUST_LOG".to_string(), "info".to_string()),
+        ]);
+        let (resolved, redactor) = Redactor::resolve_environment(&environment).unwrap();
+        assert_eq!(resolved["HF_TOKEN"], "hf_abcdef");
+
+        let masked = redactor.redact_environment(&resolved);
+        assert_eq!(masked["HF_TOKEN"], MASK);
+        assert_eq!(masked["RUST_LOG"], "info");
+
+        let args: Vec<String> = [
+            "serve",
+            "--api-key",
+            "k123",
+            "--auth-token=xyz",
+            "--hf",
+            "hf_abcdef",
+        ]
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect();
+        assert_eq!(
+            redactor.redact_args(&args),
+            vec![
+                "serve",
+                "--api-key",
+                MASK,
+                "--auth-token=********",
+                "--hf",
+                MASK
+            ]
+        );
+        assert_eq!(
+            redactor.redact_text("using token hf_abcdef"),
+            "using token ********"
+        );
+    }
+}
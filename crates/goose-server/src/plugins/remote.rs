@@ -0,0 +1,356 @@
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use utoipa::ToSchema;
+
+use super::health::ServiceHealth;
+use super::logs::{LogBuffer, LogEntry, LogLevel, LogStream};
+use super::redact::Redactor;
+use super::retention::LogRetention;
+use super::signals::ServiceSignal;
+use super::{
+    PluginError, PluginTaskType, ServiceLogsResponse, ServiceStatusResponse, SignalServiceResponse,
+    StartServiceResponse, StopServiceResponse,
+};
+
+/// Seconds a remote service gets to exit after SIGTERM before it is killed.
+const REMOTE_STOP_GRACE_SECS: u32 = 10;
+
+/// Lines read back from a remote service log per logs request.
+const REMOTE_LOG_TAIL_LINES: usize = 1000;
+
+const SSH_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A machine the plugin manages over SSH. Authentication is left to the
+/// local ssh client (agent, config file or `identity_file`); goosed never
+/// prompts.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RemoteNode {
+    pub id: String,
+    pub host: String,
+    #[serde(default)]
+    pub user: Option<String>,
+    #[serde(default)]
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub identity_file: Option<String>,
+    /// Directory on the node that holds models and service logs.
+    pub base_dir: String,
+    /// llmserver binary on the node used when a start request has none.
+    #[serde(default)]
+    pub binary: Option<String>,
+}
+
+impl RemoteNode {
+    /// Nodes from `GOOSE_PLUGIN_LLM_NODES`, a JSON array of node objects.
+    pub fn from_env() -> anyhow::Result<HashMap<String, RemoteNode>> {
+        let Ok(raw) = std::env::var("GOOSE_PLUGIN_LLM_NODES") else {
+            return Ok(HashMap::new());
+        };
+        let nodes: Vec<RemoteNode> = serde_json::from_str(&raw)
+            .map_err(|err| anyhow::anyhow!("invalid GOOSE_PLUGIN_LLM_NODES: {}", err))?;
+        Ok(nodes
+            .into_iter()
+            .map(|node| (node.id.clone(), node))
+            .collect())
+    }
+
+    pub fn model_dir(&self, task_type: &PluginTaskType) -> String {
+        format!(
+            "{}/{}",
+            self.base_dir.trim_end_matches('/'),
+            task_type.as_str()
+        )
+    }
+
+    pub fn log_path(&self, instance_id: &str) -> String {
+        format!(
+            "{}/logs/{}.log",
+            self.base_dir.trim_end_matches('/'),
+            instance_id
+        )
+    }
+
+    fn destination(&self) -> String {
+        match &self.user {
+            Some(user) => format!("{}@{}", user, self.host),
+            None => self.host.clone(),
+        }
+    }
+
+    /// Runs `script` with the node's login shell and returns its stdout.
+    pub async fn run(&self, script: &str, stdin: Option<&[u8]>) -> Result<String, PluginError> {
+        let mut command = Command::new("ssh");
+        command
+            .arg("-o")
+            .arg("BatchMode=yes")
+            .arg("-o")
+            .arg(format!("ConnectTimeout={}", SSH_CONNECT_TIMEOUT.as_secs()));
+        if let Some(port) = self.port {
+            command.arg("-p").arg(port.to_string());
+        }
+        if let Some(identity) = &self.identity_file {
+            command.arg("-i").arg(identity);
+        }
+        command
+            .arg(self.destination())
+            .arg("--")
+            .arg(script)
+            .stdin(if stdin.is_some() {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+
+        let mut child = command
+            .spawn()
+            .map_err(|err| PluginError::Remote(format!("failed to run ssh: {}", err)))?;
+        if let (Some(input), Some(mut pipe)) = (stdin, child.stdin.take()) {
+            pipe.write_all(input).await?;
+        }
+        let output = child.wait_with_output().await?;
+        if !output.status.success() {
+            return Err(PluginError::Remote(format!(
+                "{} on node {}: {}",
+                output.status,
+                self.id,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    /// Downloads `url` to `path` on the node with curl. The bearer token is
+    /// fed through stdin so it never shows up in the remote process list.
+    pub async fn download(
+        &self,
+        url: &str,
+        auth_token: Option<&str>,
+        path: &str,
+    ) -> Result<u64, PluginError> {
+        let partial = format!("{}.part", path);
+        let dir = path.rsplit_once('/').map_or(".", |(dir, _)| dir);
+        let header = if auth_token.is_some() { "-H @- " } else { "" };
+        let script = format!(
+            "mkdir -p {dir} && curl -fsSL {header}-o {partial} {url} && mv {partial} {path} && wc -c < {path}",
+            dir = quote(dir),
+            partial = quote(&partial),
+            path = quote(path),
+            url = quote(url),
+        );
+        let stdin = auth_token.map(|token| format!("Authorization: Bearer {}\n", token));
+        let output = self
+            .run(&script, stdin.as_deref().map(str::as_bytes))
+            .await?;
+        output.trim().parse().map_err(|_| {
+            PluginError::Remote(format!("unexpected size report from node {}", self.id))
+        })
+    }
+
+    /// Starts `command` detached on the node, logging to `log_path`, and
+    /// returns its pid. The environment travels over stdin into a private
+    /// file that is sourced and removed before exec, so secret values never
+    /// appear on a command line.
+    pub async fn spawn(
+        &self,
+        command: &str,
+        args: &[String],
+        environment: &HashMap<String, String>,
+        log_path: &str,
+    ) -> Result<u32, PluginError> {
+        let mut env_file = String::new();
+        for (key, value) in environment {
+            if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                return Err(PluginError::InvalidRequest(format!(
+                    "environment variable name {:?} cannot be passed to a remote node",
+                    key
+                )));
+            }
+            env_file.push_str(&format!("{}={}\n", key, quote(value)));
+        }
+
+        let log_dir = log_path.rsplit_once('/').map_or(".", |(dir, _)| dir);
+        let env_path = format!("{}.env", log_path);
+        let mut invocation = vec![quote(command)];
+        invocation.extend(args.iter().map(|arg| quote(arg)));
+        let script = format!(
+            "mkdir -p {dir} && (umask 077 && cat > {env}) || exit 1; \
+             (set -a; . {env}; rm -f {env}; exec nohup {cmd} > {log} 2>&1 < /dev/null) & echo $!",
+            dir = quote(log_dir),
+            env = quote(&env_path),
+            cmd = invocation.join(" "),
+            log = quote(log_path),
+        );
+        let output = self.run(&script, Some(env_file.as_bytes())).await?;
+        output.trim().parse().map_err(|_| {
+            PluginError::Remote(format!("unexpected pid report from node {}", self.id))
+        })
+    }
+
+    pub async fn is_running(&self, pid: u32) -> Result<bool, PluginError> {
+        let output = self
+            .run(
+                &format!("kill -0 {} 2>/dev/null && echo yes || echo no", pid),
+                None,
+            )
+            .await?;
+        Ok(output.trim() == "yes")
+    }
+
+    /// Sends SIGTERM and escalates to SIGKILL after a grace period.
+    pub async fn terminate(&self, pid: u32) -> Result<(), PluginError> {
+        let script = format!(
+            "kill {pid} 2>/dev/null || exit 0; \
+             i=0; while [ $i -lt {grace} ]; do kill -0 {pid} 2>/dev/null || exit 0; sleep 1; i=$((i+1)); done; \
+             kill -9 {pid} 2>/dev/null; exit 0",
+            pid = pid,
+            grace = REMOTE_STOP_GRACE_SECS,
+        );
+        self.run(&script, None).await.map(|_| ())
+    }
+
+    /// Last `lines` lines of a log file on the node.
+    pub async fn tail(&self, path: &str, lines: usize) -> Result<String, PluginError> {
+        self.run(
+            &format!("tail -n {} {} 2>/dev/null || true", lines, quote(path)),
+            None,
+        )
+        .await
+    }
+}
+
+/// A service started on a remote node. Remote services have no watchdog,
+/// health checks or console; their combined output goes to a log file on
+/// the node.
+#[derive(Clone)]
+pub struct RemoteInstance {
+    pub instance_id: String,
+    pub task_type: PluginTaskType,
+    pub node: RemoteNode,
+    pub pid: u32,
+    pub command: String,
+    /// Arguments and environment as launched, secret values masked.
+    pub args: Vec<String>,
+    pub environment: HashMap<String, String>,
+    pub redactor: Arc<Redactor>,
+    pub log_path: String,
+}
+
+impl RemoteInstance {
+    pub fn describe(&self) -> StartServiceResponse {
+        StartServiceResponse {
+            instance_id: self.instance_id.clone(),
+            task_type: self.task_type.clone(),
+            pid: self.pid,
+            command: self.command.clone(),
+            args: self.args.clone(),
+            node: Some(self.node.id.clone()),
+            gpu_offload: None,
+            fallback: Vec::new(),
+        }
+    }
+
+    pub async fn status(&self) -> Result<ServiceStatusResponse, PluginError> {
+        let running = self.node.is_running(self.pid).await?;
+        Ok(ServiceStatusResponse {
+            instance_id: self.instance_id.clone(),
+            task_type: self.task_type.clone(),
+            pid: running.then_some(self.pid),
+            command: self.command.clone(),
+            args: self.args.clone(),
+            environment: self.environment.clone(),
+            health: if running {
+                ServiceHealth::Starting
+            } else {
+                ServiceHealth::Unhealthy
+            },
+            consecutive_failures: 0,
+            restarts: 0,
+            last_health_check: None,
+            cpu_affinity: None,
+            started_at: None,
+            uptime_secs: None,
+            log_file: Some(self.log_path.clone()),
+            node: Some(self.node.id.clone()),
+        })
+    }
+
+    pub async fn logs(
+        &self,
+        min_level: Option<LogLevel>,
+        limit: Option<usize>,
+    ) -> Result<ServiceLogsResponse, PluginError> {
+        let output = self
+            .node
+            .tail(&self.log_path, REMOTE_LOG_TAIL_LINES)
+            .await?;
+        let mut buffer = LogBuffer::new(REMOTE_LOG_TAIL_LINES, LogRetention::default());
+        for line in output.lines() {
+            buffer.push(LogEntry::parse(
+                LogStream::Stdout,
+                &self.redactor.redact_text(line),
+            ));
+        }
+        Ok(ServiceLogsResponse {
+            instance_id: self.instance_id.clone(),
+            task_type: self.task_type.clone(),
+            entries: buffer.query(min_level, limit),
+        })
+    }
+
+    pub async fn stop(&self) -> Result<StopServiceResponse, PluginError> {
+        let running = self.node.is_running(self.pid).await?;
+        if running {
+            self.node.terminate(self.pid).await?;
+        }
+        Ok(StopServiceResponse {
+            instance_id: self.instance_id.clone(),
+            task_type: self.task_type.clone(),
+            terminated: running,
+            crash: None,
+        })
+    }
+
+    pub async fn signal(
+        &self,
+        signal: ServiceSignal,
+    ) -> Result<SignalServiceResponse, PluginError> {
+        let name = signal.to_string();
+        self.node
+            .run(
+                &format!("kill -s {} {}", name.trim_start_matches("SIG"), self.pid),
+                None,
+            )
+            .await?;
+        Ok(SignalServiceResponse {
+            instance_id: self.instance_id.clone(),
+            task_type: self.task_type.clone(),
+            pid: self.pid,
+            signal,
+        })
+    }
+}
+
+/// Quotes `value` for a POSIX shell.
+pub fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_for_posix_shells() {
+        assert_eq!(quote("plain"), "'plain'");
+        assert_eq!(quote("it's $HOME"), r"'it'\''s $HOME'");
+    }
+}
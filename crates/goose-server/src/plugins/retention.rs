@@ -0,0 +1,185 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// How often goosed prunes its own log directory in the background.
+pub const SERVER_PRUNE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Limits on how much log data is kept. Unset fields are unbounded.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    Serialize,
+    Deserialize,
+    ToSchema,
+    schemars::JsonSchema,
+    PartialEq,
+    Eq,
+)]
+pub struct LogRetention {
+    /// Total size budget; the oldest data is dropped first.
+    #[serde(default)]
+    pub max_bytes: Option<u64>,
+    /// Number of log files to keep. Only applies to logs written to disk.
+    #[serde(default)]
+    pub max_files: Option<usize>,
+    #[serde(default)]
+    pub max_age_secs: Option<u64>,
+    /// Size at which a service's log file is rotated to a new one.
+    #[serde(default)]
+    pub max_file_bytes: Option<u64>,
+}
+
+impl LogRetention {
+    /// Global policy from `GOOSE_LOG_RETENTION_MAX_BYTES`,
+    /// `GOOSE_LOG_RETENTION_MAX_FILES`, `GOOSE_LOG_RETENTION_MAX_AGE_SECS` and
+    /// `GOOSE_LOG_RETENTION_MAX_FILE_BYTES`.
+    pub fn from_env() -> Self {
+        fn var<T: std::str::FromStr>(name: &str) -> Option<T> {
+            std::env::var(name).ok()?.trim().parse().ok()
+        }
+        Self {
+            max_bytes: var("GOOSE_LOG_RETENTION_MAX_BYTES"),
+            max_files: var("GOOSE_LOG_RETENTION_MAX_FILES"),
+            max_age_secs: var("GOOSE_LOG_RETENTION_MAX_AGE_SECS"),
+            max_file_bytes: var("GOOSE_LOG_RETENTION_MAX_FILE_BYTES"),
+        }
+    }
+
+    /// Fields set on `self` take precedence over `fallback`.
+    pub fn or(self, fallback: LogRetention) -> Self {
+        Self {
+            max_bytes: self.max_bytes.or(fallback.max_bytes),
+            max_files: self.max_files.or(fallback.max_files),
+            max_age_secs: self.max_age_secs.or(fallback.max_age_secs),
+            max_file_bytes: self.max_file_bytes.or(fallback.max_file_bytes),
+        }
+    }
+
+    pub fn max_age(&self) -> Option<Duration> {
+        self.max_age_secs.map(Duration::from_secs)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+pub struct PruneReport {
+    pub files_removed: usize,
+    pub bytes_freed: u64,
+}
+
+/// Removes `.log` files under `dir` (recursively) that fall outside `policy`,
+/// oldest first, along with directories left empty. The most recent file is
+/// always kept since it is usually still being written to.
+pub fn prune_directory(dir: &Path, policy: &LogRetention) -> std::io::Result<PruneReport> {
+    let mut files = Vec::new();
+    collect_log_files(dir, &mut files)?;
+    // Newest first.
+    files.sort_by_key(|(_, modified, _)| std::cmp::Reverse(*modified));
+
+    let now = SystemTime::now();
+    let mut report = PruneReport::default();
+    let mut kept_bytes = 0u64;
+    for (index, (path, modified, len)) in files.into_iter().enumerate() {
+        let expired = policy
+            .max_age()
+            .is_some_and(|max_age| now.duration_since(modified).is_ok_and(|age| age > max_age));
+        let over_count = policy.max_files.is_some_and(|max| index >= max);
+        let over_size = policy
+            .max_bytes
+            .is_some_and(|max| kept_bytes.saturating_add(len) > max);
+
+        if index > 0 && (expired || over_count || over_size) {
+            std::fs::remove_file(&path)?;
+            report.files_removed += 1;
+            report.bytes_freed += len;
+        } else {
+            kept_bytes += len;
+        }
+    }
+
+    remove_empty_dirs(dir)?;
+    Ok(report)
+}
+
+fn collect_log_files(
+    dir: &Path,
+    files: &mut Vec<(PathBuf, SystemTime, u64)>,
+) -> std::io::Result<()> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err),
+    };
+    for entry in entries {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        let path = entry.path();
+        if metadata.is_dir() {
+            collect_log_files(&path, files)?;
+        } else if path.extension().is_some_and(|ext| ext == "log") {
+            files.push((path, metadata.modified()?, metadata.len()));
+        }
+    }
+    Ok(())
+}
+
+fn remove_empty_dirs(dir: &Path) -> std::io::Result<()> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Ok(());
+    };
+    for entry in entries {
+        let path = entry?.path();
+        if path.is_dir() {
+            remove_empty_dirs(&path)?;
+            // Fails harmlessly when the directory still has content.
+            let _ = std::fs::remove_dir(&path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_newest_files_within_budget() {
+        let dir = tempfile::tempdir().unwrap();
+        let day = dir.path().join("2024-01-01");
+        std::fs::create_dir_all(&day).unwrap();
+        for (index, name) in ["a.log", "b.log", "c.log"].iter().enumerate() {
+            let path = day.join(name);
+            std::fs::write(&path, vec![b'x'; 10]).unwrap();
+            let modified = SystemTime::now() - Duration::from_secs(100 * (3 - index as u64));
+            std::fs::File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(modified)
+                .unwrap();
+        }
+        std::fs::write(day.join("notes.txt"), "keep").unwrap();
+
+        let policy = LogRetention {
+            max_files: Some(2),
+            ..LogRetention::default()
+        };
+        let report = prune_directory(dir.path(), &policy).unwrap();
+        assert_eq!(report.files_removed, 1);
+        assert!(!day.join("a.log").exists());
+        assert!(day.join("c.log").exists());
+
+        let policy = LogRetention {
+            max_bytes: Some(5),
+            ..LogRetention::default()
+        };
+        let report = prune_directory(dir.path(), &policy).unwrap();
+        assert_eq!(report.bytes_freed, 10);
+        assert!(day.join("c.log").exists());
+        assert!(day.join("notes.txt").exists());
+    }
+}
@@ -0,0 +1,456 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use utoipa::ToSchema;
+
+use super::breaker::CircuitBreakers;
+use super::events::{EventBus, PluginEventKind};
+use super::gated;
+use super::manifest::{AvailableUpdate, ModelManifest};
+use super::mirrors::{self, HubEndpoints};
+use super::offline::OfflineMode;
+use super::PluginError;
+
+pub const HUGGING_FACE_URL: &str = "https://huggingface.co/";
+
+/// Response header carrying the commit a `resolve` download was served from.
+pub const REPO_COMMIT_HEADER: &str = "x-repo-commit";
+
+/// Default time between background update checks.
+const DEFAULT_CHECK_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Number of commits returned by a revision listing unless a limit is given.
+const DEFAULT_COMMIT_LIMIT: usize = 20;
+
+/// A branch or tag and the commit it currently points at.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct GitRef {
+    pub name: String,
+    #[serde(rename(deserialize = "targetCommit"))]
+    pub target_commit: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ModelCommit {
+    pub id: String,
+    pub title: String,
+    #[serde(default)]
+    pub date: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ModelUpdate {
+    pub model_id: String,
+    pub filename: String,
+    pub revision: String,
+    pub saved_path: String,
+    pub current_commit: Option<String>,
+    pub latest_commit: String,
+}
+
+/// A file of a model repository, as listed before picking one to download.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+pub struct RepoFile {
+    /// Path within the repository, usable as a download's `filename`.
+    pub path: String,
+    pub size: u64,
+    /// SHA-256 of files stored in LFS, usable as `expected_sha256`.
+    #[serde(default)]
+    pub sha256: Option<String>,
+}
+
+/// Builds `<hub>/<prefix...>/<model_id>/<tail...>` with every part escaped.
+pub fn repo_url(
+    hub: &reqwest::Url,
+    prefix: &[&str],
+    model_id: &str,
+    tail: &[&str],
+) -> Result<reqwest::Url, PluginError> {
+    let mut url = hub.clone();
+    {
+        let mut segments = url
+            .path_segments_mut()
+            .map_err(|_| PluginError::InvalidRequest("cannot modify URL segments".to_string()))?;
+        segments.pop_if_empty();
+        segments.extend(prefix);
+        segments.extend(model_id.split('/').filter(|segment| !segment.is_empty()));
+        segments.extend(tail);
+    }
+    Ok(url)
+}
+
+/// Whether `revision` is a full commit SHA rather than a branch or tag name.
+/// Downloads of such revisions are reproducible and verified on re-download.
+pub fn is_commit_sha(revision: &str) -> bool {
+    revision.len() == 40 && revision.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+#[derive(Deserialize)]
+struct RevisionInfo {
+    sha: String,
+}
+
+#[derive(Deserialize)]
+struct RepoRefs {
+    #[serde(default)]
+    branches: Vec<GitRef>,
+    #[serde(default)]
+    tags: Vec<GitRef>,
+}
+
+/// Branches and tags of a model repository.
+pub async fn list_refs(
+    client: &reqwest::Client,
+    hub: &reqwest::Url,
+    model_id: &str,
+) -> Result<(Vec<GitRef>, Vec<GitRef>), PluginError> {
+    let url = repo_url(hub, &["api", "models"], model_id, &["refs"])?;
+    let response = client.get(url).send().await?;
+    let refs: RepoRefs = gated::check(response, hub, model_id).await?.json().await?;
+    Ok((refs.branches, refs.tags))
+}
+
+/// Most recent commits reachable from `revision`, newest first.
+pub async fn list_commits(
+    client: &reqwest::Client,
+    hub: &reqwest::Url,
+    model_id: &str,
+    revision: &str,
+    limit: Option<usize>,
+) -> Result<Vec<ModelCommit>, PluginError> {
+    let url = repo_url(hub, &["api", "models"], model_id, &["commits", revision])?;
+    let response = client.get(url).send().await?;
+    let mut commits: Vec<ModelCommit> = gated::check(response, hub, model_id).await?.json().await?;
+    commits.truncate(limit.unwrap_or(DEFAULT_COMMIT_LIMIT));
+    Ok(commits)
+}
+
+#[derive(Deserialize)]
+struct PathInfo {
+    path: String,
+    #[serde(default)]
+    lfs: Option<LfsInfo>,
+}
+
+#[derive(Deserialize)]
+struct LfsInfo {
+    oid: String,
+}
+
+#[derive(Deserialize)]
+struct TreeEntry {
+    #[serde(rename = "type")]
+    kind: String,
+    path: String,
+    #[serde(default)]
+    size: u64,
+    #[serde(default)]
+    lfs: Option<LfsInfo>,
+}
+
+/// Every file of a model repository at `revision`, following the hub's
+/// pagination.
+pub async fn list_files(
+    client: &reqwest::Client,
+    hub: &reqwest::Url,
+    model_id: &str,
+    revision: &str,
+) -> Result<Vec<RepoFile>, PluginError> {
+    let mut url = repo_url(hub, &["api", "models"], model_id, &["tree", revision])?;
+    url.query_pairs_mut().append_pair("recursive", "true");
+    let mut files = Vec::new();
+    let mut next = Some(url);
+    while let Some(url) = next.take() {
+        let response = gated::check(client.get(url).send().await?, hub, model_id).await?;
+        next = next_page(response.headers());
+        let entries: Vec<TreeEntry> = response.json().await?;
+        files.extend(
+            entries
+                .into_iter()
+                .filter(|entry| entry.kind == "file")
+                .map(|entry| RepoFile {
+                    path: entry.path,
+                    size: entry.size,
+                    sha256: entry.lfs.map(|lfs| lfs.oid),
+                }),
+        );
+    }
+    Ok(files)
+}
+
+/// The `rel="next"` target of a `Link` header.
+fn next_page(headers: &reqwest::header::HeaderMap) -> Option<reqwest::Url> {
+    let link = headers.get(reqwest::header::LINK)?.to_str().ok()?;
+    link.split(',').find_map(|part| {
+        let (target, params) = part.split_once(';')?;
+        params
+            .split(';')
+            .any(|param| param.trim() == "rel=\"next\"")
+            .then(|| target.trim().trim_start_matches('<').trim_end_matches('>'))
+            .and_then(|target| reqwest::Url::parse(target).ok())
+    })
+}
+
+/// SHA-256 the hub lists for `filename` at `revision`. Only files stored
+/// in LFS, which model weights are, have one.
+pub async fn file_sha256(
+    client: &reqwest::Client,
+    hub: &reqwest::Url,
+    model_id: &str,
+    revision: &str,
+    filename: &str,
+    auth_token: Option<&str>,
+) -> Result<Option<String>, PluginError> {
+    let url = repo_url(hub, &["api", "models"], model_id, &["paths-info", revision])?;
+    let mut builder = client.post(url).form(&[("paths", filename)]);
+    if let Some(token) = auth_token {
+        builder = builder.bearer_auth(token);
+    }
+    let response = gated::check(builder.send().await?, hub, model_id).await?;
+    let paths: Vec<PathInfo> = response.json().await?;
+    Ok(paths
+        .into_iter()
+        .find(|info| info.path == filename)
+        .and_then(|info| info.lfs)
+        .map(|lfs| lfs.oid))
+}
+
+/// Returns the commit `revision` (a branch, tag or commit) currently points at.
+pub async fn latest_commit(
+    client: &reqwest::Client,
+    hub: &reqwest::Url,
+    model_id: &str,
+    revision: &str,
+) -> Result<String, PluginError> {
+    let url = repo_url(hub, &["api", "models"], model_id, &["revision", revision])?;
+    let response = client.get(url).send().await?;
+    let info: RevisionInfo = gated::check(response, hub, model_id).await?.json().await?;
+    Ok(info.sha)
+}
+
+/// Checks every tracked model for a newer upstream commit on its revision,
+/// records the result in the manifest and publishes newly found updates.
+/// Models pinned to a commit SHA never move and are skipped, and the check
+/// stops early while the circuits of every hub endpoint are open.
+pub async fn check_updates(
+    plugin_id: &str,
+    client: &reqwest::Client,
+    hubs: &HubEndpoints,
+    breakers: &CircuitBreakers,
+    manifest: &Mutex<ModelManifest>,
+    events: &EventBus,
+) -> Result<Vec<ModelUpdate>, PluginError> {
+    let tracked: Vec<_> = manifest
+        .lock()
+        .await
+        .records()
+        .iter()
+        .filter(|record| record.source_url.is_none() && !is_commit_sha(&record.revision))
+        .cloned()
+        .collect();
+
+    let mut latest = Vec::with_capacity(tracked.len());
+    for record in &tracked {
+        let commit = mirrors::failover(hubs, breakers, |hub| async move {
+            latest_commit(client, &hub, &record.model_id, &record.revision).await
+        });
+        match commit.await {
+            Ok(commit) => latest.push((record.saved_path.clone(), commit)),
+            Err(err @ PluginError::CircuitOpen { .. }) => {
+                tracing::debug!("skipping remaining update checks: {}", err);
+                break;
+            }
+            Err(err) => tracing::debug!(
+                "update check for {}/{} failed: {}",
+                record.model_id,
+                record.filename,
+                err
+            ),
+        }
+    }
+
+    let now = Utc::now();
+    let mut updates = Vec::new();
+    let mut manifest = manifest.lock().await;
+    for record in manifest.records_mut() {
+        let Some((_, commit)) = latest.iter().find(|(path, _)| *path == record.saved_path) else {
+            continue;
+        };
+        record.last_checked = Some(now);
+        if record.commit.as_deref() == Some(commit.as_str()) {
+            record.update = None;
+            continue;
+        }
+
+        let update = ModelUpdate {
+            model_id: record.model_id.clone(),
+            filename: record.filename.clone(),
+            revision: record.revision.clone(),
+            saved_path: record.saved_path.clone(),
+            current_commit: record.commit.clone(),
+            latest_commit: commit.clone(),
+        };
+        let already_known = record
+            .update
+            .as_ref()
+            .is_some_and(|known| known.commit == *commit);
+        if !already_known {
+            events.publish(
+                plugin_id,
+                PluginEventKind::ModelUpdateAvailable {
+                    update: update.clone(),
+                },
+            );
+        }
+        record.update = Some(AvailableUpdate {
+            commit: commit.clone(),
+            checked_at: now,
+        });
+        updates.push(update);
+    }
+    manifest.save().await?;
+    Ok(updates)
+}
+
+/// Updates found by earlier checks, for answering while offline.
+pub fn known_updates(manifest: &ModelManifest) -> Vec<ModelUpdate> {
+    manifest
+        .records()
+        .iter()
+        .filter_map(|record| {
+            let update = record.update.as_ref()?;
+            Some(ModelUpdate {
+                model_id: record.model_id.clone(),
+                filename: record.filename.clone(),
+                revision: record.revision.clone(),
+                saved_path: record.saved_path.clone(),
+                current_commit: record.commit.clone(),
+                latest_commit: update.commit.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Branches and tags of `model_id` as resolved by earlier downloads, for
+/// answering while offline. Branches and tags cannot be told apart here.
+pub fn known_refs(manifest: &ModelManifest, model_id: &str) -> Vec<GitRef> {
+    let mut refs: Vec<GitRef> = Vec::new();
+    for record in manifest.records() {
+        if record.model_id != model_id || is_commit_sha(&record.revision) {
+            continue;
+        }
+        let Some(commit) = &record.commit else {
+            continue;
+        };
+        if !refs.iter().any(|known| known.name == record.revision) {
+            refs.push(GitRef {
+                name: record.revision.clone(),
+                target_commit: commit.clone(),
+            });
+        }
+    }
+    refs
+}
+
+/// Interval from `GOOSE_PLUGIN_LLM_UPDATE_CHECK_SECS`; `0` disables background checks.
+pub fn check_interval() -> Option<Duration> {
+    match std::env::var("GOOSE_PLUGIN_LLM_UPDATE_CHECK_SECS") {
+        Ok(value) => match value.trim().parse::<u64>() {
+            Ok(0) => None,
+            Ok(secs) => Some(Duration::from_secs(secs)),
+            Err(_) => Some(DEFAULT_CHECK_INTERVAL),
+        },
+        Err(_) => Some(DEFAULT_CHECK_INTERVAL),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_update_checks(
+    plugin_id: String,
+    client: reqwest::Client,
+    hubs: HubEndpoints,
+    breakers: CircuitBreakers,
+    offline: OfflineMode,
+    manifest: Arc<Mutex<ModelManifest>>,
+    events: EventBus,
+    interval: Duration,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(interval).await;
+            if offline.is_enabled() {
+                continue;
+            }
+            let result = check_updates(&plugin_id, &client, &hubs, &breakers, &manifest, &events);
+            if let Err(err) = result.await {
+                tracing::warn!("model update check failed: {}", err);
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_escaped_repo_urls() {
+        let hub = reqwest::Url::parse(HUGGING_FACE_URL).unwrap();
+        let url = repo_url(
+            &hub,
+            &[],
+            "org/model",
+            &["resolve", "main", "model q4.gguf"],
+        )
+        .unwrap();
+        assert_eq!(
+            url.as_str(),
+            "https://huggingface.co/org/model/resolve/main/model%20q4.gguf"
+        );
+        let url = repo_url(&hub, &["api", "models"], "org/model", &["revision", "v1"]).unwrap();
+        assert_eq!(
+            url.as_str(),
+            "https://huggingface.co/api/models/org/model/revision/v1"
+        );
+        let mirror = reqwest::Url::parse("https://repo.internal/hf/").unwrap();
+        let url = repo_url(
+            &mirror,
+            &["api", "models"],
+            "org/model",
+            &["revision", "v1"],
+        )
+        .unwrap();
+        assert_eq!(
+            url.as_str(),
+            "https://repo.internal/hf/api/models/org/model/revision/v1"
+        );
+    }
+
+    #[test]
+    fn recognises_full_commit_shas() {
+        assert!(is_commit_sha("0123456789abcdef0123456789ABCDEF01234567"));
+        assert!(!is_commit_sha("main"));
+        assert!(!is_commit_sha("0123456"));
+        assert!(!is_commit_sha("g123456789abcdef0123456789abcdef01234567"));
+    }
+
+    #[test]
+    fn follows_next_page_links() {
+        let mut headers = reqwest::header::HeaderMap::new();
+        assert!(next_page(&headers).is_none());
+        headers.insert(
+            reqwest::header::LINK,
+            "<https://huggingface.co/api/models/org/model/tree/main?cursor=abc>; rel=\"next\""
+                .parse()
+                .unwrap(),
+        );
+        assert_eq!(
+            next_page(&headers).unwrap().as_str(),
+            "https://huggingface.co/api/models/org/model/tree/main?cursor=abc"
+        );
+    }
+}
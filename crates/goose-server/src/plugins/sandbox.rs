@@ -0,0 +1,135 @@
+use std::path::{Component, Path, PathBuf};
+
+use super::PluginError;
+
+/// Restricts plugin file writes to a set of approved root directories.
+#[derive(Debug, Clone)]
+pub struct PathSandbox {
+    roots: Vec<PathBuf>,
+}
+
+impl PathSandbox {
+    /// Creates a sandbox from the given roots. Roots that do not exist yet are
+    /// created so they can be canonicalized.
+    pub fn new(roots: impl IntoIterator<Item = PathBuf>) -> std::io::Result<Self> {
+        let mut canonical = Vec::new();
+        for root in roots {
+            std::fs::create_dir_all(&root)?;
+            let root = root.canonicalize()?;
+            if !canonical.contains(&root) {
+                canonical.push(root);
+            }
+        }
+        Ok(Self { roots: canonical })
+    }
+
+    /// Resolves `dir` to an absolute, canonical directory inside one of the roots.
+    /// The directory itself does not need to exist yet.
+    pub fn resolve_dir(&self, dir: &Path) -> Result<PathBuf, PluginError> {
+        let absolute = if dir.is_absolute() {
+            dir.to_path_buf()
+        } else {
+            std::env::current_dir()?.join(dir)
+        };
+        let resolved = canonicalize_lenient(&absolute)?;
+        self.ensure_contained(&resolved)?;
+        Ok(resolved)
+    }
+
+    /// Joins a user supplied relative file name onto an already resolved directory.
+    pub fn join_file(&self, dir: &Path, filename: &str) -> Result<PathBuf, PluginError> {
+        let relative = Path::new(filename);
+        let mut target = dir.to_path_buf();
+        for component in relative.components() {
+            match component {
+                Component::Normal(part) => target.push(part),
+                Component::CurDir => {}
+                _ => {
+                    return Err(PluginError::PathNotAllowed(format!(
+                        "filename must be a relative path without '..': {}",
+                        filename
+                    )))
+                }
+            }
+        }
+        if target == dir {
+            return Err(PluginError::InvalidRequest(
+                "filename does not name a file".to_string(),
+            ));
+        }
+        self.ensure_contained(&target)?;
+        Ok(target)
+    }
+
+    /// Re-checks an existing path after canonicalization, catching symlinks that
+    /// point outside of the sandbox.
+    pub fn verify_existing(&self, path: &Path) -> Result<(), PluginError> {
+        let canonical = path.canonicalize()?;
+        self.ensure_contained(&canonical)
+    }
+
+    fn ensure_contained(&self, path: &Path) -> Result<(), PluginError> {
+        if self.roots.iter().any(|root| path.starts_with(root)) {
+            Ok(())
+        } else {
+            Err(PluginError::PathNotAllowed(path.display().to_string()))
+        }
+    }
+}
+
+/// Canonicalizes the longest existing ancestor of `path` and lexically appends the
+/// remaining components, rejecting any `..` in the part that does not exist yet.
+fn canonicalize_lenient(path: &Path) -> Result<PathBuf, PluginError> {
+    let mut existing = path.to_path_buf();
+    let mut remainder = Vec::new();
+    while !existing.exists() {
+        match existing.file_name() {
+            Some(name) => {
+                remainder.push(name.to_os_string());
+                existing.pop();
+            }
+            None => {
+                return Err(PluginError::PathNotAllowed(path.display().to_string()));
+            }
+        }
+    }
+
+    let mut resolved = existing.canonicalize()?;
+    for part in remainder.into_iter().rev() {
+        if part == ".." || part == "." {
+            return Err(PluginError::PathNotAllowed(path.display().to_string()));
+        }
+        resolved.push(part);
+    }
+    Ok(resolved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_traversal_in_filename() {
+        let root = tempfile::tempdir().unwrap();
+        let sandbox = PathSandbox::new([root.path().to_path_buf()]).unwrap();
+        let dir = sandbox.resolve_dir(root.path()).unwrap();
+
+        assert!(sandbox.join_file(&dir, "model.gguf").is_ok());
+        assert!(sandbox.join_file(&dir, "sub/model.gguf").is_ok());
+        assert!(sandbox.join_file(&dir, "../model.gguf").is_err());
+        assert!(sandbox.join_file(&dir, "/etc/passwd").is_err());
+    }
+
+    #[test]
+    fn rejects_directories_outside_roots() {
+        let root = tempfile::tempdir().unwrap();
+        let other = tempfile::tempdir().unwrap();
+        let sandbox = PathSandbox::new([root.path().join("models")]).unwrap();
+
+        assert!(sandbox.resolve_dir(&root.path().join("models/new")).is_ok());
+        assert!(sandbox
+            .resolve_dir(&root.path().join("models/../escape"))
+            .is_err());
+        assert!(sandbox.resolve_dir(other.path()).is_err());
+    }
+}
@@ -0,0 +1,66 @@
+//! Downloads started later, such as large models fetched overnight, at a set
+//! time or the next time a cron expression matches.
+
+use chrono::{DateTime, Utc};
+use croner::Cron;
+
+use super::PluginError;
+
+/// When a download asked to start no earlier than `start_after` and, with a
+/// `schedule`, at a time the cron expression matches in UTC, starts. `None`
+/// when it can start right away.
+pub fn start_time(
+    start_after: Option<DateTime<Utc>>,
+    schedule: Option<&str>,
+    now: DateTime<Utc>,
+) -> Result<Option<DateTime<Utc>>, PluginError> {
+    let earliest = start_after.map_or(now, |start_after| start_after.max(now));
+    let start = match schedule
+        .map(str::trim)
+        .filter(|schedule| !schedule.is_empty())
+    {
+        Some(schedule) => {
+            let invalid = |err: croner::errors::CronError| {
+                PluginError::InvalidRequest(format!("invalid schedule {:?}: {}", schedule, err))
+            };
+            Cron::new(schedule)
+                .parse()
+                .map_err(invalid)?
+                .find_next_occurrence(&earliest, true)
+                .map_err(invalid)?
+        }
+        None => earliest,
+    };
+    Ok((start > now).then_some(start))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(time: &str) -> DateTime<Utc> {
+        time.parse().unwrap()
+    }
+
+    #[test]
+    fn starts_at_the_next_matching_time() {
+        let now = at("2026-10-17T15:30:00Z");
+        assert_eq!(start_time(None, None, now).unwrap(), None);
+        assert_eq!(
+            start_time(Some(at("2026-10-17T12:00:00Z")), None, now).unwrap(),
+            None
+        );
+        assert_eq!(
+            start_time(None, Some("0 2 * * *"), now).unwrap(),
+            Some(at("2026-10-18T02:00:00Z"))
+        );
+        assert_eq!(
+            start_time(Some(at("2026-10-20T00:00:00Z")), Some("0 2 * * *"), now).unwrap(),
+            Some(at("2026-10-20T02:00:00Z"))
+        );
+        assert!(matches!(
+            start_time(None, Some("at night"), now),
+            Err(PluginError::InvalidRequest(_))
+        ));
+    }
+}
@@ -0,0 +1,121 @@
+//! Model search on the hub, narrowed to models llmserver-rs can serve: GGUF
+//! text generation models and text-to-speech models.
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::{ModelSearchRequest, PluginError, PluginTaskType};
+
+/// Results returned when a search names no limit.
+const DEFAULT_LIMIT: usize = 20;
+
+/// Most results one search returns.
+const MAX_LIMIT: usize = 100;
+
+/// A repository found by a search, as the hub lists it.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct HubModel {
+    /// Repository id, usable as `model_id` of a download.
+    pub id: String,
+    #[serde(default)]
+    pub downloads: u64,
+    #[serde(default)]
+    pub likes: u64,
+    #[serde(default)]
+    pub pipeline_tag: Option<String>,
+    #[serde(default)]
+    pub library_name: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// The hub's pipeline tag for models of `task_type`, if it has one.
+fn pipeline_tag(task_type: &PluginTaskType) -> Option<&'static str> {
+    match task_type.as_str() {
+        "text" => Some("text-generation"),
+        "tts" => Some("text-to-speech"),
+        _ => None,
+    }
+}
+
+/// The search API URL of `hub` for `request`.
+pub fn search_url(
+    hub: &reqwest::Url,
+    request: &ModelSearchRequest,
+) -> Result<reqwest::Url, PluginError> {
+    let mut url = hub
+        .join("api/models")
+        .map_err(|err| PluginError::InvalidRequest(err.to_string()))?;
+    let library = match &request.library {
+        Some(library) => Some(library.trim()).filter(|library| !library.is_empty()),
+        None => (request.task_type == PluginTaskType::TEXT).then_some("gguf"),
+    };
+    let limit = request.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    {
+        let mut pairs = url.query_pairs_mut();
+        if let Some(query) = request.query.as_deref().map(str::trim) {
+            if !query.is_empty() {
+                pairs.append_pair("search", query);
+            }
+        }
+        if let Some(library) = library {
+            pairs.append_pair("filter", library);
+        }
+        if let Some(tag) = pipeline_tag(&request.task_type) {
+            pairs.append_pair("pipeline_tag", tag);
+        }
+        pairs
+            .append_pair("sort", "downloads")
+            .append_pair("direction", "-1")
+            .append_pair("limit", &limit.to_string());
+    }
+    Ok(url)
+}
+
+pub async fn search(
+    client: &reqwest::Client,
+    hub: &reqwest::Url,
+    request: &ModelSearchRequest,
+) -> Result<Vec<HubModel>, PluginError> {
+    let url = search_url(hub, request)?;
+    Ok(client
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(task_type: PluginTaskType, library: Option<&str>) -> ModelSearchRequest {
+        ModelSearchRequest {
+            query: Some(" llama 3 ".to_string()),
+            task_type,
+            library: library.map(str::to_string),
+            limit: Some(500),
+        }
+    }
+
+    #[test]
+    fn narrows_searches_to_servable_models() {
+        let hub = reqwest::Url::parse("https://hf-mirror.com/").unwrap();
+        let text = search_url(&hub, &request(PluginTaskType::TEXT, None)).unwrap();
+        assert_eq!(
+            text.as_str(),
+            "https://hf-mirror.com/api/models?search=llama+3&filter=gguf\
+             &pipeline_tag=text-generation&sort=downloads&direction=-1&limit=100"
+        );
+
+        let tts = search_url(&hub, &request(PluginTaskType::TTS, Some(""))).unwrap();
+        assert_eq!(
+            tts.query(),
+            Some(
+                "search=llama+3&pipeline_tag=text-to-speech&sort=downloads&direction=-1&limit=100"
+            )
+        );
+    }
+}
@@ -0,0 +1,99 @@
+//! Per-plugin configuration set through the API. It is persisted as JSON so
+//! it survives restarts, and handed to plugins through
+//! [`ServerPlugin::configure`] whenever they are registered or reloaded.
+//!
+//! [`ServerPlugin::configure`]: super::ServerPlugin::configure
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use goose::config::paths::Paths;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tokio::fs;
+use utoipa::ToSchema;
+
+use super::PluginError;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema, PartialEq)]
+pub struct PluginConfig {
+    /// Settings by key. Which keys exist is up to the plugin.
+    #[serde(default)]
+    #[schema(value_type = Object)]
+    pub values: Map<String, Value>,
+}
+
+/// Configurations by plugin id. The default store keeps them in memory only.
+#[derive(Debug, Default)]
+pub struct PluginConfigStore {
+    path: Option<PathBuf>,
+    configs: HashMap<String, PluginConfig>,
+}
+
+impl PluginConfigStore {
+    /// `GOOSE_PLUGIN_CONFIG_PATH`, or `plugin_config.json` in goose's config
+    /// directory.
+    pub fn default_path() -> PathBuf {
+        std::env::var_os("GOOSE_PLUGIN_CONFIG_PATH")
+            .filter(|path| !path.is_empty())
+            .map(PathBuf::from)
+            .unwrap_or_else(|| Paths::config_dir().join("plugin_config.json"))
+    }
+
+    /// Reads the store at `path`, which need not exist yet.
+    pub async fn load(path: &Path) -> Result<Self, PluginError> {
+        let configs = match fs::read(path).await {
+            Ok(bytes) => serde_json::from_slice(&bytes).map_err(|err| {
+                PluginError::Internal(format!("invalid {}: {}", path.display(), err))
+            })?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(err) => return Err(err.into()),
+        };
+        Ok(Self {
+            path: Some(path.to_path_buf()),
+            configs,
+        })
+    }
+
+    pub fn get(&self, plugin_id: &str) -> Option<&PluginConfig> {
+        self.configs.get(plugin_id)
+    }
+
+    /// Stores the configuration and writes the store atomically.
+    pub async fn set(&mut self, plugin_id: &str, config: PluginConfig) -> Result<(), PluginError> {
+        self.configs.insert(plugin_id.to_string(), config);
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let json = serde_json::to_vec_pretty(&self.configs)
+            .map_err(|err| PluginError::Internal(err.to_string()))?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, json).await?;
+        fs::rename(&tmp, path).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn persists_configs() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join("plugin_config.json");
+        let mut store = PluginConfigStore::load(&path).await.unwrap();
+        assert!(store.get("llmserver-rs").is_none());
+
+        let config: PluginConfig =
+            serde_json::from_value(json!({"values": {"binary": "/opt/llmserver"}})).unwrap();
+        store.set("llmserver-rs", config.clone()).await.unwrap();
+
+        let reloaded = PluginConfigStore::load(&path).await.unwrap();
+        assert_eq!(reloaded.get("llmserver-rs"), Some(&config));
+    }
+}
@@ -0,0 +1,51 @@
+//! Companion files llmserver-rs loads next to a model's weights, such as its
+//! config, tokenizer and vision projector, fetched along with the model so
+//! they need not be downloaded one by one.
+
+/// Config, tokenizer and vocabulary files downloaded along with a model.
+const SIDECAR_NAMES: &[&str] = &[
+    "config.json",
+    "generation_config.json",
+    "merges.txt",
+    "special_tokens_map.json",
+    "tokenizer.json",
+    "tokenizer.model",
+    "tokenizer_config.json",
+    "vocab.json",
+    "vocab.txt",
+];
+
+/// Whether the repository file `path` is a companion of the model file
+/// `model`: a known config, tokenizer or vocabulary file, or a multimodal
+/// projector such as `mmproj-model-f16.gguf`, in the model's directory or at
+/// the root of the repository.
+pub fn is_sidecar(path: &str, model: &str) -> bool {
+    let (dir, name) = split(path);
+    if path == model || !(dir.is_empty() || dir == split(model).0) {
+        return false;
+    }
+    let name = name.to_ascii_lowercase();
+    SIDECAR_NAMES.contains(&name.as_str()) || (name.contains("mmproj") && name.ends_with(".gguf"))
+}
+
+fn split(path: &str) -> (&str, &str) {
+    path.rsplit_once('/').unwrap_or(("", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picks_companions_next_to_the_model() {
+        let model = "gguf/model-Q4_K_M.gguf";
+        assert!(is_sidecar("tokenizer.json", model));
+        assert!(is_sidecar("gguf/config.json", model));
+        assert!(is_sidecar("gguf/mmproj-model-f16.gguf", model));
+        assert!(is_sidecar("Vocab.txt", model));
+        assert!(!is_sidecar("onnx/tokenizer.json", model));
+        assert!(!is_sidecar("gguf/model-Q8_0.gguf", model));
+        assert!(!is_sidecar("README.md", model));
+        assert!(!is_sidecar("mmproj.gguf", "mmproj.gguf"));
+    }
+}
@@ -0,0 +1,83 @@
+use serde::{Deserialize, Serialize};
+use tokio::process::Child;
+use utoipa::ToSchema;
+
+use super::PluginError;
+
+/// Signals that can be delivered to a managed process. Runtimes commonly reload
+/// their configuration or adapters on `SIGHUP`/`SIGUSR1`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum ServiceSignal {
+    Sighup,
+    Sigint,
+    Sigquit,
+    Sigterm,
+    Sigkill,
+    Sigusr1,
+    Sigusr2,
+}
+
+impl std::fmt::Display for ServiceSignal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ServiceSignal::Sighup => "SIGHUP",
+            ServiceSignal::Sigint => "SIGINT",
+            ServiceSignal::Sigquit => "SIGQUIT",
+            ServiceSignal::Sigterm => "SIGTERM",
+            ServiceSignal::Sigkill => "SIGKILL",
+            ServiceSignal::Sigusr1 => "SIGUSR1",
+            ServiceSignal::Sigusr2 => "SIGUSR2",
+        };
+        f.write_str(name)
+    }
+}
+
+#[cfg(unix)]
+impl ServiceSignal {
+    fn as_raw(self) -> libc::c_int {
+        match self {
+            ServiceSignal::Sighup => libc::SIGHUP,
+            ServiceSignal::Sigint => libc::SIGINT,
+            ServiceSignal::Sigquit => libc::SIGQUIT,
+            ServiceSignal::Sigterm => libc::SIGTERM,
+            ServiceSignal::Sigkill => libc::SIGKILL,
+            ServiceSignal::Sigusr1 => libc::SIGUSR1,
+            ServiceSignal::Sigusr2 => libc::SIGUSR2,
+        }
+    }
+}
+
+#[cfg(unix)]
+pub fn send(child: &mut Child, signal: ServiceSignal) -> Result<(), PluginError> {
+    let pid = child
+        .id()
+        .ok_or_else(|| PluginError::InvalidRequest("process has already exited".to_string()))?;
+    // SAFETY: kill takes plain integers; the pid belongs to a child that has not
+    // been reaped yet, so it cannot have been reused.
+    if unsafe { libc::kill(pid as libc::pid_t, signal.as_raw()) } != 0 {
+        return Err(PluginError::Io(std::io::Error::last_os_error()));
+    }
+    Ok(())
+}
+
+/// Windows has no signals for detached processes; the termination signals map
+/// to `TerminateProcess`, everything else is rejected.
+#[cfg(windows)]
+pub fn send(child: &mut Child, signal: ServiceSignal) -> Result<(), PluginError> {
+    match signal {
+        ServiceSignal::Sigint
+        | ServiceSignal::Sigquit
+        | ServiceSignal::Sigterm
+        | ServiceSignal::Sigkill => Ok(child.start_kill()?),
+        other => Err(PluginError::InvalidRequest(format!(
+            "{} has no equivalent on Windows",
+            other
+        ))),
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn send(_child: &mut Child, _signal: ServiceSignal) -> Result<(), PluginError> {
+    Err(PluginError::UnsupportedOperation)
+}
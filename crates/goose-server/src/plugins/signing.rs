@@ -0,0 +1,255 @@
+//! Signatures of plugin binaries, checked before executables are started and
+//! libraries are loaded. Binaries are signed with [minisign], the signature
+//! next to the binary:
+//!
+//! ```sh
+//! minisign -Sm plugins.d/piper/piper-linux-x86_64   # writes piper-linux-x86_64.minisig
+//! ```
+//!
+//! The trust root is a file of minisign public keys, one per line, read from
+//! `GOOSE_PLUGIN_TRUSTED_KEYS` or `plugin_trusted_keys.pub` in goose's config
+//! directory. Once it holds a key, executable and library plugins without a
+//! valid signature from one of its keys are refused; without it, signatures
+//! are not checked. Remote plugins run elsewhere and are not affected.
+//!
+//! [minisign]: https://jedisct1.github.io/minisign/
+
+use std::ffi::OsString;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use goose::config::paths::Paths;
+use minisign_verify::{PublicKey, Signature};
+use serde::Serialize;
+use tokio::fs;
+use utoipa::ToSchema;
+
+use super::PluginError;
+
+/// A plugin binary's verified signature.
+#[derive(Debug, Clone, Serialize, ToSchema, PartialEq)]
+pub struct PluginSignature {
+    /// Id of the trusted key that signed the binary, as minisign prints it.
+    pub key_id: String,
+    /// Signed comment, such as when and as which file the binary was signed.
+    pub trusted_comment: String,
+}
+
+struct TrustedKey {
+    id: String,
+    key: PublicKey,
+}
+
+#[derive(Default)]
+pub struct TrustRoot {
+    keys: Vec<TrustedKey>,
+}
+
+impl TrustRoot {
+    /// `GOOSE_PLUGIN_TRUSTED_KEYS`, or `plugin_trusted_keys.pub` in goose's
+    /// config directory.
+    pub fn default_path() -> PathBuf {
+        std::env::var_os("GOOSE_PLUGIN_TRUSTED_KEYS")
+            .filter(|path| !path.is_empty())
+            .map(PathBuf::from)
+            .unwrap_or_else(|| Paths::config_dir().join("plugin_trusted_keys.pub"))
+    }
+
+    /// Reads the keys at `path`; there are none when it does not exist.
+    pub async fn load(path: &Path) -> Result<Self, PluginError> {
+        match fs::read_to_string(path).await {
+            Ok(text) => Self::parse(&text).map_err(|err| {
+                PluginError::Internal(format!("invalid {}: {}", path.display(), err))
+            }),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Public keys as `minisign -G` writes them, one per line. Comment lines
+    /// and blank lines are skipped.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut keys = Vec::new();
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') || line.starts_with("untrusted comment:") {
+                continue;
+            }
+            let key = PublicKey::from_base64(line)
+                .map_err(|err| format!("{} is not a minisign public key: {}", line, err))?;
+            keys.push(TrustedKey {
+                id: key_id(line).unwrap_or_default(),
+                key,
+            });
+        }
+        Ok(Self { keys })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Checks `<binary>.minisig` against the trusted keys. `None` when there
+    /// are no trusted keys, so nothing was checked.
+    pub async fn verify(&self, binary: &Path) -> Result<Option<PluginSignature>, PluginError> {
+        if self.is_empty() {
+            return Ok(None);
+        }
+        let mut signature_path = OsString::from(binary.as_os_str());
+        signature_path.push(".minisig");
+        let signature_path = PathBuf::from(signature_path);
+        let signature = match fs::read_to_string(&signature_path).await {
+            Ok(text) => Signature::decode(&text).map_err(|err| {
+                PluginError::Untrusted(format!(
+                    "{} is not a minisign signature: {}",
+                    signature_path.display(),
+                    err
+                ))
+            })?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                return Err(PluginError::Untrusted(format!(
+                    "{} is not signed; {} does not exist",
+                    binary.display(),
+                    signature_path.display()
+                )))
+            }
+            Err(err) => return Err(err.into()),
+        };
+        let contents = fs::read(binary).await?;
+        self.keys
+            .iter()
+            .find(|trusted| trusted.key.verify(&contents, &signature, false).is_ok())
+            .map(|trusted| {
+                Some(PluginSignature {
+                    key_id: trusted.id.clone(),
+                    trusted_comment: signature.trusted_comment().to_string(),
+                })
+            })
+            .ok_or_else(|| {
+                PluginError::Untrusted(format!(
+                    "{} is not signed by a trusted key",
+                    binary.display()
+                ))
+            })
+    }
+
+    /// Checks the file at `path` against `signature`, the text of a
+    /// detached minisign signature. The file is read in chunks, so only
+    /// prehashed signatures, which minisign makes by default, are accepted.
+    /// Blocks while the file is read.
+    pub fn verify_file(&self, path: &Path, signature: &str) -> Result<PluginSignature, String> {
+        let signature = Signature::decode(signature)
+            .map_err(|err| format!("not a minisign signature: {}", err))?;
+        for trusted in &self.keys {
+            let Ok(mut verifier) = trusted.key.verify_stream(&signature) else {
+                continue;
+            };
+            let mut file = std::fs::File::open(path).map_err(|err| err.to_string())?;
+            let mut buffer = vec![0; 1 << 20];
+            loop {
+                let read = file.read(&mut buffer).map_err(|err| err.to_string())?;
+                if read == 0 {
+                    break;
+                }
+                verifier.update(&buffer[..read]);
+            }
+            if verifier.finalize().is_ok() {
+                return Ok(PluginSignature {
+                    key_id: trusted.id.clone(),
+                    trusted_comment: signature.trusted_comment().to_string(),
+                });
+            }
+        }
+        Err("not signed by a trusted key".to_string())
+    }
+
+    /// Like [`verify`](Self::verify), for the command of an executable
+    /// plugin. Bare command names are looked up on `PATH`.
+    pub async fn verify_command(
+        &self,
+        command: &str,
+    ) -> Result<Option<PluginSignature>, PluginError> {
+        if self.is_empty() {
+            return Ok(None);
+        }
+        let path = resolve_command(command).ok_or_else(|| {
+            PluginError::BinaryMissing(format!("{} not found to check its signature", command))
+        })?;
+        self.verify(&path).await
+    }
+}
+
+/// The key id minisign prints: the id bytes of the key, little endian.
+fn key_id(public_key: &str) -> Option<String> {
+    let bytes = BASE64.decode(public_key).ok()?;
+    let id = bytes.get(2..10)?;
+    Some(
+        id.iter()
+            .rev()
+            .map(|byte| format!("{:02X}", byte))
+            .collect(),
+    )
+}
+
+fn resolve_command(command: &str) -> Option<PathBuf> {
+    let path = Path::new(command);
+    if path.components().count() > 1 {
+        return Some(path.to_path_buf());
+    }
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(command))
+        .find(|candidate| candidate.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUBLIC_KEY: &str = "RWQBI0VniavN7wOhB7/zzhC+HXDdGOdLwJln5NYwm6UNXx3chmQSVTG4";
+    const BINARY: &[u8] = b"#!/bin/sh\nexec cat\n";
+    const SIGNATURE: &str = "untrusted comment: signature from minisign secret key
+RUQBI0VniavN741TQgAfRcCsKdBP5QECebD8jX/jCHWibIK4CRPC4soEPLYzSoaSprToLLgbUiH7FFMRUO6lo4fXDLaWDQd7KwA=
+trusted comment: timestamp:1760000000\tfile:echo-plugin\thashed
+96i2CMSveLHDuw6H5yXbaBTZ8OP/GYOi4ffgEyAIMlx4/HSGVO7yEKyXZqrJTKC5KBJlfTle6AOMAGsV8EY/BQ==
+";
+
+    #[tokio::test]
+    async fn verifies_against_trusted_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let binary = dir.path().join("echo-plugin");
+        std::fs::write(&binary, BINARY).unwrap();
+
+        let unchecked = TrustRoot::load(&dir.path().join("missing.pub"))
+            .await
+            .unwrap();
+        assert_eq!(unchecked.verify(&binary).await.unwrap(), None);
+
+        let keys = format!("untrusted comment: minisign public key\n{}\n", PUBLIC_KEY);
+        let trust = TrustRoot::parse(&keys).unwrap();
+        assert!(matches!(
+            trust.verify(&binary).await,
+            Err(PluginError::Untrusted(_))
+        ));
+
+        std::fs::write(dir.path().join("echo-plugin.minisig"), SIGNATURE).unwrap();
+        let signature = trust.verify(&binary).await.unwrap().unwrap();
+        assert_eq!(signature.key_id, "EFCDAB8967452301");
+        assert!(signature.trusted_comment.contains("file:echo-plugin"));
+        assert_eq!(
+            trust
+                .verify_command(&binary.to_string_lossy())
+                .await
+                .unwrap(),
+            Some(signature.clone())
+        );
+        assert_eq!(trust.verify_file(&binary, SIGNATURE), Ok(signature));
+
+        std::fs::write(&binary, b"#!/bin/sh\nexec rm -rf ~\n").unwrap();
+        assert!(trust.verify_file(&binary, SIGNATURE).is_err());
+        assert!(matches!(
+            trust.verify(&binary).await,
+            Err(PluginError::Untrusted(_))
+        ));
+        assert!(TrustRoot::parse("not a key").is_err());
+    }
+}
@@ -0,0 +1,69 @@
+//! Downloads of a whole model repository, for models that need their config
+//! and tokenizer files next to the weights. Like `huggingface-cli download`,
+//! files are picked with glob patterns in which `*` also matches `/`.
+
+use glob::Pattern;
+
+use super::PluginError;
+
+/// Which files of a repository a snapshot downloads.
+#[derive(Debug, Default)]
+pub struct SnapshotFilter {
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+}
+
+impl SnapshotFilter {
+    /// Without `include` patterns every file not excluded is downloaded.
+    pub fn new(include: &[String], exclude: &[String]) -> Result<Self, PluginError> {
+        let compile = |patterns: &[String]| {
+            patterns
+                .iter()
+                .map(|pattern| {
+                    Pattern::new(pattern).map_err(|err| {
+                        PluginError::InvalidRequest(format!(
+                            "invalid file pattern {:?}: {}",
+                            pattern, err
+                        ))
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()
+        };
+        Ok(Self {
+            include: compile(include)?,
+            exclude: compile(exclude)?,
+        })
+    }
+
+    pub fn matches(&self, path: &str) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|pattern| pattern.matches(path)))
+            && !self.exclude.iter().any(|pattern| pattern.matches(path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patterns(list: &[&str]) -> Vec<String> {
+        list.iter().map(|pattern| pattern.to_string()).collect()
+    }
+
+    #[test]
+    fn includes_then_excludes() {
+        let all = SnapshotFilter::new(&[], &[]).unwrap();
+        assert!(all.matches("onnx/model.onnx"));
+
+        let filter = SnapshotFilter::new(
+            &patterns(&["*.json", "*Q4_K_M.gguf"]),
+            &patterns(&["onnx/*"]),
+        )
+        .unwrap();
+        assert!(filter.matches("config.json"));
+        assert!(filter.matches("quants/model-Q4_K_M.gguf"));
+        assert!(!filter.matches("model-Q8_0.gguf"));
+        assert!(!filter.matches("onnx/config.json"));
+
+        assert!(SnapshotFilter::new(&patterns(&["[*.json"]), &[]).is_err());
+    }
+}
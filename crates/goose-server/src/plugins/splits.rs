@@ -0,0 +1,80 @@
+//! Models published as GGUF splits, `<name>-00001-of-00004.gguf` and so on.
+//! llama.cpp loads such a model from its first part as long as the others
+//! sit next to it.
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// What a download of a split model leaves on disk.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum GgufSplits {
+    /// The parts as published; services are started from the first one.
+    #[default]
+    Keep,
+    /// One `<name>.gguf` with the parts appended in order, for models split
+    /// into plain byte ranges.
+    Concatenate,
+}
+
+/// A file name recognized as one part of a split model.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SplitName {
+    /// Everything before `-00001-of-00004.gguf`, directories included.
+    prefix: String,
+    count: u32,
+}
+
+impl SplitName {
+    pub fn parse(filename: &str) -> Option<Self> {
+        let stem = filename.strip_suffix(".gguf")?;
+        let (rest, count) = stem.rsplit_once("-of-")?;
+        let (prefix, index) = rest.rsplit_once('-')?;
+        let digits = |part: &str| part.len() == 5 && part.bytes().all(|b| b.is_ascii_digit());
+        if prefix.is_empty() || !digits(index) || !digits(count) {
+            return None;
+        }
+        let index: u32 = index.parse().ok()?;
+        let count: u32 = count.parse().ok()?;
+        (1..=count).contains(&index).then(|| Self {
+            prefix: prefix.to_string(),
+            count,
+        })
+    }
+
+    /// Names of every part, first to last.
+    pub fn parts(&self) -> Vec<String> {
+        (1..=self.count)
+            .map(|index| format!("{}-{:05}-of-{:05}.gguf", self.prefix, index, self.count))
+            .collect()
+    }
+
+    /// Name of the file the parts are concatenated into.
+    pub fn merged(&self) -> String {
+        format!("{}.gguf", self.prefix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognizes_any_part_of_a_split() {
+        let split = SplitName::parse("Q4_K_M/model-Q4_K_M-00002-of-00003.gguf").unwrap();
+        assert_eq!(
+            split.parts(),
+            [
+                "Q4_K_M/model-Q4_K_M-00001-of-00003.gguf",
+                "Q4_K_M/model-Q4_K_M-00002-of-00003.gguf",
+                "Q4_K_M/model-Q4_K_M-00003-of-00003.gguf",
+            ]
+        );
+        assert_eq!(split.merged(), "Q4_K_M/model-Q4_K_M.gguf");
+
+        assert!(SplitName::parse("model-Q4_K_M.gguf").is_none());
+        assert!(SplitName::parse("model-00004-of-00003.gguf").is_none());
+        assert!(SplitName::parse("model-1-of-3.gguf").is_none());
+        assert!(SplitName::parse("-00001-of-00003.gguf").is_none());
+    }
+}
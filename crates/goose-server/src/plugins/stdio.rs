@@ -0,0 +1,37 @@
+use std::process::Stdio;
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// What happens to one output stream of a managed process.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StdioMode {
+    /// Write straight to goosed's own stdout/stderr.
+    Inherit,
+    /// Drop the output.
+    Discard,
+    /// Capture into the service log buffer.
+    #[default]
+    Log,
+    /// Capture like `log` and also publish each line on the plugin event stream.
+    Events,
+}
+
+impl StdioMode {
+    pub fn to_stdio(self) -> Stdio {
+        match self {
+            StdioMode::Inherit => Stdio::inherit(),
+            StdioMode::Discard => Stdio::null(),
+            StdioMode::Log | StdioMode::Events => Stdio::piped(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+pub struct StdioConfig {
+    #[serde(default)]
+    pub stdout: StdioMode,
+    #[serde(default)]
+    pub stderr: StdioMode,
+}
@@ -0,0 +1,107 @@
+//! Bandwidth limits for model downloads, so transfers leave room for the
+//! inference traffic served from the same host.
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+/// How far a transfer that was idle may run ahead of its rate.
+const BURST: Duration = Duration::from_secs(1);
+
+/// Paces every transfer sharing it to one rate.
+#[derive(Debug, Default)]
+pub struct Throttle {
+    next: Mutex<Option<Instant>>,
+}
+
+impl Throttle {
+    /// Books `bytes` at `bytes_per_sec` after what was booked before,
+    /// returning when the transfer may continue.
+    pub fn reserve(&self, bytes: u64, bytes_per_sec: u64) -> Instant {
+        let now = Instant::now();
+        let earliest = now.checked_sub(BURST).unwrap_or(now);
+        let mut next = self.next.lock().unwrap_or_else(|e| e.into_inner());
+        let start = next.map_or(earliest, |next| next.max(earliest));
+        let until = start + Duration::from_secs_f64(bytes as f64 / bytes_per_sec as f64);
+        *next = Some(until);
+        until
+    }
+}
+
+/// The limits one download is paced by: those shared by all downloads of a
+/// plugin and its own. A rate of 0 means unlimited.
+#[derive(Debug, Default)]
+pub struct DownloadThrottle {
+    global: Option<(Arc<Throttle>, u64)>,
+    own: Option<(Throttle, u64)>,
+}
+
+impl DownloadThrottle {
+    pub fn new(global: &Arc<Throttle>, global_rate: Option<u64>, own_rate: Option<u64>) -> Self {
+        Self {
+            global: global_rate
+                .filter(|rate| *rate > 0)
+                .map(|rate| (global.clone(), rate)),
+            own: own_rate
+                .filter(|rate| *rate > 0)
+                .map(|rate| (Throttle::default(), rate)),
+        }
+    }
+
+    /// Books `bytes` more, returning until when the transfer has to pause,
+    /// if it has to.
+    pub fn pace(&self, bytes: u64) -> Option<Instant> {
+        let global = self
+            .global
+            .as_ref()
+            .map(|(throttle, rate)| throttle.reserve(bytes, *rate));
+        let own = self
+            .own
+            .as_ref()
+            .map(|(throttle, rate)| throttle.reserve(bytes, *rate));
+        global
+            .into_iter()
+            .chain(own)
+            .max()
+            .filter(|until| *until > Instant::now())
+    }
+
+    /// Pauses as long as [`pace`](Self::pace) says.
+    pub async fn wait(&self, bytes: u64) {
+        if let Some(until) = self.pace(bytes) {
+            tokio::time::sleep_until(until).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pause(throttle: &DownloadThrottle, bytes: u64) -> Duration {
+        throttle
+            .pace(bytes)
+            .map(|until| until - Instant::now())
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn paces_to_the_slowest_limit() {
+        let global = Arc::new(Throttle::default());
+        assert!(DownloadThrottle::new(&global, None, Some(0))
+            .pace(u64::MAX)
+            .is_none());
+
+        let throttle = DownloadThrottle::new(&global, Some(1000), Some(500));
+        // The first second's worth passes as a burst.
+        assert_eq!(pause(&throttle, 500), Duration::ZERO);
+        let own = pause(&throttle, 1000);
+        assert!(own > Duration::from_millis(1900) && own <= Duration::from_secs(2));
+
+        // Other downloads share the global limit, booked 0.5s ahead by now.
+        let other = DownloadThrottle::new(&global, Some(1000), None);
+        let shared = pause(&other, 1000);
+        assert!(shared > Duration::from_millis(1400) && shared <= Duration::from_millis(1500));
+    }
+}
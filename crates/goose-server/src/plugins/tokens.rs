@@ -0,0 +1,153 @@
+//! Hub access tokens, checked against the `whoami` API before they are
+//! used for a download that would otherwise fail only once it is refused.
+
+use reqwest::{StatusCode, Url};
+use serde::Deserialize;
+
+use super::{PluginError, ValidateTokenResponse};
+
+#[derive(Deserialize)]
+struct WhoAmI {
+    name: String,
+    #[serde(default)]
+    auth: Option<AuthInfo>,
+}
+
+#[derive(Deserialize)]
+struct AuthInfo {
+    #[serde(rename = "accessToken", default)]
+    access_token: Option<AccessToken>,
+}
+
+#[derive(Deserialize)]
+struct AccessToken {
+    #[serde(rename = "displayName", default)]
+    display_name: Option<String>,
+    #[serde(default)]
+    role: Option<String>,
+    #[serde(rename = "fineGrained", default)]
+    fine_grained: Option<FineGrained>,
+}
+
+#[derive(Deserialize)]
+struct FineGrained {
+    #[serde(default)]
+    global: Vec<String>,
+    #[serde(default)]
+    scoped: Vec<ScopedPermissions>,
+}
+
+#[derive(Deserialize)]
+struct ScopedPermissions {
+    entity: Entity,
+    #[serde(default)]
+    permissions: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct Entity {
+    name: String,
+}
+
+impl From<WhoAmI> for ValidateTokenResponse {
+    fn from(whoami: WhoAmI) -> Self {
+        let token = whoami.auth.and_then(|auth| auth.access_token);
+        let (token_name, role, fine_grained) = match token {
+            Some(token) => (token.display_name, token.role, token.fine_grained),
+            None => (None, None, None),
+        };
+        // Read and write tokens carry their role as their only scope;
+        // fine-grained ones list permissions, per repository when scoped.
+        let scopes = match fine_grained {
+            Some(fine_grained) => fine_grained
+                .global
+                .into_iter()
+                .chain(fine_grained.scoped.into_iter().flat_map(|scoped| {
+                    let entity = scoped.entity.name;
+                    scoped
+                        .permissions
+                        .into_iter()
+                        .map(move |permission| format!("{}:{}", entity, permission))
+                }))
+                .collect(),
+            None => role.iter().cloned().collect(),
+        };
+        ValidateTokenResponse {
+            valid: true,
+            name: Some(whoami.name),
+            token_name,
+            role,
+            scopes,
+        }
+    }
+}
+
+/// Asks `hub` who `token` belongs to. A token the hub refuses is reported
+/// as invalid rather than as an error.
+pub async fn validate(
+    client: &reqwest::Client,
+    hub: &Url,
+    token: &str,
+) -> Result<ValidateTokenResponse, PluginError> {
+    let url = hub
+        .join("api/whoami-v2")
+        .map_err(|err| PluginError::InvalidRequest(err.to_string()))?;
+    let response = client.get(url).bearer_auth(token).send().await?;
+    if response.status() == StatusCode::UNAUTHORIZED {
+        return Ok(ValidateTokenResponse {
+            valid: false,
+            name: None,
+            token_name: None,
+            role: None,
+            scopes: Vec::new(),
+        });
+    }
+    let whoami: WhoAmI = response.error_for_status()?.json().await?;
+    Ok(whoami.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_scopes_of_fine_grained_tokens() {
+        let whoami: WhoAmI = serde_json::from_value(serde_json::json!({
+            "type": "user",
+            "name": "alice",
+            "auth": {
+                "type": "access_token",
+                "accessToken": {
+                    "displayName": "goose",
+                    "role": "fineGrained",
+                    "fineGrained": {
+                        "global": ["discussion.write"],
+                        "scoped": [{
+                            "entity": { "type": "model", "name": "meta-llama/Llama-3.1-8B" },
+                            "permissions": ["repo.content.read"]
+                        }]
+                    }
+                }
+            }
+        }))
+        .unwrap();
+        let response = ValidateTokenResponse::from(whoami);
+        assert!(response.valid);
+        assert_eq!(response.name.as_deref(), Some("alice"));
+        assert_eq!(response.token_name.as_deref(), Some("goose"));
+        assert_eq!(
+            response.scopes,
+            [
+                "discussion.write",
+                "meta-llama/Llama-3.1-8B:repo.content.read"
+            ]
+        );
+
+        let read: WhoAmI = serde_json::from_value(serde_json::json!({
+            "name": "bob",
+            "auth": { "accessToken": { "displayName": "ci", "role": "read" } }
+        }))
+        .unwrap();
+        assert_eq!(ValidateTokenResponse::from(read).scopes, ["read"]);
+    }
+}
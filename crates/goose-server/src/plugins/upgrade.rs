@@ -0,0 +1,103 @@
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Request sent to a freshly started instance before traffic is moved to it.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SmokeTestConfig {
+    /// Completion endpoint of the new instance, e.g. `http://127.0.0.1:8081/v1/completions`.
+    pub url: String,
+    #[serde(default = "default_prompt")]
+    pub prompt: String,
+    /// Full JSON body to send instead of the default `{"prompt": ..., "max_tokens": 16}`.
+    #[serde(default)]
+    #[schema(value_type = Option<Object>)]
+    pub body: Option<serde_json::Value>,
+    /// Text the response body must contain for the check to pass.
+    #[serde(default)]
+    pub expect_contains: Option<String>,
+    #[serde(default = "default_request_timeout_secs")]
+    pub timeout_secs: u64,
+    /// How long to keep retrying while the new instance is still loading its model.
+    #[serde(default = "default_ready_timeout_secs")]
+    pub ready_timeout_secs: u64,
+}
+
+fn default_prompt() -> String {
+    "Say hello.".to_string()
+}
+
+fn default_request_timeout_secs() -> u64 {
+    30
+}
+
+fn default_ready_timeout_secs() -> u64 {
+    120
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SmokeTestResult {
+    pub status: u16,
+    pub latency_ms: u64,
+    pub attempts: u32,
+    pub output_excerpt: String,
+}
+
+const OUTPUT_EXCERPT_CHARS: usize = 200;
+
+/// Pause between smoke attempts while the new instance is still starting.
+pub const SMOKE_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+impl SmokeTestConfig {
+    fn request_body(&self) -> serde_json::Value {
+        self.body.clone().unwrap_or_else(|| {
+            serde_json::json!({
+                "prompt": self.prompt,
+                "max_tokens": 16,
+            })
+        })
+    }
+
+    pub fn ready_deadline(&self) -> Instant {
+        Instant::now() + Duration::from_secs(self.ready_timeout_secs)
+    }
+
+    /// Sends a single smoke request, returning a human readable reason on failure.
+    pub async fn attempt(
+        &self,
+        client: &reqwest::Client,
+        attempts: u32,
+    ) -> Result<SmokeTestResult, String> {
+        let started = Instant::now();
+        let response = client
+            .post(&self.url)
+            .timeout(Duration::from_secs(self.timeout_secs.max(1)))
+            .json(&self.request_body())
+            .send()
+            .await
+            .map_err(|err| err.to_string())?;
+        let status = response.status();
+        let text = response.text().await.map_err(|err| err.to_string())?;
+        let latency_ms = started.elapsed().as_millis() as u64;
+
+        if !status.is_success() {
+            return Err(format!("smoke test returned {}", status));
+        }
+        if let Some(expected) = &self.expect_contains {
+            if !text.contains(expected.as_str()) {
+                return Err(format!(
+                    "smoke test response did not contain {:?}",
+                    expected
+                ));
+            }
+        }
+
+        Ok(SmokeTestResult {
+            status: status.as_u16(),
+            latency_ms,
+            attempts,
+            output_excerpt: text.chars().take(OUTPUT_EXCERPT_CHARS).collect(),
+        })
+    }
+}
@@ -0,0 +1,89 @@
+//! Size limits for the model directory of each task type, and how much of
+//! them is in use. Quotas come from `GOOSE_PLUGIN_LLM_MODEL_DIR_QUOTAS`, a
+//! JSON object of maximum bytes per task type:
+//!
+//! ```json
+//! {"text": 50000000000, "tts": 5000000000}
+//! ```
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::quota::disk_usage;
+use super::PluginTaskType;
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ModelDirUsage {
+    pub task_type: PluginTaskType,
+    pub dir: String,
+    /// Bytes stored in the directory, partial downloads included.
+    pub used_bytes: u64,
+    /// Size downloads into the directory may not take it past.
+    #[serde(default)]
+    pub max_bytes: Option<u64>,
+    /// `used_bytes` as a fraction of `max_bytes`.
+    #[serde(default)]
+    pub utilization: Option<f64>,
+}
+
+impl ModelDirUsage {
+    /// Measures `dir` against `max_bytes`. Walks the directory, so it blocks.
+    pub fn measure(
+        task_type: PluginTaskType,
+        dir: &Path,
+        max_bytes: Option<u64>,
+    ) -> std::io::Result<Self> {
+        let used_bytes = disk_usage(dir)?;
+        Ok(Self {
+            task_type,
+            dir: dir.to_string_lossy().to_string(),
+            used_bytes,
+            max_bytes,
+            utilization: max_bytes
+                .filter(|max| *max > 0)
+                .map(|max| used_bytes as f64 / max as f64),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ModelUsageResponse {
+    pub directories: Vec<ModelDirUsage>,
+}
+
+/// Reads `GOOSE_PLUGIN_LLM_MODEL_DIR_QUOTAS`; task types it leaves out have
+/// no limit.
+pub fn quotas_from_env() -> anyhow::Result<HashMap<String, u64>> {
+    let Ok(raw) = std::env::var("GOOSE_PLUGIN_LLM_MODEL_DIR_QUOTAS") else {
+        return Ok(HashMap::new());
+    };
+    let quotas: HashMap<String, u64> = serde_json::from_str(&raw)
+        .map_err(|err| anyhow::anyhow!("invalid GOOSE_PLUGIN_LLM_MODEL_DIR_QUOTAS: {}", err))?;
+    for task_type in quotas.keys() {
+        PluginTaskType::new(task_type.as_str())?;
+    }
+    Ok(quotas)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn measures_directory_against_quota() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("org")).unwrap();
+        std::fs::write(dir.path().join("org").join("model.gguf"), [0u8; 300]).unwrap();
+        std::fs::write(dir.path().join("small.gguf.part"), [0u8; 100]).unwrap();
+
+        let usage = ModelDirUsage::measure(PluginTaskType::TEXT, dir.path(), Some(1000)).unwrap();
+        assert_eq!(usage.used_bytes, 400);
+        assert_eq!(usage.utilization, Some(0.4));
+
+        let unlimited = ModelDirUsage::measure(PluginTaskType::TTS, dir.path(), None).unwrap();
+        assert_eq!(unlimited.utilization, None);
+    }
+}
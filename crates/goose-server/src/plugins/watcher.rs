@@ -0,0 +1,345 @@
+//! Indexing of models copied into the model directories by hand. The base
+//! directory is watched, and model files that appear in a task type's
+//! directory are added to the manifest once they stop changing; indexed
+//! files deleted outside of goosed are dropped from it again.
+
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use notify::{EventKind, RecursiveMode, Watcher};
+use reqwest::Url;
+use sha2::{Digest, Sha256};
+use tokio::sync::{mpsc, Mutex};
+use tokio::task::JoinHandle;
+
+use super::adapters::ADAPTERS_DIR;
+use super::events::{EventBus, PluginEventKind};
+use super::manifest::{ModelManifest, ModelRecord};
+use super::{PluginError, PluginTaskType};
+
+/// Extensions of the files indexed as models.
+const MODEL_EXTENSIONS: &[&str] = &["gguf", "safetensors", "onnx", "bin", "pt", "pth"];
+
+/// How long a file must go unchanged before it is indexed, so files still
+/// being copied are not.
+const SETTLE_DELAY: Duration = Duration::from_secs(2);
+
+/// How often changed paths are checked for having settled.
+const SETTLE_CHECK: Duration = Duration::from_millis(500);
+
+/// Whether models are indexed automatically; `GOOSE_PLUGIN_LLM_WATCH_MODELS=0`
+/// turns it off.
+pub fn enabled_from_env() -> bool {
+    std::env::var("GOOSE_PLUGIN_LLM_WATCH_MODELS")
+        .map(|value| !matches!(value.trim(), "0" | "false" | "no"))
+        .unwrap_or(true)
+}
+
+/// Keeps the manifest in step with the model files under a base directory.
+pub struct ModelIndex {
+    pub plugin_id: String,
+    pub base_dir: PathBuf,
+    /// Directories under the base directory that never hold models, such as
+    /// the runtime directory of decrypted copies.
+    pub excluded: Vec<PathBuf>,
+    pub manifest: Arc<Mutex<ModelManifest>>,
+    pub events: EventBus,
+}
+
+impl ModelIndex {
+    /// The task type of the model file at `path` and its name relative to
+    /// the task type's directory, or `None` for files that are not models.
+    pub fn model_file(&self, path: &Path) -> Option<(PluginTaskType, String)> {
+        if self.excluded.iter().any(|dir| path.starts_with(dir)) {
+            return None;
+        }
+        let relative = path.strip_prefix(&self.base_dir).ok()?;
+        let mut parts = Vec::new();
+        for component in relative.components() {
+            let Component::Normal(part) = component else {
+                return None;
+            };
+            let part = part.to_str()?;
+            if part.starts_with('.') || part == ADAPTERS_DIR {
+                return None;
+            }
+            parts.push(part);
+        }
+        let (task_type, rest) = parts.split_first()?;
+        let extension = Path::new(rest.last()?).extension()?.to_str()?;
+        if !MODEL_EXTENSIONS
+            .iter()
+            .any(|known| known.eq_ignore_ascii_case(extension))
+        {
+            return None;
+        }
+        Some((PluginTaskType::new(*task_type).ok()?, rest.join("/")))
+    }
+
+    /// Brings the manifest in line with whatever is at `path` now: indexes
+    /// the model files found there and drops the records of files that are
+    /// gone.
+    pub async fn reconcile(&self, path: &Path) -> Result<(), PluginError> {
+        match tokio::fs::metadata(path).await {
+            Ok(metadata) if metadata.is_dir() => {
+                let dir = path.to_path_buf();
+                let files = tokio::task::spawn_blocking(move || files_under(&dir))
+                    .await
+                    .map_err(|err| PluginError::Internal(err.to_string()))??;
+                for file in files {
+                    if let Err(err) = self.index(&file).await {
+                        tracing::warn!("indexing {} failed: {}", file.display(), err);
+                    }
+                }
+                Ok(())
+            }
+            Ok(_) => self.index(path).await,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => self.forget(path).await,
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn index(&self, path: &Path) -> Result<(), PluginError> {
+        let Some((task_type, filename)) = self.model_file(path) else {
+            return Ok(());
+        };
+        let saved_path = path.to_string_lossy().to_string();
+        if self.manifest.lock().await.find(&saved_path).is_some() {
+            return Ok(());
+        }
+        let file = path.to_path_buf();
+        let (bytes, sha256) = tokio::task::spawn_blocking(move || hash_file(&file))
+            .await
+            .map_err(|err| PluginError::Internal(err.to_string()))??;
+
+        let mut manifest = self.manifest.lock().await;
+        // goosed may have recorded the file itself while it was being hashed.
+        if manifest.find(&saved_path).is_some() {
+            return Ok(());
+        }
+        manifest.upsert(ModelRecord {
+            model_id: filename.clone(),
+            filename: filename.clone(),
+            revision: "main".to_string(),
+            commit: None,
+            task_type: task_type.clone(),
+            saved_path: saved_path.clone(),
+            bytes,
+            sha256: Some(sha256),
+            downloaded_at: Utc::now(),
+            last_checked: None,
+            update: None,
+            last_used: None,
+            encrypted: false,
+            pinned: false,
+            source_url: Url::from_file_path(path).ok().map(String::from),
+            provenance: None,
+        });
+        manifest.save().await?;
+        drop(manifest);
+        tracing::info!("indexed model {} added by hand", saved_path);
+        self.events.publish(
+            &self.plugin_id,
+            PluginEventKind::ModelDiscovered {
+                task_type,
+                filename,
+                saved_path,
+                bytes,
+            },
+        );
+        Ok(())
+    }
+
+    /// Drops the records of files at or under `path` that no longer exist.
+    async fn forget(&self, path: &Path) -> Result<(), PluginError> {
+        let mut manifest = self.manifest.lock().await;
+        let gone: Vec<ModelRecord> = manifest
+            .records()
+            .iter()
+            .filter(|record| {
+                let saved = Path::new(&record.saved_path);
+                saved.starts_with(path) && !saved.exists()
+            })
+            .cloned()
+            .collect();
+        if gone.is_empty() {
+            return Ok(());
+        }
+        for record in &gone {
+            manifest.remove(&record.saved_path);
+        }
+        manifest.save().await?;
+        drop(manifest);
+        for record in gone {
+            tracing::info!("model {} was deleted by hand", record.saved_path);
+            self.events.publish(
+                &self.plugin_id,
+                PluginEventKind::ModelRemoved {
+                    model_id: record.model_id,
+                    task_type: record.task_type,
+                    filename: record.filename,
+                    saved_path: record.saved_path,
+                },
+            );
+        }
+        Ok(())
+    }
+}
+
+/// The files under `dir`, at any depth.
+fn files_under(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        let entries = match std::fs::read_dir(&current) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(err),
+        };
+        for entry in entries {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                pending.push(entry.path());
+            } else {
+                files.push(entry.path());
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// Size and hex SHA-256 of the file at `path`.
+pub(super) fn hash_file(path: &Path) -> std::io::Result<(u64, String)> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; 1 << 20];
+    let mut bytes = 0;
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            return Ok((bytes, format!("{:x}", hasher.finalize())));
+        }
+        hasher.update(&buffer[..read]);
+        bytes += read as u64;
+    }
+}
+
+/// Indexes the models already in the base directory, then watches it for
+/// files added or deleted by hand until aborted.
+pub fn spawn(index: ModelIndex) -> notify::Result<JoinHandle<()>> {
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        let _ = sender.send(event);
+    })?;
+    watcher.watch(&index.base_dir, RecursiveMode::Recursive)?;
+    Ok(tokio::spawn(async move {
+        // Dropping the watcher stops it, so it lives as long as the task.
+        let _watcher = watcher;
+        if let Err(err) = index.reconcile(&index.base_dir).await {
+            tracing::warn!(
+                "indexing models in {} failed: {}",
+                index.base_dir.display(),
+                err
+            );
+        }
+        let mut changed: HashMap<PathBuf, Instant> = HashMap::new();
+        let mut ticks = tokio::time::interval(SETTLE_CHECK);
+        loop {
+            tokio::select! {
+                event = receiver.recv() => match event {
+                    Some(Ok(event)) => {
+                        if !matches!(event.kind, EventKind::Access(_)) {
+                            for path in event.paths {
+                                changed.insert(path, Instant::now());
+                            }
+                        }
+                    }
+                    Some(Err(err)) => tracing::warn!("watching model directories failed: {}", err),
+                    None => return,
+                },
+                _ = ticks.tick() => {
+                    let settled: Vec<PathBuf> = changed
+                        .iter()
+                        .filter(|(_, at)| at.elapsed() >= SETTLE_DELAY)
+                        .map(|(path, _)| path.clone())
+                        .collect();
+                    for path in settled {
+                        changed.remove(&path);
+                        if let Err(err) = index.reconcile(&path).await {
+                            tracing::warn!("indexing {} failed: {}", path.display(), err);
+                        }
+                    }
+                }
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn indexes_models_added_and_deleted_by_hand() {
+        let dir = tempfile::tempdir().unwrap();
+        let base_dir = dir.path().canonicalize().unwrap();
+        let events = EventBus::default();
+        let mut received = events.subscribe();
+        let index = ModelIndex {
+            plugin_id: "llmserver-rs".to_string(),
+            base_dir: base_dir.clone(),
+            excluded: vec![base_dir.join("runtime")],
+            manifest: Arc::new(Mutex::new(ModelManifest::load(&base_dir).await.unwrap())),
+            events,
+        };
+
+        let model = base_dir.join("text/org/model.gguf");
+        assert_eq!(
+            index.model_file(&model),
+            Some((PluginTaskType::TEXT, "org/model.gguf".to_string()))
+        );
+        for ignored in [
+            "text/model.gguf.part",
+            "text/.hidden.gguf",
+            "text/adapters/model/lora.gguf",
+            "runtime/model.gguf",
+            "Text/model.gguf",
+            "model.gguf",
+        ] {
+            assert_eq!(
+                index.model_file(&base_dir.join(ignored)),
+                None,
+                "{}",
+                ignored
+            );
+        }
+
+        std::fs::create_dir_all(model.parent().unwrap()).unwrap();
+        std::fs::write(&model, b"GGUF").unwrap();
+        std::fs::write(base_dir.join("text/notes.txt"), b"notes").unwrap();
+        index.reconcile(&base_dir.join("text")).await.unwrap();
+        {
+            let manifest = index.manifest.lock().await;
+            let record = manifest.find(&model.to_string_lossy()).unwrap();
+            assert_eq!(record.model_id, "org/model.gguf");
+            assert_eq!(record.bytes, 4);
+            assert_eq!(manifest.records().len(), 1);
+        }
+        assert_eq!(
+            received.recv().await.unwrap().event.event_type(),
+            "model_discovered"
+        );
+
+        std::fs::remove_dir_all(base_dir.join("text/org")).unwrap();
+        index.reconcile(&base_dir.join("text/org")).await.unwrap();
+        assert!(index.manifest.lock().await.records().is_empty());
+        assert_eq!(
+            received.recv().await.unwrap().event.event_type(),
+            "model_removed"
+        );
+    }
+}
@@ -0,0 +1,122 @@
+use std::sync::Arc;
+
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::StatusCode,
+    routing::{get, post},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::plugins::offline::OfflineStatus;
+use crate::plugins::retention::{self, LogRetention, PruneReport};
+use crate::plugins::{PluginError, ServiceLogsPruned};
+use crate::routes::errors::ErrorResponse;
+use crate::state::AppState;
+
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct PruneLogsRequest {
+    /// Policy for goosed's own log files instead of the configured global
+    /// retention. Captured service logs always use their own retention.
+    #[serde(default)]
+    pub retention: Option<LogRetention>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PruneLogsResponse {
+    pub retention: LogRetention,
+    pub server: PruneReport,
+    pub services: Vec<ServiceLogsPruned>,
+}
+
+fn internal_error(message: String) -> ErrorResponse {
+    ErrorResponse {
+        message,
+        status: StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/logs/prune",
+    request_body = PruneLogsRequest,
+    responses(
+        (status = 200, description = "Logs pruned", body = PruneLogsResponse),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+        (status = 500, description = "Failed to prune logs", body = ErrorResponse)
+    ),
+)]
+pub async fn prune_logs(
+    State(state): State<Arc<AppState>>,
+    body: Bytes,
+) -> Result<Json<PruneLogsResponse>, ErrorResponse> {
+    // The body is optional; an empty request prunes with the configured policy.
+    let request: PruneLogsRequest = if body.is_empty() {
+        PruneLogsRequest::default()
+    } else {
+        serde_json::from_slice(&body).map_err(|err| ErrorResponse {
+            message: format!("invalid request: {}", err),
+            status: StatusCode::BAD_REQUEST,
+        })?
+    };
+    let policy = request.retention.unwrap_or_else(LogRetention::from_env);
+
+    let server_dir = goose::logging::get_log_directory("server", false)
+        .map_err(|err| internal_error(err.to_string()))?;
+    let server =
+        tokio::task::spawn_blocking(move || retention::prune_directory(&server_dir, &policy))
+            .await
+            .map_err(|err| internal_error(err.to_string()))?
+            .map_err(|err| internal_error(err.to_string()))?;
+
+    let mut services = Vec::new();
+    for metadata in state.plugins.list_metadata().await {
+        let Some(Ok(plugin)) = state.plugins.active(&metadata.id).await else {
+            continue;
+        };
+        match plugin.prune_logs().await {
+            Ok(pruned) => services.push(pruned),
+            Err(PluginError::UnsupportedOperation) => {}
+            Err(err) => return Err(internal_error(err.to_string())),
+        }
+    }
+
+    Ok(Json(PruneLogsResponse {
+        retention: policy,
+        server,
+        services,
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/offline",
+    responses((status = 200, description = "Whether offline mode is enabled", body = OfflineStatus)),
+)]
+pub async fn get_offline(State(state): State<Arc<AppState>>) -> Json<OfflineStatus> {
+    Json(state.plugins.offline().await.status())
+}
+
+#[utoipa::path(
+    put,
+    path = "/admin/offline",
+    request_body = OfflineStatus,
+    responses((status = 200, description = "Offline mode updated", body = OfflineStatus)),
+)]
+pub async fn set_offline(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<OfflineStatus>,
+) -> Json<OfflineStatus> {
+    let offline = state.plugins.offline().await;
+    offline.set(request.enabled);
+    Json(offline.status())
+}
+
+pub fn routes(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/admin/logs/prune", post(prune_logs))
+        .route("/admin/offline", get(get_offline).put(set_offline))
+        .with_state(state)
+}
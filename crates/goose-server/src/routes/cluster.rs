@@ -0,0 +1,197 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    routing::{delete, get, post},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::cluster::{ClusterError, ClusterNodeStatus, NodeHeartbeat};
+use crate::jobs::JobStatus;
+use crate::plugins::{DownloadModelRequest, StartServiceRequest, StartServiceResponse};
+use crate::routes::errors::ErrorResponse;
+use crate::state::AppState;
+
+impl From<ClusterError> for ErrorResponse {
+    fn from(err: ClusterError) -> Self {
+        let status = match &err {
+            ClusterError::UnknownNode(_) => StatusCode::NOT_FOUND,
+            ClusterError::NodeOffline(_) | ClusterError::NoNodesAvailable => {
+                StatusCode::SERVICE_UNAVAILABLE
+            }
+            ClusterError::Unreachable(..) => StatusCode::BAD_GATEWAY,
+            ClusterError::Member { status, .. } => {
+                StatusCode::from_u16(*status).unwrap_or(StatusCode::BAD_GATEWAY)
+            }
+        };
+        ErrorResponse {
+            message: err.to_string(),
+            status,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ClusterDownloadRequest {
+    /// Member to download on; the least loaded online member when omitted.
+    #[serde(default)]
+    pub member: Option<String>,
+    #[serde(flatten)]
+    pub request: DownloadModelRequest,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ClusterDownloadResponse {
+    pub member: String,
+    /// The member's download job, to poll on that member.
+    #[serde(flatten)]
+    pub response: JobStatus,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ClusterStartRequest {
+    /// Member to start on; the least loaded online member when omitted.
+    #[serde(default)]
+    pub member: Option<String>,
+    #[serde(flatten)]
+    pub request: StartServiceRequest,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ClusterStartResponse {
+    pub member: String,
+    #[serde(flatten)]
+    pub response: StartServiceResponse,
+}
+
+#[utoipa::path(
+    post,
+    path = "/cluster/heartbeat",
+    request_body = NodeHeartbeat,
+    responses(
+        (status = 200, description = "Member registered or refreshed", body = ClusterNodeStatus)
+    ),
+)]
+pub async fn heartbeat(
+    State(state): State<Arc<AppState>>,
+    Json(heartbeat): Json<NodeHeartbeat>,
+) -> Json<ClusterNodeStatus> {
+    Json(state.cluster.heartbeat(heartbeat).await)
+}
+
+#[utoipa::path(
+    get,
+    path = "/cluster/nodes",
+    responses(
+        (status = 200, description = "Registered cluster members", body = [ClusterNodeStatus])
+    ),
+)]
+pub async fn list_nodes(State(state): State<Arc<AppState>>) -> Json<Vec<ClusterNodeStatus>> {
+    Json(state.cluster.list().await)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/cluster/nodes/{node_id}",
+    params(("node_id" = String, Path, description = "Cluster member identifier")),
+    responses(
+        (status = 204, description = "Member removed"),
+        (status = 404, description = "Unknown member", body = ErrorResponse)
+    ),
+)]
+pub async fn remove_node(
+    State(state): State<Arc<AppState>>,
+    Path(node_id): Path<String>,
+) -> Result<StatusCode, ErrorResponse> {
+    if state.cluster.remove(&node_id).await {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(ClusterError::UnknownNode(node_id).into())
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/cluster/plugins/{plugin_id}/models/download",
+    params(("plugin_id" = String, Path, description = "Plugin identifier")),
+    request_body = ClusterDownloadRequest,
+    responses(
+        (status = 202, description = "Download started on a member", body = ClusterDownloadResponse),
+        (status = 404, description = "Unknown member", body = ErrorResponse),
+        (status = 502, description = "Member unreachable", body = ErrorResponse),
+        (status = 503, description = "No online member available", body = ErrorResponse)
+    ),
+)]
+pub async fn download_model(
+    State(state): State<Arc<AppState>>,
+    Path(plugin_id): Path<String>,
+    Json(payload): Json<ClusterDownloadRequest>,
+) -> Result<(StatusCode, Json<ClusterDownloadResponse>), ErrorResponse> {
+    let node = state.cluster.select(payload.member.as_deref()).await?;
+    let response = state
+        .cluster
+        .forward(
+            &node,
+            &format!("/plugins/{}/models/download", plugin_id),
+            &payload.request,
+        )
+        .await?;
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(ClusterDownloadResponse {
+            member: node.node_id,
+            response,
+        }),
+    ))
+}
+
+#[utoipa::path(
+    post,
+    path = "/cluster/plugins/{plugin_id}/services/start",
+    params(("plugin_id" = String, Path, description = "Plugin identifier")),
+    request_body = ClusterStartRequest,
+    responses(
+        (status = 200, description = "Service started on a member", body = ClusterStartResponse),
+        (status = 404, description = "Unknown member", body = ErrorResponse),
+        (status = 502, description = "Member unreachable", body = ErrorResponse),
+        (status = 503, description = "No online member available", body = ErrorResponse)
+    ),
+)]
+pub async fn start_service(
+    State(state): State<Arc<AppState>>,
+    Path(plugin_id): Path<String>,
+    Json(payload): Json<ClusterStartRequest>,
+) -> Result<Json<ClusterStartResponse>, ErrorResponse> {
+    let node = state.cluster.select(payload.member.as_deref()).await?;
+    let response = state
+        .cluster
+        .forward(
+            &node,
+            &format!("/plugins/{}/services/start", plugin_id),
+            &payload.request,
+        )
+        .await?;
+    Ok(Json(ClusterStartResponse {
+        member: node.node_id,
+        response,
+    }))
+}
+
+pub fn routes(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/cluster/heartbeat", post(heartbeat))
+        .route("/cluster/nodes", get(list_nodes))
+        .route("/cluster/nodes/{node_id}", delete(remove_node))
+        .route(
+            "/cluster/plugins/{plugin_id}/models/download",
+            post(download_model),
+        )
+        .route(
+            "/cluster/plugins/{plugin_id}/services/start",
+            post(start_service),
+        )
+        .with_state(state)
+}
@@ -0,0 +1,101 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    routing::{get, post},
+    Json, Router,
+};
+
+use crate::jobs::{JobError, JobStatus, PrioritizeJobRequest};
+use crate::routes::errors::ErrorResponse;
+use crate::state::AppState;
+
+impl From<JobError> for ErrorResponse {
+    fn from(err: JobError) -> Self {
+        let status = match &err {
+            JobError::UnknownJob(_) => StatusCode::NOT_FOUND,
+            JobError::Finished(_) | JobError::NotPausable(_) | JobError::NotQueued(_) => {
+                StatusCode::CONFLICT
+            }
+        };
+        ErrorResponse {
+            message: err.to_string(),
+            status,
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/jobs",
+    responses(
+        (status = 200, description = "Background jobs, newest first", body = [JobStatus])
+    ),
+)]
+pub async fn list_jobs(State(state): State<Arc<AppState>>) -> Json<Vec<JobStatus>> {
+    Json(state.jobs.list().await)
+}
+
+#[utoipa::path(
+    get,
+    path = "/jobs/{job_id}",
+    params(("job_id" = String, Path, description = "Job identifier")),
+    responses(
+        (status = 200, description = "Job status, with the result once it finished", body = JobStatus),
+        (status = 404, description = "Unknown job", body = ErrorResponse)
+    ),
+)]
+pub async fn get_job(
+    State(state): State<Arc<AppState>>,
+    Path(job_id): Path<String>,
+) -> Result<Json<JobStatus>, ErrorResponse> {
+    Ok(Json(state.jobs.get(&job_id).await?))
+}
+
+#[utoipa::path(
+    post,
+    path = "/jobs/{job_id}/cancel",
+    params(("job_id" = String, Path, description = "Job identifier")),
+    responses(
+        (status = 200, description = "Cancellation requested; the job stops at its next cancellation point", body = JobStatus),
+        (status = 404, description = "Unknown job", body = ErrorResponse),
+        (status = 409, description = "Job already finished", body = ErrorResponse)
+    ),
+)]
+pub async fn cancel_job(
+    State(state): State<Arc<AppState>>,
+    Path(job_id): Path<String>,
+) -> Result<Json<JobStatus>, ErrorResponse> {
+    Ok(Json(state.jobs.cancel(&job_id).await?))
+}
+
+#[utoipa::path(
+    post,
+    path = "/jobs/{job_id}/prioritize",
+    params(("job_id" = String, Path, description = "Job identifier")),
+    request_body = PrioritizeJobRequest,
+    responses(
+        (status = 200, description = "Priority changed; the job's queue position reflects it", body = JobStatus),
+        (status = 404, description = "Unknown job", body = ErrorResponse),
+        (status = 409, description = "Job finished or no longer waiting in a queue", body = ErrorResponse)
+    ),
+)]
+pub async fn prioritize_job(
+    State(state): State<Arc<AppState>>,
+    Path(job_id): Path<String>,
+    Json(payload): Json<PrioritizeJobRequest>,
+) -> Result<Json<JobStatus>, ErrorResponse> {
+    Ok(Json(
+        state.jobs.prioritize(&job_id, payload.priority).await?,
+    ))
+}
+
+pub fn routes(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/jobs", get(list_jobs))
+        .route("/jobs/{job_id}", get(get_job))
+        .route("/jobs/{job_id}/cancel", post(cancel_job))
+        .route("/jobs/{job_id}/prioritize", post(prioritize_job))
+        .with_state(state)
+}
@@ -1,8 +1,11 @@
+pub mod admin;
 pub mod agent;
 pub mod audio;
+pub mod cluster;
 pub mod config_management;
 pub mod errors;
 pub mod extension;
+pub mod jobs;
 pub mod plugins;
 pub mod recipe;
 pub mod recipe_utils;
@@ -12,6 +15,7 @@ pub mod session;
 pub mod setup;
 pub mod status;
 pub mod utils;
+pub mod webhooks;
 use std::sync::Arc;
 
 use axum::Router;
@@ -29,5 +33,9 @@ pub fn configure(state: Arc<crate::state::AppState>) -> Router {
         .merge(session::routes(state.clone()))
         .merge(schedule::routes(state.clone()))
         .merge(setup::routes(state.clone()))
+        .merge(admin::routes(state.clone()))
+        .merge(cluster::routes(state.clone()))
+        .merge(jobs::routes(state.clone()))
+        .merge(webhooks::routes(state.clone()))
         .merge(plugins::routes(state))
 }
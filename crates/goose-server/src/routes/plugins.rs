@@ -1,49 +1,254 @@
+use std::convert::Infallible;
 use std::sync::Arc;
 
 use axum::{
-    extract::{Path, State},
-    routing::{get, post},
-    Json, Router,
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Path, Query, RawPathParams, Request, State,
+    },
+    middleware::{self, Next},
+    response::sse::{Event, KeepAlive, Sse},
+    response::{IntoResponse, Response},
+    routing::{delete, get, post, MethodRouter},
+    Extension, Json, Router,
 };
-use http::StatusCode;
-use serde::Serialize;
-use utoipa::ToSchema;
+use futures::{Future, Stream};
+use http::{header, StatusCode};
+use serde::{Deserialize, Serialize};
+use tokio_util::sync::CancellationToken;
+use utoipa::{IntoParams, ToSchema};
 
 use crate::state::AppState;
 
+use crate::jobs::{DownloadJob, DownloadPause, JobError, JobQueue, JobRegistry, JobStatus};
+use crate::plugins::adapters::{ListAdaptersRequest, ListAdaptersResponse};
+use crate::plugins::admission::{
+    AdmissionControl, AdmissionPermit, OperationPriority, QueueFull, QueueStatus, QueuedAdmission,
+    QueuedOperation, RETRY_AFTER_SECS,
+};
+use crate::plugins::catalog::PluginCatalog;
+use crate::plugins::codes::PluginErrorCode;
+use crate::plugins::compat::PluginCompatibility;
+use crate::plugins::convert::{ConvertModelRequest, ConvertModelResponse};
+use crate::plugins::defaults::PluginDefaults;
+use crate::plugins::diagnostics::CrashReport;
+use crate::plugins::events::PluginEventKind;
+use crate::plugins::fallback::{self, FallbackError};
+use crate::plugins::gc::{ModelGcRequest, ModelGcResponse};
+use crate::plugins::health::PluginHealthResponse;
+use crate::plugins::integrity::{ModelHealthResponse, RepairModelsRequest, RepairModelsResponse};
+use crate::plugins::logs::{FollowedLog, LogLevel};
+use crate::plugins::manifest::ModelRecord;
+use crate::plugins::metrics::PluginMetricsSnapshot;
+use crate::plugins::operations::{self, InvokeContext};
+use crate::plugins::peers::{self, RangeNotSatisfiable};
+use crate::plugins::permissions::PluginCredential;
+use crate::plugins::progress::DownloadProgress;
+use crate::plugins::quantize::{QuantizeModelRequest, QuantizeModelResponse};
+use crate::plugins::recommended::ModelCatalogResponse;
+use crate::plugins::revisions::REPO_COMMIT_HEADER;
+use crate::plugins::schedule;
+use crate::plugins::settings::PluginConfig;
+use crate::plugins::signals::ServiceSignal;
+use crate::plugins::usage::ModelUsageResponse;
 use crate::plugins::{
-    DownloadModelRequest, DownloadModelResponse, PluginError, PluginMetadata, StartServiceRequest,
-    StartServiceResponse, StopServiceRequest, StopServiceResponse,
+    AttachConsoleRequest, ConsoleSession, DeleteModelRequest, DeleteModelResponse,
+    DownloadModelRequest, FollowLogsRequest, FollowLogsSession, ImportModelRequest,
+    ImportModelResponse, ListLogFilesResponse, ListModelsResponse, ListNodesResponse,
+    ListProfilesResponse, ListServicesResponse, ModelFilesRequest, ModelFilesResponse,
+    ModelRevisionsRequest, ModelRevisionsResponse, ModelSearchRequest, ModelSearchResponse,
+    ModelUpdatesResponse, PinModelRequest, PluginCapability, PluginError, PluginMetadata,
+    PluginTaskType, RegisterPluginRequest, ReloadPluginResponse, RestoreModelsRequest,
+    RestoreModelsResponse, ServerPlugin, ServiceLogsRequest, ServiceLogsResponse, ServiceSelector,
+    ServiceStatusRequest, ServiceStatusResponse, SignalServiceRequest, SignalServiceResponse,
+    StartServiceRequest, StartServiceResponse, StopServiceRequest, StopServiceResponse,
+    UnregisterPluginResponse, UpgradeServiceRequest, UpgradeServiceResponse, ValidateTokenRequest,
+    ValidateTokenResponse,
 };
 
 #[derive(Debug, Serialize, ToSchema)]
 pub struct PluginErrorResponse {
+    pub code: PluginErrorCode,
     pub message: String,
+    /// Context for the code, e.g. the plugin id or upstream host involved.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    pub details: Option<serde_json::Value>,
+    /// Whether the same request may succeed when repeated later.
+    pub retryable: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_after_secs: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crash: Option<CrashReport>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compat: Option<PluginCompatibility>,
 }
 
 impl PluginErrorResponse {
-    fn new(message: impl Into<String>) -> Self {
+    fn new(code: PluginErrorCode, message: impl Into<String>) -> Self {
         Self {
+            code,
             message: message.into(),
+            details: None,
+            retryable: code.retryable(),
+            retry_after_secs: None,
+            crash: None,
+            compat: None,
         }
     }
 }
 
+fn plugin_not_found(plugin_id: &str) -> (StatusCode, Json<PluginErrorResponse>) {
+    let mut response =
+        PluginErrorResponse::new(PluginErrorCode::PluginNotFound, "plugin not found");
+    response.details = Some(serde_json::json!({ "plugin_id": plugin_id }));
+    (StatusCode::NOT_FOUND, Json(response))
+}
+
+/// The plugin task-level routes dispatch to for `task_type`: 404 when none
+/// is set and no registered plugin can start services.
+async fn default_plugin(
+    state: &AppState,
+    task_type: &PluginTaskType,
+) -> Result<Arc<dyn ServerPlugin>, (StatusCode, Json<PluginErrorResponse>)> {
+    let plugin_id = default_plugin_id(state, task_type).await?;
+    active_plugin(state, &plugin_id).await
+}
+
+async fn default_plugin_id(
+    state: &AppState,
+    task_type: &PluginTaskType,
+) -> Result<String, (StatusCode, Json<PluginErrorResponse>)> {
+    let plugins = state.plugins.list_metadata().await;
+    state
+        .plugin_defaults
+        .read()
+        .await
+        .resolve(task_type, &plugins)
+        .ok_or_else(|| {
+            map_error(PluginError::NotFound(format!(
+                "no default plugin for {} tasks",
+                task_type
+            )))
+        })
+}
+
+/// Looks up the plugin an operation targets: 404 when it is not registered,
+/// 409 when it is disabled.
+async fn active_plugin(
+    state: &AppState,
+    plugin_id: &str,
+) -> Result<Arc<dyn ServerPlugin>, (StatusCode, Json<PluginErrorResponse>)> {
+    state
+        .plugins
+        .active(plugin_id)
+        .await
+        .ok_or_else(|| plugin_not_found(plugin_id))?
+        .map_err(map_error)
+}
+
+/// `plugin_id` and the plugins to fall back on when it fails an operation
+/// needing `capability`, leaving out those a scoped credential may not use.
+async fn fallback_chain(
+    state: &AppState,
+    capability: &PluginCapability,
+    plugin_id: &str,
+    credential: Option<&PluginCredential>,
+) -> Vec<String> {
+    let mut chain = state
+        .plugin_defaults
+        .read()
+        .await
+        .get()
+        .fallback_chain(capability, plugin_id);
+    if let Some(credential) = credential {
+        chain.retain(|id| credential.allows(id, capability));
+    }
+    chain
+}
+
+async fn start_with_fallbacks(
+    state: &AppState,
+    plugin_id: &str,
+    credential: Option<&PluginCredential>,
+    payload: StartServiceRequest,
+) -> Result<Json<StartServiceResponse>, (StatusCode, Json<PluginErrorResponse>)> {
+    let chain = fallback_chain(
+        state,
+        &PluginCapability::ServiceStart,
+        plugin_id,
+        credential,
+    )
+    .await;
+    let (mut response, attempts) = fallback::run_chain(&state.plugins, &chain, |plugin| {
+        let payload = payload.clone();
+        async move { plugin.start_service(payload).await }
+    })
+    .await
+    .map_err(fallback_error)?;
+    response.fallback = attempts;
+    Ok(Json(response))
+}
+
+/// The last plugin's error, with the plugins tried before it in `details`.
+fn fallback_error(error: FallbackError) -> (StatusCode, Json<PluginErrorResponse>) {
+    let (status, Json(mut response)) = map_error(error.error);
+    if !error.attempts.is_empty() {
+        let details = response
+            .details
+            .get_or_insert_with(|| serde_json::json!({}));
+        details["fallback"] = serde_json::json!(error.attempts);
+    }
+    (status, Json(response))
+}
+
 fn map_error(error: PluginError) -> (StatusCode, Json<PluginErrorResponse>) {
     let status = match error {
         PluginError::UnsupportedOperation => StatusCode::BAD_REQUEST,
         PluginError::InvalidRequest(_) => StatusCode::BAD_REQUEST,
         PluginError::NotReady(_) => StatusCode::SERVICE_UNAVAILABLE,
         PluginError::NotFound(_) => StatusCode::NOT_FOUND,
+        PluginError::PathNotAllowed(_) => StatusCode::FORBIDDEN,
         PluginError::ProcessAlreadyRunning(_) => StatusCode::CONFLICT,
         PluginError::ProcessNotRunning(_) => StatusCode::CONFLICT,
+        PluginError::InstanceNotFound(_) => StatusCode::NOT_FOUND,
+        PluginError::ModelInUse(_) => StatusCode::CONFLICT,
         PluginError::Io(_) => StatusCode::INTERNAL_SERVER_ERROR,
         PluginError::Network(_) => StatusCode::BAD_GATEWAY,
+        PluginError::BinaryMissing(_) => StatusCode::BAD_REQUEST,
         PluginError::ProcessStart(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        PluginError::ProcessCrashed(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        PluginError::ServiceNotReady { .. } => StatusCode::GATEWAY_TIMEOUT,
+        PluginError::SmokeTestFailed(_) => StatusCode::BAD_GATEWAY,
+        PluginError::VerificationFailed(_) => StatusCode::BAD_GATEWAY,
+        PluginError::ChecksumMismatch { .. } => StatusCode::BAD_GATEWAY,
+        PluginError::InsufficientDiskSpace { .. } => StatusCode::INSUFFICIENT_STORAGE,
+        PluginError::GatedModel { .. } => StatusCode::FORBIDDEN,
+        PluginError::Remote(_) => StatusCode::BAD_GATEWAY,
+        PluginError::CircuitOpen { .. } => StatusCode::SERVICE_UNAVAILABLE,
+        PluginError::Offline(_) => StatusCode::SERVICE_UNAVAILABLE,
+        // Nobody is listening any more; nginx's "client closed request".
+        PluginError::Cancelled => StatusCode::from_u16(499).unwrap_or(StatusCode::BAD_REQUEST),
+        PluginError::ResourceLimit(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        PluginError::AlreadyRegistered(_) => StatusCode::CONFLICT,
+        PluginError::Disabled(_) => StatusCode::CONFLICT,
+        PluginError::Dependency(_) => StatusCode::CONFLICT,
+        PluginError::Incompatible(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        PluginError::QuotaExceeded { .. } => StatusCode::TOO_MANY_REQUESTS,
+        PluginError::Untrusted(_) => StatusCode::FORBIDDEN,
         PluginError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
     };
 
-    (status, Json(PluginErrorResponse::new(error.to_string())))
+    let mut response = PluginErrorResponse::new(error.code(), error.to_string());
+    response.details = error.details();
+    response.retryable = error.retryable();
+    response.retry_after_secs = error.retry_after_secs();
+    match error {
+        PluginError::ProcessCrashed(report) => response.crash = Some(*report),
+        PluginError::Incompatible(report) => response.compat = Some(*report),
+        _ => {}
+    }
+    (status, Json(response))
 }
 
 #[utoipa::path(
@@ -60,91 +265,2886 @@ pub async fn list_plugins(
 
 #[utoipa::path(
     post,
-    path = "/plugins/{plugin_id}/models/download",
+    path = "/plugins/register",
+    request_body = RegisterPluginRequest,
+    responses(
+        (status = 200, description = "Plugin started and registered", body = PluginMetadata),
+        (status = 400, description = "Invalid descriptor", body = PluginErrorResponse),
+        (status = 409, description = "A plugin with this id is already registered", body = PluginErrorResponse),
+        (status = 500, description = "Plugin failed to start", body = PluginErrorResponse),
+        (status = 502, description = "Remote plugin unreachable", body = PluginErrorResponse)
+    ),
+)]
+pub async fn register_plugin(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<RegisterPluginRequest>,
+) -> Result<Json<PluginMetadata>, (StatusCode, Json<PluginErrorResponse>)> {
+    state
+        .plugins
+        .register_runtime(payload)
+        .await
+        .map(Json)
+        .map_err(map_error)
+}
+
+#[utoipa::path(
+    get,
+    path = "/plugins/catalog",
+    responses(
+        (status = 200, description = "Plugins the configured index offers", body = PluginCatalog),
+        (status = 404, description = "No plugin index is configured", body = PluginErrorResponse),
+        (status = 502, description = "Plugin index unreachable", body = PluginErrorResponse),
+        (status = 503, description = "Offline mode is on", body = PluginErrorResponse)
+    ),
+)]
+pub async fn list_catalog(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<PluginCatalog>, (StatusCode, Json<PluginErrorResponse>)> {
+    let offline = state.plugins.offline().await;
+    state
+        .plugin_catalog
+        .fetch(&offline)
+        .await
+        .map(Json)
+        .map_err(map_error)
+}
+
+#[utoipa::path(
+    post,
+    path = "/plugins/catalog/{plugin_id}/install",
+    params(("plugin_id" = String, Path, description = "Plugin identifier in the index")),
+    responses(
+        (status = 200, description = "Plugin downloaded, verified and registered", body = PluginMetadata),
+        (status = 404, description = "Plugin not in the index", body = PluginErrorResponse),
+        (status = 409, description = "A plugin with this id is already installed", body = PluginErrorResponse),
+        (status = 502, description = "Download failed or does not match its checksum", body = PluginErrorResponse),
+        (status = 503, description = "Offline mode is on", body = PluginErrorResponse)
+    ),
+)]
+pub async fn install_catalog_plugin(
+    State(state): State<Arc<AppState>>,
+    Path(plugin_id): Path<String>,
+) -> Result<Json<PluginMetadata>, (StatusCode, Json<PluginErrorResponse>)> {
+    if state.plugins.active(&plugin_id).await.is_some() {
+        return Err(map_error(PluginError::AlreadyRegistered(plugin_id)));
+    }
+    let offline = state.plugins.offline().await;
+    let catalog = state
+        .plugin_catalog
+        .fetch(&offline)
+        .await
+        .map_err(map_error)?;
+    let entry = catalog.get(&plugin_id).ok_or_else(|| {
+        map_error(PluginError::NotFound(format!(
+            "plugin {} in the plugin index",
+            plugin_id
+        )))
+    })?;
+    let manifest = state
+        .plugin_catalog
+        .install(entry, &offline)
+        .await
+        .map_err(map_error)?;
+    match state.plugins.register_manifest(manifest).await {
+        Ok(metadata) => Ok(Json(metadata)),
+        Err(err) => {
+            // Otherwise the failing plugin would be loaded on every start.
+            if let Err(cleanup) = state.plugin_catalog.uninstall(&plugin_id).await {
+                tracing::warn!("cannot remove plugin {}: {}", plugin_id, cleanup);
+            }
+            Err(map_error(err))
+        }
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/plugins/{plugin_id}",
     params(("plugin_id" = String, Path, description = "Plugin identifier")),
-    request_body = DownloadModelRequest,
     responses(
-        (status = 200, description = "Model downloaded successfully", body = DownloadModelResponse),
-        (status = 400, description = "Invalid request", body = PluginErrorResponse),
-        (status = 404, description = "Plugin not found", body = PluginErrorResponse)
+        (status = 200, description = "Plugin removed and its processes stopped", body = UnregisterPluginResponse),
+        (status = 404, description = "Plugin not found", body = PluginErrorResponse),
+        (status = 500, description = "Plugin removed but failed to shut down cleanly", body = PluginErrorResponse)
     ),
 )]
-pub async fn download_model(
+pub async fn unregister_plugin(
     State(state): State<Arc<AppState>>,
     Path(plugin_id): Path<String>,
-    Json(payload): Json<DownloadModelRequest>,
-) -> Result<Json<DownloadModelResponse>, (StatusCode, Json<PluginErrorResponse>)> {
-    let plugin = state.plugins.plugin(&plugin_id).await.ok_or((
-        StatusCode::NOT_FOUND,
-        Json(PluginErrorResponse::new("plugin not found")),
-    ))?;
-    plugin
-        .download_model(payload)
+) -> Result<Json<UnregisterPluginResponse>, (StatusCode, Json<PluginErrorResponse>)> {
+    state
+        .plugins
+        .unregister(&plugin_id)
         .await
+        .ok_or_else(|| plugin_not_found(&plugin_id))?
         .map(Json)
         .map_err(map_error)
 }
 
 #[utoipa::path(
     post,
-    path = "/plugins/{plugin_id}/services/start",
+    path = "/plugins/{plugin_id}/reload",
     params(("plugin_id" = String, Path, description = "Plugin identifier")),
-    request_body = StartServiceRequest,
     responses(
-        (status = 200, description = "Service started", body = StartServiceResponse),
-        (status = 400, description = "Invalid request", body = PluginErrorResponse),
+        (status = 200, description = "Plugin reloaded", body = ReloadPluginResponse),
+        (status = 400, description = "Plugin cannot be reloaded or its manifest is invalid", body = PluginErrorResponse),
         (status = 404, description = "Plugin not found", body = PluginErrorResponse),
-        (status = 409, description = "Service already running", body = PluginErrorResponse)
+        (status = 500, description = "Reloaded plugin failed to start", body = PluginErrorResponse)
     ),
 )]
-pub async fn start_service(
+pub async fn reload_plugin(
     State(state): State<Arc<AppState>>,
     Path(plugin_id): Path<String>,
-    Json(payload): Json<StartServiceRequest>,
-) -> Result<Json<StartServiceResponse>, (StatusCode, Json<PluginErrorResponse>)> {
-    let plugin = state.plugins.plugin(&plugin_id).await.ok_or((
-        StatusCode::NOT_FOUND,
-        Json(PluginErrorResponse::new("plugin not found")),
-    ))?;
-    plugin
-        .start_service(payload)
+) -> Result<Json<ReloadPluginResponse>, (StatusCode, Json<PluginErrorResponse>)> {
+    state
+        .plugins
+        .reload(&plugin_id)
         .await
+        .ok_or_else(|| plugin_not_found(&plugin_id))?
         .map(Json)
         .map_err(map_error)
 }
 
 #[utoipa::path(
     post,
-    path = "/plugins/{plugin_id}/services/stop",
+    path = "/plugins/{plugin_id}/enable",
     params(("plugin_id" = String, Path, description = "Plugin identifier")),
-    request_body = StopServiceRequest,
     responses(
-        (status = 200, description = "Service stopped", body = StopServiceResponse),
+        (status = 200, description = "Plugin enabled", body = PluginMetadata),
+        (status = 404, description = "Plugin not found", body = PluginErrorResponse)
+    ),
+)]
+pub async fn enable_plugin(
+    State(state): State<Arc<AppState>>,
+    Path(plugin_id): Path<String>,
+) -> Result<Json<PluginMetadata>, (StatusCode, Json<PluginErrorResponse>)> {
+    set_enabled(&state, &plugin_id, true).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/plugins/{plugin_id}/disable",
+    params(("plugin_id" = String, Path, description = "Plugin identifier")),
+    responses(
+        (status = 200, description = "Plugin disabled; its running services are left alone", body = PluginMetadata),
+        (status = 404, description = "Plugin not found", body = PluginErrorResponse)
+    ),
+)]
+pub async fn disable_plugin(
+    State(state): State<Arc<AppState>>,
+    Path(plugin_id): Path<String>,
+) -> Result<Json<PluginMetadata>, (StatusCode, Json<PluginErrorResponse>)> {
+    set_enabled(&state, &plugin_id, false).await
+}
+
+async fn set_enabled(
+    state: &AppState,
+    plugin_id: &str,
+    enabled: bool,
+) -> Result<Json<PluginMetadata>, (StatusCode, Json<PluginErrorResponse>)> {
+    state
+        .plugins
+        .set_enabled(plugin_id, enabled)
+        .await
+        .map(Json)
+        .ok_or_else(|| plugin_not_found(plugin_id))
+}
+
+#[utoipa::path(
+    get,
+    path = "/plugins/{plugin_id}/config",
+    params(("plugin_id" = String, Path, description = "Plugin identifier")),
+    responses(
+        (status = 200, description = "Configuration stored for the plugin", body = PluginConfig),
+        (status = 404, description = "Plugin not found", body = PluginErrorResponse)
+    ),
+)]
+pub async fn get_plugin_config(
+    State(state): State<Arc<AppState>>,
+    Path(plugin_id): Path<String>,
+) -> Result<Json<PluginConfig>, (StatusCode, Json<PluginErrorResponse>)> {
+    state
+        .plugins
+        .config(&plugin_id)
+        .await
+        .map(Json)
+        .ok_or_else(|| plugin_not_found(&plugin_id))
+}
+
+#[utoipa::path(
+    put,
+    path = "/plugins/{plugin_id}/config",
+    params(("plugin_id" = String, Path, description = "Plugin identifier")),
+    request_body = PluginConfig,
+    responses(
+        (status = 200, description = "Configuration applied and stored", body = PluginConfig),
+        (status = 400, description = "Plugin is not configurable or rejected the configuration", body = PluginErrorResponse),
         (status = 404, description = "Plugin not found", body = PluginErrorResponse),
-        (status = 409, description = "Service not running", body = PluginErrorResponse)
+        (status = 500, description = "Configuration could not be stored", body = PluginErrorResponse)
     ),
 )]
-pub async fn stop_service(
+pub async fn set_plugin_config(
     State(state): State<Arc<AppState>>,
     Path(plugin_id): Path<String>,
-    Json(payload): Json<StopServiceRequest>,
-) -> Result<Json<StopServiceResponse>, (StatusCode, Json<PluginErrorResponse>)> {
-    let plugin = state.plugins.plugin(&plugin_id).await.ok_or((
-        StatusCode::NOT_FOUND,
-        Json(PluginErrorResponse::new("plugin not found")),
-    ))?;
+    Json(payload): Json<PluginConfig>,
+) -> Result<Json<PluginConfig>, (StatusCode, Json<PluginErrorResponse>)> {
+    state
+        .plugins
+        .set_config(&plugin_id, payload)
+        .await
+        .ok_or_else(|| plugin_not_found(&plugin_id))?
+        .map(Json)
+        .map_err(map_error)
+}
+
+#[utoipa::path(
+    get,
+    path = "/plugins/{plugin_id}/config/schema",
+    params(("plugin_id" = String, Path, description = "Plugin identifier")),
+    responses(
+        (status = 200, description = "JSON Schema of the plugin's configuration, null when it has none", body = Object),
+        (status = 404, description = "Plugin not found", body = PluginErrorResponse)
+    ),
+)]
+pub async fn get_plugin_config_schema(
+    State(state): State<Arc<AppState>>,
+    Path(plugin_id): Path<String>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<PluginErrorResponse>)> {
+    state
+        .plugins
+        .config_schema(&plugin_id)
+        .await
+        .map(Json)
+        .ok_or_else(|| plugin_not_found(&plugin_id))
+}
+
+#[utoipa::path(
+    post,
+    path = "/plugins/{plugin_id}/invoke/{operation}",
+    params(
+        ("plugin_id" = String, Path, description = "Plugin identifier"),
+        ("operation" = String, Path, description = "Operation the plugin defines, e.g. list-voices")
+    ),
+    request_body = Object,
+    responses(
+        (status = 200, description = "Result of the operation, shaped as the plugin defines", body = Object),
+        (status = 400, description = "Operation not supported", body = PluginErrorResponse),
+        (status = 404, description = "Plugin not found", body = PluginErrorResponse),
+        (status = 409, description = "Plugin is disabled", body = PluginErrorResponse)
+    ),
+)]
+pub async fn invoke_plugin_operation(
+    State(state): State<Arc<AppState>>,
+    Path((plugin_id, operation)): Path<(String, String)>,
+    Json(payload): Json<serde_json::Value>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<PluginErrorResponse>)> {
+    let plugin = active_plugin(&state, &plugin_id).await?;
     plugin
-        .stop_service(payload)
+        .invoke(&operation, payload, InvokeContext::default())
         .await
         .map(Json)
         .map_err(map_error)
 }
 
-pub fn routes(state: Arc<AppState>) -> Router {
-    Router::new()
-        .route("/plugins", get(list_plugins))
-        .route("/plugins/:plugin_id/models/download", post(download_model))
-        .route("/plugins/:plugin_id/services/start", post(start_service))
-        .route("/plugins/:plugin_id/services/stop", post(stop_service))
-        .with_state(state)
+#[utoipa::path(
+    get,
+    path = "/plugins/{plugin_id}/compat",
+    params(("plugin_id" = String, Path, description = "Plugin identifier")),
+    responses(
+        (status = 200, description = "Plugin API version of a registered plugin and the versions this server supports", body = PluginCompatibility),
+        (status = 404, description = "Plugin not found", body = PluginErrorResponse),
+        (status = 422, description = "Plugin was rejected for its plugin API version; `compat` explains why", body = PluginErrorResponse)
+    ),
+)]
+pub async fn get_plugin_compat(
+    State(state): State<Arc<AppState>>,
+    Path(plugin_id): Path<String>,
+) -> Result<Json<PluginCompatibility>, (StatusCode, Json<PluginErrorResponse>)> {
+    state
+        .plugins
+        .compatibility(&plugin_id)
+        .await
+        .ok_or_else(|| plugin_not_found(&plugin_id))?
+        .map(Json)
+        .map_err(map_error)
+}
+
+#[utoipa::path(
+    get,
+    path = "/plugins/{plugin_id}/health",
+    params(("plugin_id" = String, Path, description = "Plugin identifier")),
+    responses(
+        (status = 200, description = "Health of every service the plugin runs, probed now", body = PluginHealthResponse),
+        (status = 400, description = "Operation not supported", body = PluginErrorResponse),
+        (status = 404, description = "Plugin not found", body = PluginErrorResponse),
+        (status = 409, description = "Plugin is disabled", body = PluginErrorResponse)
+    ),
+)]
+pub async fn plugin_health(
+    State(state): State<Arc<AppState>>,
+    Path(plugin_id): Path<String>,
+) -> Result<Json<PluginHealthResponse>, (StatusCode, Json<PluginErrorResponse>)> {
+    let plugin = active_plugin(&state, &plugin_id).await?;
+    plugin.health().await.map(Json).map_err(map_error)
+}
+
+#[utoipa::path(
+    post,
+    path = "/plugins/{plugin_id}/auth/validate",
+    params(("plugin_id" = String, Path, description = "Plugin identifier")),
+    request_body = ValidateTokenRequest,
+    responses(
+        (status = 200, description = "Whether the hub accepts the token, and what it may do", body = ValidateTokenResponse),
+        (status = 400, description = "Invalid request or operation not supported", body = PluginErrorResponse),
+        (status = 404, description = "Plugin not found", body = PluginErrorResponse),
+        (status = 502, description = "Model host request failed", body = PluginErrorResponse),
+        (status = 503, description = "Offline mode is enabled", body = PluginErrorResponse)
+    ),
+)]
+pub async fn validate_token(
+    State(state): State<Arc<AppState>>,
+    Path(plugin_id): Path<String>,
+    Json(payload): Json<ValidateTokenRequest>,
+) -> Result<Json<ValidateTokenResponse>, (StatusCode, Json<PluginErrorResponse>)> {
+    let plugin = active_plugin(&state, &plugin_id).await?;
+    operations::invoke(
+        plugin.as_ref(),
+        operations::VALIDATE_TOKEN,
+        &payload,
+        InvokeContext::default(),
+    )
+    .await
+    .map(Json)
+    .map_err(map_error)
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct AdmissionQuery {
+    /// Queue priority when the operation has to wait for a free slot
+    pub priority: Option<OperationPriority>,
+}
+
+/// Waits for a slot in the operation's queue, or answers 429 with
+/// `Retry-After` when the queue is full.
+async fn admit(
+    state: &AppState,
+    operation: QueuedOperation,
+    priority: Option<OperationPriority>,
+) -> Result<AdmissionPermit, Response> {
+    state
+        .plugins
+        .admission()
+        .await
+        .admit(operation, priority.unwrap_or_default())
+        .await
+        .map_err(queue_full)
+}
+
+/// Like [`admit`], for work done by a background job: fails right away when
+/// the queue is full, and otherwise leaves waiting for a slot to the job.
+async fn join_queue(
+    state: &AppState,
+    operation: QueuedOperation,
+    priority: Option<OperationPriority>,
+) -> Result<QueuedAdmission, Response> {
+    state
+        .plugins
+        .admission()
+        .await
+        .join(operation, priority.unwrap_or_default())
+        .map_err(queue_full)
+}
+
+/// Joins the download queue for a scheduled download once it starts. With
+/// the queue full it waits for room instead of failing, as nobody is there
+/// to retry.
+async fn join_when_free(
+    admission: &AdmissionControl,
+    priority: Option<OperationPriority>,
+    cancel: &CancellationToken,
+) -> Result<QueuedAdmission, PluginError> {
+    loop {
+        match admission.join(QueuedOperation::Download, priority.unwrap_or_default()) {
+            Ok(admission) => return Ok(admission),
+            Err(err) => tracing::info!("scheduled download waits: {}", err),
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(std::time::Duration::from_secs(RETRY_AFTER_SECS)) => {}
+            _ = cancel.cancelled() => return Err(PluginError::Cancelled),
+        }
+    }
+}
+
+fn queue_full(err: QueueFull) -> Response {
+    (
+        StatusCode::TOO_MANY_REQUESTS,
+        [(header::RETRY_AFTER, RETRY_AFTER_SECS.to_string())],
+        Json(PluginErrorResponse {
+            details: Some(serde_json::json!({
+                "operation": err.operation,
+                "queued": err.queued,
+            })),
+            retry_after_secs: Some(RETRY_AFTER_SECS),
+            ..PluginErrorResponse::new(PluginErrorCode::QueueFull, err.to_string())
+        }),
+    )
+        .into_response()
+}
+
+/// Runs plugin work on its own task so it can clean up after itself. If the
+/// handler is dropped because the client disconnected, `cancel` fires and
+/// the work stops at its next cancellation point.
+async fn run_until_disconnect<T, E, F>(cancel: CancellationToken, work: F) -> Result<T, E>
+where
+    T: Send + 'static,
+    E: From<PluginError> + Send + 'static,
+    F: Future<Output = Result<T, E>> + Send + 'static,
+{
+    let guard = cancel.drop_guard();
+    let result = tokio::spawn(work)
+        .await
+        .map_err(|err| E::from(PluginError::Internal(err.to_string())));
+    guard.disarm();
+    result?
+}
+
+#[utoipa::path(
+    get,
+    path = "/plugins/queues",
+    responses((status = 200, description = "Depth of the queues for expensive plugin operations", body = [QueueStatus])),
+)]
+pub async fn list_queues(State(state): State<Arc<AppState>>) -> Json<Vec<QueueStatus>> {
+    Json(state.plugins.admission().await.status())
+}
+
+/// Kind of the jobs model downloads run as.
+pub const MODEL_DOWNLOAD_JOB: &str = "model_download";
+
+#[utoipa::path(
+    post,
+    path = "/plugins/{plugin_id}/models/download",
+    params(("plugin_id" = String, Path, description = "Plugin identifier"), AdmissionQuery),
+    request_body = DownloadModelRequest,
+    responses(
+        (status = 202, description = "Download running in the background, or scheduled to start later; its job's result is a DownloadModelResponse", body = JobStatus),
+        (status = 400, description = "Invalid request or schedule", body = PluginErrorResponse),
+        (status = 403, description = "Destination outside of allowed directories", body = PluginErrorResponse),
+        (status = 404, description = "Plugin not found", body = PluginErrorResponse),
+        (status = 429, description = "Download queue full; retry after the Retry-After delay", body = PluginErrorResponse),
+        (status = 502, description = "Download did not match the pinned commit or recorded checksum", body = PluginErrorResponse)
+    ),
+)]
+pub async fn download_model(
+    State(state): State<Arc<AppState>>,
+    Path(plugin_id): Path<String>,
+    Query(query): Query<AdmissionQuery>,
+    Extension(credential): Extension<RequestCredential>,
+    Json(mut payload): Json<DownloadModelRequest>,
+) -> Result<(StatusCode, Json<JobStatus>), Response> {
+    active_plugin(&state, &plugin_id)
+        .await
+        .map_err(IntoResponse::into_response)?;
+    let chain = fallback_chain(
+        &state,
+        &PluginCapability::ModelDownload,
+        &plugin_id,
+        credential.0.as_ref(),
+    )
+    .await;
+    let start_at = schedule::start_time(
+        payload.start_after,
+        payload.schedule.as_deref(),
+        chrono::Utc::now(),
+    )
+    .map_err(|err| map_error(err).into_response())?;
+    // Scheduled downloads take no place in the queue until they start.
+    let queue = JobQueue::default();
+    let admission = match start_at {
+        Some(_) => None,
+        None => {
+            let admission = join_queue(&state, QueuedOperation::Download, query.priority).await?;
+            queue.joined(admission.ticket());
+            Some(admission)
+        }
+    };
+    let (admissions, priority) = (state.plugins.admission().await, query.priority);
+    let job_queue = queue.clone();
+    let cancel = payload.cancel.clone();
+    let partial = payload.partial.clone();
+    let progress = DownloadProgress::new();
+    payload.progress = progress.clone();
+    let plugins = state.plugins.clone();
+    let pause = DownloadPause::default();
+    let job_pause = pause.clone();
+    let download = async move {
+        let cancel = payload.cancel.clone();
+        let admission = match admission {
+            Some(admission) => admission,
+            None => match join_when_free(&admissions, priority, &cancel).await {
+                Ok(admission) => {
+                    job_queue.joined(admission.ticket());
+                    admission
+                }
+                Err(err) => return Err(FallbackError::from(err)),
+            },
+        };
+        let Some(_permit) = admission.admitted_unless_cancelled(&cancel).await else {
+            return Err(FallbackError::from(PluginError::Cancelled));
+        };
+        // Each transfer stops at a pause; the next one resumes the partial
+        // file with a range request. Paused downloads keep their queue slot.
+        loop {
+            tokio::select! {
+                _ = pause.resumed() => {}
+                _ = cancel.cancelled() => return Err(FallbackError::from(PluginError::Cancelled)),
+            }
+            let mut transfer = payload.clone();
+            transfer.cancel = pause.transfer(&cancel);
+            let outcome = fallback::run_chain(&plugins, &chain, |plugin| {
+                let payload = transfer.clone();
+                async move { plugin.download_model(payload).await }
+            })
+            .await;
+            match outcome {
+                Ok((mut response, attempts)) => {
+                    response.fallback = attempts;
+                    return Ok::<_, FallbackError>(response);
+                }
+                Err(err)
+                    if matches!(err.error, PluginError::Cancelled)
+                        && pause.is_paused()
+                        && !cancel.is_cancelled() =>
+                {
+                    tracing::info!("download of {} paused", payload.filename);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    };
+    let job = state
+        .jobs
+        .spawn(
+            MODEL_DOWNLOAD_JOB,
+            Some(plugin_id),
+            cancel,
+            Some(DownloadJob {
+                progress: &progress,
+                partial,
+                queue,
+                pause: job_pause,
+                start_at,
+            }),
+            download,
+        )
+        .await;
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+/// Kind of the jobs restores from a plugin's lock file run as.
+const MODEL_RESTORE_JOB: &str = "model_restore";
+
+#[utoipa::path(
+    post,
+    path = "/plugins/{plugin_id}/models/restore",
+    params(("plugin_id" = String, Path, description = "Plugin identifier"), AdmissionQuery),
+    request_body = RestoreModelsRequest,
+    responses(
+        (status = 202, description = "Restore running in the background; its job's result is a RestoreModelsResponse", body = JobStatus),
+        (status = 404, description = "Plugin not found", body = PluginErrorResponse),
+        (status = 429, description = "Download queue full; retry after the Retry-After delay", body = PluginErrorResponse)
+    ),
+)]
+pub async fn restore_models(
+    State(state): State<Arc<AppState>>,
+    Path(plugin_id): Path<String>,
+    Query(query): Query<AdmissionQuery>,
+    Json(payload): Json<RestoreModelsRequest>,
+) -> Result<(StatusCode, Json<JobStatus>), Response> {
+    let plugin = active_plugin(&state, &plugin_id)
+        .await
+        .map_err(IntoResponse::into_response)?;
+    let admission = join_queue(&state, QueuedOperation::Download, query.priority).await?;
+    let context = InvokeContext::default();
+    let cancel = context.cancel.clone();
+    let restore = async move {
+        let Some(_permit) = admission.admitted_unless_cancelled(&context.cancel).await else {
+            return Err(PluginError::Cancelled);
+        };
+        operations::invoke::<RestoreModelsResponse>(
+            plugin.as_ref(),
+            operations::RESTORE_MODELS,
+            &payload,
+            context,
+        )
+        .await
+    };
+    let job = state
+        .jobs
+        .spawn(MODEL_RESTORE_JOB, Some(plugin_id), cancel, None, restore)
+        .await;
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/plugins/{plugin_id}/models/health",
+    params(("plugin_id" = String, Path, description = "Plugin identifier")),
+    responses(
+        (status = 200, description = "Damaged files the last integrity scan of the model directories found", body = ModelHealthResponse),
+        (status = 400, description = "Operation not supported", body = PluginErrorResponse),
+        (status = 404, description = "Plugin not found", body = PluginErrorResponse)
+    ),
+)]
+pub async fn model_health(
+    State(state): State<Arc<AppState>>,
+    Path(plugin_id): Path<String>,
+) -> Result<Json<ModelHealthResponse>, (StatusCode, Json<PluginErrorResponse>)> {
+    let plugin = active_plugin(&state, &plugin_id).await?;
+    operations::invoke(
+        plugin.as_ref(),
+        operations::MODEL_HEALTH,
+        &(),
+        InvokeContext::default(),
+    )
+    .await
+    .map(Json)
+    .map_err(map_error)
+}
+
+/// Kind of the jobs repairs of damaged model files run as.
+const MODEL_REPAIR_JOB: &str = "model_repair";
+
+#[utoipa::path(
+    post,
+    path = "/plugins/{plugin_id}/models/health/repair",
+    params(("plugin_id" = String, Path, description = "Plugin identifier"), AdmissionQuery),
+    request_body = RepairModelsRequest,
+    responses(
+        (status = 202, description = "Repair running in the background; its job's result is a RepairModelsResponse", body = JobStatus),
+        (status = 404, description = "Plugin not found", body = PluginErrorResponse),
+        (status = 429, description = "Download queue full; retry after the Retry-After delay", body = PluginErrorResponse)
+    ),
+)]
+pub async fn repair_models(
+    State(state): State<Arc<AppState>>,
+    Path(plugin_id): Path<String>,
+    Query(query): Query<AdmissionQuery>,
+    Json(payload): Json<RepairModelsRequest>,
+) -> Result<(StatusCode, Json<JobStatus>), Response> {
+    let plugin = active_plugin(&state, &plugin_id)
+        .await
+        .map_err(IntoResponse::into_response)?;
+    let admission = join_queue(&state, QueuedOperation::Download, query.priority).await?;
+    let context = InvokeContext::default();
+    let cancel = context.cancel.clone();
+    let repair = async move {
+        let Some(_permit) = admission.admitted_unless_cancelled(&context.cancel).await else {
+            return Err(PluginError::Cancelled);
+        };
+        operations::invoke::<RepairModelsResponse>(
+            plugin.as_ref(),
+            operations::REPAIR_MODELS,
+            &payload,
+            context,
+        )
+        .await
+    };
+    let job = state
+        .jobs
+        .spawn(MODEL_REPAIR_JOB, Some(plugin_id), cancel, None, repair)
+        .await;
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+/// The job of a download `plugin_id` was asked for.
+async fn download_job(
+    state: &AppState,
+    plugin_id: &str,
+    job_id: &str,
+) -> Result<JobStatus, (StatusCode, Json<PluginErrorResponse>)> {
+    plugin_job(state, plugin_id, job_id, MODEL_DOWNLOAD_JOB, "download").await
+}
+
+/// The job of kind `kind` that `plugin_id` was asked for, described as
+/// `what` when there is none.
+async fn plugin_job(
+    state: &AppState,
+    plugin_id: &str,
+    job_id: &str,
+    kind: &str,
+    what: &str,
+) -> Result<JobStatus, (StatusCode, Json<PluginErrorResponse>)> {
+    state
+        .jobs
+        .get(job_id)
+        .await
+        .ok()
+        .filter(|job| job.kind == kind && job.plugin_id.as_deref() == Some(plugin_id))
+        .ok_or_else(|| {
+            map_error(PluginError::NotFound(format!(
+                "{} {} of plugin {}",
+                what, job_id, plugin_id
+            )))
+        })
+}
+
+#[utoipa::path(
+    get,
+    path = "/plugins/{plugin_id}/models/downloads",
+    params(("plugin_id" = String, Path, description = "Plugin identifier")),
+    responses(
+        (status = 200, description = "Downloads asked of the plugin, newest first", body = [JobStatus])
+    ),
+)]
+pub async fn list_downloads(
+    State(state): State<Arc<AppState>>,
+    Path(plugin_id): Path<String>,
+) -> Json<Vec<JobStatus>> {
+    let mut jobs = state.jobs.list().await;
+    jobs.retain(|job| {
+        job.kind == MODEL_DOWNLOAD_JOB && job.plugin_id.as_deref() == Some(&plugin_id)
+    });
+    Json(jobs)
+}
+
+#[utoipa::path(
+    get,
+    path = "/plugins/{plugin_id}/models/downloads/{job_id}",
+    params(
+        ("plugin_id" = String, Path, description = "Plugin identifier"),
+        ("job_id" = String, Path, description = "Download identifier, the id of its job")
+    ),
+    responses(
+        (status = 200, description = "Download status, with a DownloadModelResponse as result once it succeeded", body = JobStatus),
+        (status = 404, description = "No such download for this plugin", body = PluginErrorResponse)
+    ),
+)]
+pub async fn get_download(
+    State(state): State<Arc<AppState>>,
+    Path((plugin_id, job_id)): Path<(String, String)>,
+) -> Result<Json<JobStatus>, (StatusCode, Json<PluginErrorResponse>)> {
+    download_job(&state, &plugin_id, &job_id).await.map(Json)
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct CancelDownloadQuery {
+    /// Keep what was written so far for a later download of the same file
+    /// to resume instead of removing it
+    #[serde(default)]
+    pub keep_partial: bool,
+}
+
+#[utoipa::path(
+    post,
+    path = "/plugins/{plugin_id}/models/downloads/{job_id}/cancel",
+    params(
+        ("plugin_id" = String, Path, description = "Plugin identifier"),
+        ("job_id" = String, Path, description = "Download identifier, the id of its job"),
+        CancelDownloadQuery
+    ),
+    responses(
+        (status = 200, description = "Cancellation requested; the download is marked cancelled once the transfer stops", body = JobStatus),
+        (status = 404, description = "No such download for this plugin", body = PluginErrorResponse),
+        (status = 409, description = "Download already finished", body = PluginErrorResponse)
+    ),
+)]
+pub async fn cancel_download(
+    State(state): State<Arc<AppState>>,
+    Path((plugin_id, job_id)): Path<(String, String)>,
+    Query(query): Query<CancelDownloadQuery>,
+) -> Result<Json<JobStatus>, (StatusCode, Json<PluginErrorResponse>)> {
+    download_job(&state, &plugin_id, &job_id).await?;
+    state
+        .jobs
+        .cancel_download(&job_id, query.keep_partial)
+        .await
+        .map(Json)
+        .map_err(map_job_error)
+}
+
+fn map_job_error(err: JobError) -> (StatusCode, Json<PluginErrorResponse>) {
+    match err {
+        JobError::UnknownJob(_) => map_error(PluginError::NotFound(err.to_string())),
+        JobError::Finished(_) | JobError::NotPausable(_) | JobError::NotQueued(_) => (
+            StatusCode::CONFLICT,
+            Json(PluginErrorResponse::new(
+                PluginErrorCode::InvalidRequest,
+                err.to_string(),
+            )),
+        ),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/plugins/{plugin_id}/models/downloads/{job_id}/pause",
+    params(
+        ("plugin_id" = String, Path, description = "Plugin identifier"),
+        ("job_id" = String, Path, description = "Download identifier, the id of its job")
+    ),
+    responses(
+        (status = 200, description = "Download paused; its partial file is kept for the resume", body = JobStatus),
+        (status = 404, description = "No such download for this plugin", body = PluginErrorResponse),
+        (status = 409, description = "Download already finished", body = PluginErrorResponse)
+    ),
+)]
+pub async fn pause_download(
+    State(state): State<Arc<AppState>>,
+    Path((plugin_id, job_id)): Path<(String, String)>,
+) -> Result<Json<JobStatus>, (StatusCode, Json<PluginErrorResponse>)> {
+    download_job(&state, &plugin_id, &job_id).await?;
+    state
+        .jobs
+        .pause_download(&job_id)
+        .await
+        .map(Json)
+        .map_err(map_job_error)
+}
+
+#[utoipa::path(
+    post,
+    path = "/plugins/{plugin_id}/models/downloads/{job_id}/resume",
+    params(
+        ("plugin_id" = String, Path, description = "Plugin identifier"),
+        ("job_id" = String, Path, description = "Download identifier, the id of its job")
+    ),
+    responses(
+        (status = 200, description = "Download continues where it was paused", body = JobStatus),
+        (status = 404, description = "No such download for this plugin", body = PluginErrorResponse),
+        (status = 409, description = "Download already finished", body = PluginErrorResponse)
+    ),
+)]
+pub async fn resume_download(
+    State(state): State<Arc<AppState>>,
+    Path((plugin_id, job_id)): Path<(String, String)>,
+) -> Result<Json<JobStatus>, (StatusCode, Json<PluginErrorResponse>)> {
+    download_job(&state, &plugin_id, &job_id).await?;
+    state
+        .jobs
+        .resume_download(&job_id)
+        .await
+        .map(Json)
+        .map_err(map_job_error)
+}
+
+#[utoipa::path(
+    get,
+    path = "/plugins/{plugin_id}/models/downloads/{job_id}/progress",
+    params(
+        ("plugin_id" = String, Path, description = "Plugin identifier"),
+        ("job_id" = String, Path, description = "Job of a background download")
+    ),
+    responses(
+        (status = 200, description = "`progress` events while the download runs, then a `status` event with its job", content_type = "text/event-stream", body = crate::plugins::progress::DownloadProgressUpdate),
+        (status = 404, description = "No such download for this plugin", body = PluginErrorResponse)
+    ),
+)]
+pub async fn download_progress(
+    State(state): State<Arc<AppState>>,
+    Path((plugin_id, job_id)): Path<(String, String)>,
+) -> Result<
+    Sse<impl Stream<Item = Result<Event, Infallible>>>,
+    (StatusCode, Json<PluginErrorResponse>),
+> {
+    download_job(&state, &plugin_id, &job_id).await?;
+    progress_events(state.jobs.clone(), job_id, "download").await
+}
+
+/// `progress` events of the job `job_id`, then a `status` event once it has
+/// finished.
+async fn progress_events(
+    jobs: Arc<JobRegistry>,
+    job_id: String,
+    what: &'static str,
+) -> Result<
+    Sse<impl Stream<Item = Result<Event, Infallible>>>,
+    (StatusCode, Json<PluginErrorResponse>),
+> {
+    let mut updates = jobs.progress(&job_id).await.ok().flatten().ok_or_else(|| {
+        map_error(PluginError::NotFound(format!(
+            "progress of {} {}",
+            what, job_id
+        )))
+    })?;
+    // Starts with the latest update.
+    updates.mark_changed();
+
+    let stream = futures::stream::unfold(Some(updates), move |updates| {
+        let (jobs, job_id) = (jobs.clone(), job_id.clone());
+        async move {
+            let mut updates = updates?;
+            if updates.changed().await.is_ok() {
+                let update = updates.borrow_and_update().clone();
+                let event = Event::default()
+                    .event("progress")
+                    .json_data(&update)
+                    .unwrap_or_default();
+                return Some((Ok(event), Some(updates)));
+            }
+            // The work ended; its job records the outcome right after.
+            loop {
+                let status = jobs.get(&job_id).await.ok()?;
+                if status.state.is_finished() {
+                    let event = Event::default()
+                        .event("status")
+                        .json_data(&status)
+                        .unwrap_or_default();
+                    return Some((Ok(event), None));
+                }
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+        }
+    });
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/plugins/{plugin_id}/models",
+    params(("plugin_id" = String, Path, description = "Plugin identifier")),
+    responses(
+        (status = 200, description = "Downloaded models tracked by the plugin", body = ListModelsResponse),
+        (status = 400, description = "Operation not supported", body = PluginErrorResponse),
+        (status = 404, description = "Plugin not found", body = PluginErrorResponse)
+    ),
+)]
+pub async fn list_models(
+    State(state): State<Arc<AppState>>,
+    Path(plugin_id): Path<String>,
+) -> Result<Json<ListModelsResponse>, (StatusCode, Json<PluginErrorResponse>)> {
+    let plugin = active_plugin(&state, &plugin_id).await?;
+    plugin.list_models().await.map(Json).map_err(map_error)
+}
+
+#[utoipa::path(
+    get,
+    path = "/plugins/{plugin_id}/models/usage",
+    params(("plugin_id" = String, Path, description = "Plugin identifier")),
+    responses(
+        (status = 200, description = "Space used in each task type's model directory", body = ModelUsageResponse),
+        (status = 400, description = "Operation not supported", body = PluginErrorResponse),
+        (status = 404, description = "Plugin not found", body = PluginErrorResponse)
+    ),
+)]
+pub async fn model_usage(
+    State(state): State<Arc<AppState>>,
+    Path(plugin_id): Path<String>,
+) -> Result<Json<ModelUsageResponse>, (StatusCode, Json<PluginErrorResponse>)> {
+    let plugin = active_plugin(&state, &plugin_id).await?;
+    operations::invoke(
+        plugin.as_ref(),
+        operations::MODEL_USAGE,
+        &(),
+        InvokeContext::default(),
+    )
+    .await
+    .map(Json)
+    .map_err(map_error)
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct DeleteModelQuery {
+    /// Task type the model was downloaded for
+    pub task_type: PluginTaskType,
+    /// File name relative to the task type's model directory
+    pub filename: String,
+    /// Directory the model was downloaded to, when not the default
+    pub destination_dir: Option<String>,
+}
+
+#[utoipa::path(
+    delete,
+    path = "/plugins/{plugin_id}/models",
+    params(
+        ("plugin_id" = String, Path, description = "Plugin identifier"),
+        DeleteModelQuery
+    ),
+    responses(
+        (status = 200, description = "Model deleted", body = DeleteModelResponse),
+        (status = 400, description = "Invalid request", body = PluginErrorResponse),
+        (status = 403, description = "Path not allowed", body = PluginErrorResponse),
+        (status = 404, description = "Plugin or model not found", body = PluginErrorResponse),
+        (status = 409, description = "Model used by a running service", body = PluginErrorResponse)
+    ),
+)]
+pub async fn delete_model(
+    State(state): State<Arc<AppState>>,
+    Path(plugin_id): Path<String>,
+    Query(query): Query<DeleteModelQuery>,
+) -> Result<Json<DeleteModelResponse>, (StatusCode, Json<PluginErrorResponse>)> {
+    let plugin = active_plugin(&state, &plugin_id).await?;
+    operations::invoke(
+        plugin.as_ref(),
+        operations::DELETE_MODEL,
+        &DeleteModelRequest {
+            task_type: query.task_type,
+            filename: query.filename,
+            destination_dir: query.destination_dir,
+        },
+        InvokeContext::default(),
+    )
+    .await
+    .map(Json)
+    .map_err(map_error)
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct PinModelQuery {
+    /// Task type the model was downloaded for
+    pub task_type: PluginTaskType,
+    /// Directory the model was downloaded to, when not the default
+    pub destination_dir: Option<String>,
+}
+
+async fn set_model_pinned(
+    state: &AppState,
+    plugin_id: &str,
+    filename: String,
+    query: PinModelQuery,
+    pinned: bool,
+) -> Result<Json<ModelRecord>, (StatusCode, Json<PluginErrorResponse>)> {
+    let plugin = active_plugin(state, plugin_id).await?;
+    operations::invoke(
+        plugin.as_ref(),
+        operations::PIN_MODEL,
+        &PinModelRequest {
+            task_type: query.task_type,
+            filename,
+            destination_dir: query.destination_dir,
+            pinned,
+        },
+        InvokeContext::default(),
+    )
+    .await
+    .map(Json)
+    .map_err(map_error)
+}
+
+#[utoipa::path(
+    post,
+    path = "/plugins/{plugin_id}/models/{filename}/pin",
+    params(
+        ("plugin_id" = String, Path, description = "Plugin identifier"),
+        ("filename" = String, Path, description = "File name relative to the model directory, with `/` escaped as `%2F`"),
+        PinModelQuery
+    ),
+    responses(
+        (status = 200, description = "Model pinned; garbage collection and quota eviction keep it", body = ModelRecord),
+        (status = 400, description = "Invalid request", body = PluginErrorResponse),
+        (status = 403, description = "Path not allowed", body = PluginErrorResponse),
+        (status = 404, description = "Plugin or model not found", body = PluginErrorResponse)
+    ),
+)]
+pub async fn pin_model(
+    State(state): State<Arc<AppState>>,
+    Path((plugin_id, filename)): Path<(String, String)>,
+    Query(query): Query<PinModelQuery>,
+) -> Result<Json<ModelRecord>, (StatusCode, Json<PluginErrorResponse>)> {
+    set_model_pinned(&state, &plugin_id, filename, query, true).await
+}
+
+#[utoipa::path(
+    delete,
+    path = "/plugins/{plugin_id}/models/{filename}/pin",
+    params(
+        ("plugin_id" = String, Path, description = "Plugin identifier"),
+        ("filename" = String, Path, description = "File name relative to the model directory, with `/` escaped as `%2F`"),
+        PinModelQuery
+    ),
+    responses(
+        (status = 200, description = "Model unpinned", body = ModelRecord),
+        (status = 400, description = "Invalid request", body = PluginErrorResponse),
+        (status = 403, description = "Path not allowed", body = PluginErrorResponse),
+        (status = 404, description = "Plugin or model not found", body = PluginErrorResponse)
+    ),
+)]
+pub async fn unpin_model(
+    State(state): State<Arc<AppState>>,
+    Path((plugin_id, filename)): Path<(String, String)>,
+    Query(query): Query<PinModelQuery>,
+) -> Result<Json<ModelRecord>, (StatusCode, Json<PluginErrorResponse>)> {
+    set_model_pinned(&state, &plugin_id, filename, query, false).await
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ModelAdaptersQuery {
+    /// Task type the model was downloaded for
+    pub task_type: PluginTaskType,
+    /// Directory the model was downloaded to, when not the default
+    pub destination_dir: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/plugins/{plugin_id}/models/{filename}/adapters",
+    params(
+        ("plugin_id" = String, Path, description = "Plugin identifier"),
+        ("filename" = String, Path, description = "File name relative to the model directory, with `/` escaped as `%2F`"),
+        ModelAdaptersQuery
+    ),
+    responses(
+        (status = 200, description = "LoRA adapters downloaded for the model, with whether they match its architecture", body = ListAdaptersResponse),
+        (status = 400, description = "Invalid request", body = PluginErrorResponse),
+        (status = 403, description = "Path not allowed", body = PluginErrorResponse),
+        (status = 404, description = "Plugin or model not found", body = PluginErrorResponse)
+    ),
+)]
+pub async fn list_adapters(
+    State(state): State<Arc<AppState>>,
+    Path((plugin_id, filename)): Path<(String, String)>,
+    Query(query): Query<ModelAdaptersQuery>,
+) -> Result<Json<ListAdaptersResponse>, (StatusCode, Json<PluginErrorResponse>)> {
+    let plugin = active_plugin(&state, &plugin_id).await?;
+    operations::invoke(
+        plugin.as_ref(),
+        operations::LIST_ADAPTERS,
+        &ListAdaptersRequest {
+            task_type: query.task_type,
+            filename,
+            destination_dir: query.destination_dir,
+        },
+        InvokeContext::default(),
+    )
+    .await
+    .map(Json)
+    .map_err(map_error)
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ShareModelQuery {
+    /// Repository the model was downloaded from
+    pub model_id: String,
+    /// File name relative to the repository
+    pub filename: String,
+    /// Branch, tag or commit the file was downloaded at; the latest download
+    /// when unset
+    pub revision: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/plugins/{plugin_id}/models/share",
+    params(("plugin_id" = String, Path, description = "Plugin identifier"), ShareModelQuery),
+    responses(
+        (status = 200, description = "The model file, with its SHA-256 in `X-Goose-Sha256` and its commit in `X-Repo-Commit`", content_type = "application/octet-stream", body = Vec<u8>),
+        (status = 206, description = "The bytes asked for with a `Range` header", content_type = "application/octet-stream", body = Vec<u8>),
+        (status = 404, description = "Plugin not found, or no shareable download of the file; encrypted files and files without a recorded checksum are not shared", body = PluginErrorResponse),
+        (status = 416, description = "Range past the end of the file")
+    ),
+)]
+pub async fn share_model(
+    State(state): State<Arc<AppState>>,
+    Path(plugin_id): Path<String>,
+    Query(query): Query<ShareModelQuery>,
+    headers: http::HeaderMap,
+) -> Result<Response, (StatusCode, Json<PluginErrorResponse>)> {
+    let plugin = active_plugin(&state, &plugin_id).await?;
+    let models = plugin.list_models().await.map_err(map_error)?.models;
+    let record = peers::find_shared(
+        &models,
+        &query.model_id,
+        &query.filename,
+        query.revision.as_deref(),
+    )
+    .ok_or_else(|| {
+        map_error(PluginError::NotFound(format!(
+            "shared model {}/{}",
+            query.model_id, query.filename
+        )))
+    })?;
+    let len = match tokio::fs::metadata(&record.saved_path).await {
+        Ok(metadata) => metadata.len(),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            return Err(map_error(PluginError::NotFound(format!(
+                "model {}",
+                record.saved_path
+            ))));
+        }
+        Err(err) => return Err(map_error(err.into())),
+    };
+
+    let range = headers
+        .get(header::RANGE)
+        .and_then(|value| value.to_str().ok());
+    let (status, range) = match peers::byte_range(range, len) {
+        Ok(Some(range)) => (StatusCode::PARTIAL_CONTENT, range),
+        Ok(None) => (StatusCode::OK, 0..len),
+        Err(RangeNotSatisfiable) => {
+            return Ok((
+                StatusCode::RANGE_NOT_SATISFIABLE,
+                [(header::CONTENT_RANGE, format!("bytes */{}", len))],
+            )
+                .into_response());
+        }
+    };
+    let mut response = Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "application/octet-stream")
+        .header(header::CONTENT_LENGTH, range.end - range.start)
+        .header(header::ACCEPT_RANGES, "bytes")
+        .header(
+            peers::SHA256_HEADER,
+            record.sha256.as_deref().unwrap_or_default(),
+        );
+    if status == StatusCode::PARTIAL_CONTENT {
+        response = response.header(
+            header::CONTENT_RANGE,
+            format!("bytes {}-{}/{}", range.start, range.end - 1, len),
+        );
+    }
+    if let Some(commit) = &record.commit {
+        response = response.header(REPO_COMMIT_HEADER, commit);
+    }
+    let body =
+        axum::body::Body::from_stream(peers::read_range(record.saved_path.clone().into(), range));
+    response
+        .body(body)
+        .map_err(|err| map_error(PluginError::Internal(err.to_string())))
+}
+
+#[utoipa::path(
+    post,
+    path = "/plugins/{plugin_id}/models/import",
+    params(("plugin_id" = String, Path, description = "Plugin identifier")),
+    request_body = ImportModelRequest,
+    responses(
+        (status = 200, description = "Model imported", body = ImportModelResponse),
+        (status = 400, description = "Invalid request", body = PluginErrorResponse),
+        (status = 403, description = "Path not allowed", body = PluginErrorResponse),
+        (status = 404, description = "Plugin or source file not found", body = PluginErrorResponse),
+        (status = 429, description = "Model directory quota exceeded", body = PluginErrorResponse),
+        (status = 507, description = "Not enough disk space for a copy", body = PluginErrorResponse)
+    ),
+)]
+pub async fn import_model(
+    State(state): State<Arc<AppState>>,
+    Path(plugin_id): Path<String>,
+    Json(payload): Json<ImportModelRequest>,
+) -> Result<Json<ImportModelResponse>, (StatusCode, Json<PluginErrorResponse>)> {
+    let plugin = active_plugin(&state, &plugin_id).await?;
+    operations::invoke(
+        plugin.as_ref(),
+        operations::IMPORT_MODEL,
+        &payload,
+        InvokeContext::default(),
+    )
+    .await
+    .map(Json)
+    .map_err(map_error)
+}
+
+/// Kind of the jobs model conversions run as.
+pub const MODEL_CONVERT_JOB: &str = "model_convert";
+
+#[utoipa::path(
+    post,
+    path = "/plugins/{plugin_id}/models/convert",
+    params(("plugin_id" = String, Path, description = "Plugin identifier")),
+    request_body = ConvertModelRequest,
+    responses(
+        (status = 202, description = "Conversion running in the background; its job's result is a ConvertModelResponse", body = JobStatus),
+        (status = 404, description = "Plugin not found", body = PluginErrorResponse)
+    ),
+)]
+pub async fn convert_model(
+    State(state): State<Arc<AppState>>,
+    Path(plugin_id): Path<String>,
+    Json(payload): Json<ConvertModelRequest>,
+) -> Result<(StatusCode, Json<JobStatus>), (StatusCode, Json<PluginErrorResponse>)> {
+    let plugin = active_plugin(&state, &plugin_id).await?;
+    let context = InvokeContext::default();
+    let cancel = context.cancel.clone();
+    let progress = context.progress.clone();
+    let job = state
+        .jobs
+        .spawn_with_progress(
+            MODEL_CONVERT_JOB,
+            Some(plugin_id),
+            cancel,
+            &progress,
+            async move {
+                operations::invoke::<ConvertModelResponse>(
+                    plugin.as_ref(),
+                    operations::CONVERT_MODEL,
+                    &payload,
+                    context,
+                )
+                .await
+            },
+        )
+        .await;
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/plugins/{plugin_id}/models/conversions/{job_id}/progress",
+    params(
+        ("plugin_id" = String, Path, description = "Plugin identifier"),
+        ("job_id" = String, Path, description = "Job of a background conversion")
+    ),
+    responses(
+        (status = 200, description = "`progress` events with the bytes written while the conversion runs, then a `status` event with its job", content_type = "text/event-stream", body = crate::plugins::progress::DownloadProgressUpdate),
+        (status = 404, description = "No such conversion for this plugin", body = PluginErrorResponse)
+    ),
+)]
+pub async fn conversion_progress(
+    State(state): State<Arc<AppState>>,
+    Path((plugin_id, job_id)): Path<(String, String)>,
+) -> Result<
+    Sse<impl Stream<Item = Result<Event, Infallible>>>,
+    (StatusCode, Json<PluginErrorResponse>),
+> {
+    plugin_job(&state, &plugin_id, &job_id, MODEL_CONVERT_JOB, "conversion").await?;
+    progress_events(state.jobs.clone(), job_id, "conversion").await
+}
+
+/// Kind of the jobs model quantizations run as.
+pub const MODEL_QUANTIZE_JOB: &str = "model_quantize";
+
+#[utoipa::path(
+    post,
+    path = "/plugins/{plugin_id}/models/quantize",
+    params(("plugin_id" = String, Path, description = "Plugin identifier")),
+    request_body = QuantizeModelRequest,
+    responses(
+        (status = 202, description = "Quantization running in the background; its job's result is a QuantizeModelResponse", body = JobStatus),
+        (status = 404, description = "Plugin not found", body = PluginErrorResponse)
+    ),
+)]
+pub async fn quantize_model(
+    State(state): State<Arc<AppState>>,
+    Path(plugin_id): Path<String>,
+    Json(payload): Json<QuantizeModelRequest>,
+) -> Result<(StatusCode, Json<JobStatus>), (StatusCode, Json<PluginErrorResponse>)> {
+    let plugin = active_plugin(&state, &plugin_id).await?;
+    let context = InvokeContext::default();
+    let cancel = context.cancel.clone();
+    let progress = context.progress.clone();
+    let job = state
+        .jobs
+        .spawn_with_progress(
+            MODEL_QUANTIZE_JOB,
+            Some(plugin_id),
+            cancel,
+            &progress,
+            async move {
+                operations::invoke::<QuantizeModelResponse>(
+                    plugin.as_ref(),
+                    operations::QUANTIZE_MODEL,
+                    &payload,
+                    context,
+                )
+                .await
+            },
+        )
+        .await;
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/plugins/{plugin_id}/models/quantizations/{job_id}/progress",
+    params(
+        ("plugin_id" = String, Path, description = "Plugin identifier"),
+        ("job_id" = String, Path, description = "Job of a background quantization")
+    ),
+    responses(
+        (status = 200, description = "`progress` events with the bytes written while the quantization runs, then a `status` event with its job", content_type = "text/event-stream", body = crate::plugins::progress::DownloadProgressUpdate),
+        (status = 404, description = "No such quantization for this plugin", body = PluginErrorResponse)
+    ),
+)]
+pub async fn quantization_progress(
+    State(state): State<Arc<AppState>>,
+    Path((plugin_id, job_id)): Path<(String, String)>,
+) -> Result<
+    Sse<impl Stream<Item = Result<Event, Infallible>>>,
+    (StatusCode, Json<PluginErrorResponse>),
+> {
+    plugin_job(
+        &state,
+        &plugin_id,
+        &job_id,
+        MODEL_QUANTIZE_JOB,
+        "quantization",
+    )
+    .await?;
+    progress_events(state.jobs.clone(), job_id, "quantization").await
+}
+
+#[utoipa::path(
+    get,
+    path = "/plugins/{plugin_id}/nodes",
+    params(("plugin_id" = String, Path, description = "Plugin identifier")),
+    responses(
+        (status = 200, description = "Remote nodes the plugin can manage", body = ListNodesResponse),
+        (status = 400, description = "Operation not supported", body = PluginErrorResponse),
+        (status = 404, description = "Plugin not found", body = PluginErrorResponse)
+    ),
+)]
+pub async fn list_nodes(
+    State(state): State<Arc<AppState>>,
+    Path(plugin_id): Path<String>,
+) -> Result<Json<ListNodesResponse>, (StatusCode, Json<PluginErrorResponse>)> {
+    let plugin = active_plugin(&state, &plugin_id).await?;
+    plugin.list_nodes().await.map(Json).map_err(map_error)
+}
+
+#[utoipa::path(
+    get,
+    path = "/plugins/{plugin_id}/profiles",
+    params(("plugin_id" = String, Path, description = "Plugin identifier")),
+    responses(
+        (status = 200, description = "Hardware profiles usable in start requests", body = ListProfilesResponse),
+        (status = 400, description = "Operation not supported", body = PluginErrorResponse),
+        (status = 404, description = "Plugin not found", body = PluginErrorResponse)
+    ),
+)]
+pub async fn list_profiles(
+    State(state): State<Arc<AppState>>,
+    Path(plugin_id): Path<String>,
+) -> Result<Json<ListProfilesResponse>, (StatusCode, Json<PluginErrorResponse>)> {
+    let plugin = active_plugin(&state, &plugin_id).await?;
+    plugin.list_profiles().await.map(Json).map_err(map_error)
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ModelRevisionsQuery {
+    /// Repository to inspect, e.g. `org/model`
+    pub model_id: String,
+    /// Branch, tag or commit whose history is listed (defaults to `main`)
+    pub revision: Option<String>,
+    /// Maximum number of commits to return
+    pub limit: Option<usize>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/plugins/{plugin_id}/models/revisions",
+    params(
+        ("plugin_id" = String, Path, description = "Plugin identifier"),
+        ModelRevisionsQuery
+    ),
+    responses(
+        (status = 200, description = "Branches, tags and recent commits of the repository", body = ModelRevisionsResponse),
+        (status = 400, description = "Invalid request", body = PluginErrorResponse),
+        (status = 403, description = "Model license not accepted on the hub", body = PluginErrorResponse),
+        (status = 404, description = "Plugin not found", body = PluginErrorResponse),
+        (status = 502, description = "Model host request failed", body = PluginErrorResponse)
+    ),
+)]
+pub async fn list_revisions(
+    State(state): State<Arc<AppState>>,
+    Path(plugin_id): Path<String>,
+    Query(query): Query<ModelRevisionsQuery>,
+) -> Result<Json<ModelRevisionsResponse>, (StatusCode, Json<PluginErrorResponse>)> {
+    let plugin = active_plugin(&state, &plugin_id).await?;
+    plugin
+        .list_revisions(ModelRevisionsRequest {
+            model_id: query.model_id,
+            revision: query.revision.unwrap_or_else(|| "main".to_string()),
+            limit: query.limit,
+        })
+        .await
+        .map(Json)
+        .map_err(map_error)
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ModelFilesQuery {
+    /// Branch, tag or commit whose files are listed (defaults to `main`)
+    pub revision: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/plugins/{plugin_id}/models/remote/{model_id}/files",
+    params(
+        ("plugin_id" = String, Path, description = "Plugin identifier"),
+        ("model_id" = String, Path, description = "Repository to list, e.g. `org/model`"),
+        ModelFilesQuery
+    ),
+    responses(
+        (status = 200, description = "Files of the repository with their sizes and LFS hashes", body = ModelFilesResponse),
+        (status = 400, description = "Invalid request", body = PluginErrorResponse),
+        (status = 403, description = "Model license not accepted on the hub", body = PluginErrorResponse),
+        (status = 404, description = "Plugin or repository not found", body = PluginErrorResponse),
+        (status = 502, description = "Model host request failed", body = PluginErrorResponse),
+        (status = 503, description = "Offline mode is enabled", body = PluginErrorResponse)
+    ),
+)]
+pub async fn list_model_files(
+    State(state): State<Arc<AppState>>,
+    Path((plugin_id, model_path)): Path<(String, String)>,
+    Query(query): Query<ModelFilesQuery>,
+) -> Result<Json<ModelFilesResponse>, (StatusCode, Json<PluginErrorResponse>)> {
+    // The wildcard holding the repository also takes the `/files` after it.
+    let model_id = model_path
+        .strip_suffix("/files")
+        .ok_or_else(|| {
+            map_error(PluginError::NotFound(format!(
+                "/models/remote/{}",
+                model_path
+            )))
+        })?
+        .to_string();
+    let plugin = active_plugin(&state, &plugin_id).await?;
+    operations::invoke(
+        plugin.as_ref(),
+        operations::LIST_MODEL_FILES,
+        &ModelFilesRequest {
+            model_id,
+            revision: query.revision.unwrap_or_else(|| "main".to_string()),
+        },
+        InvokeContext::default(),
+    )
+    .await
+    .map(Json)
+    .map_err(map_error)
+}
+
+#[utoipa::path(
+    post,
+    path = "/plugins/{plugin_id}/models/check-updates",
+    params(("plugin_id" = String, Path, description = "Plugin identifier")),
+    responses(
+        (status = 200, description = "Models with a newer upstream commit", body = ModelUpdatesResponse),
+        (status = 400, description = "Operation not supported", body = PluginErrorResponse),
+        (status = 404, description = "Plugin not found", body = PluginErrorResponse)
+    ),
+)]
+pub async fn check_model_updates(
+    State(state): State<Arc<AppState>>,
+    Path(plugin_id): Path<String>,
+) -> Result<Json<ModelUpdatesResponse>, (StatusCode, Json<PluginErrorResponse>)> {
+    let plugin = active_plugin(&state, &plugin_id).await?;
+    plugin
+        .check_model_updates()
+        .await
+        .map(Json)
+        .map_err(map_error)
+}
+
+#[utoipa::path(
+    post,
+    path = "/plugins/{plugin_id}/models/gc",
+    params(("plugin_id" = String, Path, description = "Plugin identifier")),
+    request_body = ModelGcRequest,
+    responses(
+        (status = 200, description = "Models not running and idle past the threshold; deleted when confirmed", body = ModelGcResponse),
+        (status = 400, description = "Operation not supported", body = PluginErrorResponse),
+        (status = 404, description = "Plugin not found", body = PluginErrorResponse)
+    ),
+)]
+pub async fn collect_models(
+    State(state): State<Arc<AppState>>,
+    Path(plugin_id): Path<String>,
+    Json(payload): Json<ModelGcRequest>,
+) -> Result<Json<ModelGcResponse>, (StatusCode, Json<PluginErrorResponse>)> {
+    let plugin = active_plugin(&state, &plugin_id).await?;
+    plugin
+        .collect_models(payload)
+        .await
+        .map(Json)
+        .map_err(map_error)
+}
+
+#[utoipa::path(
+    post,
+    path = "/plugins/{plugin_id}/services/start",
+    params(("plugin_id" = String, Path, description = "Plugin identifier")),
+    request_body = StartServiceRequest,
+    responses(
+        (status = 200, description = "Service started", body = StartServiceResponse),
+        (status = 400, description = "Invalid request", body = PluginErrorResponse),
+        (status = 404, description = "Plugin not found", body = PluginErrorResponse),
+        (status = 409, description = "Service already running", body = PluginErrorResponse),
+        (status = 422, description = "Host resource limits too low for the requested launch", body = PluginErrorResponse),
+        (status = 500, description = "Service failed to start", body = PluginErrorResponse),
+        (status = 504, description = "Service not ready in time; it was stopped", body = PluginErrorResponse)
+    ),
+)]
+pub async fn start_service(
+    State(state): State<Arc<AppState>>,
+    Path(plugin_id): Path<String>,
+    Extension(credential): Extension<RequestCredential>,
+    Json(payload): Json<StartServiceRequest>,
+) -> Result<Json<StartServiceResponse>, (StatusCode, Json<PluginErrorResponse>)> {
+    active_plugin(&state, &plugin_id).await?;
+    start_with_fallbacks(&state, &plugin_id, credential.0.as_ref(), payload).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/plugins/{plugin_id}/services/stop",
+    params(("plugin_id" = String, Path, description = "Plugin identifier")),
+    request_body = StopServiceRequest,
+    responses(
+        (status = 200, description = "Service stopped", body = StopServiceResponse),
+        (status = 400, description = "Service selector missing or ambiguous", body = PluginErrorResponse),
+        (status = 404, description = "Plugin or instance not found", body = PluginErrorResponse),
+        (status = 409, description = "Service not running", body = PluginErrorResponse)
+    ),
+)]
+pub async fn stop_service(
+    State(state): State<Arc<AppState>>,
+    Path(plugin_id): Path<String>,
+    Json(payload): Json<StopServiceRequest>,
+) -> Result<Json<StopServiceResponse>, (StatusCode, Json<PluginErrorResponse>)> {
+    let plugin = active_plugin(&state, &plugin_id).await?;
+    plugin
+        .stop_service(payload)
+        .await
+        .map(Json)
+        .map_err(map_error)
+}
+
+#[utoipa::path(
+    post,
+    path = "/plugins/{plugin_id}/services/upgrade",
+    params(("plugin_id" = String, Path, description = "Plugin identifier"), AdmissionQuery),
+    request_body = UpgradeServiceRequest,
+    responses(
+        (status = 200, description = "Service upgraded and previous instance retired", body = UpgradeServiceResponse),
+        (status = 400, description = "Invalid request", body = PluginErrorResponse),
+        (status = 404, description = "Plugin or instance not found", body = PluginErrorResponse),
+        (status = 409, description = "Service not running", body = PluginErrorResponse),
+        (status = 429, description = "Upgrade queue full; retry after the Retry-After delay", body = PluginErrorResponse),
+        (status = 500, description = "New instance failed to start", body = PluginErrorResponse),
+        (status = 502, description = "Smoke test failed, previous instance kept", body = PluginErrorResponse)
+    ),
+)]
+pub async fn upgrade_service(
+    State(state): State<Arc<AppState>>,
+    Path(plugin_id): Path<String>,
+    Query(query): Query<AdmissionQuery>,
+    Json(mut payload): Json<UpgradeServiceRequest>,
+) -> Result<Json<UpgradeServiceResponse>, Response> {
+    let plugin = active_plugin(&state, &plugin_id)
+        .await
+        .map_err(IntoResponse::into_response)?;
+    let permit = admit(&state, QueuedOperation::Upgrade, query.priority).await?;
+    // Only the download is abandoned on disconnect; once the new instance is
+    // starting the upgrade runs to completion so no half-swapped state is left.
+    let cancel = CancellationToken::new();
+    if let Some(download) = payload.download.as_mut() {
+        download.cancel = cancel.clone();
+    }
+    run_until_disconnect(cancel, async move {
+        let _permit = permit;
+        plugin.upgrade_service(payload).await
+    })
+    .await
+    .map(Json)
+    .map_err(|err| map_error(err).into_response())
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ServiceLogsQuery {
+    /// Only return entries at or above this level
+    pub level: Option<LogLevel>,
+    /// Maximum number of most recent entries to return
+    pub limit: Option<usize>,
+    /// Same as `limit`, such as `tail=500` for the last 500 entries
+    pub tail: Option<usize>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/plugins/{plugin_id}/services/{task_type}/logs",
+    params(
+        ("plugin_id" = String, Path, description = "Plugin identifier"),
+        ("task_type" = PluginTaskType, Path, description = "Task type of the running service"),
+        ServiceLogsQuery
+    ),
+    responses(
+        (status = 200, description = "Captured service log entries", body = ServiceLogsResponse),
+        (status = 404, description = "Plugin not found", body = PluginErrorResponse),
+        (status = 409, description = "Service not running", body = PluginErrorResponse)
+    ),
+)]
+pub async fn service_logs(
+    State(state): State<Arc<AppState>>,
+    Path((plugin_id, task_type)): Path<(String, PluginTaskType)>,
+    Query(query): Query<ServiceLogsQuery>,
+) -> Result<Json<ServiceLogsResponse>, (StatusCode, Json<PluginErrorResponse>)> {
+    let plugin = active_plugin(&state, &plugin_id).await?;
+    plugin
+        .service_logs(ServiceLogsRequest {
+            service: ServiceSelector::task_type(task_type),
+            min_level: query.level,
+            limit: query.limit.or(query.tail),
+        })
+        .await
+        .map(Json)
+        .map_err(map_error)
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct FollowLogsQuery {
+    /// Only send entries at or above this level
+    pub level: Option<LogLevel>,
+    /// Number of entries already captured to send first (defaults to none)
+    pub tail: Option<usize>,
+    /// Resume after the entry with this `seq`. A `Last-Event-ID` header, as
+    /// sent by a reconnecting EventSource, takes precedence.
+    pub after: Option<u64>,
+}
+
+impl FollowLogsQuery {
+    fn into_request(
+        self,
+        service: ServiceSelector,
+        headers: &http::HeaderMap,
+    ) -> FollowLogsRequest {
+        let last_event_id = headers
+            .get("last-event-id")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse().ok());
+        FollowLogsRequest {
+            service,
+            min_level: self.level,
+            after: last_event_id.or(self.after),
+            tail: self.tail.unwrap_or(0),
+        }
+    }
+}
+
+/// `log` events with each entry as the service writes it, its `seq` as the
+/// event id, and `missed` events with the number of entries dropped from the
+/// buffer before a slow client received them. The stream ends with the
+/// service.
+fn log_events(session: FollowLogsSession) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    tracing::debug!(
+        "following the logs of {:?} instance {}",
+        session.task_type,
+        session.instance_id
+    );
+    let stream = futures::stream::unfold(session.follower, |mut follower| async move {
+        let event = match follower.next().await? {
+            FollowedLog::Entry(entry) => Event::default()
+                .event("log")
+                .id(entry.seq.to_string())
+                .json_data(&entry)
+                .unwrap_or_default(),
+            FollowedLog::Missed(entries) => Event::default()
+                .event("missed")
+                .json_data(serde_json::json!({ "entries": entries }))
+                .unwrap_or_default(),
+        };
+        Some((Ok(event), follower))
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+#[utoipa::path(
+    get,
+    path = "/plugins/{plugin_id}/services/{task_type}/logs/stream",
+    params(
+        ("plugin_id" = String, Path, description = "Plugin identifier"),
+        ("task_type" = PluginTaskType, Path, description = "Task type of the running service"),
+        FollowLogsQuery
+    ),
+    responses(
+        (status = 200, description = "`log` events as the service writes its output, each with its `seq` as the event id, and `missed` events counting entries dropped before they were sent", content_type = "text/event-stream", body = crate::plugins::logs::LogEntry),
+        (status = 400, description = "Service runs on a remote node", body = PluginErrorResponse),
+        (status = 404, description = "Plugin not found", body = PluginErrorResponse),
+        (status = 409, description = "Service not running", body = PluginErrorResponse)
+    ),
+)]
+pub async fn follow_service_logs(
+    State(state): State<Arc<AppState>>,
+    Path((plugin_id, task_type)): Path<(String, PluginTaskType)>,
+    Query(query): Query<FollowLogsQuery>,
+    headers: http::HeaderMap,
+) -> Result<
+    Sse<impl Stream<Item = Result<Event, Infallible>>>,
+    (StatusCode, Json<PluginErrorResponse>),
+> {
+    let plugin = active_plugin(&state, &plugin_id).await?;
+    let request = query.into_request(ServiceSelector::task_type(task_type), &headers);
+    let session = plugin.follow_logs(request).await.map_err(map_error)?;
+    Ok(log_events(session))
+}
+
+#[utoipa::path(
+    get,
+    path = "/plugins/{plugin_id}/services",
+    params(("plugin_id" = String, Path, description = "Plugin identifier")),
+    responses(
+        (status = 200, description = "Services the plugin runs locally", body = ListServicesResponse),
+        (status = 400, description = "Operation not supported", body = PluginErrorResponse),
+        (status = 404, description = "Plugin not found", body = PluginErrorResponse)
+    ),
+)]
+pub async fn list_services(
+    State(state): State<Arc<AppState>>,
+    Path(plugin_id): Path<String>,
+) -> Result<Json<ListServicesResponse>, (StatusCode, Json<PluginErrorResponse>)> {
+    let plugin = active_plugin(&state, &plugin_id).await?;
+    plugin.list_services().await.map(Json).map_err(map_error)
+}
+
+#[utoipa::path(
+    get,
+    path = "/plugins/{plugin_id}/logs/files",
+    params(("plugin_id" = String, Path, description = "Plugin identifier")),
+    responses(
+        (status = 200, description = "Files the plugin's services wrote their output to, newest first", body = ListLogFilesResponse),
+        (status = 400, description = "Operation not supported", body = PluginErrorResponse),
+        (status = 404, description = "Plugin not found", body = PluginErrorResponse)
+    ),
+)]
+pub async fn list_log_files(
+    State(state): State<Arc<AppState>>,
+    Path(plugin_id): Path<String>,
+) -> Result<Json<ListLogFilesResponse>, (StatusCode, Json<PluginErrorResponse>)> {
+    let plugin = active_plugin(&state, &plugin_id).await?;
+    plugin.list_log_files().await.map(Json).map_err(map_error)
+}
+
+#[utoipa::path(
+    get,
+    path = "/plugins/{plugin_id}/logs/files/{task_type}/{name}",
+    params(
+        ("plugin_id" = String, Path, description = "Plugin identifier"),
+        ("task_type" = PluginTaskType, Path, description = "Task type of the service that wrote the file"),
+        ("name" = String, Path, description = "Name of the file, as listed")
+    ),
+    responses(
+        (status = 200, description = "The log file", content_type = "text/plain", body = String),
+        (status = 400, description = "Operation not supported", body = PluginErrorResponse),
+        (status = 404, description = "Plugin or log file not found", body = PluginErrorResponse)
+    ),
+)]
+pub async fn download_log_file(
+    State(state): State<Arc<AppState>>,
+    Path((plugin_id, task_type, name)): Path<(String, PluginTaskType, String)>,
+) -> Result<Response, (StatusCode, Json<PluginErrorResponse>)> {
+    let plugin = active_plugin(&state, &plugin_id).await?;
+    let file = plugin
+        .list_log_files()
+        .await
+        .map_err(map_error)?
+        .files
+        .into_iter()
+        .find(|file| file.task_type == task_type && file.name == name)
+        .ok_or_else(|| {
+            map_error(PluginError::NotFound(format!(
+                "log file {}/{}",
+                task_type, name
+            )))
+        })?;
+    // Sent as it was listed; a file still being written to may have grown.
+    let body =
+        axum::body::Body::from_stream(peers::read_range(file.path.into(), 0..file.size_bytes));
+    Response::builder()
+        .header(header::CONTENT_TYPE, "text/plain; charset=utf-8")
+        .header(header::CONTENT_LENGTH, file.size_bytes)
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}-{}\"", task_type, name),
+        )
+        .body(body)
+        .map_err(|err| map_error(PluginError::Internal(err.to_string())))
+}
+
+#[utoipa::path(
+    get,
+    path = "/plugins/{plugin_id}/services/{task_type}/status",
+    params(
+        ("plugin_id" = String, Path, description = "Plugin identifier"),
+        ("task_type" = PluginTaskType, Path, description = "Task type of the running service")
+    ),
+    responses(
+        (status = 200, description = "Service status", body = ServiceStatusResponse),
+        (status = 404, description = "Plugin not found", body = PluginErrorResponse),
+        (status = 409, description = "Service not running", body = PluginErrorResponse)
+    ),
+)]
+pub async fn service_status(
+    State(state): State<Arc<AppState>>,
+    Path((plugin_id, task_type)): Path<(String, PluginTaskType)>,
+) -> Result<Json<ServiceStatusResponse>, (StatusCode, Json<PluginErrorResponse>)> {
+    let plugin = active_plugin(&state, &plugin_id).await?;
+    plugin
+        .service_status(ServiceStatusRequest {
+            service: ServiceSelector::task_type(task_type),
+        })
+        .await
+        .map(Json)
+        .map_err(map_error)
+}
+
+#[utoipa::path(
+    post,
+    path = "/plugins/{plugin_id}/instances/{instance_id}/stop",
+    params(
+        ("plugin_id" = String, Path, description = "Plugin identifier"),
+        ("instance_id" = String, Path, description = "Instance handle returned when the service was started")
+    ),
+    responses(
+        (status = 200, description = "Service stopped", body = StopServiceResponse),
+        (status = 404, description = "Plugin or instance not found", body = PluginErrorResponse)
+    ),
+)]
+pub async fn stop_instance(
+    State(state): State<Arc<AppState>>,
+    Path((plugin_id, instance_id)): Path<(String, String)>,
+) -> Result<Json<StopServiceResponse>, (StatusCode, Json<PluginErrorResponse>)> {
+    let plugin = active_plugin(&state, &plugin_id).await?;
+    plugin
+        .stop_service(StopServiceRequest {
+            service: ServiceSelector::instance(instance_id),
+        })
+        .await
+        .map(Json)
+        .map_err(map_error)
+}
+
+#[utoipa::path(
+    get,
+    path = "/plugins/{plugin_id}/instances/{instance_id}/logs",
+    params(
+        ("plugin_id" = String, Path, description = "Plugin identifier"),
+        ("instance_id" = String, Path, description = "Instance handle returned when the service was started"),
+        ServiceLogsQuery
+    ),
+    responses(
+        (status = 200, description = "Captured service log entries", body = ServiceLogsResponse),
+        (status = 404, description = "Plugin or instance not found", body = PluginErrorResponse)
+    ),
+)]
+pub async fn instance_logs(
+    State(state): State<Arc<AppState>>,
+    Path((plugin_id, instance_id)): Path<(String, String)>,
+    Query(query): Query<ServiceLogsQuery>,
+) -> Result<Json<ServiceLogsResponse>, (StatusCode, Json<PluginErrorResponse>)> {
+    let plugin = active_plugin(&state, &plugin_id).await?;
+    plugin
+        .service_logs(ServiceLogsRequest {
+            service: ServiceSelector::instance(instance_id),
+            min_level: query.level,
+            limit: query.limit.or(query.tail),
+        })
+        .await
+        .map(Json)
+        .map_err(map_error)
+}
+
+#[utoipa::path(
+    get,
+    path = "/plugins/{plugin_id}/instances/{instance_id}/logs/stream",
+    params(
+        ("plugin_id" = String, Path, description = "Plugin identifier"),
+        ("instance_id" = String, Path, description = "Instance handle returned when the service was started"),
+        FollowLogsQuery
+    ),
+    responses(
+        (status = 200, description = "`log` events as the service writes its output, each with its `seq` as the event id, and `missed` events counting entries dropped before they were sent", content_type = "text/event-stream", body = crate::plugins::logs::LogEntry),
+        (status = 400, description = "Service runs on a remote node", body = PluginErrorResponse),
+        (status = 404, description = "Plugin or instance not found", body = PluginErrorResponse)
+    ),
+)]
+pub async fn follow_instance_logs(
+    State(state): State<Arc<AppState>>,
+    Path((plugin_id, instance_id)): Path<(String, String)>,
+    Query(query): Query<FollowLogsQuery>,
+    headers: http::HeaderMap,
+) -> Result<
+    Sse<impl Stream<Item = Result<Event, Infallible>>>,
+    (StatusCode, Json<PluginErrorResponse>),
+> {
+    let plugin = active_plugin(&state, &plugin_id).await?;
+    let request = query.into_request(ServiceSelector::instance(instance_id), &headers);
+    let session = plugin.follow_logs(request).await.map_err(map_error)?;
+    Ok(log_events(session))
+}
+
+#[utoipa::path(
+    get,
+    path = "/plugins/{plugin_id}/instances/{instance_id}/status",
+    params(
+        ("plugin_id" = String, Path, description = "Plugin identifier"),
+        ("instance_id" = String, Path, description = "Instance handle returned when the service was started")
+    ),
+    responses(
+        (status = 200, description = "Service status", body = ServiceStatusResponse),
+        (status = 404, description = "Plugin or instance not found", body = PluginErrorResponse)
+    ),
+)]
+pub async fn instance_status(
+    State(state): State<Arc<AppState>>,
+    Path((plugin_id, instance_id)): Path<(String, String)>,
+) -> Result<Json<ServiceStatusResponse>, (StatusCode, Json<PluginErrorResponse>)> {
+    let plugin = active_plugin(&state, &plugin_id).await?;
+    plugin
+        .service_status(ServiceStatusRequest {
+            service: ServiceSelector::instance(instance_id),
+        })
+        .await
+        .map(Json)
+        .map_err(map_error)
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SignalInstanceBody {
+    pub signal: ServiceSignal,
+}
+
+#[utoipa::path(
+    post,
+    path = "/plugins/{plugin_id}/services/{instance_id}/signal",
+    params(
+        ("plugin_id" = String, Path, description = "Plugin identifier"),
+        ("instance_id" = String, Path, description = "Instance handle returned when the service was started")
+    ),
+    request_body = SignalInstanceBody,
+    responses(
+        (status = 200, description = "Signal delivered", body = SignalServiceResponse),
+        (status = 400, description = "Signal not supported on this platform", body = PluginErrorResponse),
+        (status = 404, description = "Plugin or instance not found", body = PluginErrorResponse),
+        (status = 409, description = "Service not running", body = PluginErrorResponse)
+    ),
+)]
+pub async fn signal_instance(
+    State(state): State<Arc<AppState>>,
+    Path((plugin_id, instance_id)): Path<(String, String)>,
+    Json(payload): Json<SignalInstanceBody>,
+) -> Result<Json<SignalServiceResponse>, (StatusCode, Json<PluginErrorResponse>)> {
+    let plugin = active_plugin(&state, &plugin_id).await?;
+    plugin
+        .signal_service(SignalServiceRequest {
+            service: ServiceSelector::instance(instance_id),
+            signal: payload.signal,
+        })
+        .await
+        .map(Json)
+        .map_err(map_error)
+}
+
+/// Console attach gives full control over a process's stdin, so it stays off
+/// unless an administrator opts in.
+fn console_enabled() -> bool {
+    std::env::var("GOOSE_PLUGIN_CONSOLE_ENABLED")
+        .map(|value| matches!(value.as_str(), "1" | "true"))
+        .unwrap_or(false)
+}
+
+#[utoipa::path(
+    get,
+    path = "/plugins/{plugin_id}/instances/{instance_id}/console",
+    params(
+        ("plugin_id" = String, Path, description = "Plugin identifier"),
+        ("instance_id" = String, Path, description = "Instance handle returned when the service was started")
+    ),
+    responses(
+        (status = 101, description = "WebSocket attached; text frames go to stdin, captured output is sent as LogEntry JSON"),
+        (status = 400, description = "Instance was not started interactively", body = PluginErrorResponse),
+        (status = 403, description = "Console attach disabled (set GOOSE_PLUGIN_CONSOLE_ENABLED)", body = PluginErrorResponse),
+        (status = 404, description = "Plugin or instance not found", body = PluginErrorResponse)
+    ),
+)]
+pub async fn attach_console(
+    State(state): State<Arc<AppState>>,
+    Path((plugin_id, instance_id)): Path<(String, String)>,
+    ws: WebSocketUpgrade,
+) -> Result<Response, (StatusCode, Json<PluginErrorResponse>)> {
+    if !console_enabled() {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(PluginErrorResponse::new(
+                PluginErrorCode::ConsoleDisabled,
+                "console attach is disabled; set GOOSE_PLUGIN_CONSOLE_ENABLED=true",
+            )),
+        ));
+    }
+    let plugin = active_plugin(&state, &plugin_id).await?;
+    let session = plugin
+        .attach_console(AttachConsoleRequest {
+            service: ServiceSelector::instance(instance_id),
+        })
+        .await
+        .map_err(map_error)?;
+
+    Ok(ws.on_upgrade(move |socket| run_console(socket, session)))
+}
+
+async fn run_console(mut socket: WebSocket, mut session: ConsoleSession) {
+    tracing::info!(
+        "console attached to {:?} instance {}",
+        session.task_type,
+        session.instance_id
+    );
+    loop {
+        tokio::select! {
+            incoming = socket.recv() => {
+                let input = match incoming {
+                    Some(Ok(Message::Text(text))) => {
+                        let mut input = text.as_str().as_bytes().to_vec();
+                        if !input.ends_with(b"\n") {
+                            input.push(b'\n');
+                        }
+                        input
+                    }
+                    Some(Ok(Message::Binary(bytes))) => bytes.to_vec(),
+                    Some(Ok(Message::Close(_))) | None | Some(Err(_)) => break,
+                    Some(Ok(_)) => continue,
+                };
+                if session.input.send(input).await.is_err() {
+                    break;
+                }
+            }
+            output = session.output.recv() => {
+                let entry = match output {
+                    Ok(entry) => entry,
+                    Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::warn!("console subscriber lagged, skipped {} lines", skipped);
+                        continue;
+                    }
+                    Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                };
+                let Ok(text) = serde_json::to_string(&entry) else {
+                    continue;
+                };
+                if socket.send(Message::Text(text.into())).await.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+    tracing::info!("console detached from instance {}", session.instance_id);
+}
+
+#[utoipa::path(
+    get,
+    path = "/plugins/events",
+    responses(
+        (status = 200, description = "Stream of plugin events", content_type = "text/event-stream", body = crate::plugins::events::PluginEvent)
+    ),
+)]
+pub async fn plugin_events(
+    State(state): State<Arc<AppState>>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let receiver = state.events.subscribe();
+    let stream = futures::stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(event) => {
+                    let event = Event::default().json_data(&event).unwrap_or_default();
+                    return Some((Ok(event), receiver));
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::warn!("plugin event subscriber lagged, skipped {} events", skipped);
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+#[utoipa::path(
+    post,
+    path = "/plugins/{plugin_id}/events",
+    params(("plugin_id" = String, Path, description = "Plugin identifier")),
+    request_body = PluginEventKind,
+    responses(
+        (status = 204, description = "Event published on behalf of the plugin"),
+        (status = 404, description = "Plugin not found", body = PluginErrorResponse),
+        (status = 409, description = "Plugin is disabled", body = PluginErrorResponse)
+    ),
+)]
+pub async fn publish_plugin_event(
+    State(state): State<Arc<AppState>>,
+    Path(plugin_id): Path<String>,
+    Json(event): Json<PluginEventKind>,
+) -> Result<StatusCode, (StatusCode, Json<PluginErrorResponse>)> {
+    active_plugin(&state, &plugin_id).await?;
+    state.events.publish(&plugin_id, event);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    get,
+    path = "/plugins/metrics",
+    responses(
+        (status = 200, description = "Counters and gauges of every enabled plugin that reports them, with totals across plugins", body = PluginMetricsSnapshot)
+    ),
+)]
+pub async fn plugin_metrics(State(state): State<Arc<AppState>>) -> Json<PluginMetricsSnapshot> {
+    Json(state.plugins.metrics().await)
+}
+
+#[utoipa::path(
+    get,
+    path = "/plugins/defaults",
+    responses(
+        (status = 200, description = "Plugins set as default per task type; task types without one fall back to the first enabled plugin that can start services", body = PluginDefaults)
+    ),
+)]
+pub async fn get_plugin_defaults(State(state): State<Arc<AppState>>) -> Json<PluginDefaults> {
+    Json(state.plugin_defaults.read().await.get().clone())
+}
+
+#[utoipa::path(
+    put,
+    path = "/plugins/defaults",
+    request_body = PluginDefaults,
+    responses(
+        (status = 200, description = "Defaults replaced", body = PluginDefaults),
+        (status = 404, description = "A default or fallback names a plugin that is not registered", body = PluginErrorResponse),
+        (status = 500, description = "Defaults could not be stored", body = PluginErrorResponse)
+    ),
+)]
+pub async fn set_plugin_defaults(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<PluginDefaults>,
+) -> Result<Json<PluginDefaults>, (StatusCode, Json<PluginErrorResponse>)> {
+    let plugins = state.plugins.list_metadata().await;
+    if let Some(plugin_id) = payload
+        .plugin_ids()
+        .find(|plugin_id| !plugins.iter().any(|plugin| &plugin.id == *plugin_id))
+    {
+        return Err(plugin_not_found(plugin_id));
+    }
+    let mut defaults = state.plugin_defaults.write().await;
+    defaults.set(payload).await.map_err(map_error)?;
+    Ok(Json(defaults.get().clone()))
+}
+
+#[utoipa::path(
+    post,
+    path = "/tasks/{task_type}/start",
+    params(("task_type" = PluginTaskType, Path, description = "Task type to start a service for")),
+    request_body = StartServiceRequest,
+    responses(
+        (status = 200, description = "Service started by the task type's default plugin", body = StartServiceResponse),
+        (status = 400, description = "Invalid request, or its task type differs from the path", body = PluginErrorResponse),
+        (status = 404, description = "No default plugin for the task type", body = PluginErrorResponse),
+        (status = 409, description = "Service already running, or the default plugin is disabled", body = PluginErrorResponse),
+        (status = 500, description = "Service failed to start", body = PluginErrorResponse)
+    ),
+)]
+pub async fn start_task(
+    State(state): State<Arc<AppState>>,
+    Path(task_type): Path<PluginTaskType>,
+    Extension(credential): Extension<RequestCredential>,
+    Json(payload): Json<StartServiceRequest>,
+) -> Result<Json<StartServiceResponse>, (StatusCode, Json<PluginErrorResponse>)> {
+    if payload.task_type != task_type {
+        return Err(map_error(PluginError::InvalidRequest(format!(
+            "request is for {} tasks, not {}",
+            payload.task_type, task_type
+        ))));
+    }
+    let plugin_id = default_plugin_id(&state, &task_type).await?;
+    active_plugin(&state, &plugin_id).await?;
+    start_with_fallbacks(&state, &plugin_id, credential.0.as_ref(), payload).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/tasks/{task_type}/stop",
+    params(("task_type" = PluginTaskType, Path, description = "Task type of the running service")),
+    responses(
+        (status = 200, description = "Service stopped", body = StopServiceResponse),
+        (status = 400, description = "Several services of the task type are running", body = PluginErrorResponse),
+        (status = 404, description = "No default plugin for the task type", body = PluginErrorResponse),
+        (status = 409, description = "Service not running", body = PluginErrorResponse)
+    ),
+)]
+pub async fn stop_task(
+    State(state): State<Arc<AppState>>,
+    Path(task_type): Path<PluginTaskType>,
+) -> Result<Json<StopServiceResponse>, (StatusCode, Json<PluginErrorResponse>)> {
+    let plugin = default_plugin(&state, &task_type).await?;
+    plugin
+        .stop_service(StopServiceRequest {
+            service: ServiceSelector::task_type(task_type),
+        })
+        .await
+        .map(Json)
+        .map_err(map_error)
+}
+
+#[utoipa::path(
+    get,
+    path = "/tasks/{task_type}/status",
+    params(("task_type" = PluginTaskType, Path, description = "Task type of the running service")),
+    responses(
+        (status = 200, description = "Service status", body = ServiceStatusResponse),
+        (status = 404, description = "No default plugin for the task type", body = PluginErrorResponse),
+        (status = 409, description = "Service not running", body = PluginErrorResponse)
+    ),
+)]
+pub async fn task_status(
+    State(state): State<Arc<AppState>>,
+    Path(task_type): Path<PluginTaskType>,
+) -> Result<Json<ServiceStatusResponse>, (StatusCode, Json<PluginErrorResponse>)> {
+    let plugin = default_plugin(&state, &task_type).await?;
+    plugin
+        .service_status(ServiceStatusRequest {
+            service: ServiceSelector::task_type(task_type),
+        })
+        .await
+        .map(Json)
+        .map_err(map_error)
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ModelSearchQuery {
+    /// Words the repository id has to contain
+    pub q: Option<String>,
+    /// Task type the models are searched for (defaults to `text`)
+    pub task: Option<PluginTaskType>,
+    /// Library or file format tag (defaults to `gguf` for text models; empty for any)
+    pub library: Option<String>,
+    /// Most results returned, up to 100 (defaults to 20)
+    pub limit: Option<usize>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/models/search",
+    params(ModelSearchQuery),
+    responses(
+        (status = 200, description = "Matching models, most downloaded first", body = ModelSearchResponse),
+        (status = 400, description = "Invalid request or operation not supported", body = PluginErrorResponse),
+        (status = 404, description = "No default plugin for the task type", body = PluginErrorResponse),
+        (status = 502, description = "Model host request failed", body = PluginErrorResponse),
+        (status = 503, description = "Offline mode is enabled", body = PluginErrorResponse)
+    ),
+)]
+pub async fn search_models(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ModelSearchQuery>,
+) -> Result<Json<ModelSearchResponse>, (StatusCode, Json<PluginErrorResponse>)> {
+    let task_type = query.task.unwrap_or(PluginTaskType::TEXT);
+    let plugin = default_plugin(&state, &task_type).await?;
+    operations::invoke(
+        plugin.as_ref(),
+        operations::SEARCH_MODELS,
+        &ModelSearchRequest {
+            query: query.q,
+            task_type,
+            library: query.library,
+            limit: query.limit,
+        },
+        InvokeContext::default(),
+    )
+    .await
+    .map(Json)
+    .map_err(map_error)
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ModelCatalogQuery {
+    /// Task type the recommended models are listed for (every task type when omitted)
+    pub task: Option<PluginTaskType>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/models/catalog",
+    params(ModelCatalogQuery),
+    responses(
+        (status = 200, description = "Recommended models, from the remote catalog when one is configured and reachable", body = ModelCatalogResponse)
+    ),
+)]
+pub async fn model_catalog(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ModelCatalogQuery>,
+) -> Json<ModelCatalogResponse> {
+    let offline = state.plugins.offline().await;
+    Json(state.model_catalog.get(query.task.as_ref(), &offline).await)
+}
+
+/// The scoped credential a request was made with; none with the secret key.
+#[derive(Debug, Clone)]
+pub struct RequestCredential(Option<PluginCredential>);
+
+/// Looks up the scoped credential a request was made with, for the checks
+/// of the route and for handlers that keep to the plugins it may use.
+async fn identify(
+    State(state): State<Arc<AppState>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let credential = request
+        .headers()
+        .get("X-Secret-Key")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|token| state.plugin_credentials.find(token))
+        .cloned();
+    request
+        .extensions_mut()
+        .insert(RequestCredential(credential));
+    next.run(request).await
+}
+
+/// Lets scoped credentials granted `capability` for the plugin a request
+/// targets call `route`. Task routes target the default plugin for their
+/// task type.
+fn requires(
+    state: &Arc<AppState>,
+    capability: PluginCapability,
+    route: MethodRouter<Arc<AppState>>,
+) -> MethodRouter<Arc<AppState>> {
+    route.route_layer(middleware::from_fn_with_state(
+        (state.clone(), capability),
+        authorize,
+    ))
+}
+
+async fn authorize(
+    State((state, capability)): State<(Arc<AppState>, PluginCapability)>,
+    Extension(RequestCredential(credential)): Extension<RequestCredential>,
+    params: RawPathParams,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(credential) = credential else {
+        return next.run(request).await;
+    };
+    let param = |name: &str| {
+        params
+            .iter()
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value.to_string())
+    };
+    let plugin_id = if let Some(plugin_id) = param("plugin_id") {
+        Some(plugin_id)
+    } else if let Some(task_type) = param("task_type") {
+        // Invalid task types are rejected by the handler before it runs.
+        let Ok(task_type) = PluginTaskType::new(task_type) else {
+            return next.run(request).await;
+        };
+        match default_plugin_id(&state, &task_type).await {
+            Ok(plugin_id) => Some(plugin_id),
+            Err(error) => return error.into_response(),
+        }
+    } else {
+        None
+    };
+
+    let allowed = match &plugin_id {
+        Some(plugin_id) => credential.allows(plugin_id, &capability),
+        None => credential.allows_all(&capability),
+    };
+    if allowed {
+        next.run(request).await
+    } else {
+        permission_denied(&credential, plugin_id.as_deref(), Some(capability))
+    }
+}
+
+/// Turns away scoped credentials, whatever they were granted.
+async fn require_secret_key(
+    Extension(RequestCredential(credential)): Extension<RequestCredential>,
+    request: Request,
+    next: Next,
+) -> Response {
+    match credential {
+        Some(credential) => permission_denied(&credential, None, None),
+        None => next.run(request).await,
+    }
+}
+
+fn permission_denied(
+    credential: &PluginCredential,
+    plugin_id: Option<&str>,
+    capability: Option<PluginCapability>,
+) -> Response {
+    let mut details = serde_json::json!({ "credential": credential.name });
+    let message = match capability {
+        Some(capability) => {
+            details["capability"] = serde_json::json!(capability);
+            let target = match plugin_id {
+                Some(plugin_id) => {
+                    details["plugin_id"] = serde_json::json!(plugin_id);
+                    plugin_id
+                }
+                None => "every plugin",
+            };
+            format!(
+                "credential {} is not granted {} for {}",
+                credential.name,
+                details["capability"].as_str().unwrap_or_default(),
+                target
+            )
+        }
+        None => format!("credential {} needs the secret key here", credential.name),
+    };
+    let mut response = PluginErrorResponse::new(PluginErrorCode::PermissionDenied, message);
+    response.details = Some(details);
+    (StatusCode::FORBIDDEN, Json(response)).into_response()
+}
+
+pub fn routes(state: Arc<AppState>) -> Router {
+    use PluginCapability::*;
+    let scoped = |capability, route| requires(&state, capability, route);
+
+    // Routes scoped credentials may call, with the capability each needs.
+    // Listing plugins is open to every credential; it lists only the plugins
+    // the credential may use.
+    let plugin_routes = Router::new()
+        .route("/plugins", get(list_plugins))
+        .route("/plugins/metrics", scoped(Metrics, get(plugin_metrics)))
+        .route(
+            "/tasks/{task_type}/start",
+            scoped(ServiceStart, post(start_task)),
+        )
+        .route(
+            "/tasks/{task_type}/stop",
+            scoped(ServiceStop, post(stop_task)),
+        )
+        .route(
+            "/tasks/{task_type}/status",
+            scoped(ServiceStatus, get(task_status)),
+        )
+        .route("/models/search", scoped(ModelSearch, get(search_models)))
+        .route("/models/catalog", scoped(ModelSearch, get(model_catalog)))
+        .route(
+            "/plugins/{plugin_id}/invoke/{operation}",
+            scoped(CustomOperations, post(invoke_plugin_operation)),
+        )
+        .route(
+            "/plugins/{plugin_id}/health",
+            scoped(HealthCheck, get(plugin_health)),
+        )
+        .route(
+            "/plugins/{plugin_id}/auth/validate",
+            scoped(TokenValidation, post(validate_token)),
+        )
+        .route(
+            "/plugins/{plugin_id}/models",
+            scoped(ModelList, get(list_models)).merge(scoped(ModelDelete, delete(delete_model))),
+        )
+        .route(
+            "/plugins/{plugin_id}/models/usage",
+            scoped(ModelList, get(model_usage)),
+        )
+        .route(
+            "/plugins/{plugin_id}/models/import",
+            scoped(ModelImport, post(import_model)),
+        )
+        .route(
+            "/plugins/{plugin_id}/models/share",
+            scoped(ModelShare, get(share_model)),
+        )
+        .route(
+            "/plugins/{plugin_id}/models/health",
+            scoped(ModelHealth, get(model_health)),
+        )
+        .route(
+            "/plugins/{plugin_id}/models/health/repair",
+            scoped(ModelHealth, post(repair_models)),
+        )
+        .route(
+            "/plugins/{plugin_id}/models/convert",
+            scoped(ModelConvert, post(convert_model)),
+        )
+        .route(
+            "/plugins/{plugin_id}/models/conversions/{job_id}/progress",
+            scoped(ModelConvert, get(conversion_progress)),
+        )
+        .route(
+            "/plugins/{plugin_id}/models/quantize",
+            scoped(ModelQuantize, post(quantize_model)),
+        )
+        .route(
+            "/plugins/{plugin_id}/models/quantizations/{job_id}/progress",
+            scoped(ModelQuantize, get(quantization_progress)),
+        )
+        .route(
+            "/plugins/{plugin_id}/models/{filename}/pin",
+            scoped(ModelPin, post(pin_model).delete(unpin_model)),
+        )
+        .route(
+            "/plugins/{plugin_id}/models/{filename}/adapters",
+            scoped(ModelAdapters, get(list_adapters)),
+        )
+        .route(
+            "/plugins/{plugin_id}/nodes",
+            scoped(RemoteNodes, get(list_nodes)),
+        )
+        .route(
+            "/plugins/{plugin_id}/profiles",
+            scoped(HardwareProfiles, get(list_profiles)),
+        )
+        .route(
+            "/plugins/{plugin_id}/models/download",
+            scoped(ModelDownload, post(download_model)),
+        )
+        .route(
+            "/plugins/{plugin_id}/models/restore",
+            scoped(ModelDownload, post(restore_models)),
+        )
+        .route(
+            "/plugins/{plugin_id}/models/downloads",
+            scoped(ModelDownload, get(list_downloads)),
+        )
+        .route(
+            "/plugins/{plugin_id}/models/downloads/{job_id}",
+            scoped(ModelDownload, get(get_download)),
+        )
+        .route(
+            "/plugins/{plugin_id}/models/downloads/{job_id}/cancel",
+            scoped(ModelDownload, post(cancel_download)),
+        )
+        .route(
+            "/plugins/{plugin_id}/models/downloads/{job_id}/pause",
+            scoped(ModelDownload, post(pause_download)),
+        )
+        .route(
+            "/plugins/{plugin_id}/models/downloads/{job_id}/resume",
+            scoped(ModelDownload, post(resume_download)),
+        )
+        .route(
+            "/plugins/{plugin_id}/models/downloads/{job_id}/progress",
+            scoped(ModelDownload, get(download_progress)),
+        )
+        .route(
+            "/plugins/{plugin_id}/models/revisions",
+            scoped(ModelRevisions, get(list_revisions)),
+        )
+        .route(
+            "/plugins/{plugin_id}/models/remote/{*model_id}",
+            scoped(ModelRevisions, get(list_model_files)),
+        )
+        .route(
+            "/plugins/{plugin_id}/models/check-updates",
+            scoped(ModelUpdateCheck, post(check_model_updates)),
+        )
+        .route(
+            "/plugins/{plugin_id}/models/gc",
+            scoped(ModelGc, post(collect_models)),
+        )
+        .route(
+            "/plugins/{plugin_id}/services",
+            scoped(ServiceStatus, get(list_services)),
+        )
+        .route(
+            "/plugins/{plugin_id}/services/start",
+            scoped(ServiceStart, post(start_service)),
+        )
+        .route(
+            "/plugins/{plugin_id}/services/stop",
+            scoped(ServiceStop, post(stop_service)),
+        )
+        .route(
+            "/plugins/{plugin_id}/services/upgrade",
+            scoped(ServiceUpgrade, post(upgrade_service)),
+        )
+        .route(
+            "/plugins/{plugin_id}/services/{task_type}/logs",
+            scoped(ServiceLogs, get(service_logs)),
+        )
+        .route(
+            "/plugins/{plugin_id}/services/{task_type}/logs/stream",
+            scoped(ServiceLogs, get(follow_service_logs)),
+        )
+        .route(
+            "/plugins/{plugin_id}/services/{task_type}/status",
+            scoped(ServiceStatus, get(service_status)),
+        )
+        .route(
+            "/plugins/{plugin_id}/logs/files",
+            scoped(ServiceLogs, get(list_log_files)),
+        )
+        .route(
+            "/plugins/{plugin_id}/logs/files/{task_type}/{name}",
+            scoped(ServiceLogs, get(download_log_file)),
+        )
+        .route(
+            "/plugins/{plugin_id}/instances/{instance_id}/stop",
+            scoped(ServiceStop, post(stop_instance)),
+        )
+        .route(
+            "/plugins/{plugin_id}/instances/{instance_id}/logs",
+            scoped(ServiceLogs, get(instance_logs)),
+        )
+        .route(
+            "/plugins/{plugin_id}/instances/{instance_id}/logs/stream",
+            scoped(ServiceLogs, get(follow_instance_logs)),
+        )
+        .route(
+            "/plugins/{plugin_id}/instances/{instance_id}/status",
+            scoped(ServiceStatus, get(instance_status)),
+        );
+
+    // Everything else needs the secret key, including signals and consoles,
+    // which reach into running processes.
+    let admin_routes = Router::new()
+        .route(
+            "/plugins/defaults",
+            get(get_plugin_defaults).put(set_plugin_defaults),
+        )
+        .route("/plugins/register", post(register_plugin))
+        .route("/plugins/catalog", get(list_catalog))
+        .route(
+            "/plugins/catalog/{plugin_id}/install",
+            post(install_catalog_plugin),
+        )
+        .route("/plugins/{plugin_id}", delete(unregister_plugin))
+        .route("/plugins/{plugin_id}/reload", post(reload_plugin))
+        .route("/plugins/{plugin_id}/enable", post(enable_plugin))
+        .route("/plugins/{plugin_id}/disable", post(disable_plugin))
+        .route(
+            "/plugins/{plugin_id}/config",
+            get(get_plugin_config).put(set_plugin_config),
+        )
+        .route(
+            "/plugins/{plugin_id}/config/schema",
+            get(get_plugin_config_schema),
+        )
+        .route("/plugins/{plugin_id}/compat", get(get_plugin_compat))
+        .route("/plugins/events", get(plugin_events))
+        .route("/plugins/{plugin_id}/events", post(publish_plugin_event))
+        .route("/plugins/queues", get(list_queues))
+        .route(
+            "/plugins/{plugin_id}/services/{instance_id}/signal",
+            post(signal_instance),
+        )
+        .route(
+            "/plugins/{plugin_id}/instances/{instance_id}/console",
+            get(attach_console),
+        )
+        .route_layer(middleware::from_fn(require_secret_key));
+
+    plugin_routes
+        .merge(admin_routes)
+        .route_layer(middleware::from_fn_with_state(state.clone(), identify))
+        .with_state(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::{Method, Request};
+    use serde_json::json;
+    use tower::ServiceExt;
+
+    use crate::plugins::mock::MockTransport;
+    use crate::plugins::PluginCapability;
+
+    async fn app(transport: &MockTransport) -> Router {
+        let plugin = transport.plugin("piper", vec![PluginCapability::ModelList]);
+        routes(
+            AppState::with_plugins(vec![Arc::new(plugin)])
+                .await
+                .unwrap(),
+        )
+    }
+
+    async fn status(app: &Router, method: Method, uri: &str) -> StatusCode {
+        status_with_key(app, None, method, uri).await
+    }
+
+    async fn status_with_key(
+        app: &Router,
+        key: Option<&str>,
+        method: Method,
+        uri: &str,
+    ) -> StatusCode {
+        let mut request = Request::builder().method(method).uri(uri);
+        if let Some(key) = key {
+            request = request.header("X-Secret-Key", key);
+        }
+        let request = request.body(Body::empty()).unwrap();
+        app.clone().oneshot(request).await.unwrap().status()
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn disabled_plugins_answer_with_a_conflict() {
+        let transport = MockTransport::new();
+        transport.respond("list_models", json!({ "models": [] }));
+        let app = app(&transport).await;
+
+        let models = "/plugins/piper/models";
+        assert_eq!(status(&app, Method::GET, models).await, StatusCode::OK);
+        assert_eq!(
+            status(&app, Method::POST, "/plugins/piper/disable").await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            status(&app, Method::GET, models).await,
+            StatusCode::CONFLICT
+        );
+        assert_eq!(
+            status(&app, Method::POST, "/plugins/piper/enable").await,
+            StatusCode::OK
+        );
+        assert_eq!(status(&app, Method::GET, models).await, StatusCode::OK);
+        assert_eq!(
+            status(&app, Method::POST, "/plugins/whisper/enable").await,
+            StatusCode::NOT_FOUND
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn scoped_credentials_reach_only_the_routes_they_are_granted() {
+        let transport = MockTransport::new();
+        transport.respond("list_models", json!({ "models": [] }));
+        let plugin = transport.plugin("piper", vec![PluginCapability::ModelList]);
+        let mut state = AppState::with_plugins(vec![Arc::new(plugin)])
+            .await
+            .unwrap();
+        Arc::get_mut(&mut state).unwrap().plugin_credentials = Arc::new(
+            toml::from_str(
+                r#"
+                [[credentials]]
+                name = "dashboard"
+                token = "dashboard-token"
+
+                [credentials.grants]
+                piper = ["model_list", "service_signal", "service_console"]
+                "#,
+            )
+            .unwrap(),
+        );
+        let app = routes(state);
+        let scoped = |method, uri| status_with_key(&app, Some("dashboard-token"), method, uri);
+        assert_eq!(scoped(Method::GET, "/plugins").await, StatusCode::OK);
+        assert_eq!(
+            scoped(Method::GET, "/plugins/piper/models").await,
+            StatusCode::OK
+        );
+        let delete = "/plugins/piper/models?task_type=text&filename=model.gguf";
+        assert_eq!(scoped(Method::DELETE, delete).await, StatusCode::FORBIDDEN);
+        let signal = "/plugins/piper/services/abc/signal";
+        assert_eq!(scoped(Method::POST, signal).await, StatusCode::FORBIDDEN);
+        let console = "/plugins/piper/instances/abc/console";
+        assert_eq!(scoped(Method::GET, console).await, StatusCode::FORBIDDEN);
+
+        assert_ne!(
+            status(&app, Method::POST, signal).await,
+            StatusCode::FORBIDDEN
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn model_files_are_listed_for_unescaped_repositories() {
+        let transport = MockTransport::new();
+        transport.respond(
+            "invoke",
+            json!({ "model_id": "org/model", "revision": "main", "files": [] }),
+        );
+        let app = app(&transport).await;
+
+        let files = "/plugins/piper/models/remote/org/model/files";
+        assert_eq!(status(&app, Method::GET, files).await, StatusCode::OK);
+        let escaped = "/plugins/piper/models/remote/org%2Fmodel/files";
+        assert_eq!(status(&app, Method::GET, escaped).await, StatusCode::OK);
+        for call in transport.calls_to("invoke") {
+            assert_eq!(call["operation"], operations::LIST_MODEL_FILES);
+            assert_eq!(call["payload"]["model_id"], "org/model");
+        }
+        assert_eq!(transport.calls_to("invoke").len(), 2);
+        assert_eq!(
+            status(&app, Method::GET, "/plugins/piper/models/remote/org/model").await,
+            StatusCode::NOT_FOUND
+        );
+    }
 }
@@ -0,0 +1,79 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    routing::{delete, get},
+    Json, Router,
+};
+
+use crate::routes::errors::ErrorResponse;
+use crate::state::AppState;
+use crate::webhooks::{CreateWebhookRequest, CreateWebhookResponse, Webhook, WebhookError};
+
+impl From<WebhookError> for ErrorResponse {
+    fn from(err: WebhookError) -> Self {
+        let status = match &err {
+            WebhookError::UnknownWebhook(_) => StatusCode::NOT_FOUND,
+            WebhookError::InvalidUrl(_) => StatusCode::BAD_REQUEST,
+            WebhookError::Io(_) | WebhookError::Json(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        ErrorResponse {
+            message: err.to_string(),
+            status,
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/webhooks",
+    responses(
+        (status = 200, description = "Webhook subscriptions, without their secrets", body = [Webhook])
+    ),
+)]
+pub async fn list_webhooks(State(state): State<Arc<AppState>>) -> Json<Vec<Webhook>> {
+    Json(state.webhooks.read().await.list())
+}
+
+#[utoipa::path(
+    post,
+    path = "/webhooks",
+    request_body = CreateWebhookRequest,
+    responses(
+        (status = 201, description = "Webhook subscribed, with the secret its payloads are signed with", body = CreateWebhookResponse),
+        (status = 400, description = "Invalid webhook URL", body = ErrorResponse),
+        (status = 500, description = "Subscription could not be saved", body = ErrorResponse)
+    ),
+)]
+pub async fn create_webhook(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<CreateWebhookRequest>,
+) -> Result<(StatusCode, Json<CreateWebhookResponse>), ErrorResponse> {
+    let created = state.webhooks.write().await.create(payload).await?;
+    Ok((StatusCode::CREATED, Json(created)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/webhooks/{webhook_id}",
+    params(("webhook_id" = String, Path, description = "Webhook identifier")),
+    responses(
+        (status = 204, description = "Webhook unsubscribed"),
+        (status = 404, description = "Unknown webhook", body = ErrorResponse)
+    ),
+)]
+pub async fn delete_webhook(
+    State(state): State<Arc<AppState>>,
+    Path(webhook_id): Path<String>,
+) -> Result<StatusCode, ErrorResponse> {
+    state.webhooks.write().await.delete(&webhook_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub fn routes(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/webhooks", get(list_webhooks).post(create_webhook))
+        .route("/webhooks/{webhook_id}", delete(delete_webhook))
+        .with_state(state)
+}
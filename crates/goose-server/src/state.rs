@@ -5,9 +5,20 @@ use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 use std::sync::atomic::AtomicUsize;
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, RwLock};
 
-use crate::plugins::{self, llmserver::LlmServerPlugin, SharedPluginManager};
+use crate::cluster::{self, ClusterRegistry};
+use crate::jobs::JobRegistry;
+use crate::plugins::catalog::CatalogClient;
+use crate::plugins::defaults::PluginDefaultsStore;
+use crate::plugins::events::EventBus;
+use crate::plugins::permissions::PluginCredentials;
+use crate::plugins::persistence::PluginStateDir;
+use crate::plugins::recommended::ModelCatalogClient;
+use crate::plugins::settings::PluginConfigStore;
+use crate::plugins::signing::TrustRoot;
+use crate::plugins::{self, SharedPluginManager};
+use crate::webhooks::{self, WebhookStore};
 #[derive(Clone)]
 pub struct AppState {
     pub(crate) agent_manager: Arc<AgentManager>,
@@ -16,22 +27,125 @@ pub struct AppState {
     /// Tracks sessions that have already emitted recipe telemetry to prevent double counting.
     recipe_session_tracker: Arc<Mutex<HashSet<String>>>,
     pub plugins: SharedPluginManager,
+    /// Events plugins publish, delivered to subscribed plugins and clients.
+    pub events: EventBus,
+    /// Cluster members that report to this node.
+    pub cluster: Arc<ClusterRegistry>,
+    /// Operations running in the background, such as model downloads.
+    pub jobs: Arc<JobRegistry>,
+    /// Plugins that task-level routes dispatch to, by task type.
+    pub plugin_defaults: Arc<RwLock<PluginDefaultsStore>>,
+    /// Where plugins keep their state across restarts.
+    pub plugin_state: PluginStateDir,
+    /// Credentials limited to some capabilities of some plugins.
+    pub plugin_credentials: Arc<PluginCredentials>,
+    /// Plugins that can be installed from the configured index.
+    pub plugin_catalog: Arc<CatalogClient>,
+    /// Models recommended for one-click install.
+    pub model_catalog: Arc<ModelCatalogClient>,
+    /// URLs notified of finished downloads and crashed services.
+    pub webhooks: Arc<RwLock<WebhookStore>>,
 }
 
 impl AppState {
     pub async fn new() -> anyhow::Result<Arc<AppState>> {
         let agent_manager = AgentManager::instance().await?;
         let mut plugin_manager = plugins::PluginManager::new();
-        let llm_plugin = LlmServerPlugin::bootstrap().await?;
-        plugin_manager.register(Arc::new(llm_plugin));
+        plugin_manager
+            .set_config_store(PluginConfigStore::load(&PluginConfigStore::default_path()).await?);
+        plugin_manager.set_trust_root(TrustRoot::load(&TrustRoot::default_path()).await?);
+        let manifests = plugins::discovery::load(&plugins::discovery::manifest_dir())?;
+        plugin_manager.register_manifests(&manifests).await?;
+        if let Some(dir) = plugins::dynamic::plugin_dir() {
+            plugin_manager.load_directory(&dir).await?;
+        }
+        plugin_manager
+            .spawn_external(&plugins::external::ExternalPluginConfig::from_env()?)
+            .await;
+        plugin_manager
+            .connect_grpc(&plugins::grpc::GrpcPluginConfig::from_env()?)
+            .await;
+        plugin_manager.configure_all().await;
+        let plugin_defaults =
+            PluginDefaultsStore::load(&PluginDefaultsStore::default_path()).await?;
+        let plugin_credentials =
+            PluginCredentials::load(&PluginCredentials::default_path()).await?;
+        let webhook_store = WebhookStore::load(&WebhookStore::default_path()).await?;
+        let events = plugin_manager.events();
+        plugins::notifications::spawn(&events)?;
         let shared_plugins = SharedPluginManager::new(plugin_manager);
-        Ok(Arc::new(Self {
+        let plugin_state = PluginStateDir::new(PluginStateDir::default_dir());
+        shared_plugins.restore_states(&plugin_state).await;
+        shared_plugins.spawn_event_delivery().await;
+        let state = Self::from_parts(
+            agent_manager,
+            shared_plugins,
+            events,
+            plugin_defaults,
+            plugin_state,
+            plugin_credentials,
+            webhook_store,
+        );
+        webhooks::spawn(state.webhooks.clone(), &state.events, &state.jobs);
+        Ok(state)
+    }
+
+    /// State for in-process integration tests, such as with
+    /// [`MockPlugin`](crate::plugins::mock::MockPlugin)s: only `plugins` are
+    /// registered, and plugin settings, defaults and credentials start empty
+    /// instead of being read from disk.
+    #[cfg(any(test, feature = "test-util"))]
+    #[allow(dead_code)] // Used by tests of downstream crates
+    pub async fn with_plugins(
+        plugins: Vec<Arc<dyn plugins::ServerPlugin>>,
+    ) -> anyhow::Result<Arc<AppState>> {
+        let agent_manager = AgentManager::instance().await?;
+        let plugin_manager = plugins::PluginManager::new();
+        let events = plugin_manager.events();
+        let shared_plugins = SharedPluginManager::new(plugin_manager);
+        for plugin in plugins {
+            shared_plugins.register(plugin).await?;
+        }
+        shared_plugins.spawn_event_delivery().await;
+        let plugin_state = PluginStateDir::new(
+            std::env::temp_dir().join(format!("goose-plugin-state-{}", uuid::Uuid::new_v4())),
+        );
+        Ok(Self::from_parts(
+            agent_manager,
+            shared_plugins,
+            events,
+            PluginDefaultsStore::default(),
+            plugin_state,
+            PluginCredentials::default(),
+            WebhookStore::default(),
+        ))
+    }
+
+    fn from_parts(
+        agent_manager: Arc<AgentManager>,
+        plugins: SharedPluginManager,
+        events: EventBus,
+        plugin_defaults: PluginDefaultsStore,
+        plugin_state: PluginStateDir,
+        plugin_credentials: PluginCredentials,
+        webhooks: WebhookStore,
+    ) -> Arc<AppState> {
+        Arc::new(Self {
             agent_manager,
             recipe_file_hash_map: Arc::new(Mutex::new(HashMap::new())),
             session_counter: Arc::new(AtomicUsize::new(0)),
             recipe_session_tracker: Arc::new(Mutex::new(HashSet::new())),
-            plugins: shared_plugins,
-        }))
+            plugins,
+            events,
+            cluster: Arc::new(ClusterRegistry::new(cluster::cluster_secret())),
+            jobs: Arc::new(JobRegistry::new()),
+            plugin_defaults: Arc::new(RwLock::new(plugin_defaults)),
+            plugin_state,
+            plugin_credentials: Arc::new(plugin_credentials),
+            plugin_catalog: Arc::new(CatalogClient::from_env()),
+            model_catalog: Arc::new(ModelCatalogClient::from_env()),
+            webhooks: Arc::new(RwLock::new(webhooks)),
+        })
     }
 
     pub async fn scheduler(&self) -> Result<Arc<dyn SchedulerTrait>, anyhow::Error> {
@@ -0,0 +1,208 @@
+//! Request timeouts. Every request gets a deadline for producing its response
+//! headers; streamed bodies (SSE, websockets) are not cut off once started.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    extract::{Request, State},
+    http::{Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+
+/// Routes whose work is expected to take a long time, e.g. model transfers
+/// and starts waiting for a model to load.
+const LONG_ROUTES: &[&str] = &[
+    "/plugins/{plugin_id}/models/download",
+    "/plugins/{plugin_id}/services/start",
+    "/plugins/{plugin_id}/services/upgrade",
+    "/tasks/{task_type}/start",
+    "/cluster/plugins/{plugin_id}/models/download",
+    "/cluster/plugins/{plugin_id}/services/start",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RouteClass {
+    /// Listings and other reads.
+    Short,
+    Default,
+    Long,
+}
+
+/// Deadlines per class of route, with overrides for individual routes.
+///
+/// Configured with `GOOSE_SERVER_TIMEOUT_{SHORT,DEFAULT,LONG}_SECS` and
+/// `GOOSE_SERVER_ROUTE_TIMEOUTS`, a JSON object mapping route patterns such
+/// as `/plugins/{plugin_id}/models` to seconds. A value of 0 disables the
+/// timeout.
+#[derive(Debug, Clone)]
+pub struct RouteTimeouts {
+    short: Option<Duration>,
+    default: Option<Duration>,
+    long: Option<Duration>,
+    overrides: Vec<(String, Option<Duration>)>,
+}
+
+impl Default for RouteTimeouts {
+    fn default() -> Self {
+        Self {
+            short: Some(Duration::from_secs(30)),
+            default: Some(Duration::from_secs(120)),
+            long: Some(Duration::from_secs(6 * 60 * 60)),
+            overrides: Vec::new(),
+        }
+    }
+}
+
+fn seconds(secs: u64) -> Option<Duration> {
+    (secs > 0).then(|| Duration::from_secs(secs))
+}
+
+impl RouteTimeouts {
+    pub fn from_env() -> Self {
+        fn var(name: &str) -> Option<u64> {
+            std::env::var(name).ok()?.trim().parse().ok()
+        }
+        let mut timeouts = Self::default();
+        if let Some(secs) = var("GOOSE_SERVER_TIMEOUT_SHORT_SECS") {
+            timeouts.short = seconds(secs);
+        }
+        if let Some(secs) = var("GOOSE_SERVER_TIMEOUT_DEFAULT_SECS") {
+            timeouts.default = seconds(secs);
+        }
+        if let Some(secs) = var("GOOSE_SERVER_TIMEOUT_LONG_SECS") {
+            timeouts.long = seconds(secs);
+        }
+        if let Ok(raw) = std::env::var("GOOSE_SERVER_ROUTE_TIMEOUTS") {
+            match serde_json::from_str::<std::collections::HashMap<String, u64>>(&raw) {
+                Ok(overrides) => {
+                    timeouts.overrides = overrides
+                        .into_iter()
+                        .map(|(pattern, secs)| (pattern, seconds(secs)))
+                        .collect();
+                }
+                Err(err) => tracing::warn!("ignoring invalid GOOSE_SERVER_ROUTE_TIMEOUTS: {}", err),
+            }
+        }
+        timeouts
+    }
+
+    fn classify(method: &Method, path: &str) -> RouteClass {
+        if LONG_ROUTES
+            .iter()
+            .any(|pattern| matches_route(pattern, path))
+        {
+            RouteClass::Long
+        } else if method == Method::GET {
+            RouteClass::Short
+        } else {
+            RouteClass::Default
+        }
+    }
+
+    fn timeout_for(&self, method: &Method, path: &str) -> Option<Duration> {
+        if let Some((_, timeout)) = self
+            .overrides
+            .iter()
+            .find(|(pattern, _)| matches_route(pattern, path))
+        {
+            return *timeout;
+        }
+        match Self::classify(method, path) {
+            RouteClass::Short => self.short,
+            RouteClass::Default => self.default,
+            RouteClass::Long => self.long,
+        }
+    }
+}
+
+/// Matches a request path against a route pattern where `{name}` segments
+/// match any single segment.
+fn matches_route(pattern: &str, path: &str) -> bool {
+    let mut pattern = pattern.trim_end_matches('/').split('/');
+    let mut path = path.trim_end_matches('/').split('/');
+    loop {
+        match (pattern.next(), path.next()) {
+            (None, None) => return true,
+            (Some(expected), Some(actual)) => {
+                let wildcard = expected.starts_with('{') && expected.ends_with('}');
+                if !wildcard && expected != actual {
+                    return false;
+                }
+            }
+            _ => return false,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct TimeoutBody {
+    message: String,
+    path: String,
+    timeout_secs: u64,
+}
+
+/// Answers with a 504 when the handler misses its deadline. The handler is
+/// dropped, which also cancels plugin work tied to the request.
+pub async fn enforce_timeout(
+    State(timeouts): State<Arc<RouteTimeouts>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let path = request.uri().path().to_string();
+    let Some(timeout) = timeouts.timeout_for(request.method(), &path) else {
+        return next.run(request).await;
+    };
+    match tokio::time::timeout(timeout, next.run(request)).await {
+        Ok(response) => response,
+        Err(_) => {
+            tracing::warn!("request to {} timed out after {:?}", path, timeout);
+            let body = TimeoutBody {
+                message: format!("request timed out after {} seconds", timeout.as_secs()),
+                path,
+                timeout_secs: timeout.as_secs(),
+            };
+            (StatusCode::GATEWAY_TIMEOUT, Json(body)).into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picks_override_then_route_class() {
+        let timeouts = RouteTimeouts {
+            overrides: vec![("/plugins/{plugin_id}/models".to_string(), None)],
+            ..RouteTimeouts::default()
+        };
+        assert_eq!(
+            timeouts.timeout_for(&Method::POST, "/plugins/llmserver/models/download"),
+            timeouts.long
+        );
+        assert_eq!(
+            timeouts.timeout_for(&Method::GET, "/plugins"),
+            timeouts.short
+        );
+        assert_eq!(
+            timeouts.timeout_for(&Method::POST, "/plugins/llmserver/services/start"),
+            timeouts.long
+        );
+        assert_eq!(
+            timeouts.timeout_for(&Method::POST, "/plugins/llmserver/services/stop"),
+            timeouts.default
+        );
+        assert_eq!(
+            timeouts.timeout_for(&Method::POST, "/tasks/text/start"),
+            timeouts.long
+        );
+        assert_eq!(
+            timeouts.timeout_for(&Method::GET, "/plugins/llmserver/models/"),
+            None
+        );
+    }
+}
@@ -0,0 +1,429 @@
+//! Webhooks: user-provided URLs that receive signed JSON payloads when a
+//! download finishes or a service crashes. Subscriptions are set through
+//! `/webhooks` and persisted as JSON like the plugin defaults.
+//!
+//! Every delivery carries `X-Goose-Event`, `X-Goose-Delivery`,
+//! `X-Goose-Timestamp` and `X-Goose-Signature: sha256=<hex>`, the HMAC-SHA256
+//! of `<timestamp>.<body>` keyed with the subscription's secret.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use goose::config::paths::Paths;
+use ring::{hmac, rand::SecureRandom};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::fs;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::RwLock;
+use utoipa::ToSchema;
+
+use crate::jobs::{JobRegistry, JobState, JobStatus};
+use crate::plugins::events::{EventBus, PluginEvent, PluginEventKind};
+
+const SEND_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Attempts per delivery, the first one included.
+const MAX_ATTEMPTS: u32 = 3;
+
+/// Wait before the first retry; doubled for each one after it.
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+pub enum WebhookEvent {
+    /// A model download job succeeded; `data` is its job status.
+    #[serde(rename = "download.completed")]
+    DownloadCompleted,
+    /// A model download job failed; `data` is its job status.
+    #[serde(rename = "download.failed")]
+    DownloadFailed,
+    /// A plugin service exited unexpectedly, whether or not it could be
+    /// restarted; `data` is the plugin event.
+    #[serde(rename = "service.crashed")]
+    ServiceCrashed,
+}
+
+impl WebhookEvent {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            WebhookEvent::DownloadCompleted => "download.completed",
+            WebhookEvent::DownloadFailed => "download.failed",
+            WebhookEvent::ServiceCrashed => "service.crashed",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Webhook {
+    pub id: String,
+    pub url: String,
+    /// Events delivered to the URL; every event when empty.
+    #[serde(default)]
+    pub events: Vec<WebhookEvent>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Webhook {
+    fn wants(&self, event: WebhookEvent) -> bool {
+        self.events.is_empty() || self.events.contains(&event)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CreateWebhookRequest {
+    /// `http` or `https` URL the payloads are posted to.
+    pub url: String,
+    /// Events to deliver; every event when empty.
+    #[serde(default)]
+    pub events: Vec<WebhookEvent>,
+    /// Key the payloads are signed with; generated when not given.
+    #[serde(default)]
+    pub secret: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CreateWebhookResponse {
+    pub webhook: Webhook,
+    /// Key the payloads are signed with. Only returned here.
+    pub secret: String,
+}
+
+/// What is posted to a webhook.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct WebhookPayload {
+    /// Identifies the delivery, the same across its retries.
+    pub id: String,
+    pub event: WebhookEvent,
+    pub timestamp: DateTime<Utc>,
+    #[schema(value_type = Object)]
+    pub data: Value,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum WebhookError {
+    #[error("unknown webhook {0}")]
+    UnknownWebhook(String),
+    #[error("invalid webhook URL {0}")]
+    InvalidUrl(String),
+    #[error("could not save webhooks: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid webhooks file: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct Subscription {
+    #[serde(flatten)]
+    webhook: Webhook,
+    secret: String,
+}
+
+/// Webhook subscriptions. The default store keeps them in memory only.
+#[derive(Default)]
+pub struct WebhookStore {
+    path: Option<PathBuf>,
+    subscriptions: Vec<Subscription>,
+}
+
+impl WebhookStore {
+    /// `GOOSE_WEBHOOKS_PATH`, or `webhooks.json` in goose's config directory.
+    pub fn default_path() -> PathBuf {
+        std::env::var_os("GOOSE_WEBHOOKS_PATH")
+            .filter(|path| !path.is_empty())
+            .map(PathBuf::from)
+            .unwrap_or_else(|| Paths::config_dir().join("webhooks.json"))
+    }
+
+    /// Reads the store at `path`, which need not exist yet.
+    pub async fn load(path: &Path) -> Result<Self, WebhookError> {
+        let subscriptions = match fs::read(path).await {
+            Ok(bytes) => serde_json::from_slice(&bytes)?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(err.into()),
+        };
+        Ok(Self {
+            path: Some(path.to_path_buf()),
+            subscriptions,
+        })
+    }
+
+    pub fn list(&self) -> Vec<Webhook> {
+        self.subscriptions
+            .iter()
+            .map(|subscription| subscription.webhook.clone())
+            .collect()
+    }
+
+    pub async fn create(
+        &mut self,
+        request: CreateWebhookRequest,
+    ) -> Result<CreateWebhookResponse, WebhookError> {
+        let url = reqwest::Url::parse(&request.url)
+            .ok()
+            .filter(|url| matches!(url.scheme(), "http" | "https"))
+            .ok_or_else(|| WebhookError::InvalidUrl(request.url.clone()))?;
+        let secret = match request.secret.filter(|secret| !secret.is_empty()) {
+            Some(secret) => secret,
+            None => generate_secret(),
+        };
+        let webhook = Webhook {
+            id: uuid::Uuid::new_v4().to_string(),
+            url: url.to_string(),
+            events: request.events,
+            created_at: Utc::now(),
+        };
+        self.subscriptions.push(Subscription {
+            webhook: webhook.clone(),
+            secret: secret.clone(),
+        });
+        if let Err(err) = self.save().await {
+            self.subscriptions.pop();
+            return Err(err);
+        }
+        Ok(CreateWebhookResponse { webhook, secret })
+    }
+
+    pub async fn delete(&mut self, webhook_id: &str) -> Result<(), WebhookError> {
+        let before = self.subscriptions.len();
+        self.subscriptions
+            .retain(|subscription| subscription.webhook.id != webhook_id);
+        if self.subscriptions.len() == before {
+            return Err(WebhookError::UnknownWebhook(webhook_id.to_string()));
+        }
+        self.save().await
+    }
+
+    /// Writes the store atomically, readable by the owner only as it holds
+    /// the signing secrets.
+    async fn save(&self) -> Result<(), WebhookError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let json = serde_json::to_vec_pretty(&self.subscriptions)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, json).await?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&tmp, std::fs::Permissions::from_mode(0o600)).await?;
+        }
+        fs::rename(&tmp, path).await?;
+        Ok(())
+    }
+
+    fn subscribers(&self, event: WebhookEvent) -> Vec<Subscription> {
+        self.subscriptions
+            .iter()
+            .filter(|subscription| subscription.webhook.wants(event))
+            .cloned()
+            .collect()
+    }
+}
+
+fn generate_secret() -> String {
+    let mut bytes = [0u8; 32];
+    ring::rand::SystemRandom::new()
+        .fill(&mut bytes)
+        .expect("system randomness");
+    hex(&bytes)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// The `X-Goose-Signature` of `body` sent at `timestamp`, in Unix seconds.
+pub fn signature(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    let mut context = hmac::Context::with_key(&key);
+    context.update(format!("{}.", timestamp).as_bytes());
+    context.update(body);
+    format!("sha256={}", hex(context.sign().as_ref()))
+}
+
+/// The webhook event a finished job is delivered as, if any.
+fn job_event(job: &JobStatus) -> Option<WebhookEvent> {
+    if job.kind != crate::routes::plugins::MODEL_DOWNLOAD_JOB {
+        return None;
+    }
+    match job.state {
+        JobState::Succeeded => Some(WebhookEvent::DownloadCompleted),
+        JobState::Failed => Some(WebhookEvent::DownloadFailed),
+        _ => None,
+    }
+}
+
+/// The webhook event a plugin event is delivered as, if any.
+fn plugin_event(event: &PluginEvent) -> Option<WebhookEvent> {
+    match event.event {
+        PluginEventKind::ServiceRestarted { .. } | PluginEventKind::ServiceRestartFailed { .. } => {
+            Some(WebhookEvent::ServiceCrashed)
+        }
+        _ => None,
+    }
+}
+
+async fn deliver(
+    client: reqwest::Client,
+    subscription: Subscription,
+    payload: Arc<WebhookPayload>,
+) {
+    let body = match serde_json::to_vec(payload.as_ref()) {
+        Ok(body) => body,
+        Err(err) => {
+            tracing::warn!("could not serialize webhook payload: {}", err);
+            return;
+        }
+    };
+    let mut delay = RETRY_DELAY;
+    for attempt in 1..=MAX_ATTEMPTS {
+        let timestamp = Utc::now().timestamp();
+        let sent = client
+            .post(&subscription.webhook.url)
+            .timeout(SEND_TIMEOUT)
+            .header("Content-Type", "application/json")
+            .header("X-Goose-Event", payload.event.as_str())
+            .header("X-Goose-Delivery", &payload.id)
+            .header("X-Goose-Timestamp", timestamp.to_string())
+            .header(
+                "X-Goose-Signature",
+                signature(&subscription.secret, timestamp, &body),
+            )
+            .body(body.clone())
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status);
+        match sent {
+            Ok(_) => return,
+            Err(err) if attempt == MAX_ATTEMPTS => tracing::warn!(
+                "giving up on {} webhook {}: {}",
+                payload.event.as_str(),
+                subscription.webhook.id,
+                err
+            ),
+            Err(err) => {
+                tracing::debug!(
+                    "{} webhook {} failed, retrying: {}",
+                    payload.event.as_str(),
+                    subscription.webhook.id,
+                    err
+                );
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+            }
+        }
+    }
+}
+
+async fn next<T: Clone>(receiver: &mut broadcast::Receiver<T>, source: &str) -> Option<T> {
+    loop {
+        match receiver.recv().await {
+            Ok(item) => return Some(item),
+            Err(RecvError::Lagged(missed)) => {
+                tracing::warn!("webhooks skipped {} {}", missed, source);
+            }
+            Err(RecvError::Closed) => return None,
+        }
+    }
+}
+
+/// Delivers finished downloads from `jobs` and crashes from `events` to the
+/// subscribed webhooks until both close. Each delivery runs on its own task,
+/// so a slow endpoint holds up no other.
+pub fn spawn(store: Arc<RwLock<WebhookStore>>, events: &EventBus, jobs: &JobRegistry) {
+    let mut events = events.subscribe();
+    let mut jobs = jobs.subscribe();
+    let client = reqwest::Client::new();
+    tokio::spawn(async move {
+        let (mut events_open, mut jobs_open) = (true, true);
+        while events_open || jobs_open {
+            let (event, data) = tokio::select! {
+                job = next(&mut jobs, "jobs"), if jobs_open => match job {
+                    Some(job) => match job_event(&job) {
+                        Some(event) => (event, serde_json::to_value(&job)),
+                        None => continue,
+                    },
+                    None => {
+                        jobs_open = false;
+                        continue;
+                    }
+                },
+                plugin = next(&mut events, "plugin events"), if events_open => match plugin {
+                    Some(plugin) => match plugin_event(&plugin) {
+                        Some(event) => (event, serde_json::to_value(&plugin)),
+                        None => continue,
+                    },
+                    None => {
+                        events_open = false;
+                        continue;
+                    }
+                },
+            };
+            let payload = Arc::new(WebhookPayload {
+                id: uuid::Uuid::new_v4().to_string(),
+                event,
+                timestamp: Utc::now(),
+                data: data.unwrap_or_default(),
+            });
+            for subscription in store.read().await.subscribers(event) {
+                tokio::spawn(deliver(client.clone(), subscription, payload.clone()));
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn persists_subscriptions_with_their_secrets() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("webhooks.json");
+        let mut store = WebhookStore::load(&path).await.unwrap();
+        let created = store
+            .create(CreateWebhookRequest {
+                url: "https://example.com/hooks/goose".to_string(),
+                events: vec![WebhookEvent::DownloadFailed],
+                secret: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(created.secret.len(), 64);
+        assert!(store
+            .create(CreateWebhookRequest {
+                url: "file:///etc/passwd".to_string(),
+                events: Vec::new(),
+                secret: None,
+            })
+            .await
+            .is_err());
+
+        let mut store = WebhookStore::load(&path).await.unwrap();
+        assert_eq!(store.list()[0].id, created.webhook.id);
+        assert_eq!(store.subscribers(WebhookEvent::DownloadFailed).len(), 1);
+        assert!(store.subscribers(WebhookEvent::ServiceCrashed).is_empty());
+        assert_eq!(
+            store.subscribers(WebhookEvent::DownloadFailed)[0].secret,
+            created.secret
+        );
+        store.delete(&created.webhook.id).await.unwrap();
+        assert!(matches!(
+            store.delete(&created.webhook.id).await,
+            Err(WebhookError::UnknownWebhook(_))
+        ));
+    }
+
+    #[test]
+    fn signs_timestamp_and_body() {
+        let signature = signature("secret", 1_700_000_000, b"{}");
+        assert!(signature.starts_with("sha256="));
+        assert_eq!(signature.len(), "sha256=".len() + 64);
+        assert_ne!(signature, super::signature("secret", 1_700_000_001, b"{}"));
+    }
+}